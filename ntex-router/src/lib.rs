@@ -10,6 +10,7 @@ mod tree;
 
 pub use self::de::PathDeserializer;
 pub use self::path::Path;
+pub use self::quoter::{percent_decode_path_segment, DecodeError};
 pub use self::resource::ResourceDef;
 pub use self::router::{ResourceInfo, Router, RouterBuilder};
 
@@ -134,12 +135,10 @@ mod http_support {
             self.path()
         }
 
+        /// Segments with encoded NUL byte or invalid utf-8 are not decoded
         fn unquote(s: &str) -> std::borrow::Cow<'_, str> {
-            if let Some(q) = super::quoter::requote(s.as_bytes()) {
-                std::borrow::Cow::Owned(q)
-            } else {
-                std::borrow::Cow::Borrowed(s)
-            }
+            super::percent_decode_path_segment(s, true)
+                .unwrap_or(std::borrow::Cow::Borrowed(s))
         }
     }
 }
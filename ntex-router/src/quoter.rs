@@ -1,51 +1,68 @@
-pub(super) fn requote(val: &[u8]) -> Option<String> {
-    let mut has_pct = 0;
-    let mut pct = [b'%', 0, 0];
-    let mut idx = 0;
-    let mut cloned: Option<Vec<u8>> = None;
+use std::{borrow::Cow, fmt, str};
 
-    let len = val.len();
-    while idx < len {
-        let ch = val[idx];
+/// Path segment percent-decoding error
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DecodeError {
+    /// Segment contains encoded NUL byte
+    Nul,
+    /// Segment contains encoded slash
+    Slash,
+    /// Decoded segment is not valid utf-8
+    Utf8,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Nul => write!(f, "Path segment contains encoded NUL byte"),
+            DecodeError::Slash => write!(f, "Path segment contains encoded slash"),
+            DecodeError::Utf8 => write!(f, "Decoded path segment is not valid utf-8"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Percent-decode path segment
+///
+/// Encoded NUL byte is rejected, encoded slash is rejected unless
+/// `decode_slash` is set. Invalid percent-encoded sequences are kept as is.
+/// Segment is borrowed if it does not contain encoded values.
+pub fn percent_decode_path_segment(
+    val: &str,
+    decode_slash: bool,
+) -> Result<Cow<'_, str>, DecodeError> {
+    let bytes = val.as_bytes();
+    let idx = if let Some(idx) = bytes.iter().position(|ch| *ch == b'%') {
+        idx
+    } else {
+        return Ok(Cow::Borrowed(val));
+    };
 
-        if has_pct != 0 {
-            pct[has_pct] = val[idx];
-            has_pct += 1;
-            if has_pct == 3 {
-                has_pct = 0;
-                let buf = if let Some(ref mut buf) = cloned {
-                    buf
-                } else {
-                    let mut c = Vec::with_capacity(len);
-                    c.extend_from_slice(&val[..idx - 2]);
-                    cloned = Some(c);
-                    cloned.as_mut().unwrap()
-                };
+    let mut decoded = Vec::with_capacity(bytes.len());
+    decoded.extend_from_slice(&bytes[..idx]);
 
-                if let Some(ch) = restore_ch(pct[1], pct[2]) {
-                    buf.push(ch);
-                } else {
-                    buf.extend_from_slice(&pct[..]);
+    let mut idx = idx;
+    while idx < bytes.len() {
+        let ch = bytes[idx];
+        if ch == b'%' && idx + 2 < bytes.len() {
+            if let Some(ch) = restore_ch(bytes[idx + 1], bytes[idx + 2]) {
+                match ch {
+                    0 => return Err(DecodeError::Nul),
+                    b'/' if !decode_slash => return Err(DecodeError::Slash),
+                    _ => decoded.push(ch),
                 }
+                idx += 3;
+                continue;
             }
-        } else if ch == b'%' {
-            has_pct = 1;
-        } else if let Some(ref mut cloned) = cloned {
-            cloned.push(ch)
         }
+        decoded.push(ch);
         idx += 1;
     }
 
-    if let Some(mut data) = cloned {
-        if has_pct > 0 {
-            data.extend(&pct[..has_pct]);
-        }
-        // Unsafe: we get data from http::Uri, which does utf-8 checks already
-        // this code only decodes valid pct encoded values
-        Some(unsafe { String::from_utf8_unchecked(data) })
-    } else {
-        None
-    }
+    String::from_utf8(decoded)
+        .map(Cow::Owned)
+        .map_err(|_| DecodeError::Utf8)
 }
 
 #[inline]
@@ -65,3 +82,27 @@ fn from_hex(v: u8) -> Option<u8> {
 fn restore_ch(d1: u8, d2: u8) -> Option<u8> {
     from_hex(d1).and_then(|d1| from_hex(d2).map(move |d2| d1 << 4 | d2))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_decode_path_segment() {
+        let decode = |s| percent_decode_path_segment(s, false);
+        assert!(matches!(decode("test"), Ok(Cow::Borrowed("test"))));
+        assert_eq!(decode("t%65st").unwrap(), "test");
+        assert_eq!(decode("%D1%82%D0%B5%D1%81%D1%82").unwrap(), "тест");
+        assert_eq!(decode("%252F").unwrap(), "%2F");
+        assert_eq!(decode("100%").unwrap(), "100%");
+        assert_eq!(decode("%zz%4").unwrap(), "%zz%4");
+        assert_eq!(decode("a%00b"), Err(DecodeError::Nul));
+        assert_eq!(decode("a%2fb"), Err(DecodeError::Slash));
+        assert_eq!(decode("%FF"), Err(DecodeError::Utf8));
+        assert_eq!(percent_decode_path_segment("a%2Fb", true).unwrap(), "a/b");
+        assert_eq!(
+            percent_decode_path_segment("a%00b", true),
+            Err(DecodeError::Nul)
+        );
+    }
+}
@@ -686,6 +686,20 @@ mod tests {
             "/http%3A%2F%2Flocalhost%3A80%2Ffile%2F%252Fvar%252Flog%252Fsyslog/",
             "http://localhost:80/file/%2Fvar%2Flog%2Fsyslog"
         );
+        test_single_value!("/a%00b/", "a%00b");
+        test_single_value!("/a%FFb/", "a%FFb");
+
+        // tail values are decoded
+        let tree = Tree::new(&ResourceDef::new("/files/{tail}*"), 1);
+        let uri = Uri::try_from("/files/a%20b/c%2Fd").unwrap();
+        let mut resource = Path::new(uri);
+        assert_eq!(tree.find(&mut resource), Some(1));
+        assert_eq!(resource.get("tail").unwrap(), "a b/c/d");
+
+        let uri = Uri::try_from("/files/a/b").unwrap();
+        let mut resource = Path::new(uri);
+        assert_eq!(tree.find(&mut resource), Some(1));
+        assert_eq!(resource.get("tail").unwrap(), "a/b");
     }
 
     #[test]
@@ -380,7 +380,18 @@ impl Tree {
                         let mut is_match = true;
                         for name in names.iter() {
                             if let Some(m) = captures.name(&name) {
-                                let item = if quoted {
+                                let item = if tail {
+                                    // tail is matched against raw path
+                                    match T::unquote(m.as_str()) {
+                                        Cow::Owned(s) => PathItem::Segment(s),
+                                        Cow::Borrowed(_) => PathItem::IdxSegment(
+                                            (base_skip + (skip + m.start()) as isize)
+                                                as u16,
+                                            (base_skip + (skip + m.end()) as isize)
+                                                as u16,
+                                        ),
+                                    }
+                                } else if quoted {
                                     PathItem::Segment(m.as_str().to_string())
                                 } else {
                                     PathItem::IdxSegment(
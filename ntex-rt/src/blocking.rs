@@ -0,0 +1,221 @@
+//! Thread pool for blocking operations
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use std::{error, fmt};
+
+use futures::channel::oneshot;
+use threadpool::ThreadPool;
+use tokio::time::delay_for;
+
+lazy_static::lazy_static! {
+    /// Number of threads and pool, pool is created on first blocking operation
+    static ref DEFAULT_POOL: Mutex<(usize, Option<ThreadPool>)> = Mutex::new((0, None));
+}
+
+thread_local! {
+    static POOL: ThreadPool = {
+        let mut pool = DEFAULT_POOL.lock().unwrap();
+        let threads = if pool.0 == 0 { num_cpus::get() * 5 } else { pool.0 };
+        pool.1
+            .get_or_insert_with(|| {
+                threadpool::Builder::new()
+                    .thread_name("ntex-blocking".to_owned())
+                    .num_threads(threads)
+                    .build()
+            })
+            .clone()
+    };
+}
+
+static QUEUE_LIMIT: AtomicUsize = AtomicUsize::new(0);
+static QUEUED: AtomicUsize = AtomicUsize::new(0);
+static RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+/// Blocking operation execution error
+#[derive(Debug)]
+pub enum BlockingError<E: fmt::Debug> {
+    /// Blocking function returned error
+    Error(E),
+    /// Thread pool is gone
+    Canceled,
+    /// Thread pool queue is full
+    Overloaded,
+}
+
+impl<E: fmt::Debug> fmt::Display for BlockingError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlockingError::Error(e) => write!(f, "{:?}", e),
+            BlockingError::Canceled => write!(f, "Thread pool is gone"),
+            BlockingError::Overloaded => write!(f, "Thread pool queue is full"),
+        }
+    }
+}
+
+impl<E: fmt::Debug> error::Error for BlockingError<E> {}
+
+/// Configure blocking thread pool.
+///
+/// `threads` sets number of threads in the pool, `0` keeps default
+/// (number of cpus * 5). `queue_limit` sets max number of operations
+/// waiting for a free thread, `0` means no limit. Once limit is reached
+/// `run()` fails with `BlockingError::Overloaded`.
+///
+/// Number of threads could be set only before first blocking operation
+/// gets executed, otherwise thread pool is already created and function
+/// returns `false`. Queue limit is applied immediately.
+pub fn config(threads: usize, queue_limit: usize) -> bool {
+    QUEUE_LIMIT.store(queue_limit, Ordering::SeqCst);
+
+    let mut pool = DEFAULT_POOL.lock().unwrap();
+    if pool.1.is_some() {
+        false
+    } else {
+        pool.0 = threads;
+        true
+    }
+}
+
+/// Number of blocking operations waiting for a free thread
+pub fn queue_depth() -> usize {
+    QUEUED.load(Ordering::SeqCst)
+}
+
+/// Number of blocking operations currently executing
+pub fn running() -> usize {
+    RUNNING.load(Ordering::SeqCst)
+}
+
+/// Execute blocking function on a thread pool, returns future that resolves
+/// to result of the function execution.
+pub fn run<F, I, E>(f: F) -> CpuFuture<I, E>
+where
+    F: FnOnce() -> Result<I, E> + Send + 'static,
+    I: Send + 'static,
+    E: Send + fmt::Debug + 'static,
+{
+    let limit = QUEUE_LIMIT.load(Ordering::SeqCst);
+    let queued = QUEUED.fetch_add(1, Ordering::SeqCst);
+    if limit != 0 && queued >= limit {
+        QUEUED.fetch_sub(1, Ordering::SeqCst);
+        return CpuFuture { rx: None };
+    }
+
+    let guard = Queued(true);
+    let (tx, rx) = oneshot::channel();
+    POOL.with(|pool| {
+        pool.execute(move || {
+            let mut guard = guard;
+            guard.0 = false;
+            QUEUED.fetch_sub(1, Ordering::SeqCst);
+            if !tx.is_canceled() {
+                RUNNING.fetch_add(1, Ordering::SeqCst);
+                let _running = Running;
+                let _ = tx.send(f());
+            }
+        })
+    });
+    CpuFuture { rx: Some(rx) }
+}
+
+/// Wait until all queued and running blocking operations complete,
+/// or `deadline` is reached. Returns `false` if deadline is reached.
+pub async fn wait(deadline: Instant) -> bool {
+    loop {
+        if queue_depth() == 0 && running() == 0 {
+            return true;
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return false;
+        }
+        delay_for(std::cmp::min(deadline - now, Duration::from_millis(10))).await;
+    }
+}
+
+/// Keeps queue counter correct if operation gets dropped before execution
+struct Queued(bool);
+
+impl Drop for Queued {
+    fn drop(&mut self) {
+        if self.0 {
+            QUEUED.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+}
+
+struct Running;
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        RUNNING.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Blocking operation completion future. It resolves with results
+/// of blocking function execution.
+pub struct CpuFuture<I, E> {
+    rx: Option<oneshot::Receiver<Result<I, E>>>,
+}
+
+impl<I, E: fmt::Debug> Future for CpuFuture<I, E> {
+    type Output = Result<I, BlockingError<E>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let rx = match self.rx {
+            Some(ref mut rx) => rx,
+            None => return Poll::Ready(Err(BlockingError::Overloaded)),
+        };
+        match Pin::new(rx).poll(cx) {
+            Poll::Ready(Ok(res)) => Poll::Ready(res.map_err(BlockingError::Error)),
+            Poll::Ready(Err(_)) => Poll::Ready(Err(BlockingError::Canceled)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+
+    #[test]
+    fn test_queue_limit() {
+        crate::System::new("test").block_on(async {
+            let (tx, rx) = mpsc::channel::<()>();
+            let (started_tx, started_rx) = mpsc::channel::<()>();
+            assert!(config(1, 1));
+
+            // occupies the only thread
+            let first = run(move || {
+                let _ = started_tx.send(());
+                let _ = rx.recv();
+                Ok::<_, ()>(1)
+            });
+            started_rx.recv().unwrap();
+            assert_eq!(running(), 1);
+
+            let second = run(|| Ok::<_, ()>(2));
+            assert_eq!(queue_depth(), 1);
+            match run(|| Ok::<_, ()>(3)).await {
+                Err(BlockingError::Overloaded) => (),
+                _ => panic!(),
+            }
+
+            tx.send(()).unwrap();
+            assert_eq!(first.await.unwrap(), 1);
+            assert_eq!(second.await.unwrap(), 2);
+            assert!(wait(Instant::now() + Duration::from_secs(1)).await);
+            assert_eq!(queue_depth(), 0);
+            assert_eq!(running(), 0);
+
+            // thread pool is already created
+            assert!(!config(2, 0));
+        })
+    }
+}
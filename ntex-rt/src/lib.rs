@@ -2,6 +2,7 @@
 use futures::future::{self, Future, FutureExt};
 
 mod arbiter;
+pub mod blocking;
 mod builder;
 mod runtime;
 mod system;
@@ -14,9 +15,6 @@ pub use self::system::System;
 #[cfg(not(test))] // Work around for rust-lang/rust#62127
 pub use ntex_rt_macros::{rt_main as main, rt_test as test};
 
-#[doc(hidden)]
-pub use actix_threadpool as blocking;
-
 /// Spawn a future on the current thread. This does not create a new Arbiter
 /// or Arbiter address, it is simply a helper for spawning futures on the current
 /// thread.
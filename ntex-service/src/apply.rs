@@ -6,6 +6,35 @@ use std::task::{Context, Poll};
 use super::{IntoService, IntoServiceFactory, Service, ServiceFactory};
 
 /// Apply tranform function to a service.
+///
+/// Transform function receives request and reference to the inner service,
+/// it could modify request before calling inner service and modify
+/// response of the inner service.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// use ntex_service::{apply_fn, fn_service, Service};
+/// use futures_util::future::ok;
+///
+/// #[ntex_rt::main]
+/// async fn main() -> io::Result<()> {
+///     let srv = fn_service(|x: usize| ok::<_, io::Error>(x * 2));
+///
+///     // call inner service with length of the string
+///     let srv = apply_fn(srv, |req: &'static str, srv| {
+///         let fut = srv.call(req.len());
+///         async move {
+///             let res = fut.await?;
+///             Ok::<_, io::Error>(res + 1)
+///         }
+///     });
+///
+///     assert_eq!(srv.call("abc").await?, 7);
+///     Ok(())
+/// }
+/// ```
 pub fn apply_fn<T, F, R, In, Out, Err, U>(
     service: U,
     f: F,
@@ -20,6 +49,30 @@ where
 }
 
 /// Service factory that prodices `apply_fn` service.
+///
+/// Transform function is cloned for each created service.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// use ntex_service::{apply_fn_factory, fn_factory, fn_service, Service, ServiceFactory};
+/// use futures_util::future::ok;
+///
+/// #[ntex_rt::main]
+/// async fn main() -> io::Result<()> {
+///     let factory = fn_factory(|| {
+///         ok::<_, io::Error>(fn_service(|x: usize| ok::<_, io::Error>(x * 2)))
+///     });
+///
+///     // increment request before calling inner service
+///     let factory = apply_fn_factory(factory, |req: usize, srv| srv.call(req + 1));
+///
+///     let srv = factory.new_service(()).await?;
+///     assert_eq!(srv.call(1).await?, 4);
+///     Ok(())
+/// }
+/// ```
 pub fn apply_fn_factory<T, F, R, In, Out, Err, U>(
     service: U,
     f: F,
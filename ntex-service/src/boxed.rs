@@ -1,3 +1,12 @@
+//! Type-erased services and service factories
+//!
+//! Boxed services are not `Send`, so services with `Rc` based state could
+//! be boxed as well. Boxed factories preserve config type.
+//!
+//! `local_service()` does not wrap services that are boxed already, and
+//! returns response futures that are `BoxFuture` already as is, without
+//! additional allocation.
+use std::any::Any;
 use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
@@ -17,6 +26,9 @@ pub type BoxService<Req, Res, Err> = Box<
     >,
 >;
 
+/// Boxed service that is not `Send`, same type as `BoxService`
+pub type LocalBoxService<Req, Res, Err> = BoxService<Req, Res, Err>;
+
 pub struct BoxServiceFactory<C, Req, Res, Err, InitErr>(
     Inner<C, Req, Res, Err, InitErr>,
 );
@@ -49,6 +61,36 @@ where
     Box::new(ServiceWrapper(service))
 }
 
+/// Create boxed service without double boxing
+///
+/// Service that is `LocalBoxService` already is returned as is, response
+/// futures that are `BoxFuture` already are not boxed again. Service and
+/// response future types are checked at runtime with `Any`, so request,
+/// response and error types must be `'static`.
+pub fn local_service<T>(service: T) -> LocalBoxService<T::Request, T::Response, T::Error>
+where
+    T: Service + 'static,
+    T::Request: 'static,
+    T::Response: 'static,
+    T::Error: 'static,
+    T::Future: 'static,
+{
+    match downcast(service) {
+        Ok(service) => service,
+        Err(service) => Box::new(LocalServiceWrapper(service)),
+    }
+}
+
+/// Move value to type `U` if value is of that type
+fn downcast<T: 'static, U: 'static>(val: T) -> Result<U, T> {
+    let mut val = Some(val);
+    if let Some(val) = (&mut val as &mut dyn Any).downcast_mut::<Option<U>>() {
+        Ok(val.take().unwrap())
+    } else {
+        Err(val.unwrap())
+    }
+}
+
 type Inner<C, Req, Res, Err, InitErr> = Box<
     dyn ServiceFactory<
         Config = C,
@@ -117,25 +159,46 @@ where
         Box::pin(
             self.factory
                 .new_service(cfg)
-                .map(|res| res.map(ServiceWrapper::boxed)),
+                .map(|res| res.map(local_service)),
         )
     }
 }
 
 struct ServiceWrapper<T: Service>(T);
 
-impl<T> ServiceWrapper<T>
+impl<T, Req, Res, Err> Service for ServiceWrapper<T>
 where
-    T: Service + 'static,
+    T: Service<Request = Req, Response = Res, Error = Err>,
     T::Future: 'static,
 {
-    fn boxed(service: T) -> BoxService<T::Request, T::Response, T::Error> {
-        Box::new(ServiceWrapper(service))
+    type Request = Req;
+    type Response = Res;
+    type Error = Err;
+    type Future = BoxFuture<Res, Err>;
+
+    #[inline]
+    fn poll_ready(&self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.0.poll_ready(ctx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.0.poll_shutdown(cx, is_error)
+    }
+
+    #[inline]
+    fn call(&self, req: Self::Request) -> Self::Future {
+        Box::pin(self.0.call(req))
     }
 }
 
-impl<T, Req, Res, Err> Service for ServiceWrapper<T>
+struct LocalServiceWrapper<T: Service>(T);
+
+impl<T, Req, Res, Err> Service for LocalServiceWrapper<T>
 where
+    Req: 'static,
+    Res: 'static,
+    Err: 'static,
     T: Service<Request = Req, Response = Res, Error = Err>,
     T::Future: 'static,
 {
@@ -156,6 +219,136 @@ where
 
     #[inline]
     fn call(&self, req: Self::Request) -> Self::Future {
-        Box::pin(self.0.call(req))
+        // do not box response future twice
+        match downcast(self.0.call(req)) {
+            Ok(fut) => fut,
+            Err(fut) => Box::pin(fut),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use futures_util::future::{lazy, ok};
+
+    use super::*;
+    use crate::{fn_factory_with_config, fn_service};
+
+    #[derive(Clone)]
+    struct Srv(Rc<Cell<usize>>);
+
+    impl Service for Srv {
+        type Request = usize;
+        type Response = usize;
+        type Error = ();
+        type Future = BoxFuture<usize, ()>;
+
+        fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&self, req: usize) -> Self::Future {
+            self.0.set(self.0.get() + 1);
+            Box::pin(ok(req * 2))
+        }
+    }
+
+    #[ntex_rt::test]
+    async fn test_service() {
+        let counter = Rc::new(Cell::new(0));
+        let srv = service(Srv(counter.clone()));
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(srv.call(1).await, Ok(2));
+        assert_eq!(counter.get(), 1);
+
+        let srv = service(fn_service(|i: usize| ok::<_, ()>(i + 1)));
+        assert_eq!(srv.call(1).await, Ok(2));
+    }
+
+    // generic code does not need to know that request, response
+    // and error types are 'static
+    fn boxed<T>(srv: T) -> BoxService<T::Request, T::Response, T::Error>
+    where
+        T: Service + 'static,
+        T::Future: 'static,
+    {
+        service(srv)
+    }
+
+    #[ntex_rt::test]
+    async fn test_service_generic() {
+        let counter = Rc::new(Cell::new(0));
+        let srv = boxed(Srv(counter.clone()));
+        assert_eq!(srv.call(1).await, Ok(2));
+        assert_eq!(counter.get(), 1);
+    }
+
+    #[ntex_rt::test]
+    async fn test_local_service() {
+        let counter = Rc::new(Cell::new(0));
+        let srv = local_service(Srv(counter.clone()));
+        assert_eq!(srv.call(1).await, Ok(2));
+        assert_eq!(counter.get(), 1);
+
+        // boxed service is not wrapped again
+        let ptr = &*srv as *const _ as *const u8;
+        let srv = local_service(srv);
+        assert_eq!(ptr, &*srv as *const _ as *const u8);
+        assert_eq!(srv.call(2).await, Ok(4));
+        assert_eq!(counter.get(), 2);
+    }
+
+    struct FutSrv(Rc<Cell<usize>>);
+
+    impl Service for FutSrv {
+        type Request = usize;
+        type Response = usize;
+        type Error = ();
+        type Future = BoxFuture<usize, ()>;
+
+        fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&self, req: usize) -> Self::Future {
+            let fut: BoxFuture<usize, ()> = Box::pin(ok(req));
+            self.0.set(&*fut as *const _ as *const u8 as usize);
+            fut
+        }
+    }
+
+    #[ntex_rt::test]
+    async fn test_service_future() {
+        let ptr = Rc::new(Cell::new(0));
+        let srv = local_service(FutSrv(ptr.clone()));
+
+        // boxed response future is not boxed again
+        let fut = srv.call(1);
+        assert_eq!(&*fut as *const _ as *const u8 as usize, ptr.get());
+        assert_eq!(fut.await, Ok(1));
+    }
+
+    #[ntex_rt::test]
+    async fn test_factory() {
+        let counter = Rc::new(Cell::new(0));
+        let counter2 = counter.clone();
+        let srv_factory = factory(fn_factory_with_config(move |cfg: usize| {
+            let counter = counter2.clone();
+            async move {
+                if cfg == 0 {
+                    Err("err")
+                } else {
+                    Ok(Srv(counter))
+                }
+            }
+        }));
+
+        assert_eq!(srv_factory.new_service(0).await.err(), Some("err"));
+        let srv = srv_factory.new_service(1).await.unwrap();
+        assert_eq!(srv.call(2).await, Ok(4));
+        assert_eq!(counter.get(), 1);
     }
 }
@@ -1,6 +1,7 @@
 use std::cell::RefCell;
 use std::future::Future;
 use std::marker::PhantomData;
+use std::rc::Rc;
 use std::task::{Context, Poll};
 
 use futures_util::future::{ok, Ready};
@@ -29,6 +30,53 @@ where
     FnMutService::new(f)
 }
 
+#[inline]
+/// Create `Service` for function that receives shared state with each call
+///
+/// State is stored in `Rc`, function receives clone of it with every
+/// request, so returned future could use state without borrowing service.
+/// Service also acts as a `ServiceFactory`, all produced services share
+/// the same state.
+///
+/// # Example
+///
+/// ```rust
+/// use std::{cell::Cell, io, rc::Rc};
+/// use ntex_service::{fn_factory, fn_state_service, Service, ServiceFactory};
+///
+/// async fn count(counter: Rc<Cell<usize>>, step: usize) -> Result<usize, io::Error> {
+///     counter.set(counter.get() + step);
+///     Ok(counter.get())
+/// }
+///
+/// #[ntex_rt::main]
+/// async fn main() -> io::Result<()> {
+///     // every new service gets its own counter
+///     let factory = fn_factory(|| async {
+///         Ok::<_, io::Error>(fn_state_service(Cell::new(0), count))
+///     });
+///
+///     let srv = factory.new_service(()).await?;
+///     assert_eq!(srv.call(1).await?, 1);
+///     assert_eq!(srv.call(2).await?, 3);
+///     Ok(())
+/// }
+/// ```
+pub fn fn_state_service<F, Fut, St, Req, Res, Err>(
+    state: St,
+    f: F,
+) -> FnStateService<F, Fut, St, Req, Res, Err>
+where
+    F: Fn(Rc<St>, Req) -> Fut,
+    Fut: Future<Output = Result<Res, Err>>,
+{
+    FnStateService {
+        f,
+        state: Rc::new(state),
+        _t: PhantomData,
+    }
+}
+
 #[inline]
 /// Create `ServiceFactory` for function that can produce services
 ///
@@ -265,6 +313,85 @@ where
     }
 }
 
+/// Service for `Fn(Rc<State>, Req) -> Future<Result<Res, Err>>` fn
+pub struct FnStateService<F, Fut, St, Req, Res, Err>
+where
+    F: Fn(Rc<St>, Req) -> Fut,
+    Fut: Future<Output = Result<Res, Err>>,
+{
+    f: F,
+    state: Rc<St>,
+    _t: PhantomData<Req>,
+}
+
+impl<F, Fut, St, Req, Res, Err> FnStateService<F, Fut, St, Req, Res, Err>
+where
+    F: Fn(Rc<St>, Req) -> Fut,
+    Fut: Future<Output = Result<Res, Err>>,
+{
+    /// Service state
+    pub fn state(&self) -> &Rc<St> {
+        &self.state
+    }
+}
+
+impl<F, Fut, St, Req, Res, Err> Clone for FnStateService<F, Fut, St, Req, Res, Err>
+where
+    F: Fn(Rc<St>, Req) -> Fut + Clone,
+    Fut: Future<Output = Result<Res, Err>>,
+{
+    #[inline]
+    fn clone(&self) -> Self {
+        FnStateService {
+            f: self.f.clone(),
+            state: self.state.clone(),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<F, Fut, St, Req, Res, Err> Service for FnStateService<F, Fut, St, Req, Res, Err>
+where
+    F: Fn(Rc<St>, Req) -> Fut,
+    Fut: Future<Output = Result<Res, Err>>,
+{
+    type Request = Req;
+    type Response = Res;
+    type Error = Err;
+    type Future = Fut;
+
+    #[inline]
+    fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    #[inline]
+    fn call(&self, req: Req) -> Self::Future {
+        (self.f)(self.state.clone(), req)
+    }
+}
+
+impl<F, Fut, St, Req, Res, Err> ServiceFactory
+    for FnStateService<F, Fut, St, Req, Res, Err>
+where
+    F: Fn(Rc<St>, Req) -> Fut + Clone,
+    Fut: Future<Output = Result<Res, Err>>,
+{
+    type Request = Req;
+    type Response = Res;
+    type Error = Err;
+
+    type Config = ();
+    type Service = FnStateService<F, Fut, St, Req, Res, Err>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Service, Self::InitError>>;
+
+    #[inline]
+    fn new_service(&self, _: ()) -> Self::Future {
+        ok(self.clone())
+    }
+}
+
 /// Convert `Fn(&Config) -> Future<Service>` fn to NewService
 pub struct FnServiceConfig<F, Fut, Cfg, Srv, Err>
 where
@@ -488,6 +615,23 @@ mod tests {
         assert_eq!(res.unwrap(), "srv");
     }
 
+    #[ntex_rt::test]
+    async fn test_fn_state_service() {
+        let new_srv = fn_state_service(std::cell::Cell::new(0), |st, step: usize| {
+            st.set(st.get() + step);
+            ok::<_, ()>(st.get())
+        });
+
+        let srv = new_srv.new_service(()).await.unwrap();
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(srv.call(1).await, Ok(1));
+        assert_eq!(srv.call(2).await, Ok(3));
+
+        // services produced by factory share state
+        assert_eq!(new_srv.call(1).await, Ok(4));
+        assert_eq!(new_srv.state().get(), 4);
+    }
+
     #[ntex_rt::test]
     async fn test_fn_service_with_config() {
         let new_srv = fn_factory_with_config(|cfg: usize| {
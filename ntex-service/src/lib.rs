@@ -23,10 +23,11 @@ mod transform_err;
 
 pub use self::apply::{apply_fn, apply_fn_factory};
 pub use self::fn_service::{
-    fn_factory, fn_factory_with_config, fn_mut_service, fn_service,
+    fn_factory, fn_factory_with_config, fn_mut_service, fn_service, fn_state_service,
 };
 pub use self::fn_transform::fn_transform;
 pub use self::map_config::{map_config, map_config_service, unit_config};
+pub use self::map_init_err::map_init_err;
 pub use self::pipeline::{pipeline, pipeline_factory, Pipeline, PipelineFactory};
 pub use self::transform::{apply, Transform};
 
@@ -302,6 +303,24 @@ where
 }
 
 /// Trait for types that can be converted to a `ServiceFactory`
+///
+/// Closures that return future of a service are converted to service
+/// factories, so they could be used anywhere service factory is expected.
+///
+/// ```rust
+/// use ntex_service::{fn_service, pipeline_factory, Service, ServiceFactory};
+/// use futures_util::future::ok;
+///
+/// #[ntex_rt::main]
+/// async fn main() {
+///     let factory = pipeline_factory(|| async {
+///         Ok::<_, ()>(fn_service(|x: usize| ok::<_, ()>(x + 1)))
+///     });
+///
+///     let srv = factory.new_service(()).await.unwrap();
+///     assert_eq!(srv.call(1).await, Ok(2));
+/// }
+/// ```
 pub trait IntoServiceFactory<T>
 where
     T: ServiceFactory,
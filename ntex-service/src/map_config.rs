@@ -13,6 +13,28 @@ use super::{IntoServiceFactory, Service, ServiceFactory};
 ///
 /// Note that this function consumes the receiving service factory and returns
 /// a wrapped version of it.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// use ntex_service::{fn_factory_with_config, fn_service, map_config, Service, ServiceFactory};
+/// use futures_util::future::ok;
+///
+/// #[ntex_rt::main]
+/// async fn main() -> io::Result<()> {
+///     let factory = fn_factory_with_config(|y: usize| {
+///         ok::<_, io::Error>(fn_service(move |x: usize| ok::<_, io::Error>(x * y)))
+///     });
+///
+///     // factory accepts `u8` config
+///     let factory = map_config(factory, |cfg: u8| cfg as usize);
+///
+///     let srv = factory.new_service(3u8).await?;
+///     assert_eq!(srv.call(2).await?, 6);
+///     Ok(())
+/// }
+/// ```
 pub fn map_config<T, U, F, C>(factory: U, f: F) -> MapConfig<T, F, C>
 where
     T: ServiceFactory,
@@ -45,6 +67,31 @@ where
 }
 
 /// Replace config with unit
+///
+/// Factory that does not use config could be used in places that require
+/// specific config type, config value is dropped.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// use ntex_service::{fn_factory, fn_service, unit_config, Service, ServiceFactory};
+/// use futures_util::future::ok;
+///
+/// #[ntex_rt::main]
+/// async fn main() -> io::Result<()> {
+///     let factory = fn_factory(|| {
+///         ok::<_, io::Error>(fn_service(|x: usize| ok::<_, io::Error>(x * 2)))
+///     });
+///
+///     // factory accepts `usize` config
+///     let factory = unit_config(factory);
+///
+///     let srv = factory.new_service(10usize).await?;
+///     assert_eq!(srv.call(2).await?, 4);
+///     Ok(())
+/// }
+/// ```
 pub fn unit_config<T, U, C>(factory: U) -> UnitConfig<T, C>
 where
     T: ServiceFactory<Config = ()>,
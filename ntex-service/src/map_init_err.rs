@@ -3,7 +3,46 @@ use std::marker::PhantomData;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
-use super::ServiceFactory;
+use super::{IntoServiceFactory, ServiceFactory};
+
+/// Map init error of provided service factory
+///
+/// Note that this function consumes the receiving service factory and returns
+/// a wrapped version of it.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// use ntex_service::{fn_factory_with_config, fn_service, map_init_err, ServiceFactory};
+/// use futures_util::future::ok;
+///
+/// #[ntex_rt::main]
+/// async fn main() {
+///     let factory = fn_factory_with_config(|fail: bool| async move {
+///         if fail {
+///             Err(())
+///         } else {
+///             Ok(fn_service(|x: usize| ok::<_, io::Error>(x * 2)))
+///         }
+///     });
+///
+///     let factory = map_init_err(factory, |_| {
+///         io::Error::new(io::ErrorKind::Other, "can not create service")
+///     });
+///
+///     assert!(factory.new_service(true).await.is_err());
+///     assert!(factory.new_service(false).await.is_ok());
+/// }
+/// ```
+pub fn map_init_err<T, U, F, E>(factory: U, f: F) -> MapInitErr<T, F, E>
+where
+    T: ServiceFactory,
+    U: IntoServiceFactory<T>,
+    F: Fn(T::InitError) -> E + Clone,
+{
+    MapInitErr::new(factory.into_factory(), f)
+}
 
 /// `MapInitErr` service combinator
 pub struct MapInitErr<A, F, E> {
@@ -99,7 +138,8 @@ where
 mod tests {
     use futures_util::future::ok;
 
-    use crate::{fn_factory_with_config, fn_service, pipeline_factory, ServiceFactory};
+    use crate::{fn_factory_with_config, fn_service, pipeline_factory};
+    use crate::{Service, ServiceFactory};
 
     #[ntex_rt::test]
     async fn map_init_err() {
@@ -116,4 +156,15 @@ mod tests {
         assert!(factory.new_service(true).await.is_err());
         assert!(factory.new_service(false).await.is_ok());
     }
+
+    #[ntex_rt::test]
+    async fn map_init_err_fn() {
+        let factory = super::map_init_err(
+            || ok::<_, ()>(fn_service(|i: usize| ok::<_, ()>(i * 2))),
+            |_| "err",
+        )
+        .clone();
+        let srv = factory.new_service(()).await.unwrap();
+        assert_eq!(srv.call(2).await, Ok(4));
+    }
 }
@@ -0,0 +1,89 @@
+//! Allocations and time per http/1 request with and without objects pooling
+//!
+//! Pooling is disabled with `NTEX_DISABLE_POOL` environment variable,
+//! pools are created per thread, so each measurement runs in new thread.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{env, thread};
+
+use bytes::{Bytes, BytesMut};
+use criterion::Criterion;
+use ntex::codec::Decoder;
+use ntex::http::h1::{Codec, Message, Payload};
+
+struct Counter;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counter {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counter = Counter;
+
+const REQUEST: &[u8] =
+    b"POST /test HTTP/1.1\r\nhost: localhost\r\ncontent-length: 4\r\n\r\n";
+
+/// Decode request head and pass body through payload channel
+fn request(codec: &mut Codec, buf: &mut BytesMut) {
+    buf.extend_from_slice(REQUEST);
+    let req = match codec.decode(buf).unwrap() {
+        Some(Message::Item(req)) => req,
+        _ => panic!(),
+    };
+    let (mut sender, payload) = Payload::create(false);
+    sender.feed_data(Bytes::from_static(b"test"));
+    sender.feed_eof();
+    drop(payload);
+    drop(req);
+
+    buf.extend_from_slice(b"test");
+    let _ = codec.decode(buf).unwrap();
+    let _ = codec.decode(buf).unwrap();
+}
+
+/// Average number of allocations per request
+fn allocations() -> usize {
+    const REQUESTS: usize = 10_000;
+
+    let mut codec = Codec::default();
+    let mut buf = BytesMut::with_capacity(1024);
+    // warm up pools
+    request(&mut codec, &mut buf);
+
+    let start = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..REQUESTS {
+        request(&mut codec, &mut buf);
+    }
+    (ALLOCATIONS.load(Ordering::Relaxed) - start) / REQUESTS
+}
+
+fn main() {
+    for &(name, pool) in &[("h1 request pooled", true), ("h1 request unpooled", false)] {
+        if pool {
+            env::remove_var("NTEX_DISABLE_POOL");
+        } else {
+            env::set_var("NTEX_DISABLE_POOL", "1");
+        }
+
+        thread::spawn(move || {
+            println!("{}: {} allocations per request", name, allocations());
+
+            let mut c = Criterion::default().configure_from_args();
+            let mut codec = Codec::default();
+            let mut buf = BytesMut::with_capacity(1024);
+            c.bench_function(name, |b| b.iter(|| request(&mut codec, &mut buf)));
+            c.final_summary();
+        })
+        .join()
+        .unwrap();
+    }
+}
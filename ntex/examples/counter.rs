@@ -0,0 +1,34 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::{env, io};
+
+use futures::future::ok;
+use ntex::http::{HttpService, Request, Response};
+use ntex::server::Server;
+use ntex::service::{fn_factory, fn_state_service};
+
+/// Per-worker request counter
+async fn handle_request(
+    counter: Rc<Cell<usize>>,
+    _: Request,
+) -> Result<Response, io::Error> {
+    counter.set(counter.get() + 1);
+    Ok(Response::Ok().body(format!("requests: {}\n", counter.get())))
+}
+
+#[ntex::main]
+async fn main() -> io::Result<()> {
+    env::set_var("RUST_LOG", "counter=info");
+    env_logger::init();
+
+    Server::build()
+        .bind("counter", "127.0.0.1:8080", || {
+            HttpService::build()
+                .finish(fn_factory(|| {
+                    ok::<_, ()>(fn_state_service(Cell::new(0), handle_request))
+                }))
+                .tcp()
+        })?
+        .run()
+        .await
+}
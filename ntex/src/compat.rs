@@ -0,0 +1,294 @@
+//! Adapters for `tower` services and layers
+//!
+//! Tower services take `&mut self` in `poll_ready()` and `call()`, ntex
+//! services take `&self`. `TowerService` keeps tower service in `RefCell`
+//! and `NtexService` keeps ntex service in `Rc`, so adapters are not `Send`
+//! and could be used only on the worker thread they are created on. Tower
+//! middlewares that require `Send` inner service or move it to another task,
+//! like `tower::buffer`, could not be applied to ntex services.
+//!
+//! Tower services expect `poll_ready()` to be called before each `call()`,
+//! ntex dispatchers check readiness of the service before calling it as well.
+//!
+//! Tower layer applied to web application:
+//!
+//! ```rust,no_run
+//! use std::task::{Context, Poll};
+//! use ntex::{compat, web};
+//! use tower_layer::layer_fn;
+//!
+//! /// Tower middleware that logs requests
+//! struct Log<S>(S);
+//!
+//! impl<S, Req> tower_service::Service<Req> for Log<S>
+//! where
+//!     S: tower_service::Service<Req>,
+//! {
+//!     type Response = S::Response;
+//!     type Error = S::Error;
+//!     type Future = S::Future;
+//!
+//!     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+//!         self.0.poll_ready(cx)
+//!     }
+//!
+//!     fn call(&mut self, req: Req) -> S::Future {
+//!         println!("request");
+//!         self.0.call(req)
+//!     }
+//! }
+//!
+//! #[ntex::main]
+//! async fn main() -> std::io::Result<()> {
+//!     web::server(|| {
+//!         web::App::new()
+//!             .wrap(compat::layer(layer_fn(Log)))
+//!             .route("/index.html", web::get().to(|| async { "hello_world" }))
+//!     })
+//!     .bind("127.0.0.1:8080")?
+//!     .run()
+//!     .await
+//! }
+//! ```
+use std::cell::RefCell;
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use futures::future::{ok, Ready};
+use tower_layer::Layer;
+
+use crate::service::{Service, Transform};
+
+/// Use `tower` service as ntex service
+pub fn from_tower<S, Req>(service: S) -> TowerService<S, Req>
+where
+    S: tower_service::Service<Req>,
+{
+    TowerService {
+        service: RefCell::new(service),
+        _t: PhantomData,
+    }
+}
+
+/// Use ntex service as `tower` service
+pub fn into_tower<S: Service>(service: S) -> NtexService<S> {
+    NtexService(Rc::new(service))
+}
+
+/// Use `tower` layer as ntex transform
+///
+/// Layer wraps inner service with `NtexService` adapter, shutdown of
+/// transform service is passed to inner service.
+pub fn layer<L>(layer: L) -> LayerTransform<L> {
+    LayerTransform(layer)
+}
+
+/// Ntex service for `tower` service
+pub struct TowerService<S, Req> {
+    service: RefCell<S>,
+    _t: PhantomData<fn(Req)>,
+}
+
+impl<S: Clone, Req> Clone for TowerService<S, Req> {
+    fn clone(&self) -> Self {
+        TowerService {
+            service: RefCell::new(self.service.borrow().clone()),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<S, Req> Service for TowerService<S, Req>
+where
+    S: tower_service::Service<Req>,
+{
+    type Request = Req;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    #[inline]
+    fn call(&self, req: Req) -> Self::Future {
+        self.service.borrow_mut().call(req)
+    }
+}
+
+/// `Tower` service for ntex service
+pub struct NtexService<S>(Rc<S>);
+
+impl<S> Clone for NtexService<S> {
+    fn clone(&self) -> Self {
+        NtexService(self.0.clone())
+    }
+}
+
+impl<S: Service> tower_service::Service<S::Request> for NtexService<S> {
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.0.poll_ready(cx)
+    }
+
+    #[inline]
+    fn call(&mut self, req: S::Request) -> Self::Future {
+        self.0.call(req)
+    }
+}
+
+/// Ntex transform for `tower` layer
+#[derive(Clone)]
+pub struct LayerTransform<L>(L);
+
+impl<L, S> Transform<S> for LayerTransform<L>
+where
+    S: Service,
+    L: Layer<NtexService<S>>,
+    L::Service: tower_service::Service<S::Request>,
+{
+    type Request = S::Request;
+    type Response = <L::Service as tower_service::Service<S::Request>>::Response;
+    type Error = <L::Service as tower_service::Service<S::Request>>::Error;
+    type Transform = LayerService<L::Service, S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        let inner = Rc::new(service);
+        let service = self.0.layer(NtexService(inner.clone()));
+        ok(LayerService {
+            service: from_tower(service),
+            inner,
+        })
+    }
+}
+
+/// Service created by `tower` layer
+pub struct LayerService<T, S: Service> {
+    service: TowerService<T, S::Request>,
+    inner: Rc<S>,
+}
+
+impl<T, S> Service for LayerService<T, S>
+where
+    S: Service,
+    T: tower_service::Service<S::Request>,
+{
+    type Request = S::Request;
+    type Response = T::Response;
+    type Error = T::Error;
+    type Future = T::Future;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.inner.poll_shutdown(cx, is_error)
+    }
+
+    #[inline]
+    fn call(&self, req: S::Request) -> Self::Future {
+        self.service.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use futures::future::{lazy, ok, Ready};
+    use tower_layer::layer_fn;
+
+    use super::*;
+    use crate::service::{apply, fn_factory, fn_service, ServiceFactory};
+
+    /// Tower service that counts calls
+    #[derive(Clone)]
+    struct Count<S>(S, Rc<Cell<usize>>);
+
+    impl<S, Req> tower_service::Service<Req> for Count<S>
+    where
+        S: tower_service::Service<Req>,
+    {
+        type Response = S::Response;
+        type Error = S::Error;
+        type Future = S::Future;
+
+        fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+            self.0.poll_ready(cx)
+        }
+
+        fn call(&mut self, req: Req) -> S::Future {
+            self.1.set(self.1.get() + 1);
+            self.0.call(req)
+        }
+    }
+
+    struct Srv(Rc<Cell<bool>>);
+
+    impl Service for Srv {
+        type Request = usize;
+        type Response = usize;
+        type Error = ();
+        type Future = Ready<Result<usize, ()>>;
+
+        fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), ()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(&self, _: &mut Context<'_>, _: bool) -> Poll<()> {
+            self.0.set(true);
+            Poll::Ready(())
+        }
+
+        fn call(&self, req: usize) -> Self::Future {
+            ok(req * 2)
+        }
+    }
+
+    #[ntex_rt::test]
+    async fn test_tower_service() {
+        let counter = Rc::new(Cell::new(0));
+        let srv = into_tower(fn_service(|i: usize| ok::<_, ()>(i + 1)));
+        let srv = from_tower(Count(srv, counter.clone())).clone();
+
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(srv.call(1).await, Ok(2));
+        assert_eq!(counter.get(), 1);
+    }
+
+    #[ntex_rt::test]
+    async fn test_layer() {
+        let counter = Rc::new(Cell::new(0));
+        let shutdown = Rc::new(Cell::new(false));
+
+        let counter2 = counter.clone();
+        let shutdown2 = shutdown.clone();
+        let factory = apply(
+            layer(layer_fn(move |srv| Count(srv, counter2.clone()))),
+            fn_factory(move || ok::<_, ()>(Srv(shutdown2.clone()))),
+        );
+        let srv = factory.new_service(()).await.unwrap();
+
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(srv.call(2).await, Ok(4));
+        assert_eq!(counter.get(), 1);
+
+        assert_eq!(
+            lazy(|cx| srv.poll_shutdown(cx, false)).await,
+            Poll::Ready(())
+        );
+        assert!(shutdown.get());
+    }
+}
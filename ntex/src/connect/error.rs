@@ -1,8 +1,14 @@
 use std::io;
+use std::net::SocketAddr;
 
 use derive_more::{Display, From};
 use trust_dns_resolver::error::ResolveError;
 
+/// Connect error
+///
+/// `Resolver`, `NoRecords` and `Unresolved` errors happen during address
+/// resolution, `Bind`, `Timeout` and `Io` errors during tcp connect,
+/// `Handshake` errors during tls handshake.
 #[derive(Debug, From, Display)]
 pub enum ConnectError {
     /// Failed to resolve the hostname
@@ -20,7 +26,32 @@ pub enum ConnectError {
     #[display(fmt = "Connector received `Connect` method with unresolved host")]
     Unresolved,
 
+    /// Failed to bind socket to local address
+    #[display(fmt = "Failed to bind local address {}: {}", _0, _1)]
+    #[from(ignore)]
+    Bind(SocketAddr, io::Error),
+
+    /// Tcp connection is not established within timeout
+    #[display(fmt = "Timeout out while establishing connection")]
+    Timeout,
+
+    /// Tls handshake error
+    #[display(fmt = "Tls handshake error: {}", _0)]
+    #[from(ignore)]
+    Handshake(Box<dyn std::error::Error + Send + Sync>),
+
     /// Connection io error
     #[display(fmt = "{}", _0)]
     Io(io::Error),
 }
+
+impl std::error::Error for ConnectError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConnectError::Resolver(e) => Some(e),
+            ConnectError::Bind(_, e) | ConnectError::Io(e) => Some(e),
+            ConnectError::Handshake(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
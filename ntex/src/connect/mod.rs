@@ -58,5 +58,33 @@ where
 {
     service::ConnectServiceResponse::new(
         Resolver::new(default_resolver()).lookup(message.into()),
+        None,
+        None,
+        None,
     )
 }
+
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+/// Run tls handshake, zero timeout disables limit
+pub(crate) async fn handshake<F, R>(
+    timeout: std::time::Duration,
+    fut: F,
+) -> Result<R, ConnectError>
+where
+    F: Future<Output = Result<R, ConnectError>>,
+{
+    if timeout == std::time::Duration::from_millis(0) {
+        fut.await
+    } else {
+        match crate::rt::time::timeout(timeout, fut).await {
+            Ok(res) => res,
+            Err(_) => {
+                trace!("SSL Handshake timeout");
+                Err(ConnectError::Handshake(Box::new(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "Tls handshake timeout",
+                ))))
+            }
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use std::future::Future;
-use std::io;
 use std::task::{Context, Poll};
+use std::time::Duration;
 
 use futures::future::{ok, FutureExt, LocalBoxFuture, Ready};
 pub use open_ssl::ssl::{Error as SslError, SslConnector, SslMethod};
@@ -14,6 +14,7 @@ use super::{Address, AsyncResolver, Connect, ConnectError, Connector};
 pub struct OpensslConnector<T> {
     connector: Connector<T>,
     openssl: SslConnector,
+    timeout: Duration,
 }
 
 impl<T> OpensslConnector<T> {
@@ -22,6 +23,7 @@ impl<T> OpensslConnector<T> {
         OpensslConnector {
             connector: Connector::default(),
             openssl: connector,
+            timeout: Duration::from_millis(0),
         }
     }
 
@@ -30,8 +32,31 @@ impl<T> OpensslConnector<T> {
         OpensslConnector {
             connector: Connector::new(resolver),
             openssl: connector,
+            timeout: Duration::from_millis(0),
         }
     }
+
+    /// Construct new connect service with configured tcp connector
+    pub fn with_connector(openssl: SslConnector, connector: Connector<T>) -> Self {
+        OpensslConnector {
+            connector,
+            openssl,
+            timeout: Duration::from_millis(0),
+        }
+    }
+
+    /// Set tls handshake timeout
+    ///
+    /// Timeout starts after tcp connection is established and covers
+    /// whole handshake including certificate verification. Connect fails
+    /// with `ConnectError::Handshake` error if handshake is not completed
+    /// in time. To disable timeout set value to 0.
+    ///
+    /// By default handshake timeout is disabled.
+    pub fn handshake_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
 }
 
 impl<T: Address + 'static> OpensslConnector<T> {
@@ -47,25 +72,28 @@ impl<T: Address + 'static> OpensslConnector<T> {
         let host = message.host().to_string();
         let conn = self.connector.call(message);
         let openssl = self.openssl.clone();
+        let timeout = self.timeout;
 
         async move {
             let io = conn.await?;
             trace!("SSL Handshake start for: {:?}", host);
 
-            match openssl.configure() {
-                Err(e) => Err(io::Error::new(io::ErrorKind::Other, e).into()),
-                Ok(config) => match tokio_openssl::connect(config, &host, io).await {
+            let config = openssl
+                .configure()
+                .map_err(|e| ConnectError::Handshake(Box::new(e)))?;
+            super::handshake(timeout, async {
+                match tokio_openssl::connect(config, &host, io).await {
                     Ok(io) => {
                         trace!("SSL Handshake success: {:?}", host);
                         Ok(io)
                     }
                     Err(e) => {
                         trace!("SSL Handshake error: {:?}", e);
-                        Err(io::Error::new(io::ErrorKind::Other, format!("{}", e))
-                            .into())
+                        Err(ConnectError::Handshake(Box::new(e)))
                     }
-                },
-            }
+                }
+            })
+            .await
         }
     }
 }
@@ -75,6 +103,7 @@ impl<T> Clone for OpensslConnector<T> {
         OpensslConnector {
             connector: self.connector.clone(),
             openssl: self.openssl.clone(),
+            timeout: self.timeout,
         }
     }
 }
@@ -128,5 +157,47 @@ mod tests {
             .call(Connect::new("").set_addr(Some(server.addr())))
             .await;
         assert!(result.is_err());
+
+        // tls layer over configured tcp connector
+        let ssl = SslConnector::builder(SslMethod::tls()).unwrap();
+        let srv = Connector::default()
+            .timeout(std::time::Duration::from_secs(5))
+            .openssl(ssl.build());
+        let err = srv
+            .call(Connect::new("localhost").set_addr(Some(server.addr())))
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err, ConnectError::Handshake(_)));
+    }
+
+    #[ntex_rt::test]
+    async fn test_openssl_handshake_timeout() {
+        // server never responds to client hello
+        let server = crate::server::test_server(|| {
+            crate::fn_service(|io: TcpStream| async move {
+                crate::rt::time::delay_for(Duration::from_secs(1)).await;
+                drop(io);
+                Ok::<_, ()>(())
+            })
+        });
+
+        let ssl = SslConnector::builder(SslMethod::tls()).unwrap();
+        let srv = OpensslConnector::new(ssl.build())
+            .handshake_timeout(Duration::from_millis(100));
+        let err = srv
+            .call(Connect::new("localhost").set_addr(Some(server.addr())))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(
+            format!("{}", err),
+            "Tls handshake error: Tls handshake timeout"
+        );
+        let source = std::error::Error::source(&err).unwrap();
+        assert_eq!(
+            source.downcast_ref::<std::io::Error>().unwrap().kind(),
+            std::io::ErrorKind::TimedOut
+        );
     }
 }
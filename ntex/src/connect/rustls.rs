@@ -1,6 +1,6 @@
-use std::io;
 use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::Duration;
 
 pub use rust_tls::Session;
 pub use tokio_rustls::{client::TlsStream, rustls::ClientConfig};
@@ -18,6 +18,7 @@ use super::{Address, AsyncResolver, Connect, ConnectError, Connector};
 pub struct RustlsConnector<T> {
     connector: Connector<T>,
     config: Arc<ClientConfig>,
+    timeout: Duration,
 }
 
 impl<T> RustlsConnector<T> {
@@ -25,6 +26,7 @@ impl<T> RustlsConnector<T> {
         RustlsConnector {
             config,
             connector: Connector::default(),
+            timeout: Duration::from_millis(0),
         }
     }
 
@@ -33,8 +35,31 @@ impl<T> RustlsConnector<T> {
         RustlsConnector {
             config,
             connector: Connector::new(resolver),
+            timeout: Duration::from_millis(0),
         }
     }
+
+    /// Construct new connect service with configured tcp connector
+    pub fn with_connector(config: Arc<ClientConfig>, connector: Connector<T>) -> Self {
+        RustlsConnector {
+            config,
+            connector,
+            timeout: Duration::from_millis(0),
+        }
+    }
+
+    /// Set tls handshake timeout
+    ///
+    /// Timeout starts after tcp connection is established and covers
+    /// whole handshake including certificate verification. Connect fails
+    /// with `ConnectError::Handshake` error if handshake is not completed
+    /// in time. To disable timeout set value to 0.
+    ///
+    /// By default handshake timeout is disabled.
+    pub fn handshake_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
 }
 
 impl<T: Address + 'static> RustlsConnector<T> {
@@ -50,24 +75,28 @@ impl<T: Address + 'static> RustlsConnector<T> {
         let host = req.host().to_string();
         let conn = self.connector.call(req);
         let config = self.config.clone();
+        let timeout = self.timeout;
 
         async move {
             let io = conn.await?;
             trace!("SSL Handshake start for: {:?}", host);
 
             let host = DNSNameRef::try_from_ascii_str(&host)
-                .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("{}", e)))?;
-
-            match TlsConnector::from(config).connect(host, io).await {
-                Ok(io) => {
-                    trace!("SSL Handshake success: {:?}", host);
-                    Ok(io)
-                }
-                Err(e) => {
-                    trace!("SSL Handshake error: {:?}", e);
-                    Err(io::Error::new(io::ErrorKind::Other, format!("{}", e)).into())
+                .map_err(|e| ConnectError::Handshake(Box::new(e)))?;
+
+            super::handshake(timeout, async {
+                match TlsConnector::from(config).connect(host, io).await {
+                    Ok(io) => {
+                        trace!("SSL Handshake success: {:?}", host);
+                        Ok(io)
+                    }
+                    Err(e) => {
+                        trace!("SSL Handshake error: {:?}", e);
+                        Err(ConnectError::Handshake(Box::new(e)))
+                    }
                 }
-            }
+            })
+            .await
         }
     }
 }
@@ -77,6 +106,7 @@ impl<T> Clone for RustlsConnector<T> {
         Self {
             config: self.config.clone(),
             connector: self.connector.clone(),
+            timeout: self.timeout,
         }
     }
 }
@@ -133,5 +163,45 @@ mod tests {
             .call(Connect::new("www.rust-lang.org").set_addr(Some(server.addr())))
             .await;
         assert!(result.is_err());
+
+        // tls layer over configured tcp connector
+        let srv = Connector::default()
+            .timeout(std::time::Duration::from_secs(5))
+            .rustls(factory.config.clone());
+        let err = srv
+            .call(Connect::new("www.rust-lang.org").set_addr(Some(server.addr())))
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err, ConnectError::Handshake(_)));
+    }
+
+    #[ntex_rt::test]
+    async fn test_rustls_handshake_timeout() {
+        // server never responds to client hello
+        let server = crate::server::test_server(|| {
+            crate::fn_service(|io: TcpStream| async move {
+                crate::rt::time::delay_for(Duration::from_secs(1)).await;
+                drop(io);
+                Ok::<_, ()>(())
+            })
+        });
+
+        let srv = RustlsConnector::new(Arc::new(ClientConfig::new()))
+            .handshake_timeout(Duration::from_millis(100));
+        let err = srv
+            .call(Connect::new("www.rust-lang.org").set_addr(Some(server.addr())))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(
+            format!("{}", err),
+            "Tls handshake error: Tls handshake timeout"
+        );
+        let source = std::error::Error::source(&err).unwrap();
+        assert_eq!(
+            source.downcast_ref::<std::io::Error>().unwrap().kind(),
+            std::io::ErrorKind::TimedOut
+        );
     }
 }
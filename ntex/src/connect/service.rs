@@ -4,17 +4,39 @@ use std::io;
 use std::net::SocketAddr;
 use std::pin::Pin;
 use std::task::{Context, Poll};
+use std::time::Duration;
 
 use either::Either;
-use futures::future::{ok, FutureExt, LocalBoxFuture, Ready};
+use futures::future::{ok, ready, FutureExt, LocalBoxFuture, Ready};
+use socket2::{Domain, Protocol, Socket, Type};
 
 use crate::rt::net::TcpStream;
+use crate::rt::time::{delay_for, Delay};
 use crate::service::{Service, ServiceFactory};
 
 use super::{Address, AsyncResolver, Connect, ConnectError, Resolver};
 
+/// Tcp connector service factory
+///
+/// Connector resolves address and connects to remote host, tls layer
+/// could be added with `openssl()` or `rustls()` methods.
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+/// use ntex::connect::{Connect, Connector};
+/// use ntex::service::Service;
+///
+/// #[ntex::main]
+/// async fn main() {
+///     let connector = Connector::default().timeout(Duration::from_secs(5));
+///     let _io = connector.call(Connect::new("example.com:80")).await.unwrap();
+/// }
+/// ```
 pub struct Connector<T> {
     resolver: Resolver<T>,
+    bind: Option<SocketAddr>,
+    linger: Option<Duration>,
+    timeout: Option<Duration>,
 }
 
 impl<T> Connector<T> {
@@ -22,8 +44,64 @@ impl<T> Connector<T> {
     pub fn new(resolver: AsyncResolver) -> Self {
         Connector {
             resolver: Resolver::new(resolver),
+            bind: None,
+            linger: None,
+            timeout: None,
         }
     }
+
+    /// Bind socket to a local address before connecting.
+    ///
+    /// Use port 0 to let the OS choose local port. Socket is created
+    /// with `SO_REUSEADDR` option, so local port could be reused.
+    pub fn bind(mut self, addr: SocketAddr) -> Self {
+        self.bind = Some(addr);
+        self
+    }
+
+    /// Set `SO_LINGER` option of connected sockets.
+    ///
+    /// With linger timeout set, closing the socket blocks until pending
+    /// data is sent or timeout expires. Zero timeout resets connection
+    /// on close, `RST` is sent instead of regular `FIN` and socket does not
+    /// enter `TIME_WAIT` state, any unsent data is discarded. Async io
+    /// does not block on close, so only zero timeout is useful.
+    ///
+    /// By default `SO_LINGER` is not set and system defaults are used.
+    pub fn linger(mut self, dur: Duration) -> Self {
+        self.linger = Some(dur);
+        self
+    }
+
+    /// Set tcp connect timeout.
+    ///
+    /// Timeout covers connection attempts to all resolved addresses,
+    /// address resolution is limited by resolver options. If connection
+    /// is not established in time, `ConnectError::Timeout` is returned.
+    ///
+    /// By default connect timeout is not set.
+    pub fn timeout(mut self, dur: Duration) -> Self {
+        self.timeout = Some(dur);
+        self
+    }
+
+    #[cfg(feature = "openssl")]
+    /// Add openssl tls layer, host name of `Connect` message is used for sni
+    pub fn openssl(
+        self,
+        connector: super::openssl::SslConnector,
+    ) -> super::openssl::OpensslConnector<T> {
+        super::openssl::OpensslConnector::with_connector(connector, self)
+    }
+
+    #[cfg(feature = "rustls")]
+    /// Add rustls tls layer, host name of `Connect` message is used for sni
+    pub fn rustls(
+        self,
+        config: std::sync::Arc<super::rustls::ClientConfig>,
+    ) -> super::rustls::RustlsConnector<T> {
+        super::rustls::RustlsConnector::with_connector(config, self)
+    }
 }
 
 impl<T: Address> Connector<T> {
@@ -35,7 +113,12 @@ impl<T: Address> Connector<T> {
     where
         Connect<T>: From<U>,
     {
-        ConnectServiceResponse::new(self.resolver.lookup(message.into()))
+        ConnectServiceResponse::new(
+            self.resolver.lookup(message.into()),
+            self.bind,
+            self.linger,
+            self.timeout,
+        )
     }
 }
 
@@ -43,6 +126,9 @@ impl<T> Default for Connector<T> {
     fn default() -> Self {
         Connector {
             resolver: Resolver::default(),
+            bind: None,
+            linger: None,
+            timeout: None,
         }
     }
 }
@@ -51,6 +137,9 @@ impl<T> Clone for Connector<T> {
     fn clone(&self) -> Self {
         Connector {
             resolver: self.resolver.clone(),
+            bind: self.bind,
+            linger: self.linger,
+            timeout: self.timeout,
         }
     }
 }
@@ -83,24 +172,40 @@ impl<T: Address> Service for Connector<T> {
 
     #[inline]
     fn call(&self, req: Connect<T>) -> Self::Future {
-        ConnectServiceResponse::new(self.resolver.lookup(req))
+        ConnectServiceResponse::new(
+            self.resolver.lookup(req),
+            self.bind,
+            self.linger,
+            self.timeout,
+        )
     }
 }
 
 enum ConnectState<T: Address> {
     Resolve(<Resolver<T> as Service>::Future),
-    Connect(TcpConnectorResponse<T>),
+    Connect(TcpConnectorResponse<T>, Option<Delay>),
 }
 
 #[doc(hidden)]
 pub struct ConnectServiceResponse<T: Address> {
     state: ConnectState<T>,
+    bind: Option<SocketAddr>,
+    linger: Option<Duration>,
+    timeout: Option<Duration>,
 }
 
 impl<T: Address> ConnectServiceResponse<T> {
-    pub(super) fn new(fut: <Resolver<T> as Service>::Future) -> Self {
+    pub(super) fn new(
+        fut: <Resolver<T> as Service>::Future,
+        bind: Option<SocketAddr>,
+        linger: Option<Duration>,
+        timeout: Option<Duration>,
+    ) -> Self {
         ConnectServiceResponse {
             state: ConnectState::Resolve(fut),
+            bind,
+            linger,
+            timeout,
         }
     }
 }
@@ -116,17 +221,30 @@ impl<T: Address> Future for ConnectServiceResponse<T> {
                     let port = address.port();
                     let Connect { req, addr, .. } = address;
 
+                    let delay = self.timeout.map(delay_for);
                     if let Some(addr) = addr {
-                        self.state = ConnectState::Connect(TcpConnectorResponse::new(
-                            req, port, addr,
-                        ));
+                        self.state = ConnectState::Connect(
+                            TcpConnectorResponse::new(
+                                req,
+                                port,
+                                addr,
+                                self.bind,
+                                self.linger,
+                            ),
+                            delay,
+                        );
                         self.poll(cx)
                     } else if let Some(addr) = req.addr() {
-                        self.state = ConnectState::Connect(TcpConnectorResponse::new(
-                            req,
-                            addr.port(),
-                            Either::Left(addr),
-                        ));
+                        self.state = ConnectState::Connect(
+                            TcpConnectorResponse::new(
+                                req,
+                                addr.port(),
+                                Either::Left(addr),
+                                self.bind,
+                                self.linger,
+                            ),
+                            delay,
+                        );
                         self.poll(cx)
                     } else {
                         error!("TCP connector: got unresolved address");
@@ -134,7 +252,18 @@ impl<T: Address> Future for ConnectServiceResponse<T> {
                     }
                 }
             },
-            ConnectState::Connect(ref mut fut) => Pin::new(fut).poll(cx),
+            ConnectState::Connect(ref mut fut, ref mut delay) => {
+                if let Poll::Ready(res) = Pin::new(fut).poll(cx) {
+                    return Poll::Ready(res);
+                }
+                if let Some(delay) = delay {
+                    if Pin::new(delay).poll(cx).is_ready() {
+                        trace!("TCP connector - connect timeout");
+                        return Poll::Ready(Err(ConnectError::Timeout));
+                    }
+                }
+                Poll::Pending
+            }
         }
     }
 }
@@ -144,7 +273,9 @@ struct TcpConnectorResponse<T> {
     req: Option<T>,
     port: u16,
     addrs: Option<VecDeque<SocketAddr>>,
-    stream: Option<LocalBoxFuture<'static, Result<TcpStream, io::Error>>>,
+    bind: Option<SocketAddr>,
+    linger: Option<Duration>,
+    stream: Option<LocalBoxFuture<'static, Result<TcpStream, ConnectError>>>,
 }
 
 impl<T: Address> TcpConnectorResponse<T> {
@@ -152,6 +283,8 @@ impl<T: Address> TcpConnectorResponse<T> {
         req: T,
         port: u16,
         addr: Either<SocketAddr, VecDeque<SocketAddr>>,
+        bind: Option<SocketAddr>,
+        linger: Option<Duration>,
     ) -> TcpConnectorResponse<T> {
         trace!(
             "TCP connector - connecting to {:?} port:{}",
@@ -164,12 +297,16 @@ impl<T: Address> TcpConnectorResponse<T> {
                 req: Some(req),
                 port,
                 addrs: None,
-                stream: Some(TcpStream::connect(addr).boxed_local()),
+                bind,
+                linger,
+                stream: Some(tcp_connect(addr, bind, linger)),
             },
             Either::Right(addrs) => TcpConnectorResponse {
                 req: Some(req),
                 port,
                 addrs: Some(addrs),
+                bind,
+                linger,
                 stream: None,
             },
         }
@@ -203,8 +340,9 @@ impl<T: Address> Future for TcpConnectorResponse<T> {
                         );
                         if this.addrs.is_none()
                             || this.addrs.as_ref().unwrap().is_empty()
+                            || matches!(err, ConnectError::Bind(..))
                         {
-                            return Poll::Ready(Err(err.into()));
+                            return Poll::Ready(Err(err));
                         }
                     }
                 }
@@ -212,11 +350,53 @@ impl<T: Address> Future for TcpConnectorResponse<T> {
 
             // try to connect
             let addr = this.addrs.as_mut().unwrap().pop_front().unwrap();
-            this.stream = Some(TcpStream::connect(addr).boxed());
+            this.stream = Some(tcp_connect(addr, this.bind, this.linger));
         }
     }
 }
 
+fn tcp_connect(
+    addr: SocketAddr,
+    bind: Option<SocketAddr>,
+    linger: Option<Duration>,
+) -> LocalBoxFuture<'static, Result<TcpStream, ConnectError>> {
+    let fut = if let Some(local) = bind {
+        match bind_socket(&addr, &local) {
+            Ok(sock) => {
+                async move { TcpStream::connect_std(sock, &addr).await }.boxed_local()
+            }
+            Err(err) => return ready(Err(ConnectError::Bind(local, err))).boxed_local(),
+        }
+    } else {
+        TcpStream::connect(addr).boxed_local()
+    };
+
+    async move {
+        let sock = fut.await?;
+        if linger.is_some() {
+            sock.set_linger(linger)?;
+        }
+        Ok(sock)
+    }
+    .boxed_local()
+}
+
+/// Create socket bound to local address
+fn bind_socket(
+    addr: &SocketAddr,
+    local: &SocketAddr,
+) -> io::Result<std::net::TcpStream> {
+    let domain = if addr.is_ipv4() {
+        Domain::ipv4()
+    } else {
+        Domain::ipv6()
+    };
+    let sock = Socket::new(domain, Type::stream(), Some(Protocol::tcp()))?;
+    sock.set_reuse_address(true)?;
+    sock.bind(&(*local).into())?;
+    Ok(sock.into_tcp_stream())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -250,4 +430,64 @@ mod tests {
         let result = crate::connect::connect(msg).await;
         assert!(result.is_ok());
     }
+
+    #[ntex_rt::test]
+    async fn test_connect_bind() {
+        let server = crate::server::test_server(|| {
+            crate::fn_service(|_| async { Ok::<_, ()>(()) })
+        });
+
+        let local: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        let srv = Connector::default().bind(local);
+        let sock = srv.connect(server.addr()).await.unwrap();
+        assert_eq!(sock.local_addr().unwrap().ip(), local.ip());
+
+        // address is not local
+        let local: SocketAddr = "192.0.2.1:0".parse().unwrap();
+        let srv = Connector::default().bind(local);
+        let err = srv.connect(server.addr()).await.err().unwrap();
+        assert!(matches!(err, ConnectError::Bind(addr, _) if addr == local));
+        assert!(format!("{}", err).contains("192.0.2.1"));
+    }
+
+    #[ntex_rt::test]
+    async fn test_connect_linger() {
+        let server = crate::server::test_server(|| {
+            crate::fn_service(|_| async { Ok::<_, ()>(()) })
+        });
+
+        let srv = Connector::default().linger(Duration::from_secs(0));
+        let sock = srv.connect(server.addr()).await.unwrap();
+        assert_eq!(sock.linger().unwrap(), Some(Duration::from_secs(0)));
+
+        let sock = Connector::default().connect(server.addr()).await.unwrap();
+        assert_eq!(sock.linger().unwrap(), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[ntex_rt::test]
+    async fn test_connect_timeout() {
+        // listener does not accept connections, syn is dropped
+        // once accept queue is full
+        let sock =
+            Socket::new(Domain::ipv4(), Type::stream(), Some(Protocol::tcp())).unwrap();
+        let local: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        sock.bind(&local.into()).unwrap();
+        sock.listen(0).unwrap();
+        let lst = sock.into_tcp_listener();
+        let addr = lst.local_addr().unwrap();
+
+        let srv = Connector::default().timeout(Duration::from_millis(100));
+        let mut conns = Vec::new();
+        for _ in 0..16 {
+            match srv.connect(addr).await {
+                Ok(sock) => conns.push(sock),
+                Err(err) => {
+                    assert!(matches!(err, ConnectError::Timeout));
+                    return;
+                }
+            }
+        }
+        panic!("accept queue is not saturated");
+    }
 }
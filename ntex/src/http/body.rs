@@ -7,6 +7,10 @@ use std::{fmt, mem};
 use bytes::{Bytes, BytesMut};
 use futures::{ready, Stream};
 
+use crate::http::header::{HeaderMap, CONTENT_LENGTH};
+use crate::http::message::ResponseHead;
+use crate::http::payload::Payload;
+
 #[derive(Debug, PartialEq, Copy, Clone)]
 /// Body size hint
 pub enum BodySize {
@@ -30,6 +34,14 @@ pub trait MessageBody {
         &mut self,
         cx: &mut Context<'_>,
     ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>>;
+
+    /// Trailer headers, available after the last chunk is polled.
+    ///
+    /// Trailers get sent only for chunked http/1 responses to peers
+    /// that requested them with `TE: trailers`, and for http/2 responses.
+    fn trailers(&mut self) -> Option<HeaderMap> {
+        None
+    }
 }
 
 impl MessageBody for () {
@@ -56,6 +68,10 @@ impl<T: MessageBody> MessageBody for Box<T> {
     ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
         self.as_mut().poll_next_chunk(cx)
     }
+
+    fn trailers(&mut self) -> Option<HeaderMap> {
+        self.as_mut().trailers()
+    }
 }
 
 pub enum ResponseBody<B> {
@@ -115,6 +131,13 @@ impl<B: MessageBody> MessageBody for ResponseBody<B> {
             ResponseBody::Other(ref mut body) => body.poll_next_chunk(cx),
         }
     }
+
+    fn trailers(&mut self) -> Option<HeaderMap> {
+        match self {
+            ResponseBody::Body(ref mut body) => body.trailers(),
+            ResponseBody::Other(ref mut body) => body.trailers(),
+        }
+    }
 }
 
 impl<B: MessageBody + Unpin> Stream for ResponseBody<B> {
@@ -131,6 +154,110 @@ impl<B: MessageBody + Unpin> Stream for ResponseBody<B> {
     }
 }
 
+/// Response body transform
+///
+/// Transform is applied by h1 and h2 dispatchers to response body chunks
+/// right before they are written to the connection. Transformed response
+/// does not have `Content-Length` header, body is sent with chunked
+/// transfer encoding. Transform is not applied to responses without body.
+pub trait BodyTransform {
+    /// Called once, before response head is sent
+    fn start(&mut self, head: &ResponseHead);
+
+    /// Transform body chunk, empty result is not sent
+    fn chunk(&mut self, chunk: Bytes) -> Bytes;
+
+    /// Called at the end of response body, returned bytes are sent as
+    /// last chunk
+    fn finish(&mut self) -> Option<Bytes>;
+}
+
+pub(crate) struct BoxedTransform(pub(crate) Box<dyn BodyTransform>);
+
+/// Response body with optional transform
+pub(crate) struct TransformBody<B> {
+    body: B,
+    transform: Option<Box<dyn BodyTransform>>,
+    eof: bool,
+}
+
+impl<B: MessageBody> TransformBody<B> {
+    /// Take transform from response extensions and prepare response head
+    pub(crate) fn new(body: B, head: &mut ResponseHead) -> Self {
+        let transform = head
+            .extensions
+            .get_mut()
+            .remove::<BoxedTransform>()
+            .and_then(|t| {
+                if body.size() == BodySize::None {
+                    None
+                } else {
+                    Some(t.0)
+                }
+            });
+        let transform = transform.map(|mut t| {
+            head.headers.remove(CONTENT_LENGTH);
+            t.start(head);
+            t
+        });
+
+        TransformBody {
+            body,
+            transform,
+            eof: false,
+        }
+    }
+}
+
+impl<B: MessageBody> MessageBody for TransformBody<B> {
+    fn size(&self) -> BodySize {
+        if self.transform.is_some() {
+            BodySize::Stream
+        } else {
+            self.body.size()
+        }
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        loop {
+            if self.eof {
+                return Poll::Ready(None);
+            }
+
+            let transform = if let Some(ref mut transform) = self.transform {
+                transform
+            } else {
+                return self.body.poll_next_chunk(cx);
+            };
+
+            return match ready!(self.body.poll_next_chunk(cx)) {
+                Some(Ok(chunk)) => {
+                    let chunk = transform.chunk(chunk);
+                    if chunk.is_empty() {
+                        continue;
+                    }
+                    Poll::Ready(Some(Ok(chunk)))
+                }
+                Some(Err(e)) => Poll::Ready(Some(Err(e))),
+                None => {
+                    self.eof = true;
+                    match transform.finish() {
+                        Some(chunk) if !chunk.is_empty() => Poll::Ready(Some(Ok(chunk))),
+                        _ => Poll::Ready(None),
+                    }
+                }
+            };
+        }
+    }
+
+    fn trailers(&mut self) -> Option<HeaderMap> {
+        self.body.trailers()
+    }
+}
+
 /// Represents various types of http message body.
 pub enum Body {
     /// Empty response. `Content-Length` header is not set.
@@ -149,10 +276,89 @@ impl Body {
         Body::Bytes(Bytes::copy_from_slice(s))
     }
 
+    /// Create body from static slice (no copy)
+    ///
+    /// Useful for embedded static content, for example `include_bytes!()`.
+    /// `Content-Length` is set to the slice length.
+    pub fn from_static(s: &'static [u8]) -> Body {
+        Body::Bytes(Bytes::from_static(s))
+    }
+
     /// Create body from generic message body.
     pub fn from_message<B: MessageBody + 'static>(body: B) -> Body {
         Body::Message(Box::new(body))
     }
+
+    /// Create body from request or response payload.
+    ///
+    /// Payload is streamed as is, `size` is used for body framing,
+    /// for example size from original `Content-Length` header.
+    pub fn from_payload(payload: Payload, size: BodySize) -> Body {
+        Body::Message(Box::new(PayloadBody { payload, size }))
+    }
+
+    /// Create body from list of chunks.
+    ///
+    /// Body size is the sum of chunk sizes, chunks are sent sequentially
+    /// without concatenating them into one buffer.
+    pub fn from_chunks<I>(chunks: I) -> Body
+    where
+        I: IntoIterator<Item = Bytes>,
+    {
+        let chunks: Vec<Bytes> = chunks.into_iter().filter(|b| !b.is_empty()).collect();
+        let size = chunks.iter().map(|b| b.len() as u64).sum();
+        Body::Message(Box::new(ChunksBody {
+            size,
+            chunks: chunks.into_iter(),
+        }))
+    }
+}
+
+/// Payload stream adapter for `Body::from_payload()`
+struct PayloadBody {
+    payload: Payload,
+    size: BodySize,
+}
+
+impl MessageBody for PayloadBody {
+    fn size(&self) -> BodySize {
+        self.size
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        loop {
+            return Poll::Ready(
+                match ready!(Pin::new(&mut self.payload).poll_next(cx)) {
+                    Some(Ok(ref bytes)) if bytes.is_empty() => continue,
+                    Some(Ok(bytes)) => Some(Ok(bytes)),
+                    Some(Err(err)) => Some(Err(Box::new(err))),
+                    None => None,
+                },
+            );
+        }
+    }
+}
+
+/// Chunks adapter for `Body::from_chunks()`
+struct ChunksBody {
+    size: u64,
+    chunks: std::vec::IntoIter<Bytes>,
+}
+
+impl MessageBody for ChunksBody {
+    fn size(&self) -> BodySize {
+        BodySize::Sized(self.size)
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        _: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        Poll::Ready(self.chunks.next().map(Ok))
+    }
 }
 
 impl MessageBody for Body {
@@ -183,6 +389,13 @@ impl MessageBody for Body {
             Body::Message(ref mut body) => body.poll_next_chunk(cx),
         }
     }
+
+    fn trailers(&mut self) -> Option<HeaderMap> {
+        match self {
+            Body::Message(ref mut body) => body.trailers(),
+            _ => None,
+        }
+    }
 }
 
 impl PartialEq for Body {
@@ -249,6 +462,12 @@ impl From<BytesMut> for Body {
     }
 }
 
+impl From<Vec<Bytes>> for Body {
+    fn from(chunks: Vec<Bytes>) -> Body {
+        Body::from_chunks(chunks)
+    }
+}
+
 impl From<serde_json::Value> for Body {
     fn from(v: serde_json::Value) -> Body {
         Body::Bytes(v.to_string().into())
@@ -538,6 +757,58 @@ mod tests {
         }
     }
 
+    struct Upper(usize);
+
+    impl BodyTransform for Upper {
+        fn start(&mut self, head: &ResponseHead) {
+            assert!(!head.headers.contains_key(CONTENT_LENGTH));
+        }
+
+        fn chunk(&mut self, chunk: Bytes) -> Bytes {
+            self.0 += chunk.len();
+            Bytes::from(chunk.to_ascii_uppercase())
+        }
+
+        fn finish(&mut self) -> Option<Bytes> {
+            Some(Bytes::from(format!("|{}", self.0)))
+        }
+    }
+
+    #[ntex_rt::test]
+    async fn test_transform_body() {
+        let mut head = ResponseHead::new(crate::http::StatusCode::OK);
+        head.headers.insert(
+            CONTENT_LENGTH,
+            crate::http::header::HeaderValue::from_static("4"),
+        );
+        let body = TransformBody::new(Body::from("test"), &mut head);
+        assert_eq!(body.size(), BodySize::Sized(4));
+        assert!(head.headers.contains_key(CONTENT_LENGTH));
+
+        head.extensions
+            .get_mut()
+            .insert(BoxedTransform(Box::new(Upper(0))));
+        let mut body = TransformBody::new(
+            Body::from_chunks(vec!["ab".into(), "cd".into()]),
+            &mut head,
+        );
+        assert_eq!(body.size(), BodySize::Stream);
+        assert!(!head.headers.contains_key(CONTENT_LENGTH));
+
+        let mut data = BytesMut::new();
+        while let Some(chunk) = poll_fn(|cx| body.poll_next_chunk(cx)).await {
+            data.extend_from_slice(&chunk.unwrap());
+        }
+        assert_eq!(data.freeze(), Bytes::from_static(b"ABCD|4"));
+
+        // response without body
+        head.extensions
+            .get_mut()
+            .insert(BoxedTransform(Box::new(Upper(0))));
+        let body = TransformBody::new(Body::None, &mut head);
+        assert_eq!(body.size(), BodySize::None);
+    }
+
     #[ntex_rt::test]
     async fn test_static_str() {
         assert_eq!(Body::from("").size(), BodySize::Sized(0));
@@ -565,6 +836,9 @@ mod tests {
             BodySize::Sized(4)
         );
         assert_eq!(Body::from_slice(b"test".as_ref()).get_ref(), b"test");
+        assert_eq!(Body::from_static(b"test").size(), BodySize::Sized(4));
+        assert_eq!(Body::from_static(b"test").get_ref(), b"test");
+        assert_eq!(Body::from_static(b"").size(), BodySize::Sized(0));
 
         assert_eq!((&b"test"[..]).size(), BodySize::Sized(4));
         assert_eq!(
@@ -737,4 +1011,52 @@ mod tests {
             Some(Bytes::from("2")),
         );
     }
+
+    #[ntex_rt::test]
+    async fn body_from_chunks() {
+        let mut body: Body =
+            vec![Bytes::from("12"), Bytes::new(), Bytes::from("345")].into();
+        assert_eq!(body.size(), BodySize::Sized(5));
+        assert_eq!(
+            poll_fn(|cx| body.poll_next_chunk(cx)).await.unwrap().ok(),
+            Some(Bytes::from("12")),
+        );
+        assert_eq!(
+            poll_fn(|cx| body.poll_next_chunk(cx)).await.unwrap().ok(),
+            Some(Bytes::from("345")),
+        );
+        assert!(poll_fn(|cx| body.poll_next_chunk(cx)).await.is_none());
+
+        let body = Body::from_chunks(Vec::new());
+        assert_eq!(body.size(), BodySize::Sized(0));
+    }
+
+    #[ntex_rt::test]
+    async fn body_from_payload() {
+        let (mut sender, payload) = crate::http::h1::Payload::create(false);
+        sender.feed_data(Bytes::from("1"));
+        sender.feed_data(Bytes::new());
+        sender.feed_data(Bytes::from("2"));
+        sender.feed_eof();
+
+        let mut body = Body::from_payload(payload.into(), BodySize::Sized(2));
+        assert_eq!(body.size(), BodySize::Sized(2));
+        assert_eq!(
+            poll_fn(|cx| body.poll_next_chunk(cx)).await.unwrap().ok(),
+            Some(Bytes::from("1")),
+        );
+        assert_eq!(
+            poll_fn(|cx| body.poll_next_chunk(cx)).await.unwrap().ok(),
+            Some(Bytes::from("2")),
+        );
+        assert!(poll_fn(|cx| body.poll_next_chunk(cx)).await.is_none());
+
+        let (mut sender, payload) = crate::http::h1::Payload::create(false);
+        sender.set_error(crate::http::error::PayloadError::Incomplete(None));
+        let mut body = Body::from_payload(payload.into(), BodySize::Stream);
+        assert!(poll_fn(|cx| body.poll_next_chunk(cx))
+            .await
+            .unwrap()
+            .is_err());
+    }
 }
@@ -1,17 +1,28 @@
+use std::convert::TryFrom;
 use std::fmt;
 use std::marker::PhantomData;
 use std::rc::Rc;
+use std::time::Duration;
 
 use crate::codec::Framed;
 use crate::http::body::MessageBody;
-use crate::http::config::{KeepAlive, ServiceConfig};
-use crate::http::error::ResponseError;
+use crate::http::config::{
+    AllowedHosts, DispatchErrorHook, EmptyHeaderValue, ErrorFormat, ErrorFormatter,
+    ErrorHandler, ExpectContinue, HeaderValidation, Http10Body, Inner, KeepAlive,
+    PipelineOverflow, ServiceConfig, TcpKeepalive, TransferCodings, UnknownExpectation,
+    UriRewrite, WireCapture, WireDirection,
+};
+use crate::http::error::{ConfigError, ConfigIssue, DispatchError, ResponseError};
 use crate::http::h1::{Codec, ExpectHandler, H1Service, UpgradeHandler};
 use crate::http::h2::H2Service;
+use crate::http::header::HeaderValue;
 use crate::http::helpers::{Data, DataFactory};
 use crate::http::request::Request;
 use crate::http::response::Response;
 use crate::http::service::HttpService;
+use crate::http::stats::ServiceStats;
+use crate::http::validate::validate;
+use crate::http::{StatusCode, Uri};
 use crate::service::{IntoServiceFactory, Service, ServiceFactory};
 
 /// A http service builder
@@ -26,6 +37,37 @@ pub struct HttpServiceBuilder<T, S, X = ExpectHandler, U = UpgradeHandler<T>> {
     expect: X,
     upgrade: Option<U>,
     on_connect: Option<Rc<dyn Fn(&T) -> Box<dyn DataFactory>>>,
+    wire_capture: Option<WireCapture>,
+    max_requests: usize,
+    max_pipelined: usize,
+    pipeline_overflow: PipelineOverflow,
+    http10_body: Http10Body,
+    expect_continue: ExpectContinue,
+    unknown_expectation: UnknownExpectation,
+    transfer_codings: TransferCodings,
+    max_decompressed_size: usize,
+    allowed_hosts: Option<Rc<AllowedHosts>>,
+    error_handler: Option<ErrorHandler>,
+    catch_panic: bool,
+    first_byte_timeout: u64,
+    payload_timeout: u64,
+    max_header_size: usize,
+    max_line_size: usize,
+    h2_send_buffer: usize,
+    verify_digest: bool,
+    connection_handle: bool,
+    error_formatter: Option<ErrorFormatter>,
+    request_timing: bool,
+    server_timing: bool,
+    empty_header_value: EmptyHeaderValue,
+    header_validation: HeaderValidation,
+    rate_window: Duration,
+    alt_svc: Option<HeaderValue>,
+    invalid_alt_svc: bool,
+    rewrite_uri: Option<UriRewrite>,
+    on_dispatch_error: Option<DispatchErrorHook>,
+    tcp_keepalive: Option<TcpKeepalive>,
+    stats: ServiceStats,
     _t: PhantomData<(T, S)>,
 }
 
@@ -40,9 +82,83 @@ impl<T, S> HttpServiceBuilder<T, S, ExpectHandler, UpgradeHandler<T>> {
             expect: ExpectHandler,
             upgrade: None,
             on_connect: None,
+            wire_capture: None,
+            max_requests: 0,
+            max_pipelined: 0,
+            pipeline_overflow: PipelineOverflow::Backpressure(5000),
+            http10_body: Http10Body::Close,
+            expect_continue: ExpectContinue::Continue(u64::MAX),
+            unknown_expectation: UnknownExpectation::Reject,
+            transfer_codings: TransferCodings::Reject,
+            max_decompressed_size: 262_144,
+            allowed_hosts: None,
+            error_handler: None,
+            catch_panic: true,
+            first_byte_timeout: 0,
+            payload_timeout: 0,
+            max_header_size: 32_768,
+            max_line_size: 8_192,
+            h2_send_buffer: 16_384,
+            verify_digest: false,
+            connection_handle: false,
+            error_formatter: None,
+            request_timing: false,
+            server_timing: false,
+            empty_header_value: EmptyHeaderValue::Accept,
+            header_validation: HeaderValidation::Strict,
+            rate_window: Duration::from_secs(0),
+            alt_svc: None,
+            invalid_alt_svc: false,
+            rewrite_uri: None,
+            on_dispatch_error: None,
+            tcp_keepalive: None,
+            stats: ServiceStats::new(),
             _t: PhantomData,
         }
     }
+
+    /// Create builder with settings of existing `ServiceConfig`.
+    ///
+    /// Hooks and callbacks of the config are shared with the builder.
+    pub fn from_config(cfg: ServiceConfig) -> Self {
+        let inner = &cfg.0;
+        let mut builder = Self::new();
+        builder.keep_alive = cfg.keep_alive();
+        builder.tcp_keepalive = inner.tcp_keepalive;
+        builder.client_timeout = inner.client_timeout;
+        builder.client_disconnect = inner.client_disconnect;
+        builder.handshake_timeout = inner.ssl_handshake_timeout;
+        builder.wire_capture = inner.wire_capture.clone();
+        builder.max_requests = inner.max_requests;
+        builder.max_pipelined = inner.max_pipelined;
+        builder.pipeline_overflow = inner.pipeline_overflow;
+        builder.http10_body = inner.http10_body;
+        builder.expect_continue = inner.expect_continue;
+        builder.unknown_expectation = inner.unknown_expectation;
+        builder.transfer_codings = inner.transfer_codings;
+        builder.max_decompressed_size = inner.max_decompressed_size;
+        builder.allowed_hosts = inner.allowed_hosts.clone();
+        builder.stats = inner.stats.clone();
+        builder.error_handler = inner.error_handler.clone();
+        builder.catch_panic = inner.catch_panic;
+        builder.first_byte_timeout = inner.first_byte_timeout;
+        builder.payload_timeout = inner.payload_timeout;
+        builder.max_header_size = inner.max_header_size;
+        builder.max_line_size = inner.max_line_size;
+        builder.h2_send_buffer = inner.h2_send_buffer;
+        builder.verify_digest = inner.verify_digest;
+        builder.connection_handle = inner.connection_handle;
+        builder.error_formatter = inner.error_formatter.clone();
+        builder.request_timing = inner.request_timing;
+        builder.server_timing = inner.server_timing;
+        builder.empty_header_value = inner.empty_header_value;
+        builder.header_validation = inner.header_validation;
+        builder.rate_window = inner.rate_window;
+        builder.alt_svc = inner.alt_svc.clone();
+        builder.rewrite_uri = inner.rewrite_uri.clone();
+        builder.on_dispatch_error = inner.on_dispatch_error.clone();
+        builder
+    }
 }
 
 impl<T, S, X, U> HttpServiceBuilder<T, S, X, U>
@@ -62,12 +178,27 @@ where
 {
     /// Set server keep-alive setting.
     ///
+    /// To disable keep-alive set value to 0.
+    ///
     /// By default keep alive is set to a 5 seconds.
     pub fn keep_alive<W: Into<KeepAlive>>(mut self, val: W) -> Self {
         self.keep_alive = val.into();
         self
     }
 
+    /// Set tcp keep-alive for accepted connections.
+    ///
+    /// Enables `SO_KEEPALIVE` on accepted tcp connections. Accepts idle time
+    /// before first probe or `TcpKeepalive` with complete probe settings.
+    /// Unlike `KeepAlive::Tcp`, http keep-alive timer is not affected, idle
+    /// connections are still closed after keep-alive timeout.
+    ///
+    /// By default tcp keep-alive is configured by `keep_alive()` setting.
+    pub fn tcp_keepalive<K: Into<TcpKeepalive>>(mut self, val: K) -> Self {
+        self.tcp_keepalive = Some(val.into());
+        self
+    }
+
     /// Set server client timeout in milliseconds for first request.
     ///
     /// Defines a timeout for reading client request header. If a client does not transmit
@@ -98,7 +229,8 @@ where
     /// Set server ssl handshake timeout in milliseconds.
     ///
     /// Defines a timeout for connection ssl handshake negotiation.
-    /// To disable timeout set value to 0.
+    /// Timeout covers whole handshake, including client certificate
+    /// verification callbacks. To disable timeout set value to 0.
     ///
     /// By default handshake timeout is set to 5 seconds.
     pub fn ssl_handshake_timeout(mut self, val: u64) -> Self {
@@ -106,6 +238,218 @@ where
         self
     }
 
+    /// Set server time to first byte timeout in milliseconds.
+    ///
+    /// Defines maximum time between fully received request and response
+    /// head returned by the service. If service does not respond within
+    /// this time, for example request waits behind concurrency limit,
+    /// service future is dropped and 503 (Service Unavailable) response
+    /// is sent. For http/1 timer starts when request payload is received,
+    /// for http/2 timer starts when request head is received.
+    ///
+    /// To disable timeout set value to 0.
+    ///
+    /// By default time to first byte timeout is disabled.
+    pub fn first_byte_timeout(mut self, val: u64) -> Self {
+        self.first_byte_timeout = val;
+        self
+    }
+
+    /// Set server request payload timeout in milliseconds.
+    ///
+    /// Defines maximum time between received request head and fully
+    /// received request payload. Timer is not reset by received chunks,
+    /// so slow clients could not hold request open by trickling payload.
+    /// On expiration request payload stream returns
+    /// `PayloadError::Timeout` error, default error renderer responds
+    /// with 408 (Request Timeout). For http/1 connection is closed after
+    /// response, for http/2 only request stream is affected.
+    ///
+    /// To disable timeout set value to 0.
+    ///
+    /// By default payload timeout is disabled.
+    pub fn payload_timeout(mut self, val: u64) -> Self {
+        self.payload_timeout = val;
+        self
+    }
+
+    /// Set max size of http/1 request head in bytes.
+    ///
+    /// Request line and headers must fit into this size. Read buffer is
+    /// not grown beyond this size while request head is not complete,
+    /// larger request heads get 431 (Request Header Fields Too Large)
+    /// response and connection is closed.
+    ///
+    /// By default max header size is set to 32Kb.
+    pub fn max_header_size(mut self, val: usize) -> Self {
+        self.max_header_size = val;
+        self
+    }
+
+    /// Set max size of http/1 request line in bytes.
+    ///
+    /// Request line (method, uri and version) is checked as soon as it
+    /// gets read, before the rest of request head arrives. Requests with
+    /// longer lines get 414 (URI Too Long) response and connection is
+    /// closed. There is no separate uri length limit, uri is limited by
+    /// this setting. Request line is part of request head, so this limit
+    /// has no effect if it exceeds max header size.
+    ///
+    /// By default max request line size is set to 8Kb.
+    pub fn max_request_line_size(mut self, val: usize) -> Self {
+        self.max_line_size = val;
+        self
+    }
+
+    /// Set maximum size of http/2 per-stream send buffer.
+    ///
+    /// Limits amount of response body data queued for sending on each
+    /// http/2 stream. Dispatcher does not poll response body for the next
+    /// chunk until current chunk is queued, so slow clients apply
+    /// backpressure to the response body instead of growing the buffer.
+    ///
+    /// Data is still sent only within peer's flow-control window. Buffer
+    /// larger than the window does not increase memory use, buffer smaller
+    /// than the window does not limit throughput but response task gets
+    /// woken up more often.
+    ///
+    /// By default send buffer is set to 16Kb.
+    pub fn h2_send_buffer_size(mut self, val: usize) -> Self {
+        self.h2_send_buffer = val;
+        self
+    }
+
+    /// Set maximum number of requests served per connection.
+    ///
+    /// Last allowed response on http/1 connection is sent with
+    /// `Connection: close` header, pipelined requests after it are
+    /// not processed. http/2 connection gets gracefully closed with
+    /// `GOAWAY` frame.
+    ///
+    /// By default number of requests is unlimited, set value to 0
+    /// to disable the limit.
+    pub fn max_requests_per_connection(mut self, val: usize) -> Self {
+        self.max_requests = val;
+        self
+    }
+
+    /// Set maximum number of pipelined http/1 requests.
+    ///
+    /// Request is pipelined if it is received before responses to previous
+    /// requests are flushed to the peer. Requests above the limit are
+    /// handled according to `pipeline_overflow` setting.
+    ///
+    /// By default number of pipelined requests is unlimited, set value
+    /// to 0 to disable the limit.
+    pub fn max_pipelined_requests(mut self, val: usize) -> Self {
+        self.max_pipelined = val;
+        self
+    }
+
+    /// Set handling of http/1 requests above pipelined requests limit.
+    ///
+    /// By default connection stops reading and processing requests until
+    /// responses are flushed, connection is closed if peer does not read
+    /// responses within 5 seconds.
+    pub fn pipeline_overflow(mut self, val: PipelineOverflow) -> Self {
+        self.pipeline_overflow = val;
+        self
+    }
+
+    /// Set framing of streaming response bodies for http/1.0 clients.
+    ///
+    /// Http/1.0 clients do not understand chunked encoding. By default
+    /// body is written as is and connection get closed after response.
+    pub fn http10_body(mut self, val: Http10Body) -> Self {
+        self.http10_body = val;
+        self
+    }
+
+    /// Set handling of empty http/1 request header values.
+    ///
+    /// Trailing whitespace of header values is always trimmed.
+    ///
+    /// By default headers with empty values are accepted.
+    pub fn empty_header_value(mut self, val: EmptyHeaderValue) -> Self {
+        self.empty_header_value = val;
+        self
+    }
+
+    /// Set validation of critical http/1 request headers.
+    ///
+    /// Strict validation rejects requests with duplicate `Host` headers or
+    /// `Host` header that does not match absolute-form request target.
+    ///
+    /// By default strict validation is used.
+    pub fn header_validation(mut self, val: HeaderValidation) -> Self {
+        self.header_validation = val;
+        self
+    }
+
+    /// Set handling of `Expect: 100-continue` requests.
+    ///
+    /// By default, `100 Continue` is sent for every request that passes
+    /// expect service. Use `ExpectContinue::Continue(limit)` to reject
+    /// requests with larger `Content-Length` with `417 Expectation Failed`.
+    pub fn expect_continue(mut self, val: ExpectContinue) -> Self {
+        self.expect_continue = val;
+        self
+    }
+
+    /// Set handling of requests with unknown expectation.
+    ///
+    /// Requests with `Expect` header other than `100-continue` are
+    /// rejected with `417 Expectation Failed` by default. Use
+    /// `UnknownExpectation::Ignore` for lenient handling.
+    pub fn unknown_expectation(mut self, val: UnknownExpectation) -> Self {
+        self.unknown_expectation = val;
+        self
+    }
+
+    /// Set handling of requests with transfer codings other than `chunked`.
+    ///
+    /// Requests with `Transfer-Encoding: gzip, chunked` and similar are
+    /// rejected with `501 Not Implemented` by default. Use
+    /// `TransferCodings::Decompress` to pass decompressed payload to
+    /// the service.
+    pub fn transfer_codings(mut self, val: TransferCodings) -> Self {
+        self.transfer_codings = val;
+        self
+    }
+
+    /// Set max size of decompressed request payload.
+    ///
+    /// Applies to payloads decompressed with `TransferCodings::Decompress`.
+    /// Reading payload fails with `PayloadError::Overflow` once decompressed
+    /// data exceeds the limit. By default limit is 256Kb.
+    pub fn max_decompressed_size(mut self, size: usize) -> Self {
+        self.max_decompressed_size = size;
+        self
+    }
+
+    /// Set allowed request hosts.
+    ///
+    /// Host is taken from `Host` header or from absolute-form request target,
+    /// port is ignored. `*.example.com` pattern matches any subdomain of
+    /// `example.com`, `*` matches any host. Requests for other hosts are
+    /// rejected with `421 Misdirected Request`, http/1.1 and http/2 requests
+    /// without host are rejected with `400 Bad Request`.
+    ///
+    /// By default all hosts are allowed.
+    pub fn allowed_hosts(mut self, hosts: Vec<String>) -> Self {
+        self.allowed_hosts = Some(Rc::new(AllowedHosts::new(hosts)));
+        self
+    }
+
+    /// Set live counters handle of the service.
+    ///
+    /// Same handle could be shared between services of all server workers.
+    /// By default every service gets new handle.
+    pub fn stats(mut self, stats: ServiceStats) -> Self {
+        self.stats = stats;
+        self
+    }
+
     /// Provide service for `EXPECT: 100-Continue` support.
     ///
     /// Service get called with request that contains `EXPECT` header.
@@ -127,6 +471,37 @@ where
             expect: expect.into_factory(),
             upgrade: self.upgrade,
             on_connect: self.on_connect,
+            wire_capture: self.wire_capture,
+            max_requests: self.max_requests,
+            max_pipelined: self.max_pipelined,
+            pipeline_overflow: self.pipeline_overflow,
+            http10_body: self.http10_body,
+            expect_continue: self.expect_continue,
+            unknown_expectation: self.unknown_expectation,
+            transfer_codings: self.transfer_codings,
+            max_decompressed_size: self.max_decompressed_size,
+            allowed_hosts: self.allowed_hosts,
+            error_handler: self.error_handler,
+            catch_panic: self.catch_panic,
+            first_byte_timeout: self.first_byte_timeout,
+            payload_timeout: self.payload_timeout,
+            max_header_size: self.max_header_size,
+            max_line_size: self.max_line_size,
+            h2_send_buffer: self.h2_send_buffer,
+            verify_digest: self.verify_digest,
+            connection_handle: self.connection_handle,
+            error_formatter: self.error_formatter,
+            request_timing: self.request_timing,
+            server_timing: self.server_timing,
+            empty_header_value: self.empty_header_value,
+            header_validation: self.header_validation,
+            rate_window: self.rate_window,
+            alt_svc: self.alt_svc,
+            invalid_alt_svc: self.invalid_alt_svc,
+            rewrite_uri: self.rewrite_uri,
+            on_dispatch_error: self.on_dispatch_error,
+            tcp_keepalive: self.tcp_keepalive,
+            stats: self.stats,
             _t: PhantomData,
         }
     }
@@ -155,6 +530,37 @@ where
             expect: self.expect,
             upgrade: Some(upgrade.into_factory()),
             on_connect: self.on_connect,
+            wire_capture: self.wire_capture,
+            max_requests: self.max_requests,
+            max_pipelined: self.max_pipelined,
+            pipeline_overflow: self.pipeline_overflow,
+            http10_body: self.http10_body,
+            expect_continue: self.expect_continue,
+            unknown_expectation: self.unknown_expectation,
+            transfer_codings: self.transfer_codings,
+            max_decompressed_size: self.max_decompressed_size,
+            allowed_hosts: self.allowed_hosts,
+            error_handler: self.error_handler,
+            catch_panic: self.catch_panic,
+            first_byte_timeout: self.first_byte_timeout,
+            payload_timeout: self.payload_timeout,
+            max_header_size: self.max_header_size,
+            max_line_size: self.max_line_size,
+            h2_send_buffer: self.h2_send_buffer,
+            verify_digest: self.verify_digest,
+            connection_handle: self.connection_handle,
+            error_formatter: self.error_formatter,
+            request_timing: self.request_timing,
+            server_timing: self.server_timing,
+            empty_header_value: self.empty_header_value,
+            header_validation: self.header_validation,
+            rate_window: self.rate_window,
+            alt_svc: self.alt_svc,
+            invalid_alt_svc: self.invalid_alt_svc,
+            rewrite_uri: self.rewrite_uri,
+            on_dispatch_error: self.on_dispatch_error,
+            tcp_keepalive: self.tcp_keepalive,
+            stats: self.stats,
             _t: PhantomData,
         }
     }
@@ -172,6 +578,305 @@ where
         self
     }
 
+    /// Set wire capture callback.
+    ///
+    /// Callback get called with raw bytes as they are read from and
+    /// written to http/1 and http/2 connections. For secure connections
+    /// bytes are captured after decryption. Capture of http/1 connection
+    /// stops when connection get upgraded. This is useful only for
+    /// debugging purposes.
+    pub fn wire_capture<F>(mut self, f: F) -> Self
+    where
+        F: Fn(WireDirection, &[u8]) + 'static,
+    {
+        self.wire_capture = Some(Rc::new(f));
+        self
+    }
+
+    /// Set service error handler.
+    ///
+    /// Handler get called with error returned by the service or expect
+    /// service before error response is sent to the peer. If handler
+    /// returns `None`, default `ResponseError::error_response()` mapping
+    /// is used.
+    pub fn error_handler<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&dyn ResponseError) -> Option<Response> + 'static,
+    {
+        self.error_handler = Some(Rc::new(f));
+        self
+    }
+
+    /// Set request uri rewrite hook.
+    ///
+    /// Hook get called with request uri after request head is parsed and
+    /// before request is passed to expect, upgrade or main service. It could
+    /// be used for stripping mount prefix or normalizing path. Rewrite is
+    /// discarded if new uri does not have absolute path.
+    ///
+    /// ```rust
+    /// use std::convert::TryFrom;
+    /// use ntex::http::{HttpService, Response, Uri};
+    ///
+    /// let srv = HttpService::build()
+    ///     .rewrite_uri(|uri: &mut Uri| {
+    ///         if let Some(path) = uri.path().strip_prefix("/api") {
+    ///             if let Ok(new_uri) = Uri::try_from(path) {
+    ///                 *uri = new_uri;
+    ///             }
+    ///         }
+    ///     })
+    ///     .finish(|_| futures::future::ok::<_, std::io::Error>(Response::Ok()))
+    ///     .tcp();
+    /// ```
+    pub fn rewrite_uri<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&mut Uri) + 'static,
+    {
+        self.rewrite_uri = Some(Rc::new(f));
+        self
+    }
+
+    /// Set dispatcher error hook.
+    ///
+    /// Hook get called with error that terminates http/1 connection, http/2
+    /// connection or http/2 stream. Errors that happen during writing response
+    /// to the peer are reported as `DispatchError::Write` with method and path
+    /// of the request, it could be used for logging of interrupted responses.
+    ///
+    /// ```rust
+    /// use ntex::http::error::DispatchError;
+    /// use ntex::http::{HttpService, Response};
+    ///
+    /// let srv = HttpService::build()
+    ///     .on_dispatch_error(|err: &DispatchError| {
+    ///         if let DispatchError::Write(ref err) = err {
+    ///             log::info!("{} {}: {}", err.method(), err.path(), err.kind());
+    ///         }
+    ///     })
+    ///     .finish(|_| futures::future::ok::<_, std::io::Error>(Response::Ok()))
+    ///     .tcp();
+    /// ```
+    pub fn on_dispatch_error<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&DispatchError) + 'static,
+    {
+        self.on_dispatch_error = Some(Rc::new(f));
+        self
+    }
+
+    /// Set body format of error responses generated by dispatcher.
+    ///
+    /// Responses for malformed requests (400), slow requests (408),
+    /// too large requests (417, 431), captured panics (500) and time to
+    /// first byte timeouts (503) get body with short error message and
+    /// appropriate `Content-Type` header.
+    ///
+    /// By default error responses have empty body.
+    pub fn error_format(mut self, format: ErrorFormat) -> Self {
+        self.error_formatter = format.formatter();
+        self
+    }
+
+    /// Set formatter of error responses generated by dispatcher.
+    ///
+    /// Formatter get called with response status and short error message.
+    /// Response body should be in memory, connection could be closed
+    /// right after error response.
+    pub fn error_formatter<F>(mut self, f: F) -> Self
+    where
+        F: Fn(StatusCode, &str) -> Response + 'static,
+    {
+        self.error_formatter = Some(Rc::new(f));
+        self
+    }
+
+    /// Capture panics in service call.
+    ///
+    /// Panic in service call or service response future is logged with
+    /// request uri and converted to `500 Internal Server Error` response,
+    /// error handler gets called with `PanicError`. Connection and other
+    /// http/2 streams are kept alive. If disabled, panic is propagated
+    /// and connection is dropped.
+    ///
+    /// By default panics are captured.
+    pub fn catch_panic(mut self, val: bool) -> Self {
+        self.catch_panic = val;
+        self
+    }
+
+    /// Enable connection handle.
+    ///
+    /// `ConnectionHandle` is created for each connection and stored to
+    /// the request's extensions. Handle tracks number of started and
+    /// finished requests and allows to drain connection.
+    ///
+    /// By default connection handle is disabled.
+    pub fn connection_handle(mut self, val: bool) -> Self {
+        self.connection_handle = val;
+        self
+    }
+
+    /// Set window for per-connection request rate.
+    ///
+    /// Connection handle tracks number of requests received within the
+    /// sliding window, rate is available with `ConnectionHandle::request_rate()`.
+    /// Enables connection handle as well.
+    ///
+    /// To disable request rate tracking set value to 0. By default request
+    /// rate is not tracked.
+    pub fn request_rate_window(mut self, dur: Duration) -> Self {
+        self.rate_window = dur;
+        self
+    }
+
+    /// Set `Alt-Svc` header value for responses.
+    ///
+    /// Header is added to every http/1 and http/2 response, responses
+    /// with `Alt-Svc` header set by application are not changed. It could be
+    /// used for advertising http/3 endpoint, `h3=":443"; ma=86400`.
+    ///
+    /// Invalid header value is reported by `validate()` as
+    /// `ConfigIssue::InvalidAltSvc` and header is not set.
+    ///
+    /// By default `Alt-Svc` header is not set.
+    pub fn alt_svc<V>(mut self, value: V) -> Self
+    where
+        HeaderValue: TryFrom<V>,
+    {
+        match HeaderValue::try_from(value) {
+            Ok(value) => {
+                self.alt_svc = Some(value);
+                self.invalid_alt_svc = false;
+            }
+            Err(_) => {
+                self.alt_svc = None;
+                self.invalid_alt_svc = true;
+            }
+        }
+        self
+    }
+
+    /// Enable request body digest verification.
+    ///
+    /// Request payload is verified against `Digest` header with `sha-256`
+    /// or `md5` algorithm or against `Content-MD5` header. Digest is computed
+    /// while payload is read, on mismatch payload stream ends with
+    /// `PayloadError::DigestMismatch` error instead of eof, web extractors
+    /// respond with `400 Bad Request`. Requests without supported digest
+    /// headers are not verified.
+    ///
+    /// By default digest verification is disabled.
+    pub fn verify_body_digest(mut self, val: bool) -> Self {
+        self.verify_digest = val;
+        self
+    }
+
+    /// Enable request timing.
+    ///
+    /// `RequestTiming` with request receive time is stored to the
+    /// request's extensions.
+    ///
+    /// By default request timing is disabled.
+    pub fn request_timing(mut self, val: bool) -> Self {
+        self.request_timing = val;
+        self
+    }
+
+    /// Enable `Server-Timing` response header.
+    ///
+    /// Header contains total request processing time, from receiving
+    /// request head to sending response head, `Server-Timing: total;dur=1.250`.
+    /// Enables request timing as well.
+    ///
+    /// By default `Server-Timing` header is disabled.
+    pub fn server_timing(mut self, val: bool) -> Self {
+        self.server_timing = val;
+        self
+    }
+
+    /// Check configuration for inconsistent settings.
+    ///
+    /// Returns every inconsistent setting. Service construction does not
+    /// fail on inconsistent configuration, each issue is logged and setting
+    /// is corrected:
+    ///
+    /// * disconnect timeout larger than keep-alive timeout is reduced
+    ///   to keep-alive timeout
+    /// * zero max header size is replaced with default 32Kb
+    /// * zero http/2 send buffer size is replaced with default 16Kb
+    /// * zero tcp keep-alive retries is replaced with system default
+    /// * invalid `Alt-Svc` header value is ignored
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.check(&mut self.inner())
+    }
+
+    fn check(&self, inner: &mut Inner) -> Result<(), ConfigError> {
+        let mut issues = match validate(inner) {
+            Ok(()) => Vec::new(),
+            Err(e) => e.0,
+        };
+        if self.invalid_alt_svc {
+            issues.push(ConfigIssue::InvalidAltSvc);
+        }
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError(issues))
+        }
+    }
+
+    fn config(&self) -> ServiceConfig {
+        let mut inner = self.inner();
+        if let Err(e) = self.check(&mut inner) {
+            log::warn!("{}", e);
+        }
+        ServiceConfig(Rc::new(inner))
+    }
+
+    fn inner(&self) -> Inner {
+        let mut inner = Inner::new(
+            self.keep_alive,
+            self.client_timeout,
+            self.client_disconnect,
+            self.handshake_timeout,
+        );
+        inner.wire_capture = self.wire_capture.clone();
+        inner.max_requests = self.max_requests;
+        inner.max_pipelined = self.max_pipelined;
+        inner.pipeline_overflow = self.pipeline_overflow;
+        inner.http10_body = self.http10_body;
+        inner.expect_continue = self.expect_continue;
+        inner.unknown_expectation = self.unknown_expectation;
+        inner.transfer_codings = self.transfer_codings;
+        inner.max_decompressed_size = self.max_decompressed_size;
+        inner.allowed_hosts = self.allowed_hosts.clone();
+        inner.stats = self.stats.clone();
+        inner.error_handler = self.error_handler.clone();
+        inner.catch_panic = self.catch_panic;
+        inner.first_byte_timeout = self.first_byte_timeout;
+        inner.payload_timeout = self.payload_timeout;
+        inner.max_header_size = self.max_header_size;
+        inner.max_line_size = self.max_line_size;
+        inner.h2_send_buffer = self.h2_send_buffer;
+        inner.verify_digest = self.verify_digest;
+        inner.connection_handle = self.connection_handle;
+        inner.error_formatter = self.error_formatter.clone();
+        inner.request_timing = self.request_timing;
+        inner.server_timing = self.server_timing;
+        inner.empty_header_value = self.empty_header_value;
+        inner.header_validation = self.header_validation;
+        inner.rate_window = self.rate_window;
+        inner.alt_svc = self.alt_svc.clone();
+        inner.rewrite_uri = self.rewrite_uri.clone();
+        inner.on_dispatch_error = self.on_dispatch_error.clone();
+        if self.tcp_keepalive.is_some() {
+            inner.tcp_keepalive = self.tcp_keepalive;
+        }
+        inner
+    }
+
     /// Finish service configuration and create *http service* for HTTP/1 protocol.
     pub fn h1<F, B>(self, service: F) -> H1Service<T, S, B, X, U>
     where
@@ -181,12 +886,7 @@ where
         S::InitError: fmt::Debug,
         S::Response: Into<Response<B>>,
     {
-        let cfg = ServiceConfig::new(
-            self.keep_alive,
-            self.client_timeout,
-            self.client_disconnect,
-            self.handshake_timeout,
-        );
+        let cfg = self.config();
         H1Service::with_config(cfg, service.into_factory())
             .expect(self.expect)
             .upgrade(self.upgrade)
@@ -203,12 +903,7 @@ where
         S::Response: Into<Response<B>> + 'static,
         <S::Service as Service>::Future: 'static,
     {
-        let cfg = ServiceConfig::new(
-            self.keep_alive,
-            self.client_timeout,
-            self.client_disconnect,
-            self.handshake_timeout,
-        );
+        let cfg = self.config();
         H2Service::with_config(cfg, service.into_factory()).on_connect(self.on_connect)
     }
 
@@ -222,15 +917,72 @@ where
         S::Response: Into<Response<B>> + 'static,
         <S::Service as Service>::Future: 'static,
     {
-        let cfg = ServiceConfig::new(
-            self.keep_alive,
-            self.client_timeout,
-            self.client_disconnect,
-            self.handshake_timeout,
-        );
+        let cfg = self.config();
         HttpService::with_config(cfg, service.into_factory())
             .expect(self.expect)
             .upgrade(self.upgrade)
             .on_connect(self.on_connect)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rt::net::TcpStream;
+
+    #[ntex_rt::test]
+    async fn test_from_config() {
+        let cfg = HttpServiceBuilder::<TcpStream, ExpectHandler>::new()
+            .keep_alive(KeepAlive::Timeout(10))
+            .client_timeout(1500)
+            .payload_timeout(2000)
+            .max_header_size(1024)
+            .max_request_line_size(512)
+            .allowed_hosts(vec!["Example.com".to_string()])
+            .request_rate_window(Duration::from_secs(1))
+            .error_handler(|_| None)
+            .config();
+        assert_eq!(cfg.keep_alive(), KeepAlive::Timeout(10));
+        assert_eq!(cfg.client_timeout(), 1500);
+        assert_eq!(cfg.payload_timeout(), 2000);
+        assert_eq!(cfg.max_header_size(), 1024);
+        assert_eq!(cfg.max_request_line_size(), 512);
+        assert_eq!(cfg.allowed_hosts(), Some(&["example.com".to_string()][..]));
+
+        let repr = format!("{:?}", cfg);
+        assert!(repr.contains("keep_alive: 10s"));
+        assert!(repr.contains("client_timeout: 1500ms"));
+        assert!(repr.contains("payload_timeout: 2s"));
+        assert!(repr.contains("first_byte_timeout: disabled"));
+        assert!(repr.contains("request_rate_window: 1s"));
+        assert!(repr.contains("error_handler: set"));
+        assert!(repr.contains("wire_capture: unset"));
+
+        let cfg2 =
+            HttpServiceBuilder::<TcpStream, ExpectHandler>::from_config(cfg.clone())
+                .config();
+        assert_eq!(format!("{:?}", cfg2), repr);
+
+        let cfg = HttpServiceBuilder::<TcpStream, ExpectHandler>::new()
+            .keep_alive(KeepAlive::Os)
+            .config();
+        assert_eq!(cfg.keep_alive(), KeepAlive::Os);
+        let cfg =
+            HttpServiceBuilder::<TcpStream, ExpectHandler>::from_config(cfg).config();
+        assert_eq!(cfg.keep_alive(), KeepAlive::Os);
+        assert!(format!("{:?}", cfg).contains("keep_alive: os"));
+    }
+
+    #[ntex_rt::test]
+    async fn test_invalid_alt_svc() {
+        let builder = HttpServiceBuilder::<TcpStream, ExpectHandler>::new()
+            .alt_svc("h3=\":443\"\n");
+        let err = builder.validate().err().unwrap();
+        assert_eq!(err.issues(), &[ConfigIssue::InvalidAltSvc]);
+        assert!(builder.config().alt_svc().is_none());
+
+        let builder = builder.alt_svc("h3=\":443\"");
+        assert!(builder.validate().is_ok());
+        assert_eq!(builder.config().alt_svc().unwrap(), "h3=\":443\"");
+    }
+}
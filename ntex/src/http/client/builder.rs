@@ -37,6 +37,7 @@ impl ClientBuilder {
             config: ClientConfig {
                 headers: HeaderMap::new(),
                 timeout: Some(Duration::from_secs(5)),
+                max_decompressed_size: 262_144,
                 connector: Box::new(ConnectorWrapper(Connector::default().finish())),
             },
         }
@@ -69,6 +70,15 @@ impl ClientBuilder {
         self
     }
 
+    /// Set max size of decompressed response body.
+    ///
+    /// Reading response body fails with `PayloadError::Overflow` once
+    /// decompressed data exceeds the limit. By default limit is 256Kb.
+    pub fn max_decompressed_size(mut self, size: usize) -> Self {
+        self.config.max_decompressed_size = size;
+        self
+    }
+
     /// Do not follow redirects.
     ///
     /// Redirects are allowed by default.
@@ -152,8 +162,10 @@ mod tests {
             .disable_timeout()
             .disable_redirects()
             .max_redirects(10)
+            .max_decompressed_size(1024)
             .no_default_headers();
         assert!(!builder.allow_redirects);
+        assert_eq!(builder.config.max_decompressed_size, 1024);
         assert!(!builder.default_headers);
         assert_eq!(builder.max_redirects, 10);
     }
@@ -1,3 +1,6 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::task::{Context, Poll};
 use std::{fmt, time};
 
 use bytes::Bytes;
@@ -10,6 +13,7 @@ use crate::http::h1::ClientCodec;
 use crate::http::message::{RequestHeadType, ResponseHead};
 use crate::http::payload::Payload;
 use crate::http::Protocol;
+use crate::task::LocalWaker;
 
 use super::error::SendRequestError;
 use super::pool::Acquired;
@@ -26,6 +30,9 @@ pub trait Connection {
 
     fn protocol(&self) -> Protocol;
 
+    /// Handle for closing underlying connection
+    fn close_handle(&self) -> CloseHandle;
+
     /// Send request and body
     fn send_request<B: MessageBody + 'static, H: Into<RequestHeadType>>(
         self,
@@ -41,6 +48,60 @@ pub trait Connection {
     fn open_tunnel<H: Into<RequestHeadType>>(self, head: H) -> Self::TunnelFuture;
 }
 
+/// Client connection close handle
+///
+/// Closing connection cancels all in-flight requests on it, pending
+/// request and response payload futures resolve with error. Closed
+/// connection is not returned to the connection pool.
+///
+/// Http/2 connection task is dropped without `GOAWAY` frame.
+#[derive(Clone)]
+pub struct CloseHandle(Rc<CloseInner>);
+
+struct CloseInner {
+    closed: Cell<bool>,
+    waker: LocalWaker,
+}
+
+impl CloseHandle {
+    pub(super) fn new() -> Self {
+        CloseHandle(Rc::new(CloseInner {
+            closed: Cell::new(false),
+            waker: LocalWaker::new(),
+        }))
+    }
+
+    /// Close connection
+    pub fn close(&self) {
+        if !self.0.closed.replace(true) {
+            self.0.waker.wake();
+        }
+    }
+
+    /// Check if connection is closed
+    pub fn is_closed(&self) -> bool {
+        self.0.closed.get()
+    }
+
+    /// Register connection task, it gets woken up on close
+    pub(super) fn poll_closed(&self, cx: &mut Context<'_>) -> Poll<()> {
+        if self.is_closed() {
+            Poll::Ready(())
+        } else {
+            self.0.waker.register(cx.waker());
+            Poll::Pending
+        }
+    }
+}
+
+impl fmt::Debug for CloseHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CloseHandle")
+            .field("closed", &self.is_closed())
+            .finish()
+    }
+}
+
 pub(super) trait ConnectionLifetime:
     AsyncRead + AsyncWrite + Unpin + 'static
 {
@@ -56,6 +117,7 @@ pub(super) trait ConnectionLifetime:
 pub(super) struct IoConnection<T> {
     io: Option<ConnectionType<T>>,
     created: time::Instant,
+    handle: CloseHandle,
     pool: Option<Acquired<T>>,
 }
 
@@ -79,11 +141,13 @@ where
     pub(super) fn new(
         io: ConnectionType<T>,
         created: time::Instant,
+        handle: CloseHandle,
         pool: Option<Acquired<T>>,
     ) -> Self {
         IoConnection {
             pool,
             created,
+            handle,
             io: Some(io),
         }
     }
@@ -93,13 +157,14 @@ where
             pool.release(Self {
                 io: self.io,
                 created: self.created,
+                handle: self.handle,
                 pool: None,
             });
         }
     }
 
-    pub(super) fn into_inner(self) -> (ConnectionType<T>, time::Instant) {
-        (self.io.unwrap(), self.created)
+    pub(super) fn into_inner(self) -> (ConnectionType<T>, time::Instant, CloseHandle) {
+        (self.io.unwrap(), self.created, self.handle)
     }
 }
 
@@ -119,20 +184,47 @@ where
         }
     }
 
+    fn close_handle(&self) -> CloseHandle {
+        self.handle.clone()
+    }
+
     fn send_request<B: MessageBody + 'static, H: Into<RequestHeadType>>(
         mut self,
         head: H,
         body: B,
     ) -> Self::Future {
-        match self.io.take().unwrap() {
-            ConnectionType::H1(io) => {
-                h1proto::send_request(io, head.into(), body, self.created, self.pool)
-                    .boxed_local()
-            }
-            ConnectionType::H2(io) => {
-                h2proto::send_request(io, head.into(), body, self.created, self.pool)
-                    .boxed_local()
-            }
+        let head = head.into();
+        #[cfg(feature = "tracing")]
+        let span = crate::http::trace::RequestSpan::client(head.as_ref());
+
+        let fut = match self.io.take().unwrap() {
+            ConnectionType::H1(io) => h1proto::send_request(
+                io,
+                head,
+                body,
+                self.created,
+                self.handle,
+                self.pool,
+            )
+            .boxed_local(),
+            ConnectionType::H2(io) => h2proto::send_request(
+                io,
+                head,
+                body,
+                self.created,
+                self.handle,
+                self.pool,
+            )
+            .boxed_local(),
+        };
+
+        #[cfg(feature = "tracing")]
+        {
+            crate::http::trace::client_request(span, fut).boxed_local()
+        }
+        #[cfg(not(feature = "tracing"))]
+        {
+            fut
         }
     }
 
@@ -155,6 +247,7 @@ where
                     pool.release(IoConnection::new(
                         ConnectionType::H2(io),
                         self.created,
+                        self.handle,
                         None,
                     ));
                 }
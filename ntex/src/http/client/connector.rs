@@ -6,15 +6,19 @@ use futures::future::{err, Either, Ready};
 
 use crate::codec::{AsyncRead, AsyncWrite};
 use crate::connect::{self, Connect as TcpConnect, Connector as TcpConnector};
-use crate::http::{Protocol, Uri};
+use crate::http::config::{WireCapture, WireCaptureIo};
+use crate::http::{Protocol, ResponseHead, Uri, WireDirection};
 use crate::service::{apply_fn, boxed, Service};
 use crate::util::timeout::{TimeoutError, TimeoutService};
 
 use super::connection::Connection;
 use super::error::ConnectError;
-use super::pool::ConnectionPool;
+use super::pool::{ConnectionPool, ReusePolicy};
 use super::Connect;
 
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+use super::tls::TlsSessionCache;
+
 #[cfg(feature = "openssl")]
 use crate::connect::openssl::SslConnector as OpensslConnector;
 
@@ -44,11 +48,31 @@ pub struct Connector {
     conn_lifetime: Duration,
     conn_keep_alive: Duration,
     disconnect_timeout: Duration,
+    handshake_timeout: Duration,
     limit: usize,
-    connector: BoxedConnector,
-    ssl_connector: Option<BoxedConnector>,
-    #[allow(dead_code)]
-    resolver: connect::AsyncResolver,
+    host_limit: usize,
+    validate_on_checkout: bool,
+    idle_poll: Duration,
+    read_timeout: Duration,
+    write_timeout: Duration,
+    https_only: bool,
+    h2_prior_knowledge: bool,
+    tcp: TcpConnector<Uri>,
+    connector: Option<BoxedConnector>,
+    ssl_connector: Option<SecureConnector>,
+    wire_capture: Option<WireCapture>,
+    reuse_policy: Option<ReusePolicy>,
+    #[cfg(any(feature = "openssl", feature = "rustls"))]
+    tls_sessions: TlsSessionCache,
+}
+
+/// Secure connector, tls connectors are built on `finish()`
+enum SecureConnector {
+    Custom(BoxedConnector),
+    #[cfg(feature = "openssl")]
+    Openssl(OpensslConnector, TlsSessionCache),
+    #[cfg(feature = "rustls")]
+    Rustls(Arc<ClientConfig>),
 }
 
 trait Io: AsyncRead + AsyncWrite + Unpin {}
@@ -63,18 +87,26 @@ impl Default for Connector {
 impl Connector {
     pub fn new(resolver: connect::AsyncResolver) -> Connector {
         let conn = Connector {
-            connector: boxed::service(
-                TcpConnector::new(resolver.clone())
-                    .map(|io| (Box::new(io) as Box<dyn Io>, Protocol::Http1))
-                    .map_err(ConnectError::from),
-            ),
+            tcp: TcpConnector::new(resolver),
+            connector: None,
             ssl_connector: None,
+            wire_capture: None,
+            reuse_policy: None,
+            #[cfg(any(feature = "openssl", feature = "rustls"))]
+            tls_sessions: TlsSessionCache::new(256),
             timeout: Duration::from_secs(1),
             conn_lifetime: Duration::from_secs(75),
             conn_keep_alive: Duration::from_secs(15),
             disconnect_timeout: Duration::from_millis(3000),
+            handshake_timeout: Duration::from_millis(0),
             limit: 100,
-            resolver,
+            host_limit: 0,
+            validate_on_checkout: true,
+            idle_poll: Duration::from_secs(0),
+            read_timeout: Duration::from_secs(0),
+            write_timeout: Duration::from_secs(0),
+            https_only: false,
+            h2_prior_knowledge: false,
         };
 
         #[cfg(feature = "openssl")]
@@ -114,51 +146,81 @@ impl Connector {
 
     #[cfg(feature = "openssl")]
     /// Use openssl connector for secured connections.
-    pub fn openssl(self, connector: OpensslConnector) -> Self {
-        use crate::connect::openssl::OpensslConnector;
-
-        let resolver = self.resolver.clone();
-
-        const H2: &[u8] = b"h2";
-        self.secure_connector(OpensslConnector::with_resolver(connector, resolver).map(
-            |sock| {
-                let h2 = sock
-                    .ssl()
-                    .selected_alpn_protocol()
-                    .map(|protos| protos.windows(2).any(|w| w == H2))
-                    .unwrap_or(false);
-                if h2 {
-                    (sock, Protocol::Http2)
-                } else {
-                    (sock, Protocol::Http1)
-                }
-            },
-        ))
+    ///
+    /// Tls sessions are resumed with connector's session cache.
+    pub fn openssl(mut self, connector: OpensslConnector) -> Self {
+        // sessions of previous connector must not be resumed
+        self.tls_sessions.clear();
+        self.ssl_connector = Some(SecureConnector::Openssl(
+            connector,
+            self.tls_sessions.clone(),
+        ));
+        self
     }
 
     #[cfg(feature = "rustls")]
     /// Use rustls connector for secured connections.
-    pub fn rustls(self, connector: Arc<ClientConfig>) -> Self {
-        use crate::connect::rustls::{RustlsConnector, Session};
+    ///
+    /// Client session storage of the config is replaced with connector's
+    /// session cache.
+    pub fn rustls(mut self, connector: Arc<ClientConfig>) -> Self {
+        // sessions of previous connector must not be resumed
+        self.tls_sessions.clear();
+        let mut config = (*connector).clone();
+        config.session_persistence = Arc::new(self.tls_sessions.clone());
+        self.ssl_connector = Some(SecureConnector::Rustls(Arc::new(config)));
+        self
+    }
 
-        let resolver = self.resolver.clone();
+    #[cfg(any(feature = "openssl", feature = "rustls"))]
+    /// Set tls handshake timeout.
+    ///
+    /// Timeout starts after tcp connection is established and covers
+    /// whole handshake including server certificate verification. It
+    /// applies only to openssl and rustls connectors, handshake is also
+    /// limited by connection timeout.
+    ///
+    /// To disable timeout set value to 0. By default handshake timeout
+    /// is disabled.
+    pub fn ssl_handshake_timeout(mut self, dur: Duration) -> Self {
+        self.handshake_timeout = dur;
+        self
+    }
 
-        const H2: &[u8] = b"h2";
-        self.secure_connector(RustlsConnector::with_resolver(connector, resolver).map(
-            |sock| {
-                let h2 = sock
-                    .get_ref()
-                    .1
-                    .get_alpn_protocol()
-                    .map(|protos| protos.windows(2).any(|w| w == H2))
-                    .unwrap_or(false);
-                if h2 {
-                    (Box::new(sock) as Box<dyn Io>, Protocol::Http2)
-                } else {
-                    (Box::new(sock) as Box<dyn Io>, Protocol::Http1)
-                }
-            },
-        ))
+    #[cfg(any(feature = "openssl", feature = "rustls"))]
+    /// Set max number of cached tls sessions.
+    ///
+    /// Sessions of closed connections are cached per remote authority,
+    /// new connections resume cached session instead of full handshake.
+    /// Cache belongs to this connector and is not shared with other
+    /// clients. If size is 0, sessions are not resumed.
+    /// The default size is 256.
+    pub fn tls_session_cache(self, size: usize) -> Self {
+        self.tls_sessions.set_capacity(size);
+        self
+    }
+
+    #[cfg(any(feature = "openssl", feature = "rustls"))]
+    /// Get tls session cache of this connector.
+    ///
+    /// Cache provides counters of resumed and full handshakes.
+    pub fn tls_sessions(&self) -> TlsSessionCache {
+        self.tls_sessions.clone()
+    }
+
+    /// Set `SO_LINGER` option of client connections.
+    ///
+    /// Zero timeout resets connection on close, `RST` is sent instead
+    /// of regular `FIN` and socket does not accumulate in `TIME_WAIT` state.
+    /// Any unsent data is discarded, so zero linger timeout is suitable only
+    /// for connections that are closed after response is completely read.
+    /// Connectors set with `connector()` or `secure_connector()` are not
+    /// affected.
+    ///
+    /// By default `SO_LINGER` is not set and system defaults are used.
+    pub fn linger(mut self, dur: Duration) -> Self {
+        self.tcp = self.tcp.linger(dur);
+        self
     }
 
     /// Set total number of simultaneous connections per type of scheme.
@@ -170,6 +232,19 @@ impl Connector {
         self
     }
 
+    /// Set number of simultaneous connections per destination host.
+    ///
+    /// Requests to a host that reached the limit wait for available
+    /// connection, requests to other hosts are not blocked by them.
+    /// Waiting requests for the same host are served in order.
+    ///
+    /// If limit is 0, the connector has no per host limit.
+    /// By default per host limit is not set.
+    pub fn max_connections_per_host(mut self, limit: usize) -> Self {
+        self.host_limit = limit;
+        self
+    }
+
     /// Set keep-alive period for opened connection.
     ///
     /// Keep-alive period is the period between connection usage. If
@@ -191,6 +266,82 @@ impl Connector {
         self
     }
 
+    /// Validate idle connection before it is returned from the pool.
+    ///
+    /// Idle http/1 connection is checked for eof or unexpected data,
+    /// half-closed connections get dropped and next idle connection
+    /// is used instead.
+    ///
+    /// By default validation is enabled.
+    pub fn validate_on_checkout(mut self, validate: bool) -> Self {
+        self.validate_on_checkout = validate;
+        self
+    }
+
+    /// Set idle connections check interval.
+    ///
+    /// Pool periodically checks idle connections and evicts connections
+    /// that are closed by the peer or exceeded keep-alive period.
+    ///
+    /// To disable periodic check set value to 0. By default periodic
+    /// check is disabled.
+    pub fn idle_poll(mut self, dur: Duration) -> Self {
+        self.idle_poll = dur;
+        self
+    }
+
+    /// Set socket read timeout.
+    ///
+    /// Request fails with `SendRequestError::ReadTimeout` if a single read
+    /// from the connection stalls for longer than this duration while waiting
+    /// for response head or payload. Unlike request timeout, it does not limit
+    /// total response time. Timed out http/1 connection is not returned to the
+    /// pool, for http/2 connection only request's stream gets reset.
+    ///
+    /// To disable timeout set value to 0. By default read timeout is disabled.
+    pub fn read_timeout(mut self, dur: Duration) -> Self {
+        self.read_timeout = dur;
+        self
+    }
+
+    /// Set socket write timeout.
+    ///
+    /// Request fails with `SendRequestError::WriteTimeout` if a single write
+    /// to the connection stalls for longer than this duration while sending
+    /// request head or payload. Timed out http/1 connection is not returned
+    /// to the pool, for http/2 connection only request's stream gets reset.
+    ///
+    /// To disable timeout set value to 0. By default write timeout is disabled.
+    pub fn write_timeout(mut self, dur: Duration) -> Self {
+        self.write_timeout = dur;
+        self
+    }
+
+    /// Reject plaintext connections.
+    ///
+    /// If enabled, connector refuses to connect to any `http://` or `ws://`
+    /// url with `ConnectError::InsecureScheme` error. Check happens before
+    /// name resolution, so no plaintext connection is ever opened.
+    ///
+    /// By default plaintext connections are allowed.
+    pub fn https_only(mut self, val: bool) -> Self {
+        self.https_only = val;
+        self
+    }
+
+    /// Use http/2 with prior knowledge for plaintext connections.
+    ///
+    /// If enabled, connector sends http/2 connection preface directly
+    /// over plaintext connections (h2c), without `Upgrade` negotiation.
+    /// Server must support http/2 over cleartext, otherwise requests
+    /// fail. Secure connections still negotiate protocol with ALPN.
+    ///
+    /// By default http/1 is used for plaintext connections.
+    pub fn h2_prior_knowledge(mut self, val: bool) -> Self {
+        self.h2_prior_knowledge = val;
+        self
+    }
+
     /// Set server connection disconnect timeout in milliseconds.
     ///
     /// Defines a timeout for disconnect connection. If a disconnect procedure does not complete
@@ -204,6 +355,44 @@ impl Connector {
         self
     }
 
+    /// Set wire capture callback.
+    ///
+    /// Callback get called with raw bytes as they are read from and
+    /// written to client connections. For secure connections bytes are
+    /// captured after decryption. This is useful only for debugging purposes.
+    pub fn wire_capture<F>(mut self, f: F) -> Self
+    where
+        F: Fn(WireDirection, &[u8]) + 'static,
+    {
+        self.wire_capture = Some(Rc::new(f));
+        self
+    }
+
+    /// Set connection reuse policy.
+    ///
+    /// Policy get called with response head, if it returns `false`
+    /// connection is closed after response instead of returning it to
+    /// the pool. Policy applies only to http/1 connections.
+    ///
+    /// By default connection is reused after any response if response
+    /// body is fully read.
+    ///
+    /// ```rust,no_run
+    /// use ntex::http::client::Connector;
+    ///
+    /// // do not reuse connections after server errors
+    /// let connector = Connector::default()
+    ///      .reuse_policy(|head| !head.status.is_server_error())
+    ///      .finish();
+    /// ```
+    pub fn reuse_policy<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&ResponseHead) -> bool + 'static,
+    {
+        self.reuse_policy = Some(Rc::new(f));
+        self
+    }
+
     /// Use custom connector to open un-secured connections.
     pub fn connector<T, U>(mut self, connector: T) -> Self
     where
@@ -214,11 +403,11 @@ impl Connector {
                 Error = crate::connect::ConnectError,
             > + 'static,
     {
-        self.connector = boxed::service(
+        self.connector = Some(boxed::service(
             connector
                 .map(|(io, proto)| (Box::new(io) as Box<dyn Io>, proto))
                 .map_err(ConnectError::from),
-        );
+        ));
         self
     }
 
@@ -232,11 +421,11 @@ impl Connector {
                 Error = crate::connect::ConnectError,
             > + 'static,
     {
-        self.ssl_connector = Some(boxed::service(
+        self.ssl_connector = Some(SecureConnector::Custom(boxed::service(
             connector
                 .map(|(io, proto)| (Box::new(io) as Box<dyn Io>, proto))
                 .map_err(ConnectError::from),
-        ));
+        )));
         self
     }
 
@@ -247,17 +436,50 @@ impl Connector {
         self,
     ) -> impl Service<Request = Connect, Response = impl Connection, Error = ConnectError>
            + Clone {
-        let tcp_service = connector(self.connector, self.timeout);
+        let tcp = self.tcp;
+        let tcp_connector = self.connector.unwrap_or_else(|| {
+            boxed::service(
+                tcp.clone()
+                    .map(|io| (Box::new(io) as Box<dyn Io>, Protocol::Http1))
+                    .map_err(ConnectError::from),
+            )
+        });
+        let tcp_connector = if self.h2_prior_knowledge {
+            boxed::service(tcp_connector.map(|(io, _)| (io, Protocol::Http2)))
+        } else {
+            tcp_connector
+        };
+        let handshake_timeout = self.handshake_timeout;
+        let ssl_connector = self
+            .ssl_connector
+            .map(|conn| conn.finish(tcp, handshake_timeout));
+        let (tcp_connector, ssl_connector) = if let Some(capture) = self.wire_capture {
+            (
+                wire_capture(tcp_connector, capture.clone()),
+                ssl_connector.map(|conn| wire_capture(conn, capture)),
+            )
+        } else {
+            (tcp_connector, ssl_connector)
+        };
+        let tcp_service = connector(tcp_connector, self.timeout);
 
-        let ssl_pool = if let Some(ssl_connector) = self.ssl_connector {
+        let ssl_pool = if let Some(ssl_connector) = ssl_connector {
             let srv = connector(ssl_connector, self.timeout);
-            Some(ConnectionPool::new(
-                srv,
-                self.conn_lifetime,
-                self.conn_keep_alive,
-                self.disconnect_timeout,
-                self.limit,
-            ))
+            Some(
+                ConnectionPool::new(
+                    srv,
+                    self.conn_lifetime,
+                    self.conn_keep_alive,
+                    self.disconnect_timeout,
+                    self.limit,
+                    self.host_limit,
+                    self.validate_on_checkout,
+                    self.idle_poll,
+                    self.read_timeout,
+                    self.write_timeout,
+                )
+                .reuse_policy(self.reuse_policy.clone()),
+            )
         } else {
             None
         };
@@ -269,12 +491,74 @@ impl Connector {
                 self.conn_keep_alive,
                 self.disconnect_timeout,
                 self.limit,
-            ),
+                self.host_limit,
+                self.validate_on_checkout,
+                self.idle_poll,
+                self.read_timeout,
+                self.write_timeout,
+            )
+            .reuse_policy(self.reuse_policy),
             ssl_pool,
+            https_only: self.https_only,
         })
     }
 }
 
+impl SecureConnector {
+    #[cfg_attr(
+        not(any(feature = "openssl", feature = "rustls")),
+        allow(unused_variables)
+    )]
+    fn finish(self, tcp: TcpConnector<Uri>, timeout: Duration) -> BoxedConnector {
+        #[cfg(any(feature = "openssl", feature = "rustls"))]
+        const H2: &[u8] = b"h2";
+
+        match self {
+            SecureConnector::Custom(connector) => connector,
+            #[cfg(feature = "openssl")]
+            SecureConnector::Openssl(connector, sessions) => boxed::service(
+                super::tls::openssl_connector(connector, tcp, sessions, timeout)
+                    .map(|sock| {
+                        let h2 = sock
+                            .ssl()
+                            .selected_alpn_protocol()
+                            .map(|protos| protos.windows(2).any(|w| w == H2))
+                            .unwrap_or(false);
+                        if h2 {
+                            (Box::new(sock) as Box<dyn Io>, Protocol::Http2)
+                        } else {
+                            (Box::new(sock) as Box<dyn Io>, Protocol::Http1)
+                        }
+                    })
+                    .map_err(ConnectError::from),
+            ),
+            #[cfg(feature = "rustls")]
+            SecureConnector::Rustls(config) => {
+                use crate::connect::rustls::{RustlsConnector, Session};
+
+                boxed::service(
+                    RustlsConnector::with_connector(config, tcp)
+                        .handshake_timeout(timeout)
+                        .map(|sock| {
+                            let h2 = sock
+                                .get_ref()
+                                .1
+                                .get_alpn_protocol()
+                                .map(|protos| protos.windows(2).any(|w| w == H2))
+                                .unwrap_or(false);
+                            if h2 {
+                                (Box::new(sock) as Box<dyn Io>, Protocol::Http2)
+                            } else {
+                                (Box::new(sock) as Box<dyn Io>, Protocol::Http1)
+                            }
+                        })
+                        .map_err(ConnectError::from),
+                )
+            }
+        }
+    }
+}
+
 fn connector(
     connector: BoxedConnector,
     timeout: Duration,
@@ -297,11 +581,19 @@ fn connector(
     })
 }
 
+fn wire_capture(connector: BoxedConnector, capture: WireCapture) -> BoxedConnector {
+    boxed::service(connector.map(move |(io, proto)| {
+        let io = WireCaptureIo::new(io, Some(capture.clone()));
+        (Box::new(io) as Box<dyn Io>, proto)
+    }))
+}
+
 type Pool<T> = ConnectionPool<T, Box<dyn Io>>;
 
 struct InnerConnector<T> {
     tcp_pool: Pool<T>,
     ssl_pool: Option<Pool<T>>,
+    https_only: bool,
 }
 
 impl<T> Service for InnerConnector<T>
@@ -359,6 +651,7 @@ where
                     Either::Right(err(ConnectError::SslIsNotSupported))
                 }
             }
+            _ if self.https_only => Either::Right(err(ConnectError::InsecureScheme)),
             _ => Either::Left(self.tcp_pool.call(req)),
         }
     }
@@ -375,4 +668,27 @@ mod tests {
         assert!(lazy(|cx| conn.poll_ready(cx).is_ready()).await);
         assert!(lazy(|cx| conn.poll_shutdown(cx, true).is_ready()).await);
     }
+
+    #[ntex_rt::test]
+    async fn test_https_only() {
+        let conn = Connector::default().https_only(true).finish();
+
+        let req = Connect {
+            uri: Uri::from_static("http://localhost:8080/"),
+            addr: None,
+        };
+        match conn.call(req).await {
+            Err(ConnectError::InsecureScheme) => (),
+            _ => panic!(),
+        }
+
+        let req = Connect {
+            uri: Uri::from_static("ws://localhost:8080/"),
+            addr: None,
+        };
+        match conn.call(req).await {
+            Err(ConnectError::InsecureScheme) => (),
+            _ => panic!(),
+        }
+    }
 }
@@ -1,6 +1,7 @@
 //! Http client errors
 use std::error::Error;
-use std::io;
+use std::net::SocketAddr;
+use std::{fmt, io};
 
 use derive_more::{Display, From};
 use serde_json::error::Error as JsonError;
@@ -35,6 +36,10 @@ pub enum WsClientError {
     /// Invalid challenge response
     #[display(fmt = "Invalid challenge response")]
     InvalidChallengeResponse(String, HeaderValue),
+    /// Server selected protocol that was not requested
+    #[display(fmt = "Unexpected websocket protocol: {:?}", _0)]
+    #[from(ignore)]
+    UnexpectedProtocol(HeaderValue),
     /// Protocol error
     #[display(fmt = "{}", _0)]
     Protocol(ProtocolError),
@@ -73,6 +78,46 @@ pub enum JsonPayloadError {
 
 impl std::error::Error for JsonPayloadError {}
 
+/// A set of errors that can occur while saving response body to a file
+#[derive(Debug, Display, From)]
+pub enum SaveToError {
+    /// Payload error
+    #[display(fmt = "Error that occur during reading payload: {}", _0)]
+    Payload(PayloadError),
+    /// File io error
+    #[display(fmt = "File io error: {}", _0)]
+    Io(io::Error),
+    /// Body size does not match `Content-Length` header
+    #[display(
+        fmt = "Body size does not match Content-Length, expected {} got {}",
+        expected,
+        received
+    )]
+    #[from(ignore)]
+    Length { expected: u64, received: u64 },
+}
+
+impl std::error::Error for SaveToError {}
+
+/// A set of errors that can occur while copying response body to a sink
+#[derive(Debug, Display)]
+pub enum CopyToError<E: fmt::Debug> {
+    /// Payload error
+    #[display(fmt = "Error that occur during reading payload: {}", _0)]
+    Payload(PayloadError),
+    /// Sink error
+    #[display(fmt = "Sink error: {:?}", _0)]
+    Sink(E),
+}
+
+impl<E: fmt::Debug> std::error::Error for CopyToError<E> {}
+
+impl<E: fmt::Debug> From<PayloadError> for CopyToError<E> {
+    fn from(err: PayloadError) -> Self {
+        CopyToError::Payload(err)
+    }
+}
+
 /// A set of errors that can occur while connecting to an HTTP host
 #[derive(Debug, Display, From)]
 pub enum ConnectError {
@@ -80,13 +125,17 @@ pub enum ConnectError {
     #[display(fmt = "SSL is not supported")]
     SslIsNotSupported,
 
+    /// Plaintext connection is rejected by https only connector
+    #[display(fmt = "Plaintext http connection is not allowed")]
+    InsecureScheme,
+
     /// SSL error
     #[cfg(feature = "openssl")]
     #[display(fmt = "{}", _0)]
     SslError(SslError),
 
     /// SSL Handshake error
-    #[cfg(feature = "openssl")]
+    #[cfg(any(feature = "openssl", feature = "rustls"))]
     #[display(fmt = "{}", _0)]
     SslHandshakeError(String),
 
@@ -114,6 +163,11 @@ pub enum ConnectError {
     #[display(fmt = "Connector received `Connect` method with unresolved host")]
     Unresolved,
 
+    /// Failed to bind socket to local address
+    #[display(fmt = "Failed to bind local address {}: {}", _0, _1)]
+    #[from(ignore)]
+    Bind(SocketAddr, io::Error),
+
     /// Connection io error
     #[display(fmt = "{}", _0)]
     Io(io::Error),
@@ -128,6 +182,16 @@ impl From<crate::connect::ConnectError> for ConnectError {
             crate::connect::ConnectError::NoRecords => ConnectError::NoRecords,
             crate::connect::ConnectError::InvalidInput => panic!(),
             crate::connect::ConnectError::Unresolved => ConnectError::Unresolved,
+            crate::connect::ConnectError::Bind(addr, e) => ConnectError::Bind(addr, e),
+            crate::connect::ConnectError::Timeout => ConnectError::Timeout,
+            #[cfg(any(feature = "openssl", feature = "rustls"))]
+            crate::connect::ConnectError::Handshake(e) => {
+                ConnectError::SslHandshakeError(e.to_string())
+            }
+            #[cfg(not(any(feature = "openssl", feature = "rustls")))]
+            crate::connect::ConnectError::Handshake(e) => {
+                ConnectError::Io(io::Error::new(io::ErrorKind::Other, e))
+            }
             crate::connect::ConnectError::Io(e) => ConnectError::Io(e),
         }
     }
@@ -176,6 +240,12 @@ pub enum SendRequestError {
     /// Response took too long
     #[display(fmt = "Timeout out while waiting for response")]
     Timeout,
+    /// Read from connection stalled longer than read timeout
+    #[display(fmt = "Timeout out while reading from connection")]
+    ReadTimeout,
+    /// Write to connection stalled longer than write timeout
+    #[display(fmt = "Timeout out while writing to connection")]
+    WriteTimeout,
     /// Tunnels are not supported for http2 connection
     #[display(fmt = "Tunnels are not supported for http2 connection")]
     TunnelNotSupported,
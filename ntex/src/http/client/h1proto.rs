@@ -1,6 +1,8 @@
+use std::future::Future;
 use std::io::Write;
 use std::pin::Pin;
 use std::task::{Context, Poll};
+use std::time::Duration;
 use std::{io, mem, time};
 
 use bytes::buf::BufMutExt;
@@ -10,21 +12,25 @@ use futures::{SinkExt, Stream, StreamExt};
 
 use crate::codec::{AsyncRead, AsyncWrite, Framed};
 use crate::http::body::{BodySize, MessageBody};
-use crate::http::error::PayloadError;
+use crate::http::error::{ParseError, PayloadError};
 use crate::http::h1;
-use crate::http::header::{HeaderMap, HeaderValue, HOST};
+use crate::http::header::{HeaderMap, HeaderValue, EXPECT, HOST};
 use crate::http::message::{RequestHeadType, ResponseHead};
 use crate::http::payload::{Payload, PayloadStream};
+use crate::http::StatusCode;
+use crate::rt::time::{delay_for, Delay};
 
-use super::connection::{ConnectionLifetime, ConnectionType, IoConnection};
+use super::connection::{CloseHandle, ConnectionLifetime, ConnectionType, IoConnection};
 use super::error::{ConnectError, SendRequestError};
 use super::pool::Acquired;
+use super::response::ResponseTrailers;
 
 pub(super) async fn send_request<T, B>(
     io: T,
     mut head: RequestHeadType,
     body: B,
     created: time::Instant,
+    handle: CloseHandle,
     pool: Option<Acquired<T>>,
 ) -> Result<(ResponseHead, Payload), SendRequestError>
 where
@@ -58,39 +64,74 @@ where
         }
     }
 
+    let (read_timeout, write_timeout) = pool
+        .as_ref()
+        .map(|pool| pool.io_timeouts())
+        .unwrap_or_default();
     let io = H1Connection {
         created,
+        handle,
         pool,
+        read_timeout,
+        write_timeout,
+        read_timer: None,
+        write_timer: None,
         io: Some(io),
     };
 
+    let has_body = !matches!(
+        body.size(),
+        BodySize::None | BodySize::Empty | BodySize::Sized(0)
+    );
+    let expect = has_body && expect_continue(&head);
+
     // create Framed and send request
     let mut framed = Framed::new(io, h1::ClientCodec::default());
-    framed.send((head, body.size()).into()).await?;
-
-    // send request body
-    match body.size() {
-        BodySize::None | BodySize::Empty | BodySize::Sized(0) => (),
-        _ => send_body(body, &mut framed).await?,
-    };
+    framed
+        .send((head, body.size()).into())
+        .await
+        .map_err(stall_error)?;
 
-    // read response and init read body
-    let res = framed.into_future().await;
-    let (head, framed) = if let (Some(result), framed) = res {
-        let item = result.map_err(SendRequestError::from)?;
-        (item, framed)
+    // send request body and read response
+    let mut body_sent = true;
+    let head = if expect {
+        // wait for `100 Continue` before sending request body,
+        // final response means body must not be sent at all
+        let head = read_head(&mut framed, true).await?;
+        if head.status == StatusCode::CONTINUE {
+            send_body(body, &mut framed).await.map_err(stall_error)?;
+            read_head(&mut framed, false).await?
+        } else {
+            body_sent = false;
+            head
+        }
     } else {
-        return Err(SendRequestError::from(ConnectError::Disconnected));
+        if has_body {
+            send_body(body, &mut framed).await.map_err(stall_error)?;
+        }
+        read_head(&mut framed, false).await?
     };
 
+    // check connection reuse policy, connection with unsent request body
+    // could not be reused
+    let reuse = body_sent
+        && framed
+            .get_ref()
+            .pool
+            .as_ref()
+            .map(|pool| pool.reuse(&head))
+            .unwrap_or(true);
+
     match framed.get_codec().message_type() {
         h1::MessageType::None => {
-            let force_close = !framed.get_codec().keepalive();
+            let force_close = !reuse || !framed.get_codec().keepalive();
             release_connection(framed, force_close);
             Ok((head, Payload::None))
         }
         _ => {
-            let pl: PayloadStream = PlStream::new(framed).boxed_local();
+            let trailers = ResponseTrailers::default();
+            head.extensions_mut().insert(trailers.clone());
+            let pl: PayloadStream = PlStream::new(framed, trailers, reuse).boxed_local();
             Ok((head, pl.into()))
         }
     }
@@ -116,6 +157,43 @@ where
     }
 }
 
+/// Check if request expects `100 Continue` response
+fn expect_continue(head: &RequestHeadType) -> bool {
+    head.as_ref()
+        .headers
+        .get(EXPECT)
+        .into_iter()
+        .chain(head.extra_headers().and_then(|h| h.get(EXPECT)))
+        .any(|val| val.as_bytes().eq_ignore_ascii_case(b"100-continue"))
+}
+
+/// Read response head
+///
+/// If `expect` is set, interim responses are skipped until
+/// `100 Continue` or final response.
+async fn read_head<I>(
+    framed: &mut Framed<I, h1::ClientCodec>,
+    expect: bool,
+) -> Result<ResponseHead, SendRequestError>
+where
+    I: ConnectionLifetime,
+{
+    loop {
+        let head = match framed.next().await {
+            Some(result) => result.map_err(stall_error)?,
+            None => return Err(SendRequestError::from(ConnectError::Disconnected)),
+        };
+        if expect
+            && head.status.is_informational()
+            && head.status != StatusCode::CONTINUE
+            && head.status != StatusCode::SWITCHING_PROTOCOLS
+        {
+            continue;
+        }
+        return Ok(head);
+    }
+}
+
 /// send request body to the peer
 pub(super) async fn send_body<I, B>(
     mut body: B,
@@ -159,12 +237,82 @@ where
     Ok(())
 }
 
+/// Socket read or write stalled longer than configured timeout
+#[derive(Debug, Display, Copy, Clone)]
+enum Stall {
+    #[display(fmt = "Read timeout")]
+    Read,
+    #[display(fmt = "Write timeout")]
+    Write,
+}
+
+impl std::error::Error for Stall {}
+
+/// Convert socket stall io error to specific send request error
+fn stall_error<E: Into<SendRequestError>>(err: E) -> SendRequestError {
+    let err = err.into();
+    let stall = match err {
+        SendRequestError::Send(ref e)
+        | SendRequestError::Response(ParseError::Io(ref e)) => {
+            e.get_ref().and_then(|e| e.downcast_ref::<Stall>()).copied()
+        }
+        _ => None,
+    };
+    match stall {
+        Some(Stall::Read) => SendRequestError::ReadTimeout,
+        Some(Stall::Write) => SendRequestError::WriteTimeout,
+        None => err,
+    }
+}
+
+/// Start stall timer if io operation is pending, reset it otherwise
+fn poll_stall<R>(
+    timer: &mut Option<Delay>,
+    timeout: Duration,
+    res: &Poll<R>,
+    stall: Stall,
+    cx: &mut Context<'_>,
+) -> io::Result<()> {
+    if res.is_ready() || timeout == Duration::from_secs(0) {
+        *timer = None;
+    } else {
+        let delay = timer.get_or_insert_with(|| delay_for(timeout));
+        if Pin::new(delay).poll(cx).is_ready() {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, stall));
+        }
+    }
+    Ok(())
+}
+
 #[doc(hidden)]
 /// HTTP client connection
 pub(super) struct H1Connection<T> {
     io: Option<T>,
     created: time::Instant,
+    handle: CloseHandle,
     pool: Option<Acquired<T>>,
+    read_timeout: Duration,
+    write_timeout: Duration,
+    read_timer: Option<Delay>,
+    write_timer: Option<Delay>,
+}
+
+impl<T> H1Connection<T> {
+    /// Check if connection is closed with close handle
+    fn poll_closed<R>(&self, cx: &mut Context<'_>, res: Poll<R>) -> Poll<io::Result<R>> {
+        if self.handle.is_closed() {
+            Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::ConnectionAborted,
+                "Connection is closed",
+            )))
+        } else if res.is_pending() {
+            // register for close notification
+            let _ = self.handle.poll_closed(cx);
+            Poll::Pending
+        } else {
+            res.map(Ok)
+        }
+    }
 }
 
 impl<T> ConnectionLifetime for H1Connection<T>
@@ -178,6 +326,7 @@ where
                 pool.close(IoConnection::new(
                     ConnectionType::H1(io),
                     self.created,
+                    self.handle.clone(),
                     None,
                 ));
             }
@@ -191,6 +340,7 @@ where
                 pool.release(IoConnection::new(
                     ConnectionType::H1(io),
                     self.created,
+                    self.handle.clone(),
                     None,
                 ));
             }
@@ -211,7 +361,16 @@ impl<T: AsyncRead + AsyncWrite + Unpin + 'static> AsyncRead for H1Connection<T>
         cx: &mut Context<'_>,
         buf: &mut [u8],
     ) -> Poll<io::Result<usize>> {
-        Pin::new(&mut self.io.as_mut().unwrap()).poll_read(cx, buf)
+        let res = Pin::new(&mut self.io.as_mut().unwrap()).poll_read(cx, buf)?;
+        let this = self.as_mut().get_mut();
+        poll_stall(
+            &mut this.read_timer,
+            this.read_timeout,
+            &res,
+            Stall::Read,
+            cx,
+        )?;
+        self.poll_closed(cx, res)
     }
 }
 
@@ -221,7 +380,16 @@ impl<T: AsyncRead + AsyncWrite + Unpin + 'static> AsyncWrite for H1Connection<T>
         cx: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<io::Result<usize>> {
-        Pin::new(&mut self.io.as_mut().unwrap()).poll_write(cx, buf)
+        let res = Pin::new(&mut self.io.as_mut().unwrap()).poll_write(cx, buf)?;
+        let this = self.as_mut().get_mut();
+        poll_stall(
+            &mut this.write_timer,
+            this.write_timeout,
+            &res,
+            Stall::Write,
+            cx,
+        )?;
+        self.poll_closed(cx, res)
     }
 
     fn poll_flush(
@@ -241,11 +409,19 @@ impl<T: AsyncRead + AsyncWrite + Unpin + 'static> AsyncWrite for H1Connection<T>
 
 pub(super) struct PlStream<Io> {
     framed: Option<Framed<Io, h1::ClientPayloadCodec>>,
+    trailers: ResponseTrailers,
+    reuse: bool,
 }
 
 impl<Io: ConnectionLifetime> PlStream<Io> {
-    fn new(framed: Framed<Io, h1::ClientCodec>) -> Self {
+    fn new(
+        framed: Framed<Io, h1::ClientCodec>,
+        trailers: ResponseTrailers,
+        reuse: bool,
+    ) -> Self {
         PlStream {
+            trailers,
+            reuse,
             framed: Some(framed.map_codec(|codec| codec.into_payload_codec())),
         }
     }
@@ -266,8 +442,11 @@ impl<Io: ConnectionLifetime> Stream for PlStream<Io> {
                 if let Some(chunk) = chunk {
                     Poll::Ready(Some(Ok(chunk)))
                 } else {
-                    let framed = this.framed.take().unwrap();
-                    let force_close = !framed.get_codec().keepalive();
+                    let mut framed = this.framed.take().unwrap();
+                    if let Some(trailers) = framed.get_codec_mut().take_trailers() {
+                        *this.trailers.0.borrow_mut() = Some(trailers);
+                    }
+                    let force_close = !this.reuse || !framed.get_codec().keepalive();
                     release_connection(framed, force_close);
                     Poll::Ready(None)
                 }
@@ -1,19 +1,26 @@
 use std::convert::TryFrom;
-use std::time;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{self, Duration};
 
 use bytes::Bytes;
 use futures::future::poll_fn;
+use futures::{Stream, StreamExt};
 use h2::{client::SendRequest, SendStream};
 use http::header::{HeaderValue, CONNECTION, CONTENT_LENGTH, TRANSFER_ENCODING};
 use http::{request::Request, Method, Version};
 
 use crate::codec::{AsyncRead, AsyncWrite};
 use crate::http::body::{BodySize, MessageBody};
+use crate::http::error::PayloadError;
 use crate::http::header::HeaderMap;
 use crate::http::message::{RequestHeadType, ResponseHead};
-use crate::http::payload::Payload;
+use crate::http::payload::{Payload, PayloadStream};
+use crate::rt::time::{delay_for, timeout, Delay};
 
-use super::connection::{ConnectionType, IoConnection};
+use super::connection::{CloseHandle, ConnectionType, IoConnection};
 use super::error::SendRequestError;
 use super::pool::Acquired;
 
@@ -22,6 +29,7 @@ pub(super) async fn send_request<T, B>(
     head: RequestHeadType,
     body: B,
     created: time::Instant,
+    handle: CloseHandle,
     pool: Option<Acquired<T>>,
 ) -> Result<(ResponseHead, Payload), SendRequestError>
 where
@@ -29,6 +37,10 @@ where
     B: MessageBody,
 {
     trace!("Sending client request: {:?} {:?}", head, body.size());
+    let (read_timeout, write_timeout) = pool
+        .as_ref()
+        .map(|pool| pool.io_timeouts())
+        .unwrap_or_default();
     let head_req = head.as_ref().method == Method::HEAD;
     let length = body.size();
     let eof = matches!(
@@ -88,27 +100,41 @@ where
 
     let res = poll_fn(|cx| io.poll_ready(cx)).await;
     if let Err(e) = res {
-        release(io, pool, created, e.is_io());
+        release(io, pool, created, handle, e.is_io());
         return Err(SendRequestError::from(e));
     }
 
     let resp = match io.send_request(req, eof) {
         Ok((fut, send)) => {
-            release(io, pool, created, false);
+            release(io, pool, created, handle, false);
 
             if !eof {
-                send_body(body, send).await?;
+                send_body(body, send, write_timeout).await?;
             }
-            fut.await.map_err(SendRequestError::from)?
+            stalled(fut, read_timeout, SendRequestError::ReadTimeout)
+                .await?
+                .map_err(SendRequestError::from)?
         }
         Err(e) => {
-            release(io, pool, created, e.is_io());
+            release(io, pool, created, handle, e.is_io());
             return Err(e.into());
         }
     };
 
     let (parts, body) = resp.into_parts();
-    let payload = if head_req { Payload::None } else { body.into() };
+    let payload = if head_req {
+        Payload::None
+    } else if read_timeout != Duration::from_secs(0) {
+        let pl: PayloadStream = StallPayload {
+            payload: body.into(),
+            timeout: read_timeout,
+            timer: None,
+        }
+        .boxed_local();
+        pl.into()
+    } else {
+        body.into()
+    };
 
     let mut head = ResponseHead::new(parts.status);
     head.version = parts.version;
@@ -119,6 +145,7 @@ where
 async fn send_body<B: MessageBody>(
     mut body: B,
     mut send: SendStream<Bytes>,
+    write_timeout: Duration,
 ) -> Result<(), SendRequestError> {
     let mut buf = None;
     loop {
@@ -139,7 +166,8 @@ async fn send_body<B: MessageBody>(
             }
         }
 
-        match poll_fn(|cx| send.poll_capacity(cx)).await {
+        let capacity = poll_fn(|cx| send.poll_capacity(cx));
+        match stalled(capacity, write_timeout, SendRequestError::WriteTimeout).await? {
             None => return Ok(()),
             Some(Ok(cap)) => {
                 let b = buf.as_mut().unwrap();
@@ -162,18 +190,70 @@ async fn send_body<B: MessageBody>(
     }
 }
 
+/// Wait for stream operation, fail if it stalls longer than timeout
+async fn stalled<F: Future>(
+    fut: F,
+    dur: Duration,
+    err: SendRequestError,
+) -> Result<F::Output, SendRequestError> {
+    if dur == Duration::from_secs(0) {
+        Ok(fut.await)
+    } else {
+        timeout(dur, fut).await.map_err(|_| err)
+    }
+}
+
+/// Response payload that fails if next chunk stalls longer than read timeout
+struct StallPayload {
+    payload: Payload,
+    timeout: Duration,
+    timer: Option<Delay>,
+}
+
+impl Stream for StallPayload {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        match Pin::new(&mut this.payload).poll_next(cx) {
+            Poll::Pending => {
+                let dur = this.timeout;
+                let timer = this.timer.get_or_insert_with(|| delay_for(dur));
+                if Pin::new(timer).poll(cx).is_ready() {
+                    Poll::Ready(Some(Err(PayloadError::Io(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "Read timeout",
+                    )))))
+                } else {
+                    Poll::Pending
+                }
+            }
+            res => {
+                this.timer = None;
+                res
+            }
+        }
+    }
+}
+
 // release SendRequest object
 fn release<T: AsyncRead + AsyncWrite + Unpin + 'static>(
     io: SendRequest<Bytes>,
     pool: Option<Acquired<T>>,
     created: time::Instant,
+    handle: CloseHandle,
     close: bool,
 ) {
     if let Some(mut pool) = pool {
+        let conn = IoConnection::new(ConnectionType::H2(io), created, handle, None);
         if close {
-            pool.close(IoConnection::new(ConnectionType::H2(io), created, None));
+            pool.close(conn);
         } else {
-            pool.release(IoConnection::new(ConnectionType::H2(io), created, None));
+            pool.release(conn);
         }
     }
 }
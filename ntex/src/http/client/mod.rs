@@ -28,22 +28,28 @@ pub mod error;
 mod frozen;
 mod h1proto;
 mod h2proto;
+mod multipart;
 mod pool;
 mod request;
 mod response;
 mod sender;
 mod test;
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+mod tls;
 pub mod ws;
 
 pub use self::builder::ClientBuilder;
 pub use self::connect::BoxedSocket;
-pub use self::connection::Connection;
+pub use self::connection::{CloseHandle, Connection};
 pub use self::connector::Connector;
 pub use self::frozen::{FrozenClientRequest, FrozenSendBuilder};
+pub use self::multipart::Multipart;
 pub use self::request::ClientRequest;
-pub use self::response::{ClientResponse, JsonBody, MessageBody};
+pub use self::response::{ClientResponse, CopyTo, JsonBody, MessageBody, SaveTo};
 pub use self::sender::SendClientRequest;
 pub use self::test::TestResponse;
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+pub use self::tls::TlsSessionCache;
 
 use crate::http::error::HttpError;
 use crate::http::{HeaderMap, Method, RequestHead, Uri};
@@ -80,6 +86,7 @@ pub(self) struct ClientConfig {
     pub(self) connector: Box<dyn InnerConnect>,
     pub(self) headers: HeaderMap,
     pub(self) timeout: Option<Duration>,
+    pub(self) max_decompressed_size: usize,
 }
 
 impl Default for Client {
@@ -88,6 +95,7 @@ impl Default for Client {
             connector: Box::new(ConnectorWrapper(Connector::default().finish())),
             headers: HeaderMap::new(),
             timeout: Some(Duration::from_secs(5)),
+            max_decompressed_size: 262_144,
         }))
     }
 }
@@ -0,0 +1,405 @@
+//! Multipart form body
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::{Bytes, BytesMut};
+use futures::Stream;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+
+use crate::codec::AsyncRead;
+use crate::http::body::{Body, BodySize, MessageBody};
+
+const READ_BUFFER_SIZE: usize = 8192;
+
+/// `multipart/form-data` request body builder
+///
+/// Parts are sent in order of addition. File parts are streamed, content
+/// is not buffered in memory. Body size is known and `Content-Length`
+/// header is set only if sizes of all parts are known, otherwise body is
+/// sent with chunked transfer encoding.
+///
+/// ```rust,no_run
+/// use ntex::http::client::{Client, Multipart};
+///
+/// #[ntex::main]
+/// async fn main() {
+///     let form = Multipart::new()
+///         .text("name", "value")
+///         .bytes("file", "file.txt", mime::TEXT_PLAIN, "content");
+///
+///     let response = Client::new()
+///         .post("http://127.0.0.1:8080/upload")
+///         .send_multipart(form)
+///         .await;
+/// }
+/// ```
+pub struct Multipart {
+    boundary: String,
+    parts: VecDeque<Part>,
+    size: Option<u64>,
+}
+
+struct Part {
+    head: Bytes,
+    body: PartBody,
+}
+
+enum PartBody {
+    Bytes(Bytes),
+    Stream(Box<dyn Stream<Item = Result<Bytes, Box<dyn Error>>> + Unpin>),
+    Reader(Box<dyn AsyncRead + Unpin>, BytesMut),
+}
+
+impl Default for Multipart {
+    fn default() -> Self {
+        Multipart::new()
+    }
+}
+
+impl Multipart {
+    /// Create multipart form with random boundary
+    pub fn new() -> Self {
+        let boundary: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect();
+
+        Multipart {
+            boundary,
+            parts: VecDeque::new(),
+            size: Some(0),
+        }
+    }
+
+    /// Form boundary
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    /// Value for `Content-Type` header
+    pub fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    /// Add text field
+    pub fn text<N, V>(self, name: N, value: V) -> Self
+    where
+        N: AsRef<str>,
+        V: Into<String>,
+    {
+        let value = Bytes::from(value.into());
+        let size = value.len() as u64;
+        self.part(name.as_ref(), None, Some(size), PartBody::Bytes(value))
+    }
+
+    /// Add file part with in-memory content
+    pub fn bytes<N, F, B>(
+        self,
+        name: N,
+        filename: F,
+        content_type: mime::Mime,
+        data: B,
+    ) -> Self
+    where
+        N: AsRef<str>,
+        F: AsRef<str>,
+        B: Into<Bytes>,
+    {
+        let data = data.into();
+        let size = data.len() as u64;
+        self.part(
+            name.as_ref(),
+            Some((filename.as_ref(), content_type)),
+            Some(size),
+            PartBody::Bytes(data),
+        )
+    }
+
+    /// Add file part with streaming content
+    ///
+    /// `size` must match actual stream size if provided.
+    pub fn stream<N, F, S, E>(
+        self,
+        name: N,
+        filename: F,
+        content_type: mime::Mime,
+        size: Option<u64>,
+        stream: S,
+    ) -> Self
+    where
+        N: AsRef<str>,
+        F: AsRef<str>,
+        S: Stream<Item = Result<Bytes, E>> + Unpin + 'static,
+        E: Error + 'static,
+    {
+        self.part(
+            name.as_ref(),
+            Some((filename.as_ref(), content_type)),
+            size,
+            PartBody::Stream(Box::new(StreamAdapter(stream))),
+        )
+    }
+
+    /// Add file part with content from async reader
+    ///
+    /// `size` must match actual content size if provided.
+    pub fn reader<N, F, R>(
+        self,
+        name: N,
+        filename: F,
+        content_type: mime::Mime,
+        size: Option<u64>,
+        reader: R,
+    ) -> Self
+    where
+        N: AsRef<str>,
+        F: AsRef<str>,
+        R: AsyncRead + Unpin + 'static,
+    {
+        self.part(
+            name.as_ref(),
+            Some((filename.as_ref(), content_type)),
+            size,
+            PartBody::Reader(Box::new(reader), BytesMut::new()),
+        )
+    }
+
+    fn part(
+        mut self,
+        name: &str,
+        file: Option<(&str, mime::Mime)>,
+        size: Option<u64>,
+        body: PartBody,
+    ) -> Self {
+        let mut head = format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"",
+            self.boundary,
+            escape(name)
+        );
+        if let Some((filename, content_type)) = file {
+            head.push_str(&format!(
+                "; filename=\"{}\"\r\nContent-Type: {}",
+                escape(filename),
+                content_type
+            ));
+        }
+        head.push_str("\r\n\r\n");
+
+        // part head, content and trailing crlf
+        self.size = match (self.size, size) {
+            (Some(total), Some(size)) => Some(total + head.len() as u64 + size + 2),
+            _ => None,
+        };
+        self.parts.push_back(Part {
+            head: Bytes::from(head),
+            body,
+        });
+        self
+    }
+}
+
+impl From<Multipart> for Body {
+    fn from(form: Multipart) -> Body {
+        let closing = Bytes::from(format!("--{}--\r\n", form.boundary));
+        let size = match form.size {
+            Some(size) => BodySize::Sized(size + closing.len() as u64),
+            None => BodySize::Stream,
+        };
+        Body::from_message(MultipartBody {
+            size,
+            parts: form.parts,
+            current: None,
+            closing: Some(closing),
+        })
+    }
+}
+
+impl fmt::Debug for Multipart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Multipart")
+            .field("boundary", &self.boundary)
+            .field("parts", &self.parts.len())
+            .field("size", &self.size)
+            .finish()
+    }
+}
+
+/// Escape quotes and line breaks in field name or file name
+fn escape(val: &str) -> String {
+    val.replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+struct StreamAdapter<S>(S);
+
+impl<S, E> Stream for StreamAdapter<S>
+where
+    S: Stream<Item = Result<Bytes, E>> + Unpin,
+    E: Error + 'static,
+{
+    type Item = Result<Bytes, Box<dyn Error>>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.0)
+            .poll_next(cx)
+            .map(|res| res.map(|res| res.map_err(|e| Box::new(e) as Box<dyn Error>)))
+    }
+}
+
+struct MultipartBody {
+    size: BodySize,
+    parts: VecDeque<Part>,
+    current: Option<PartBody>,
+    closing: Option<Bytes>,
+}
+
+impl PartBody {
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        match self {
+            PartBody::Bytes(ref mut data) => {
+                if data.is_empty() {
+                    Poll::Ready(None)
+                } else {
+                    Poll::Ready(Some(Ok(std::mem::take(data))))
+                }
+            }
+            PartBody::Stream(ref mut stream) => Pin::new(stream).poll_next(cx),
+            PartBody::Reader(ref mut reader, ref mut buf) => {
+                buf.reserve(READ_BUFFER_SIZE);
+                match Pin::new(reader).poll_read_buf(cx, buf) {
+                    Poll::Pending => Poll::Pending,
+                    Poll::Ready(Ok(0)) => Poll::Ready(None),
+                    Poll::Ready(Ok(_)) => Poll::Ready(Some(Ok(buf.split().freeze()))),
+                    Poll::Ready(Err(e)) => Poll::Ready(Some(Err(Box::new(e)))),
+                }
+            }
+        }
+    }
+}
+
+impl MessageBody for MultipartBody {
+    fn size(&self) -> BodySize {
+        self.size
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        loop {
+            if let Some(ref mut body) = self.current {
+                return match body.poll_next_chunk(cx) {
+                    Poll::Pending => Poll::Pending,
+                    Poll::Ready(Some(Ok(chunk))) => {
+                        if chunk.is_empty() {
+                            continue;
+                        }
+                        Poll::Ready(Some(Ok(chunk)))
+                    }
+                    Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(e))),
+                    Poll::Ready(None) => {
+                        self.current = None;
+                        Poll::Ready(Some(Ok(Bytes::from_static(b"\r\n"))))
+                    }
+                };
+            }
+
+            return if let Some(part) = self.parts.pop_front() {
+                self.current = Some(part.body);
+                Poll::Ready(Some(Ok(part.head)))
+            } else {
+                Poll::Ready(self.closing.take().map(Ok))
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use futures::future::poll_fn;
+
+    use super::*;
+
+    async fn read_body(mut body: Body) -> Bytes {
+        let mut buf = BytesMut::new();
+        while let Some(chunk) = poll_fn(|cx| body.poll_next_chunk(cx)).await {
+            buf.extend_from_slice(&chunk.unwrap());
+        }
+        buf.freeze()
+    }
+
+    #[ntex_rt::test]
+    async fn test_multipart() {
+        let form = Multipart::new().text("na\"me", "value").bytes(
+            "file",
+            "a.txt",
+            mime::TEXT_PLAIN,
+            "content",
+        );
+        let boundary = form.boundary().to_string();
+        assert_eq!(boundary.len(), 32);
+        assert_eq!(
+            form.content_type(),
+            format!("multipart/form-data; boundary={}", boundary)
+        );
+
+        let expected = format!(
+            "--{b}\r\nContent-Disposition: form-data; name=\"na%22me\"\r\n\r\nvalue\r\n\
+             --{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\
+             Content-Type: text/plain\r\n\r\ncontent\r\n--{b}--\r\n",
+            b = boundary
+        );
+        let body = Body::from(form);
+        assert_eq!(body.size(), BodySize::Sized(expected.len() as u64));
+        assert_eq!(read_body(body).await, Bytes::from(expected));
+    }
+
+    #[ntex_rt::test]
+    async fn test_multipart_streaming() {
+        let form = Multipart::new()
+            .reader(
+                "r",
+                "r.bin",
+                mime::APPLICATION_OCTET_STREAM,
+                Some(4),
+                &b"data"[..],
+            )
+            .stream(
+                "s",
+                "s.txt",
+                mime::TEXT_PLAIN,
+                None,
+                futures::stream::iter(vec![
+                    Ok::<_, io::Error>(Bytes::from_static(b"ab")),
+                    Ok(Bytes::new()),
+                    Ok(Bytes::from_static(b"cd")),
+                ]),
+            );
+        let boundary = form.boundary().to_string();
+
+        let expected = format!(
+            "--{b}\r\nContent-Disposition: form-data; name=\"r\"; filename=\"r.bin\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\ndata\r\n\
+             --{b}\r\nContent-Disposition: form-data; name=\"s\"; filename=\"s.txt\"\r\n\
+             Content-Type: text/plain\r\n\r\nabcd\r\n--{b}--\r\n",
+            b = boundary
+        );
+        let body = Body::from(form);
+        assert_eq!(body.size(), BodySize::Stream);
+        assert_eq!(read_body(body).await, Bytes::from(expected));
+    }
+}
@@ -1,19 +1,21 @@
 use std::cell::RefCell;
 use std::collections::VecDeque;
 use std::future::Future;
+use std::mem;
 use std::pin::Pin;
 use std::rc::Rc;
 use std::task::{Context, Poll};
 use std::time::{Duration, Instant};
 
 use bytes::Bytes;
-use futures::future::{poll_fn, FutureExt, LocalBoxFuture};
+use futures::future::{poll_fn, select, FutureExt, LocalBoxFuture};
 use fxhash::FxHashMap;
 use h2::client::{handshake, Connection, SendRequest};
 use http::uri::Authority;
 
 use crate::channel::pool;
 use crate::codec::{AsyncRead, AsyncWrite};
+use crate::http::message::ResponseHead;
 use crate::http::Protocol;
 use crate::rt::{
     spawn,
@@ -22,7 +24,7 @@ use crate::rt::{
 use crate::service::Service;
 use crate::task::LocalWaker;
 
-use super::connection::{ConnectionType, IoConnection};
+use super::connection::{CloseHandle, ConnectionType, IoConnection};
 use super::error::ConnectError;
 use super::Connect;
 
@@ -41,6 +43,9 @@ type Waiter<Io> = pool::Sender<Result<IoConnection<Io>, ConnectError>>;
 type WaiterReceiver<Io> = pool::Receiver<Result<IoConnection<Io>, ConnectError>>;
 const ZERO: Duration = Duration::from_millis(0);
 
+/// Decides if connection could be reused after response
+pub(super) type ReusePolicy = Rc<dyn Fn(&ResponseHead) -> bool>;
+
 /// Connections pool
 pub(super) struct ConnectionPool<T, Io: 'static>(Rc<T>, Rc<RefCell<Inner<Io>>>);
 
@@ -58,6 +63,11 @@ where
         conn_keep_alive: Duration,
         disconnect_timeout: Duration,
         limit: usize,
+        host_limit: usize,
+        validate_on_checkout: bool,
+        idle_poll: Duration,
+        read_timeout: Duration,
+        write_timeout: Duration,
     ) -> Self {
         let connector = Rc::new(connector);
         let inner = Rc::new(RefCell::new(Inner {
@@ -65,7 +75,13 @@ where
             conn_keep_alive,
             disconnect_timeout,
             limit,
+            host_limit,
+            validate_on_checkout,
+            read_timeout,
+            write_timeout,
+            reuse_policy: None,
             acquired: 0,
+            host_acquired: FxHashMap::default(),
             waiters: VecDeque::new(),
             available: FxHashMap::default(),
             pool: pool::new(),
@@ -74,12 +90,24 @@ where
 
         // start pool support future
         crate::rt::spawn(ConnectionPoolSupport {
+            idle_poll,
+            idle_timer: if idle_poll != ZERO {
+                Some(delay_for(idle_poll))
+            } else {
+                None
+            },
             connector: connector.clone(),
             inner: inner.clone(),
         });
 
         ConnectionPool(connector, inner)
     }
+
+    /// Set connection reuse policy
+    pub(super) fn reuse_policy(self, policy: Option<ReusePolicy>) -> Self {
+        self.1.borrow_mut().reuse_policy = policy;
+        self
+    }
 }
 
 impl<T, Io> Drop for ConnectionPool<T, Io>
@@ -136,13 +164,19 @@ where
             };
 
             // acquire connection
-            match poll_fn(|cx| Poll::Ready(inner.borrow_mut().acquire(&key, cx))).await {
+            let acquire = poll_fn(|cx| {
+                let mut inner = inner.borrow_mut();
+                inner.cleanup();
+                Poll::Ready(inner.acquire(&key, cx))
+            });
+            match acquire.await {
                 // use existing connection
-                Acquire::Acquired(io, created) => {
+                Acquire::Acquired(io, created, handle) => {
                     trace!("Use existing connection for {:?}", req.uri);
                     Ok(IoConnection::new(
                         io,
                         created,
+                        handle,
                         Some(Acquired(key, Some(inner))),
                     ))
                 }
@@ -177,7 +211,7 @@ where
 }
 
 enum Acquire<T> {
-    Acquired(ConnectionType<T>, Instant),
+    Acquired(ConnectionType<T>, Instant, CloseHandle),
     Available,
     NotAvailable,
 }
@@ -186,6 +220,7 @@ struct AvailableConnection<Io> {
     io: ConnectionType<Io>,
     used: Instant,
     created: Instant,
+    handle: CloseHandle,
 }
 
 pub(super) struct Inner<Io> {
@@ -193,7 +228,13 @@ pub(super) struct Inner<Io> {
     conn_keep_alive: Duration,
     disconnect_timeout: Duration,
     limit: usize,
+    host_limit: usize,
+    validate_on_checkout: bool,
+    read_timeout: Duration,
+    write_timeout: Duration,
+    reuse_policy: Option<ReusePolicy>,
     acquired: usize,
+    host_acquired: FxHashMap<Key, usize>,
     available: FxHashMap<Key, VecDeque<AvailableConnection<Io>>>,
     waiters: VecDeque<(Key, Connect, Waiter<Io>)>,
     waker: LocalWaker,
@@ -201,12 +242,29 @@ pub(super) struct Inner<Io> {
 }
 
 impl<Io> Inner<Io> {
-    fn reserve(&mut self) {
+    fn reserve(&mut self, key: &Key) {
         self.acquired += 1;
+        if self.host_limit > 0 {
+            *self.host_acquired.entry(key.clone()).or_insert(0) += 1;
+        }
     }
 
-    fn release(&mut self) {
+    fn release(&mut self, key: &Key) {
         self.acquired -= 1;
+        if self.host_limit > 0 {
+            if let Some(acquired) = self.host_acquired.get_mut(key) {
+                *acquired -= 1;
+                if *acquired == 0 {
+                    self.host_acquired.remove(key);
+                }
+            }
+        }
+    }
+
+    /// check if host reached connections limit
+    fn host_limit_reached(&self, key: &Key) -> bool {
+        self.host_limit > 0
+            && self.host_acquired.get(key).copied().unwrap_or(0) >= self.host_limit
     }
 }
 
@@ -238,14 +296,14 @@ where
     }
 
     fn acquire(&mut self, key: &Key, cx: &mut Context<'_>) -> Acquire<Io> {
-        self.cleanup();
-
         // check limits
-        if self.limit > 0 && self.acquired >= self.limit {
+        if (self.limit > 0 && self.acquired >= self.limit)
+            || self.host_limit_reached(key)
+        {
             return Acquire::NotAvailable;
         }
 
-        self.reserve();
+        self.reserve(key);
 
         // check if open connection is available
         // cleanup stale connections at the same time
@@ -255,47 +313,75 @@ where
                 // check if it still usable
                 if (now - conn.used) > self.conn_keep_alive
                     || (now - conn.created) > self.conn_lifetime
+                    || conn.handle.is_closed()
                 {
                     if let ConnectionType::H1(io) = conn.io {
                         CloseConnection::spawn(io, self.disconnect_timeout);
                     }
-                } else {
-                    let mut io = conn.io;
-                    let mut buf = [0; 2];
-                    if let ConnectionType::H1(ref mut s) = io {
-                        match Pin::new(s).poll_read(cx, &mut buf) {
-                            Poll::Pending => (),
-                            Poll::Ready(Ok(n)) if n > 0 => {
-                                if let ConnectionType::H1(io) = io {
-                                    CloseConnection::spawn(io, self.disconnect_timeout);
-                                }
-                                continue;
-                            }
-                            _ => continue,
-                        }
-                    }
-                    return Acquire::Acquired(io, conn.created);
+                } else if !self.validate_on_checkout {
+                    return Acquire::Acquired(conn.io, conn.created, conn.handle);
+                } else if let Some(io) = probe(conn.io, self.disconnect_timeout, cx) {
+                    return Acquire::Acquired(io, conn.created, conn.handle);
                 }
             }
         }
         Acquire::Available
     }
 
-    fn release_conn(&mut self, key: &Key, io: ConnectionType<Io>, created: Instant) {
-        self.acquired -= 1;
+    /// check idle connections, evict closed and expired connections
+    fn evict_idle(&mut self, cx: &mut Context<'_>) {
+        let now = Instant::now();
+        let conn_keep_alive = self.conn_keep_alive;
+        let conn_lifetime = self.conn_lifetime;
+        let disconnect_timeout = self.disconnect_timeout;
+
+        for connections in self.available.values_mut() {
+            for conn in mem::take(connections) {
+                if (now - conn.used) > conn_keep_alive
+                    || (now - conn.created) > conn_lifetime
+                    || conn.handle.is_closed()
+                {
+                    if let ConnectionType::H1(io) = conn.io {
+                        CloseConnection::spawn(io, disconnect_timeout);
+                    }
+                } else if let Some(io) = probe(conn.io, disconnect_timeout, cx) {
+                    connections.push_back(AvailableConnection { io, ..conn });
+                } else {
+                    trace!("Evict half-closed connection");
+                }
+            }
+        }
+        self.available
+            .retain(|_, connections| !connections.is_empty());
+    }
+
+    fn release_conn(
+        &mut self,
+        key: &Key,
+        io: ConnectionType<Io>,
+        created: Instant,
+        handle: CloseHandle,
+    ) {
+        // closed connection could not be re-used
+        if handle.is_closed() {
+            return self.release_close(key, io);
+        }
+
+        self.release(key);
         self.available
             .entry(key.clone())
             .or_insert_with(VecDeque::new)
             .push_back(AvailableConnection {
                 io,
                 created,
+                handle,
                 used: Instant::now(),
             });
         self.check_availibility();
     }
 
-    fn release_close(&mut self, io: ConnectionType<Io>) {
-        self.acquired -= 1;
+    fn release_close(&mut self, key: &Key, io: ConnectionType<Io>) {
+        self.release(key);
         if let ConnectionType::H1(io) = io {
             CloseConnection::spawn(io, self.disconnect_timeout);
         }
@@ -304,16 +390,46 @@ where
 
     fn check_availibility(&mut self) {
         self.cleanup();
-        if !self.waiters.is_empty() && self.acquired < self.limit {
+        if !self.waiters.is_empty() && (self.limit == 0 || self.acquired < self.limit) {
             self.waker.wake();
         }
     }
 }
 
+/// Check if idle http/1 connection is still usable
+///
+/// Idle connection must not have any data to read, eof or unexpected
+/// data means connection is closed by the peer or broken.
+fn probe<Io>(
+    mut io: ConnectionType<Io>,
+    disconnect_timeout: Duration,
+    cx: &mut Context<'_>,
+) -> Option<ConnectionType<Io>>
+where
+    Io: AsyncRead + AsyncWrite + Unpin + 'static,
+{
+    let mut buf = [0; 2];
+    if let ConnectionType::H1(ref mut s) = io {
+        match Pin::new(s).poll_read(cx, &mut buf) {
+            Poll::Pending => (),
+            Poll::Ready(Ok(n)) if n > 0 => {
+                if let ConnectionType::H1(io) = io {
+                    CloseConnection::spawn(io, disconnect_timeout);
+                }
+                return None;
+            }
+            _ => return None,
+        }
+    }
+    Some(io)
+}
+
 struct ConnectionPoolSupport<T, Io>
 where
     Io: AsyncRead + AsyncWrite + Unpin + 'static,
 {
+    idle_poll: Duration,
+    idle_timer: Option<Delay>,
     connector: T,
     inner: Rc<RefCell<Inner<Io>>>,
 }
@@ -338,27 +454,42 @@ where
         let mut inner = this.inner.as_ref().borrow_mut();
         inner.waker.register(cx.waker());
 
-        // check waiters
-        while let Some((key, _, tx)) = inner.waiters.front() {
+        // check idle connections
+        if let Some(ref mut timer) = this.idle_timer {
+            while Pin::new(&mut *timer).poll(cx).is_ready() {
+                inner.evict_idle(cx);
+                *timer = delay_for(this.idle_poll);
+            }
+        }
+
+        // check waiters, waiters for hosts that reached connections
+        // limit are skipped and keep their position in the queue
+        let mut idx = 0;
+        while let Some((key, _, tx)) = inner.waiters.get(idx) {
             // is waiter still alive
             if tx.is_canceled() {
-                inner.waiters.pop_front();
+                inner.waiters.remove(idx);
                 continue;
             };
+            if inner.host_limit_reached(key) {
+                idx += 1;
+                continue;
+            }
             let key = key.clone();
 
             match inner.acquire(&key, cx) {
                 Acquire::NotAvailable => break,
-                Acquire::Acquired(io, created) => {
-                    let (key, _, tx) = inner.waiters.pop_front().unwrap();
+                Acquire::Acquired(io, created, handle) => {
+                    let (key, _, tx) = inner.waiters.remove(idx).unwrap();
                     let _ = tx.send(Ok(IoConnection::new(
                         io,
                         created,
+                        handle,
                         Some(Acquired(key.clone(), Some(this.inner.clone()))),
                     )));
                 }
                 Acquire::Available => {
-                    let (key, connect, tx) = inner.waiters.pop_front().unwrap();
+                    let (key, connect, tx) = inner.waiters.remove(idx).unwrap();
                     OpenConnection::spawn(
                         key,
                         tx,
@@ -486,16 +617,20 @@ where
             return match Pin::new(h2).poll(cx) {
                 Poll::Ready(Ok((snd, connection))) => {
                     // h2 connection is ready
+                    let handle = CloseHandle::new();
                     let conn = IoConnection::new(
                         ConnectionType::H2(snd),
                         Instant::now(),
+                        handle.clone(),
                         Some(this.guard.take().unwrap().consume()),
                     );
                     if let Err(Ok(conn)) = this.tx.take().unwrap().send(Ok(conn)) {
                         // waiter is gone, return connection to pool
                         conn.release()
                     }
-                    spawn(connection.map(|_| ()));
+                    // dropping connection task cancels all streams
+                    let closed = poll_fn(move |cx| handle.poll_closed(cx));
+                    spawn(select(connection, closed).map(|_| ()));
                     Poll::Ready(())
                 }
                 Poll::Pending => Poll::Pending,
@@ -524,6 +659,7 @@ where
                     let conn = IoConnection::new(
                         ConnectionType::H1(io),
                         Instant::now(),
+                        CloseHandle::new(),
                         Some(this.guard.take().unwrap().consume()),
                     );
                     if let Err(Ok(conn)) = this.tx.take().unwrap().send(Ok(conn)) {
@@ -566,7 +702,7 @@ where
     fn drop(&mut self) {
         if let Some(i) = self.inner.take() {
             let mut inner = i.as_ref().borrow_mut();
-            inner.release();
+            inner.release(&self.key);
             inner.check_availibility();
         }
     }
@@ -580,26 +716,46 @@ where
 {
     pub(super) fn close(&mut self, conn: IoConnection<T>) {
         if let Some(inner) = self.1.take() {
-            let (io, _) = conn.into_inner();
-            inner.as_ref().borrow_mut().release_close(io);
+            let (io, _, _) = conn.into_inner();
+            inner.as_ref().borrow_mut().release_close(&self.0, io);
         }
     }
 
     pub(super) fn release(&mut self, conn: IoConnection<T>) {
         if let Some(inner) = self.1.take() {
-            let (io, created) = conn.into_inner();
+            let (io, created, handle) = conn.into_inner();
             inner
                 .as_ref()
                 .borrow_mut()
-                .release_conn(&self.0, io, created);
+                .release_conn(&self.0, io, created, handle);
         }
     }
+
+    /// Socket read and write stall timeouts
+    pub(super) fn io_timeouts(&self) -> (Duration, Duration) {
+        if let Some(ref inner) = self.1 {
+            let inner = inner.borrow();
+            (inner.read_timeout, inner.write_timeout)
+        } else {
+            (ZERO, ZERO)
+        }
+    }
+
+    /// Check if connection could be reused after response
+    pub(super) fn reuse(&self, head: &ResponseHead) -> bool {
+        if let Some(ref inner) = self.1 {
+            if let Some(ref policy) = inner.borrow().reuse_policy {
+                return policy(head);
+            }
+        }
+        true
+    }
 }
 
 impl<T> Drop for Acquired<T> {
     fn drop(&mut self) {
         if let Some(inner) = self.1.take() {
-            inner.borrow_mut().release();
+            inner.borrow_mut().release(&self.0);
         }
     }
 }
@@ -613,8 +769,9 @@ mod tests {
     use std::time::Duration;
 
     use super::*;
+    use crate::http::body::Body;
     use crate::http::client::Connection;
-    use crate::http::Uri;
+    use crate::http::{RequestHead, Uri};
     use crate::rt::time::delay_for;
     use crate::service::fn_service;
     use crate::testing::Io;
@@ -634,6 +791,11 @@ mod tests {
             Duration::from_secs(10),
             Duration::from_millis(0),
             1,
+            0,
+            true,
+            Duration::from_millis(0),
+            Duration::from_millis(0),
+            Duration::from_millis(0),
         )
         .clone();
 
@@ -680,4 +842,218 @@ mod tests {
         assert!(lazy(|cx| pool.poll_ready(cx)).await.is_ready());
         assert!(lazy(|cx| pool.poll_shutdown(cx, false)).await.is_ready());
     }
+
+    #[ntex_rt::test]
+    async fn test_validate_on_checkout() {
+        let store = Rc::new(RefCell::new(Vec::new()));
+        let store2 = store.clone();
+        let pool = ConnectionPool::new(
+            fn_service(move |req| {
+                let (client, server) = Io::create();
+                store2.borrow_mut().push((req, server));
+                ok((client, Protocol::Http1))
+            }),
+            Duration::from_secs(10),
+            Duration::from_secs(10),
+            Duration::from_millis(0),
+            1,
+            0,
+            true,
+            Duration::from_millis(0),
+            Duration::from_millis(0),
+            Duration::from_millis(0),
+        );
+        let req = Connect {
+            uri: Uri::try_from("http://localhost/test").unwrap(),
+            addr: None,
+        };
+
+        // half-closed connection gets replaced
+        pool.call(req.clone()).await.unwrap().release();
+        store.borrow()[0]
+            .1
+            .read_error(std::io::ErrorKind::ConnectionReset.into());
+        let _conn = pool.call(req.clone()).await.unwrap();
+        assert_eq!(store.borrow().len(), 2);
+
+        let store = Rc::new(RefCell::new(Vec::new()));
+        let store2 = store.clone();
+        let pool = ConnectionPool::new(
+            fn_service(move |req| {
+                let (client, server) = Io::create();
+                store2.borrow_mut().push((req, server));
+                ok((client, Protocol::Http1))
+            }),
+            Duration::from_secs(10),
+            Duration::from_secs(10),
+            Duration::from_millis(0),
+            1,
+            0,
+            false,
+            Duration::from_millis(0),
+            Duration::from_millis(0),
+            Duration::from_millis(0),
+        );
+
+        // validation is disabled
+        pool.call(req.clone()).await.unwrap().release();
+        store.borrow()[0]
+            .1
+            .read_error(std::io::ErrorKind::ConnectionReset.into());
+        let _conn = pool.call(req).await.unwrap();
+        assert_eq!(store.borrow().len(), 1);
+    }
+
+    #[ntex_rt::test]
+    async fn test_idle_poll() {
+        let store = Rc::new(RefCell::new(Vec::new()));
+        let store2 = store.clone();
+        let pool = ConnectionPool::new(
+            fn_service(move |req| {
+                let (client, server) = Io::create();
+                store2.borrow_mut().push((req, server));
+                ok((client, Protocol::Http1))
+            }),
+            Duration::from_secs(10),
+            Duration::from_secs(10),
+            Duration::from_millis(0),
+            2,
+            0,
+            false,
+            Duration::from_millis(25),
+            Duration::from_millis(0),
+            Duration::from_millis(0),
+        );
+        let req = Connect {
+            uri: Uri::try_from("http://localhost/test").unwrap(),
+            addr: None,
+        };
+        let conn1 = pool.call(req.clone()).await.unwrap();
+        let conn2 = pool.call(req.clone()).await.unwrap();
+        conn1.release();
+        conn2.release();
+        let key: Key = req.uri.authority().unwrap().clone().into();
+        assert_eq!(pool.1.borrow().available[&key].len(), 2);
+
+        // peer closed one of idle connections
+        store.borrow()[0].1.write(b"x");
+        delay_for(Duration::from_millis(100)).await;
+        assert_eq!(pool.1.borrow().available[&key].len(), 1);
+
+        store.borrow()[1]
+            .1
+            .read_error(std::io::ErrorKind::ConnectionReset.into());
+        delay_for(Duration::from_millis(100)).await;
+        assert!(pool.1.borrow().available.is_empty());
+    }
+
+    #[ntex_rt::test]
+    async fn test_host_limit() {
+        let store = Rc::new(RefCell::new(Vec::new()));
+        let store2 = store.clone();
+        let pool = ConnectionPool::new(
+            fn_service(move |req| {
+                let (client, server) = Io::create();
+                store2.borrow_mut().push((req, server));
+                ok((client, Protocol::Http1))
+            }),
+            Duration::from_secs(10),
+            Duration::from_secs(10),
+            Duration::from_millis(0),
+            0,
+            1,
+            false,
+            Duration::from_millis(0),
+            Duration::from_millis(0),
+            Duration::from_millis(0),
+        );
+        let req1 = Connect {
+            uri: Uri::try_from("http://host1/test").unwrap(),
+            addr: None,
+        };
+        let req2 = Connect {
+            uri: Uri::try_from("http://host2/test").unwrap(),
+            addr: None,
+        };
+        let conn1 = pool.call(req1.clone()).await.unwrap();
+
+        // host1 is saturated
+        let mut fut1 = pool.call(req1.clone());
+        assert!(lazy(|cx| Pin::new(&mut fut1).poll(cx)).await.is_pending());
+        let mut fut2 = pool.call(req1.clone());
+        assert!(lazy(|cx| Pin::new(&mut fut2).poll(cx)).await.is_pending());
+        assert_eq!(pool.1.borrow().waiters.len(), 2);
+
+        // host2 is not blocked by host1 waiters
+        let conn2 = pool.call(req2.clone()).await.unwrap();
+        assert_eq!(store.borrow().len(), 2);
+        let mut fut3 = pool.call(req2.clone());
+        assert!(lazy(|cx| Pin::new(&mut fut3).poll(cx)).await.is_pending());
+        assert_eq!(pool.1.borrow().waiters.len(), 3);
+
+        // host2 waiter is served while host1 waiters are queued
+        conn2.release();
+        let conn3 = fut3.await.unwrap();
+        assert_eq!(store.borrow().len(), 2);
+        assert_eq!(pool.1.borrow().waiters.len(), 2);
+
+        // host1 waiters are served in order
+        conn1.release();
+        let conn4 = fut1.await.unwrap();
+        assert!(lazy(|cx| Pin::new(&mut fut2).poll(cx)).await.is_pending());
+        assert_eq!(pool.1.borrow().waiters.len(), 1);
+        conn4.release();
+        let _conn5 = fut2.await.unwrap();
+        assert!(pool.1.borrow().waiters.is_empty());
+        assert_eq!(store.borrow().len(), 2);
+        assert_eq!(pool.1.borrow().acquired, 2);
+        drop(conn3);
+        assert_eq!(pool.1.borrow().acquired, 1);
+    }
+
+    #[ntex_rt::test]
+    async fn test_close_handle() {
+        let store = Rc::new(RefCell::new(Vec::new()));
+        let store2 = store.clone();
+        let pool = ConnectionPool::new(
+            fn_service(move |req| {
+                let (client, server) = Io::create();
+                store2.borrow_mut().push((req, server));
+                ok((client, Protocol::Http1))
+            }),
+            Duration::from_secs(10),
+            Duration::from_secs(10),
+            Duration::from_millis(0),
+            0,
+            0,
+            false,
+            Duration::from_millis(0),
+            Duration::from_millis(0),
+            Duration::from_millis(0),
+        );
+        let req = Connect {
+            uri: Uri::try_from("http://localhost/test").unwrap(),
+            addr: None,
+        };
+        let conn = pool.call(req.clone()).await.unwrap();
+        let handle = conn.close_handle();
+        assert!(!handle.is_closed());
+
+        // in-flight request is canceled
+        let handle2 = handle.clone();
+        crate::rt::spawn(async move {
+            delay_for(Duration::from_millis(50)).await;
+            handle2.close();
+        });
+        let res = conn.send_request(RequestHead::default(), Body::Empty).await;
+        assert!(res.is_err());
+        assert!(handle.is_closed());
+
+        // closed connection is not returned to the pool
+        assert!(pool.1.borrow().available.is_empty());
+        assert_eq!(pool.1.borrow().acquired, 0);
+        let conn = pool.call(req).await.unwrap();
+        assert!(!conn.close_handle().is_closed());
+        assert_eq!(store.borrow().len(), 2);
+    }
 }
@@ -11,15 +11,16 @@ use serde::Serialize;
 #[cfg(feature = "cookie")]
 use coo_kie::{Cookie, CookieJar};
 
-use crate::http::body::Body;
+use crate::http::body::{Body, BodySize};
 use crate::http::error::HttpError;
 use crate::http::header::{self, HeaderMap, HeaderName, HeaderValue};
 use crate::http::{
-    uri, ConnectionType, Method, RequestHead, RequestHeadType, Uri, Version,
+    uri, ConnectionType, Method, Payload, RequestHead, RequestHeadType, Uri, Version,
 };
 
 use super::error::{FreezeRequestError, InvalidUrl};
 use super::frozen::FrozenClientRequest;
+use super::multipart::Multipart;
 use super::sender::{PrepForSendingError, SendClientRequest};
 use super::ClientConfig;
 
@@ -201,6 +202,45 @@ impl ClientRequest {
         self
     }
 
+    #[cfg(feature = "preserve-header-case")]
+    /// Append a header, spelling of the name is preserved.
+    ///
+    /// Name is written to the wire exactly as provided, headers are
+    /// written in insertion order, including multiple headers with
+    /// the same name. This applies to http/1 only, http/2 always
+    /// uses lowercase names.
+    ///
+    /// ```rust
+    /// use ntex::http::client::Client;
+    ///
+    /// #[ntex::main]
+    /// async fn main() {
+    ///     let req = Client::new()
+    ///         .post("http://www.rust-lang.org")
+    ///         .insert_header_raw("SOAPAction", "urn:Action");
+    /// }
+    /// ```
+    pub fn insert_header_raw<K, V>(mut self, name: K, value: V) -> Self
+    where
+        K: AsRef<[u8]>,
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: Into<HttpError>,
+    {
+        let name = name.as_ref();
+        match HeaderName::from_bytes(name) {
+            Ok(key) => match HeaderValue::try_from(value) {
+                Ok(value) => self.head.headers.append_raw(
+                    key,
+                    Bytes::copy_from_slice(name),
+                    value,
+                ),
+                Err(e) => self.err = Some(e.into()),
+            },
+            Err(e) => self.err = Some(e.into()),
+        }
+        self
+    }
+
     /// Insert a header only if it is not yet set.
     pub fn set_header_if_none<K, V>(mut self, key: K, value: V) -> Self
     where
@@ -446,6 +486,13 @@ impl ClientRequest {
         )
     }
 
+    /// Set a `multipart/form-data` body and generate `ClientRequest`
+    ///
+    /// `Content-Type` header is set with form boundary.
+    pub fn send_multipart(self, form: Multipart) -> SendClientRequest {
+        self.content_type(form.content_type()).send_body(form)
+    }
+
     /// Set an streaming body and generate `ClientRequest`.
     pub fn send_stream<S, E>(self, stream: S) -> SendClientRequest
     where
@@ -466,6 +513,44 @@ impl ClientRequest {
         )
     }
 
+    /// Stream request or response payload as request body and generate
+    /// `ClientRequest`.
+    ///
+    /// Payload chunks are forwarded as is, without copying. Body size
+    /// is taken from request's `Content-Length` header, otherwise body
+    /// is sent as stream. Payload applies backpressure, peer connection
+    /// is not read until forwarded chunk is consumed by this request.
+    ///
+    /// ```rust,no_run
+    /// use ntex::http::client::Client;
+    /// use ntex::web::{self, types, HttpRequest, HttpResponse};
+    ///
+    /// async fn proxy(
+    ///     req: HttpRequest,
+    ///     payload: types::Payload,
+    /// ) -> Result<HttpResponse, web::Error> {
+    ///     let res = Client::new()
+    ///         .request_from("http://127.0.0.1:8080/", req.head())
+    ///         .send_payload(payload.into_inner())
+    ///         .await?;
+    ///     Ok(HttpResponse::build(res.status()).finish())
+    /// }
+    /// ```
+    pub fn send_payload<P>(self, payload: P) -> SendClientRequest
+    where
+        P: Into<Payload>,
+    {
+        let size = self
+            .head
+            .headers
+            .get(&header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok())
+            .map(BodySize::Sized)
+            .unwrap_or(BodySize::Stream);
+        self.send_body(Body::from_payload(payload.into(), size))
+    }
+
     /// Set an empty body and generate `ClientRequest`.
     pub fn send(self) -> SendClientRequest {
         let slf = match self.prep_for_sending() {
@@ -585,6 +670,34 @@ mod tests {
         assert!(repr.contains("x-test"));
     }
 
+    #[cfg(feature = "preserve-header-case")]
+    #[ntex_rt::test]
+    async fn test_insert_header_raw() {
+        let req = Client::new()
+            .post("/")
+            .insert_header_raw("SOAPAction", "first")
+            .header("x-test", "111")
+            .insert_header_raw(b"soapACTION", "second");
+        assert!(req.err.is_none());
+
+        let headers: Vec<_> = req
+            .headers()
+            .iter_cased()
+            .map(|(_, raw, val)| (raw, val.to_str().unwrap()))
+            .collect();
+        assert_eq!(
+            headers,
+            vec![
+                (&b"SOAPAction"[..], "first"),
+                (&b"x-test"[..], "111"),
+                (&b"soapACTION"[..], "second"),
+            ]
+        );
+
+        let req = Client::new().post("/").insert_header_raw("bad name", "1");
+        assert!(req.err.is_some());
+    }
+
     #[ntex_rt::test]
     async fn test_basics() {
         let mut req = Client::new()
@@ -1,22 +1,40 @@
-use std::cell::{Ref, RefMut};
-use std::fmt;
+use std::cell::{Ref, RefCell, RefMut};
+use std::error::Error;
+use std::io::{self, Write};
 use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
+use std::rc::Rc;
 use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use std::{fmt, fs};
 
+use crate::rt::blocking::BlockingError;
 use bytes::{Bytes, BytesMut};
-use futures::{ready, Future, Stream};
+use futures::{ready, Future, Sink, Stream, StreamExt};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
 use serde::de::DeserializeOwned;
 
 #[cfg(feature = "cookie")]
 use coo_kie::{Cookie, ParseError as CookieParseError};
 
+#[cfg(feature = "cookie")]
+use crate::http::response::CookieIter;
+
+use crate::http::body::{self, BodySize};
 use crate::http::error::PayloadError;
-use crate::http::header::CONTENT_LENGTH;
+use crate::http::header::{
+    HttpDate, RetryAfter, CONTENT_LENGTH, DATE, SEC_WEBSOCKET_PROTOCOL,
+};
 use crate::http::{Extensions, HttpMessage, Payload, PayloadStream, ResponseHead};
 use crate::http::{HeaderMap, StatusCode, Version};
 
-use super::error::JsonPayloadError;
+use super::error::{CopyToError, JsonPayloadError, SaveToError};
+
+/// Trailer headers of a response payload, populated at payload eof
+#[derive(Clone, Default)]
+pub(crate) struct ResponseTrailers(pub(crate) Rc<RefCell<Option<HeaderMap>>>);
 
 /// Client Response
 pub struct ClientResponse<S = PayloadStream> {
@@ -66,7 +84,8 @@ impl<S> ClientResponse<S> {
     }
 
     #[inline]
-    pub(crate) fn head(&self) -> &ResponseHead {
+    /// Returns response head.
+    pub fn head(&self) -> &ResponseHead {
         &self.head
     }
 
@@ -88,6 +107,57 @@ impl<S> ClientResponse<S> {
         &self.head().headers
     }
 
+    /// Returns websocket protocol selected by the server.
+    ///
+    /// This is `Sec-WebSocket-Protocol` header of websocket handshake
+    /// response. `WebsocketsRequest::connect()` verifies that selected
+    /// protocol is one of requested protocols.
+    pub fn selected_protocol(&self) -> Option<&str> {
+        self.head()
+            .headers
+            .get(&SEC_WEBSOCKET_PROTOCOL)
+            .and_then(|hdr| hdr.to_str().ok())
+    }
+
+    /// Returns response's trailer headers.
+    ///
+    /// Trailers are available only after the response payload
+    /// is completely read. Only http/1 chunked responses are supported.
+    pub fn trailers(&self) -> Option<HeaderMap> {
+        self.extensions()
+            .get::<ResponseTrailers>()
+            .and_then(|t| t.0.borrow().clone())
+    }
+
+    #[cfg(feature = "cookie")]
+    /// Returns an iterator over cookies set by the response.
+    ///
+    /// Every `Set-Cookie` header is parsed separately, commas inside
+    /// `Expires` attribute are not treated as cookie separators. Invalid
+    /// cookies are skipped, unlike `HttpMessage::cookies()` which fails
+    /// on the first invalid cookie.
+    pub fn cookies_iter(&self) -> CookieIter<'_> {
+        CookieIter::new(&self.head.headers)
+    }
+
+    /// Returns time to wait before retry from `Retry-After` header.
+    ///
+    /// Servers send `Retry-After` header mostly with `429 Too Many Requests`
+    /// and `503 Service Unavailable` responses. Date value is converted
+    /// to delay relative to response's `Date` header, so clock skew between
+    /// client and server does not matter. Current time is used if response
+    /// has no valid `Date` header.
+    pub fn retry_after(&self) -> Option<Duration> {
+        let retry = self.headers().typed_get::<RetryAfter>()?;
+        let date = self
+            .headers()
+            .get(&DATE)
+            .and_then(|hdr| hdr.to_str().ok())
+            .and_then(|hdr| hdr.parse::<HttpDate>().ok())
+            .unwrap_or_else(HttpDate::now);
+        Some(retry.delay_since(date))
+    }
+
     /// Set a body and return previous body value
     pub fn map_body<F, U>(mut self, f: F) -> ClientResponse<U>
     where
@@ -138,6 +208,26 @@ where
     pub fn json<T: DeserializeOwned>(&mut self) -> JsonBody<S, T> {
         JsonBody::new(self)
     }
+
+    /// Stream http response's body to a file.
+    ///
+    /// Return `SaveTo` future. It resolves to the number of written bytes.
+    /// See [`SaveTo`] for details.
+    pub fn save_to<P: AsRef<Path>>(&mut self, path: P) -> SaveTo<S> {
+        SaveTo::new(self, path.as_ref())
+    }
+
+    /// Copy http response's body to a sink.
+    ///
+    /// Return `CopyTo` future. It resolves to the number of copied bytes.
+    /// See [`CopyTo`] for details.
+    pub fn copy_to<W>(&mut self, sink: W) -> CopyTo<S, W>
+    where
+        W: Sink<Bytes> + Unpin,
+        W::Error: fmt::Debug,
+    {
+        CopyTo::new(self, sink)
+    }
 }
 
 impl<S> Stream for ClientResponse<S>
@@ -154,6 +244,33 @@ where
     }
 }
 
+/// Client response could be used as a response body, for example in a proxy.
+/// Response trailers are forwarded as well.
+impl<S> body::MessageBody for ClientResponse<S>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>> + Unpin,
+{
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        match Pin::new(&mut self.payload).poll_next(cx) {
+            Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(Box::new(e)))),
+            Poll::Ready(Some(Ok(chunk))) => Poll::Ready(Some(Ok(chunk))),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn trailers(&mut self) -> Option<HeaderMap> {
+        ClientResponse::trailers(self)
+    }
+}
+
 impl<S> fmt::Debug for ClientResponse<S> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "\nClientResponse {:?} {}", self.version(), self.status(),)?;
@@ -331,6 +448,339 @@ where
     }
 }
 
+/// Marks response which payload gets decompressed, body size differs
+/// from `Content-Length` header
+#[cfg(feature = "compress")]
+pub(super) struct Decompressed;
+
+/// Future that streams response's payload to a file.
+///
+/// Body is written to a temporary file in the same directory, which is
+/// created exclusively and renamed to destination path once body is
+/// complete, existing destination file is replaced. File operations run
+/// on the blocking thread pool.
+///
+/// Returns error and removes temporary file if payload or file operation
+/// fails, or if body size does not match `Content-Length` header.
+/// Body size is not checked if response payload gets decompressed.
+/// Connection is released to the pool only if body is read completely.
+pub struct SaveTo<S> {
+    path: PathBuf,
+    length: Option<u64>,
+    err: Option<PayloadError>,
+    payload: Option<Payload<S>>,
+    progress: Option<Box<dyn FnMut(u64, Option<u64>)>>,
+    fut: Option<Pin<Box<dyn Future<Output = Result<u64, SaveToError>>>>>,
+}
+
+impl<S> SaveTo<S>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>>,
+{
+    fn new(res: &mut ClientResponse<S>, path: &Path) -> Self {
+        let mut err = None;
+        let mut length = None;
+        // content-length is size of compressed body
+        #[cfg(feature = "compress")]
+        let decompressed = res.extensions().get::<Decompressed>().is_some();
+        #[cfg(not(feature = "compress"))]
+        let decompressed = false;
+        if !decompressed {
+            if let Some(l) = res.headers().get(&CONTENT_LENGTH) {
+                match l.to_str().ok().and_then(|s| s.parse::<u64>().ok()) {
+                    Some(l) => length = Some(l),
+                    None => err = Some(PayloadError::UnknownLength),
+                }
+            }
+        }
+
+        SaveTo {
+            err,
+            length,
+            path: path.to_path_buf(),
+            payload: Some(res.take_payload()),
+            progress: None,
+            fut: None,
+        }
+    }
+
+    /// Set progress callback.
+    ///
+    /// Callback receives number of written bytes and expected body size
+    /// from `Content-Length` header, size is not known for decompressed
+    /// payload. It is called at most once in 100ms
+    /// and once on completion.
+    pub fn progress<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(u64, Option<u64>) + 'static,
+    {
+        self.progress = Some(Box::new(f));
+        self
+    }
+}
+
+impl<S> Future for SaveTo<S>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>> + Unpin + 'static,
+{
+    type Output = Result<u64, SaveToError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        if let Some(err) = this.err.take() {
+            return Poll::Ready(Err(err.into()));
+        }
+
+        if this.fut.is_none() {
+            this.fut = Some(Box::pin(save_to(
+                this.payload.take().unwrap(),
+                std::mem::take(&mut this.path),
+                this.length,
+                this.progress.take(),
+            )));
+        }
+        this.fut.as_mut().unwrap().as_mut().poll(cx)
+    }
+}
+
+/// Future that copies response body to a sink.
+///
+/// Body chunks are sent to the sink as they arrive, next chunk is read
+/// from the payload only when sink is ready to accept it. Sink is flushed
+/// when payload has no data available and once body is complete.
+/// Decompression settings of the request apply to the body.
+///
+/// By default body size is not limited, use `CopyTo::limit()` to set
+/// max size of the body.
+pub struct CopyTo<S, W> {
+    payload: Payload<S>,
+    sink: W,
+    limit: u64,
+    length: Option<u64>,
+    copied: u64,
+    chunk: Option<Bytes>,
+    eof: bool,
+    err: Option<PayloadError>,
+}
+
+impl<S, W> CopyTo<S, W>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>>,
+    W: Sink<Bytes> + Unpin,
+{
+    fn new(res: &mut ClientResponse<S>, sink: W) -> Self {
+        let mut err = None;
+        let mut length = None;
+        if let Some(l) = res.headers().get(&CONTENT_LENGTH) {
+            match l.to_str().ok().and_then(|s| s.parse::<u64>().ok()) {
+                Some(l) => length = Some(l),
+                None => err = Some(PayloadError::UnknownLength),
+            }
+        }
+
+        CopyTo {
+            err,
+            length,
+            sink,
+            payload: res.take_payload(),
+            limit: u64::MAX,
+            copied: 0,
+            chunk: None,
+            eof: false,
+        }
+    }
+
+    /// Change max size of the body.
+    ///
+    /// Future fails with `PayloadError::Overflow` once body exceeds
+    /// the limit.
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.limit = limit;
+        self
+    }
+}
+
+impl<S, W> Future for CopyTo<S, W>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>> + Unpin,
+    W: Sink<Bytes> + Unpin,
+    W::Error: fmt::Debug,
+{
+    type Output = Result<u64, CopyToError<W::Error>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        if let Some(err) = this.err.take() {
+            return Poll::Ready(Err(err.into()));
+        }
+        if let Some(len) = this.length.take() {
+            if len > this.limit {
+                return Poll::Ready(Err(PayloadError::Overflow.into()));
+            }
+        }
+
+        loop {
+            if let Some(chunk) = this.chunk.take() {
+                match Pin::new(&mut this.sink).poll_ready(cx) {
+                    Poll::Ready(Ok(())) => Pin::new(&mut this.sink)
+                        .start_send(chunk)
+                        .map_err(CopyToError::Sink)?,
+                    Poll::Ready(Err(e)) => {
+                        return Poll::Ready(Err(CopyToError::Sink(e)))
+                    }
+                    Poll::Pending => {
+                        this.chunk = Some(chunk);
+                        return Poll::Pending;
+                    }
+                }
+            }
+
+            if this.eof {
+                ready!(Pin::new(&mut this.sink).poll_flush(cx))
+                    .map_err(CopyToError::Sink)?;
+                return Poll::Ready(Ok(this.copied));
+            }
+
+            match Pin::new(&mut this.payload).poll_next(cx) {
+                Poll::Ready(Some(Ok(chunk))) => {
+                    this.copied += chunk.len() as u64;
+                    if this.copied > this.limit {
+                        return Poll::Ready(Err(PayloadError::Overflow.into()));
+                    }
+                    this.chunk = Some(chunk);
+                }
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Err(e.into())),
+                Poll::Ready(None) => this.eof = true,
+                Poll::Pending => {
+                    if let Poll::Ready(Err(e)) = Pin::new(&mut this.sink).poll_flush(cx)
+                    {
+                        return Poll::Ready(Err(CopyToError::Sink(e)));
+                    }
+                    return Poll::Pending;
+                }
+            }
+        }
+    }
+}
+
+const SAVE_BUFFER_SIZE: usize = 65_536;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Temporary file, removed on drop unless persisted
+struct TempFile(Option<PathBuf>);
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if let Some(ref path) = self.0 {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+async fn save_to<S>(
+    mut payload: Payload<S>,
+    path: PathBuf,
+    length: Option<u64>,
+    mut progress: Option<Box<dyn FnMut(u64, Option<u64>)>>,
+) -> Result<u64, SaveToError>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>> + Unpin,
+{
+    let name = path.file_name().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "Path does not name a file")
+    })?;
+    let suffix: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(8)
+        .map(char::from)
+        .collect();
+    let tmp = path.with_file_name(format!(".{}.{}.tmp", name.to_string_lossy(), suffix));
+
+    let tmp_path = tmp.clone();
+    let mut file = block(move || {
+        fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(tmp_path)
+    })
+    .await?;
+    let mut guard = TempFile(Some(tmp));
+
+    let mut received = 0;
+    let mut buf = BytesMut::new();
+    let mut reported = Instant::now();
+
+    loop {
+        let eof = match payload.next().await {
+            Some(chunk) => {
+                let chunk = chunk?;
+                received += chunk.len() as u64;
+                if let Some(expected) = length {
+                    if received > expected {
+                        return Err(SaveToError::Length { expected, received });
+                    }
+                }
+                buf.extend_from_slice(&chunk);
+                false
+            }
+            None => true,
+        };
+
+        if buf.len() >= SAVE_BUFFER_SIZE || (eof && !buf.is_empty()) {
+            let data = buf.split().freeze();
+            file = block(move || file.write_all(&data).map(|_| file)).await?;
+
+            if let Some(ref mut progress) = progress {
+                if !eof && reported.elapsed() >= PROGRESS_INTERVAL {
+                    reported = Instant::now();
+                    (*progress)(received, length);
+                }
+            }
+        }
+
+        if eof {
+            break;
+        }
+    }
+
+    if let Some(expected) = length {
+        if received != expected {
+            return Err(SaveToError::Length { expected, received });
+        }
+    }
+
+    let tmp = guard.0.clone().unwrap();
+    block(move || {
+        file.sync_all()?;
+        fs::rename(tmp, path)
+    })
+    .await?;
+    guard.0 = None;
+
+    if let Some(ref mut progress) = progress {
+        (*progress)(received, length);
+    }
+    Ok(received)
+}
+
+async fn block<F, I>(f: F) -> Result<I, io::Error>
+where
+    F: FnOnce() -> Result<I, io::Error> + Send + 'static,
+    I: Send + 'static,
+{
+    crate::rt::blocking::run(f).await.map_err(|e| match e {
+        BlockingError::Error(e) => e,
+        BlockingError::Canceled => {
+            io::Error::new(io::ErrorKind::Interrupted, "Canceled")
+        }
+        BlockingError::Overloaded => {
+            io::Error::new(io::ErrorKind::Other, "Thread pool queue is full")
+        }
+    })
+}
+
 struct ReadBody<S> {
     stream: Payload<S>,
     buf: BytesMut,
@@ -381,6 +831,70 @@ mod tests {
     use crate::http::client::test::TestResponse;
     use crate::http::header;
 
+    #[cfg(feature = "cookie")]
+    #[test]
+    fn test_cookies() {
+        let res = TestResponse::with_header(
+            header::SET_COOKIE,
+            "id=a=b; Expires=Wed, 21 Oct 2015 07:28:00 GMT; Path=/",
+        )
+        .header(
+            header::SET_COOKIE,
+            "lang=en-US, fr; Expires=Thu, 22 Oct 2015 07:28:00 GMT",
+        )
+        .finish();
+
+        let cookies = res.cookies().unwrap();
+        assert_eq!(cookies.len(), 2);
+        assert_eq!((cookies[0].name(), cookies[0].value()), ("id", "a=b"));
+        assert_eq!(cookies[0].path(), Some("/"));
+        assert_eq!(cookies[1].name(), "lang");
+        assert_eq!(cookies[1].value(), "en-US, fr");
+        assert_eq!(cookies[1].expires().unwrap().day(), 22);
+        drop(cookies);
+        assert_eq!(res.cookie("id").unwrap().value(), "a=b");
+
+        let names: Vec<_> = res.cookies_iter().map(|c| c.name().to_string()).collect();
+        assert_eq!(names, vec!["id", "lang"]);
+
+        // invalid cookie
+        let res = TestResponse::with_header(header::SET_COOKIE, "=value")
+            .header(header::SET_COOKIE, "id=1")
+            .finish();
+        assert!(res.cookies().is_err());
+        let names: Vec<_> = res.cookies_iter().map(|c| c.name().to_string()).collect();
+        assert_eq!(names, vec!["id"]);
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let res = TestResponse::default().finish();
+        assert_eq!(res.retry_after(), None);
+
+        let res = TestResponse::with_header(header::RETRY_AFTER, "30").finish();
+        assert_eq!(res.retry_after(), Some(Duration::from_secs(30)));
+
+        // relative to server date
+        let res = TestResponse::with_header(
+            header::RETRY_AFTER,
+            "Sun, 06 Nov 1994 08:51:37 GMT",
+        )
+        .header(header::DATE, "Sun, 06 Nov 1994 08:49:37 GMT")
+        .finish();
+        assert_eq!(res.retry_after(), Some(Duration::from_secs(120)));
+
+        // without server date, date in the past
+        let res = TestResponse::with_header(
+            header::RETRY_AFTER,
+            "Sun, 06 Nov 1994 08:51:37 GMT",
+        )
+        .finish();
+        assert_eq!(res.retry_after(), Some(Duration::from_secs(0)));
+
+        let res = TestResponse::with_header(header::RETRY_AFTER, "soon").finish();
+        assert_eq!(res.retry_after(), None);
+    }
+
     #[ntex_rt::test]
     async fn test_body() {
         let mut req = TestResponse::with_header(header::CONTENT_LENGTH, "xxxx").finish();
@@ -410,6 +924,59 @@ mod tests {
         }
     }
 
+    #[ntex_rt::test]
+    async fn test_copy_to() {
+        let mut sink: Vec<Bytes> = Vec::new();
+        let mut req = TestResponse::default()
+            .set_payload(Bytes::from_static(b"test"))
+            .finish();
+        assert_eq!(req.copy_to(&mut sink).await.unwrap(), 4);
+        assert_eq!(sink, vec![Bytes::from_static(b"test")]);
+
+        let mut req = TestResponse::with_header(header::CONTENT_LENGTH, "xxxx").finish();
+        match req.copy_to(Vec::<Bytes>::new()).await.err().unwrap() {
+            CopyToError::Payload(PayloadError::UnknownLength) => (),
+            _ => unreachable!("error"),
+        }
+
+        let mut req =
+            TestResponse::with_header(header::CONTENT_LENGTH, "1000000").finish();
+        match req
+            .copy_to(Vec::<Bytes>::new())
+            .limit(10)
+            .await
+            .err()
+            .unwrap()
+        {
+            CopyToError::Payload(PayloadError::Overflow) => (),
+            _ => unreachable!("error"),
+        }
+
+        let mut req = TestResponse::default()
+            .set_payload(Bytes::from_static(b"11111111111111"))
+            .finish();
+        match req
+            .copy_to(Vec::<Bytes>::new())
+            .limit(5)
+            .await
+            .err()
+            .unwrap()
+        {
+            CopyToError::Payload(PayloadError::Overflow) => (),
+            _ => unreachable!("error"),
+        }
+
+        let (tx, rx) = futures::channel::mpsc::channel::<Bytes>(1);
+        drop(rx);
+        let mut req = TestResponse::default()
+            .set_payload(Bytes::from_static(b"test"))
+            .finish();
+        match req.copy_to(tx).await.err().unwrap() {
+            CopyToError::Sink(_) => (),
+            _ => unreachable!("error"),
+        }
+    }
+
     #[derive(Serialize, Deserialize, PartialEq, Debug)]
     struct MyObject {
         name: String,
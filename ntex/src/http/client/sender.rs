@@ -25,6 +25,8 @@ use crate::http::{Payload, PayloadStream};
 
 use super::error::{FreezeRequestError, InvalidUrl, SendRequestError};
 use super::response::ClientResponse;
+#[cfg(feature = "compress")]
+use super::response::Decompressed;
 use super::ClientConfig;
 
 #[derive(Debug, From)]
@@ -58,6 +60,7 @@ pub enum SendClientRequest {
         Pin<Box<dyn Future<Output = Result<ClientResponse, SendRequestError>>>>,
         Option<Delay>,
         bool,
+        usize,
     ),
     Err(Option<SendRequestError>),
 }
@@ -66,10 +69,11 @@ impl SendClientRequest {
     pub(crate) fn new(
         send: Pin<Box<dyn Future<Output = Result<ClientResponse, SendRequestError>>>>,
         response_decompress: bool,
+        max_decompressed_size: usize,
         timeout: Option<Duration>,
     ) -> SendClientRequest {
         let delay = timeout.map(delay_for);
-        SendClientRequest::Fut(send, delay, response_decompress)
+        SendClientRequest::Fut(send, delay, response_decompress, max_decompressed_size)
     }
 }
 
@@ -84,7 +88,7 @@ impl Future for SendClientRequest {
         let this = self.get_mut();
 
         match this {
-            SendClientRequest::Fut(send, delay, _response_decompress) => {
+            SendClientRequest::Fut(send, delay, _response_decompress, _limit) => {
                 if delay.is_some() {
                     match Pin::new(delay.as_mut().unwrap()).poll(cx) {
                         Poll::Pending => (),
@@ -98,10 +102,12 @@ impl Future for SendClientRequest {
                 let res = res.map(|res| {
                     res.map_body(|head, payload| {
                         if *_response_decompress {
-                            Payload::Stream(Decoder::from_headers(
-                                payload,
-                                &head.headers,
-                            ))
+                            let decoder = Decoder::from_headers(payload, &head.headers)
+                                .limit(*_limit);
+                            if decoder.is_decompressing() {
+                                head.extensions_mut().insert(Decompressed);
+                            }
+                            Payload::Stream(decoder)
                         } else {
                             Payload::Stream(Decoder::new(
                                 payload,
@@ -154,6 +160,7 @@ impl RequestHeadType {
         SendClientRequest::new(
             config.connector.send_request(self, body.into(), addr),
             response_decompress,
+            config.max_decompressed_size,
             timeout.or(config.timeout),
         )
     }
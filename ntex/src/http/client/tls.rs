@@ -0,0 +1,337 @@
+use std::borrow::Borrow;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "openssl")]
+use std::{io, mem, pin::Pin, task::Context, task::Poll, time::Duration};
+
+#[cfg(feature = "openssl")]
+use futures::future::TryFutureExt;
+
+#[cfg(feature = "openssl")]
+use crate::codec::{AsyncRead, AsyncWrite};
+#[cfg(feature = "openssl")]
+use crate::connect::openssl::{SslConnector, SslStream};
+#[cfg(feature = "openssl")]
+use crate::connect::{self, Connect as TcpConnect, Connector};
+#[cfg(feature = "openssl")]
+use crate::http::Uri;
+#[cfg(feature = "openssl")]
+use crate::rt::net::TcpStream;
+#[cfg(feature = "openssl")]
+use crate::service::{apply_fn, Service};
+#[cfg(feature = "openssl")]
+use open_ssl::ssl::{SslRef, SslSession};
+
+/// Client tls session cache
+///
+/// Cache keeps tls sessions of closed connections, so new connections to
+/// the same remote authority (`host:port`) could resume session instead
+/// of running full handshake. Each `Connector` has its own cache, cache is
+/// cleared if tls connector gets replaced, so sessions are never resumed
+/// with different tls settings. Cloning handle is cheap.
+///
+/// Openssl sessions are stored when connection gets closed, handshake
+/// counters are maintained for openssl connections only. For rustls cache
+/// is used as client session storage.
+#[derive(Clone)]
+pub struct TlsSessionCache(Arc<Inner>);
+
+struct Inner {
+    capacity: AtomicUsize,
+    resumed: AtomicUsize,
+    full: AtomicUsize,
+    #[cfg(feature = "openssl")]
+    openssl: Mutex<Store<String, SslSession>>,
+    #[cfg(feature = "rustls")]
+    rustls: Mutex<Store<Vec<u8>, Vec<u8>>>,
+}
+
+impl TlsSessionCache {
+    /// Create session cache, zero capacity disables session resumption
+    pub fn new(capacity: usize) -> Self {
+        TlsSessionCache(Arc::new(Inner {
+            capacity: AtomicUsize::new(capacity),
+            resumed: AtomicUsize::new(0),
+            full: AtomicUsize::new(0),
+            #[cfg(feature = "openssl")]
+            openssl: Mutex::new(Store::default()),
+            #[cfg(feature = "rustls")]
+            rustls: Mutex::new(Store::default()),
+        }))
+    }
+
+    /// Max number of cached sessions
+    pub fn capacity(&self) -> usize {
+        self.0.capacity.load(Ordering::Relaxed)
+    }
+
+    /// Number of handshakes with resumed session
+    pub fn resumed(&self) -> usize {
+        self.0.resumed.load(Ordering::Relaxed)
+    }
+
+    /// Number of full handshakes
+    pub fn full_handshakes(&self) -> usize {
+        self.0.full.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn set_capacity(&self, capacity: usize) {
+        self.0.capacity.store(capacity, Ordering::Relaxed);
+        self.clear();
+    }
+
+    /// Remove all cached sessions
+    pub(super) fn clear(&self) {
+        #[cfg(feature = "openssl")]
+        self.0.openssl.lock().unwrap().clear();
+        #[cfg(feature = "rustls")]
+        self.0.rustls.lock().unwrap().clear();
+    }
+
+    #[cfg(feature = "openssl")]
+    fn handshake(&self, resumed: bool) {
+        if resumed {
+            self.0.resumed.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.0.full.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl fmt::Debug for TlsSessionCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsSessionCache")
+            .field("capacity", &self.capacity())
+            .field("resumed", &self.resumed())
+            .field("full_handshakes", &self.full_handshakes())
+            .finish()
+    }
+}
+
+#[cfg(feature = "rustls")]
+impl rust_tls::StoresClientSessions for TlsSessionCache {
+    fn put(&self, key: Vec<u8>, value: Vec<u8>) -> bool {
+        let capacity = self.capacity();
+        self.0.rustls.lock().unwrap().put(key, value, capacity)
+    }
+
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.0.rustls.lock().unwrap().get(key).cloned()
+    }
+}
+
+/// Bounded map, oldest entries are evicted first
+struct Store<K, V> {
+    map: HashMap<K, V>,
+    order: VecDeque<K>,
+}
+
+impl<K, V> Default for Store<K, V> {
+    fn default() -> Self {
+        Store {
+            map: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> Store<K, V> {
+    fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.get(key)
+    }
+
+    fn put(&mut self, key: K, value: V, capacity: usize) -> bool {
+        if capacity == 0 {
+            return false;
+        }
+        if self.map.insert(key.clone(), value).is_none() {
+            self.order.push_back(key);
+        }
+        while self.map.len() > capacity {
+            if let Some(key) = self.order.pop_front() {
+                self.map.remove(&key);
+            }
+        }
+        true
+    }
+
+    fn clear(&mut self) {
+        self.map.clear();
+        self.order.clear();
+    }
+}
+
+#[cfg(feature = "openssl")]
+/// Openssl connect service that resumes cached sessions
+pub(super) fn openssl_connector(
+    connector: SslConnector,
+    tcp: Connector<Uri>,
+    sessions: TlsSessionCache,
+    timeout: Duration,
+) -> impl Service<
+    Request = TcpConnect<Uri>,
+    Response = SessionStream,
+    Error = connect::ConnectError,
+> {
+    apply_fn(tcp, move |req: TcpConnect<Uri>, srv| {
+        let host = req.host().to_string();
+        let openssl = connector.clone();
+        let sessions = sessions.clone();
+        let key = format!("{}:{}", host, req.port());
+
+        srv.call(req).and_then(move |io| {
+            connect::handshake(timeout, handshake(io, host, key, openssl, sessions))
+        })
+    })
+}
+
+#[cfg(feature = "openssl")]
+async fn handshake(
+    io: TcpStream,
+    host: String,
+    key: String,
+    openssl: SslConnector,
+    sessions: TlsSessionCache,
+) -> Result<SessionStream, connect::ConnectError> {
+    trace!("SSL Handshake start for: {:?}", host);
+
+    let mut config = openssl
+        .configure()
+        .map_err(|e| connect::ConnectError::Handshake(Box::new(e)))?;
+    if let Some(session) = sessions.0.openssl.lock().unwrap().get(&key) {
+        // session is created by the same ssl connector,
+        // cache is cleared if connector changes
+        unsafe {
+            let _ = config.set_session(session);
+        }
+    }
+
+    match tokio_openssl::connect(config, &host, io).await {
+        Ok(io) => {
+            trace!("SSL Handshake success: {:?}", host);
+            sessions.handshake(io.ssl().session_reused());
+            Ok(SessionStream { io, key, sessions })
+        }
+        Err(e) => {
+            trace!("SSL Handshake error: {:?}", e);
+            Err(connect::ConnectError::Handshake(Box::new(e)))
+        }
+    }
+}
+
+#[cfg(feature = "openssl")]
+/// Tls stream that stores its session to the cache on close
+///
+/// With tls 1.3 session tickets are received after handshake,
+/// so session could be stored only when connection is not used anymore.
+pub(super) struct SessionStream {
+    io: SslStream<TcpStream>,
+    key: String,
+    sessions: TlsSessionCache,
+}
+
+#[cfg(feature = "openssl")]
+impl SessionStream {
+    pub(super) fn ssl(&self) -> &SslRef {
+        self.io.ssl()
+    }
+}
+
+#[cfg(feature = "openssl")]
+impl Drop for SessionStream {
+    fn drop(&mut self) {
+        if let Some(session) = self.io.ssl().session() {
+            let capacity = self.sessions.capacity();
+            self.sessions.0.openssl.lock().unwrap().put(
+                mem::take(&mut self.key),
+                session.to_owned(),
+                capacity,
+            );
+        }
+    }
+}
+
+#[cfg(feature = "openssl")]
+impl AsyncRead for SessionStream {
+    unsafe fn prepare_uninitialized_buffer(
+        &self,
+        buf: &mut [mem::MaybeUninit<u8>],
+    ) -> bool {
+        self.io.prepare_uninitialized_buffer(buf)
+    }
+
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().io).poll_read(cx, buf)
+    }
+}
+
+#[cfg(feature = "openssl")]
+impl AsyncWrite for SessionStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store() {
+        let mut store = Store::default();
+        assert!(!store.put("a", 1, 0));
+        assert!(store.get("a").is_none());
+
+        assert!(store.put("a", 1, 2));
+        assert!(store.put("b", 2, 2));
+        assert!(store.put("a", 3, 2));
+        assert_eq!(store.get("a"), Some(&3));
+
+        // oldest entry is evicted
+        assert!(store.put("c", 4, 2));
+        assert!(store.get("a").is_none());
+        assert_eq!(store.get("b"), Some(&2));
+        assert_eq!(store.get("c"), Some(&4));
+
+        store.clear();
+        assert!(store.get("b").is_none());
+    }
+
+    #[test]
+    fn test_cache() {
+        let cache = TlsSessionCache::new(16);
+        assert_eq!(cache.capacity(), 16);
+        assert_eq!(cache.resumed(), 0);
+        assert_eq!(cache.full_handshakes(), 0);
+
+        cache.set_capacity(0);
+        assert_eq!(cache.capacity(), 0);
+        assert!(format!("{:?}", cache).contains("capacity: 0"));
+    }
+}
@@ -33,6 +33,7 @@ pub struct WebsocketsRequest {
     addr: Option<SocketAddr>,
     max_size: usize,
     server_mode: bool,
+    require_masking: bool,
     #[cfg(feature = "cookie")]
     cookies: Option<CookieJar>,
     config: Rc<ClientConfig>,
@@ -65,6 +66,7 @@ impl WebsocketsRequest {
             protocols: None,
             max_size: 65_536,
             server_mode: false,
+            require_masking: true,
             #[cfg(feature = "cookie")]
             cookies: None,
         }
@@ -133,6 +135,14 @@ impl WebsocketsRequest {
         self
     }
 
+    /// Require masking direction of incoming frames.
+    ///
+    /// By default client rejects masked frames from the server.
+    pub fn require_masking(mut self, val: bool) -> Self {
+        self.require_masking = val;
+        self
+    }
+
     /// Append a header.
     ///
     /// Header gets appended to existing header.
@@ -290,7 +300,8 @@ impl WebsocketsRequest {
             HeaderValue::from_static("13"),
         );
 
-        if let Some(protocols) = self.protocols.take() {
+        let protocols = self.protocols.take();
+        if let Some(ref protocols) = protocols {
             self.head.headers.insert(
                 header::SEC_WEBSOCKET_PROTOCOL,
                 HeaderValue::try_from(protocols.as_str()).unwrap(),
@@ -311,6 +322,7 @@ impl WebsocketsRequest {
         let head = self.head;
         let max_size = self.max_size;
         let server_mode = self.server_mode;
+        let require_masking = self.require_masking;
 
         let fut = self.config.connector.open_tunnel(head.into(), self.addr);
 
@@ -366,7 +378,7 @@ impl WebsocketsRequest {
                 log::trace!(
                     "Invalid challenge response: expected: {} received: {:?}",
                     encoded,
-                    key
+                    hdr_key
                 );
                 return Err(WsClientError::InvalidChallengeResponse(
                     encoded,
@@ -378,14 +390,31 @@ impl WebsocketsRequest {
             return Err(WsClientError::MissingWebSocketAcceptHeader);
         };
 
+        // selected protocol must be one of requested protocols
+        if let Some(proto) = head.headers.get(&header::SEC_WEBSOCKET_PROTOCOL) {
+            let requested = match (proto.to_str(), protocols) {
+                (Ok(s), Some(protocols)) => {
+                    protocols.split(',').any(|p| p.trim() == s.trim())
+                }
+                _ => false,
+            };
+            if !requested {
+                log::trace!("Unexpected websocket protocol: {:?}", proto);
+                return Err(WsClientError::UnexpectedProtocol(proto.clone()));
+            }
+        }
+
         // response and ws framed
         Ok((
             ClientResponse::new(head, Payload::None),
             framed.map_codec(|_| {
+                let codec = ws::Codec::new()
+                    .max_frame_size(max_size)
+                    .require_masking(require_masking);
                 if server_mode {
-                    ws::Codec::new().max_size(max_size)
+                    codec
                 } else {
-                    ws::Codec::new().max_size(max_size).client_mode()
+                    codec.client_mode()
                 }
             }),
         ))
@@ -1,14 +1,27 @@
 use std::cell::UnsafeCell;
 use std::fmt;
 use std::fmt::Write;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
 use std::ptr::copy_nonoverlapping;
 use std::rc::Rc;
+use std::task::{Context, Poll};
 use std::time::Duration;
+use std::{io, mem};
 
 use bytes::BytesMut;
 use futures::{future, FutureExt};
 use time::OffsetDateTime;
 
+use crate::codec::{AsyncRead, AsyncWrite};
+use crate::http::error::{DispatchError, PanicError, ResponseError};
+use crate::http::header::{HeaderValue, HOST};
+use crate::http::message::{ConnectionType, RequestHead};
+use crate::http::response::Response;
+use crate::http::stats::ServiceStats;
+use crate::http::{StatusCode, Uri, Version};
+use crate::rt::net::TcpStream;
 use crate::rt::time::{delay_for, delay_until, Delay, Instant};
 
 // "Sun, 06 Nov 1994 08:49:37 GMT".len()
@@ -17,14 +30,149 @@ const DATE_VALUE_LENGTH: usize = 29;
 #[derive(Debug, PartialEq, Clone, Copy)]
 /// Server keep-alive setting
 pub enum KeepAlive {
-    /// Keep alive in seconds
+    /// Keep alive in seconds, zero value disables keep-alive
     Timeout(usize),
     /// Relay on OS to shutdown tcp connection
+    ///
+    /// Enables `SO_KEEPALIVE` on accepted tcp connections with system
+    /// default probe settings. Keep-alive timer is not used, idle connections
+    /// stay open until OS detects dead peer. `client_timeout` still applies
+    /// to the first request.
     Os,
+    /// Relay on OS to shutdown tcp connection, with explicit probe settings
+    ///
+    /// Same as `Os`, but configures tcp keep-alive probes.
+    Tcp(TcpKeepalive),
     /// Disabled
     Disabled,
 }
 
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+/// Tcp keep-alive probes settings
+///
+/// Unset values use system defaults. Interval and retries are supported
+/// on linux, android, freebsd, macos and ios, other platforms ignore them.
+pub struct TcpKeepalive {
+    /// Idle time before first keep-alive probe is sent
+    pub time: Option<Duration>,
+    /// Interval between keep-alive probes
+    pub interval: Option<Duration>,
+    /// Number of unacknowledged probes before connection is dropped
+    pub retries: Option<u32>,
+}
+
+impl TcpKeepalive {
+    /// Create keep-alive settings with system defaults
+    pub fn new() -> Self {
+        TcpKeepalive::default()
+    }
+
+    /// Set idle time before first keep-alive probe is sent
+    pub fn time(mut self, time: Duration) -> Self {
+        self.time = Some(time);
+        self
+    }
+
+    /// Set interval between keep-alive probes
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    /// Set number of unacknowledged probes before connection is dropped
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = Some(retries);
+        self
+    }
+
+    /// Enable keep-alive probes on tcp stream
+    pub(super) fn apply(&self, io: &TcpStream) -> io::Result<()> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::io::AsRawFd;
+
+            let fd = io.as_raw_fd();
+            setsockopt(fd, libc::SOL_SOCKET, libc::SO_KEEPALIVE, 1)?;
+            if let Some(time) = self.time {
+                #[cfg(any(target_os = "macos", target_os = "ios"))]
+                let opt = libc::TCP_KEEPALIVE;
+                #[cfg(not(any(target_os = "macos", target_os = "ios")))]
+                let opt = libc::TCP_KEEPIDLE;
+                setsockopt(fd, libc::IPPROTO_TCP, opt, secs(time))?;
+            }
+            #[cfg(any(
+                target_os = "linux",
+                target_os = "android",
+                target_os = "freebsd",
+                target_os = "macos",
+                target_os = "ios"
+            ))]
+            {
+                let tcp = libc::IPPROTO_TCP;
+                if let Some(interval) = self.interval {
+                    setsockopt(fd, tcp, libc::TCP_KEEPINTVL, secs(interval))?;
+                }
+                if let Some(retries) = self.retries {
+                    setsockopt(fd, tcp, libc::TCP_KEEPCNT, retries as libc::c_int)?;
+                }
+            }
+            Ok(())
+        }
+
+        #[cfg(not(unix))]
+        io.set_keepalive(Some(self.time.unwrap_or(Duration::from_secs(7200))))
+    }
+}
+
+#[cfg(unix)]
+fn secs(d: Duration) -> libc::c_int {
+    d.as_secs().max(1).min(libc::c_int::MAX as u64) as libc::c_int
+}
+
+#[cfg(unix)]
+fn setsockopt(
+    fd: libc::c_int,
+    level: libc::c_int,
+    name: libc::c_int,
+    val: libc::c_int,
+) -> io::Result<()> {
+    let res = unsafe {
+        libc::setsockopt(
+            fd,
+            level,
+            name,
+            &val as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if res == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// Enable tcp keep-alive probes on accepted connection, if configured
+pub(super) fn set_tcp_keepalive(io: &TcpStream, ka: Option<&TcpKeepalive>) {
+    if let Some(ka) = ka {
+        if let Err(e) = ka.apply(io) {
+            log::warn!("Cannot set tcp keep-alive on connection: {}", e);
+        }
+    }
+}
+
+impl From<Duration> for TcpKeepalive {
+    fn from(time: Duration) -> Self {
+        TcpKeepalive::new().time(time)
+    }
+}
+
+impl From<TcpKeepalive> for KeepAlive {
+    fn from(keepalive: TcpKeepalive) -> Self {
+        KeepAlive::Tcp(keepalive)
+    }
+}
+
 impl From<usize> for KeepAlive {
     fn from(keepalive: usize) -> Self {
         KeepAlive::Timeout(keepalive)
@@ -41,6 +189,318 @@ impl From<Option<usize>> for KeepAlive {
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// Framing of streaming response bodies for http/1.0 clients
+///
+/// Http/1.0 clients do not support chunked transfer encoding.
+pub enum Http10Body {
+    /// Write body and close connection
+    Close,
+    /// Buffer body up to specified size and send it with `content-length`
+    /// header. If body is larger, fall back to `Close` framing.
+    Buffer(usize),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// Handling of `Expect: 100-continue` requests
+pub enum ExpectContinue {
+    /// Call expect service and send `100 Continue` if request's
+    /// `Content-Length` does not exceed limit, otherwise respond with
+    /// `417 Expectation Failed` and close connection. Zero value
+    /// disables the limit.
+    Continue(u64),
+    /// Do not send `100 Continue`, request is passed to the service
+    /// as is and client sends body after its own timeout.
+    Ignore,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// Handling of http/1 requests with `Expect` header other than `100-continue`
+///
+/// `Expect` header of http/1.0 requests is always ignored.
+pub enum UnknownExpectation {
+    /// Respond with `417 Expectation Failed` and close connection,
+    /// rfc7231 section 5.1.1
+    Reject,
+    /// Pass request to the service, expectation is ignored
+    Ignore,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// Handling of http/1 requests with transfer codings other than `chunked`
+///
+/// `chunked` must be the final transfer coding of a request, otherwise
+/// request is rejected with `400 Bad Request` regardless of this setting.
+pub enum TransferCodings {
+    /// Respond with `501 Not Implemented` and close connection,
+    /// rfc7230 section 3.3.1
+    Reject,
+    /// Decompress `gzip, chunked` and `deflate, chunked` payloads,
+    /// other codings are rejected. Requires `compress` feature,
+    /// without it every coding is rejected.
+    Decompress,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// Handling of http/1 requests above pipelined requests limit
+pub enum PipelineOverflow {
+    /// Stop reading and processing requests until responses are flushed.
+    /// If peer does not read responses within timeout, in milliseconds,
+    /// connection is closed. Peer that waits for its requests to be read
+    /// before reading responses could never make progress. Zero value
+    /// disables timeout.
+    Backpressure(u64),
+    /// Close connection after flushing responses, requests above the limit
+    /// are not processed.
+    Close,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// Handling of empty http/1 request header values
+///
+/// Trailing whitespace of header values is always trimmed, value that
+/// contains only whitespace is empty.
+pub enum EmptyHeaderValue {
+    /// Keep headers with empty values
+    Accept,
+    /// Respond with `400 Bad Request`
+    Reject,
+    /// Trim headers with empty values from request
+    Trim,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// Validation of critical http/1 request headers
+///
+/// `Content-Length` headers with different values and whitespace between
+/// header name and colon are always rejected with `400 Bad Request`,
+/// there is no safe way to correct them.
+pub enum HeaderValidation {
+    /// Respond with `400 Bad Request` to requests with more than one `Host`
+    /// header or with `Host` header that does not match absolute-form
+    /// request target
+    Strict,
+    /// Keep first `Host` header and replace `Host` header that does not match
+    /// absolute-form request target with target's authority. Corrected
+    /// requests get `HeaderAnomalies` in request's extensions.
+    Lenient,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+/// Request headers corrected by lenient header validation
+///
+/// Stored to request's extensions, see `HeaderValidation::Lenient`.
+pub struct HeaderAnomalies {
+    /// Request contains more than one `Host` header
+    pub duplicate_host: bool,
+    /// `Host` header does not match absolute-form request target
+    pub host_mismatch: bool,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// Direction of bytes passed to a wire capture callback
+pub enum WireDirection {
+    /// Bytes read from the peer
+    Read,
+    /// Bytes written to the peer
+    Write,
+}
+
+/// Wire capture callback
+pub(crate) type WireCapture = Rc<dyn Fn(WireDirection, &[u8])>;
+
+/// Io stream that passes all read and written bytes to wire capture callback
+pub(crate) struct WireCaptureIo<T> {
+    io: T,
+    capture: Option<WireCapture>,
+}
+
+impl<T> WireCaptureIo<T> {
+    pub(crate) fn new(io: T, capture: Option<WireCapture>) -> Self {
+        WireCaptureIo { io, capture }
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for WireCaptureIo<T> {
+    unsafe fn prepare_uninitialized_buffer(
+        &self,
+        buf: &mut [mem::MaybeUninit<u8>],
+    ) -> bool {
+        self.io.prepare_uninitialized_buffer(buf)
+    }
+
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let result = Pin::new(&mut this.io).poll_read(cx, buf);
+        if let (Poll::Ready(Ok(n)), Some(capture)) = (&result, &this.capture) {
+            capture(WireDirection::Read, &buf[..*n]);
+        }
+        result
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for WireCaptureIo<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let result = Pin::new(&mut this.io).poll_write(cx, buf);
+        if let (Poll::Ready(Ok(n)), Some(capture)) = (&result, &this.capture) {
+            capture(WireDirection::Write, &buf[..*n]);
+        }
+        result
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_shutdown(cx)
+    }
+}
+
+/// Service error handler
+pub(crate) type ErrorHandler = Rc<dyn Fn(&dyn ResponseError) -> Option<Response>>;
+
+/// Request uri rewrite hook
+pub(crate) type UriRewrite = Rc<dyn Fn(&mut Uri)>;
+
+/// Dispatcher error hook
+pub(crate) type DispatchErrorHook = Rc<dyn Fn(&DispatchError)>;
+
+/// Formatter of dispatcher generated error responses
+pub(crate) type ErrorFormatter = Rc<dyn Fn(StatusCode, &str) -> Response>;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// Body format of error responses generated by http dispatcher
+///
+/// Applies to responses for malformed or too large requests, slow
+/// requests, time to first byte timeouts and captured panics.
+pub enum ErrorFormat {
+    /// Empty body
+    Empty,
+    /// `text/plain` body with error message
+    Text,
+    /// `application/json` body, `{"status": 400, "error": "message"}`
+    Json,
+    /// `text/html` page with status and error message
+    Html,
+}
+
+impl ErrorFormat {
+    pub(super) fn formatter(self) -> Option<ErrorFormatter> {
+        let f: fn(StatusCode, &str) -> Response = match self {
+            ErrorFormat::Empty => return None,
+            ErrorFormat::Text => |status, msg| {
+                Response::build(status)
+                    .content_type("text/plain; charset=utf-8")
+                    .body(msg.to_string())
+            },
+            ErrorFormat::Json => |status, msg| {
+                let body = serde_json::json!({"status": status.as_u16(), "error": msg});
+                Response::build(status)
+                    .content_type("application/json")
+                    .body(body.to_string())
+            },
+            ErrorFormat::Html => |status, msg| {
+                let title = format!(
+                    "{} {}",
+                    status.as_u16(),
+                    status.canonical_reason().unwrap_or("")
+                );
+                Response::build(status)
+                    .content_type("text/html; charset=utf-8")
+                    .body(format!(
+                        "<!DOCTYPE html><html><head><title>{}</title></head>\
+                         <body><h1>{}</h1><p>{}</p></body></html>",
+                        title, title, msg
+                    ))
+            },
+        };
+        Some(Rc::new(f))
+    }
+}
+
+/// Allowlist of request hosts
+///
+/// Patterns are host names without port, `*.example.com` matches any
+/// subdomain of `example.com` and `*` matches any host.
+pub(super) struct AllowedHosts(Vec<String>);
+
+impl AllowedHosts {
+    pub(super) fn new(hosts: Vec<String>) -> Self {
+        AllowedHosts(
+            hosts
+                .into_iter()
+                .map(|h| h.trim_end_matches('.').to_ascii_lowercase())
+                .collect(),
+        )
+    }
+
+    fn is_allowed(&self, host: &str) -> bool {
+        self.0.iter().any(|pattern| {
+            if pattern == "*" {
+                true
+            } else if pattern.starts_with("*.") {
+                host.len() > pattern.len() - 1 && host.ends_with(&pattern[1..])
+            } else {
+                pattern == host
+            }
+        })
+    }
+
+    /// Check request's host, returns response status for rejected requests
+    ///
+    /// Host is taken from request target authority, `Host` header is used
+    /// only if request target has no authority (rfc 7230 section 5.4).
+    /// Http/1.1 and http/2 requests without host are rejected with
+    /// `400 Bad Request`, requests for not allowed hosts with
+    /// `421 Misdirected Request`.
+    pub(super) fn check(&self, head: &RequestHead) -> Option<StatusCode> {
+        let host = if let Some(authority) = head.uri.authority() {
+            authority.as_str()
+        } else if let Some(host) = head.headers.get(&HOST) {
+            match host.to_str() {
+                Ok(host) => host,
+                Err(_) => return Some(StatusCode::BAD_REQUEST),
+            }
+        } else if head.version == Version::HTTP_10 {
+            return None;
+        } else {
+            return Some(StatusCode::BAD_REQUEST);
+        };
+
+        // strip port
+        let host = if host.starts_with('[') {
+            match host.find(']') {
+                Some(idx) => &host[..=idx],
+                None => return Some(StatusCode::BAD_REQUEST),
+            }
+        } else {
+            host.split(':').next().unwrap_or("")
+        };
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+
+        if host.is_empty() {
+            Some(StatusCode::BAD_REQUEST)
+        } else if self.is_allowed(&host) {
+            None
+        } else {
+            Some(StatusCode::MISDIRECTED_REQUEST)
+        }
+    }
+}
+
 /// Http service configuration
 pub struct ServiceConfig(pub(super) Rc<Inner>);
 
@@ -51,6 +511,97 @@ pub(super) struct Inner {
     pub(super) ka_enabled: bool,
     pub(super) timer: DateService,
     pub(super) ssl_handshake_timeout: u64,
+    pub(super) wire_capture: Option<WireCapture>,
+    pub(super) max_requests: usize,
+    pub(super) max_pipelined: usize,
+    pub(super) pipeline_overflow: PipelineOverflow,
+    pub(super) http10_body: Http10Body,
+    pub(super) expect_continue: ExpectContinue,
+    pub(super) unknown_expectation: UnknownExpectation,
+    pub(super) transfer_codings: TransferCodings,
+    pub(super) max_decompressed_size: usize,
+    pub(super) allowed_hosts: Option<Rc<AllowedHosts>>,
+    pub(super) error_handler: Option<ErrorHandler>,
+    pub(super) catch_panic: bool,
+    pub(super) first_byte_timeout: u64,
+    pub(super) payload_timeout: u64,
+    pub(super) max_header_size: usize,
+    pub(super) max_line_size: usize,
+    pub(super) h2_send_buffer: usize,
+    pub(super) verify_digest: bool,
+    pub(super) connection_handle: bool,
+    pub(super) error_formatter: Option<ErrorFormatter>,
+    pub(super) request_timing: bool,
+    pub(super) server_timing: bool,
+    pub(super) empty_header_value: EmptyHeaderValue,
+    pub(super) header_validation: HeaderValidation,
+    pub(super) rate_window: Duration,
+    pub(super) alt_svc: Option<HeaderValue>,
+    pub(super) rewrite_uri: Option<UriRewrite>,
+    pub(super) on_dispatch_error: Option<DispatchErrorHook>,
+    pub(super) tcp_keepalive: Option<TcpKeepalive>,
+    pub(super) stats: ServiceStats,
+}
+
+impl Inner {
+    pub(super) fn new(
+        keep_alive: KeepAlive,
+        client_timeout: u64,
+        client_disconnect: u64,
+        ssl_handshake_timeout: u64,
+    ) -> Inner {
+        let (keep_alive, ka_enabled, tcp_keepalive) = match keep_alive {
+            KeepAlive::Timeout(0) => (0, false, None),
+            KeepAlive::Timeout(val) => (val as u64, true, None),
+            KeepAlive::Os => (0, true, Some(TcpKeepalive::default())),
+            KeepAlive::Tcp(ka) => (0, true, Some(ka)),
+            KeepAlive::Disabled => (0, false, None),
+        };
+        let keep_alive = if ka_enabled && keep_alive > 0 {
+            Some(Duration::from_secs(keep_alive))
+        } else {
+            None
+        };
+
+        Inner {
+            keep_alive,
+            ka_enabled,
+            client_timeout,
+            client_disconnect,
+            ssl_handshake_timeout,
+            timer: DateService::new(),
+            wire_capture: None,
+            max_requests: 0,
+            max_pipelined: 0,
+            pipeline_overflow: PipelineOverflow::Backpressure(5000),
+            http10_body: Http10Body::Close,
+            expect_continue: ExpectContinue::Continue(u64::MAX),
+            unknown_expectation: UnknownExpectation::Reject,
+            transfer_codings: TransferCodings::Reject,
+            max_decompressed_size: 262_144,
+            allowed_hosts: None,
+            error_handler: None,
+            catch_panic: true,
+            first_byte_timeout: 0,
+            payload_timeout: 0,
+            max_header_size: 32_768,
+            max_line_size: 8_192,
+            h2_send_buffer: 16_384,
+            verify_digest: false,
+            connection_handle: false,
+            error_formatter: None,
+            request_timing: false,
+            server_timing: false,
+            empty_header_value: EmptyHeaderValue::Accept,
+            header_validation: HeaderValidation::Strict,
+            rate_window: Duration::from_secs(0),
+            alt_svc: None,
+            rewrite_uri: None,
+            on_dispatch_error: None,
+            tcp_keepalive,
+            stats: ServiceStats::new(),
+        }
+    }
 }
 
 impl Clone for ServiceConfig {
@@ -73,25 +624,258 @@ impl ServiceConfig {
         client_disconnect: u64,
         ssl_handshake_timeout: u64,
     ) -> ServiceConfig {
-        let (keep_alive, ka_enabled) = match keep_alive {
-            KeepAlive::Timeout(val) => (val as u64, true),
-            KeepAlive::Os => (0, true),
-            KeepAlive::Disabled => (0, false),
-        };
-        let keep_alive = if ka_enabled && keep_alive > 0 {
-            Some(Duration::from_secs(keep_alive))
-        } else {
-            None
-        };
-
-        ServiceConfig(Rc::new(Inner {
+        ServiceConfig(Rc::new(Inner::new(
             keep_alive,
-            ka_enabled,
             client_timeout,
             client_disconnect,
             ssl_handshake_timeout,
-            timer: DateService::new(),
-        }))
+        )))
+    }
+
+    /// Server keep-alive setting
+    pub fn keep_alive(&self) -> KeepAlive {
+        let inner = &self.0;
+        if !inner.ka_enabled {
+            KeepAlive::Disabled
+        } else if let Some(ka) = inner.keep_alive {
+            KeepAlive::Timeout(ka.as_secs() as usize)
+        } else {
+            match inner.tcp_keepalive {
+                Some(ka) if ka == TcpKeepalive::default() => KeepAlive::Os,
+                Some(ka) => KeepAlive::Tcp(ka),
+                None => KeepAlive::Disabled,
+            }
+        }
+    }
+
+    /// Tcp keep-alive probes settings of accepted connections
+    pub fn tcp_keepalive(&self) -> Option<TcpKeepalive> {
+        self.0.tcp_keepalive
+    }
+
+    /// Client timeout for first request in milliseconds
+    pub fn client_timeout(&self) -> u64 {
+        self.0.client_timeout
+    }
+
+    /// Connection disconnect timeout in milliseconds
+    pub fn disconnect_timeout(&self) -> u64 {
+        self.0.client_disconnect
+    }
+
+    /// Ssl handshake timeout in milliseconds
+    pub fn ssl_handshake_timeout(&self) -> u64 {
+        self.0.ssl_handshake_timeout
+    }
+
+    /// Time to first byte timeout in milliseconds
+    pub fn first_byte_timeout(&self) -> u64 {
+        self.0.first_byte_timeout
+    }
+
+    /// Request payload timeout in milliseconds
+    pub fn payload_timeout(&self) -> u64 {
+        self.0.payload_timeout
+    }
+
+    /// Max size of http/1 request head
+    pub fn max_header_size(&self) -> usize {
+        self.0.max_header_size
+    }
+
+    /// Max size of http/1 request line
+    pub fn max_request_line_size(&self) -> usize {
+        self.0.max_line_size
+    }
+
+    /// Max size of data queued to http/2 stream
+    pub fn h2_send_buffer_size(&self) -> usize {
+        self.0.h2_send_buffer
+    }
+
+    /// Max number of requests per connection, zero means no limit
+    pub fn max_requests_per_connection(&self) -> usize {
+        self.0.max_requests
+    }
+
+    /// Max number of pipelined http/1 requests, zero means no limit
+    pub fn max_pipelined_requests(&self) -> usize {
+        self.0.max_pipelined
+    }
+
+    /// Handling of http/1 requests above pipelined requests limit
+    pub fn pipeline_overflow(&self) -> PipelineOverflow {
+        self.0.pipeline_overflow
+    }
+
+    /// Handling of http/1.0 requests body
+    pub fn http10_body(&self) -> Http10Body {
+        self.0.http10_body
+    }
+
+    /// Handling of empty http/1 request header values
+    pub fn empty_header_value(&self) -> EmptyHeaderValue {
+        self.0.empty_header_value
+    }
+
+    /// Validation of critical http/1 request headers
+    pub fn header_validation(&self) -> HeaderValidation {
+        self.0.header_validation
+    }
+
+    /// Handling of `Expect: 100-continue` requests
+    pub fn expect_continue(&self) -> ExpectContinue {
+        self.0.expect_continue
+    }
+
+    /// Handling of requests with unknown expectation
+    pub fn unknown_expectation(&self) -> UnknownExpectation {
+        self.0.unknown_expectation
+    }
+
+    /// Handling of requests with transfer codings other than `chunked`
+    pub fn transfer_codings(&self) -> TransferCodings {
+        self.0.transfer_codings
+    }
+
+    /// Max size of decompressed request payload
+    pub fn max_decompressed_size(&self) -> usize {
+        self.0.max_decompressed_size
+    }
+
+    /// Allowed request hosts, `None` if all hosts are allowed
+    ///
+    /// Host patterns are normalized to lowercase.
+    pub fn allowed_hosts(&self) -> Option<&[String]> {
+        self.0
+            .allowed_hosts
+            .as_ref()
+            .map(|hosts| hosts.0.as_slice())
+    }
+
+    /// Live counters of the service
+    pub fn stats(&self) -> &ServiceStats {
+        &self.0.stats
+    }
+
+    /// Check if service panics are converted to error responses
+    pub fn catch_panic(&self) -> bool {
+        self.0.catch_panic
+    }
+
+    /// Check if connection handle is available to services
+    pub fn connection_handle(&self) -> bool {
+        self.0.connection_handle
+    }
+
+    /// Window of request rate tracking, zero duration disables tracking
+    pub fn request_rate_window(&self) -> Duration {
+        self.0.rate_window
+    }
+
+    /// `Alt-Svc` header value added to responses
+    pub fn alt_svc(&self) -> Option<&HeaderValue> {
+        self.0.alt_svc.as_ref()
+    }
+
+    /// Check if request body digest is verified
+    pub fn verify_body_digest(&self) -> bool {
+        self.0.verify_digest
+    }
+
+    /// Check if request timing is recorded
+    pub fn request_timing(&self) -> bool {
+        self.0.request_timing
+    }
+
+    /// Check if `Server-Timing` header is added to responses
+    pub fn server_timing(&self) -> bool {
+        self.0.server_timing
+    }
+}
+
+/// Milliseconds formatted with human friendly units, zero is `disabled`
+struct Millis(u64);
+
+impl fmt::Debug for Millis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            0 => write!(f, "disabled"),
+            ms if ms % 1000 == 0 => write!(f, "{}s", ms / 1000),
+            ms => write!(f, "{}ms", ms),
+        }
+    }
+}
+
+/// Presence of a hook
+struct Hook(bool);
+
+impl fmt::Debug for Hook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", if self.0 { "set" } else { "unset" })
+    }
+}
+
+struct Ka(KeepAlive);
+
+impl fmt::Debug for Ka {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            KeepAlive::Timeout(secs) => Millis(secs as u64 * 1000).fmt(f),
+            KeepAlive::Os => write!(f, "os"),
+            KeepAlive::Tcp(ka) => write!(f, "{:?}", ka),
+            KeepAlive::Disabled => write!(f, "disabled"),
+        }
+    }
+}
+
+impl fmt::Debug for ServiceConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = &self.0;
+        f.debug_struct("ServiceConfig")
+            .field("keep_alive", &Ka(self.keep_alive()))
+            .field("tcp_keepalive", &inner.tcp_keepalive)
+            .field("client_timeout", &Millis(inner.client_timeout))
+            .field("disconnect_timeout", &Millis(inner.client_disconnect))
+            .field(
+                "ssl_handshake_timeout",
+                &Millis(inner.ssl_handshake_timeout),
+            )
+            .field("first_byte_timeout", &Millis(inner.first_byte_timeout))
+            .field("payload_timeout", &Millis(inner.payload_timeout))
+            .field("max_header_size", &inner.max_header_size)
+            .field("max_request_line_size", &inner.max_line_size)
+            .field("h2_send_buffer_size", &inner.h2_send_buffer)
+            .field("max_requests_per_connection", &inner.max_requests)
+            .field("max_pipelined_requests", &inner.max_pipelined)
+            .field("pipeline_overflow", &inner.pipeline_overflow)
+            .field("http10_body", &inner.http10_body)
+            .field("empty_header_value", &inner.empty_header_value)
+            .field("header_validation", &inner.header_validation)
+            .field("expect_continue", &inner.expect_continue)
+            .field("unknown_expectation", &inner.unknown_expectation)
+            .field("transfer_codings", &inner.transfer_codings)
+            .field("max_decompressed_size", &inner.max_decompressed_size)
+            .field("allowed_hosts", &self.allowed_hosts())
+            .field("catch_panic", &inner.catch_panic)
+            .field("connection_handle", &inner.connection_handle)
+            .field(
+                "request_rate_window",
+                &Millis(inner.rate_window.as_millis() as u64),
+            )
+            .field("alt_svc", &inner.alt_svc)
+            .field("verify_body_digest", &inner.verify_digest)
+            .field("request_timing", &inner.request_timing)
+            .field("server_timing", &inner.server_timing)
+            .field("wire_capture", &Hook(inner.wire_capture.is_some()))
+            .field("error_handler", &Hook(inner.error_handler.is_some()))
+            .field("error_formatter", &Hook(inner.error_formatter.is_some()))
+            .field("rewrite_uri", &Hook(inner.rewrite_uri.is_some()))
+            .field(
+                "on_dispatch_error",
+                &Hook(inner.on_dispatch_error.is_some()),
+            )
+            .finish()
     }
 }
 
@@ -104,6 +888,36 @@ pub(super) struct DispatcherConfig<S, X, U> {
     pub(super) client_disconnect: u64,
     pub(super) ka_enabled: bool,
     pub(super) timer: DateService,
+    pub(super) wire_capture: Option<WireCapture>,
+    pub(super) max_requests: usize,
+    pub(super) max_pipelined: usize,
+    pub(super) pipeline_overflow: PipelineOverflow,
+    pub(super) http10_body: Http10Body,
+    pub(super) expect_continue: ExpectContinue,
+    pub(super) unknown_expectation: UnknownExpectation,
+    pub(super) transfer_codings: TransferCodings,
+    #[cfg_attr(not(feature = "compress"), allow(dead_code))]
+    pub(super) max_decompressed_size: usize,
+    pub(super) allowed_hosts: Option<Rc<AllowedHosts>>,
+    pub(super) error_handler: Option<ErrorHandler>,
+    pub(super) catch_panic: bool,
+    pub(super) first_byte_timeout: u64,
+    pub(super) payload_timeout: u64,
+    pub(super) max_header_size: usize,
+    pub(super) max_line_size: usize,
+    pub(super) h2_send_buffer: usize,
+    pub(super) verify_digest: bool,
+    pub(super) connection_handle: bool,
+    pub(super) error_formatter: Option<ErrorFormatter>,
+    pub(super) request_timing: bool,
+    pub(super) server_timing: bool,
+    pub(super) empty_header_value: EmptyHeaderValue,
+    pub(super) header_validation: HeaderValidation,
+    pub(super) rate_window: Duration,
+    pub(super) alt_svc: Option<HeaderValue>,
+    pub(super) rewrite_uri: Option<UriRewrite>,
+    pub(super) on_dispatch_error: Option<DispatchErrorHook>,
+    pub(super) stats: ServiceStats,
 }
 
 impl<S, X, U> DispatcherConfig<S, X, U> {
@@ -122,9 +936,85 @@ impl<S, X, U> DispatcherConfig<S, X, U> {
             client_disconnect: cfg.0.client_disconnect,
             ka_enabled: cfg.0.ka_enabled,
             timer: cfg.0.timer.clone(),
+            wire_capture: cfg.0.wire_capture.clone(),
+            max_requests: cfg.0.max_requests,
+            max_pipelined: cfg.0.max_pipelined,
+            pipeline_overflow: cfg.0.pipeline_overflow,
+            http10_body: cfg.0.http10_body,
+            expect_continue: cfg.0.expect_continue,
+            unknown_expectation: cfg.0.unknown_expectation,
+            transfer_codings: cfg.0.transfer_codings,
+            max_decompressed_size: cfg.0.max_decompressed_size,
+            allowed_hosts: cfg.0.allowed_hosts.clone(),
+            error_handler: cfg.0.error_handler.clone(),
+            catch_panic: cfg.0.catch_panic,
+            first_byte_timeout: cfg.0.first_byte_timeout,
+            payload_timeout: cfg.0.payload_timeout,
+            max_header_size: cfg.0.max_header_size,
+            max_line_size: cfg.0.max_line_size,
+            h2_send_buffer: cfg.0.h2_send_buffer,
+            verify_digest: cfg.0.verify_digest,
+            connection_handle: cfg.0.connection_handle
+                || cfg.0.rate_window != Duration::from_secs(0),
+            error_formatter: cfg.0.error_formatter.clone(),
+            request_timing: cfg.0.request_timing || cfg.0.server_timing,
+            server_timing: cfg.0.server_timing,
+            empty_header_value: cfg.0.empty_header_value,
+            header_validation: cfg.0.header_validation,
+            rate_window: cfg.0.rate_window,
+            alt_svc: cfg.0.alt_svc.clone(),
+            rewrite_uri: cfg.0.rewrite_uri.clone(),
+            on_dispatch_error: cfg.0.on_dispatch_error.clone(),
+            stats: cfg.0.stats.clone(),
         }
     }
 
+    /// Create dispatcher generated error response
+    pub(super) fn format_error(&self, status: StatusCode, msg: &str) -> Response {
+        format_error(self.error_formatter.as_ref(), status, msg)
+    }
+
+    /// Apply request uri rewrite hook
+    ///
+    /// Rewrite is discarded if new uri does not have absolute path.
+    pub(super) fn rewrite_uri(&self, uri: &mut Uri) {
+        if let Some(ref rewrite) = self.rewrite_uri {
+            let mut new_uri = uri.clone();
+            rewrite(&mut new_uri);
+            if new_uri.path().starts_with('/') {
+                *uri = new_uri;
+            } else {
+                log::warn!("Uri rewrite produced invalid uri {:?}, ignored", new_uri);
+            }
+        }
+    }
+
+    /// Check request's host against allowed hosts, returns error
+    /// response for rejected requests
+    pub(super) fn check_host(&self, head: &RequestHead) -> Option<Response> {
+        let status = self.allowed_hosts.as_ref()?.check(head)?;
+        let msg = if status == StatusCode::BAD_REQUEST {
+            "Invalid host"
+        } else {
+            "Host is not allowed"
+        };
+        let mut res = self.format_error(status, msg);
+        res.head_mut().set_connection_type(ConnectionType::Close);
+        Some(res)
+    }
+
+    /// Pass dispatcher error to the error hook
+    pub(super) fn dispatch_error(&self, err: &DispatchError) {
+        if let Some(ref hook) = self.on_dispatch_error {
+            hook(err);
+        }
+    }
+
+    /// Check if connection served maximum number of requests
+    pub(super) fn max_requests_reached(&self, requests: usize) -> bool {
+        self.max_requests != 0 && requests >= self.max_requests
+    }
+
     /// Return state of connection keep-alive functionality
     pub(super) fn keep_alive_enabled(&self) -> bool {
         self.ka_enabled
@@ -142,6 +1032,40 @@ impl<S, X, U> DispatcherConfig<S, X, U> {
         }
     }
 
+    /// Time to first byte timer, starts when request is received.
+    pub(super) fn first_byte_timer(&self) -> Option<Delay> {
+        let delay_time = self.first_byte_timeout;
+        if delay_time != 0 {
+            Some(delay_until(
+                self.timer.now() + Duration::from_millis(delay_time),
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Request payload timer, starts when request head is received.
+    pub(super) fn payload_timer(&self) -> Option<Delay> {
+        let delay_time = self.payload_timeout;
+        if delay_time != 0 {
+            Some(delay_until(
+                self.timer.now() + Duration::from_millis(delay_time),
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Pipelined responses flush timer, starts when reading is paused.
+    pub(super) fn pipeline_timer(&self) -> Option<Delay> {
+        match self.pipeline_overflow {
+            PipelineOverflow::Backpressure(delay_time) if delay_time != 0 => Some(
+                delay_until(self.timer.now() + Duration::from_millis(delay_time)),
+            ),
+            _ => None,
+        }
+    }
+
     /// Client disconnect timer
     pub(super) fn client_disconnect_timer(&self) -> Option<Instant> {
         let delay = self.client_disconnect;
@@ -180,6 +1104,80 @@ impl<S, X, U> DispatcherConfig<S, X, U> {
     }
 }
 
+/// Create response for service error
+///
+/// Error handler response is used if handler is set and returns
+/// response, otherwise default `ResponseError` mapping is used.
+pub(super) fn error_response<E: ResponseError>(
+    handler: Option<&ErrorHandler>,
+    err: E,
+) -> Response {
+    if let Some(res) = handler.and_then(|handler| handler(&err)) {
+        res
+    } else {
+        err.into()
+    }
+}
+
+/// Run service call, panic is captured if `catch` is enabled
+pub(super) fn call_service<F, R>(catch: bool, f: F) -> Result<R, PanicError>
+where
+    F: FnOnce() -> R,
+{
+    if catch {
+        panic::catch_unwind(AssertUnwindSafe(f)).map_err(PanicError::new)
+    } else {
+        Ok(f())
+    }
+}
+
+/// Poll service future, panic is captured if `catch` is enabled
+pub(super) fn poll_service<F: Future>(
+    catch: bool,
+    fut: Pin<&mut F>,
+    cx: &mut Context<'_>,
+) -> Poll<Result<F::Output, PanicError>> {
+    match call_service(catch, || fut.poll(cx)) {
+        Ok(Poll::Ready(res)) => Poll::Ready(Ok(res)),
+        Ok(Poll::Pending) => Poll::Pending,
+        Err(err) => Poll::Ready(Err(err)),
+    }
+}
+
+/// Create response for service panic
+pub(super) fn panic_response(
+    handler: Option<&ErrorHandler>,
+    formatter: Option<&ErrorFormatter>,
+    uri: &crate::http::Uri,
+    err: PanicError,
+) -> Response {
+    error!("Service panicked while handling {}: {}", uri, err.message());
+    if let Some(res) = handler.and_then(|handler| handler(&err)) {
+        res
+    } else if formatter.is_some() {
+        format_error(
+            formatter,
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Internal server error",
+        )
+    } else {
+        err.error_response()
+    }
+}
+
+/// Create dispatcher generated error response
+pub(super) fn format_error(
+    formatter: Option<&ErrorFormatter>,
+    status: StatusCode,
+    msg: &str,
+) -> Response {
+    if let Some(formatter) = formatter {
+        formatter(status, msg)
+    } else {
+        Response::new(status)
+    }
+}
+
 #[derive(Copy, Clone)]
 pub(super) struct Date {
     pub(super) bytes: [u8; DATE_VALUE_LENGTH],
@@ -300,6 +1298,73 @@ impl DateService {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_allowed_hosts() {
+        let hosts = AllowedHosts::new(vec![
+            "Example.com.".to_string(),
+            "*.example.org".to_string(),
+            "[::1]".to_string(),
+        ]);
+
+        let check = |version, host: Option<&'static str>, uri: &str| {
+            let mut head = RequestHead::default();
+            head.version = version;
+            head.uri = uri.parse().unwrap();
+            if let Some(host) = host {
+                head.headers.insert(HOST, HeaderValue::from_static(host));
+            }
+            hosts.check(&head)
+        };
+
+        assert_eq!(check(Version::HTTP_11, Some("example.com"), "/"), None);
+        assert_eq!(check(Version::HTTP_11, Some("EXAMPLE.com:8080"), "/"), None);
+        assert_eq!(check(Version::HTTP_11, Some("a.b.example.org"), "/"), None);
+        assert_eq!(check(Version::HTTP_11, Some("[::1]:8080"), "/"), None);
+        assert_eq!(check(Version::HTTP_2, None, "https://example.com/"), None);
+        assert_eq!(check(Version::HTTP_10, None, "/"), None);
+
+        assert_eq!(
+            check(Version::HTTP_11, Some("example.org"), "/"),
+            Some(StatusCode::MISDIRECTED_REQUEST)
+        );
+        assert_eq!(
+            check(Version::HTTP_11, Some("evil.com"), "/"),
+            Some(StatusCode::MISDIRECTED_REQUEST)
+        );
+        assert_eq!(
+            check(Version::HTTP_11, Some("badexample.org"), "/"),
+            Some(StatusCode::MISDIRECTED_REQUEST)
+        );
+        assert_eq!(
+            check(Version::HTTP_11, None, "/"),
+            Some(StatusCode::BAD_REQUEST)
+        );
+        assert_eq!(
+            check(Version::HTTP_11, Some(":80"), "/"),
+            Some(StatusCode::BAD_REQUEST)
+        );
+
+        // absolute-form request target authority overrides host header
+        assert_eq!(
+            check(Version::HTTP_11, Some("example.com"), "http://evil.com/"),
+            Some(StatusCode::MISDIRECTED_REQUEST)
+        );
+        assert_eq!(
+            check(
+                Version::HTTP_11,
+                Some("evil.com"),
+                "http://example.com:8080/"
+            ),
+            None
+        );
+
+        let any = AllowedHosts::new(vec!["*".to_string()]);
+        let mut head = RequestHead::default();
+        head.headers
+            .insert(HOST, HeaderValue::from_static("anything.com"));
+        assert_eq!(any.check(&head), None);
+    }
+
     #[test]
     fn test_date_len() {
         assert_eq!(DATE_VALUE_LENGTH, "Sun, 06 Nov 1994 08:49:37 GMT".len());
@@ -315,6 +1380,40 @@ mod tests {
         assert_eq!(buf1, buf2);
     }
 
+    #[ntex_rt::test]
+    async fn test_error_format() {
+        use crate::http::body::{Body, ResponseBody};
+        use crate::http::header::CONTENT_TYPE;
+
+        assert!(ErrorFormat::Empty.formatter().is_none());
+        let res = format_error(None, StatusCode::BAD_REQUEST, "msg");
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert!(res.headers().get(CONTENT_TYPE).is_none());
+
+        let check = |format: ErrorFormat, ct: &str, body: &str| {
+            let f = format.formatter();
+            let res = format_error(f.as_ref(), StatusCode::REQUEST_TIMEOUT, "msg");
+            assert_eq!(res.status(), StatusCode::REQUEST_TIMEOUT);
+            assert_eq!(res.headers().get(CONTENT_TYPE).unwrap(), ct);
+            match res.body() {
+                ResponseBody::Body(Body::Bytes(b)) => assert_eq!(b, body),
+                _ => panic!(),
+            }
+        };
+        check(ErrorFormat::Text, "text/plain; charset=utf-8", "msg");
+        check(
+            ErrorFormat::Json,
+            "application/json",
+            r#"{"error":"msg","status":408}"#,
+        );
+        check(
+            ErrorFormat::Html,
+            "text/html; charset=utf-8",
+            "<!DOCTYPE html><html><head><title>408 Request Timeout</title></head>\
+             <body><h1>408 Request Timeout</h1><p>msg</p></body></html>",
+        );
+    }
+
     #[test]
     fn keep_alive() {
         assert_eq!(KeepAlive::Disabled, Option::<usize>::None.into());
@@ -0,0 +1,259 @@
+use std::cell::Cell;
+use std::fmt;
+use std::rc::Rc;
+use std::task::Waker;
+use std::time::{Duration, Instant};
+
+use crate::task::LocalWaker;
+
+/// Connection-scoped requests state
+///
+/// Handle is created once per connection if it is enabled with
+/// `HttpServiceBuilder::connection_handle()` and gets stored to the
+/// request's extensions, so it could be used from middlewares or stored
+/// for later use. Cloning handle is cheap.
+///
+/// ```rust
+/// use ntex::http::ConnectionHandle;
+/// use ntex::web::{self, HttpRequest, HttpResponse};
+///
+/// async fn index(req: HttpRequest) -> HttpResponse {
+///     if let Some(handle) = req.extensions().get::<ConnectionHandle>() {
+///         // close connection after this response
+///         handle.drain();
+///     }
+///     HttpResponse::Ok().finish()
+/// }
+/// ```
+///
+/// If request rate window is set with `HttpServiceBuilder::request_rate_window()`,
+/// handle tracks number of requests received within the window.
+///
+/// ```rust
+/// use ntex::http::ConnectionHandle;
+/// use ntex::web::{self, HttpRequest, HttpResponse};
+///
+/// async fn index(req: HttpRequest) -> HttpResponse {
+///     let rate = req
+///         .extensions()
+///         .get::<ConnectionHandle>()
+///         .and_then(|handle| handle.request_rate())
+///         .unwrap_or(0.0);
+///     if rate > 100.0 {
+///         HttpResponse::TooManyRequests().finish()
+///     } else {
+///         HttpResponse::Ok().finish()
+///     }
+/// }
+/// ```
+#[derive(Clone)]
+pub struct ConnectionHandle(Rc<Inner>);
+
+struct Inner {
+    started: Cell<usize>,
+    finished: Cell<usize>,
+    drain: Cell<bool>,
+    waker: LocalWaker,
+    rate: Option<RequestRate>,
+}
+
+/// Sliding window request counter
+///
+/// Number of requests in the window is estimated from counters
+/// of current and previous fixed windows, previous counter is weighted
+/// by the part of the sliding window that overlaps previous window.
+struct RequestRate {
+    window: Duration,
+    start: Cell<Instant>,
+    prev: Cell<u32>,
+    current: Cell<u32>,
+}
+
+impl RequestRate {
+    fn new(window: Duration, now: Instant) -> Self {
+        RequestRate {
+            window,
+            start: Cell::new(now),
+            prev: Cell::new(0),
+            current: Cell::new(0),
+        }
+    }
+
+    /// Move fixed windows forward, returns time elapsed in current window
+    fn advance(&self, now: Instant) -> Duration {
+        let start = self.start.get();
+        let elapsed = now.saturating_duration_since(start);
+        if elapsed < self.window {
+            elapsed
+        } else if elapsed < self.window * 2 {
+            self.prev.set(self.current.replace(0));
+            self.start.set(start + self.window);
+            elapsed - self.window
+        } else {
+            self.prev.set(0);
+            self.current.set(0);
+            self.start.set(now);
+            Duration::from_secs(0)
+        }
+    }
+
+    fn record(&self, now: Instant) {
+        self.advance(now);
+        self.current.set(self.current.get().saturating_add(1));
+    }
+
+    fn rate(&self, now: Instant) -> f64 {
+        let elapsed = self.advance(now).as_secs_f64();
+        let weight = 1.0 - elapsed / self.window.as_secs_f64();
+        f64::from(self.prev.get()) * weight + f64::from(self.current.get())
+    }
+}
+
+impl ConnectionHandle {
+    pub(super) fn new(rate_window: Duration) -> Self {
+        let rate = if rate_window != Duration::from_secs(0) {
+            Some(RequestRate::new(rate_window, Instant::now()))
+        } else {
+            None
+        };
+        ConnectionHandle(Rc::new(Inner {
+            rate,
+            started: Cell::new(0),
+            finished: Cell::new(0),
+            drain: Cell::new(false),
+            waker: LocalWaker::new(),
+        }))
+    }
+
+    /// Number of requests received on the connection
+    pub fn requests_started(&self) -> usize {
+        self.0.started.get()
+    }
+
+    /// Number of requests with completely sent responses
+    pub fn requests_finished(&self) -> usize {
+        self.0.finished.get()
+    }
+
+    /// Number of requests received on the connection within request
+    /// rate window
+    ///
+    /// Returns `None` if request rate is not tracked.
+    pub fn request_rate(&self) -> Option<f64> {
+        self.0.rate.as_ref().map(|rate| rate.rate(Instant::now()))
+    }
+
+    /// Request rate window
+    pub fn request_rate_window(&self) -> Option<Duration> {
+        self.0.rate.as_ref().map(|rate| rate.window)
+    }
+
+    /// Check if connection is draining
+    pub fn is_draining(&self) -> bool {
+        self.0.drain.get()
+    }
+
+    /// Stop accepting new requests on the connection.
+    ///
+    /// Http/1 connection sends next response with `Connection: close`
+    /// header and closes connection after it, idle connection gets closed
+    /// immediately. Http/2 connection sends `GOAWAY` frame and closes
+    /// connection after current streams complete.
+    pub fn drain(&self) {
+        if !self.0.drain.replace(true) {
+            self.0.waker.wake();
+        }
+    }
+
+    pub(super) fn request_started(&self) {
+        self.0.started.set(self.0.started.get() + 1);
+        if let Some(ref rate) = self.0.rate {
+            rate.record(Instant::now());
+        }
+    }
+
+    pub(super) fn request_finished(&self) {
+        let finished = self.0.finished.get();
+        if finished < self.0.started.get() {
+            self.0.finished.set(finished + 1);
+        }
+    }
+
+    /// Register connection task, it gets woken up on drain
+    pub(super) fn register(&self, waker: &Waker) {
+        self.0.waker.register(waker);
+    }
+}
+
+impl fmt::Debug for ConnectionHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectionHandle")
+            .field("requests_started", &self.requests_started())
+            .field("requests_finished", &self.requests_finished())
+            .field("draining", &self.is_draining())
+            .field("request_rate", &self.request_rate())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handle() {
+        let handle = ConnectionHandle::new(Duration::from_secs(0));
+        assert!(!handle.is_draining());
+        assert_eq!(handle.request_rate(), None);
+        assert_eq!(handle.request_rate_window(), None);
+
+        // response without request is not counted
+        handle.request_finished();
+        assert_eq!(handle.requests_finished(), 0);
+
+        handle.request_started();
+        handle.clone().request_started();
+        handle.request_finished();
+        assert_eq!(handle.requests_started(), 2);
+        assert_eq!(handle.requests_finished(), 1);
+
+        handle.clone().drain();
+        assert!(handle.is_draining());
+        assert!(format!("{:?}", handle).contains("draining: true"));
+    }
+
+    #[test]
+    fn test_request_rate() {
+        let window = Duration::from_secs(8);
+        let start = Instant::now();
+        let secs = |n| start + Duration::from_secs(n);
+        let rate = RequestRate::new(window, start);
+        assert_eq!(rate.rate(start), 0.0);
+
+        for _ in 0..4 {
+            rate.record(secs(1));
+        }
+        assert_eq!(rate.rate(secs(2)), 4.0);
+
+        // previous window is weighted by overlap with sliding window
+        rate.record(secs(10));
+        assert_eq!(rate.rate(secs(10)), 4.0 * 0.75 + 1.0);
+        assert_eq!(rate.rate(secs(12)), 4.0 * 0.5 + 1.0);
+        assert_eq!(rate.rate(secs(16)), 1.0);
+        assert_eq!(rate.rate(secs(20)), 0.5);
+
+        // no requests for two windows
+        assert_eq!(rate.rate(secs(40)), 0.0);
+        rate.record(secs(41));
+        assert_eq!(rate.rate(secs(41)), 1.0);
+
+        // time before window start
+        assert_eq!(rate.rate(start), 1.0);
+
+        let handle = ConnectionHandle::new(window);
+        handle.request_started();
+        handle.request_started();
+        assert_eq!(handle.request_rate(), Some(2.0));
+        assert_eq!(handle.request_rate_window(), Some(window));
+    }
+}
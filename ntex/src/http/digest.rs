@@ -0,0 +1,205 @@
+//! Request body digest verification
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures::Stream;
+use sha2::Digest;
+
+use super::error::PayloadError;
+use super::header::{HeaderMap, HeaderName};
+use super::payload::Payload;
+use super::request::Request;
+
+/// Wrap request payload with digest verification
+///
+/// Payload is verified against `Digest` header with `sha-256` or `md5`
+/// algorithm or against `Content-MD5` header, `sha-256` is preferred.
+/// Requests without supported digest are not changed.
+pub(super) fn verify_body(req: &mut Request) {
+    if let Some(expected) = Expected::from_headers(req.headers()) {
+        let payload = req.take_payload();
+        *req.payload() = Payload::Stream(Box::pin(DigestPayload {
+            payload,
+            hasher: expected.hasher(),
+            expected,
+            verified: false,
+        }));
+    }
+}
+
+enum Expected {
+    Sha256(Vec<u8>),
+    Md5(Vec<u8>),
+}
+
+impl Expected {
+    fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let mut md5 = None;
+        for hdr in headers.get_all(HeaderName::from_static("digest")) {
+            let hdr = if let Ok(hdr) = hdr.to_str() {
+                hdr
+            } else {
+                continue;
+            };
+            for item in hdr.split(',') {
+                let mut parts = item.trim().splitn(2, '=');
+                let alg = parts.next().unwrap_or("");
+                let value = parts.next().unwrap_or("");
+                if alg.eq_ignore_ascii_case("sha-256") {
+                    return Some(Expected::Sha256(decode(value)));
+                } else if alg.eq_ignore_ascii_case("md5") {
+                    md5 = Some(decode(value));
+                }
+            }
+        }
+        if md5.is_none() {
+            md5 = headers
+                .get(HeaderName::from_static("content-md5"))
+                .map(|hdr| decode(hdr.to_str().unwrap_or("")));
+        }
+        md5.map(Expected::Md5)
+    }
+
+    fn hasher(&self) -> Hasher {
+        match self {
+            Expected::Sha256(_) => Hasher::Sha256(sha2::Sha256::new()),
+            Expected::Md5(_) => Hasher::Md5(md5::Md5::new()),
+        }
+    }
+
+    fn matches(&self, digest: &[u8]) -> bool {
+        match self {
+            Expected::Sha256(val) | Expected::Md5(val) => val.as_slice() == digest,
+        }
+    }
+}
+
+/// Malformed digest values are decoded to empty value, which never matches
+fn decode(value: &str) -> Vec<u8> {
+    base64::decode(value.trim()).unwrap_or_default()
+}
+
+enum Hasher {
+    Sha256(sha2::Sha256),
+    Md5(md5::Md5),
+}
+
+impl Hasher {
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha256(h) => h.update(data),
+            Hasher::Md5(h) => h.update(data),
+        }
+    }
+
+    fn finalize(&mut self) -> Vec<u8> {
+        match self {
+            Hasher::Sha256(h) => h.finalize_reset().to_vec(),
+            Hasher::Md5(h) => h.finalize_reset().to_vec(),
+        }
+    }
+}
+
+struct DigestPayload {
+    payload: Payload,
+    hasher: Hasher,
+    expected: Expected,
+    verified: bool,
+}
+
+impl Stream for DigestPayload {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.as_mut().get_mut();
+        match futures::ready!(Pin::new(&mut this.payload).poll_next(cx)) {
+            Some(Ok(chunk)) => {
+                this.hasher.update(&chunk);
+                Poll::Ready(Some(Ok(chunk)))
+            }
+            Some(Err(e)) => Poll::Ready(Some(Err(e))),
+            None => {
+                if this.verified {
+                    return Poll::Ready(None);
+                }
+                this.verified = true;
+                if this.expected.matches(&this.hasher.finalize()) {
+                    Poll::Ready(None)
+                } else {
+                    Poll::Ready(Some(Err(PayloadError::DigestMismatch)))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::http::test::TestRequest;
+
+    async fn read(mut req: Request) -> Result<Vec<u8>, PayloadError> {
+        let mut pl = req.take_payload();
+        let mut body = Vec::new();
+        while let Some(chunk) = pl.next().await {
+            body.extend_from_slice(&chunk?);
+        }
+        Ok(body)
+    }
+
+    fn request(header: &str, value: &str, body: &'static [u8]) -> Request {
+        let mut req = TestRequest::default()
+            .header(header, value)
+            .set_payload(Bytes::from_static(body))
+            .finish();
+        verify_body(&mut req);
+        req
+    }
+
+    #[ntex_rt::test]
+    async fn test_sha256() {
+        // sha-256 of "hello"
+        let digest = "sha-256=LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ=";
+        let req = request("digest", digest, b"hello");
+        assert_eq!(read(req).await.unwrap(), b"hello");
+
+        let req = request("digest", digest, b"hell0");
+        assert!(matches!(read(req).await, Err(PayloadError::DigestMismatch)));
+
+        // sha-256 is preferred over md5
+        let value = format!("MD5=AAAA, {}", digest);
+        let req = request("digest", &value, b"hello");
+        assert_eq!(read(req).await.unwrap(), b"hello");
+    }
+
+    #[ntex_rt::test]
+    async fn test_md5() {
+        // md5 of "hello"
+        let digest = "XUFAKrxLKna5cZ2REBfFkg==";
+        let req = request("content-md5", digest, b"hello");
+        assert_eq!(read(req).await.unwrap(), b"hello");
+
+        let req = request("digest", &format!("md5={}", digest), b"hello");
+        assert_eq!(read(req).await.unwrap(), b"hello");
+
+        let req = request("content-md5", digest, b"hello!");
+        assert!(matches!(read(req).await, Err(PayloadError::DigestMismatch)));
+
+        // malformed digest never matches
+        let req = request("content-md5", "not base64!", b"hello");
+        assert!(matches!(read(req).await, Err(PayloadError::DigestMismatch)));
+    }
+
+    #[ntex_rt::test]
+    async fn test_unsupported() {
+        let req = request("digest", "sha-512=AAAA", b"hello");
+        assert!(matches!(req.payload, Payload::H1(_)));
+        assert_eq!(read(req).await.unwrap(), b"hello");
+    }
+}
@@ -3,18 +3,21 @@ use std::io::{self, Write};
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
-use actix_threadpool::{run, CpuFuture};
 use brotli2::write::BrotliDecoder;
 use bytes::Bytes;
 use flate2::write::{GzDecoder, ZlibDecoder};
 use futures::{ready, Stream};
 
-use super::Writer;
+use super::{LimitExceeded, Writer};
 use crate::http::error::PayloadError;
 use crate::http::header::{ContentEncoding, HeaderMap, CONTENT_ENCODING};
+use crate::rt::blocking::{run, BlockingError, CpuFuture};
 
 const INPLACE: usize = 2049;
 
+/// Default max size of decompressed data, 256Kb
+pub(crate) const DEFAULT_LIMIT: usize = 262_144;
+
 pub struct Decoder<S> {
     decoder: Option<ContentDecoder>,
     stream: S,
@@ -27,17 +30,19 @@ where
     S: Stream<Item = Result<Bytes, PayloadError>>,
 {
     /// Construct a decoder.
+    ///
+    /// Max size of decompressed data is 256Kb, use `limit()` to change it.
     #[inline]
     pub fn new(stream: S, encoding: ContentEncoding) -> Decoder<S> {
         let decoder = match encoding {
             ContentEncoding::Br => Some(ContentDecoder::Br(Box::new(
-                BrotliDecoder::new(Writer::new()),
+                BrotliDecoder::new(Writer::with_limit(DEFAULT_LIMIT)),
             ))),
             ContentEncoding::Deflate => Some(ContentDecoder::Deflate(Box::new(
-                ZlibDecoder::new(Writer::new()),
+                ZlibDecoder::new(Writer::with_limit(DEFAULT_LIMIT)),
             ))),
             ContentEncoding::Gzip => Some(ContentDecoder::Gzip(Box::new(
-                GzDecoder::new(Writer::new()),
+                GzDecoder::new(Writer::with_limit(DEFAULT_LIMIT)),
             ))),
             _ => None,
         };
@@ -65,6 +70,35 @@ where
 
         Self::new(stream, encoding)
     }
+
+    /// Check if stream content gets decompressed
+    pub(crate) fn is_decompressing(&self) -> bool {
+        self.decoder.is_some()
+    }
+
+    /// Set max size of decompressed data.
+    ///
+    /// Decoder fails with `PayloadError::Overflow` as soon as decompressed
+    /// data exceeds the limit, remaining compressed input is not processed.
+    pub fn limit(mut self, limit: usize) -> Self {
+        if let Some(ref mut decoder) = self.decoder {
+            decoder.writer().limit = limit;
+        }
+        self
+    }
+}
+
+/// Convert decoder error, limit errors are reported as `PayloadError::Overflow`
+fn decode_error(err: io::Error) -> PayloadError {
+    if err
+        .get_ref()
+        .map(|e| e.is::<LimitExceeded>())
+        .unwrap_or(false)
+    {
+        PayloadError::Overflow
+    } else {
+        err.into()
+    }
 }
 
 impl<S> Stream for Decoder<S>
@@ -81,6 +115,9 @@ where
             if let Some(ref mut fut) = self.fut {
                 let (chunk, decoder) = match ready!(Pin::new(fut).poll(cx)) {
                     Ok(item) => item,
+                    Err(BlockingError::Error(e)) => {
+                        return Poll::Ready(Some(Err(decode_error(e))))
+                    }
                     Err(e) => return Poll::Ready(Some(Err(e.into()))),
                 };
                 self.decoder = Some(decoder);
@@ -99,7 +136,8 @@ where
                 Poll::Ready(Some(Ok(chunk))) => {
                     if let Some(mut decoder) = self.decoder.take() {
                         if chunk.len() < INPLACE {
-                            let chunk = decoder.feed_data(chunk)?;
+                            let chunk =
+                                decoder.feed_data(chunk).map_err(decode_error)?;
                             self.decoder = Some(decoder);
                             if let Some(chunk) = chunk {
                                 return Poll::Ready(Some(Ok(chunk)));
@@ -121,7 +159,7 @@ where
                         match decoder.feed_eof() {
                             Ok(Some(res)) => Poll::Ready(Some(Ok(res))),
                             Ok(None) => Poll::Ready(None),
-                            Err(err) => Poll::Ready(Some(Err(err.into()))),
+                            Err(err) => Poll::Ready(Some(Err(decode_error(err)))),
                         }
                     } else {
                         Poll::Ready(None)
@@ -141,6 +179,14 @@ enum ContentDecoder {
 }
 
 impl ContentDecoder {
+    fn writer(&mut self) -> &mut Writer {
+        match self {
+            ContentDecoder::Br(ref mut decoder) => decoder.get_mut(),
+            ContentDecoder::Gzip(ref mut decoder) => decoder.get_mut(),
+            ContentDecoder::Deflate(ref mut decoder) => decoder.get_mut(),
+        }
+    }
+
     fn feed_eof(&mut self) -> io::Result<Option<Bytes>> {
         match self {
             ContentDecoder::Br(ref mut decoder) => match decoder.flush() {
@@ -220,3 +266,59 @@ impl ContentDecoder {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use futures::{stream, StreamExt};
+
+    use super::*;
+
+    fn gzip(data: &[u8]) -> Bytes {
+        let mut enc = GzEncoder::new(Vec::new(), Compression::default());
+        enc.write_all(data).unwrap();
+        Bytes::from(enc.finish().unwrap())
+    }
+
+    #[ntex_rt::test]
+    async fn test_decoder_limit() {
+        let data = gzip(&[0u8; 1_048_576]);
+        assert!(data.len() < INPLACE);
+
+        let mut dec = Decoder::new(
+            stream::once(async move { Ok::<_, PayloadError>(data) }).boxed_local(),
+            ContentEncoding::Gzip,
+        );
+        let mut size = 0;
+        loop {
+            match dec.next().await {
+                Some(Ok(chunk)) => size += chunk.len(),
+                Some(Err(PayloadError::Overflow)) => break,
+                res => panic!("unexpected result: {:?}", res.map(|r| r.is_ok())),
+            }
+        }
+        assert!(size <= DEFAULT_LIMIT);
+
+        let data = gzip(&[0u8; 4096]);
+        let mut dec = Decoder::new(
+            stream::once(async move { Ok::<_, PayloadError>(data) }).boxed_local(),
+            ContentEncoding::Gzip,
+        )
+        .limit(1024);
+        match dec.next().await {
+            Some(Err(PayloadError::Overflow)) => (),
+            _ => panic!(),
+        }
+
+        let data = gzip(&[0u8; 4096]);
+        let dec = Decoder::new(
+            stream::once(async move { Ok::<_, PayloadError>(data) }).boxed_local(),
+            ContentEncoding::Gzip,
+        )
+        .limit(4096);
+        let chunks: Vec<_> = dec.collect().await;
+        let size: usize = chunks.into_iter().map(|c| c.unwrap().len()).sum();
+        assert_eq!(size, 4096);
+    }
+}
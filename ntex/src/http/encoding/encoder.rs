@@ -5,17 +5,17 @@ use std::io::{self, Write};
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
-use actix_threadpool::{run, BlockingError, CpuFuture};
+use crate::rt::blocking::{run, BlockingError, CpuFuture};
 use brotli2::write::BrotliEncoder;
 use bytes::Bytes;
 use flate2::write::{GzEncoder, ZlibEncoder};
 use futures::ready;
 
 use crate::http::body::{Body, BodySize, MessageBody, ResponseBody};
-use crate::http::header::{ContentEncoding, HeaderValue, CONTENT_ENCODING};
+use crate::http::header::{ContentEncoding, HeaderMap, HeaderValue, CONTENT_ENCODING};
 use crate::http::{ResponseHead, StatusCode};
 
-use super::Writer;
+use super::{Level, Writer};
 
 const INPLACE: usize = 1024;
 
@@ -32,14 +32,17 @@ impl<B: MessageBody + 'static> Encoder<B> {
         head: &mut ResponseHead,
         body: ResponseBody<B>,
     ) -> ResponseBody<B> {
-        let can_encode = ContentEncoder::can_encode(encoding)
-            && !(head.headers().contains_key(&CONTENT_ENCODING)
-                || head.status == StatusCode::SWITCHING_PROTOCOLS
-                || head.status == StatusCode::NO_CONTENT
-                || encoding == ContentEncoding::Identity
-                || encoding == ContentEncoding::Auto);
+        Encoder::response_with_level(encoding, Level::default(), head, body)
+    }
 
-        if !can_encode {
+    /// Encode response body with specified compression level
+    pub fn response_with_level(
+        encoding: ContentEncoding,
+        level: Level,
+        head: &mut ResponseHead,
+        body: ResponseBody<B>,
+    ) -> ResponseBody<B> {
+        if !can_encode(encoding, head) {
             body
         } else {
             let body = match body {
@@ -53,7 +56,7 @@ impl<B: MessageBody + 'static> Encoder<B> {
             };
 
             // Modify response body only if encoder is not None
-            let encoder = ContentEncoder::encoder(encoding).unwrap();
+            let encoder = ContentEncoder::encoder(encoding, level).unwrap();
             update_head(encoding, head);
             head.no_chunking(false);
             ResponseBody::Other(Body::from_message(Encoder {
@@ -64,6 +67,53 @@ impl<B: MessageBody + 'static> Encoder<B> {
             }))
         }
     }
+
+    /// Prepare response to `HEAD` request
+    ///
+    /// Response head gets same headers as encoded response to `GET` request
+    /// would get, body is not encoded. Content length of encoded body is not
+    /// known, so body size is reported as a stream. Responses that are
+    /// already encoded, i.e. precompressed files, keep their content length.
+    pub fn head_response(
+        encoding: ContentEncoding,
+        head: &mut ResponseHead,
+        body: ResponseBody<B>,
+    ) -> ResponseBody<B> {
+        match body {
+            ResponseBody::Other(Body::None) | ResponseBody::Other(Body::Empty) => body,
+            _ if !can_encode(encoding, head) => body,
+            _ => {
+                update_head(encoding, head);
+                head.no_chunking(false);
+                ResponseBody::Other(Body::from_message(HeadBody))
+            }
+        }
+    }
+}
+
+fn can_encode(encoding: ContentEncoding, head: &ResponseHead) -> bool {
+    ContentEncoder::can_encode(encoding)
+        && !(head.headers().contains_key(&CONTENT_ENCODING)
+            || head.status == StatusCode::SWITCHING_PROTOCOLS
+            || head.status == StatusCode::NO_CONTENT
+            || encoding == ContentEncoding::Identity
+            || encoding == ContentEncoding::Auto)
+}
+
+/// Body of encoded response to `HEAD` request
+struct HeadBody;
+
+impl MessageBody for HeadBody {
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        _: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        Poll::Ready(None)
+    }
 }
 
 enum EncoderBody<B> {
@@ -103,6 +153,10 @@ impl<B: MessageBody> MessageBody for Encoder<B> {
                             BlockingError::Canceled => {
                                 io::Error::new(io::ErrorKind::Other, "Canceled")
                             }
+                            BlockingError::Overloaded => io::Error::new(
+                                io::ErrorKind::Other,
+                                "Thread pool queue is full",
+                            ),
                         };
                         return Poll::Ready(Some(Err(Box::new(e))));
                     }
@@ -163,6 +217,14 @@ impl<B: MessageBody> MessageBody for Encoder<B> {
             }
         }
     }
+
+    fn trailers(&mut self) -> Option<HeaderMap> {
+        match self.body {
+            EncoderBody::Bytes(_) => None,
+            EncoderBody::Stream(ref mut b) => b.trailers(),
+            EncoderBody::BoxedStream(ref mut b) => b.trailers(),
+        }
+    }
 }
 
 fn update_head(encoding: ContentEncoding, head: &mut ResponseHead) {
@@ -188,19 +250,20 @@ impl ContentEncoder {
         }
     }
 
-    fn encoder(encoding: ContentEncoding) -> Option<Self> {
+    fn encoder(encoding: ContentEncoding, level: Level) -> Option<Self> {
         match encoding {
             ContentEncoding::Deflate => Some(ContentEncoder::Deflate(ZlibEncoder::new(
                 Writer::new(),
-                flate2::Compression::fast(),
+                level.flate2(),
             ))),
             ContentEncoding::Gzip => Some(ContentEncoder::Gzip(GzEncoder::new(
                 Writer::new(),
-                flate2::Compression::fast(),
+                level.flate2(),
+            ))),
+            ContentEncoding::Br => Some(ContentEncoder::Br(BrotliEncoder::new(
+                Writer::new(),
+                level.brotli(),
             ))),
-            ContentEncoding::Br => {
-                Some(ContentEncoder::Br(BrotliEncoder::new(Writer::new(), 3)))
-            }
             _ => None,
         }
     }
@@ -9,14 +9,65 @@ mod encoder;
 pub use self::decoder::Decoder;
 pub use self::encoder::Encoder;
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Compression level
+///
+/// Lower levels use less cpu, higher levels produce smaller responses.
+pub enum Level {
+    /// Fast compression, level 1 for gzip and deflate, quality 3 for brotli
+    Fast,
+    /// Level 6 for gzip and deflate, quality 6 for brotli
+    Balanced,
+    /// Best compression, level 9 for gzip and deflate, quality 11 for brotli
+    Best,
+    /// Encoding specific level, 0-9 for gzip and deflate, 0-11 for brotli.
+    /// Larger values are reduced to encoding's maximum level.
+    Precise(u32),
+}
+
+impl Default for Level {
+    fn default() -> Self {
+        Level::Fast
+    }
+}
+
+impl Level {
+    fn flate2(self) -> flate2::Compression {
+        match self {
+            Level::Fast => flate2::Compression::fast(),
+            Level::Balanced => flate2::Compression::new(6),
+            Level::Best => flate2::Compression::best(),
+            Level::Precise(level) => flate2::Compression::new(level.min(9)),
+        }
+    }
+
+    fn brotli(self) -> u32 {
+        match self {
+            Level::Fast => 3,
+            Level::Balanced => 6,
+            Level::Best => 11,
+            Level::Precise(level) => level.min(11),
+        }
+    }
+}
+
 pub(self) struct Writer {
     buf: BytesMut,
+    limit: usize,
+    written: usize,
 }
 
 impl Writer {
     fn new() -> Writer {
+        Writer::with_limit(usize::MAX)
+    }
+
+    /// Writer that fails once total written size exceeds `limit`
+    fn with_limit(limit: usize) -> Writer {
         Writer {
             buf: BytesMut::with_capacity(8192),
+            limit,
+            written: 0,
         }
     }
 
@@ -25,8 +76,24 @@ impl Writer {
     }
 }
 
+/// Decompressed data exceeds configured limit
+#[derive(Debug)]
+struct LimitExceeded;
+
+impl std::fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Decompressed data exceeds limit")
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
 impl io::Write for Writer {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.written = self.written.saturating_add(buf.len());
+        if self.written > self.limit {
+            return Err(io::Error::new(io::ErrorKind::Other, LimitExceeded));
+        }
         self.buf.extend_from_slice(buf);
         Ok(buf.len())
     }
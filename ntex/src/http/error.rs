@@ -5,10 +5,10 @@ use std::string::FromUtf8Error;
 use std::{fmt, io};
 
 use http::uri::InvalidUri;
-use http::{header, StatusCode};
+use http::{header, Method, StatusCode};
 
 // re-export for convinience
-pub use actix_threadpool::BlockingError;
+pub use crate::rt::blocking::BlockingError;
 pub use futures::channel::oneshot::Canceled;
 pub use http::Error as HttpError;
 
@@ -83,6 +83,9 @@ pub enum ParseError {
     /// A message head is too large to be reasonable.
     #[display(fmt = "Message head is too large")]
     TooLarge,
+    /// A request or status line is too long to be reasonable.
+    #[display(fmt = "Request or status line is too long")]
+    LineTooLong,
     /// A message reached EOF, but is not complete.
     #[display(fmt = "Message is incomplete")]
     Incomplete,
@@ -144,6 +147,12 @@ pub enum PayloadError {
     /// A payload length is unknown.
     #[display(fmt = "A payload length is unknown.")]
     UnknownLength,
+    /// Payload does not match `Digest` or `Content-MD5` header
+    #[display(fmt = "Payload does not match digest header.")]
+    DigestMismatch,
+    /// Payload is not received within payload timeout
+    #[display(fmt = "Payload read timed out.")]
+    Timeout,
     /// Http2 payload error
     #[display(fmt = "{}", _0)]
     Http2Payload(h2::Error),
@@ -164,6 +173,10 @@ impl From<BlockingError<io::Error>> for PayloadError {
                 io::ErrorKind::Other,
                 "Operation is canceled",
             )),
+            BlockingError::Overloaded => PayloadError::Io(io::Error::new(
+                io::ErrorKind::Other,
+                "Thread pool queue is full",
+            )),
         }
     }
 }
@@ -182,6 +195,10 @@ pub enum DispatchError {
     #[display(fmt = "IO error: {}", _0)]
     Io(io::Error),
 
+    /// Response could not be written to the peer
+    #[display(fmt = "{}", _0)]
+    Write(WriteError),
+
     /// Http request parse error.
     #[display(fmt = "Parse error: {}", _0)]
     Parse(ParseError),
@@ -217,6 +234,187 @@ pub enum DispatchError {
 
 impl std::error::Error for DispatchError {}
 
+/// Cause of response write failure
+#[derive(Debug, Display, PartialEq, Eq, Clone, Copy)]
+pub enum WriteErrorKind {
+    /// Connection or http/2 stream is reset by the peer
+    #[display(fmt = "peer reset")]
+    PeerReset,
+    /// Write operation timed out
+    #[display(fmt = "timeout")]
+    Timeout,
+    /// Peer closed connection
+    #[display(fmt = "broken pipe")]
+    BrokenPipe,
+    /// Other io error
+    #[display(fmt = "io error")]
+    Other,
+}
+
+impl WriteErrorKind {
+    fn from_io(err: &io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::ConnectionReset | io::ErrorKind::ConnectionAborted => {
+                WriteErrorKind::PeerReset
+            }
+            io::ErrorKind::TimedOut => WriteErrorKind::Timeout,
+            io::ErrorKind::BrokenPipe
+            | io::ErrorKind::WriteZero
+            | io::ErrorKind::UnexpectedEof => WriteErrorKind::BrokenPipe,
+            _ => WriteErrorKind::Other,
+        }
+    }
+}
+
+/// Response write error
+///
+/// Holds method and path of the request the response belongs to.
+#[derive(Debug, Display)]
+#[display(
+    fmt = "Cannot write response for {} {}: {} ({})",
+    method,
+    path,
+    kind,
+    error
+)]
+pub struct WriteError {
+    kind: WriteErrorKind,
+    method: Method,
+    path: String,
+    error: io::Error,
+}
+
+impl WriteError {
+    pub(crate) fn new(method: Method, path: &str, error: io::Error) -> Self {
+        WriteError {
+            kind: WriteErrorKind::from_io(&error),
+            path: path.to_string(),
+            method,
+            error,
+        }
+    }
+
+    pub(crate) fn h2(method: Method, path: &str, err: h2::Error) -> Self {
+        if err.is_io() {
+            WriteError::new(method, path, err.into_io().unwrap())
+        } else {
+            // stream or connection is reset
+            WriteError {
+                kind: WriteErrorKind::PeerReset,
+                path: path.to_string(),
+                error: io::Error::new(io::ErrorKind::ConnectionReset, err),
+                method,
+            }
+        }
+    }
+
+    /// Cause of the failure
+    pub fn kind(&self) -> WriteErrorKind {
+        self.kind
+    }
+
+    /// Request method
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// Request path
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Underlying io error
+    pub fn io_error(&self) -> &io::Error {
+        &self.error
+    }
+}
+
+impl std::error::Error for WriteError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Service panicked during request processing
+///
+/// Default response is `500 Internal Server Error` without body,
+/// panic message is not exposed to the peer.
+#[derive(Debug, Display)]
+#[display(fmt = "Service panicked: {}", _0)]
+pub struct PanicError(String);
+
+impl PanicError {
+    pub(crate) fn new(err: Box<dyn std::any::Any + Send>) -> Self {
+        let msg = if let Some(msg) = err.downcast_ref::<&'static str>() {
+            (*msg).to_string()
+        } else if let Some(msg) = err.downcast_ref::<String>() {
+            msg.clone()
+        } else {
+            "Box<dyn Any>".to_string()
+        };
+        PanicError(msg)
+    }
+
+    /// Panic message
+    pub fn message(&self) -> &str {
+        &self.0
+    }
+}
+
+impl ResponseError for PanicError {
+    fn error_response(&self) -> Response {
+        Response::new(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
+
+/// Inconsistent http service setting
+#[derive(Debug, Display, PartialEq, Eq, Clone, Copy)]
+pub enum ConfigIssue {
+    /// Disconnect timeout in milliseconds exceeds keep-alive timeout in seconds
+    #[display(fmt = "Disconnect timeout {}ms exceeds keep-alive timeout {}s", _0, _1)]
+    DisconnectExceedsKeepAlive(u64, u64),
+    /// Max header size is zero, every request would be rejected
+    #[display(fmt = "Max header size is 0, every request would be rejected")]
+    ZeroMaxHeaderSize,
+    /// Max request line size is zero, every request would be rejected
+    #[display(fmt = "Max request line size is 0, every request would be rejected")]
+    ZeroMaxLineSize,
+    /// Http/2 send buffer size is zero, response body would never be sent
+    #[display(fmt = "Http/2 send buffer size is 0, response body would never be sent")]
+    ZeroH2SendBuffer,
+    /// Tcp keep-alive retries is zero, it is rejected by OS
+    #[display(fmt = "Tcp keep-alive retries is 0")]
+    ZeroKeepaliveRetries,
+    /// Alt-Svc value is not a valid header value, header is not set
+    #[display(fmt = "Alt-Svc value is not a valid header value")]
+    InvalidAltSvc,
+}
+
+/// Http service configuration error
+///
+/// Contains every inconsistent setting found by validation.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ConfigError(pub(crate) Vec<ConfigIssue>);
+
+impl ConfigError {
+    /// Inconsistent settings
+    pub fn issues(&self) -> &[ConfigIssue] {
+        &self.0
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Inconsistent http service configuration")?;
+        for (idx, issue) in self.0.iter().enumerate() {
+            write!(f, "{} {}", if idx == 0 { ":" } else { ";" }, issue)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
 /// A set of error that can occure during parsing content type
 #[derive(PartialEq, Debug, Display)]
 pub enum ContentTypeError {
@@ -240,6 +438,32 @@ mod tests {
     use http::{Error as HttpError, StatusCode};
     use std::io;
 
+    #[test]
+    fn test_write_error() {
+        let err = WriteError::new(
+            Method::GET,
+            "/index.html",
+            io::Error::new(io::ErrorKind::ConnectionReset, "reset"),
+        );
+        assert_eq!(err.kind(), WriteErrorKind::PeerReset);
+        assert_eq!(err.method(), &Method::GET);
+        assert_eq!(err.path(), "/index.html");
+        assert_eq!(
+            format!("{}", DispatchError::from(err)),
+            "Cannot write response for GET /index.html: peer reset (reset)"
+        );
+
+        let kind =
+            |kind: io::ErrorKind| WriteError::new(Method::GET, "/", kind.into()).kind();
+        assert_eq!(kind(io::ErrorKind::BrokenPipe), WriteErrorKind::BrokenPipe);
+        assert_eq!(kind(io::ErrorKind::WriteZero), WriteErrorKind::BrokenPipe);
+        assert_eq!(kind(io::ErrorKind::TimedOut), WriteErrorKind::Timeout);
+        assert_eq!(kind(io::ErrorKind::Other), WriteErrorKind::Other);
+
+        let err = WriteError::h2(Method::POST, "/", h2::Reason::CANCEL.into());
+        assert_eq!(err.kind(), WriteErrorKind::PeerReset);
+    }
+
     #[test]
     fn test_into_response() {
         let err: HttpError = StatusCode::from_u16(10000).err().unwrap().into();
@@ -263,6 +487,9 @@ mod tests {
         let err: PayloadError = BlockingError::Canceled.into();
         assert!(format!("{}", err).contains("Operation is canceled"));
 
+        let err: PayloadError = BlockingError::Overloaded.into();
+        assert!(format!("{}", err).contains("Thread pool queue is full"));
+
         let err: PayloadError =
             BlockingError::Error(io::Error::new(io::ErrorKind::Other, "ParseError"))
                 .into();
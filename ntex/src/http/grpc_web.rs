@@ -0,0 +1,353 @@
+//! gRPC-Web framing helpers
+//!
+//! gRPC-Web body is a sequence of length-prefixed frames. Each frame
+//! starts with 1-byte flag and 4-byte big-endian length. Flag `0x01`
+//! marks compressed message, flag `0x80` marks trailer frame, trailers
+//! are encoded as http/1 headers block.
+use std::task::{Context, Poll};
+use std::{convert::TryFrom, error::Error, io};
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use derive_more::{Display, From};
+
+use crate::codec::{Decoder, Encoder};
+use crate::http::body::{BodySize, MessageBody};
+use crate::http::header::{HeaderMap, HeaderName, HeaderValue};
+
+const HEADER_SIZE: usize = 5;
+const FLAG_COMPRESSED: u8 = 0x01;
+const FLAG_TRAILERS: u8 = 0x80;
+
+/// gRPC-Web framing errors
+#[derive(Debug, Display, From)]
+pub enum GrpcWebError {
+    /// Frame size is larger than max allowed size
+    #[display(fmt = "Frame size is larger than max allowed size")]
+    Overflow,
+    /// Trailer frame could not be parsed
+    #[display(fmt = "Invalid trailer frame")]
+    InvalidTrailers,
+    /// Io error
+    #[display(fmt = "io error: {}", _0)]
+    Io(io::Error),
+}
+
+impl std::error::Error for GrpcWebError {}
+
+/// gRPC-Web frame
+#[derive(Debug)]
+pub enum Frame {
+    /// Length-prefixed message
+    Message { compressed: bool, data: Bytes },
+    /// Trailer frame
+    Trailers(HeaderMap),
+}
+
+/// gRPC-Web framing codec
+#[derive(Debug, Clone)]
+pub struct Codec {
+    max_size: usize,
+}
+
+impl Codec {
+    /// Create new gRPC-Web codec
+    pub fn new() -> Codec {
+        Codec {
+            max_size: 4_194_304,
+        }
+    }
+
+    /// Set max frame size
+    ///
+    /// By default max size is set to 4mb
+    pub fn max_size(mut self, size: usize) -> Self {
+        self.max_size = size;
+        self
+    }
+}
+
+impl Default for Codec {
+    fn default() -> Self {
+        Codec::new()
+    }
+}
+
+impl Decoder for Codec {
+    type Item = Frame;
+    type Error = GrpcWebError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Frame>, Self::Error> {
+        if src.len() < HEADER_SIZE {
+            return Ok(None);
+        }
+        let flag = src[0];
+        let len = u32::from_be_bytes([src[1], src[2], src[3], src[4]]) as usize;
+        if len > self.max_size {
+            return Err(GrpcWebError::Overflow);
+        }
+        if src.len() < HEADER_SIZE + len {
+            src.reserve(HEADER_SIZE + len - src.len());
+            return Ok(None);
+        }
+        src.advance(HEADER_SIZE);
+        let data = src.split_to(len).freeze();
+
+        if flag & FLAG_TRAILERS != 0 {
+            Ok(Some(Frame::Trailers(decode_trailers(&data)?)))
+        } else {
+            Ok(Some(Frame::Message {
+                compressed: flag & FLAG_COMPRESSED != 0,
+                data,
+            }))
+        }
+    }
+}
+
+impl Encoder for Codec {
+    type Item = Frame;
+    type Error = GrpcWebError;
+
+    fn encode(&mut self, item: Frame, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        match item {
+            Frame::Message { compressed, data } => {
+                if data.len() > self.max_size {
+                    return Err(GrpcWebError::Overflow);
+                }
+                encode_message(&data, compressed, dst);
+            }
+            Frame::Trailers(trailers) => encode_trailers(&trailers, dst),
+        }
+        Ok(())
+    }
+}
+
+/// Write length-prefixed message frame
+pub fn encode_message(data: &[u8], compressed: bool, dst: &mut BytesMut) {
+    dst.reserve(HEADER_SIZE + data.len());
+    dst.put_u8(if compressed { FLAG_COMPRESSED } else { 0 });
+    dst.put_u32(data.len() as u32);
+    dst.extend_from_slice(data);
+}
+
+/// Write trailer frame
+pub fn encode_trailers(trailers: &HeaderMap, dst: &mut BytesMut) {
+    let len = trailers
+        .iter()
+        .map(|(name, value)| name.as_str().len() + value.len() + 4)
+        .sum::<usize>();
+
+    dst.reserve(HEADER_SIZE + len);
+    dst.put_u8(FLAG_TRAILERS);
+    dst.put_u32(len as u32);
+    for (name, value) in trailers.iter() {
+        dst.extend_from_slice(name.as_str().as_bytes());
+        dst.extend_from_slice(b": ");
+        dst.extend_from_slice(value.as_bytes());
+        dst.extend_from_slice(b"\r\n");
+    }
+}
+
+/// Parse trailer frame payload
+pub fn decode_trailers(data: &[u8]) -> Result<HeaderMap, GrpcWebError> {
+    let mut trailers = HeaderMap::new();
+    for line in data.split(|b| *b == b'\n') {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.is_empty() {
+            continue;
+        }
+        let pos = line
+            .iter()
+            .position(|b| *b == b':')
+            .ok_or(GrpcWebError::InvalidTrailers)?;
+        let name = HeaderName::try_from(&line[..pos])
+            .map_err(|_| GrpcWebError::InvalidTrailers)?;
+        let value = HeaderValue::try_from(trim(&line[pos + 1..]))
+            .map_err(|_| GrpcWebError::InvalidTrailers)?;
+        trailers.append(name, value);
+    }
+    Ok(trailers)
+}
+
+fn trim(mut val: &[u8]) -> &[u8] {
+    while let Some((b' ', rest)) | Some((b'\t', rest)) = val.split_first() {
+        val = rest;
+    }
+    while let Some((b' ', rest)) | Some((b'\t', rest)) = val.split_last() {
+        val = rest;
+    }
+    val
+}
+
+/// gRPC to gRPC-Web body translation
+///
+/// Wraps body that contains length-prefixed gRPC messages and
+/// appends trailers of the wrapped body as a trailer frame.
+pub struct GrpcWebBody<B> {
+    body: B,
+    eof: bool,
+}
+
+impl<B: MessageBody> GrpcWebBody<B> {
+    /// Create new gRPC-Web body
+    pub fn new(body: B) -> Self {
+        GrpcWebBody { body, eof: false }
+    }
+}
+
+impl<B: MessageBody> MessageBody for GrpcWebBody<B> {
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        if self.eof {
+            return Poll::Ready(None);
+        }
+        match self.body.poll_next_chunk(cx) {
+            Poll::Ready(None) => {
+                self.eof = true;
+                let trailers = self.body.trailers().unwrap_or_default();
+                let mut buf = BytesMut::new();
+                encode_trailers(&trailers, &mut buf);
+                Poll::Ready(Some(Ok(buf.freeze())))
+            }
+            res => res,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::poll_fn;
+
+    use super::*;
+    use crate::http::body::Body;
+
+    #[test]
+    fn test_message() {
+        let mut codec = Codec::new();
+        let mut buf = BytesMut::new();
+        codec
+            .encode(
+                Frame::Message {
+                    compressed: true,
+                    data: Bytes::from_static(b"data"),
+                },
+                &mut buf,
+            )
+            .unwrap();
+        assert_eq!(&buf[..], b"\x01\x00\x00\x00\x04data");
+        encode_message(b"msg", false, &mut buf);
+
+        let mut partial = BytesMut::from(&buf[..3]);
+        assert!(codec.decode(&mut partial).unwrap().is_none());
+
+        match codec.decode(&mut buf).unwrap() {
+            Some(Frame::Message { compressed, data }) => {
+                assert!(compressed);
+                assert_eq!(data, Bytes::from_static(b"data"));
+            }
+            _ => panic!(),
+        }
+        match codec.decode(&mut buf).unwrap() {
+            Some(Frame::Message { compressed, data }) => {
+                assert!(!compressed);
+                assert_eq!(data, Bytes::from_static(b"msg"));
+            }
+            _ => panic!(),
+        }
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+
+        let mut codec = Codec::new().max_size(2);
+        let mut buf = BytesMut::new();
+        encode_message(b"msg", false, &mut buf);
+        assert!(matches!(
+            codec.decode(&mut buf),
+            Err(GrpcWebError::Overflow)
+        ));
+    }
+
+    #[test]
+    fn test_trailers() {
+        let mut trailers = HeaderMap::new();
+        trailers.insert(
+            HeaderName::from_static("grpc-status"),
+            HeaderValue::from_static("0"),
+        );
+        let mut buf = BytesMut::new();
+        encode_trailers(&trailers, &mut buf);
+        assert_eq!(&buf[..], b"\x80\x00\x00\x00\x10grpc-status: 0\r\n");
+
+        let mut codec = Codec::new();
+        match codec.decode(&mut buf).unwrap() {
+            Some(Frame::Trailers(trailers)) => {
+                assert_eq!(trailers.get("grpc-status").unwrap(), "0");
+            }
+            _ => panic!(),
+        }
+
+        let trailers =
+            decode_trailers(b"Grpc-Status:13\r\ngrpc-message:  failed \r\n").unwrap();
+        assert_eq!(trailers.get("grpc-status").unwrap(), "13");
+        assert_eq!(trailers.get("grpc-message").unwrap(), "failed");
+        assert!(matches!(
+            decode_trailers(b"grpc-status"),
+            Err(GrpcWebError::InvalidTrailers)
+        ));
+    }
+
+    struct TrailersBody(Option<Bytes>);
+
+    impl MessageBody for TrailersBody {
+        fn size(&self) -> BodySize {
+            BodySize::Stream
+        }
+
+        fn poll_next_chunk(
+            &mut self,
+            _: &mut Context<'_>,
+        ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+            Poll::Ready(self.0.take().map(Ok))
+        }
+
+        fn trailers(&mut self) -> Option<HeaderMap> {
+            let mut trailers = HeaderMap::new();
+            trailers.insert(
+                HeaderName::from_static("grpc-status"),
+                HeaderValue::from_static("0"),
+            );
+            Some(trailers)
+        }
+    }
+
+    #[ntex_rt::test]
+    async fn test_body() {
+        let mut msg = BytesMut::new();
+        encode_message(b"msg", false, &mut msg);
+
+        let mut body = GrpcWebBody::new(TrailersBody(Some(msg.freeze())));
+        assert_eq!(body.size(), BodySize::Stream);
+        let chunk = poll_fn(|cx| body.poll_next_chunk(cx))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(&chunk[..], b"\x00\x00\x00\x00\x03msg");
+        let chunk = poll_fn(|cx| body.poll_next_chunk(cx))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(&chunk[..], b"\x80\x00\x00\x00\x10grpc-status: 0\r\n");
+        assert!(poll_fn(|cx| body.poll_next_chunk(cx)).await.is_none());
+
+        // body without trailers gets empty trailer frame
+        let mut body = GrpcWebBody::new(Body::Empty);
+        let chunk = poll_fn(|cx| body.poll_next_chunk(cx))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(&chunk[..], b"\x80\x00\x00\x00\x00");
+    }
+}
@@ -7,6 +7,7 @@ use crate::codec::{Decoder, Encoder};
 use crate::http::body::BodySize;
 use crate::http::config::DateService;
 use crate::http::error::{ParseError, PayloadError};
+use crate::http::header::HeaderMap;
 use crate::http::message::{ConnectionType, RequestHeadType, ResponseHead};
 use crate::http::{Method, Version};
 
@@ -35,6 +36,7 @@ struct ClientCodecInner {
     timer: DateService,
     decoder: decoder::MessageDecoder<ResponseHead>,
     payload: Option<PayloadDecoder>,
+    trailers: Option<HeaderMap>,
     version: Version,
     ctype: ConnectionType,
 
@@ -64,6 +66,7 @@ impl ClientCodec {
                 timer,
                 decoder: decoder::MessageDecoder::default(),
                 payload: None,
+                trailers: None,
                 version: Version::HTTP_11,
                 ctype: ConnectionType::Close,
 
@@ -106,6 +109,11 @@ impl ClientPayloadCodec {
         self.inner.ctype == ConnectionType::KeepAlive
     }
 
+    /// Take trailer headers of the last response, available after payload eof
+    pub fn take_trailers(&mut self) -> Option<HeaderMap> {
+        self.inner.trailers.take()
+    }
+
     /// Transform payload codec to a message codec
     pub fn into_message_codec(self) -> ClientCodec {
         ClientCodec { inner: self.inner }
@@ -165,12 +173,43 @@ impl Decoder for ClientPayloadCodec {
                 Some(Some(chunk))
             }
             Some(PayloadItem::Eof) => {
-                self.inner.payload.take();
+                self.inner.trailers = self
+                    .inner
+                    .payload
+                    .take()
+                    .and_then(|mut pl| pl.take_trailers());
                 Some(None)
             }
             None => None,
         })
     }
+
+    fn decode_eof(
+        &mut self,
+        src: &mut BytesMut,
+    ) -> Result<Option<Self::Item>, Self::Error> {
+        if self.inner.payload.is_none() {
+            return Ok(None);
+        }
+        if let Some(item) = self.decode(src)? {
+            return Ok(Some(item));
+        }
+
+        // payload delimited by connection close is completed with eof,
+        // any other payload is incomplete
+        if self
+            .inner
+            .payload
+            .take()
+            .map(|pl| pl.is_eof())
+            .unwrap_or(false)
+        {
+            self.inner.ctype = ConnectionType::Close;
+            Ok(Some(None))
+        } else {
+            Err(PayloadError::Incomplete(None))
+        }
+    }
 }
 
 impl Encoder for ClientCodec {
@@ -6,8 +6,9 @@ use http::{Method, Version};
 
 use crate::codec::{Decoder, Encoder};
 use crate::http::body::BodySize;
-use crate::http::config::DateService;
+use crate::http::config::{DateService, EmptyHeaderValue, HeaderValidation};
 use crate::http::error::ParseError;
+use crate::http::header::{HeaderMap, TE};
 use crate::http::message::ConnectionType;
 use crate::http::request::Request;
 use crate::http::response::Response;
@@ -21,6 +22,7 @@ bitflags! {
         const HEAD              = 0b0000_0001;
         const KEEPALIVE_ENABLED = 0b0000_0010;
         const STREAM            = 0b0000_0100;
+        const TRAILERS          = 0b0000_1000;
     }
 }
 
@@ -72,6 +74,35 @@ impl Codec {
     }
 
     #[inline]
+    /// Set max size of request head.
+    ///
+    /// Request is rejected with `ParseError::TooLarge` if request line
+    /// and headers exceed this size.
+    pub(crate) fn set_max_header_size(&mut self, size: usize) {
+        self.decoder.set_max_size(size);
+    }
+
+    #[inline]
+    /// Set max size of request line.
+    ///
+    /// Request is rejected with `ParseError::LineTooLong` if request line
+    /// exceeds this size, line is checked before request head is complete.
+    pub(crate) fn set_max_line_size(&mut self, size: usize) {
+        self.decoder.set_max_line_size(size);
+    }
+
+    #[inline]
+    /// Set handling of empty request header values.
+    pub(crate) fn set_empty_header_value(&mut self, val: EmptyHeaderValue) {
+        self.decoder.set_empty_values(val);
+    }
+
+    #[inline]
+    /// Set validation of critical request headers.
+    pub(crate) fn set_header_validation(&mut self, val: HeaderValidation) {
+        self.decoder.set_validation(val);
+    }
+
     /// Check if request is upgrade
     pub fn upgrade(&self) -> bool {
         self.ctype == ConnectionType::Upgrade
@@ -101,6 +132,34 @@ impl Codec {
         }
     }
 
+    #[inline]
+    /// Http version of last request
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    #[inline]
+    /// Check if last request accepts trailers (`TE: trailers`)
+    pub fn trailers(&self) -> bool {
+        self.flags.contains(Flags::TRAILERS)
+    }
+
+    /// Encode payload eof with trailer headers.
+    ///
+    /// Trailers are sent only if peer accepts them, otherwise
+    /// plain eof is encoded.
+    pub(super) fn encode_trailers(
+        &mut self,
+        trailers: &HeaderMap,
+        dst: &mut BytesMut,
+    ) -> io::Result<()> {
+        if self.flags.contains(Flags::TRAILERS) {
+            self.encoder.encode_trailers(trailers, dst)
+        } else {
+            self.encoder.encode_eof(dst)
+        }
+    }
+
     #[inline]
     #[doc(hidden)]
     pub fn set_date_header(&self, dst: &mut BytesMut) {
@@ -125,6 +184,17 @@ impl Decoder for Codec {
         } else if let Some((req, payload)) = self.decoder.decode(src)? {
             let head = req.head();
             self.flags.set(Flags::HEAD, head.method == Method::HEAD);
+            self.flags.set(
+                Flags::TRAILERS,
+                head.headers.get_all(TE).any(|val| {
+                    val.to_str()
+                        .map(|s| {
+                            s.split(',')
+                                .any(|te| te.trim().eq_ignore_ascii_case("trailers"))
+                        })
+                        .unwrap_or(false)
+                }),
+            );
             self.version = head.version;
             self.ctype = head.connection_type();
             if self.ctype == ConnectionType::KeepAlive
@@ -172,6 +242,18 @@ impl Encoder for Codec {
                     self.ctype
                 };
 
+                // http/1.0 does not support chunked encoding,
+                // streaming body is delimited by connection close,
+                // same for http/1.1 if chunking is disabled
+                if length == BodySize::Stream
+                    && (self.version < Version::HTTP_11 || !res.head().chunked())
+                {
+                    res.head_mut().no_chunking(true);
+                    if self.ctype == ConnectionType::KeepAlive {
+                        self.ctype = ConnectionType::Close;
+                    }
+                }
+
                 // encode message
                 self.encoder.encode(
                     dst,
@@ -198,7 +280,7 @@ impl Encoder for Codec {
 
 #[cfg(test)]
 mod tests {
-    use bytes::BytesMut;
+    use bytes::{Bytes, BytesMut};
 
     use super::*;
     use crate::http::{HttpMessage, Method};
@@ -249,4 +331,75 @@ mod tests {
         assert!(codec.upgrade());
         assert!(!codec.keepalive_enabled());
     }
+
+    #[ntex_rt::test]
+    async fn test_trailers() {
+        let mut trailers = HeaderMap::new();
+        trailers.insert(
+            http::header::HeaderName::from_static("grpc-status"),
+            http::header::HeaderValue::from_static("0"),
+        );
+
+        let mut codec = Codec::default();
+        let mut buf = BytesMut::from("GET /test HTTP/1.1\r\nte: gzip, trailers\r\n\r\n");
+        let _item = codec.decode(&mut buf).unwrap().unwrap();
+        assert!(codec.trailers());
+
+        let mut out = BytesMut::new();
+        codec
+            .encode(
+                Message::Item((Response::Ok().finish().drop_body(), BodySize::Stream)),
+                &mut out,
+            )
+            .unwrap();
+        out.clear();
+        codec.encode_trailers(&trailers, &mut out).unwrap();
+        assert_eq!(&out[..], b"0\r\ngrpc-status: 0\r\n\r\n");
+
+        // peer does not accept trailers
+        let mut codec = Codec::default();
+        let mut buf = BytesMut::from("GET /test HTTP/1.1\r\n\r\n");
+        let _item = codec.decode(&mut buf).unwrap().unwrap();
+        assert!(!codec.trailers());
+
+        codec
+            .encode(
+                Message::Item((Response::Ok().finish().drop_body(), BodySize::Stream)),
+                &mut out,
+            )
+            .unwrap();
+        out.clear();
+        codec.encode_trailers(&trailers, &mut out).unwrap();
+        assert_eq!(&out[..], b"0\r\n\r\n");
+    }
+
+    #[ntex_rt::test]
+    async fn test_http10_stream() {
+        let mut codec = Codec::new(DateService::default(), true);
+        let mut buf =
+            BytesMut::from("GET /test HTTP/1.0\r\nconnection: keep-alive\r\n\r\n");
+        let _item = codec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(codec.version(), Version::HTTP_10);
+        assert!(codec.keepalive());
+
+        let mut out = BytesMut::new();
+        codec
+            .encode(
+                Message::Item((Response::Ok().finish().drop_body(), BodySize::Stream)),
+                &mut out,
+            )
+            .unwrap();
+        let data = String::from_utf8(out.to_vec()).unwrap();
+        assert!(data.starts_with("HTTP/1.0 200 OK\r\n"));
+        assert!(!data.contains("transfer-encoding"));
+        assert!(!data.contains("connection"));
+        assert!(!codec.keepalive());
+
+        out.clear();
+        codec
+            .encode(Message::Chunk(Some(Bytes::from_static(b"data"))), &mut out)
+            .unwrap();
+        codec.encode(Message::Chunk(None), &mut out).unwrap();
+        assert_eq!(&out[..], b"data");
+    }
 }
@@ -5,21 +5,29 @@ use std::task::Poll;
 
 use bytes::{Buf, Bytes, BytesMut};
 use http::header::{HeaderName, HeaderValue};
+use http::uri::Authority;
 use http::{header, Method, StatusCode, Uri, Version};
 use log::{debug, error, trace};
 
 use crate::codec::Decoder;
+use crate::http::config::{EmptyHeaderValue, HeaderAnomalies, HeaderValidation};
 use crate::http::error::ParseError;
-use crate::http::header::HeaderMap;
+use crate::http::header::{ContentEncoding, HeaderMap};
 use crate::http::message::{ConnectionType, ResponseHead};
 use crate::http::request::Request;
 
-use super::MAX_BUFFER_SIZE;
+use super::{MAX_BUFFER_SIZE, MAX_LINE_SIZE};
 
 const MAX_HEADERS: usize = 96;
 
 /// Incoming messagd decoder
-pub(super) struct MessageDecoder<T: MessageType>(PhantomData<T>);
+pub(super) struct MessageDecoder<T: MessageType> {
+    max_size: usize,
+    max_line_size: usize,
+    empty_values: EmptyHeaderValue,
+    validation: HeaderValidation,
+    _t: PhantomData<T>,
+}
 
 #[derive(Debug)]
 /// Incoming request type
@@ -31,7 +39,35 @@ pub(super) enum PayloadType {
 
 impl<T: MessageType> Default for MessageDecoder<T> {
     fn default() -> Self {
-        MessageDecoder(PhantomData)
+        MessageDecoder {
+            max_size: MAX_BUFFER_SIZE,
+            max_line_size: MAX_LINE_SIZE,
+            empty_values: EmptyHeaderValue::Accept,
+            validation: HeaderValidation::Strict,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<T: MessageType> MessageDecoder<T> {
+    /// Set max size of message head
+    pub(super) fn set_max_size(&mut self, size: usize) {
+        self.max_size = size;
+    }
+
+    /// Set max size of request or status line
+    pub(super) fn set_max_line_size(&mut self, size: usize) {
+        self.max_line_size = size;
+    }
+
+    /// Set handling of empty header values
+    pub(super) fn set_empty_values(&mut self, val: EmptyHeaderValue) {
+        self.empty_values = val;
+    }
+
+    /// Set validation of critical headers
+    pub(super) fn set_validation(&mut self, val: HeaderValidation) {
+        self.validation = val;
     }
 }
 
@@ -40,7 +76,39 @@ impl<T: MessageType> Decoder for MessageDecoder<T> {
     type Error = ParseError;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        T::decode(src)
+        check_line_size(src, self.max_line_size)?;
+        T::decode(src, self.max_size, self.empty_values, self.validation)
+    }
+}
+
+/// Check size of the first line of message head
+///
+/// Line is checked before head is complete, so too long line is
+/// rejected as soon as limit is reached.
+fn check_line_size(src: &[u8], max_size: usize) -> Result<(), ParseError> {
+    // leading empty lines are ignored by parser
+    let start = src
+        .iter()
+        .position(|b| *b != b'\r' && *b != b'\n')
+        .unwrap_or(src.len());
+    let line = &src[start..];
+
+    // line content plus crlf, line could be incomplete
+    let limit = line.len().min(max_size.saturating_add(2));
+    let size = line[..limit]
+        .iter()
+        .position(|b| *b == b'\n')
+        .unwrap_or(limit);
+    let size = if size > 0 && line[size - 1] == b'\r' {
+        size - 1
+    } else {
+        size
+    };
+    if size > max_size {
+        trace!("Max message line size reached, closing");
+        Err(ParseError::LineTooLong)
+    } else {
+        Ok(())
     }
 }
 
@@ -53,27 +121,46 @@ pub(super) enum PayloadLength {
 pub(super) trait MessageType: Sized {
     fn set_connection_type(&mut self, ctype: Option<ConnectionType>);
 
-    fn set_expect(&mut self);
+    fn set_expect(&mut self, known: bool);
 
     fn headers_mut(&mut self) -> &mut HeaderMap;
 
-    fn decode(src: &mut BytesMut) -> Result<Option<(Self, PayloadType)>, ParseError>;
+    fn decode(
+        src: &mut BytesMut,
+        max_size: usize,
+        empty_values: EmptyHeaderValue,
+        validation: HeaderValidation,
+    ) -> Result<Option<(Self, PayloadType)>, ParseError>;
 
     fn set_headers(
         &mut self,
         slice: &Bytes,
         raw_headers: &[HeaderIndex],
+        empty_values: EmptyHeaderValue,
     ) -> Result<PayloadLength, ParseError> {
         let mut ka = None;
         let mut has_upgrade = false;
         let mut expect = false;
-        let mut chunked = false;
+        let mut unknown_expect = false;
+        let mut has_te = false;
         let mut content_length = None;
+        let mut ws_upgrade = false;
 
         {
             let headers = self.headers_mut();
 
             for idx in raw_headers.iter() {
+                if idx.value.0 == idx.value.1 {
+                    match empty_values {
+                        EmptyHeaderValue::Accept => (),
+                        EmptyHeaderValue::Reject => {
+                            debug!("empty header value");
+                            return Err(ParseError::Header);
+                        }
+                        EmptyHeaderValue::Trim => continue,
+                    }
+                }
+
                 let name =
                     HeaderName::from_bytes(&slice[idx.name.0..idx.name.1]).unwrap();
 
@@ -85,43 +172,34 @@ pub(super) trait MessageType: Sized {
                 };
                 match name {
                     header::CONTENT_LENGTH => {
-                        if let Ok(s) = value.to_str() {
-                            if let Ok(len) = s.parse::<u64>() {
-                                if len != 0 {
-                                    content_length = Some(len);
-                                }
-                            } else {
+                        // https://tools.ietf.org/html/rfc7230#section-3.3.2
+                        // list of identical values is accepted
+                        let s = value.to_str().map_err(|_| {
+                            debug!("illegal Content-Length: {:?}", value);
+                            ParseError::Header
+                        })?;
+                        for item in s.split(',') {
+                            let len = item.trim().parse::<u64>().map_err(|_| {
                                 debug!("illegal Content-Length: {:?}", s);
+                                ParseError::Header
+                            })?;
+                            if content_length.map(|l| l != len).unwrap_or(false) {
+                                debug!("conflicting Content-Length: {:?}", s);
                                 return Err(ParseError::Header);
                             }
-                        } else {
-                            debug!("illegal Content-Length: {:?}", value);
-                            return Err(ParseError::Header);
+                            content_length = Some(len);
                         }
                     }
                     // transfer-encoding
                     header::TRANSFER_ENCODING => {
-                        if let Ok(s) = value.to_str().map(|s| s.trim()) {
-                            chunked = s.eq_ignore_ascii_case("chunked");
-                        } else {
+                        if value.to_str().is_err() {
                             return Err(ParseError::Header);
                         }
+                        has_te = true;
                     }
                     // connection keep-alive state
                     header::CONNECTION => {
-                        ka = if let Ok(conn) = value.to_str().map(|conn| conn.trim()) {
-                            if conn.eq_ignore_ascii_case("keep-alive") {
-                                Some(ConnectionType::KeepAlive)
-                            } else if conn.eq_ignore_ascii_case("close") {
-                                Some(ConnectionType::Close)
-                            } else if conn.eq_ignore_ascii_case("upgrade") {
-                                Some(ConnectionType::Upgrade)
-                            } else {
-                                None
-                            }
-                        } else {
-                            None
-                        };
+                        ka = value.to_str().ok().and_then(connection_type);
                     }
                     header::UPGRADE => {
                         has_upgrade = true;
@@ -129,25 +207,46 @@ pub(super) trait MessageType: Sized {
                         // sends "content-length: 0" with websocket upgrade
                         if let Ok(val) = value.to_str().map(|val| val.trim()) {
                             if val.eq_ignore_ascii_case("websocket") {
-                                content_length = None;
+                                ws_upgrade = true;
                             }
                         }
                     }
                     header::EXPECT => {
-                        let bytes = value.as_bytes();
-                        if bytes.len() >= 4 && &bytes[0..4] == b"100-" {
-                            expect = true;
+                        if let Ok(val) = value.to_str() {
+                            for item in val.split(',').map(|item| item.trim()) {
+                                if item.eq_ignore_ascii_case("100-continue") {
+                                    expect = true;
+                                } else if !item.is_empty() {
+                                    unknown_expect = true;
+                                }
+                            }
+                        } else {
+                            unknown_expect = true;
                         }
                     }
                     _ => (),
                 }
 
+                #[cfg(not(feature = "preserve-header-case"))]
                 headers.append(name, value);
+                #[cfg(feature = "preserve-header-case")]
+                headers.append_raw(name, slice.slice(idx.name.0..idx.name.1), value);
             }
         }
+        let chunked = has_te
+            && match transfer_coding(self.headers_mut()) {
+                Some(TransferCoding::NotChunked) | None => false,
+                Some(_) => true,
+            };
         self.set_connection_type(ka);
         if expect {
-            self.set_expect()
+            self.set_expect(true)
+        }
+        if unknown_expect {
+            self.set_expect(false)
+        }
+        if ws_upgrade || content_length == Some(0) {
+            content_length = None;
         }
 
         // https://tools.ietf.org/html/rfc7230#section-3.3.3
@@ -169,15 +268,118 @@ pub(super) trait MessageType: Sized {
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// Transfer codings of a message
+pub(super) enum TransferCoding {
+    /// `chunked` is the only coding
+    Chunked,
+    /// Payload is compressed before chunking, `gzip, chunked`
+    Compressed(ContentEncoding),
+    /// `chunked` is the final coding, but other codings are not supported
+    Unsupported,
+    /// `chunked` is not the final coding or it is applied more than once
+    NotChunked,
+}
+
+/// Transfer codings from `Transfer-Encoding` headers, rfc7230 section 3.3.1
+///
+/// Codings are listed in the order they were applied, multiple headers
+/// form a single list. Only one of `gzip`, `x-gzip` or `deflate` codings
+/// before `chunked` is supported. Returns `None` if message does not
+/// have `Transfer-Encoding` header.
+pub(super) fn transfer_coding(headers: &HeaderMap) -> Option<TransferCoding> {
+    let mut found = false;
+    let mut chunked = false;
+    let mut supported = true;
+    let mut encoding = None;
+
+    for hdr in headers.get_all(header::TRANSFER_ENCODING) {
+        found = true;
+        let hdr = if let Ok(hdr) = hdr.to_str() {
+            hdr
+        } else {
+            return Some(TransferCoding::NotChunked);
+        };
+        for item in hdr.split(',').map(|item| item.trim()) {
+            if item.is_empty() {
+                continue;
+            }
+            if chunked {
+                return Some(TransferCoding::NotChunked);
+            }
+            if item.eq_ignore_ascii_case("chunked") {
+                chunked = true;
+            } else if encoding.is_some() {
+                supported = false;
+            } else if item.eq_ignore_ascii_case("gzip")
+                || item.eq_ignore_ascii_case("x-gzip")
+            {
+                encoding = Some(ContentEncoding::Gzip);
+            } else if item.eq_ignore_ascii_case("deflate") {
+                encoding = Some(ContentEncoding::Deflate);
+            } else {
+                supported = false;
+            }
+        }
+    }
+
+    if !found {
+        None
+    } else if !chunked {
+        Some(TransferCoding::NotChunked)
+    } else if !supported {
+        Some(TransferCoding::Unsupported)
+    } else if let Some(enc) = encoding {
+        Some(TransferCoding::Compressed(enc))
+    } else {
+        Some(TransferCoding::Chunked)
+    }
+}
+
+/// Connection type from `Connection` header value
+///
+/// Value could be a list of options, `Keep-Alive, TE`, `close` takes
+/// precedence over `keep-alive`.
+fn connection_type(value: &str) -> Option<ConnectionType> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("upgrade") {
+        return Some(ConnectionType::Upgrade);
+    }
+
+    let mut ctype = None;
+    for opt in value.split(',').map(|opt| opt.trim()) {
+        if opt.eq_ignore_ascii_case("close") {
+            return Some(ConnectionType::Close);
+        } else if opt.eq_ignore_ascii_case("keep-alive") {
+            ctype = Some(ConnectionType::KeepAlive);
+        }
+    }
+    ctype
+}
+
 impl MessageType for Request {
     fn set_connection_type(&mut self, ctype: Option<ConnectionType>) {
+        // http/1.0 does not support upgrade, rfc7230 section 6.7
+        if ctype == Some(ConnectionType::Upgrade)
+            && self.head().version < Version::HTTP_11
+        {
+            return;
+        }
         if let Some(ctype) = ctype {
             self.head_mut().set_connection_type(ctype);
         }
     }
 
-    fn set_expect(&mut self) {
-        self.head_mut().set_expect();
+    fn set_expect(&mut self, known: bool) {
+        // http/1.0 clients do not expect `100 Continue`, rfc7231 section 5.1.1
+        if self.head().version < Version::HTTP_11 {
+            return;
+        }
+        if known {
+            self.head_mut().set_expect();
+        } else {
+            self.head_mut().set_unknown_expectation();
+        }
     }
 
     fn headers_mut(&mut self) -> &mut HeaderMap {
@@ -185,7 +387,12 @@ impl MessageType for Request {
     }
 
     #[allow(clippy::uninit_assumed_init)]
-    fn decode(src: &mut BytesMut) -> Result<Option<(Self, PayloadType)>, ParseError> {
+    fn decode(
+        src: &mut BytesMut,
+        max_size: usize,
+        empty_values: EmptyHeaderValue,
+        validation: HeaderValidation,
+    ) -> Result<Option<(Self, PayloadType)>, ParseError> {
         // Unsafe: we read this data only after httparse parses headers into.
         // performance bump for pipeline benchmarks.
         let mut headers: [HeaderIndex; MAX_HEADERS] =
@@ -198,6 +405,10 @@ impl MessageType for Request {
             let mut req = httparse::Request::new(&mut parsed);
             match req.parse(src)? {
                 httparse::Status::Complete(len) => {
+                    if len > max_size {
+                        trace!("Max request head size reached, closing");
+                        return Err(ParseError::TooLarge);
+                    }
                     let method = Method::from_bytes(req.method.unwrap().as_bytes())
                         .map_err(|_| ParseError::Method)?;
                     let uri = Uri::try_from(req.path.unwrap())?;
@@ -211,8 +422,8 @@ impl MessageType for Request {
                     (len, method, uri, version, req.headers.len())
                 }
                 httparse::Status::Partial => {
-                    if src.len() >= MAX_BUFFER_SIZE {
-                        trace!("MAX_BUFFER_SIZE unprocessed data reached, closing");
+                    if src.len() >= max_size {
+                        trace!("Max request head size reached, closing");
                         return Err(ParseError::TooLarge);
                     }
                     return Ok(None);
@@ -221,13 +432,37 @@ impl MessageType for Request {
         };
 
         let mut msg = Request::new();
+        msg.head_mut().version = ver;
 
         // convert headers
-        let length = msg.set_headers(&src.split_to(len).freeze(), &headers[..h_len])?;
+        let length = msg.set_headers(
+            &src.split_to(len).freeze(),
+            &headers[..h_len],
+            empty_values,
+        )?;
+
+        // http/1.0 does not support transfer codings, message framing
+        // could not be trusted, rfc7230 section 3.3.1
+        if ver == Version::HTTP_10
+            && msg.headers().contains_key(header::TRANSFER_ENCODING)
+        {
+            debug!("transfer-encoding in http/1.0 request");
+            return Err(ParseError::Header);
+        }
+
+        // chunked must be the final transfer coding of a request,
+        // otherwise payload length could not be determined,
+        // rfc7230 section 3.3.3
+        if transfer_coding(msg.headers()) == Some(TransferCoding::NotChunked) {
+            debug!("chunked is not the final transfer coding");
+            return Err(ParseError::Header);
+        }
 
         // payload decoder
         let decoder = match length {
             PayloadLength::Payload(pl) => pl,
+            // upgrade is ignored for http/1.0 requests
+            PayloadLength::Upgrade if ver == Version::HTTP_10 => PayloadType::None,
             PayloadLength::Upgrade => {
                 // upgrade(websocket)
                 PayloadType::Stream(PayloadDecoder::eof())
@@ -241,15 +476,76 @@ impl MessageType for Request {
             }
         };
 
+        validate_host(&mut msg, &uri, validation)?;
+
         let head = msg.head_mut();
         head.uri = uri;
         head.method = method;
-        head.version = ver;
 
         Ok(Some((msg, decoder)))
     }
 }
 
+/// Check `Host` header, https://tools.ietf.org/html/rfc7230#section-5.4
+fn validate_host(
+    msg: &mut Request,
+    uri: &Uri,
+    validation: HeaderValidation,
+) -> Result<(), ParseError> {
+    let mut anomalies = HeaderAnomalies::default();
+
+    if msg.headers().get_all(header::HOST).nth(1).is_some() {
+        if validation == HeaderValidation::Strict {
+            debug!("multiple Host headers");
+            return Err(ParseError::Header);
+        }
+        let host = msg.headers().get(header::HOST).unwrap().clone();
+        msg.headers_mut().insert(header::HOST, host);
+        anomalies.duplicate_host = true;
+    }
+
+    // absolute-form request target
+    if let Some(authority) = uri.authority() {
+        let matches = msg
+            .headers()
+            .get(header::HOST)
+            .map(|host| host_matches(uri, authority, host))
+            .unwrap_or(true);
+        if !matches {
+            if validation == HeaderValidation::Strict {
+                debug!("Host header does not match request target");
+                return Err(ParseError::Header);
+            }
+            let host = HeaderValue::from_str(authority.as_str())
+                .map_err(|_| ParseError::Header)?;
+            msg.headers_mut().insert(header::HOST, host);
+            anomalies.host_mismatch = true;
+        }
+    }
+
+    if anomalies != HeaderAnomalies::default() {
+        msg.extensions_mut().insert(anomalies);
+    }
+    Ok(())
+}
+
+/// Compare `Host` header with request target authority, missing port
+/// is equal to scheme's default port
+fn host_matches(uri: &Uri, authority: &Authority, host: &HeaderValue) -> bool {
+    let default_port = match uri.scheme_str() {
+        Some("https") | Some("wss") => 443,
+        _ => 80,
+    };
+    match Authority::try_from(host.as_bytes()) {
+        Ok(host) => {
+            host.host().eq_ignore_ascii_case(authority.host())
+                && host.port_u16().unwrap_or(default_port)
+                    == authority.port_u16().unwrap_or(default_port)
+        }
+        Err(_) => false,
+    }
+}
+
 impl MessageType for ResponseHead {
     fn set_connection_type(&mut self, ctype: Option<ConnectionType>) {
         if let Some(ctype) = ctype {
@@ -257,14 +553,19 @@ impl MessageType for ResponseHead {
         }
     }
 
-    fn set_expect(&mut self) {}
+    fn set_expect(&mut self, _: bool) {}
 
     fn headers_mut(&mut self) -> &mut HeaderMap {
         &mut self.headers
     }
 
     #[allow(clippy::uninit_assumed_init)]
-    fn decode(src: &mut BytesMut) -> Result<Option<(Self, PayloadType)>, ParseError> {
+    fn decode(
+        src: &mut BytesMut,
+        max_size: usize,
+        empty_values: EmptyHeaderValue,
+        _: HeaderValidation,
+    ) -> Result<Option<(Self, PayloadType)>, ParseError> {
         // Unsafe: we read this data only after httparse parses headers into.
         // performance bump for pipeline benchmarks.
         let mut headers: [HeaderIndex; MAX_HEADERS] =
@@ -289,8 +590,8 @@ impl MessageType for ResponseHead {
                     (len, version, status, res.headers.len())
                 }
                 httparse::Status::Partial => {
-                    return if src.len() >= MAX_BUFFER_SIZE {
-                        error!("MAX_BUFFER_SIZE unprocessed data reached, closing");
+                    return if src.len() >= max_size {
+                        error!("Max response head size reached, closing");
                         Err(ParseError::TooLarge)
                     } else {
                         Ok(None)
@@ -303,22 +604,31 @@ impl MessageType for ResponseHead {
         msg.version = ver;
 
         // convert headers
-        let length = msg.set_headers(&src.split_to(len).freeze(), &headers[..h_len])?;
+        let length = msg.set_headers(
+            &src.split_to(len).freeze(),
+            &headers[..h_len],
+            empty_values,
+        )?;
 
         // message payload
-        let decoder = if let PayloadLength::Payload(pl) = length {
-            pl
-        } else if status == StatusCode::SWITCHING_PROTOCOLS {
+        let decoder = if status == StatusCode::SWITCHING_PROTOCOLS {
             // switching protocol or connect
             PayloadType::Stream(PayloadDecoder::eof())
+        } else if status.is_informational()
+            || status == StatusCode::NO_CONTENT
+            || status == StatusCode::NOT_MODIFIED
+        {
+            // these responses never have body, regardless of headers
+            PayloadType::None
+        } else if let PayloadLength::Payload(pl) = length {
+            pl
+        } else if msg.headers.contains_key(header::CONTENT_LENGTH) {
+            // content-length: 0
+            PayloadType::None
         } else {
-            // for HTTP/1.0 read to eof and close connection
-            if msg.version == Version::HTTP_10 {
-                msg.set_connection_type(ConnectionType::Close);
-                PayloadType::Payload(PayloadDecoder::eof())
-            } else {
-                PayloadType::None
-            }
+            // no content-length and no chunking, read to eof and close connection
+            msg.set_connection_type(ConnectionType::Close);
+            PayloadType::Payload(PayloadDecoder::eof())
         };
 
         Ok(Some((msg, decoder)))
@@ -342,8 +652,15 @@ impl HeaderIndex {
             let name_start = header.name.as_ptr() as usize - bytes_ptr;
             let name_end = name_start + header.name.len();
             indices.name = (name_start, name_end);
-            let value_start = header.value.as_ptr() as usize - bytes_ptr;
-            let value_end = value_start + header.value.len();
+            // trailing whitespace is not part of header value
+            let value = header.value;
+            let len = value
+                .iter()
+                .rposition(|b| *b != b' ' && *b != b'\t')
+                .map(|pos| pos + 1)
+                .unwrap_or(0);
+            let value_start = value.as_ptr() as usize - bytes_ptr;
+            let value_end = value_start + len;
             indices.value = (value_start, value_end);
         }
     }
@@ -360,26 +677,42 @@ pub(super) enum PayloadItem {
 ///
 /// If a message body does not include a Transfer-Encoding, it *should*
 /// include a Content-Length header.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub(super) struct PayloadDecoder {
     kind: Kind,
+    trailers: Option<HeaderMap>,
 }
 
 impl PayloadDecoder {
     pub(super) fn length(x: u64) -> PayloadDecoder {
         PayloadDecoder {
             kind: Kind::Length(x),
+            trailers: None,
         }
     }
 
     pub(super) fn chunked() -> PayloadDecoder {
         PayloadDecoder {
             kind: Kind::Chunked(ChunkedState::Size, 0),
+            trailers: None,
         }
     }
 
     pub(super) fn eof() -> PayloadDecoder {
-        PayloadDecoder { kind: Kind::Eof }
+        PayloadDecoder {
+            kind: Kind::Eof,
+            trailers: None,
+        }
+    }
+
+    /// Check if payload is delimited by connection close
+    pub(super) fn is_eof(&self) -> bool {
+        self.kind == Kind::Eof
+    }
+
+    /// Trailer headers of chunked payload, available after eof
+    pub(super) fn take_trailers(&mut self) -> Option<HeaderMap> {
+        self.trailers.take()
     }
 }
 
@@ -449,6 +782,22 @@ impl Decoder for PayloadDecoder {
             }
             Kind::Chunked(ref mut state, ref mut size) => {
                 loop {
+                    // last chunk is followed by trailer headers
+                    if *state == ChunkedState::EndCr
+                        && !src.is_empty()
+                        && src[0] != b'\r'
+                    {
+                        return match parse_trailers(src)? {
+                            Some(trailers) => {
+                                trace!("End of chunked stream with trailers");
+                                *state = ChunkedState::End;
+                                self.trailers = Some(trailers);
+                                Ok(Some(PayloadItem::Eof))
+                            }
+                            None => Ok(None),
+                        };
+                    }
+
                     let mut buf = None;
                     // advances the chunked state
                     *state = match state.step(src, size, &mut buf) {
@@ -479,6 +828,34 @@ impl Decoder for PayloadDecoder {
     }
 }
 
+fn parse_trailers(src: &mut BytesMut) -> Result<Option<HeaderMap>, ParseError> {
+    let mut parsed = [httparse::EMPTY_HEADER; MAX_HEADERS];
+
+    let (len, headers) = match httparse::parse_headers(src, &mut parsed)? {
+        httparse::Status::Complete((len, raw)) => {
+            let mut headers = HeaderMap::with_capacity(raw.len());
+            for h in raw {
+                let name = HeaderName::from_bytes(h.name.as_bytes())
+                    .map_err(|_| ParseError::Header)?;
+                let value =
+                    HeaderValue::from_bytes(h.value).map_err(|_| ParseError::Header)?;
+                headers.append(name, value);
+            }
+            (len, headers)
+        }
+        httparse::Status::Partial => {
+            return if src.len() >= MAX_BUFFER_SIZE {
+                trace!("MAX_BUFFER_SIZE unprocessed data reached, closing");
+                Err(ParseError::TooLarge)
+            } else {
+                Ok(None)
+            };
+        }
+    };
+    src.advance(len);
+    Ok(Some(headers))
+}
+
 macro_rules! byte (
     ($rdr:ident) => ({
         if $rdr.len() > 0 {
@@ -718,6 +1095,60 @@ mod tests {
         assert_eq!(req.path(), "/test");
     }
 
+    #[test]
+    fn test_parse_max_size() {
+        let mut reader = MessageDecoder::<Request>::default();
+        reader.set_max_size(32);
+
+        let mut buf = BytesMut::from("GET /test HTTP/1.1\r\n\r\n");
+        assert!(reader.decode(&mut buf).unwrap().is_some());
+
+        // partial head reached max size
+        let mut buf = BytesMut::from("GET /test HTTP/1.1\r\nx-header: 0123");
+        assert!(matches!(reader.decode(&mut buf), Err(ParseError::TooLarge)));
+
+        // complete head is larger than max size
+        let mut buf =
+            BytesMut::from("GET /test HTTP/1.1\r\nx-header: 0123456789\r\n\r\n");
+        assert!(matches!(reader.decode(&mut buf), Err(ParseError::TooLarge)));
+    }
+
+    #[test]
+    fn test_parse_max_line_size() {
+        let mut reader = MessageDecoder::<Request>::default();
+        reader.set_max_line_size(18);
+
+        let mut buf =
+            BytesMut::from("\r\nGET /test HTTP/1.1\r\nx-header: 0123456789\r\n\r\n");
+        assert!(reader.decode(&mut buf).unwrap().is_some());
+
+        // partial line reached max size
+        let mut buf = BytesMut::from("GET /test1 HTTP/1.");
+        assert!(reader.decode(&mut buf).unwrap().is_none());
+        let mut buf = BytesMut::from("GET /test1 HTTP/1.1\r");
+        assert!(matches!(
+            reader.decode(&mut buf),
+            Err(ParseError::LineTooLong)
+        ));
+
+        // complete head with too long line
+        let mut buf = BytesMut::from("GET /test1 HTTP/1.1\r\n\r\n");
+        assert!(matches!(
+            reader.decode(&mut buf),
+            Err(ParseError::LineTooLong)
+        ));
+
+        let mut reader = MessageDecoder::<ResponseHead>::default();
+        reader.set_max_line_size(16);
+        let mut buf = BytesMut::from("HTTP/1.1 200 OK\r\n\r\n");
+        assert!(reader.decode(&mut buf).unwrap().is_some());
+        let mut buf = BytesMut::from("HTTP/1.1 200 Fine\r\n\r\n");
+        assert!(matches!(
+            reader.decode(&mut buf),
+            Err(ParseError::LineTooLong)
+        ));
+    }
+
     #[test]
     fn test_parse_post() {
         let mut buf = BytesMut::from("POST /test2 HTTP/1.0\r\n\r\n");
@@ -818,8 +1249,55 @@ mod tests {
             .get_all(SET_COOKIE)
             .map(|v| v.to_str().unwrap().to_owned())
             .collect();
-        assert_eq!(val[1], "c1=cookie1");
-        assert_eq!(val[0], "c2=cookie2");
+        assert_eq!(val[0], "c1=cookie1");
+        assert_eq!(val[1], "c2=cookie2");
+    }
+
+    #[test]
+    fn test_headers_trailing_whitespace() {
+        let mut buf = BytesMut::from(
+            "GET /test HTTP/1.1\r\n\
+             x-first: value  \t\r\n\
+             x-second:   inner  space \r\n\
+             content-length: 4 \r\n\r\n",
+        );
+        let req = parse_ready!(&mut buf);
+
+        assert_eq!(req.headers().get("x-first").unwrap(), "value");
+        assert_eq!(req.headers().get("x-second").unwrap(), "inner  space");
+        assert_eq!(req.headers().get(header::CONTENT_LENGTH).unwrap(), "4");
+    }
+
+    #[test]
+    fn test_headers_empty_value() {
+        let data = "GET /test HTTP/1.1\r\n\
+                    x-empty:\r\n\
+                    x-space:   \r\n\
+                    x-value: 1\r\n\r\n";
+
+        // accept
+        let req = parse_ready!(&mut BytesMut::from(data));
+        assert_eq!(req.headers().get("x-empty").unwrap(), "");
+        assert_eq!(req.headers().get("x-space").unwrap(), "");
+        assert_eq!(req.headers().get("x-value").unwrap(), "1");
+
+        // reject
+        let mut reader = MessageDecoder::<Request>::default();
+        reader.set_empty_values(EmptyHeaderValue::Reject);
+        assert!(matches!(
+            reader.decode(&mut BytesMut::from(data)),
+            Err(ParseError::Header)
+        ));
+        let mut buf = BytesMut::from("GET /test HTTP/1.1\r\nx-value: 1\r\n\r\n");
+        assert!(reader.decode(&mut buf).unwrap().is_some());
+
+        // trim
+        let mut reader = MessageDecoder::<Request>::default();
+        reader.set_empty_values(EmptyHeaderValue::Trim);
+        let (req, _) = reader.decode(&mut BytesMut::from(data)).unwrap().unwrap();
+        assert!(!req.headers().contains_key("x-empty"));
+        assert!(!req.headers().contains_key("x-space"));
+        assert_eq!(req.headers().get("x-value").unwrap(), "1");
     }
 
     #[test]
@@ -944,6 +1422,110 @@ mod tests {
         assert_eq!(req.head().connection_type(), ConnectionType::Upgrade);
     }
 
+    #[test]
+    fn test_conn_list() {
+        let mut buf = BytesMut::from(
+            "GET /test HTTP/1.0\r\n\
+             connection: Keep-Alive, TE\r\n\r\n",
+        );
+        let req = parse_ready!(&mut buf);
+        assert_eq!(req.head().connection_type(), ConnectionType::KeepAlive);
+
+        let mut buf = BytesMut::from(
+            "GET /test HTTP/1.1\r\n\
+             connection: keep-alive, close\r\n\r\n",
+        );
+        let req = parse_ready!(&mut buf);
+        assert_eq!(req.head().connection_type(), ConnectionType::Close);
+    }
+
+    #[test]
+    fn test_conn_upgrade_1_0() {
+        let mut buf = BytesMut::from(
+            "GET /test HTTP/1.0\r\n\
+             upgrade: websocket\r\n\
+             connection: upgrade\r\n\r\n\
+             some raw data",
+        );
+        let mut reader = MessageDecoder::<Request>::default();
+        let (req, pl) = reader.decode(&mut buf).unwrap().unwrap();
+        assert!(!req.upgrade());
+        assert_eq!(req.head().connection_type(), ConnectionType::Close);
+        assert!(matches!(pl, PayloadType::None));
+    }
+
+    #[test]
+    fn test_expect_1_0() {
+        let mut buf = BytesMut::from(
+            "POST /test HTTP/1.0\r\n\
+             expect: 100-continue\r\n\
+             content-length: 4\r\n\r\n",
+        );
+        let req = parse_ready!(&mut buf);
+        assert!(!req.head().expect());
+
+        let mut buf = BytesMut::from(
+            "POST /test HTTP/1.1\r\n\
+             expect: 100-continue\r\n\
+             content-length: 4\r\n\r\n",
+        );
+        let req = parse_ready!(&mut buf);
+        assert!(req.head().expect());
+    }
+
+    #[test]
+    fn test_unknown_expectation() {
+        for (value, expect, unknown) in &[
+            ("100-Continue", true, false),
+            ("foo", false, true),
+            ("100-continue, foo", true, true),
+            ("100-foo", false, true),
+        ] {
+            let mut buf = BytesMut::from(
+                format!(
+                    "POST /test HTTP/1.1\r\nexpect: {}\r\ncontent-length: 4\r\n\r\n",
+                    value
+                )
+                .as_str(),
+            );
+            let req = parse_ready!(&mut buf);
+            assert_eq!(req.head().expect(), *expect);
+            assert_eq!(req.head().unknown_expectation(), *unknown);
+        }
+
+        let mut buf = BytesMut::from(
+            "POST /test HTTP/1.0\r\n\
+             expect: foo\r\n\
+             content-length: 4\r\n\r\n",
+        );
+        let req = parse_ready!(&mut buf);
+        assert!(!req.head().unknown_expectation());
+    }
+
+    #[test]
+    fn test_transfer_encoding_1_0() {
+        let mut buf = BytesMut::from(
+            "POST /test HTTP/1.0\r\n\
+             transfer-encoding: chunked\r\n\r\n",
+        );
+        expect_parse_err!(&mut buf);
+
+        let mut buf = BytesMut::from(
+            "POST /test HTTP/1.0\r\n\
+             transfer-encoding: chunked\r\n\
+             content-length: 4\r\n\r\n",
+        );
+        expect_parse_err!(&mut buf);
+    }
+
+    #[test]
+    fn test_no_host_1_0() {
+        let mut buf = BytesMut::from("GET /test HTTP/1.0\r\n\r\n");
+        let req = parse_ready!(&mut buf);
+        assert!(req.headers().get(header::HOST).is_none());
+        assert_eq!(req.version(), Version::HTTP_10);
+    }
+
     #[test]
     fn test_conn_upgrade_connect_method() {
         let mut buf = BytesMut::from(
@@ -969,18 +1551,76 @@ mod tests {
             unreachable!("Error");
         }
 
-        // type in chunked
+        // type in chunked, chunked is not the final coding
         let mut buf = BytesMut::from(
             "GET /test HTTP/1.1\r\n\
              transfer-encoding: chnked\r\n\r\n",
         );
-        let req = parse_ready!(&mut buf);
+        expect_parse_err!(&mut buf);
+    }
 
-        if let Ok(val) = req.chunked() {
-            assert!(!val);
-        } else {
-            unreachable!("Error");
+    #[test]
+    fn test_transfer_coding() {
+        fn coding(values: &[&'static str]) -> Option<TransferCoding> {
+            let mut headers = HeaderMap::new();
+            for val in values {
+                headers.append(header::TRANSFER_ENCODING, HeaderValue::from_static(val));
+            }
+            transfer_coding(&headers)
         }
+
+        assert_eq!(coding(&[]), None);
+        assert_eq!(coding(&["chunked"]), Some(TransferCoding::Chunked));
+        assert_eq!(coding(&["Chunked "]), Some(TransferCoding::Chunked));
+        assert_eq!(
+            coding(&["gzip, chunked"]),
+            Some(TransferCoding::Compressed(ContentEncoding::Gzip))
+        );
+        assert_eq!(
+            coding(&["x-gzip", "chunked"]),
+            Some(TransferCoding::Compressed(ContentEncoding::Gzip))
+        );
+        assert_eq!(
+            coding(&["deflate,chunked"]),
+            Some(TransferCoding::Compressed(ContentEncoding::Deflate))
+        );
+        assert_eq!(coding(&["br, chunked"]), Some(TransferCoding::Unsupported));
+        assert_eq!(
+            coding(&["gzip, gzip, chunked"]),
+            Some(TransferCoding::Unsupported)
+        );
+        assert_eq!(coding(&["gzip"]), Some(TransferCoding::NotChunked));
+        assert_eq!(coding(&["chunked, gzip"]), Some(TransferCoding::NotChunked));
+        assert_eq!(
+            coding(&["chunked", "chunked"]),
+            Some(TransferCoding::NotChunked)
+        );
+        assert_eq!(coding(&[""]), Some(TransferCoding::NotChunked));
+    }
+
+    #[test]
+    fn test_request_transfer_codings() {
+        let mut buf = BytesMut::from(
+            "POST /test HTTP/1.1\r\n\
+             transfer-encoding: gzip, chunked\r\n\r\n",
+        );
+        let mut reader = MessageDecoder::<Request>::default();
+        let (_, pl) = reader.decode(&mut buf).unwrap().unwrap();
+        assert!(matches!(pl.unwrap().kind, Kind::Chunked(..)));
+
+        // chunked must be the final coding
+        let mut buf = BytesMut::from(
+            "POST /test HTTP/1.1\r\n\
+             transfer-encoding: chunked, gzip\r\n\r\n",
+        );
+        expect_parse_err!(&mut buf);
+
+        let mut buf = BytesMut::from(
+            "POST /test HTTP/1.1\r\n\
+             transfer-encoding: chunked\r\n\
+             transfer-encoding: chunked\r\n\r\n",
+        );
+        expect_parse_err!(&mut buf);
     }
 
     #[test]
@@ -1003,6 +1643,137 @@ mod tests {
         expect_parse_err!(&mut buf);
     }
 
+    #[test]
+    fn test_headers_content_length_list() {
+        let mut buf = BytesMut::from(
+            "GET /test HTTP/1.1\r\n\
+             content-length: 4, 4\r\n\r\nbody",
+        );
+        let mut reader = MessageDecoder::<Request>::default();
+        let (_, pl) = reader.decode(&mut buf).unwrap().unwrap();
+        let mut pl = pl.unwrap();
+        assert_eq!(
+            pl.decode(&mut buf).unwrap().unwrap().chunk().as_ref(),
+            b"body"
+        );
+
+        let mut buf = BytesMut::from(
+            "GET /test HTTP/1.1\r\n\
+             content-length: 4\r\n\
+             content-length: 4\r\n\r\nbody",
+        );
+        let (_, pl) = reader.decode(&mut buf).unwrap().unwrap();
+        let mut pl = pl.unwrap();
+        assert_eq!(
+            pl.decode(&mut buf).unwrap().unwrap().chunk().as_ref(),
+            b"body"
+        );
+    }
+
+    #[test]
+    fn test_headers_content_length_conflict() {
+        let cases = [
+            "content-length: 4, 5\r\n",
+            "content-length: 4\r\ncontent-length: 5\r\n",
+            "content-length: 0\r\ncontent-length: 5\r\n",
+            "content-length: 4,\r\n",
+        ];
+        for case in cases.iter() {
+            let data = format!("GET /test HTTP/1.1\r\n{}\r\n", case);
+            for validation in
+                [HeaderValidation::Strict, HeaderValidation::Lenient].iter()
+            {
+                let mut reader = MessageDecoder::<Request>::default();
+                reader.set_validation(*validation);
+                assert!(
+                    matches!(
+                        reader.decode(&mut BytesMut::from(data.as_str())),
+                        Err(ParseError::Header)
+                    ),
+                    "{:?}",
+                    case
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_header_name_whitespace() {
+        let data = "GET /test HTTP/1.1\r\nhost : example.com\r\n\r\n";
+        for validation in [HeaderValidation::Strict, HeaderValidation::Lenient].iter() {
+            let mut reader = MessageDecoder::<Request>::default();
+            reader.set_validation(*validation);
+            assert!(reader.decode(&mut BytesMut::from(data)).is_err());
+        }
+    }
+
+    #[test]
+    fn test_duplicate_host() {
+        let data = "GET /test HTTP/1.1\r\n\
+                    host: example.com\r\n\
+                    host: other.com\r\n\r\n";
+        expect_parse_err!(&mut BytesMut::from(data));
+
+        let mut reader = MessageDecoder::<Request>::default();
+        reader.set_validation(HeaderValidation::Lenient);
+        let (req, _) = reader.decode(&mut BytesMut::from(data)).unwrap().unwrap();
+        let hosts: Vec<_> = req.headers().get_all(header::HOST).collect();
+        assert_eq!(hosts, vec!["example.com"]);
+        assert_eq!(
+            *req.extensions().get::<HeaderAnomalies>().unwrap(),
+            HeaderAnomalies {
+                duplicate_host: true,
+                host_mismatch: false,
+            }
+        );
+
+        // no anomalies
+        let mut buf = BytesMut::from("GET /test HTTP/1.1\r\nhost: example.com\r\n\r\n");
+        let (req, _) = reader.decode(&mut buf).unwrap().unwrap();
+        assert!(req.extensions().get::<HeaderAnomalies>().is_none());
+    }
+
+    #[test]
+    fn test_host_mismatch() {
+        let data = "GET http://example.com/test HTTP/1.1\r\n\
+                    host: other.com\r\n\r\n";
+        expect_parse_err!(&mut BytesMut::from(data));
+
+        let mut reader = MessageDecoder::<Request>::default();
+        reader.set_validation(HeaderValidation::Lenient);
+        let (req, _) = reader.decode(&mut BytesMut::from(data)).unwrap().unwrap();
+        assert_eq!(req.headers().get(header::HOST).unwrap(), "example.com");
+        assert_eq!(
+            *req.extensions().get::<HeaderAnomalies>().unwrap(),
+            HeaderAnomalies {
+                duplicate_host: false,
+                host_mismatch: true,
+            }
+        );
+
+        // default port, case insensitive
+        let mut buf = BytesMut::from(
+            "GET http://Example.com:80/test HTTP/1.1\r\nhost: example.COM\r\n\r\n",
+        );
+        let req = parse_ready!(&mut buf);
+        assert_eq!(req.headers().get(header::HOST).unwrap(), "example.COM");
+
+        let mut buf = BytesMut::from(
+            "GET https://example.com/test HTTP/1.1\r\nhost: example.com:443\r\n\r\n",
+        );
+        parse_ready!(&mut buf);
+
+        // different port
+        let mut buf = BytesMut::from(
+            "GET http://example.com:8080/test HTTP/1.1\r\nhost: example.com\r\n\r\n",
+        );
+        expect_parse_err!(&mut buf);
+
+        // missing host
+        let mut buf = BytesMut::from("GET http://example.com/test HTTP/1.1\r\n\r\n");
+        parse_ready!(&mut buf);
+    }
+
     #[test]
     fn test_invalid_header() {
         let mut buf = BytesMut::from(
@@ -1199,6 +1970,31 @@ mod tests {
         assert!(msg.eof());
     }
 
+    #[test]
+    fn test_parse_chunked_payload_trailers() {
+        let mut buf = BytesMut::from(
+            "HTTP/1.1 200 Ok\r\n\
+             transfer-encoding: chunked\r\n\r\n",
+        );
+
+        let mut reader = MessageDecoder::<ResponseHead>::default();
+        let (_msg, pl) = reader.decode(&mut buf).unwrap().unwrap();
+        let mut pl = pl.unwrap();
+
+        buf.extend(b"4\r\ndata\r\n0\r\ngrpc-status: 0\r\n");
+        let chunk = pl.decode(&mut buf).unwrap().unwrap().chunk();
+        assert_eq!(chunk, Bytes::from_static(b"data"));
+        assert!(pl.decode(&mut buf).unwrap().is_none());
+
+        buf.extend(b"grpc-message: ok\r\n\r\nHTTP/1.1");
+        assert!(pl.decode(&mut buf).unwrap().unwrap().eof());
+        assert_eq!(&buf[..], b"HTTP/1.1");
+
+        let trailers = pl.take_trailers().unwrap();
+        assert_eq!(trailers.get("grpc-status").unwrap(), "0");
+        assert_eq!(trailers.get("grpc-message").unwrap(), "ok");
+    }
+
     #[test]
     fn test_response_http10_read_until_eof() {
         let mut buf = BytesMut::from(&"HTTP/1.0 200 Ok\r\n\r\ntest data"[..]);
@@ -1210,4 +2006,62 @@ mod tests {
         let chunk = pl.decode(&mut buf).unwrap().unwrap();
         assert_eq!(chunk, PayloadItem::Chunk(Bytes::from_static(b"test data")));
     }
+
+    #[test]
+    fn test_response_http11_read_until_eof() {
+        let mut buf = BytesMut::from("HTTP/1.1 200 Ok\r\n\r\ntest data");
+
+        let mut reader = MessageDecoder::<ResponseHead>::default();
+        let (msg, pl) = reader.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(msg.connection_type(), ConnectionType::Close);
+        let mut pl = pl.unwrap();
+        assert!(pl.is_eof());
+
+        let chunk = pl.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(chunk, PayloadItem::Chunk(Bytes::from_static(b"test data")));
+
+        let mut buf = BytesMut::from("HTTP/1.1 200 Ok\r\ncontent-length: 0\r\n\r\n");
+        let (msg, pl) = reader.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(msg.connection_type(), ConnectionType::KeepAlive);
+        assert!(matches!(pl, PayloadType::None));
+    }
+
+    #[test]
+    fn test_response_no_body() {
+        let mut reader = MessageDecoder::<ResponseHead>::default();
+        for status in &["204 No Content", "304 Not Modified"] {
+            let mut buf = BytesMut::from(
+                format!("HTTP/1.1 {}\r\ncontent-length: 10\r\n\r\n", status).as_str(),
+            );
+            let (_msg, pl) = reader.decode(&mut buf).unwrap().unwrap();
+            assert!(matches!(pl, PayloadType::None));
+        }
+    }
+
+    #[cfg(feature = "preserve-header-case")]
+    #[test]
+    fn test_preserve_header_case() {
+        let mut buf = BytesMut::from(
+            "GET /test HTTP/1.1\r\n\
+             X-Custom: 1\r\n\
+             Content-Type: text\r\n\
+             x-CUSTOM: 2\r\n\
+             host: localhost\r\n\r\n",
+        );
+        let req = parse_ready!(&mut buf);
+        let headers: Vec<_> = req
+            .headers()
+            .iter_cased()
+            .map(|(_, raw, val)| (raw, val.to_str().unwrap()))
+            .collect();
+        assert_eq!(
+            headers,
+            vec![
+                (&b"X-Custom"[..], "1"),
+                (&b"Content-Type"[..], "text"),
+                (&b"x-CUSTOM"[..], "2"),
+                (&b"host"[..], "localhost"),
+            ]
+        );
+    }
 }
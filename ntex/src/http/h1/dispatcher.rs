@@ -2,7 +2,7 @@ use std::future::Future;
 use std::pin::Pin;
 use std::rc::Rc;
 use std::task::{Context, Poll};
-use std::{fmt, io, mem, net};
+use std::{cmp, fmt, io, mem, net};
 
 use bitflags::bitflags;
 use bytes::{Buf, BytesMut};
@@ -10,16 +10,34 @@ use futures::ready;
 use pin_project::pin_project;
 
 use crate::codec::{AsyncRead, AsyncWrite, Decoder, Encoder, Framed, FramedParts};
-use crate::http::body::{Body, BodySize, MessageBody, ResponseBody};
-use crate::http::config::DispatcherConfig;
-use crate::http::error::{DispatchError, ParseError, PayloadError, ResponseError};
+use crate::http::body::{BodySize, MessageBody, ResponseBody, TransformBody};
+use crate::http::config::{
+    call_service, error_response, panic_response, poll_service, DispatcherConfig,
+    ExpectContinue, Http10Body, PipelineOverflow, TransferCodings, UnknownExpectation,
+    WireDirection,
+};
+use crate::http::connection::ConnectionHandle;
+use crate::http::digest::verify_body;
+use crate::http::error::{
+    DispatchError, PanicError, ParseError, PayloadError, ResponseError, WriteError,
+};
+use crate::http::header::{ALT_SVC, CONTENT_LENGTH};
 use crate::http::helpers::DataFactory;
+use crate::http::message::ConnectionType;
 use crate::http::request::Request;
 use crate::http::response::Response;
+use crate::http::stats::{ConnectionStats, RequestStats};
+use crate::http::timing::{server_timing_name, RequestTiming};
+use crate::http::{Method, StatusCode, Uri, Version};
 use crate::rt::time::{delay_until, Delay, Instant};
+use crate::server::DrainWatch;
 use crate::Service;
 
+#[cfg(feature = "tracing")]
+use crate::http::trace::RequestSpan;
+
 use super::codec::Codec;
+use super::decoder::{transfer_coding, TransferCoding};
 use super::payload::{Payload, PayloadSender, PayloadStatus};
 use super::{Message, MessageType};
 
@@ -86,15 +104,38 @@ where
 {
     config: Rc<DispatcherConfig<S, X, U>>,
     on_connect: Option<Box<dyn DataFactory>>,
+    handle: Option<ConnectionHandle>,
+    stats: ConnectionStats,
+    // in-flight request counters
+    req_stats: Option<RequestStats>,
+    // server connections draining
+    drain: DrainWatch,
     peer_addr: Option<net::SocketAddr>,
     flags: Flags,
     error: Option<DispatchError>,
 
-    res_payload: Option<ResponseBody<B>>,
+    res_payload: Option<TransformBody<ResponseBody<B>>>,
+    // response head and buffered body for http/1.0 client
+    res_buffered: Option<(Response<()>, BytesMut)>,
     req_payload: Option<PayloadSender>,
 
     ka_expire: Instant,
     ka_timer: Option<Delay>,
+    requests: usize,
+    // requests processed since write buffer got flushed
+    pipelined: usize,
+    // pipelined responses flush timer
+    pp_timer: Option<Delay>,
+    // request method and uri, for panic and write error logging
+    req_head: Option<(Method, Uri)>,
+    // time to first byte timer
+    fb_timer: Option<Delay>,
+    // request payload timer
+    pl_timer: Option<Delay>,
+    // timing of current request, for server-timing header
+    req_timing: Option<RequestTiming>,
+    #[cfg(feature = "tracing")]
+    span: Option<RequestSpan>,
 
     io: Option<T>,
     read_buf: BytesMut,
@@ -105,7 +146,7 @@ where
 enum DispatcherMessage {
     Request(Request),
     Upgrade(Request),
-    Error(Response<()>),
+    Error(Response),
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -156,7 +197,11 @@ where
         peer_addr: Option<net::SocketAddr>,
         on_connect: Option<Box<dyn DataFactory>>,
     ) -> Self {
-        let codec = Codec::new(config.timer.clone(), config.keep_alive_enabled());
+        let mut codec = Codec::new(config.timer.clone(), config.keep_alive_enabled());
+        codec.set_max_header_size(config.max_header_size);
+        codec.set_max_line_size(config.max_line_size);
+        codec.set_empty_header_value(config.empty_header_value);
+        codec.set_header_validation(config.header_validation);
         // slow request timer
         let timeout = config.client_timer();
 
@@ -199,6 +244,13 @@ where
         } else {
             (config.now(), None)
         };
+        let handle = if config.connection_handle {
+            Some(ConnectionHandle::new(config.rate_window))
+        } else {
+            None
+        };
+
+        let stats = config.stats.connection();
 
         Dispatcher {
             call: CallState::Io,
@@ -207,6 +259,7 @@ where
                 write_buf: BytesMut::with_capacity(WRITE_HW_BUFFER_SIZE),
                 req_payload: None,
                 res_payload: None,
+                res_buffered: None,
                 error: None,
                 io: Some(io),
                 config,
@@ -215,8 +268,21 @@ where
                 flags,
                 peer_addr,
                 on_connect,
+                handle,
+                stats,
+                req_stats: None,
+                drain: DrainWatch::new(),
                 ka_expire,
                 ka_timer,
+                requests: 0,
+                pipelined: 0,
+                pp_timer: None,
+                req_head: None,
+                fb_timer: None,
+                pl_timer: None,
+                req_timing: None,
+                #[cfg(feature = "tracing")]
+                span: None,
             },
         }
     }
@@ -236,8 +302,32 @@ where
 {
     type Output = Result<(), DispatchError>;
 
-    #[allow(clippy::cognitive_complexity)]
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let result = self.as_mut().poll_dispatch(cx);
+        if let Poll::Ready(Err(ref err)) = result {
+            self.inner.config.dispatch_error(err);
+        }
+        result
+    }
+}
+
+impl<T, S, B, X, U> Dispatcher<T, S, B, X, U>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+    S: Service<Request = Request>,
+    S::Error: ResponseError,
+    S::Response: Into<Response<B>>,
+    B: MessageBody,
+    X: Service<Request = Request, Response = Request>,
+    X::Error: ResponseError,
+    U: Service<Request = (Request, Framed<T, Codec>), Response = ()>,
+    U::Error: fmt::Display,
+{
+    #[allow(clippy::cognitive_complexity)]
+    fn poll_dispatch(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), DispatchError>> {
         let mut this = self.as_mut().project();
 
         // handle upgrade request
@@ -264,24 +354,52 @@ where
                 not_completed = !this.inner.poll_read(cx);
             }
 
+            // poll service inside of request span
+            #[cfg(feature = "tracing")]
+            let _entered = this.inner.span.as_ref().map(|span| span.enter());
+
             let st = match this.call.project() {
                 CallStateProject::Service(mut fut) => {
                     loop {
                         // we have to loop because of read back-pressure,
                         // check Poll::Pending processing
-                        match fut.poll(cx) {
-                            Poll::Ready(result) => match result {
+                        match poll_service(
+                            this.inner.config.catch_panic,
+                            fut.as_mut(),
+                            cx,
+                        ) {
+                            Poll::Ready(Err(err)) => {
+                                break this.inner.process_panic(err)?
+                            }
+                            Poll::Ready(Ok(result)) => match result {
                                 Ok(res) => {
                                     break this.inner.process_response(res.into())?
                                 }
                                 Err(e) => {
-                                    let res: Response = e.into();
+                                    let res = error_response(
+                                        this.inner.config.error_handler.as_ref(),
+                                        e,
+                                    );
                                     break this.inner.process_response(
                                         res.map_body(|_, body| body.into_body()),
                                     )?;
                                 }
                             },
                             Poll::Pending => {
+                                // request payload is not received within deadline
+                                this.inner.poll_payload_timer(cx);
+
+                                // service did not respond within deadline
+                                if this.inner.poll_first_byte_timer(cx) {
+                                    let res = this.inner.config.format_error(
+                                        StatusCode::SERVICE_UNAVAILABLE,
+                                        "Service did not respond in time",
+                                    );
+                                    break this.inner.process_response(
+                                        res.map_body(|_, body| body.into_body()),
+                                    )?;
+                                }
+
                                 // if read back-pressure is enabled, we might need
                                 // to read more data (ie service future can wait for payload data)
                                 if this.inner.req_payload.is_some() && not_completed {
@@ -313,12 +431,13 @@ where
                             this.inner
                                 .write_buf
                                 .extend_from_slice(b"HTTP/1.1 100 Continue\r\n\r\n");
-                            CallProcess::Next(CallState::Service(
-                                this.inner.config.service.call(req),
-                            ))
+                            this.inner.call_service(req)?
                         }
                         Err(e) => {
-                            let res: Response = e.into();
+                            let res = error_response(
+                                this.inner.config.error_handler.as_ref(),
+                                e,
+                            );
                             this.inner.process_response(
                                 res.map_body(|_, body| body.into_body()),
                             )?
@@ -366,9 +485,10 @@ where
                                 }
                                 CallProcess::Upgrade(fut) => {
                                     this.upgrade.set(Some(fut));
-                                    return self.poll(cx);
+                                    return self.poll_dispatch(cx);
                                 }
-                                CallProcess::Io => true,
+                                // error response payload is not sent yet
+                                CallProcess::Io => this.inner.res_payload.is_none(),
                                 CallProcess::Pending => unreachable!(),
                             }
                         }
@@ -380,7 +500,7 @@ where
                 }
                 CallProcess::Upgrade(fut) => {
                     this.upgrade.set(Some(fut));
-                    return self.poll(cx);
+                    return self.poll_dispatch(cx);
                 }
             };
 
@@ -408,6 +528,20 @@ where
                     trace!("Shutdown, keep-alive is not enabled");
                     this.inner.flags.insert(Flags::SHUTDOWN);
                 }
+                // disconnect drained connection if it is idle after served request,
+                // freshly accepted connection is served and closed after response
+                else if this.inner.flags.contains(Flags::STARTED)
+                    && this.inner.is_draining()
+                    && this.inner.read_buf.is_empty()
+                {
+                    trace!("Shutdown, connection is drained");
+                    this.inner.flags.insert(Flags::SHUTDOWN);
+                } else {
+                    this.inner.drain.register(cx.waker());
+                    if let Some(ref handle) = this.inner.handle {
+                        handle.register(cx.waker());
+                    }
+                }
             }
 
             // disconnect if shutdown
@@ -420,6 +554,14 @@ where
                     continue;
                 }
 
+                // peer does not read pipelined responses
+                if this.inner.poll_pipeline_timer(cx) {
+                    return Poll::Ready(Err(this.inner.write_error(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "Peer does not read pipelined responses",
+                    ))));
+                }
+
                 // keep-alive book-keeping
                 if this.inner.ka_timer.is_some() && this.inner.poll_keepalive(cx, idle) {
                     this.inner.poll_shutdown(cx)
@@ -526,18 +668,24 @@ where
                 Poll::Ready(Ok(n)) => {
                     if n == 0 {
                         trace!("Disconnected during flush, written {}", written);
-                        return Err(DispatchError::Io(io::Error::new(
+                        return Err(self.write_error(io::Error::new(
                             io::ErrorKind::WriteZero,
                             "failed to write frame to transport",
                         )));
                     } else {
+                        if let Some(ref capture) = self.config.wire_capture {
+                            capture(
+                                WireDirection::Write,
+                                &self.write_buf[written..written + n],
+                            );
+                        }
                         written += n
                     }
                 }
                 Poll::Pending => break,
                 Poll::Ready(Err(e)) => {
                     trace!("Error during flush: {}", e);
-                    return Err(DispatchError::Io(e));
+                    return Err(self.write_error(e));
                 }
             }
         }
@@ -547,32 +695,74 @@ where
         } else {
             self.write_buf.advance(written);
         }
+        if written != 0 {
+            // peer reads responses
+            self.pp_timer = None;
+        }
         Ok(written != 0)
     }
 
+    /// Response write error with current request's method and path
+    ///
+    /// Response body is dropped, no more data could be sent to the peer.
+    fn write_error(&mut self, err: io::Error) -> DispatchError {
+        self.res_payload = None;
+        self.res_buffered = None;
+        if let Some((ref method, ref uri)) = self.req_head {
+            DispatchError::Write(WriteError::new(method.clone(), uri.path(), err))
+        } else {
+            DispatchError::Io(err)
+        }
+    }
+
     fn send_response(
         &mut self,
-        msg: Response<()>,
+        mut msg: Response<()>,
         body: ResponseBody<B>,
     ) -> Result<bool, DispatchError> {
+        let body = TransformBody::new(body, msg.head_mut());
         trace!("Sending response: {:?} body: {:?}", msg, body.size());
+        #[cfg(feature = "tracing")]
+        {
+            if let Some(span) = self.span.take() {
+                span.record_response(Some(msg.status()));
+            }
+        }
         // we dont need to process responses if socket is disconnected
         // but we still want to handle requests with app service
         // so we skip response processing for disconnected connection
         if !self.flags.contains(Flags::DISCONNECT) {
-            self.codec
-                .encode(Message::Item((msg, body.size())), &mut self.write_buf)
-                .map_err(|err| {
-                    if let Some(mut payload) = self.req_payload.take() {
-                        payload.set_error(PayloadError::Incomplete(None));
-                    }
-                    DispatchError::Io(err)
-                })?;
+            // connection served maximum number of requests
+            if self.config.max_requests_reached(self.requests) || self.is_draining() {
+                msg.head_mut().set_connection_type(ConnectionType::Close);
+            }
+            if let Some(timing) = self.req_timing.take() {
+                msg.headers_mut()
+                    .append(server_timing_name(), timing.server_timing());
+            }
+            if let Some(ref alt_svc) = self.config.alt_svc {
+                if !msg.headers().contains_key(ALT_SVC) {
+                    msg.headers_mut().insert(ALT_SVC, alt_svc.clone());
+                }
+            }
+
+            // http/1.0 client does not support chunked encoding,
+            // buffer body to calculate content-length
+            if body.size() == BodySize::Stream
+                && self.codec.version() < Version::HTTP_11
+                && self.config.http10_body != Http10Body::Close
+            {
+                self.res_buffered = Some((msg, BytesMut::new()));
+                self.res_payload = Some(body);
+                return Ok(false);
+            }
 
-            self.flags.set(Flags::KEEPALIVE, self.codec.keepalive());
+            self.encode_head(msg, body.size())?;
 
             match body.size() {
                 BodySize::None | BodySize::Empty => {
+                    self.request_finished();
+
                     // update keep-alive timer
                     if self.flags.contains(Flags::HAS_KEEPALIVE) {
                         if let Some(expire) = self.config.keep_alive_expire() {
@@ -591,6 +781,31 @@ where
         }
     }
 
+    fn request_finished(&mut self) {
+        self.req_stats = None;
+        if let Some(ref handle) = self.handle {
+            handle.request_finished();
+        }
+    }
+
+    fn encode_head(
+        &mut self,
+        msg: Response<()>,
+        size: BodySize,
+    ) -> Result<(), DispatchError> {
+        self.codec
+            .encode(Message::Item((msg, size)), &mut self.write_buf)
+            .map_err(|err| {
+                if let Some(mut payload) = self.req_payload.take() {
+                    payload.set_error(PayloadError::Incomplete(None));
+                }
+                DispatchError::Io(err)
+            })?;
+
+        self.flags.set(Flags::KEEPALIVE, self.codec.keepalive());
+        Ok(())
+    }
+
     fn poll_write(&mut self, cx: &mut Context<'_>) -> Result<PollWrite, DispatchError> {
         while let Some(ref mut stream) = self.res_payload {
             let len = self.write_buf.len();
@@ -605,14 +820,46 @@ where
                 match stream.poll_next_chunk(cx) {
                     Poll::Ready(Some(Ok(item))) => {
                         trace!("Got response chunk: {:?}", item.len());
-                        self.codec
-                            .encode(Message::Chunk(Some(item)), &mut self.write_buf)?;
+                        if let Some((_, ref mut buf)) = self.res_buffered {
+                            buf.extend_from_slice(&item);
+                            if let Http10Body::Buffer(limit) = self.config.http10_body {
+                                if buf.len() <= limit {
+                                    continue;
+                                }
+                            }
+                            // body is too large, fall back to a connection close
+                            let (msg, buf) = self.res_buffered.take().unwrap();
+                            self.encode_head(msg, BodySize::Stream)?;
+                            self.codec.encode(
+                                Message::Chunk(Some(buf.freeze())),
+                                &mut self.write_buf,
+                            )?;
+                        } else {
+                            self.codec.encode(
+                                Message::Chunk(Some(item)),
+                                &mut self.write_buf,
+                            )?;
+                        }
                     }
                     Poll::Ready(None) => {
                         trace!("Response payload eof");
-                        self.codec
-                            .encode(Message::Chunk(None), &mut self.write_buf)?;
+                        if let Some((msg, buf)) = self.res_buffered.take() {
+                            self.encode_head(msg, BodySize::Sized(buf.len() as u64))?;
+                            self.codec.encode(
+                                Message::Chunk(Some(buf.freeze())),
+                                &mut self.write_buf,
+                            )?;
+                            self.codec
+                                .encode(Message::Chunk(None), &mut self.write_buf)?;
+                        } else if let Some(trailers) = stream.trailers() {
+                            self.codec
+                                .encode_trailers(&trailers, &mut self.write_buf)?;
+                        } else {
+                            self.codec
+                                .encode(Message::Chunk(None), &mut self.write_buf)?;
+                        }
                         self.res_payload = None;
+                        self.request_finished();
 
                         // update keep-alive timer
                         if self.flags.contains(Flags::HAS_KEEPALIVE) {
@@ -653,6 +900,7 @@ where
         if !self
             .flags
             .intersects(Flags::DISCONNECT | Flags::STOP_READING)
+            && !self.pipeline_paused()
         {
             // drain until request payload is consumed and requires more data (backpressure off)
             if !self
@@ -664,6 +912,13 @@ where
                 return false;
             }
 
+            // request head must fit into max header size
+            let limit = if self.req_payload.is_none() {
+                cmp::max(BUFFER_SIZE, self.config.max_header_size)
+            } else {
+                BUFFER_SIZE
+            };
+
             // read data from socket
             let io = self.io.as_mut().unwrap();
             let buf = &mut self.read_buf;
@@ -674,7 +929,7 @@ where
                 buf.reserve(BUFFER_SIZE);
             }
 
-            while buf.len() < BUFFER_SIZE {
+            while buf.len() < limit {
                 match Pin::new(&mut *io).poll_read_buf(cx, buf) {
                     Poll::Pending => {
                         completed = true;
@@ -689,6 +944,9 @@ where
                             self.flags.insert(Flags::DISCONNECT);
                             break;
                         }
+                        if let Some(ref capture) = self.config.wire_capture {
+                            capture(WireDirection::Read, &buf[buf.len() - n..]);
+                        }
                         self.flags.remove(Flags::READ_EOF);
                     }
                     Poll::Ready(Err(e)) => {
@@ -704,11 +962,34 @@ where
         completed
     }
 
+    /// Check if pipelined requests limit is reached and
+    /// responses are not flushed yet
+    fn pipeline_paused(&self) -> bool {
+        self.config.max_pipelined != 0
+            && self.pipelined >= self.config.max_pipelined
+            && !self.write_buf.is_empty()
+    }
+
+    /// Check if connection is drained by connection handle or by server
+    fn is_draining(&self) -> bool {
+        self.drain.is_draining()
+            || self
+                .handle
+                .as_ref()
+                .map(|h| h.is_draining())
+                .unwrap_or(false)
+    }
+
     fn internal_error(&mut self, msg: &'static str) -> DispatcherMessage {
         error!("{}", msg);
         self.flags.insert(Flags::DISCONNECT | Flags::READ_EOF);
         self.error = Some(DispatchError::InternalError);
-        DispatcherMessage::Error(Response::InternalServerError().finish().drop_body())
+        DispatcherMessage::Error(
+            self.config.format_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Internal server error",
+            ),
+        )
     }
 
     fn decode_error(&mut self, e: ParseError) -> DispatcherMessage {
@@ -717,11 +998,24 @@ where
             payload.set_error(PayloadError::EncodingCorrupted);
         }
 
-        // Malformed requests should be responded with 400
-        self.flags.insert(Flags::STOP_READING);
+        // Malformed requests should be responded with 400,
+        // too large request heads with 431, too long request lines with 414
+        let res = match e {
+            ParseError::TooLarge => self.config.format_error(
+                StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE,
+                "Request header fields are too large",
+            ),
+            ParseError::LineTooLong => self
+                .config
+                .format_error(StatusCode::URI_TOO_LONG, "Request line is too long"),
+            _ => self
+                .config
+                .format_error(StatusCode::BAD_REQUEST, "Malformed request"),
+        };
+        self.flags.insert(Flags::STARTED | Flags::STOP_READING);
         self.read_buf.clear();
         self.error = Some(e.into());
-        DispatcherMessage::Error(Response::BadRequest().finish().drop_body())
+        DispatcherMessage::Error(res)
     }
 
     fn decode_payload(&mut self) -> bool {
@@ -774,7 +1068,22 @@ where
     }
 
     fn decode_message(&mut self) -> Option<DispatcherMessage> {
-        if self.flags.contains(Flags::READ_EOF) || self.read_buf.is_empty() {
+        if self.flags.contains(Flags::READ_EOF)
+            || self.read_buf.is_empty()
+            || self.config.max_requests_reached(self.requests)
+        {
+            return None;
+        }
+
+        // previous responses are flushed, request is not pipelined
+        if self.write_buf.is_empty() {
+            self.pipelined = 0;
+        } else if self.pipeline_paused() {
+            if self.config.pipeline_overflow == PipelineOverflow::Close {
+                trace!("Pipelined requests limit is reached, close connection");
+                self.flags.insert(Flags::STOP_READING);
+                self.read_buf.clear();
+            }
             return None;
         }
 
@@ -784,13 +1093,28 @@ where
 
                 match msg {
                     Message::Item(mut req) => {
+                        self.requests += 1;
+                        self.pipelined += 1;
+                        self.req_stats = Some(self.stats.request());
                         let pl = self.codec.message_type();
                         req.head_mut().peer_addr = self.peer_addr;
+                        self.config.rewrite_uri(&mut req.head_mut().uri);
 
                         // set on_connect data
                         if let Some(ref on_connect) = self.on_connect {
                             on_connect.set(&mut req.extensions_mut());
                         }
+                        if let Some(ref handle) = self.handle {
+                            handle.request_started();
+                            req.extensions_mut().insert(handle.clone());
+                        }
+                        if self.config.request_timing {
+                            let timing = RequestTiming::new();
+                            req.extensions_mut().insert(timing);
+                            if self.config.server_timing {
+                                self.req_timing = Some(timing);
+                            }
+                        }
 
                         // handle upgrade request
                         if pl == MessageType::Stream && self.config.upgrade.is_some() {
@@ -804,6 +1128,21 @@ where
                                     req.replace_payload(crate::http::Payload::H1(pl));
                                 req = req1;
                                 self.req_payload = Some(ps);
+                                self.pl_timer = self.config.payload_timer();
+                            }
+                            #[cfg(feature = "compress")]
+                            {
+                                if self.config.transfer_codings
+                                    == TransferCodings::Decompress
+                                {
+                                    decompress_body(
+                                        &mut req,
+                                        self.config.max_decompressed_size,
+                                    );
+                                }
+                            }
+                            if self.config.verify_digest {
+                                verify_body(&mut req);
                             }
 
                             Some(DispatcherMessage::Request(req))
@@ -834,10 +1173,14 @@ where
             if Pin::new(ka_timer).poll(cx).is_ready() {
                 // timeout on first request (slow request) return 408
                 trace!("Slow request timeout");
-                let _ = self.send_response(
-                    Response::RequestTimeout().finish().drop_body(),
-                    ResponseBody::Other(Body::Empty),
-                );
+                let (res, body) = self
+                    .config
+                    .format_error(StatusCode::REQUEST_TIMEOUT, "Request timeout")
+                    .replace_body(());
+                // error body is in memory, write it before shutdown
+                if let Ok(false) = self.send_response(res, body.into_body()) {
+                    let _ = self.poll_write(cx);
+                }
                 self.flags.insert(Flags::STARTED | Flags::SHUTDOWN);
                 return true;
             }
@@ -865,10 +1208,85 @@ where
         false
     }
 
+    /// Check if transfer codings of request payload could be decoded
+    fn supported_coding(&self, req: &Request) -> bool {
+        match transfer_coding(req.headers()) {
+            Some(TransferCoding::Unsupported) => false,
+            Some(TransferCoding::Compressed(_)) => {
+                cfg!(feature = "compress")
+                    && self.config.transfer_codings == TransferCodings::Decompress
+            }
+            _ => true,
+        }
+    }
+
+    /// Poll request payload timer.
+    ///
+    /// On expiration request payload gets terminated with timeout error,
+    /// connection is closed after response.
+    fn poll_payload_timer(&mut self, cx: &mut Context<'_>) {
+        if self.req_payload.is_none() {
+            self.pl_timer = None;
+        } else if let Some(ref mut timer) = self.pl_timer {
+            if Pin::new(timer).poll(cx).is_ready() {
+                trace!("Request payload timeout");
+                if let Some(mut payload) = self.req_payload.take() {
+                    payload.set_error(PayloadError::Timeout);
+                }
+                self.pl_timer = None;
+                self.flags.insert(Flags::STOP_READING);
+                self.read_buf.clear();
+            }
+        }
+    }
+
+    /// Poll pipelined responses timer, returns true if peer does not read
+    /// responses within deadline.
+    ///
+    /// Timer starts when reading is paused because of pipelined requests
+    /// limit and restarts on every successful write.
+    fn poll_pipeline_timer(&mut self, cx: &mut Context<'_>) -> bool {
+        if !self.pipeline_paused() {
+            self.pp_timer = None;
+            return false;
+        }
+        if self.pp_timer.is_none() {
+            self.pp_timer = self.config.pipeline_timer();
+        }
+        if let Some(ref mut timer) = self.pp_timer {
+            if Pin::new(timer).poll(cx).is_ready() {
+                trace!("Pipelined responses are not read by peer, close connection");
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Poll time to first byte timer, returns true if deadline is expired.
+    ///
+    /// Timer starts when request payload is fully received.
+    fn poll_first_byte_timer(&mut self, cx: &mut Context<'_>) -> bool {
+        if self.fb_timer.is_none() {
+            if self.req_payload.is_some() {
+                return false;
+            }
+            self.fb_timer = self.config.first_byte_timer();
+        }
+        if let Some(ref mut timer) = self.fb_timer {
+            if Pin::new(timer).poll(cx).is_ready() {
+                trace!("Time to first byte deadline expired, respond with 503");
+                return true;
+            }
+        }
+        false
+    }
+
     fn process_response(
         &mut self,
         res: Response<B>,
     ) -> Result<CallProcess<S, X, U>, DispatchError> {
+        self.fb_timer = None;
+        self.pl_timer = None;
         let (res, body) = res.replace_body(());
         if self.send_response(res, body)? {
             // response does not have body, so we can process next request
@@ -878,6 +1296,37 @@ where
         }
     }
 
+    /// Call service, panic is converted to error response if enabled
+    fn call_service(
+        &mut self,
+        req: Request,
+    ) -> Result<CallProcess<S, X, U>, DispatchError> {
+        let catch = self.config.catch_panic;
+        let service = &self.config.service;
+        match call_service(catch, || service.call(req)) {
+            Ok(fut) => Ok(CallProcess::Next(CallState::Service(fut))),
+            Err(err) => self.process_panic(err),
+        }
+    }
+
+    fn process_panic(
+        &mut self,
+        err: PanicError,
+    ) -> Result<CallProcess<S, X, U>, DispatchError> {
+        let uri = self
+            .req_head
+            .as_ref()
+            .map(|(_, uri)| uri.clone())
+            .unwrap_or_default();
+        let res = panic_response(
+            self.config.error_handler.as_ref(),
+            self.config.error_formatter.as_ref(),
+            &uri,
+            err,
+        );
+        self.process_response(res.map_body(|_, body| body.into_body()))
+    }
+
     fn process_messages(
         &mut self,
         io: CallProcess<S, X, U>,
@@ -888,16 +1337,71 @@ where
                     if self.req_payload.is_some() {
                         self.decode_payload();
                     }
+                    self.req_head =
+                        Some((req.head().method.clone(), req.head().uri.clone()));
+
+                    #[cfg(feature = "tracing")]
+                    let _entered = {
+                        let span = RequestSpan::server(req.head());
+                        let entered = span.enter();
+                        self.span = Some(span);
+                        entered
+                    };
 
-                    // Handle `EXPECT: 100-Continue` header
-                    Ok(CallProcess::Next(if req.head().expect() {
-                        CallState::Expect(self.config.expect.call(req))
+                    // Reject requests for not allowed hosts
+                    if let Some(res) = self.config.check_host(req.head()) {
+                        self.process_response(res.map_body(|_, body| body.into_body()))
+                    }
+                    // Reject unsupported transfer codings, rfc7230 section 3.3.1
+                    else if !self.supported_coding(&req) {
+                        let mut res = self.config.format_error(
+                            StatusCode::NOT_IMPLEMENTED,
+                            "Unsupported transfer coding",
+                        );
+                        res.head_mut().set_connection_type(ConnectionType::Close);
+                        self.process_response(res.map_body(|_, body| body.into_body()))
+                    }
+                    // Reject unknown expectations, rfc7231 section 5.1.1
+                    else if req.head().unknown_expectation()
+                        && self.config.unknown_expectation == UnknownExpectation::Reject
+                    {
+                        let mut res = self.config.format_error(
+                            StatusCode::EXPECTATION_FAILED,
+                            "Unsupported expectation",
+                        );
+                        res.head_mut().set_connection_type(ConnectionType::Close);
+                        self.process_response(res.map_body(|_, body| body.into_body()))
+                    } else if req.head().expect() {
+                        // Handle `EXPECT: 100-Continue` header
+                        match self.config.expect_continue {
+                            ExpectContinue::Ignore => self.call_service(req),
+                            ExpectContinue::Continue(limit)
+                                if limit != 0 && content_length(&req) > limit =>
+                            {
+                                let mut res = self.config.format_error(
+                                    StatusCode::EXPECTATION_FAILED,
+                                    "Request payload is too large",
+                                );
+                                res.head_mut()
+                                    .set_connection_type(ConnectionType::Close);
+                                self.process_response(
+                                    res.map_body(|_, body| body.into_body()),
+                                )
+                            }
+                            ExpectContinue::Continue(_) => Ok(CallProcess::Next(
+                                CallState::Expect(self.config.expect.call(req)),
+                            )),
+                        }
                     } else {
-                        CallState::Service(self.config.service.call(req))
-                    }))
+                        self.call_service(req)
+                    }
                 }
                 // switch to upgrade handler
                 DispatcherMessage::Upgrade(req) => {
+                    if let Some(res) = self.config.check_host(req.head()) {
+                        return self
+                            .process_response(res.map_body(|_, body| body.into_body()));
+                    }
                     self.flags.insert(Flags::UPGRADE);
                     let mut parts = FramedParts::with_read_buf(
                         self.io.take().unwrap(),
@@ -912,7 +1416,9 @@ where
                     ))
                 }
                 DispatcherMessage::Error(res) => {
-                    if self.send_response(res, ResponseBody::Other(Body::Empty))? {
+                    self.req_head = None;
+                    let (res, body) = res.replace_body(());
+                    if self.send_response(res, body.into_body())? {
                         // response does not have body, so we can process next request
                         continue;
                     } else {
@@ -925,6 +1431,27 @@ where
     }
 }
 
+/// Declared content length of the request
+fn content_length(req: &Request) -> u64 {
+    req.head()
+        .headers
+        .get(CONTENT_LENGTH)
+        .and_then(|val| val.to_str().ok())
+        .and_then(|val| val.parse().ok())
+        .unwrap_or(0)
+}
+
+#[cfg(feature = "compress")]
+/// Decompress payload of `gzip, chunked` and `deflate, chunked` requests
+fn decompress_body(req: &mut Request, limit: usize) {
+    if let Some(TransferCoding::Compressed(enc)) = transfer_coding(req.headers()) {
+        let payload = req.take_payload();
+        *req.payload() = crate::http::Payload::Stream(Box::pin(
+            crate::http::encoding::Decoder::new(payload, enc).limit(limit),
+        ));
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use bytes::Bytes;
@@ -937,8 +1464,10 @@ mod tests {
     use std::time::Duration;
 
     use super::*;
-    use crate::http::config::{DispatcherConfig, ServiceConfig};
+    use crate::http::config::{DispatcherConfig, Inner, KeepAlive, ServiceConfig};
+    use crate::http::error::WriteErrorKind;
     use crate::http::h1::{ClientCodec, ExpectHandler, UpgradeHandler};
+    use crate::http::header::CONNECTION;
     use crate::http::{body, Request, ResponseHead, StatusCode};
     use crate::rt::time::delay_for;
     use crate::service::IntoService;
@@ -1013,6 +1542,264 @@ mod tests {
         assert!(h1.inner.flags.contains(Flags::SHUTDOWN_IO));
     }
 
+    #[ntex_rt::test]
+    async fn test_wire_capture() {
+        let data = Rc::new(std::cell::RefCell::new(Vec::new()));
+        let data2 = data.clone();
+
+        let mut inner = Inner::new(KeepAlive::Timeout(5), 0, 0, 5000);
+        inner.wire_capture = Some(Rc::new(move |dir, buf: &[u8]| {
+            data2.borrow_mut().push((dir, buf.to_vec()))
+        }));
+
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+        crate::rt::spawn(
+            Dispatcher::<_, _, _, ExpectHandler, UpgradeHandler<Io>>::new(
+                Rc::new(DispatcherConfig::new(
+                    ServiceConfig(Rc::new(inner)),
+                    (|_| ok::<_, io::Error>(Response::Ok().finish())).into_service(),
+                    ExpectHandler,
+                    None,
+                )),
+                server,
+                None,
+                None,
+            ),
+        );
+
+        client.write("GET /test HTTP/1.1\r\n\r\n");
+        let buf = client.read().await.unwrap();
+
+        let data = data.borrow();
+        assert_eq!(data[0].0, WireDirection::Read);
+        assert_eq!(data[0].1, b"GET /test HTTP/1.1\r\n\r\n".to_vec());
+        assert_eq!(data[1].0, WireDirection::Write);
+        assert_eq!(&data[1].1[..], &buf[..]);
+    }
+
+    #[ntex_rt::test]
+    async fn test_max_requests() {
+        let mut inner = Inner::new(KeepAlive::Timeout(5), 0, 0, 5000);
+        inner.max_requests = 2;
+
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(4096);
+        let mut decoder = ClientCodec::default();
+        crate::rt::spawn(
+            Dispatcher::<_, _, _, ExpectHandler, UpgradeHandler<Io>>::new(
+                Rc::new(DispatcherConfig::new(
+                    ServiceConfig(Rc::new(inner)),
+                    (|_| ok::<_, io::Error>(Response::Ok().finish())).into_service(),
+                    ExpectHandler,
+                    None,
+                )),
+                server,
+                None,
+                None,
+            ),
+        );
+
+        client.write("GET /test HTTP/1.1\r\n\r\n");
+        let mut buf = client.read().await.unwrap();
+        let res = load(&mut decoder, &mut buf);
+        assert!(res.status.is_success());
+        assert!(res.headers.get(CONNECTION).is_none());
+        assert!(!client.is_server_dropped());
+
+        // pipelined request after last allowed one is not processed
+        client.write("GET /test HTTP/1.1\r\n\r\n");
+        client.write("GET /test HTTP/1.1\r\n\r\n");
+        let mut buf = client.read().await.unwrap();
+        let res = load(&mut decoder, &mut buf);
+        assert!(res.status.is_success());
+        assert_eq!(res.headers.get(CONNECTION).unwrap(), "close");
+        assert!(decoder.decode(&mut buf).unwrap().is_none());
+
+        // connection is closed by server
+        delay_for(Duration::from_millis(50)).await;
+        assert!(client.is_closed());
+    }
+
+    fn h1_pipelined(
+        stream: Io,
+        max: usize,
+        overflow: PipelineOverflow,
+        num: Arc<AtomicUsize>,
+    ) -> impl Future<Output = Result<(), DispatchError>> + Unpin {
+        let mut inner = Inner::new(KeepAlive::Timeout(5), 0, 0, 5000);
+        inner.max_pipelined = max;
+        inner.pipeline_overflow = overflow;
+
+        Dispatcher::<_, _, _, ExpectHandler, UpgradeHandler<Io>>::new(
+            Rc::new(DispatcherConfig::new(
+                ServiceConfig(Rc::new(inner)),
+                (move |_| {
+                    num.fetch_add(1, Ordering::Relaxed);
+                    ok::<_, io::Error>(Response::Ok().finish())
+                })
+                .into_service(),
+                ExpectHandler,
+                None,
+            )),
+            stream,
+            None,
+            None,
+        )
+    }
+
+    #[ntex_rt::test]
+    async fn test_pipeline_backpressure() {
+        let num = Arc::new(AtomicUsize::new(0));
+        let (client, server) = Io::create();
+        let mut h1 =
+            h1_pipelined(server, 2, PipelineOverflow::Backpressure(5000), num.clone());
+
+        // do not allow to write to socket
+        client.remote_buffer_cap(0);
+        client.write("GET /test HTTP/1.1\r\n\r\n");
+        client.write("GET /test HTTP/1.1\r\n\r\n");
+        client.write("GET /test HTTP/1.1\r\n\r\n");
+        assert!(lazy(|cx| Pin::new(&mut h1).poll(cx)).await.is_pending());
+        assert_eq!(num.load(Ordering::Relaxed), 2);
+
+        // reading is paused until responses are flushed
+        client.write("GET /test HTTP/1.1\r\n\r\n");
+        assert!(lazy(|cx| Pin::new(&mut h1).poll(cx)).await.is_pending());
+        assert_eq!(num.load(Ordering::Relaxed), 2);
+        assert_eq!(client.remote_buffer(|buf| buf.len()), 22);
+
+        client.remote_buffer_cap(4096);
+        assert!(lazy(|cx| Pin::new(&mut h1).poll(cx)).await.is_pending());
+        assert_eq!(num.load(Ordering::Relaxed), 4);
+
+        let mut decoder = ClientCodec::default();
+        let mut buf = client.read_any();
+        for _ in 0..4 {
+            assert!(load(&mut decoder, &mut buf).status.is_success());
+        }
+        assert!(!client.is_server_dropped());
+    }
+
+    #[ntex_rt::test]
+    async fn test_pipeline_backpressure_timeout() {
+        let num = Arc::new(AtomicUsize::new(0));
+        let (client, server) = Io::create();
+        let mut h1 =
+            h1_pipelined(server, 1, PipelineOverflow::Backpressure(100), num.clone());
+
+        // peer does not read responses
+        client.remote_buffer_cap(0);
+        client.write("GET /test HTTP/1.1\r\n\r\n");
+        client.write("GET /test HTTP/1.1\r\n\r\n");
+        assert!(lazy(|cx| Pin::new(&mut h1).poll(cx)).await.is_pending());
+        assert_eq!(num.load(Ordering::Relaxed), 1);
+
+        delay_for(Duration::from_millis(300)).await;
+        match lazy(|cx| Pin::new(&mut h1).poll(cx)).await {
+            Poll::Ready(Err(DispatchError::Write(err))) => {
+                assert_eq!(err.kind(), WriteErrorKind::Timeout)
+            }
+            _ => panic!(),
+        }
+        assert_eq!(num.load(Ordering::Relaxed), 1);
+    }
+
+    #[ntex_rt::test]
+    async fn test_pipeline_close() {
+        let num = Arc::new(AtomicUsize::new(0));
+        let (client, server) = Io::create();
+        let mut h1 = h1_pipelined(server, 1, PipelineOverflow::Close, num.clone());
+
+        client.remote_buffer_cap(0);
+        client.write("GET /test HTTP/1.1\r\n\r\n");
+        client.write("GET /test HTTP/1.1\r\n\r\n");
+        client.write("GET /test HTTP/1.1\r\n\r\n");
+        assert!(lazy(|cx| Pin::new(&mut h1).poll(cx)).await.is_pending());
+        assert_eq!(num.load(Ordering::Relaxed), 1);
+
+        // pending responses are flushed, pipelined requests are not processed
+        client.remote_buffer_cap(4096);
+        assert!(lazy(|cx| Pin::new(&mut h1).poll(cx)).await.is_pending());
+        assert_eq!(num.load(Ordering::Relaxed), 1);
+
+        let mut decoder = ClientCodec::default();
+        let mut buf = client.read_any();
+        assert!(load(&mut decoder, &mut buf).status.is_success());
+        assert!(decoder.decode(&mut buf).unwrap().is_none());
+        assert!(client.is_closed());
+    }
+
+    fn spawn_h10(body: Http10Body) -> Io {
+        let mut inner = Inner::new(KeepAlive::Timeout(5), 0, 0, 5000);
+        inner.http10_body = body;
+
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(4096);
+        crate::rt::spawn(
+            Dispatcher::<_, _, _, ExpectHandler, UpgradeHandler<Io>>::new(
+                Rc::new(DispatcherConfig::new(
+                    ServiceConfig(Rc::new(inner)),
+                    (|_| {
+                        ok::<_, io::Error>(Response::Ok().streaming(
+                            futures::stream::iter(vec![
+                                Ok::<_, io::Error>(Bytes::from_static(b"data")),
+                                Ok(Bytes::from_static(b"data")),
+                            ]),
+                        ))
+                    })
+                    .into_service(),
+                    ExpectHandler,
+                    None,
+                )),
+                server,
+                None,
+                None,
+            ),
+        );
+        client
+    }
+
+    #[ntex_rt::test]
+    async fn test_http10_body() {
+        // body is delimited by connection close
+        let client = spawn_h10(Http10Body::Close);
+        client.write("GET /test HTTP/1.0\r\nconnection: keep-alive\r\n\r\n");
+        delay_for(Duration::from_millis(50)).await;
+        let data = String::from_utf8(client.read_any().to_vec()).unwrap();
+        assert!(data.starts_with("HTTP/1.0 200 OK\r\n"));
+        assert!(!data.contains("transfer-encoding"));
+        assert!(!data.contains("content-length"));
+        assert!(data.ends_with("\r\n\r\ndatadata"));
+        assert!(client.is_closed());
+
+        // body is buffered
+        let client = spawn_h10(Http10Body::Buffer(1024));
+        client.write("GET /test HTTP/1.0\r\nconnection: keep-alive\r\n\r\n");
+        delay_for(Duration::from_millis(50)).await;
+        let data = String::from_utf8(client.read_any().to_vec()).unwrap();
+        assert!(data.contains("content-length: 8\r\n"));
+        assert!(data.contains("connection: keep-alive\r\n"));
+        assert!(data.ends_with("\r\n\r\ndatadata"));
+        assert!(!client.is_closed());
+
+        // body is larger than buffer limit
+        let client = spawn_h10(Http10Body::Buffer(4));
+        client.write("GET /test HTTP/1.0\r\nconnection: keep-alive\r\n\r\n");
+        delay_for(Duration::from_millis(50)).await;
+        let data = String::from_utf8(client.read_any().to_vec()).unwrap();
+        assert!(!data.contains("content-length"));
+        assert!(data.ends_with("\r\n\r\ndatadata"));
+        assert!(client.is_closed());
+
+        // http/1.1 client still gets chunked body
+        let client = spawn_h10(Http10Body::Buffer(1024));
+        client.write("GET /test HTTP/1.1\r\n\r\n");
+        delay_for(Duration::from_millis(50)).await;
+        let data = String::from_utf8(client.read_any().to_vec()).unwrap();
+        assert!(data.contains("transfer-encoding: chunked\r\n"));
+    }
+
     #[ntex_rt::test]
     async fn test_pipeline() {
         let (client, server) = Io::create();
@@ -1153,7 +1940,10 @@ mod tests {
         assert!(h1.inner.flags.contains(Flags::SHUTDOWN));
 
         let mut buf = client.read().await.unwrap();
-        assert_eq!(load(&mut decoder, &mut buf).status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            load(&mut decoder, &mut buf).status,
+            StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE
+        );
     }
 
     #[ntex_rt::test]
@@ -1293,4 +2083,80 @@ mod tests {
         client.close().await;
         assert!(lazy(|cx| Pin::new(&mut h1).poll(cx)).await.is_ready());
     }
+
+    #[ntex_rt::test]
+    async fn test_write_error() {
+        struct Stream(Rc<std::cell::Cell<bool>>);
+
+        impl body::MessageBody for Stream {
+            fn size(&self) -> body::BodySize {
+                body::BodySize::Stream
+            }
+            fn poll_next_chunk(
+                &mut self,
+                _: &mut Context<'_>,
+            ) -> Poll<Option<Result<Bytes, Box<dyn std::error::Error>>>> {
+                Poll::Pending
+            }
+        }
+
+        impl Drop for Stream {
+            fn drop(&mut self) {
+                self.0.set(true);
+            }
+        }
+
+        let errors = Rc::new(std::cell::RefCell::new(Vec::new()));
+        let errors2 = errors.clone();
+        let mut inner = Inner::new(KeepAlive::Timeout(5), 0, 0, 5000);
+        inner.on_dispatch_error = Some(Rc::new(move |err: &DispatchError| {
+            if let DispatchError::Write(ref err) = err {
+                errors2.borrow_mut().push((
+                    err.kind(),
+                    err.method().clone(),
+                    err.path().to_string(),
+                ));
+            }
+        }));
+
+        let dropped = Rc::new(std::cell::Cell::new(false));
+        let dropped2 = dropped.clone();
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(4096);
+        client.write_error(io::Error::new(io::ErrorKind::BrokenPipe, "closed"));
+        let mut h1 = Dispatcher::<_, _, _, ExpectHandler, UpgradeHandler<Io>>::new(
+            Rc::new(DispatcherConfig::new(
+                ServiceConfig(Rc::new(inner)),
+                (move |_| {
+                    ok::<_, io::Error>(
+                        Response::Ok().message_body(Stream(dropped2.clone())),
+                    )
+                })
+                .into_service(),
+                ExpectHandler,
+                None,
+            )),
+            server,
+            None,
+            None,
+        );
+
+        client.write("POST /upload HTTP/1.1\r\n\r\n");
+        match lazy(|cx| Pin::new(&mut h1).poll(cx)).await {
+            Poll::Ready(Err(DispatchError::Write(err))) => {
+                assert_eq!(err.kind(), crate::http::error::WriteErrorKind::BrokenPipe)
+            }
+            _ => panic!(),
+        }
+        // response body is dropped with the failure
+        assert!(dropped.get());
+        assert_eq!(
+            &*errors.borrow(),
+            &[(
+                crate::http::error::WriteErrorKind::BrokenPipe,
+                Method::POST,
+                "/upload".to_string()
+            )]
+        );
+    }
 }
@@ -4,10 +4,13 @@ use std::ptr::copy_nonoverlapping;
 use std::{cmp, io, mem, ptr, slice};
 
 use bytes::{BufMut, BytesMut};
+use log::warn;
 
 use crate::http::body::BodySize;
 use crate::http::config::DateService;
-use crate::http::header::{map, CONNECTION, CONTENT_LENGTH, DATE, TRANSFER_ENCODING};
+use crate::http::header::{
+    HeaderName, HeaderValue, CONNECTION, CONTENT_LENGTH, DATE, TRANSFER_ENCODING,
+};
 use crate::http::helpers;
 use crate::http::message::{ConnectionType, RequestHeadType};
 use crate::http::response::Response;
@@ -52,19 +55,18 @@ pub(super) trait MessageType: Sized {
         timer: &DateService,
     ) -> io::Result<()> {
         let chunked = self.chunked();
-        let mut skip_len = length != BodySize::Stream;
+        // response framing is computed from body size only,
+        // user supplied content-length and transfer-encoding are ignored.
+        // request keeps user supplied headers only for streaming body
+        // without chunked encoding
+        let response = self.status().is_some();
+        let mut skip_len = response || length != BodySize::Stream;
 
         // Content length
         if let Some(status) = self.status() {
-            match status {
-                StatusCode::NO_CONTENT
-                | StatusCode::CONTINUE
-                | StatusCode::PROCESSING => length = BodySize::None,
-                StatusCode::SWITCHING_PROTOCOLS => {
-                    skip_len = true;
-                    length = BodySize::Stream;
-                }
-                _ => (),
+            length = response_body_size(status, length);
+            if status == StatusCode::SWITCHING_PROTOCOLS {
+                length = BodySize::Stream;
             }
         }
         match length {
@@ -73,9 +75,10 @@ pub(super) trait MessageType: Sized {
             BodySize::Sized(len) => write_content_length(len, dst),
             BodySize::Stream => {
                 if chunked {
+                    skip_len = true;
                     dst.extend_from_slice(b"\r\ntransfer-encoding: chunked\r\n")
                 } else {
-                    skip_len = false;
+                    skip_len = response;
                     dst.extend_from_slice(b"\r\n");
                 }
             }
@@ -96,84 +99,66 @@ pub(super) trait MessageType: Sized {
         // merging headers from head and extra headers. HeaderMap::new() does not allocate.
         let empty_headers = HeaderMap::new();
         let extra_headers = self.extra_headers().unwrap_or(&empty_headers);
+        #[cfg(not(feature = "preserve-header-case"))]
         let headers = self
             .headers()
-            .inner
             .iter()
             .filter(|(name, _)| !extra_headers.contains_key(*name))
-            .chain(extra_headers.inner.iter());
+            .chain(extra_headers.iter())
+            .map(|(name, value)| (name, name.as_str().as_bytes(), value));
+        #[cfg(feature = "preserve-header-case")]
+        let headers = self
+            .headers()
+            .iter_cased()
+            .filter(|(name, _, _)| !extra_headers.contains_key(*name))
+            .chain(extra_headers.iter_cased());
 
         // write headers
         let mut pos = 0;
         let mut has_date = false;
         let mut remaining = dst.capacity() - dst.len();
         let mut buf = dst.bytes_mut().as_mut_ptr() as *mut u8;
-        for (key, value) in headers {
+        for (key, k, value) in headers {
             match *key {
                 CONNECTION => continue,
-                TRANSFER_ENCODING | CONTENT_LENGTH if skip_len => continue,
+                TRANSFER_ENCODING | CONTENT_LENGTH if skip_len => {
+                    if response && conflicts(key, value, length, chunked) {
+                        warn!(
+                            "Response {:?} header conflicts with body size {:?}, ignored",
+                            key, length
+                        );
+                    }
+                    continue;
+                }
                 DATE => {
                     has_date = true;
                 }
                 _ => (),
             }
-            let k = key.as_str().as_bytes();
-            match value {
-                map::Value::One(ref val) => {
-                    let v = val.as_ref();
-                    let v_len = v.len();
-                    let k_len = k.len();
-                    let len = k_len + v_len + 4;
-
-                    unsafe {
-                        if len > remaining {
-                            dst.advance_mut(pos);
-                            pos = 0;
-                            dst.reserve(len * 2);
-                            remaining = dst.capacity() - dst.len();
-                            buf = dst.bytes_mut().as_mut_ptr() as *mut u8;
-                        }
-                        copy_nonoverlapping(k.as_ptr(), buf, k_len);
-                        buf = buf.add(k_len);
-                        copy_nonoverlapping(b": ".as_ptr(), buf, 2);
-                        buf = buf.add(2);
-                        copy_nonoverlapping(v.as_ptr(), buf, v_len);
-                        buf = buf.add(v_len);
-                        copy_nonoverlapping(b"\r\n".as_ptr(), buf, 2);
-                        buf = buf.add(2);
-                    }
-                    pos += len;
-                    remaining -= len;
-                }
-                map::Value::Multi(ref vec) => {
-                    for val in vec {
-                        let v = val.as_ref();
-                        let v_len = v.len();
-                        let k_len = k.len();
-                        let len = k_len + v_len + 4;
-
-                        unsafe {
-                            if len > remaining {
-                                dst.advance_mut(pos);
-                                pos = 0;
-                                dst.reserve(len * 2);
-                                remaining = dst.capacity() - dst.len();
-                                buf = dst.bytes_mut().as_mut_ptr() as *mut u8;
-                            }
-                            copy_nonoverlapping(k.as_ptr(), buf, k_len);
-                            buf = buf.add(k_len);
-                            copy_nonoverlapping(b": ".as_ptr(), buf, 2);
-                            buf = buf.add(2);
-                            copy_nonoverlapping(v.as_ptr(), buf, v_len);
-                            buf = buf.add(v_len);
-                            copy_nonoverlapping(b"\r\n".as_ptr(), buf, 2);
-                            buf = buf.add(2);
-                        };
-                        pos += len;
-                        remaining -= len;
-                    }
+            let v = value.as_ref();
+            let v_len = v.len();
+            let k_len = k.len();
+            let len = k_len + v_len + 4;
+
+            unsafe {
+                if len > remaining {
+                    dst.advance_mut(pos);
+                    pos = 0;
+                    dst.reserve(len * 2);
+                    remaining = dst.capacity() - dst.len();
+                    buf = dst.bytes_mut().as_mut_ptr() as *mut u8;
                 }
+                copy_nonoverlapping(k.as_ptr(), buf, k_len);
+                buf = buf.add(k_len);
+                copy_nonoverlapping(b": ".as_ptr(), buf, 2);
+                buf = buf.add(2);
+                copy_nonoverlapping(v.as_ptr(), buf, v_len);
+                buf = buf.add(v_len);
+                copy_nonoverlapping(b"\r\n".as_ptr(), buf, 2);
+                buf = buf.add(2);
             }
+            pos += len;
+            remaining -= len;
         }
         unsafe {
             dst.advance_mut(pos);
@@ -276,6 +261,15 @@ impl<T: MessageType> MessageEncoder<T> {
         self.te.encode_eof(buf)
     }
 
+    /// Encode eof with trailer headers
+    pub(super) fn encode_trailers(
+        &mut self,
+        trailers: &HeaderMap,
+        buf: &mut BytesMut,
+    ) -> io::Result<()> {
+        self.te.encode_trailers(trailers, buf)
+    }
+
     pub(super) fn encode(
         &mut self,
         dst: &mut BytesMut,
@@ -287,6 +281,12 @@ impl<T: MessageType> MessageEncoder<T> {
         ctype: ConnectionType,
         timer: &DateService,
     ) -> io::Result<()> {
+        // responses to informational requests, 204 and 304 do not have body
+        let length = match message.status() {
+            Some(status) => response_body_size(status, length),
+            None => length,
+        };
+
         // transfer encoding
         if !head {
             self.te = match length {
@@ -310,6 +310,44 @@ impl<T: MessageType> MessageEncoder<T> {
     }
 }
 
+/// Body size of response, body is not allowed for informational,
+/// `204 No Content` and `304 Not Modified` responses
+fn response_body_size(status: StatusCode, length: BodySize) -> BodySize {
+    match status {
+        StatusCode::NO_CONTENT | StatusCode::NOT_MODIFIED => BodySize::None,
+        StatusCode::SWITCHING_PROTOCOLS => length,
+        _ if status.is_informational() => BodySize::None,
+        _ => length,
+    }
+}
+
+/// Check if user supplied framing header conflicts with body size
+fn conflicts(
+    name: &HeaderName,
+    value: &HeaderValue,
+    length: BodySize,
+    chunked: bool,
+) -> bool {
+    if *name == CONTENT_LENGTH {
+        match length {
+            BodySize::Empty => *value != "0",
+            BodySize::Sized(len) => value
+                .to_str()
+                .ok()
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .map(|v| v != len)
+                .unwrap_or(true),
+            // 304 response could contain content length of selected representation
+            BodySize::None => false,
+            BodySize::Stream => true,
+        }
+    } else {
+        !(length == BodySize::Stream
+            && chunked
+            && value.as_bytes().eq_ignore_ascii_case(b"chunked"))
+    }
+}
+
 /// Encoders to handle different Transfer-Encodings.
 #[derive(Debug)]
 pub(super) struct TransferEncoding {
@@ -425,6 +463,38 @@ impl TransferEncoding {
             }
         }
     }
+
+    /// Encode eof with trailer headers.
+    ///
+    /// Trailers could be sent only with chunked encoding,
+    /// for other encodings they are dropped.
+    #[inline]
+    pub(super) fn encode_trailers(
+        &mut self,
+        trailers: &HeaderMap,
+        buf: &mut BytesMut,
+    ) -> io::Result<()> {
+        match self.kind {
+            TransferEncodingKind::Chunked(ref mut eof) => {
+                if !*eof {
+                    *eof = true;
+                    buf.extend_from_slice(b"0\r\n");
+                    for (key, value) in trailers.iter() {
+                        let k = key.as_str().as_bytes();
+                        let v = value.as_ref();
+                        buf.reserve(k.len() + v.len() + 4);
+                        buf.extend_from_slice(k);
+                        buf.extend_from_slice(b": ");
+                        buf.extend_from_slice(v);
+                        buf.extend_from_slice(b"\r\n");
+                    }
+                    buf.extend_from_slice(b"\r\n");
+                }
+                Ok(())
+            }
+            _ => self.encode_eof(buf),
+        }
+    }
 }
 
 const DEC_DIGITS_LUT: &[u8] = b"0001020304050607080910111213141516171819\
@@ -580,7 +650,7 @@ mod tests {
     use bytes::Bytes;
 
     use super::*;
-    use crate::http::header::{HeaderValue, AUTHORIZATION};
+    use crate::http::header::{HeaderName, HeaderValue, AUTHORIZATION};
     use crate::http::RequestHead;
 
     #[test]
@@ -597,6 +667,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_chunked_te_trailers() {
+        let mut bytes = BytesMut::new();
+        let mut trailers = HeaderMap::new();
+        trailers.insert(
+            HeaderName::from_static("grpc-status"),
+            HeaderValue::from_static("0"),
+        );
+
+        let mut enc = TransferEncoding::chunked();
+        assert!(!enc.encode(b"test", &mut bytes).unwrap());
+        enc.encode_trailers(&trailers, &mut bytes).unwrap();
+        assert_eq!(
+            bytes.split().freeze(),
+            Bytes::from_static(b"4\r\ntest\r\n0\r\ngrpc-status: 0\r\n\r\n")
+        );
+
+        // trailers are dropped for non-chunked encoding
+        let mut enc = TransferEncoding::length(4);
+        assert!(enc.encode(b"test", &mut bytes).unwrap());
+        enc.encode_trailers(&trailers, &mut bytes).unwrap();
+        assert_eq!(bytes.split().freeze(), Bytes::from_static(b"test"));
+    }
+
     #[test]
     fn test_extra_headers() {
         let mut bytes = BytesMut::with_capacity(2048);
@@ -631,6 +725,177 @@ mod tests {
         assert!(data.contains("date: date\r\n"));
     }
 
+    #[ntex_rt::test]
+    async fn test_request_chunked_te() {
+        let mut bytes = BytesMut::with_capacity(2048);
+
+        // proxied request carries transfer-encoding of original request
+        let mut head = RequestHead::default();
+        head.headers
+            .insert(TRANSFER_ENCODING, HeaderValue::from_static("chunked"));
+        let mut head = RequestHeadType::Owned(head);
+
+        let _ = head.encode_headers(
+            &mut bytes,
+            Version::HTTP_11,
+            BodySize::Stream,
+            ConnectionType::KeepAlive,
+            &DateService::default(),
+        );
+        let data =
+            String::from_utf8(Vec::from(bytes.split().freeze().as_ref())).unwrap();
+        assert_eq!(data.matches("transfer-encoding").count(), 1);
+        assert!(data.contains("transfer-encoding: chunked\r\n"));
+    }
+
+    #[cfg(feature = "preserve-header-case")]
+    #[test]
+    fn test_preserve_header_case() {
+        use crate::http::header::CONTENT_TYPE;
+
+        let mut bytes = BytesMut::with_capacity(2048);
+
+        let mut head = RequestHead::default();
+        head.headers
+            .append_cased("X-First", HeaderValue::from_static("1"))
+            .unwrap();
+        head.headers
+            .append_cased("Authorization", HeaderValue::from_static("auth"))
+            .unwrap();
+        head.headers
+            .append(CONTENT_TYPE, HeaderValue::from_static("text"));
+        head.headers
+            .append_cased("x-FIRST", HeaderValue::from_static("2"))
+            .unwrap();
+
+        let mut extra_headers = HeaderMap::new();
+        extra_headers
+            .append_cased("AUTHORIZATION", HeaderValue::from_static("another"))
+            .unwrap();
+        extra_headers.insert(DATE, HeaderValue::from_static("date"));
+
+        let mut head = RequestHeadType::Rc(Rc::new(head), Some(extra_headers));
+        let _ = head.encode_headers(
+            &mut bytes,
+            Version::HTTP_11,
+            BodySize::Empty,
+            ConnectionType::KeepAlive,
+            &DateService::default(),
+        );
+        assert_eq!(
+            bytes.split().freeze(),
+            Bytes::from_static(
+                b"\r\ncontent-length: 0\r\nX-First: 1\r\ncontent-type: text\r\n\
+                  x-FIRST: 2\r\nAUTHORIZATION: another\r\ndate: date\r\n\r\n"
+            )
+        );
+    }
+
+    fn encode_response<B>(
+        res: Response<B>,
+        length: BodySize,
+        head: bool,
+    ) -> (String, MessageEncoder<Response<()>>) {
+        let mut res = res.drop_body();
+        let mut bytes = BytesMut::with_capacity(2048);
+        let mut enc = MessageEncoder::<Response<()>>::default();
+        enc.encode(
+            &mut bytes,
+            &mut res,
+            head,
+            false,
+            Version::HTTP_11,
+            length,
+            ConnectionType::KeepAlive,
+            &DateService::default(),
+        )
+        .unwrap();
+        (String::from_utf8(bytes.to_vec()).unwrap(), enc)
+    }
+
+    #[ntex_rt::test]
+    async fn test_response_framing_override() {
+        // mismatched content-length is replaced
+        let mut res = Response::new(StatusCode::OK);
+        res.headers_mut()
+            .insert(CONTENT_LENGTH, HeaderValue::from_static("100"));
+        let (data, _) = encode_response(res, BodySize::Sized(10), false);
+        assert!(data.contains("content-length: 10\r\n"));
+        assert!(!data.contains("content-length: 100"));
+
+        // stream is always chunked on http/1.1
+        let mut res = Response::new(StatusCode::OK);
+        res.headers_mut()
+            .insert(CONTENT_LENGTH, HeaderValue::from_static("100"));
+        res.headers_mut()
+            .insert(TRANSFER_ENCODING, HeaderValue::from_static("gzip"));
+        let (data, mut enc) = encode_response(res, BodySize::Stream, false);
+        assert!(data.contains("transfer-encoding: chunked\r\n"));
+        assert!(!data.contains("content-length"));
+        assert!(!data.contains("gzip"));
+        let mut bytes = BytesMut::new();
+        enc.encode_chunk(b"test", &mut bytes).unwrap();
+        assert_eq!(&bytes[..], b"4\r\ntest\r\n");
+
+        // stream without chunking is close delimited
+        let mut res = Response::new(StatusCode::OK);
+        res.head_mut().no_chunking(true);
+        res.headers_mut()
+            .insert(CONTENT_LENGTH, HeaderValue::from_static("100"));
+        let (data, _) = encode_response(res, BodySize::Stream, false);
+        assert!(!data.contains("content-length"));
+        assert!(!data.contains("transfer-encoding"));
+    }
+
+    #[ntex_rt::test]
+    async fn test_response_without_body() {
+        for status in &[
+            StatusCode::NO_CONTENT,
+            StatusCode::NOT_MODIFIED,
+            StatusCode::CONTINUE,
+        ] {
+            let mut res = Response::new(*status);
+            res.headers_mut()
+                .insert(TRANSFER_ENCODING, HeaderValue::from_static("chunked"));
+            let (data, mut enc) = encode_response(res, BodySize::Sized(4), false);
+            assert!(!data.contains("content-length"));
+            assert!(!data.contains("transfer-encoding"));
+
+            // body is not sent
+            let mut bytes = BytesMut::new();
+            assert!(enc.encode_chunk(b"test", &mut bytes).unwrap());
+            assert!(bytes.is_empty());
+        }
+
+        // head response has content-length of body, but no body
+        let res = Response::new(StatusCode::OK);
+        let (data, mut enc) = encode_response(res, BodySize::Sized(4), true);
+        assert!(data.contains("content-length: 4\r\n"));
+        let mut bytes = BytesMut::new();
+        assert!(enc.encode_chunk(b"test", &mut bytes).unwrap());
+        assert!(bytes.is_empty());
+    }
+
+    #[test]
+    fn test_framing_conflicts() {
+        let val = HeaderValue::from_static("10");
+        assert!(!conflicts(&CONTENT_LENGTH, &val, BodySize::Sized(10), true));
+        assert!(conflicts(&CONTENT_LENGTH, &val, BodySize::Sized(11), true));
+        assert!(conflicts(&CONTENT_LENGTH, &val, BodySize::Stream, true));
+        assert!(conflicts(&CONTENT_LENGTH, &val, BodySize::Empty, true));
+        assert!(!conflicts(&CONTENT_LENGTH, &val, BodySize::None, true));
+
+        let val = HeaderValue::from_static("chunked");
+        assert!(!conflicts(&TRANSFER_ENCODING, &val, BodySize::Stream, true));
+        assert!(conflicts(&TRANSFER_ENCODING, &val, BodySize::Stream, false));
+        assert!(conflicts(
+            &TRANSFER_ENCODING,
+            &val,
+            BodySize::Sized(1),
+            true
+        ));
+    }
+
     #[test]
     fn test_write_content_length() {
         let mut bytes = BytesMut::new();
@@ -16,11 +16,12 @@ pub use self::codec::Codec;
 pub use self::expect::ExpectHandler;
 pub use self::payload::Payload;
 pub use self::service::{H1Service, H1ServiceHandler};
-pub use self::upgrade::UpgradeHandler;
+pub use self::upgrade::{into_io, UpgradeHandler};
 
 pub(super) use self::dispatcher::Dispatcher;
 
 const MAX_BUFFER_SIZE: usize = 32_768;
+const MAX_LINE_SIZE: usize = 8_192;
 
 #[derive(Debug)]
 /// Codec message
@@ -9,11 +9,14 @@ use bytes::Bytes;
 use futures::Stream;
 
 use crate::http::error::PayloadError;
+use crate::http::message::pool_capacity;
 use crate::task::LocalWaker;
 
 /// max buffer size 32k
 const MAX_BUFFER_SIZE: usize = 32_768;
 
+thread_local!(static POOL: PayloadPool = PayloadPool::new());
+
 #[derive(Debug, PartialEq)]
 pub(super) enum PayloadStatus {
     Read,
@@ -43,7 +46,7 @@ impl Payload {
     ///
     /// * `Payload` - *Receiver* side of the stream
     pub fn create(eof: bool) -> (PayloadSender, Payload) {
-        let shared = Rc::new(RefCell::new(Inner::new(eof)));
+        let shared = POOL.with(|p| p.get(eof));
 
         (
             PayloadSender {
@@ -57,7 +60,7 @@ impl Payload {
     #[doc(hidden)]
     pub fn empty() -> Payload {
         Payload {
-            inner: Rc::new(RefCell::new(Inner::new(true))),
+            inner: POOL.with(|p| p.get(true)),
         }
     }
 
@@ -76,6 +79,16 @@ impl Payload {
     }
 }
 
+impl Drop for Payload {
+    fn drop(&mut self) {
+        // payload could be reused only if sender side is gone
+        if let Some(inner) = Rc::get_mut(&mut self.inner) {
+            inner.get_mut().clear();
+            let _ = POOL.try_with(|p| p.release(self.inner.clone()));
+        }
+    }
+}
+
 impl Stream for Payload {
     type Item = Result<Bytes, PayloadError>;
 
@@ -159,8 +172,20 @@ impl Inner {
         }
     }
 
+    fn clear(&mut self) {
+        self.len = 0;
+        self.err = None;
+        self.need_read = true;
+        self.items.clear();
+        self.task = LocalWaker::new();
+        self.io_task = LocalWaker::new();
+    }
+
     fn set_error(&mut self, err: PayloadError) {
         self.err = Some(err);
+        if let Some(task) = self.task.take() {
+            task.wake()
+        }
     }
 
     fn feed_eof(&mut self) {
@@ -210,6 +235,34 @@ impl Inner {
     }
 }
 
+/// Payload's objects pool
+struct PayloadPool(RefCell<Vec<Rc<RefCell<Inner>>>>, usize);
+
+impl PayloadPool {
+    fn new() -> Self {
+        let cap = pool_capacity();
+        PayloadPool(RefCell::new(Vec::with_capacity(cap)), cap)
+    }
+
+    /// Get payload from the pool
+    fn get(&self, eof: bool) -> Rc<RefCell<Inner>> {
+        if let Some(inner) = self.0.borrow_mut().pop() {
+            inner.borrow_mut().eof = eof;
+            inner
+        } else {
+            Rc::new(RefCell::new(Inner::new(eof)))
+        }
+    }
+
+    /// Release payload instance
+    fn release(&self, inner: Rc<RefCell<Inner>>) {
+        let v = &mut self.0.borrow_mut();
+        if v.len() < self.1 {
+            v.push(inner);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -227,4 +280,25 @@ mod tests {
             poll_fn(|cx| payload.readany(cx)).await.unwrap().unwrap()
         );
     }
+
+    #[ntex_rt::test]
+    async fn test_pool() {
+        let (mut sender, mut payload) = Payload::create(false);
+        sender.feed_data(Bytes::from("data"));
+        sender.set_error(PayloadError::Incomplete(None));
+        drop(sender);
+        assert!(poll_fn(|cx| payload.readany(cx)).await.unwrap().is_ok());
+        drop(payload);
+
+        // reused payload does not keep state of previous one
+        let (_sender, payload) = Payload::create(false);
+        {
+            let inner = payload.inner.borrow();
+            assert_eq!(inner.len, 0);
+            assert!(!inner.eof);
+            assert!(inner.err.is_none());
+            assert!(inner.need_read);
+            assert!(inner.items.is_empty());
+        }
+    }
 }
@@ -10,7 +10,7 @@ use futures::ready;
 
 use crate::codec::{AsyncRead, AsyncWrite, Framed};
 use crate::http::body::MessageBody;
-use crate::http::config::{DispatcherConfig, ServiceConfig};
+use crate::http::config::{set_tcp_keepalive, DispatcherConfig, ServiceConfig};
 use crate::http::error::{DispatchError, ResponseError};
 use crate::http::helpers::DataFactory;
 use crate::http::request::Request;
@@ -87,7 +87,9 @@ where
         Error = DispatchError,
         InitError = (),
     > {
-        pipeline_factory(|io: TcpStream| {
+        let ka = self.cfg.0.tcp_keepalive;
+        pipeline_factory(move |io: TcpStream| {
+            set_tcp_keepalive(&io, ka.as_ref());
             let peer_addr = io.peer_addr().ok();
             ok((io, peer_addr))
         })
@@ -131,13 +133,15 @@ mod openssl {
             Error = SslError<DispatchError>,
             InitError = (),
         > {
+            let ka = self.cfg.0.tcp_keepalive;
             pipeline_factory(
                 Acceptor::new(acceptor)
                     .timeout(self.handshake_timeout)
                     .map_err(SslError::Ssl)
                     .map_init_err(|_| panic!()),
             )
-            .and_then(|io: SslStream<TcpStream>| {
+            .and_then(move |io: SslStream<TcpStream>| {
+                set_tcp_keepalive(io.get_ref(), ka.as_ref());
                 let peer_addr = io.get_ref().peer_addr().ok();
                 ok((io, peer_addr))
             })
@@ -182,13 +186,15 @@ mod rustls {
             Error = SslError<DispatchError>,
             InitError = (),
         > {
+            let ka = self.cfg.0.tcp_keepalive;
             pipeline_factory(
                 Acceptor::new(config)
                     .timeout(self.handshake_timeout)
                     .map_err(SslError::Ssl)
                     .map_init_err(|_| panic!()),
             )
-            .and_then(|io: TlsStream<TcpStream>| {
+            .and_then(move |io: TlsStream<TcpStream>| {
+                set_tcp_keepalive(io.get_ref().0, ka.as_ref());
                 let peer_addr = io.get_ref().0.peer_addr().ok();
                 ok((io, peer_addr))
             })
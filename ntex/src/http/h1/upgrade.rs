@@ -1,16 +1,50 @@
 use std::io;
 use std::marker::PhantomData;
+use std::pin::Pin;
 use std::task::{Context, Poll};
 
-use futures::future::Ready;
+use bytes::BytesMut;
+use futures::future::{poll_fn, Ready};
 
-use crate::codec::Framed;
+use crate::codec::{AsyncWrite, Framed};
 use crate::http::h1::Codec;
 use crate::http::request::Request;
 use crate::{Service, ServiceFactory};
 
 pub struct UpgradeHandler<T>(PhantomData<T>);
 
+/// Extract underlying io stream from upgraded connection.
+///
+/// Pending data in write buffer (for example handshake response written with
+/// `Framed::send()` or `Framed::write()`) is flushed to the io stream first.
+/// Returned buffer contains bytes that are already read from the io stream
+/// but not decoded yet, bytes that client sent right after upgrade request.
+/// These bytes must be processed before any data read from io stream.
+///
+/// ```rust,no_run
+/// use ntex::codec::Framed;
+/// use ntex::http::{h1, HttpService, Request, Response};
+///
+/// let srv = HttpService::build()
+///     .upgrade(|(req, framed): (Request, Framed<_, _>)| async move {
+///         let (io, buf) = h1::into_io(framed).await?;
+///         // pass `io` and `buf` to external protocol implementation
+///         Ok::<_, std::io::Error>(())
+///     })
+///     .finish(|_| futures::future::ok::<_, std::io::Error>(Response::NotFound()))
+///     .tcp();
+/// ```
+pub async fn into_io<T>(mut framed: Framed<T, Codec>) -> io::Result<(T, BytesMut)>
+where
+    T: AsyncWrite + Unpin,
+{
+    poll_fn(|cx| framed.flush(cx)).await?;
+
+    let mut parts = framed.into_parts();
+    poll_fn(|cx| Pin::new(&mut parts.io).poll_flush(cx)).await?;
+    Ok((parts.io, parts.read_buf))
+}
+
 impl<T> ServiceFactory for UpgradeHandler<T> {
     type Config = ();
     type Request = (Request, Framed<T, Codec>);
@@ -9,32 +9,48 @@ use std::task::{Context, Poll};
 use bytes::{Bytes, BytesMut};
 use h2::server::{Connection, SendResponse};
 use h2::SendStream;
-use http::header::{HeaderValue, CONNECTION, CONTENT_LENGTH, DATE, TRANSFER_ENCODING};
+use http::header::{
+    HeaderValue, ALT_SVC, CONNECTION, CONTENT_LENGTH, DATE, TRANSFER_ENCODING,
+};
 use log::{error, trace};
 
 use crate::codec::{AsyncRead, AsyncWrite};
-use crate::http::body::{BodySize, MessageBody, ResponseBody};
-use crate::http::config::{DateService, DispatcherConfig};
-use crate::http::error::{DispatchError, ResponseError};
+use crate::http::body::{BodySize, MessageBody, ResponseBody, TransformBody};
+use crate::http::config::{
+    call_service, error_response, format_error, panic_response, poll_service,
+    DateService, DispatchErrorHook, DispatcherConfig, ErrorFormatter, ErrorHandler,
+};
+use crate::http::connection::ConnectionHandle;
+use crate::http::digest::verify_body;
+use crate::http::error::{DispatchError, PanicError, ResponseError, WriteError};
 use crate::http::helpers::DataFactory;
 use crate::http::message::ResponseHead;
 use crate::http::payload::Payload;
 use crate::http::request::Request;
 use crate::http::response::Response;
+use crate::http::stats::{ConnectionStats, RequestStats};
+use crate::http::timing::{server_timing_name, RequestTiming};
+#[cfg(feature = "tracing")]
+use crate::http::trace::RequestSpan;
+use crate::http::{Method, StatusCode, Uri};
 use crate::rt::time::{Delay, Instant};
+use crate::server::DrainWatch;
 use crate::Service;
 
-const CHUNK_SIZE: usize = 16_384;
-
 pin_project_lite::pin_project! {
     /// Dispatcher for HTTP/2 protocol
     pub struct Dispatcher<T, S: Service<Request = Request>, B: MessageBody, X, U> {
         config: Rc<DispatcherConfig<S, X, U>>,
         connection: Connection<T, Bytes>,
         on_connect: Option<Box<dyn DataFactory>>,
+        handle: Option<ConnectionHandle>,
+        stats: ConnectionStats,
+        drain: DrainWatch,
+        goaway: bool,
         peer_addr: Option<net::SocketAddr>,
         ka_expire: Instant,
         ka_timer: Option<Delay>,
+        requests: usize,
         _t: PhantomData<B>,
     }
 }
@@ -62,14 +78,26 @@ where
         } else {
             (config.now(), None)
         };
+        let handle = if config.connection_handle {
+            Some(ConnectionHandle::new(config.rate_window))
+        } else {
+            None
+        };
+
+        let stats = config.stats.connection();
 
         Dispatcher {
             config,
+            stats,
             peer_addr,
             connection,
             on_connect,
+            handle,
+            drain: DrainWatch::new(),
+            goaway: false,
             ka_expire,
             ka_timer,
+            requests: 0,
             _t: PhantomData,
         }
     }
@@ -90,10 +118,34 @@ where
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let this = self.get_mut();
 
+        // drained connection, send GOAWAY and wait for current streams
+        let draining = this.drain.is_draining()
+            || this
+                .handle
+                .as_ref()
+                .map(|h| h.is_draining())
+                .unwrap_or(false);
+        if draining {
+            if !this.goaway {
+                trace!("Connection is drained, send GOAWAY");
+                this.goaway = true;
+                this.connection.graceful_shutdown();
+            }
+        } else {
+            this.drain.register(cx.waker());
+            if let Some(ref handle) = this.handle {
+                handle.register(cx.waker());
+            }
+        }
+
         loop {
             match Pin::new(&mut this.connection).poll_accept(cx) {
                 Poll::Ready(None) => return Poll::Ready(Ok(())),
-                Poll::Ready(Some(Err(err))) => return Poll::Ready(Err(err.into())),
+                Poll::Ready(Some(Err(err))) => {
+                    let err = err.into();
+                    this.config.dispatch_error(&err);
+                    return Poll::Ready(Err(err));
+                }
                 Poll::Ready(Some(Ok((req, res)))) => {
                     // update keep-alive expire
                     if this.ka_timer.is_some() {
@@ -103,10 +155,15 @@ where
                     }
 
                     let (parts, body) = req.into_parts();
+                    let timer = if body.is_end_stream() {
+                        None
+                    } else {
+                        this.config.payload_timer()
+                    };
                     let mut req = Request::with_payload(Payload::<
                         crate::http::payload::PayloadStream,
                     >::H2(
-                        crate::http::h2::Payload::new(body),
+                        crate::http::h2::Payload::with_timer(body, timer),
                     ));
 
                     let head = &mut req.head_mut();
@@ -115,19 +172,78 @@ where
                     head.version = parts.version;
                     head.headers = parts.headers.into();
                     head.peer_addr = this.peer_addr;
+                    this.config.rewrite_uri(&mut head.uri);
+                    if this.config.verify_digest {
+                        verify_body(&mut req);
+                    }
 
                     // set on_connect data
                     if let Some(ref on_connect) = this.on_connect {
                         on_connect.set(&mut req.extensions_mut());
                     }
+                    let timing = if this.config.request_timing {
+                        let timing = RequestTiming::new();
+                        req.extensions_mut().insert(timing);
+                        Some(timing).filter(|_| this.config.server_timing)
+                    } else {
+                        None
+                    };
+                    let finished = if let Some(ref handle) = this.handle {
+                        handle.request_started();
+                        req.extensions_mut().insert(handle.clone());
+                        Some(RequestFinished(handle.clone()))
+                    } else {
+                        None
+                    };
+
+                    let req_stats = this.stats.request();
+
+                    // connection served maximum number of requests
+                    this.requests += 1;
+                    if this.config.max_requests_reached(this.requests) && !this.goaway {
+                        trace!("Max number of requests is reached, send GOAWAY");
+                        this.goaway = true;
+                        this.connection.graceful_shutdown();
+                    }
+
+                    #[cfg(feature = "tracing")]
+                    let span = RequestSpan::server(req.head());
+                    #[cfg(feature = "tracing")]
+                    let _entered = span.enter();
+
+                    let catch_panic = this.config.catch_panic;
+                    let uri = req.head().uri.clone();
+                    let method = req.head().method.clone();
+                    let service = &this.config.service;
+                    let state = if let Some(err) = this.config.check_host(req.head()) {
+                        ServiceResponseState::Reject(Some(err), Some(res))
+                    } else {
+                        match call_service(catch_panic, || service.call(req)) {
+                            Ok(fut) => ServiceResponseState::ServiceCall(fut, Some(res)),
+                            Err(err) => {
+                                ServiceResponseState::Panic(Some(err), Some(res))
+                            }
+                        }
+                    };
 
                     crate::rt::spawn(ServiceResponse {
-                        state: ServiceResponseState::ServiceCall(
-                            this.config.service.call(req),
-                            Some(res),
-                        ),
+                        state,
                         timer: this.config.timer.clone(),
+                        error_handler: this.config.error_handler.clone(),
+                        error_formatter: this.config.error_formatter.clone(),
+                        catch_panic,
+                        uri,
+                        method,
+                        on_error: this.config.on_dispatch_error.clone(),
+                        fb_timer: this.config.first_byte_timer(),
                         buffer: None,
+                        send_buffer: this.config.h2_send_buffer,
+                        timing,
+                        alt_svc: this.config.alt_svc.clone(),
+                        _finished: finished,
+                        _stats: req_stats,
+                        #[cfg(feature = "tracing")]
+                        span,
                         _t: PhantomData,
                     });
                 }
@@ -137,20 +253,46 @@ where
     }
 }
 
-pin_project_lite::pin_project! {
-    struct ServiceResponse<F, I, E, B> {
-        #[pin]
-        state: ServiceResponseState<F, B>,
-        timer: DateService,
-        buffer: Option<Bytes>,
-        _t: PhantomData<(I, E)>,
+#[pin_project::pin_project]
+struct ServiceResponse<F, I, E, B> {
+    #[pin]
+    state: ServiceResponseState<F, B>,
+    timer: DateService,
+    error_handler: Option<ErrorHandler>,
+    error_formatter: Option<ErrorFormatter>,
+    catch_panic: bool,
+    uri: Uri,
+    method: Method,
+    on_error: Option<DispatchErrorHook>,
+    fb_timer: Option<Delay>,
+    buffer: Option<Bytes>,
+    // max size of data queued to the stream
+    send_buffer: usize,
+    // request timing, for server-timing header
+    timing: Option<RequestTiming>,
+    alt_svc: Option<HeaderValue>,
+    _finished: Option<RequestFinished>,
+    _stats: RequestStats,
+    #[cfg(feature = "tracing")]
+    span: RequestSpan,
+    _t: PhantomData<(I, E)>,
+}
+
+/// Marks request as finished when response task completes
+struct RequestFinished(ConnectionHandle);
+
+impl Drop for RequestFinished {
+    fn drop(&mut self) {
+        self.0.request_finished();
     }
 }
 
 #[pin_project::pin_project(project = ServiceResponseStateProject)]
 enum ServiceResponseState<F, B> {
     ServiceCall(#[pin] F, Option<SendResponse<Bytes>>),
-    SendPayload(SendStream<Bytes>, ResponseBody<B>),
+    Panic(Option<PanicError>, Option<SendResponse<Bytes>>),
+    Reject(Option<Response>, Option<SendResponse<Bytes>>),
+    SendPayload(SendStream<Bytes>, TransformBody<ResponseBody<B>>),
 }
 
 impl<F, I, E, B> ServiceResponse<F, I, E, B>
@@ -166,6 +308,7 @@ where
         size: &mut BodySize,
     ) -> http::Response<()> {
         let mut has_date = false;
+        let mut has_alt_svc = false;
         let mut skip_len = size != &BodySize::Stream;
 
         let mut res = http::Response::new(());
@@ -200,10 +343,20 @@ where
                 CONNECTION | TRANSFER_ENCODING => continue, // http2 specific
                 CONTENT_LENGTH if skip_len => continue,
                 DATE => has_date = true,
+                ALT_SVC => has_alt_svc = true,
                 _ => (),
             }
             res.headers_mut().append(key, value.clone());
         }
+        if let Some(ref timing) = self.timing {
+            res.headers_mut()
+                .append(server_timing_name(), timing.server_timing());
+        }
+        if let Some(ref alt_svc) = self.alt_svc {
+            if !has_alt_svc {
+                res.headers_mut().insert(ALT_SVC, alt_svc.clone());
+            }
+        }
 
         // set date header
         if !has_date {
@@ -231,65 +384,81 @@ where
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let mut this = self.as_mut().project();
 
-        match this.state.project() {
-            ServiceResponseStateProject::ServiceCall(call, send) => {
-                match call.poll(cx) {
-                    Poll::Ready(Ok(res)) => {
-                        let (res, body) = res.into().replace_body(());
-
-                        let mut send = send.take().unwrap();
-                        let mut size = body.size();
-                        let h2_res =
-                            self.as_mut().prepare_response(res.head(), &mut size);
-                        this = self.as_mut().project();
+        // poll service and send payload inside of request span
+        #[cfg(feature = "tracing")]
+        let _entered = this.span.enter();
 
-                        let stream = match send.send_response(h2_res, size.is_eof()) {
-                            Err(e) => {
-                                trace!("Error sending h2 response: {:?}", e);
-                                return Poll::Ready(());
+        let (mut res, body, mut send) = match this.state.project() {
+            ServiceResponseStateProject::ServiceCall(call, send) => {
+                match poll_service(*this.catch_panic, call, cx) {
+                    Poll::Pending => {
+                        // service did not respond within deadline
+                        match this.fb_timer.as_mut().map(|t| Pin::new(t).poll(cx)) {
+                            Some(Poll::Ready(_)) => {
+                                trace!("Time to first byte deadline expired, respond with 503");
+                                let res = format_error(
+                                    this.error_formatter.as_ref(),
+                                    StatusCode::SERVICE_UNAVAILABLE,
+                                    "Service did not respond in time",
+                                );
+                                let (res, body) = res.replace_body(());
+                                (res, body.into_body(), send.take().unwrap())
+                            }
+                            _ => {
+                                // stream is reset by the peer, drop service call
+                                if let Poll::Ready(res) =
+                                    send.as_mut().unwrap().poll_reset(cx)
+                                {
+                                    let err =
+                                        res.map(h2::Error::from).unwrap_or_else(|e| e);
+                                    write_error(
+                                        this.on_error.as_ref(),
+                                        this.method,
+                                        this.uri,
+                                        err,
+                                    );
+                                    return Poll::Ready(());
+                                }
+                                return Poll::Pending;
                             }
-                            Ok(stream) => stream,
-                        };
-
-                        if size.is_eof() {
-                            Poll::Ready(())
-                        } else {
-                            this.state
-                                .set(ServiceResponseState::SendPayload(stream, body));
-                            self.poll(cx)
                         }
                     }
-                    Poll::Pending => Poll::Pending,
-                    Poll::Ready(Err(e)) => {
-                        let res: Response = e.into();
+                    Poll::Ready(Ok(Ok(res))) => {
+                        let (res, body) = res.into().replace_body(());
+                        (res, body, send.take().unwrap())
+                    }
+                    Poll::Ready(Ok(Err(e))) => {
+                        let res = error_response(this.error_handler.as_ref(), e);
                         let (res, body) = res.replace_body(());
-
-                        let mut send = send.take().unwrap();
-                        let mut size = body.size();
-                        let h2_res =
-                            self.as_mut().prepare_response(res.head(), &mut size);
-                        this = self.as_mut().project();
-
-                        let stream = match send.send_response(h2_res, size.is_eof()) {
-                            Err(e) => {
-                                trace!("Error sending h2 response: {:?}", e);
-                                return Poll::Ready(());
-                            }
-                            Ok(stream) => stream,
-                        };
-
-                        if size.is_eof() {
-                            Poll::Ready(())
-                        } else {
-                            this.state.set(ServiceResponseState::SendPayload(
-                                stream,
-                                body.into_body(),
-                            ));
-                            self.poll(cx)
-                        }
+                        (res, body.into_body(), send.take().unwrap())
+                    }
+                    Poll::Ready(Err(err)) => {
+                        let res = panic_response(
+                            this.error_handler.as_ref(),
+                            this.error_formatter.as_ref(),
+                            this.uri,
+                            err,
+                        );
+                        let (res, body) = res.replace_body(());
+                        (res, body.into_body(), send.take().unwrap())
                     }
                 }
             }
+            ServiceResponseStateProject::Panic(err, send) => {
+                let err = err.take().unwrap();
+                let res = panic_response(
+                    this.error_handler.as_ref(),
+                    this.error_formatter.as_ref(),
+                    this.uri,
+                    err,
+                );
+                let (res, body) = res.replace_body(());
+                (res, body.into_body(), send.take().unwrap())
+            }
+            ServiceResponseStateProject::Reject(res, send) => {
+                let (res, body) = res.take().unwrap().replace_body(());
+                (res, body.into_body(), send.take().unwrap())
+            }
             ServiceResponseStateProject::SendPayload(stream, body) => loop {
                 loop {
                     if let Some(buffer) = this.buffer {
@@ -301,44 +470,122 @@ where
                                 let bytes = buffer.split_to(std::cmp::min(cap, len));
 
                                 if let Err(e) = stream.send_data(bytes, false) {
-                                    warn!("{:?}", e);
+                                    write_error(
+                                        this.on_error.as_ref(),
+                                        this.method,
+                                        this.uri,
+                                        e,
+                                    );
                                     return Poll::Ready(());
                                 } else if !buffer.is_empty() {
-                                    let cap = std::cmp::min(buffer.len(), CHUNK_SIZE);
+                                    let cap =
+                                        std::cmp::min(buffer.len(), *this.send_buffer);
                                     stream.reserve_capacity(cap);
                                 } else {
                                     this.buffer.take();
                                 }
                             }
                             Poll::Ready(Some(Err(e))) => {
-                                warn!("{:?}", e);
+                                write_error(
+                                    this.on_error.as_ref(),
+                                    this.method,
+                                    this.uri,
+                                    e,
+                                );
                                 return Poll::Ready(());
                             }
                         }
                     } else {
                         match body.poll_next_chunk(cx) {
-                            Poll::Pending => return Poll::Pending,
+                            Poll::Pending => {
+                                // stream is reset by the peer, drop response body
+                                if let Poll::Ready(res) = stream.poll_reset(cx) {
+                                    let err =
+                                        res.map(h2::Error::from).unwrap_or_else(|e| e);
+                                    write_error(
+                                        this.on_error.as_ref(),
+                                        this.method,
+                                        this.uri,
+                                        err,
+                                    );
+                                    return Poll::Ready(());
+                                }
+                                return Poll::Pending;
+                            }
                             Poll::Ready(None) => {
-                                if let Err(e) = stream.send_data(Bytes::new(), true) {
-                                    warn!("{:?}", e);
+                                let res = if let Some(trailers) = body.trailers() {
+                                    let mut map = http::HeaderMap::new();
+                                    for (key, value) in trailers.iter() {
+                                        map.append(key, value.clone());
+                                    }
+                                    stream.send_trailers(map)
+                                } else {
+                                    stream.send_data(Bytes::new(), true)
+                                };
+                                if let Err(e) = res {
+                                    write_error(
+                                        this.on_error.as_ref(),
+                                        this.method,
+                                        this.uri,
+                                        e,
+                                    );
                                 }
                                 return Poll::Ready(());
                             }
                             Poll::Ready(Some(Ok(chunk))) => {
                                 stream.reserve_capacity(std::cmp::min(
                                     chunk.len(),
-                                    CHUNK_SIZE,
+                                    *this.send_buffer,
                                 ));
                                 *this.buffer = Some(chunk);
                             }
                             Poll::Ready(Some(Err(e))) => {
                                 error!("Response payload stream error: {:?}", e);
+                                // reset stream, so client could detect incomplete body
+                                stream.send_reset(h2::Reason::INTERNAL_ERROR);
                                 return Poll::Ready(());
                             }
                         }
                     }
                 }
             },
+        };
+        #[cfg(feature = "tracing")]
+        this.span.record_response(Some(res.status()));
+
+        let body = TransformBody::new(body, res.head_mut());
+        let mut size = body.size();
+        let h2_res = self.as_mut().prepare_response(res.head(), &mut size);
+        this = self.as_mut().project();
+
+        let stream = match send.send_response(h2_res, size.is_eof()) {
+            Err(e) => {
+                write_error(this.on_error.as_ref(), this.method, this.uri, e);
+                return Poll::Ready(());
+            }
+            Ok(stream) => stream,
+        };
+
+        if size.is_eof() {
+            Poll::Ready(())
+        } else {
+            this.state
+                .set(ServiceResponseState::SendPayload(stream, body));
+            self.poll(cx)
         }
     }
 }
+
+/// Report response write error to the dispatcher error hook
+fn write_error(
+    hook: Option<&DispatchErrorHook>,
+    method: &Method,
+    uri: &Uri,
+    err: h2::Error,
+) {
+    let err = DispatchError::Write(WriteError::h2(method.clone(), uri.path(), err));
+    trace!("{}", err);
+    if let Some(hook) = hook {
+        hook(&err);
+    }
+}
@@ -3,8 +3,9 @@ use std::pin::Pin;
 use std::task::{Context, Poll};
 
 use bytes::Bytes;
-use futures::Stream;
+use futures::{Future, Stream};
 use h2::RecvStream;
+use tokio::time::Delay;
 
 mod dispatcher;
 mod service;
@@ -17,11 +18,26 @@ use crate::http::error::PayloadError;
 #[derive(Debug)]
 pub struct Payload {
     pl: RecvStream,
+    timer: Option<Delay>,
+    expired: bool,
 }
 
 impl Payload {
     pub(crate) fn new(pl: RecvStream) -> Self {
-        Self { pl }
+        Self {
+            pl,
+            timer: None,
+            expired: false,
+        }
+    }
+
+    /// Payload must be received before timer expires
+    pub(super) fn with_timer(pl: RecvStream, timer: Option<Delay>) -> Self {
+        Self {
+            pl,
+            timer,
+            expired: false,
+        }
     }
 }
 
@@ -33,6 +49,9 @@ impl Stream for Payload {
         cx: &mut Context<'_>,
     ) -> Poll<Option<Self::Item>> {
         let this = self.get_mut();
+        if this.expired {
+            return Poll::Ready(None);
+        }
 
         match Pin::new(&mut this.pl).poll_data(cx) {
             Poll::Ready(Some(Ok(chunk))) => {
@@ -44,8 +63,20 @@ impl Stream for Payload {
                 }
             }
             Poll::Ready(Some(Err(err))) => Poll::Ready(Some(Err(err.into()))),
-            Poll::Pending => Poll::Pending,
-            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => {
+                if let Some(ref mut timer) = this.timer {
+                    if Pin::new(timer).poll(cx).is_ready() {
+                        trace!("Request payload timeout");
+                        this.expired = true;
+                        return Poll::Ready(Some(Err(PayloadError::Timeout)));
+                    }
+                }
+                Poll::Pending
+            }
+            Poll::Ready(None) => {
+                this.timer = None;
+                Poll::Ready(None)
+            }
         }
     }
 }
@@ -13,7 +13,9 @@ use log::error;
 
 use crate::codec::{AsyncRead, AsyncWrite};
 use crate::http::body::MessageBody;
-use crate::http::config::{DispatcherConfig, ServiceConfig};
+use crate::http::config::{
+    set_tcp_keepalive, DispatcherConfig, ServiceConfig, WireCaptureIo,
+};
 use crate::http::error::{DispatchError, ResponseError};
 use crate::http::helpers::DataFactory;
 use crate::http::request::Request;
@@ -86,8 +88,10 @@ where
         Error = DispatchError,
         InitError = S::InitError,
     > {
-        pipeline_factory(fn_factory(|| async {
-            Ok::<_, S::InitError>(fn_service(|io: TcpStream| {
+        let ka = self.cfg.0.tcp_keepalive;
+        pipeline_factory(fn_factory(move || async move {
+            Ok::<_, S::InitError>(fn_service(move |io: TcpStream| {
+                set_tcp_keepalive(&io, ka.as_ref());
                 let peer_addr = io.peer_addr().ok();
                 ok::<_, DispatchError>((io, peer_addr))
             }))
@@ -123,14 +127,16 @@ mod openssl {
             Error = SslError<DispatchError>,
             InitError = S::InitError,
         > {
+            let ka = self.cfg.0.tcp_keepalive;
             pipeline_factory(
                 Acceptor::new(acceptor)
                     .timeout(self.handshake_timeout)
                     .map_err(SslError::Ssl)
                     .map_init_err(|_| panic!()),
             )
-            .and_then(fn_factory(|| {
-                ok::<_, S::InitError>(fn_service(|io: SslStream<TcpStream>| {
+            .and_then(fn_factory(move || {
+                ok::<_, S::InitError>(fn_service(move |io: SslStream<TcpStream>| {
+                    set_tcp_keepalive(io.get_ref(), ka.as_ref());
                     let peer_addr = io.get_ref().peer_addr().ok();
                     ok((io, peer_addr))
                 }))
@@ -168,14 +174,16 @@ mod rustls {
             let protos = vec!["h2".to_string().into()];
             config.set_protocols(&protos);
 
+            let ka = self.cfg.0.tcp_keepalive;
             pipeline_factory(
                 Acceptor::new(config)
                     .timeout(self.handshake_timeout)
                     .map_err(SslError::Ssl)
                     .map_init_err(|_| panic!()),
             )
-            .and_then(fn_factory(|| {
-                ok::<_, S::InitError>(fn_service(|io: TlsStream<TcpStream>| {
+            .and_then(fn_factory(move || {
+                ok::<_, S::InitError>(fn_service(move |io: TlsStream<TcpStream>| {
+                    set_tcp_keepalive(io.get_ref().0, ka.as_ref());
                     let peer_addr = io.get_ref().0.peer_addr().ok();
                     ok((io, peer_addr))
                 }))
@@ -297,7 +305,10 @@ where
                 self.config.clone(),
                 addr,
                 on_connect,
-                server::handshake(io),
+                server::handshake(WireCaptureIo::new(
+                    io,
+                    self.config.wire_capture.clone(),
+                )),
             ),
         }
     }
@@ -308,12 +319,12 @@ where
     T: AsyncRead + AsyncWrite + Unpin,
     S::Future: 'static,
 {
-    Incoming(Dispatcher<T, S, B, (), ()>),
+    Incoming(Dispatcher<WireCaptureIo<T>, S, B, (), ()>),
     Handshake(
         Rc<DispatcherConfig<S, (), ()>>,
         Option<net::SocketAddr>,
         Option<Box<dyn DataFactory>>,
-        Handshake<T, Bytes>,
+        Handshake<WireCaptureIo<T>, Bytes>,
     ),
 }
 
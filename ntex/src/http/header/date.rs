@@ -0,0 +1,562 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{fmt, str::FromStr};
+
+use derive_more::Display;
+
+use super::{
+    Header, HeaderName, HeaderValue, EXPIRES, IF_MODIFIED_SINCE, LAST_MODIFIED,
+    RETRY_AFTER,
+};
+
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const WEEKDAYS_LONG: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Seconds since epoch of `Fri, 31 Dec 9999 23:59:59 GMT`
+const MAX_SECS: u64 = 253_402_300_799;
+
+/// Invalid http date
+#[derive(Debug, Display, Copy, Clone, PartialEq)]
+#[display(fmt = "Invalid http date")]
+pub struct InvalidHttpDate;
+
+impl std::error::Error for InvalidHttpDate {}
+
+/// Http date (RFC 7231 §7.1.1.1)
+///
+/// Date could be parsed from any of IMF-fixdate, obsolete RFC 850 and
+/// asctime formats, it is always formatted as IMF-fixdate.
+/// Http dates have one second precision, supported range is from
+/// `1970-01-01` to `9999-12-31`. Conversion from `SystemTime` clamps
+/// value to that range.
+///
+/// ```rust
+/// use ntex::http::header::HttpDate;
+///
+/// let date: HttpDate = "Sunday, 06-Nov-94 08:49:37 GMT".parse().unwrap();
+/// assert_eq!(date.to_string(), "Sun, 06 Nov 1994 08:49:37 GMT");
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HttpDate {
+    secs: u64,
+}
+
+impl HttpDate {
+    /// Current date
+    pub fn now() -> Self {
+        HttpDate::from(SystemTime::now())
+    }
+
+    fn year(&self) -> u64 {
+        civil_from_days(self.secs / 86400).0
+    }
+
+    fn from_parts(
+        year: u64,
+        month: u64,
+        day: u64,
+        hour: u64,
+        min: u64,
+        sec: u64,
+    ) -> Result<Self, InvalidHttpDate> {
+        if !(1970..=9999).contains(&year)
+            || month == 0
+            || month > 12
+            || day == 0
+            || day > days_in_month(year, month)
+            || hour > 23
+            || min > 59
+            || sec > 59
+        {
+            return Err(InvalidHttpDate);
+        }
+        let days = days_from_civil(year, month, day);
+        Ok(HttpDate {
+            secs: days * 86400 + hour * 3600 + min * 60 + sec,
+        })
+    }
+}
+
+impl From<SystemTime> for HttpDate {
+    fn from(time: SystemTime) -> Self {
+        let secs = time
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        HttpDate {
+            secs: std::cmp::min(secs, MAX_SECS),
+        }
+    }
+}
+
+impl From<HttpDate> for SystemTime {
+    fn from(date: HttpDate) -> Self {
+        UNIX_EPOCH + Duration::from_secs(date.secs)
+    }
+}
+
+impl From<HttpDate> for HeaderValue {
+    fn from(date: HttpDate) -> Self {
+        HeaderValue::from_str(&date.to_string()).unwrap()
+    }
+}
+
+impl fmt::Display for HttpDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let days = self.secs / 86400;
+        let secs = self.secs % 86400;
+        let (year, month, day) = civil_from_days(days);
+
+        write!(
+            f,
+            "{}, {:02} {} {:04} {:02}:{:02}:{:02} GMT",
+            WEEKDAYS[((days + 4) % 7) as usize],
+            day,
+            MONTHS[(month - 1) as usize],
+            year,
+            secs / 3600,
+            secs % 3600 / 60,
+            secs % 60
+        )
+    }
+}
+
+impl FromStr for HttpDate {
+    type Err = InvalidHttpDate;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if !s.is_ascii() {
+            return Err(InvalidHttpDate);
+        }
+        parse_imf_fixdate(s)
+            .or_else(|_| parse_rfc850(s, HttpDate::now().year()))
+            .or_else(|_| parse_asctime(s))
+    }
+}
+
+/// `Sun, 06 Nov 1994 08:49:37 GMT`
+fn parse_imf_fixdate(s: &str) -> Result<HttpDate, InvalidHttpDate> {
+    let b = s.as_bytes();
+    if b.len() != 29 || &s[3..5] != ", " || b[7] != b' ' || b[11] != b' ' {
+        return Err(InvalidHttpDate);
+    }
+    if b[16] != b' ' || &s[25..] != " GMT" || !WEEKDAYS.contains(&&s[..3]) {
+        return Err(InvalidHttpDate);
+    }
+    let (hour, min, sec) = parse_time(&s[17..25])?;
+    HttpDate::from_parts(
+        parse_num(&s[12..16])?,
+        parse_month(&s[8..11])?,
+        parse_num(&s[5..7])?,
+        hour,
+        min,
+        sec,
+    )
+}
+
+/// `Sunday, 06-Nov-94 08:49:37 GMT`
+///
+/// Two digit year which appears to be more than 50 years in the future
+/// is interpreted as the most recent year in the past with the same last
+/// two digits (RFC 7231 §7.1.1.1), otherwise the year within 50 years
+/// from current year is used.
+fn parse_rfc850(s: &str, current_year: u64) -> Result<HttpDate, InvalidHttpDate> {
+    let comma = s.find(", ").ok_or(InvalidHttpDate)?;
+    if !WEEKDAYS_LONG.contains(&&s[..comma]) {
+        return Err(InvalidHttpDate);
+    }
+    let s = &s[comma + 2..];
+    let b = s.as_bytes();
+    if b.len() != 22
+        || b[2] != b'-'
+        || b[6] != b'-'
+        || b[9] != b' '
+        || &s[18..] != " GMT"
+    {
+        return Err(InvalidHttpDate);
+    }
+    let (hour, min, sec) = parse_time(&s[10..18])?;
+
+    let mut year = current_year - current_year % 100 + parse_num(&s[7..9])?;
+    if year > current_year + 50 {
+        year -= 100;
+    } else if year + 50 < current_year {
+        year += 100;
+    }
+    HttpDate::from_parts(
+        year,
+        parse_month(&s[3..6])?,
+        parse_num(&s[..2])?,
+        hour,
+        min,
+        sec,
+    )
+}
+
+/// `Sun Nov  6 08:49:37 1994`
+fn parse_asctime(s: &str) -> Result<HttpDate, InvalidHttpDate> {
+    let b = s.as_bytes();
+    if b.len() != 24 || b[3] != b' ' || b[7] != b' ' || b[10] != b' ' || b[19] != b' ' {
+        return Err(InvalidHttpDate);
+    }
+    if !WEEKDAYS.contains(&&s[..3]) {
+        return Err(InvalidHttpDate);
+    }
+    let day = if b[8] == b' ' {
+        parse_num(&s[9..10])?
+    } else {
+        parse_num(&s[8..10])?
+    };
+    let (hour, min, sec) = parse_time(&s[11..19])?;
+    HttpDate::from_parts(
+        parse_num(&s[20..])?,
+        parse_month(&s[4..7])?,
+        day,
+        hour,
+        min,
+        sec,
+    )
+}
+
+/// `08:49:37`
+fn parse_time(s: &str) -> Result<(u64, u64, u64), InvalidHttpDate> {
+    let b = s.as_bytes();
+    if b.len() != 8 || b[2] != b':' || b[5] != b':' {
+        return Err(InvalidHttpDate);
+    }
+    Ok((
+        parse_num(&s[..2])?,
+        parse_num(&s[3..5])?,
+        parse_num(&s[6..])?,
+    ))
+}
+
+fn parse_num(s: &str) -> Result<u64, InvalidHttpDate> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(InvalidHttpDate);
+    }
+    s.parse().map_err(|_| InvalidHttpDate)
+}
+
+fn parse_month(s: &str) -> Result<u64, InvalidHttpDate> {
+    MONTHS
+        .iter()
+        .position(|m| *m == s)
+        .map(|idx| idx as u64 + 1)
+        .ok_or(InvalidHttpDate)
+}
+
+fn is_leap_year(year: u64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: u64, month: u64) -> u64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since epoch for a date, year must not be less than 1970
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let yoe = year - era * 400;
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Date for days since epoch
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let doe = days - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+macro_rules! date_header {
+    ($(#[$meta:meta])* $name:ident, $header:expr) => {
+        $(#[$meta])*
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $name(pub HttpDate);
+
+        impl Header for $name {
+            fn name() -> HeaderName {
+                $header
+            }
+
+            fn parse<'a, I>(mut values: I) -> Option<Self>
+            where
+                I: Iterator<Item = &'a HeaderValue>,
+            {
+                values
+                    .next()
+                    .and_then(|val| val.to_str().ok())
+                    .and_then(|val| val.parse().ok())
+                    .map($name)
+            }
+
+            fn to_value(&self) -> HeaderValue {
+                self.0.into()
+            }
+        }
+
+        impl From<HttpDate> for $name {
+            fn from(date: HttpDate) -> Self {
+                $name(date)
+            }
+        }
+
+        impl From<SystemTime> for $name {
+            fn from(time: SystemTime) -> Self {
+                $name(time.into())
+            }
+        }
+    };
+}
+
+date_header!(
+    /// `Last-Modified` header (RFC 7232 §2.2)
+    LastModified,
+    LAST_MODIFIED
+);
+
+date_header!(
+    /// `If-Modified-Since` header (RFC 7232 §3.3)
+    IfModifiedSince,
+    IF_MODIFIED_SINCE
+);
+
+date_header!(
+    /// `Expires` header (RFC 7234 §5.3)
+    ///
+    /// Invalid dates, like `0`, represent a time in the past, parsing
+    /// of such values returns `None`.
+    Expires,
+    EXPIRES
+);
+
+/// `Retry-After` header (RFC 7231 §7.1.3)
+///
+/// Value is either a delay in seconds or a date after which request
+/// could be retried.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum RetryAfter {
+    /// Delay after response is received
+    Delay(Duration),
+    /// Date after which request could be retried
+    Date(HttpDate),
+}
+
+impl RetryAfter {
+    /// Time to wait before retry
+    ///
+    /// Date value is compared with `date`, time when response was
+    /// generated, for example response's `Date` header. That way clock skew
+    /// between client and server does not affect the delay. Dates in the
+    /// past mean no wait.
+    pub fn delay_since(&self, date: HttpDate) -> Duration {
+        match self {
+            RetryAfter::Delay(delay) => *delay,
+            RetryAfter::Date(retry) => {
+                Duration::from_secs(retry.secs.saturating_sub(date.secs))
+            }
+        }
+    }
+}
+
+impl Header for RetryAfter {
+    fn name() -> HeaderName {
+        RETRY_AFTER
+    }
+
+    fn parse<'a, I>(mut values: I) -> Option<Self>
+    where
+        I: Iterator<Item = &'a HeaderValue>,
+    {
+        let val = values.next()?.to_str().ok()?.trim();
+        if !val.is_empty() && val.bytes().all(|b| b.is_ascii_digit()) {
+            val.parse()
+                .ok()
+                .map(|secs| RetryAfter::Delay(Duration::from_secs(secs)))
+        } else {
+            val.parse().ok().map(RetryAfter::Date)
+        }
+    }
+
+    fn to_value(&self) -> HeaderValue {
+        match self {
+            RetryAfter::Delay(delay) => delay.as_secs().into(),
+            RetryAfter::Date(date) => (*date).into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::header::HeaderMap;
+
+    const NOV_6_1994: u64 = 784_111_777;
+
+    #[test]
+    fn test_formats() {
+        let expected = HttpDate { secs: NOV_6_1994 };
+        for s in &[
+            "Sun, 06 Nov 1994 08:49:37 GMT",
+            "Sunday, 06-Nov-94 08:49:37 GMT",
+            "Sun Nov  6 08:49:37 1994",
+            " Sun, 06 Nov 1994 08:49:37 GMT ",
+        ] {
+            assert_eq!(s.parse::<HttpDate>(), Ok(expected), "{}", s);
+        }
+        assert_eq!(expected.to_string(), "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(
+            "Thu Dec 25 00:00:00 2014".parse::<HttpDate>().unwrap(),
+            "Thu, 25 Dec 2014 00:00:00 GMT".parse::<HttpDate>().unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        for s in &[
+            "",
+            "0",
+            "Sun, 06 Nov 1994 08:49:37 UTC",
+            "Sun, 06 Nov 1994 24:00:00 GMT",
+            "Sun, 06 Nov 1994 08:60:37 GMT",
+            "Sun, 31 Nov 1994 08:49:37 GMT",
+            "Sun, 29 Feb 2019 08:49:37 GMT",
+            "Sun, 06 Noo 1994 08:49:37 GMT",
+            "Son, 06 Nov 1994 08:49:37 GMT",
+            "Sun, +6 Nov 1994 08:49:37 GMT",
+            "Sun, 06 Nov 1969 08:49:37 GMT",
+            "Sun, 06 Nov 1994 08:49:37 GMT1",
+            "Sunday, 06-Nov-1994 08:49:37 GMT",
+            "Sun Nov 06 08:49:37 94",
+            "Sun, 06 Nov 1994 08:49:3\u{e9} GMT",
+        ] {
+            assert_eq!(s.parse::<HttpDate>(), Err(InvalidHttpDate), "{}", s);
+        }
+        assert!("Tue, 29 Feb 2000 00:00:00 GMT".parse::<HttpDate>().is_ok());
+    }
+
+    #[test]
+    fn test_rfc850_year() {
+        let date = |s, year| parse_rfc850(s, year).unwrap().year();
+        assert_eq!(date("Sunday, 06-Nov-94 08:49:37 GMT", 2026), 1994);
+        assert_eq!(date("Sunday, 06-Nov-76 08:49:37 GMT", 2026), 2076);
+        assert_eq!(date("Sunday, 06-Nov-77 08:49:37 GMT", 2026), 1977);
+        assert_eq!(date("Sunday, 06-Nov-30 08:49:37 GMT", 2026), 2030);
+        assert_eq!(date("Sunday, 06-Nov-10 08:49:37 GMT", 2090), 2110);
+        assert_eq!(date("Sunday, 06-Nov-99 08:49:37 GMT", 2090), 2099);
+        // 1969 is out of range
+        assert!(parse_rfc850("Sunday, 06-Nov-69 08:49:37 GMT", 2000).is_err());
+    }
+
+    #[test]
+    fn test_system_time() {
+        let time = UNIX_EPOCH + Duration::from_secs(NOV_6_1994);
+        let date = HttpDate::from(time + Duration::from_millis(500));
+        assert_eq!(SystemTime::from(date), time);
+
+        // out of range values are clamped
+        let date = HttpDate::from(UNIX_EPOCH - Duration::from_secs(1));
+        assert_eq!(date.to_string(), "Thu, 01 Jan 1970 00:00:00 GMT");
+        let date = HttpDate::from(UNIX_EPOCH + Duration::from_secs(MAX_SECS * 2));
+        assert_eq!(date.to_string(), "Fri, 31 Dec 9999 23:59:59 GMT");
+        assert_eq!(date, date.to_string().parse::<HttpDate>().unwrap());
+
+        let leap = "Tue, 29 Feb 2000 12:00:00 GMT".parse::<HttpDate>().unwrap();
+        assert_eq!(leap.to_string(), "Tue, 29 Feb 2000 12:00:00 GMT");
+    }
+
+    #[test]
+    fn test_ordering() {
+        let d1: HttpDate = "Sun, 06 Nov 1994 08:49:37 GMT".parse().unwrap();
+        let d2: HttpDate = "Sun, 06 Nov 1994 08:49:38 GMT".parse().unwrap();
+        assert!(d1 < d2);
+        assert!(IfModifiedSince(d1) < IfModifiedSince(d2));
+        assert!(HttpDate::now() > d2);
+    }
+
+    #[test]
+    fn test_typed_headers() {
+        let date: HttpDate = "Sun, 06 Nov 1994 08:49:37 GMT".parse().unwrap();
+
+        let mut m = HeaderMap::new();
+        m.typed_insert(&LastModified(date));
+        m.typed_insert(&Expires::from(SystemTime::from(date)));
+        assert_eq!(
+            m.get(LAST_MODIFIED).unwrap(),
+            "Sun, 06 Nov 1994 08:49:37 GMT"
+        );
+        assert_eq!(m.typed_get::<LastModified>(), Some(LastModified(date)));
+        assert_eq!(m.typed_get::<Expires>(), Some(Expires(date)));
+        assert_eq!(m.typed_get::<IfModifiedSince>(), None);
+
+        m.insert(
+            IF_MODIFIED_SINCE,
+            HeaderValue::from_static("Sun Nov  6 08:49:37 1994"),
+        );
+        assert_eq!(
+            m.typed_get::<IfModifiedSince>(),
+            Some(IfModifiedSince(date))
+        );
+
+        m.insert(EXPIRES, HeaderValue::from_static("0"));
+        assert_eq!(m.typed_get::<Expires>(), None);
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let date: HttpDate = "Sun, 06 Nov 1994 08:49:37 GMT".parse().unwrap();
+
+        let mut m = HeaderMap::new();
+        m.insert(RETRY_AFTER, HeaderValue::from_static(" 120 "));
+        let retry = m.typed_get::<RetryAfter>().unwrap();
+        assert_eq!(retry, RetryAfter::Delay(Duration::from_secs(120)));
+        assert_eq!(retry.delay_since(date), Duration::from_secs(120));
+
+        m.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Sun, 06 Nov 1994 08:50:37 GMT"),
+        );
+        let retry = m.typed_get::<RetryAfter>().unwrap();
+        assert_eq!(retry.delay_since(date), Duration::from_secs(60));
+        // date in the past
+        let later: HttpDate = "Sun, 06 Nov 1994 09:00:00 GMT".parse().unwrap();
+        assert_eq!(retry.delay_since(later), Duration::from_secs(0));
+
+        m.typed_insert(&RetryAfter::Delay(Duration::from_secs(5)));
+        assert_eq!(m.get(RETRY_AFTER).unwrap(), "5");
+        m.typed_insert(&retry);
+        assert_eq!(m.get(RETRY_AFTER).unwrap(), "Sun, 06 Nov 1994 08:50:37 GMT");
+
+        for val in &["", "-1", "1.5", "99999999999999999999", "tomorrow"] {
+            m.insert(RETRY_AFTER, HeaderValue::from_static(val));
+            assert_eq!(m.typed_get::<RetryAfter>(), None, "{}", val);
+        }
+    }
+}
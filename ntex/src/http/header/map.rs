@@ -1,18 +1,42 @@
-use std::collections::hash_map::{self, Entry};
+use std::collections::hash_map;
 use std::convert::TryFrom;
+use std::vec;
 
+#[cfg(feature = "preserve-header-case")]
+use bytes::Bytes;
 use either::Either;
 use fxhash::FxHashMap;
+#[cfg(feature = "preserve-header-case")]
+use http::header::InvalidHeaderName;
 use http::header::{HeaderName, HeaderValue};
 
+use super::Header;
+
 /// A set of HTTP headers
 ///
 /// `HeaderMap` is an multimap of [`HeaderName`] to values.
 ///
+/// With `preserve-header-case` feature enabled, map keeps insertion order
+/// of headers and original spelling of header names received from the peer,
+/// http/1 encoder uses both when writing headers to the wire.
+///
 /// [`HeaderName`]: struct.HeaderName.html
 #[derive(Debug, Clone)]
 pub struct HeaderMap {
     pub(crate) inner: FxHashMap<HeaderName, Value>,
+    #[cfg(feature = "preserve-header-case")]
+    order: Vec<Position>,
+}
+
+/// Position of a header value in insertion order
+#[cfg(feature = "preserve-header-case")]
+#[derive(Debug, Clone)]
+struct Position {
+    name: HeaderName,
+    // original spelling of the name, if differs from normalized
+    raw: Option<Bytes>,
+    // index of the value in `Value`
+    idx: usize,
 }
 
 #[derive(Debug, Clone)]
@@ -36,12 +60,38 @@ impl Value {
         }
     }
 
+    #[cfg(feature = "preserve-header-case")]
+    fn len(&self) -> usize {
+        match self {
+            Value::One(_) => 1,
+            Value::Multi(ref val) => val.len(),
+        }
+    }
+
+    #[cfg(feature = "preserve-header-case")]
+    /// Get value by insertion index
+    fn get_idx(&self, idx: usize) -> Option<&HeaderValue> {
+        match self {
+            Value::One(ref val) => {
+                if idx == 0 {
+                    Some(val)
+                } else {
+                    None
+                }
+            }
+            Value::Multi(ref val) => val.get(idx),
+        }
+    }
+
     fn append(&mut self, val: HeaderValue) {
         match self {
             Value::One(_) => {
-                let data = std::mem::replace(self, Value::Multi(vec![val]));
+                let data = std::mem::replace(self, Value::Multi(Vec::with_capacity(2)));
                 match data {
-                    Value::One(val) => self.append(val),
+                    Value::One(prev) => {
+                        self.append(prev);
+                        self.append(val);
+                    }
                     Value::Multi(_) => unreachable!(),
                 }
             }
@@ -64,6 +114,8 @@ impl HeaderMap {
     pub fn new() -> Self {
         HeaderMap {
             inner: FxHashMap::default(),
+            #[cfg(feature = "preserve-header-case")]
+            order: Vec::new(),
         }
     }
 
@@ -78,6 +130,8 @@ impl HeaderMap {
     pub fn with_capacity(capacity: usize) -> HeaderMap {
         HeaderMap {
             inner: FxHashMap::with_capacity_and_hasher(capacity, Default::default()),
+            #[cfg(feature = "preserve-header-case")]
+            order: Vec::with_capacity(capacity),
         }
     }
 
@@ -98,6 +152,8 @@ impl HeaderMap {
     /// for reuse.
     pub fn clear(&mut self) {
         self.inner.clear();
+        #[cfg(feature = "preserve-header-case")]
+        self.order.clear();
     }
 
     /// Returns the number of headers the map can hold without reallocating.
@@ -145,7 +201,8 @@ impl HeaderMap {
     /// Returns a view of all values associated with a key.
     ///
     /// The returned view does not incur any allocations and allows iterating
-    /// the values associated with the key.  See [`GetAll`] for more details.
+    /// the values associated with the key in insertion order. See [`GetAll`]
+    /// for more details.
     /// Returns `None` if there are no values associated with the key.
     ///
     /// [`GetAll`]: struct.GetAll.html
@@ -193,8 +250,27 @@ impl HeaderMap {
     /// The iteration order is arbitrary, but consistent across platforms for
     /// the same crate version. Each key will be yielded once per associated
     /// value. So, if a key has 3 associated values, it will be yielded 3 times.
+    ///
+    /// With `preserve-header-case` feature, pairs are yielded in insertion order.
     pub fn iter(&self) -> Iter<'_> {
-        Iter::new(self.inner.iter())
+        #[cfg(not(feature = "preserve-header-case"))]
+        {
+            Iter::new(self.inner.iter())
+        }
+        #[cfg(feature = "preserve-header-case")]
+        {
+            Iter(self.iter_cased())
+        }
+    }
+
+    #[cfg(feature = "preserve-header-case")]
+    /// An iterator visiting all headers in insertion order.
+    ///
+    /// Along with normalized name, iterator yields original spelling of
+    /// the name. Headers parsed by http/1 decoder keep spelling used by
+    /// the peer, headers added with `append_cased` keep provided spelling.
+    pub fn iter_cased(&self) -> IterCased<'_> {
+        IterCased { map: self, pos: 0 }
     }
 
     /// An iterator visiting all keys.
@@ -206,37 +282,34 @@ impl HeaderMap {
         Keys(self.inner.keys())
     }
 
-    /// Inserts a key-value pair into the map.
-    ///
-    /// If the map did not previously have this key present, then `None` is
-    /// returned.
+    /// Inserts a key-value pair into the map, replacing all values.
     ///
-    /// If the map did have this key present, the new value is associated with
-    /// the key and all previous values are removed. **Note** that only a single
-    /// one of the previous values is returned. If there are multiple values
-    /// that have been previously associated with the key, then the first one is
-    /// returned. See `insert_mult` on `OccupiedEntry` for an API that returns
-    /// all values.
+    /// If the map did have this key present, all previous values
+    /// associated with the key are removed and the new value becomes
+    /// the only value.
     ///
     /// The key is not updated, though; this matters for types that can be `==`
     /// without being identical.
     pub fn insert(&mut self, key: HeaderName, val: HeaderValue) {
+        #[cfg(feature = "preserve-header-case")]
+        order_replace(&mut self.order, &key);
+
         let _ = self.inner.insert(key, Value::One(val));
     }
 
-    /// Inserts a key-value pair into the map.
-    ///
-    /// If the map did not previously have this key present, then `false` is
-    /// returned.
+    /// Appends a key-value pair into the map, keeping existing values.
     ///
     /// If the map did have this key present, the new value is pushed to the end
     /// of the list of values currently associated with the key. The key is not
     /// updated, though; this matters for types that can be `==` without being
     /// identical.
     pub fn append(&mut self, key: HeaderName, value: HeaderValue) {
+        #[cfg(feature = "preserve-header-case")]
+        self.push_position(&key, None);
+
         match self.inner.entry(key) {
-            Entry::Occupied(mut entry) => entry.get_mut().append(value),
-            Entry::Vacant(entry) => {
+            hash_map::Entry::Occupied(mut entry) => entry.get_mut().append(value),
+            hash_map::Entry::Vacant(entry) => {
                 entry.insert(Value::One(value));
             }
         }
@@ -246,15 +319,326 @@ impl HeaderMap {
     pub fn remove<N: AsName>(&mut self, key: N) {
         match key.as_name() {
             Either::Left(name) => {
+                #[cfg(feature = "preserve-header-case")]
+                self.order.retain(|p| p.name != name);
                 let _ = self.inner.remove(name);
             }
             Either::Right(s) => {
                 if let Ok(name) = HeaderName::try_from(s) {
+                    #[cfg(feature = "preserve-header-case")]
+                    self.order.retain(|p| p.name != name);
                     let _ = self.inner.remove(&name);
                 }
             }
         }
     }
+
+    /// Gets the given key's corresponding entry in the map for in-place
+    /// manipulation.
+    pub fn entry(&mut self, key: HeaderName) -> Entry<'_> {
+        match self.inner.entry(key) {
+            hash_map::Entry::Occupied(entry) => Entry::Occupied(OccupiedEntry {
+                entry,
+                #[cfg(feature = "preserve-header-case")]
+                order: &mut self.order,
+            }),
+            hash_map::Entry::Vacant(entry) => Entry::Vacant(VacantEntry {
+                entry,
+                #[cfg(feature = "preserve-header-case")]
+                order: &mut self.order,
+            }),
+        }
+    }
+
+    /// Retains only the headers specified by the predicate.
+    ///
+    /// Predicate is called once for every value.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&HeaderName, &HeaderValue) -> bool,
+    {
+        #[cfg(not(feature = "preserve-header-case"))]
+        self.inner.retain(|name, value| match value {
+            Value::One(ref val) => f(name, val),
+            Value::Multi(ref mut vec) => {
+                vec.retain(|val| f(name, val));
+                !vec.is_empty()
+            }
+        });
+
+        #[cfg(feature = "preserve-header-case")]
+        {
+            let order = std::mem::take(&mut self.order);
+            let inner = std::mem::take(&mut self.inner);
+            for p in order {
+                if let Some(val) = inner.get(&p.name).and_then(|v| v.get_idx(p.idx)) {
+                    if f(&p.name, val) {
+                        self.push_position(&p.name, p.raw);
+                        match self.inner.entry(p.name) {
+                            hash_map::Entry::Occupied(mut entry) => {
+                                entry.get_mut().append(val.clone())
+                            }
+                            hash_map::Entry::Vacant(entry) => {
+                                entry.insert(Value::One(val.clone()));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Clears the map, returning all headers as an iterator.
+    ///
+    /// The iteration order is arbitrary. Each key is yielded once
+    /// per associated value. Keeps the allocated memory for reuse.
+    pub fn drain(&mut self) -> Drain<'_> {
+        #[cfg(feature = "preserve-header-case")]
+        self.order.clear();
+
+        Drain {
+            iter: self.inner.drain(),
+            current: None,
+        }
+    }
+
+    /// Get typed header
+    ///
+    /// Returns `None` if header is not present or could not be parsed.
+    pub fn typed_get<H: Header>(&self) -> Option<H> {
+        let name = H::name();
+        if self.inner.contains_key(&name) {
+            H::parse(self.get_all(&name))
+        } else {
+            None
+        }
+    }
+
+    /// Insert typed header, replacing all values of the header
+    pub fn typed_insert<H: Header>(&mut self, header: &H) {
+        self.insert(H::name(), header.to_value())
+    }
+}
+
+#[cfg(feature = "preserve-header-case")]
+impl HeaderMap {
+    /// Appends a header, original spelling of the name is preserved.
+    ///
+    /// Name is used as is by http/1 encoder, http/2 always uses
+    /// lowercase names.
+    pub fn append_cased(
+        &mut self,
+        name: &str,
+        value: HeaderValue,
+    ) -> Result<(), InvalidHeaderName> {
+        let key = HeaderName::try_from(name)?;
+        self.append_raw(key, Bytes::copy_from_slice(name.as_bytes()), value);
+        Ok(())
+    }
+
+    pub(crate) fn append_raw(
+        &mut self,
+        key: HeaderName,
+        raw: Bytes,
+        value: HeaderValue,
+    ) {
+        let raw = if raw == key.as_str().as_bytes() {
+            None
+        } else {
+            Some(raw)
+        };
+        self.push_position(&key, raw);
+
+        match self.inner.entry(key) {
+            hash_map::Entry::Occupied(mut entry) => entry.get_mut().append(value),
+            hash_map::Entry::Vacant(entry) => {
+                entry.insert(Value::One(value));
+            }
+        }
+    }
+
+    fn push_position(&mut self, key: &HeaderName, raw: Option<Bytes>) {
+        let idx = self.inner.get(key).map(|v| v.len()).unwrap_or(0);
+        self.order.push(Position {
+            name: key.clone(),
+            raw,
+            idx,
+        });
+    }
+}
+
+/// Replaced header keeps position and spelling of first value
+#[cfg(feature = "preserve-header-case")]
+fn order_replace(order: &mut Vec<Position>, key: &HeaderName) {
+    if let Some(pos) = order.iter().position(|p| p.name == key) {
+        let mut first = true;
+        order.retain(|p| {
+            if p.name != key {
+                true
+            } else {
+                std::mem::replace(&mut first, false)
+            }
+        });
+        order[pos].idx = 0;
+    } else {
+        order.push(Position {
+            name: key.clone(),
+            raw: None,
+            idx: 0,
+        });
+    }
+}
+
+/// A view into a single header name in a `HeaderMap`.
+///
+/// This enum is constructed from the `entry` method on `HeaderMap`.
+pub enum Entry<'a> {
+    /// An occupied entry
+    Occupied(OccupiedEntry<'a>),
+    /// A vacant entry
+    Vacant(VacantEntry<'a>),
+}
+
+impl<'a> Entry<'a> {
+    /// Returns a reference to this entry's key.
+    pub fn key(&self) -> &HeaderName {
+        match self {
+            Entry::Occupied(ref entry) => entry.key(),
+            Entry::Vacant(ref entry) => entry.key(),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the default if empty.
+    ///
+    /// Returns a mutable reference to the first value in the entry.
+    pub fn or_insert(self, default: HeaderValue) -> &'a mut HeaderValue {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the result of the
+    /// default function if empty.
+    ///
+    /// Returns a mutable reference to the first value in the entry.
+    pub fn or_insert_with<F>(self, default: F) -> &'a mut HeaderValue
+    where
+        F: FnOnce() -> HeaderValue,
+    {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Provides in-place mutable access to the first value of
+    /// an occupied entry.
+    pub fn and_modify<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut HeaderValue),
+    {
+        if let Entry::Occupied(ref mut entry) = self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+/// A view into an occupied entry in a `HeaderMap`.
+pub struct OccupiedEntry<'a> {
+    entry: hash_map::OccupiedEntry<'a, HeaderName, Value>,
+    #[cfg(feature = "preserve-header-case")]
+    order: &'a mut Vec<Position>,
+}
+
+impl<'a> OccupiedEntry<'a> {
+    /// Returns a reference to this entry's key.
+    pub fn key(&self) -> &HeaderName {
+        self.entry.key()
+    }
+
+    /// Returns a reference to the first value in the entry.
+    pub fn get(&self) -> &HeaderValue {
+        self.entry.get().get()
+    }
+
+    /// Returns a mutable reference to the first value in the entry.
+    pub fn get_mut(&mut self) -> &mut HeaderValue {
+        self.entry.get_mut().get_mut()
+    }
+
+    /// Converts the entry into a mutable reference to the first value.
+    pub fn into_mut(self) -> &'a mut HeaderValue {
+        self.entry.into_mut().get_mut()
+    }
+
+    /// Iterate all values in the entry in insertion order.
+    pub fn iter(&self) -> GetAll<'_> {
+        GetAll {
+            idx: 0,
+            item: Some(self.entry.get()),
+        }
+    }
+
+    /// Replaces all values in the entry with the new value.
+    pub fn insert(&mut self, value: HeaderValue) {
+        #[cfg(feature = "preserve-header-case")]
+        order_replace(self.order, self.entry.key());
+
+        self.entry.insert(Value::One(value));
+    }
+
+    /// Appends value to the end of the entry's values.
+    pub fn append(&mut self, value: HeaderValue) {
+        #[cfg(feature = "preserve-header-case")]
+        self.order.push(Position {
+            name: self.entry.key().clone(),
+            raw: None,
+            idx: self.entry.get().len(),
+        });
+
+        self.entry.get_mut().append(value);
+    }
+
+    /// Removes the entry from the map.
+    pub fn remove(self) {
+        #[cfg(feature = "preserve-header-case")]
+        {
+            let key = self.entry.key();
+            self.order.retain(|p| p.name != key);
+        }
+
+        let _ = self.entry.remove();
+    }
+}
+
+/// A view into a vacant entry in a `HeaderMap`.
+pub struct VacantEntry<'a> {
+    entry: hash_map::VacantEntry<'a, HeaderName, Value>,
+    #[cfg(feature = "preserve-header-case")]
+    order: &'a mut Vec<Position>,
+}
+
+impl<'a> VacantEntry<'a> {
+    /// Returns a reference to this entry's key.
+    pub fn key(&self) -> &HeaderName {
+        self.entry.key()
+    }
+
+    /// Inserts the value into the map.
+    ///
+    /// Returns a mutable reference to the inserted value.
+    pub fn insert(self, value: HeaderValue) -> &'a mut HeaderValue {
+        #[cfg(feature = "preserve-header-case")]
+        self.order.push(Position {
+            name: self.entry.key().clone(),
+            raw: None,
+            idx: 0,
+        });
+
+        self.entry.insert(Value::One(value)).get_mut()
+    }
 }
 
 #[doc(hidden)]
@@ -345,12 +729,39 @@ impl<'a> IntoIterator for &'a HeaderMap {
     }
 }
 
+pub struct Drain<'a> {
+    iter: hash_map::Drain<'a, HeaderName, Value>,
+    current: Option<(HeaderName, vec::IntoIter<HeaderValue>)>,
+}
+
+impl<'a> Iterator for Drain<'a> {
+    type Item = (HeaderName, HeaderValue);
+
+    fn next(&mut self) -> Option<(HeaderName, HeaderValue)> {
+        if let Some((ref name, ref mut values)) = self.current {
+            if let Some(value) = values.next() {
+                return Some((name.clone(), value));
+            }
+            self.current = None;
+        }
+        match self.iter.next()? {
+            (name, Value::One(value)) => Some((name, value)),
+            (name, Value::Multi(values)) => {
+                self.current = Some((name, values.into_iter()));
+                self.next()
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "preserve-header-case"))]
 pub struct Iter<'a> {
     idx: usize,
     current: Option<(&'a HeaderName, &'a Vec<HeaderValue>)>,
     iter: hash_map::Iter<'a, HeaderName, Value>,
 }
 
+#[cfg(not(feature = "preserve-header-case"))]
 impl<'a> Iter<'a> {
     fn new(iter: hash_map::Iter<'a, HeaderName, Value>) -> Self {
         Self {
@@ -361,6 +772,7 @@ impl<'a> Iter<'a> {
     }
 }
 
+#[cfg(not(feature = "preserve-header-case"))]
 impl<'a> Iterator for Iter<'a> {
     type Item = (&'a HeaderName, &'a HeaderValue);
 
@@ -390,10 +802,50 @@ impl<'a> Iterator for Iter<'a> {
     }
 }
 
+#[cfg(feature = "preserve-header-case")]
+pub struct Iter<'a>(IterCased<'a>);
+
+#[cfg(feature = "preserve-header-case")]
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a HeaderName, &'a HeaderValue);
+
+    #[inline]
+    fn next(&mut self) -> Option<(&'a HeaderName, &'a HeaderValue)> {
+        self.0.next().map(|(name, _, value)| (name, value))
+    }
+}
+
+#[cfg(feature = "preserve-header-case")]
+pub struct IterCased<'a> {
+    map: &'a HeaderMap,
+    pos: usize,
+}
+
+#[cfg(feature = "preserve-header-case")]
+impl<'a> Iterator for IterCased<'a> {
+    type Item = (&'a HeaderName, &'a [u8], &'a HeaderValue);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.map.order.get(self.pos)?;
+        self.pos += 1;
+
+        let raw = item
+            .raw
+            .as_ref()
+            .map(|raw| raw.as_ref())
+            .unwrap_or_else(|| item.name.as_str().as_bytes());
+        let value = self.map.inner.get(&item.name)?.get_idx(item.idx)?;
+        Some((&item.name, raw, value))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::http::header::CONTENT_TYPE;
+    use crate::http::header::{
+        ContentEncoding, ACCEPT, CONTENT_ENCODING, CONTENT_TYPE, SET_COOKIE,
+    };
 
     #[test]
     fn test_basics() {
@@ -424,4 +876,162 @@ mod tests {
         m.remove("content-type");
         assert!(m.is_empty());
     }
+
+    #[cfg(feature = "preserve-header-case")]
+    #[test]
+    fn test_preserve_case() {
+        let mut m = HeaderMap::new();
+        m.append_cased("X-Custom", HeaderValue::from_static("1"))
+            .unwrap();
+        m.append(CONTENT_TYPE, HeaderValue::from_static("text"));
+        m.append_cased("x-custom", HeaderValue::from_static("2"))
+            .unwrap();
+        m.append_cased("ACCEPT", HeaderValue::from_static("*/*"))
+            .unwrap();
+        assert!(m
+            .append_cased("in valid", HeaderValue::from_static("1"))
+            .is_err());
+
+        let items: Vec<_> = m.iter_cased().map(|(_, raw, v)| (raw, v)).collect();
+        assert_eq!(
+            items,
+            vec![
+                (&b"X-Custom"[..], &HeaderValue::from_static("1")),
+                (&b"content-type"[..], &HeaderValue::from_static("text")),
+                (&b"x-custom"[..], &HeaderValue::from_static("2")),
+                (&b"ACCEPT"[..], &HeaderValue::from_static("*/*")),
+            ]
+        );
+
+        // insert keeps position of replaced header
+        m.insert(
+            HeaderName::from_static("x-custom"),
+            HeaderValue::from_static("3"),
+        );
+        m.remove(CONTENT_TYPE);
+        let items: Vec<_> = m.iter().collect();
+        assert_eq!(
+            items,
+            vec![
+                (
+                    &HeaderName::from_static("x-custom"),
+                    &HeaderValue::from_static("3")
+                ),
+                (&http::header::ACCEPT, &HeaderValue::from_static("*/*")),
+            ]
+        );
+        let raw: Vec<_> = m.iter_cased().map(|(_, raw, _)| raw).collect();
+        assert_eq!(raw, vec![&b"X-Custom"[..], &b"ACCEPT"[..]]);
+
+        // retain and entry keep order
+        m.entry(CONTENT_TYPE)
+            .or_insert(HeaderValue::from_static("text"));
+        m.append_cased("X-Last", HeaderValue::from_static("4"))
+            .unwrap();
+        m.retain(|name, _| name != http::header::ACCEPT);
+        let raw: Vec<_> = m.iter_cased().map(|(_, raw, _)| raw).collect();
+        assert_eq!(
+            raw,
+            vec![&b"X-Custom"[..], &b"content-type"[..], &b"X-Last"[..]]
+        );
+
+        m.clear();
+        assert_eq!(m.iter().count(), 0);
+    }
+
+    #[test]
+    fn test_append_insert() {
+        let mut m = HeaderMap::new();
+        m.append(SET_COOKIE, HeaderValue::from_static("c1"));
+        m.append(SET_COOKIE, HeaderValue::from_static("c2"));
+        m.append(SET_COOKIE, HeaderValue::from_static("c3"));
+        assert_eq!(m.get(SET_COOKIE).unwrap(), "c1");
+        let vals: Vec<_> = m.get_all(SET_COOKIE).collect();
+        assert_eq!(vals, vec!["c1", "c2", "c3"]);
+
+        m.insert(SET_COOKIE, HeaderValue::from_static("c4"));
+        let vals: Vec<_> = m.get_all(SET_COOKIE).collect();
+        assert_eq!(vals, vec!["c4"]);
+    }
+
+    #[test]
+    fn test_entry() {
+        let mut m = HeaderMap::new();
+        assert_eq!(
+            *m.entry(CONTENT_TYPE)
+                .or_insert(HeaderValue::from_static("text")),
+            "text"
+        );
+        assert_eq!(
+            *m.entry(CONTENT_TYPE)
+                .or_insert_with(|| HeaderValue::from_static("json")),
+            "text"
+        );
+        m.entry(CONTENT_TYPE)
+            .and_modify(|val| *val = HeaderValue::from_static("json"))
+            .or_insert(HeaderValue::from_static("text"));
+        assert_eq!(m.get(CONTENT_TYPE).unwrap(), "json");
+
+        match m.entry(SET_COOKIE) {
+            Entry::Vacant(entry) => {
+                assert_eq!(entry.key(), SET_COOKIE);
+                entry.insert(HeaderValue::from_static("c1"));
+            }
+            Entry::Occupied(_) => panic!(),
+        }
+        match m.entry(SET_COOKIE) {
+            Entry::Occupied(mut entry) => {
+                entry.append(HeaderValue::from_static("c2"));
+                assert_eq!(entry.get(), "c1");
+                assert_eq!(entry.iter().collect::<Vec<_>>(), vec!["c1", "c2"]);
+                entry.insert(HeaderValue::from_static("c3"));
+                assert_eq!(entry.iter().collect::<Vec<_>>(), vec!["c3"]);
+                entry.remove();
+            }
+            Entry::Vacant(_) => panic!(),
+        }
+        assert!(!m.contains_key(SET_COOKIE));
+        assert_eq!(m.len(), 1);
+    }
+
+    #[test]
+    fn test_retain_drain() {
+        let mut m = HeaderMap::new();
+        m.append(SET_COOKIE, HeaderValue::from_static("c1"));
+        m.append(SET_COOKIE, HeaderValue::from_static("c2"));
+        m.append(SET_COOKIE, HeaderValue::from_static("c3"));
+        m.insert(CONTENT_TYPE, HeaderValue::from_static("text"));
+        m.insert(ACCEPT, HeaderValue::from_static("*/*"));
+
+        m.retain(|name, val| name != ACCEPT && val != "c2");
+        let vals: Vec<_> = m.get_all(SET_COOKIE).collect();
+        assert_eq!(vals, vec!["c1", "c3"]);
+        assert!(!m.contains_key(ACCEPT));
+
+        let mut items: Vec<_> = m.drain().collect();
+        items.sort_by(|a, b| a.1.as_bytes().cmp(b.1.as_bytes()));
+        assert_eq!(
+            items,
+            vec![
+                (SET_COOKIE, HeaderValue::from_static("c1")),
+                (SET_COOKIE, HeaderValue::from_static("c3")),
+                (CONTENT_TYPE, HeaderValue::from_static("text")),
+            ]
+        );
+        assert!(m.is_empty());
+        assert_eq!(m.iter().count(), 0);
+    }
+
+    #[test]
+    fn test_typed() {
+        let mut m = HeaderMap::new();
+        assert_eq!(m.typed_get::<ContentEncoding>(), None);
+
+        m.typed_insert(&ContentEncoding::Gzip);
+        assert_eq!(m.get(CONTENT_ENCODING).unwrap(), "gzip");
+        assert_eq!(
+            m.typed_get::<ContentEncoding>(),
+            Some(ContentEncoding::Gzip)
+        );
+    }
 }
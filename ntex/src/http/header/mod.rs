@@ -2,11 +2,35 @@
 
 pub use http::header::{HeaderName, HeaderValue, InvalidHeaderValue};
 
+mod date;
 pub(crate) mod map;
 
+pub use self::date::{
+    Expires, HttpDate, IfModifiedSince, InvalidHttpDate, LastModified, RetryAfter,
+};
+
+#[cfg(feature = "preserve-header-case")]
+pub use self::map::IterCased;
 #[doc(hidden)]
-pub use self::map::GetAll;
-pub use self::map::HeaderMap;
+pub use self::map::{Drain, GetAll};
+pub use self::map::{Entry, HeaderMap, OccupiedEntry, VacantEntry};
+
+/// Typed header
+///
+/// Typed headers could be read and written with
+/// `HeaderMap::typed_get()` and `HeaderMap::typed_insert()`.
+pub trait Header: Sized {
+    /// Name of the header
+    fn name() -> HeaderName;
+
+    /// Parse header from its values, values are yielded in insertion order
+    fn parse<'a, I>(values: I) -> Option<Self>
+    where
+        I: Iterator<Item = &'a HeaderValue>;
+
+    /// Convert header to a header value
+    fn to_value(&self) -> HeaderValue;
+}
 
 /// Represents supported types of content encodings
 #[derive(Copy, Clone, PartialEq, Debug)]
@@ -69,6 +93,26 @@ impl<'a> From<&'a str> for ContentEncoding {
     }
 }
 
+impl Header for ContentEncoding {
+    fn name() -> HeaderName {
+        CONTENT_ENCODING
+    }
+
+    fn parse<'a, I>(mut values: I) -> Option<Self>
+    where
+        I: Iterator<Item = &'a HeaderValue>,
+    {
+        values
+            .next()
+            .and_then(|val| val.to_str().ok())
+            .map(ContentEncoding::from)
+    }
+
+    fn to_value(&self) -> HeaderValue {
+        HeaderValue::from_static(self.as_str())
+    }
+}
+
 /// Convert http::HeaderMap to a HeaderMap
 impl From<http::HeaderMap> for HeaderMap {
     fn from(map: http::HeaderMap) -> HeaderMap {
@@ -80,6 +124,43 @@ impl From<http::HeaderMap> for HeaderMap {
     }
 }
 
+/// Check if header is hop-by-hop header
+///
+/// Hop-by-hop headers are `Connection`, `Keep-Alive`, `TE`, `Trailer`,
+/// `Transfer-Encoding`, `Upgrade` and `Proxy-*` headers (RFC 7230 §6.1).
+/// Headers listed in `Connection` header are hop-by-hop as well,
+/// use `connection_headers()` to get them.
+pub fn is_hop_by_hop(name: &HeaderName) -> bool {
+    matches!(
+        *name,
+        CONNECTION | TE | TRAILER | TRANSFER_ENCODING | UPGRADE
+    ) || name == "keep-alive"
+        || name.as_str().starts_with("proxy-")
+}
+
+/// Get header names listed in `Connection` header
+///
+/// Comma-separated tokens of all `Connection` header values are returned,
+/// invalid tokens are skipped.
+pub fn connection_headers(headers: &HeaderMap) -> Vec<HeaderName> {
+    headers
+        .get_all(CONNECTION)
+        .filter_map(|val| val.to_str().ok())
+        .flat_map(|val| val.split(','))
+        .map(|name| name.trim())
+        .filter(|name| !name.is_empty())
+        .filter_map(|name| HeaderName::from_bytes(name.as_bytes()).ok())
+        .collect()
+}
+
+/// Remove hop-by-hop headers and headers listed in `Connection` header
+///
+/// Remaining end-to-end headers could be forwarded by proxy.
+pub fn remove_hop_by_hop(headers: &mut HeaderMap) {
+    let listed = connection_headers(headers);
+    headers.retain(|name, _| !is_hop_by_hop(name) && !listed.contains(name));
+}
+
 pub use http::header::{
     ACCEPT, ACCEPT_CHARSET, ACCEPT_ENCODING, ACCEPT_LANGUAGE, ACCEPT_RANGES,
     ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS,
@@ -112,4 +193,44 @@ mod tests {
         assert!(!ContentEncoding::Auto.is_compressed());
         assert_eq!(format!("{:?}", ContentEncoding::Identity), "Identity");
     }
+
+    #[test]
+    fn hop_by_hop() {
+        assert!(is_hop_by_hop(&CONNECTION));
+        assert!(is_hop_by_hop(&HeaderName::from_static("keep-alive")));
+        assert!(is_hop_by_hop(&PROXY_AUTHORIZATION));
+        assert!(!is_hop_by_hop(&CONTENT_TYPE));
+
+        let mut headers = HeaderMap::new();
+        headers.append(CONNECTION, HeaderValue::from_static("Keep-Alive, X-Hop"));
+        headers.append(CONNECTION, HeaderValue::from_static("x-other,,"));
+        headers.insert(
+            HeaderName::from_static("keep-alive"),
+            HeaderValue::from_static("1"),
+        );
+        headers.insert(
+            HeaderName::from_static("x-hop"),
+            HeaderValue::from_static("1"),
+        );
+        headers.insert(
+            HeaderName::from_static("x-other"),
+            HeaderValue::from_static("1"),
+        );
+        headers.insert(TRANSFER_ENCODING, HeaderValue::from_static("chunked"));
+        headers.insert(UPGRADE, HeaderValue::from_static("websocket"));
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+
+        assert_eq!(
+            connection_headers(&headers),
+            vec![
+                HeaderName::from_static("keep-alive"),
+                HeaderName::from_static("x-hop"),
+                HeaderName::from_static("x-other"),
+            ]
+        );
+
+        remove_hop_by_hop(&mut headers);
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers.get(CONTENT_TYPE).unwrap(), "text/plain");
+    }
 }
@@ -60,6 +60,10 @@ impl Response {
     STATIC_RESP!(ExpectationFailed, StatusCode::EXPECTATION_FAILED);
     STATIC_RESP!(UnprocessableEntity, StatusCode::UNPROCESSABLE_ENTITY);
     STATIC_RESP!(TooManyRequests, StatusCode::TOO_MANY_REQUESTS);
+    STATIC_RESP!(
+        RequestHeaderFieldsTooLarge,
+        StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE
+    );
 
     STATIC_RESP!(InternalServerError, StatusCode::INTERNAL_SERVER_ERROR);
     STATIC_RESP!(NotImplemented, StatusCode::NOT_IMPLEMENTED);
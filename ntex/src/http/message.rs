@@ -4,7 +4,7 @@ use std::rc::Rc;
 
 use bitflags::bitflags;
 
-use crate::http::header::HeaderMap;
+use crate::http::header::{HeaderMap, HeaderValue};
 use crate::http::{header, Method, StatusCode, Uri, Version};
 use crate::util::Extensions;
 
@@ -26,6 +26,7 @@ bitflags! {
         const UPGRADE     = 0b0000_0100;
         const EXPECT      = 0b0000_1000;
         const NO_CHUNKING = 0b0001_0000;
+        const UNKNOWN_EXPECT = 0b0010_0000;
     }
 }
 
@@ -63,9 +64,13 @@ impl Default for RequestHead {
 
 impl Head for RequestHead {
     fn clear(&mut self) {
+        self.uri = Uri::default();
+        self.method = Method::GET;
+        self.version = Version::HTTP_11;
+        self.peer_addr = None;
         self.flags = Flags::empty();
         self.headers.clear();
-        self.extensions.borrow_mut().clear();
+        self.extensions.get_mut().clear_on_reuse();
     }
 
     fn pool() -> &'static MessagePool<Self> {
@@ -160,6 +165,65 @@ impl RequestHead {
     pub(crate) fn set_expect(&mut self) {
         self.flags.insert(Flags::EXPECT);
     }
+
+    #[inline]
+    /// Request contains `EXPECT` header with expectation other than
+    /// `100-continue`
+    pub fn unknown_expectation(&self) -> bool {
+        self.flags.contains(Flags::UNKNOWN_EXPECT)
+    }
+
+    #[inline]
+    pub(crate) fn set_unknown_expectation(&mut self) {
+        self.flags.insert(Flags::UNKNOWN_EXPECT);
+    }
+
+    /// Create request head for forwarding request to upstream server
+    ///
+    /// Method, uri, version and end-to-end headers are copied, hop-by-hop
+    /// headers (RFC 7230 §6.1) and headers listed in `Connection` header
+    /// are removed. `Via` header with `pseudonym` is appended.
+    /// Original `Host` header is preserved, for http/2 requests it is set
+    /// from uri authority.
+    pub fn clone_for_proxy(&self, pseudonym: &str) -> RequestHead {
+        let mut headers = proxy_headers(&self.headers, self.version, pseudonym);
+        if !headers.contains_key(header::HOST) {
+            if let Some(host) = self
+                .uri
+                .authority()
+                .and_then(|a| HeaderValue::from_str(a.as_str()).ok())
+            {
+                headers.insert(header::HOST, host);
+            }
+        }
+
+        RequestHead {
+            headers,
+            uri: self.uri.clone(),
+            method: self.method.clone(),
+            version: self.version,
+            peer_addr: self.peer_addr,
+            ..Default::default()
+        }
+    }
+}
+
+/// Copy end-to-end headers and append `Via` header
+fn proxy_headers(src: &HeaderMap, version: Version, pseudonym: &str) -> HeaderMap {
+    let mut headers = src.clone();
+    header::remove_hop_by_hop(&mut headers);
+
+    let protocol = match version {
+        Version::HTTP_09 => "0.9",
+        Version::HTTP_10 => "1.0",
+        Version::HTTP_2 => "2",
+        Version::HTTP_3 => "3",
+        _ => "1.1",
+    };
+    if let Ok(via) = HeaderValue::from_str(&format!("{} {}", protocol, pseudonym)) {
+        headers.append(header::VIA, via);
+    }
+    headers
 }
 
 #[derive(Debug)]
@@ -203,6 +267,19 @@ pub struct ResponseHead {
 }
 
 impl ResponseHead {
+    /// Create response head for forwarding upstream response to the client
+    ///
+    /// Status, reason, version and end-to-end headers are copied, hop-by-hop
+    /// headers (RFC 7230 §6.1) and headers listed in `Connection` header
+    /// are removed. `Via` header with `pseudonym` is appended.
+    pub fn clone_for_proxy(&self, pseudonym: &str) -> ResponseHead {
+        let mut head = ResponseHead::new(self.status);
+        head.version = self.version;
+        head.reason = self.reason;
+        head.headers = proxy_headers(&self.headers, self.version, pseudonym);
+        head
+    }
+
     /// Create new instance of `ResponseHead` type
     #[inline]
     pub fn new(status: StatusCode) -> ResponseHead {
@@ -347,7 +424,10 @@ impl<T: Head> std::ops::DerefMut for Message<T> {
 
 impl<T: Head> Drop for Message<T> {
     fn drop(&mut self) {
-        if Rc::strong_count(&self.head) == 1 {
+        // message is cleared before it gets back to the pool, so
+        // data of previous request never leaks to the next one
+        if let Some(head) = Rc::get_mut(&mut self.head) {
+            head.clear();
             T::pool().release(self.head.clone());
         }
     }
@@ -362,6 +442,16 @@ impl BoxedResponseHead {
     pub(crate) fn new(status: StatusCode) -> Self {
         RESPONSE_POOL.with(|p| p.get_message(status))
     }
+
+    /// Copy of the message head, extensions are not copied
+    pub(crate) fn clone_head(&self) -> Self {
+        let mut head = BoxedResponseHead::new(self.status);
+        head.version = self.version;
+        head.reason = self.reason;
+        head.headers = self.headers.clone();
+        head.flags = self.flags;
+        head
+    }
 }
 
 impl std::ops::Deref for BoxedResponseHead {
@@ -386,31 +476,45 @@ impl Drop for BoxedResponseHead {
     }
 }
 
+/// Max number of objects kept by per-thread pools
+const POOL_SIZE: usize = 128;
+
+/// Capacity of per-thread objects pools
+///
+/// Pooling could be disabled for debugging purpose with `NTEX_DISABLE_POOL`
+/// environment variable. Variable is checked once, when thread's pool
+/// gets created.
+pub(super) fn pool_capacity() -> usize {
+    if std::env::var_os("NTEX_DISABLE_POOL").is_some() {
+        0
+    } else {
+        POOL_SIZE
+    }
+}
+
 #[doc(hidden)]
 /// Request's objects pool
-pub(crate) struct MessagePool<T: Head>(RefCell<Vec<Rc<T>>>);
+pub(crate) struct MessagePool<T: Head>(RefCell<Vec<Rc<T>>>, usize);
 
 #[doc(hidden)]
 #[allow(clippy::vec_box)]
 /// Request's objects pool
-pub(super) struct BoxedResponsePool(RefCell<Vec<Box<ResponseHead>>>);
+pub(super) struct BoxedResponsePool(RefCell<Vec<Box<ResponseHead>>>, usize);
 
 thread_local!(static REQUEST_POOL: &'static MessagePool<RequestHead> = MessagePool::<RequestHead>::create());
 thread_local!(static RESPONSE_POOL: &'static BoxedResponsePool = BoxedResponsePool::create());
 
 impl<T: Head> MessagePool<T> {
     fn create() -> &'static MessagePool<T> {
-        let pool = MessagePool(RefCell::new(Vec::with_capacity(128)));
+        let cap = pool_capacity();
+        let pool = MessagePool(RefCell::new(Vec::with_capacity(cap)), cap);
         Box::leak(Box::new(pool))
     }
 
     /// Get message from the pool
     #[inline]
     fn get_message(&'static self) -> Message<T> {
-        if let Some(mut msg) = self.0.borrow_mut().pop() {
-            if let Some(r) = Rc::get_mut(&mut msg) {
-                r.clear();
-            }
+        if let Some(msg) = self.0.borrow_mut().pop() {
             Message { head: msg }
         } else {
             Message {
@@ -423,7 +527,7 @@ impl<T: Head> MessagePool<T> {
     /// Release request instance
     fn release(&self, msg: Rc<T>) {
         let v = &mut self.0.borrow_mut();
-        if v.len() < 128 {
+        if v.len() < self.1 {
             v.push(msg);
         }
     }
@@ -431,7 +535,8 @@ impl<T: Head> MessagePool<T> {
 
 impl BoxedResponsePool {
     fn create() -> &'static BoxedResponsePool {
-        let pool = BoxedResponsePool(RefCell::new(Vec::with_capacity(128)));
+        let cap = pool_capacity();
+        let pool = BoxedResponsePool(RefCell::new(Vec::with_capacity(cap)), cap);
         Box::leak(Box::new(pool))
     }
 
@@ -455,9 +560,124 @@ impl BoxedResponsePool {
     /// Release request instance
     fn release(&self, msg: Box<ResponseHead>) {
         let v = &mut self.0.borrow_mut();
-        if v.len() < 128 {
-            msg.extensions.borrow_mut().clear();
+        if v.len() < self.1 {
+            msg.extensions.borrow_mut().clear_on_reuse();
             v.push(msg);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_clears_extensions() {
+        let mut msg = Message::<RequestHead>::new();
+        msg.extensions_mut().insert(10u32);
+        msg.headers
+            .insert(header::HOST, HeaderValue::from_static("test"));
+        drop(msg);
+
+        let msg = Message::<RequestHead>::new();
+        assert!(msg.extensions().is_empty());
+        assert!(msg.headers.is_empty());
+    }
+
+    #[test]
+    fn test_pool_resets_head() {
+        let mut msg = Message::<RequestHead>::new();
+        msg.uri = Uri::from_static("/test?q=1");
+        msg.method = Method::POST;
+        msg.version = Version::HTTP_10;
+        msg.peer_addr = Some("127.0.0.1:8080".parse().unwrap());
+        msg.set_connection_type(ConnectionType::Close);
+        drop(msg);
+
+        let msg = Message::<RequestHead>::new();
+        assert_eq!(msg.uri, "/");
+        assert_eq!(msg.method, Method::GET);
+        assert_eq!(msg.version, Version::HTTP_11);
+        assert!(msg.peer_addr.is_none());
+        assert_eq!(msg.connection_type(), ConnectionType::KeepAlive);
+    }
+
+    #[test]
+    fn test_request_clone_for_proxy() {
+        let mut head = RequestHead {
+            method: Method::POST,
+            uri: Uri::from_static("/test?q=1"),
+            ..Default::default()
+        };
+        for (name, value) in &[
+            ("host", "example.com"),
+            ("connection", "keep-alive, x-hop"),
+            ("keep-alive", "timeout=5"),
+            ("x-hop", "1"),
+            ("te", "trailers"),
+            ("transfer-encoding", "chunked"),
+            ("proxy-authorization", "basic"),
+            ("content-type", "text/plain"),
+            ("via", "1.0 first"),
+        ] {
+            head.headers.append(
+                header::HeaderName::from_static(name),
+                HeaderValue::from_static(value),
+            );
+        }
+
+        let proxy = head.clone_for_proxy("ntex");
+        assert_eq!(proxy.method, Method::POST);
+        assert_eq!(proxy.uri, "/test?q=1");
+        assert_eq!(proxy.headers.get(header::HOST).unwrap(), "example.com");
+        assert_eq!(
+            proxy.headers.get(header::CONTENT_TYPE).unwrap(),
+            "text/plain"
+        );
+        for name in &[
+            "connection",
+            "keep-alive",
+            "x-hop",
+            "te",
+            "transfer-encoding",
+            "proxy-authorization",
+        ] {
+            assert!(!proxy.headers.contains_key(*name), "{}", name);
+        }
+        let via: Vec<_> = proxy.headers.get_all(header::VIA).collect();
+        assert_eq!(via, vec!["1.0 first", "1.1 ntex"]);
+
+        // http/2 request does not have host header
+        let head = RequestHead {
+            version: Version::HTTP_2,
+            uri: Uri::from_static("https://example.com:8443/test"),
+            ..Default::default()
+        };
+        let proxy = head.clone_for_proxy("ntex");
+        assert_eq!(proxy.headers.get(header::HOST).unwrap(), "example.com:8443");
+        assert_eq!(proxy.headers.get(header::VIA).unwrap(), "2 ntex");
+    }
+
+    #[test]
+    fn test_response_clone_for_proxy() {
+        let mut head = ResponseHead::new(StatusCode::CREATED);
+        head.version = Version::HTTP_10;
+        head.headers
+            .insert(header::CONNECTION, HeaderValue::from_static("close"));
+        head.headers
+            .insert(header::UPGRADE, HeaderValue::from_static("websocket"));
+        head.headers.insert(
+            header::PROXY_AUTHENTICATE,
+            HeaderValue::from_static("basic"),
+        );
+        head.headers
+            .insert(header::ETAG, HeaderValue::from_static("\"1\""));
+
+        let proxy = head.clone_for_proxy("ntex");
+        assert_eq!(proxy.status, StatusCode::CREATED);
+        assert_eq!(proxy.version, Version::HTTP_10);
+        assert_eq!(proxy.headers.len(), 2);
+        assert_eq!(proxy.headers.get(header::ETAG).unwrap(), "\"1\"");
+        assert_eq!(proxy.headers.get(header::VIA).unwrap(), "1.0 ntex");
+    }
+}
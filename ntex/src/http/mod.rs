@@ -3,8 +3,11 @@ pub mod body;
 mod builder;
 pub mod client;
 mod config;
+mod connection;
+mod digest;
 #[cfg(feature = "compress")]
 pub mod encoding;
+pub mod grpc_web;
 pub(crate) mod helpers;
 mod httpcodes;
 mod httpmessage;
@@ -13,30 +16,43 @@ mod payload;
 mod request;
 mod response;
 mod service;
+mod stats;
+mod timing;
+#[cfg(feature = "tracing")]
+mod trace;
+mod validate;
 
 pub mod error;
 pub mod h1;
 pub mod h2;
 pub mod header;
 pub mod test;
+pub mod uri;
 pub mod ws;
 
 pub(crate) use self::message::Message;
 
 pub use self::builder::HttpServiceBuilder;
 pub use self::client::Client;
-pub use self::config::{DateService, KeepAlive, ServiceConfig};
+pub use self::config::{
+    DateService, EmptyHeaderValue, ErrorFormat, ExpectContinue, HeaderAnomalies,
+    HeaderValidation, Http10Body, KeepAlive, PipelineOverflow, ServiceConfig,
+    TcpKeepalive, TransferCodings, UnknownExpectation, WireDirection,
+};
+pub use self::connection::ConnectionHandle;
 pub use self::error::ResponseError;
 pub use self::header::HeaderMap;
 pub use self::httpmessage::HttpMessage;
 pub use self::message::{ConnectionType, RequestHead, RequestHeadType, ResponseHead};
-pub use self::payload::{Payload, PayloadStream};
+pub use self::payload::{Payload, PayloadCopy, PayloadStream};
 pub use self::request::Request;
 pub use self::response::{Response, ResponseBuilder};
 pub use self::service::HttpService;
+pub use self::stats::ServiceStats;
+pub use self::timing::RequestTiming;
+pub use self::uri::Uri;
 
 // re-exports
-pub use http::uri::{self, Uri};
 pub use http::{Method, StatusCode, Version};
 
 /// Http protocol
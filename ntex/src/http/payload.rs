@@ -1,8 +1,9 @@
-use std::fmt;
+use std::cell::RefCell;
 use std::pin::Pin;
 use std::task::{Context, Poll};
+use std::{fmt, rc::Rc};
 
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use futures::Stream;
 use h2::RecvStream;
 
@@ -67,6 +68,103 @@ impl<S> Payload<S> {
     }
 }
 
+impl<S> Payload<S>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>> + Unpin + 'static,
+{
+    /// Copy payload chunks while they pass through
+    ///
+    /// Returned payload yields the same chunks as original payload,
+    /// first `limit` bytes get copied to `PayloadCopy` buffer. Payload
+    /// stream is not delayed, copying stops after limit is reached.
+    pub fn tee(self, limit: usize) -> (Payload, PayloadCopy) {
+        let copy = PayloadCopy(Rc::new(RefCell::new(CopyInner {
+            limit,
+            buf: BytesMut::new(),
+            truncated: false,
+            eof: false,
+        })));
+        let tee = Tee {
+            payload: self,
+            copy: copy.clone(),
+        };
+        (Payload::Stream(Box::pin(tee)), copy)
+    }
+}
+
+/// Copy of a payload captured with `Payload::tee()`
+#[derive(Clone)]
+pub struct PayloadCopy(Rc<RefCell<CopyInner>>);
+
+struct CopyInner {
+    limit: usize,
+    buf: BytesMut,
+    truncated: bool,
+    eof: bool,
+}
+
+impl PayloadCopy {
+    /// Captured bytes
+    pub fn bytes(&self) -> Bytes {
+        Bytes::copy_from_slice(&self.0.borrow().buf)
+    }
+
+    /// Check if payload was larger than copy limit
+    pub fn is_truncated(&self) -> bool {
+        self.0.borrow().truncated
+    }
+
+    /// Check if payload is completely read
+    pub fn is_eof(&self) -> bool {
+        self.0.borrow().eof
+    }
+}
+
+impl fmt::Debug for PayloadCopy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = self.0.borrow();
+        f.debug_struct("PayloadCopy")
+            .field("size", &inner.buf.len())
+            .field("truncated", &inner.truncated)
+            .field("eof", &inner.eof)
+            .finish()
+    }
+}
+
+struct Tee<S> {
+    payload: Payload<S>,
+    copy: PayloadCopy,
+}
+
+impl<S> Stream for Tee<S>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>> + Unpin,
+{
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.as_mut().get_mut();
+        let item = futures::ready!(Pin::new(&mut this.payload).poll_next(cx));
+
+        let mut inner = this.copy.0.borrow_mut();
+        match item {
+            Some(Ok(ref chunk)) => {
+                let size = std::cmp::min(inner.limit - inner.buf.len(), chunk.len());
+                inner.buf.extend_from_slice(&chunk[..size]);
+                if size < chunk.len() {
+                    inner.truncated = true;
+                }
+            }
+            None => inner.eof = true,
+            _ => (),
+        }
+        Poll::Ready(item)
+    }
+}
+
 impl<S> Stream for Payload<S>
 where
     S: Stream<Item = Result<Bytes, PayloadError>> + Unpin,
@@ -109,4 +207,25 @@ mod tests {
         )
         .contains("Payload::Stream"));
     }
+
+    #[ntex_rt::test]
+    async fn payload_tee() {
+        use futures::StreamExt;
+
+        let (mut sender, payload) = crate::http::h1::Payload::create(false);
+        let (mut payload, copy) = Payload::<PayloadStream>::H1(payload).tee(6);
+
+        sender.feed_data(Bytes::from_static(b"data"));
+        assert_eq!(payload.next().await.unwrap().unwrap(), "data");
+        sender.feed_data(Bytes::from_static(b"line"));
+        sender.feed_eof();
+        assert_eq!(payload.next().await.unwrap().unwrap(), "line");
+        assert!(!copy.is_eof());
+        assert!(payload.next().await.is_none());
+
+        assert_eq!(copy.bytes(), "datali");
+        assert!(copy.is_truncated());
+        assert!(copy.is_eof());
+        assert!(format!("{:?}", copy).contains("PayloadCopy"));
+    }
 }
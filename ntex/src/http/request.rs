@@ -119,9 +119,12 @@ impl<P> Request<P> {
     /// Check if request requires connection upgrade
     #[inline]
     pub fn upgrade(&self) -> bool {
-        if let Some(conn) = self.head().headers.get(header::CONNECTION) {
-            if let Ok(s) = conn.to_str() {
-                return s.to_lowercase().contains("upgrade");
+        // http/1.0 does not support upgrade, rfc7230 section 6.7
+        if self.head().version >= Version::HTTP_11 {
+            if let Some(conn) = self.head().headers.get(header::CONNECTION) {
+                if let Ok(s) = conn.to_str() {
+                    return s.to_lowercase().contains("upgrade");
+                }
             }
         }
         self.head().method == Method::CONNECT
@@ -1,7 +1,8 @@
 //! Http response
-use std::cell::{Ref, RefMut};
+use std::cell::{Ref, RefCell, RefMut};
 use std::convert::TryFrom;
 use std::error::Error;
+use std::rc::Rc;
 use std::{fmt, str};
 
 use bytes::{Bytes, BytesMut};
@@ -11,9 +12,12 @@ use serde::Serialize;
 #[cfg(feature = "cookie")]
 use coo_kie::{Cookie, CookieJar};
 
-use crate::http::body::{Body, BodyStream, MessageBody, ResponseBody};
+use crate::http::body::{
+    Body, BodyStream, BodyTransform, BoxedTransform, MessageBody, ResponseBody,
+};
 use crate::http::error::{HttpError, ResponseError};
 use crate::http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use crate::http::helpers::Writer;
 use crate::http::message::{BoxedResponseHead, ConnectionType, ResponseHead};
 use crate::http::StatusCode;
 use crate::util::Extensions;
@@ -109,9 +113,7 @@ impl<B> Response<B> {
     /// Get an iterator for the cookies set by this response
     #[inline]
     pub fn cookies(&self) -> CookieIter<'_> {
-        CookieIter {
-            iter: self.head.headers.get_all(header::SET_COOKIE),
-        }
+        CookieIter::new(&self.head.headers)
     }
 
     #[cfg(feature = "cookie")]
@@ -176,6 +178,17 @@ impl<B> Response<B> {
         self.head.extensions.borrow_mut()
     }
 
+    /// Set response body transform
+    ///
+    /// Transform is applied to response body by http dispatcher,
+    /// see [`BodyTransform`] for details.
+    pub fn set_body_transform<T: BodyTransform + 'static>(&mut self, transform: T) {
+        self.head
+            .extensions
+            .get_mut()
+            .insert(BoxedTransform(Box::new(transform)));
+    }
+
     /// Get body of this response
     #[inline]
     pub fn body(&self) -> &ResponseBody<B> {
@@ -258,10 +271,23 @@ impl<B: MessageBody> fmt::Debug for Response<B> {
 }
 
 #[cfg(feature = "cookie")]
+/// Iterator over cookies of `Set-Cookie` headers
+///
+/// Every `Set-Cookie` header contains exactly one cookie, invalid
+/// cookies are skipped.
 pub struct CookieIter<'a> {
     iter: header::GetAll<'a>,
 }
 
+#[cfg(feature = "cookie")]
+impl<'a> CookieIter<'a> {
+    pub(crate) fn new(headers: &'a HeaderMap) -> Self {
+        CookieIter {
+            iter: headers.get_all(header::SET_COOKIE),
+        }
+    }
+}
+
 #[cfg(feature = "cookie")]
 impl<'a> Iterator for CookieIter<'a> {
     type Item = Cookie<'a>;
@@ -269,7 +295,8 @@ impl<'a> Iterator for CookieIter<'a> {
     #[inline]
     fn next(&mut self) -> Option<Cookie<'a>> {
         for v in self.iter.by_ref() {
-            if let Ok(c) = Cookie::parse_encoded(v.to_str().ok()?) {
+            if let Some(c) = v.to_str().ok().and_then(|v| Cookie::parse_encoded(v).ok())
+            {
                 return Some(c);
             }
         }
@@ -281,9 +308,24 @@ impl<'a> Iterator for CookieIter<'a> {
 ///
 /// This type can be used to construct an instance of `Response` through a
 /// builder-like pattern.
+///
+/// Builder could be cloned, that allows to prepare prototype response
+/// and reuse it for multiple responses.
+///
+/// ```rust
+/// use ntex::http::{header, Response};
+///
+/// let mut proto = Response::Ok();
+/// proto.header(header::CACHE_CONTROL, "no-cache");
+///
+/// let resp = proto.clone().text("first");
+/// assert_eq!(resp.headers().get(header::CACHE_CONTROL).unwrap(), "no-cache");
+/// let resp = proto.clone().json(&vec![1, 2]);
+/// assert_eq!(resp.headers().get(header::CACHE_CONTROL).unwrap(), "no-cache");
+/// ```
 pub struct ResponseBuilder {
     head: Option<BoxedResponseHead>,
-    err: Option<HttpError>,
+    err: Option<Rc<HttpError>>,
     #[cfg(feature = "cookie")]
     cookies: Option<CookieJar>,
 }
@@ -416,6 +458,9 @@ impl ResponseBuilder {
     }
 
     /// Disable chunked transfer encoding for HTTP/1.1 streaming responses.
+    ///
+    /// Streaming body is delimited by connection close. Use sized body,
+    /// for example `SizedStream`, to send stream with known length.
     #[inline]
     pub fn no_chunking(&mut self) -> &mut Self {
         if let Some(parts) = parts(&mut self.head, &self.err) {
@@ -557,7 +602,11 @@ impl ResponseBuilder {
     /// `ResponseBuilder` can not be used after this call.
     pub fn message_body<B>(&mut self, body: B) -> Response<B> {
         if let Some(e) = self.err.take() {
-            return Response::from(e).into_body();
+            let res = match Rc::try_unwrap(e) {
+                Ok(e) => Response::from(e),
+                Err(e) => e.error_response(),
+            };
+            return res.into_body();
         }
 
         #[allow(unused_mut)]
@@ -595,25 +644,42 @@ impl ResponseBuilder {
 
     /// Set a json body and generate `Response`
     ///
+    /// Content type is set to `application/json` unless it is already set.
+    /// Serialization error generates internal server error response,
+    /// error details are logged.
+    ///
     /// `ResponseBuilder` can not be used after this call.
     pub fn json<T: Serialize>(&mut self, value: &T) -> Response {
-        match serde_json::to_string(value) {
+        match serialize_json(value) {
             Ok(body) => {
-                let contains = if let Some(parts) = parts(&mut self.head, &self.err) {
-                    parts.headers.contains_key(header::CONTENT_TYPE)
-                } else {
-                    true
-                };
-                if !contains {
-                    self.header(header::CONTENT_TYPE, "application/json");
-                }
-
-                self.body(Body::from(body))
+                self.default_content_type("application/json");
+                self.body(Body::Bytes(body))
             }
             Err(e) => e.into(),
         }
     }
 
+    /// Set a text body and generate `Response`
+    ///
+    /// Content type is set to `text/plain; charset=utf-8` unless it is
+    /// already set.
+    ///
+    /// `ResponseBuilder` can not be used after this call.
+    pub fn text<T: Into<String>>(&mut self, body: T) -> Response {
+        self.default_content_type("text/plain; charset=utf-8");
+        self.body(Body::from(body.into()))
+    }
+
+    fn default_content_type(&mut self, value: &'static str) {
+        if let Some(parts) = parts(&mut self.head, &self.err) {
+            if !parts.headers.contains_key(header::CONTENT_TYPE) {
+                parts
+                    .headers
+                    .insert(header::CONTENT_TYPE, HeaderValue::from_static(value));
+            }
+        }
+    }
+
     #[inline]
     /// Set an empty body and generate `Response`
     ///
@@ -636,7 +702,7 @@ impl ResponseBuilder {
 #[inline]
 fn parts<'a>(
     parts: &'a mut Option<BoxedResponseHead>,
-    err: &Option<HttpError>,
+    err: &Option<Rc<HttpError>>,
 ) -> Option<&'a mut ResponseHead> {
     if err.is_some() {
         return None;
@@ -693,9 +759,7 @@ impl<'a> From<&'a ResponseHead> for ResponseBuilder {
             // If this response has cookies, load them into a jar
             let mut jar: Option<CookieJar> = None;
 
-            let cookies = CookieIter {
-                iter: head.headers.get_all(header::SET_COOKIE),
-            };
+            let cookies = CookieIter::new(&head.headers);
             for c in cookies {
                 if let Some(ref mut j) = jar {
                     j.add_original(c.into_owned());
@@ -722,6 +786,17 @@ impl<'a> From<&'a ResponseHead> for ResponseBuilder {
     }
 }
 
+impl Clone for ResponseBuilder {
+    fn clone(&self) -> Self {
+        ResponseBuilder {
+            head: self.head.as_ref().map(|head| head.clone_head()),
+            err: self.err.clone(),
+            #[cfg(feature = "cookie")]
+            cookies: self.cookies.clone(),
+        }
+    }
+}
+
 impl fmt::Debug for ResponseBuilder {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let head = self.head.as_ref().unwrap();
@@ -741,10 +816,32 @@ impl fmt::Debug for ResponseBuilder {
     }
 }
 
-fn log_error<T: Into<HttpError>>(err: T) -> HttpError {
+fn log_error<T: Into<HttpError>>(err: T) -> Rc<HttpError> {
     let e = err.into();
     error!("Error in ResponseBuilder {}", e);
-    e
+    Rc::new(e)
+}
+
+thread_local! {
+    static JSON_BUF: RefCell<BytesMut> = RefCell::new(BytesMut::new());
+}
+
+/// Serialize value to a thread local buffer, serialized value
+/// is split from the buffer so its capacity is reused
+fn serialize_json<T: Serialize>(value: &T) -> Result<Bytes, serde_json::Error> {
+    JSON_BUF.with(|buf| {
+        let mut buf = buf.borrow_mut();
+        if buf.capacity() < 1024 {
+            buf.reserve(8192);
+        }
+        match serde_json::to_writer(Writer(&mut buf), value) {
+            Ok(_) => Ok(buf.split().freeze()),
+            Err(e) => {
+                buf.clear();
+                Err(e)
+            }
+        }
+    })
 }
 
 /// Helper converters
@@ -857,7 +954,7 @@ mod tests {
                     .max_age(time::Duration::days(1))
                     .finish(),
             )
-            .del_cookie(&cookies[1])
+            .del_cookie(&cookies[0])
             .finish();
 
         let mut val: Vec<_> = resp
@@ -892,9 +989,33 @@ mod tests {
 
         let mut iter = r.cookies();
         let v = iter.next().unwrap();
-        assert_eq!((v.name(), v.value()), ("cookie3", "val300"));
-        let v = iter.next().unwrap();
         assert_eq!((v.name(), v.value()), ("original", "val100"));
+        let v = iter.next().unwrap();
+        assert_eq!((v.name(), v.value()), ("cookie3", "val300"));
+    }
+
+    #[cfg(feature = "cookie")]
+    #[test]
+    fn test_cookies_invalid() {
+        let mut r = Response::Ok().finish();
+        let h = r.headers_mut();
+        h.append(
+            header::SET_COOKIE,
+            HeaderValue::from_static("id=a=b; Expires=Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        h.append(
+            header::SET_COOKIE,
+            HeaderValue::from_bytes(b"bin=\xfe").unwrap(),
+        );
+        h.append(header::SET_COOKIE, HeaderValue::from_static("=novalue"));
+        h.append(header::SET_COOKIE, HeaderValue::from_static("last=1"));
+
+        // invalid cookies are skipped
+        let cookies: Vec<_> = r.cookies().collect();
+        assert_eq!(cookies.len(), 2);
+        assert_eq!((cookies[0].name(), cookies[0].value()), ("id", "a=b"));
+        assert_eq!(cookies[0].expires().unwrap().year(), 2015);
+        assert_eq!((cookies[1].name(), cookies[1].value()), ("last", "1"));
     }
 
     #[test]
@@ -955,6 +1076,63 @@ mod tests {
         assert_eq!(resp.body().get_ref(), br#"{"test-key":"test-value"}"#);
     }
 
+    #[test]
+    fn test_json_error() {
+        let mut map = std::collections::BTreeMap::new();
+        map.insert((1, 2), 3);
+        let resp = Response::build(StatusCode::OK).json(&map);
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        // buffer is usable after error
+        let resp = Response::build(StatusCode::OK).json(&vec![1]);
+        assert_eq!(resp.body().get_ref(), b"[1]");
+    }
+
+    #[test]
+    fn test_text() {
+        let resp = Response::build(StatusCode::OK).text("hello");
+        let ct = resp.headers().get(CONTENT_TYPE).unwrap();
+        assert_eq!(ct, HeaderValue::from_static("text/plain; charset=utf-8"));
+        assert_eq!(resp.body().get_ref(), b"hello");
+
+        let resp = Response::build(StatusCode::OK)
+            .content_type(mime::TEXT_HTML_UTF_8.as_ref())
+            .text(String::from("<p>hello</p>"));
+        let ct = resp.headers().get(CONTENT_TYPE).unwrap();
+        assert_eq!(ct, HeaderValue::from_static("text/html; charset=utf-8"));
+    }
+
+    #[test]
+    fn test_clone() {
+        let mut proto = Response::build(StatusCode::CREATED);
+        proto
+            .header(COOKIE, "cookie1=value1")
+            .reason("Done")
+            .force_close()
+            .if_true(true, |b| {
+                b.header(CONTENT_TYPE, "text/json");
+            });
+
+        for _ in 0..2 {
+            let resp = proto.clone().json(&vec!["v1"]);
+            assert_eq!(resp.status(), StatusCode::CREATED);
+            assert_eq!(resp.head().reason, Some("Done"));
+            assert!(!resp.keep_alive());
+            assert_eq!(resp.headers().get(COOKIE).unwrap(), "cookie1=value1");
+            assert_eq!(resp.headers().get(CONTENT_TYPE).unwrap(), "text/json");
+            assert_eq!(resp.body().get_ref(), b"[\"v1\"]");
+        }
+        let resp = proto.take().finish();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+
+        let mut proto = Response::build(StatusCode::OK);
+        proto.header(COOKIE, "\n");
+        let resp = proto.clone().finish();
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let resp = proto.finish();
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
     #[test]
     #[allow(clippy::cognitive_complexity)]
     fn test_into_response() {
@@ -16,11 +16,14 @@ use crate::service::{pipeline_factory, IntoServiceFactory, Service, ServiceFacto
 
 use super::body::MessageBody;
 use super::builder::HttpServiceBuilder;
-use super::config::{DispatcherConfig, KeepAlive, ServiceConfig};
+use super::config::{
+    set_tcp_keepalive, DispatcherConfig, KeepAlive, ServiceConfig, WireCaptureIo,
+};
 use super::error::{DispatchError, ResponseError};
 use super::helpers::DataFactory;
 use super::request::Request;
 use super::response::Response;
+use super::stats::ServiceStats;
 use super::{h1, h2::Dispatcher, Protocol};
 
 /// `ServiceFactory` HTTP1.1/HTTP2 transport implementation
@@ -96,6 +99,11 @@ where
     <S::Service as Service>::Future: 'static,
     B: MessageBody,
 {
+    /// Live counters of the service
+    pub fn stats(&self) -> &ServiceStats {
+        self.cfg.stats()
+    }
+
     /// Provide service for `EXPECT: 100-Continue` support.
     ///
     /// Service get called with request that contains `EXPECT` header.
@@ -182,7 +190,9 @@ where
         Error = DispatchError,
         InitError = (),
     > {
-        pipeline_factory(|io: TcpStream| {
+        let ka = self.cfg.0.tcp_keepalive;
+        pipeline_factory(move |io: TcpStream| {
+            set_tcp_keepalive(&io, ka.as_ref());
             let peer_addr = io.peer_addr().ok();
             ok((io, Protocol::Http1, peer_addr))
         })
@@ -228,13 +238,15 @@ mod openssl {
             Error = SslError<DispatchError>,
             InitError = (),
         > {
+            let ka = self.cfg.0.tcp_keepalive;
             pipeline_factory(
                 Acceptor::new(acceptor)
                     .timeout(self.cfg.0.ssl_handshake_timeout)
                     .map_err(SslError::Ssl)
                     .map_init_err(|_| panic!()),
             )
-            .and_then(|io: SslStream<TcpStream>| {
+            .and_then(move |io: SslStream<TcpStream>| {
+                set_tcp_keepalive(io.get_ref(), ka.as_ref());
                 let proto = if let Some(protos) = io.ssl().selected_alpn_protocol() {
                     if protos.windows(2).any(|window| window == b"h2") {
                         Protocol::Http2
@@ -293,13 +305,15 @@ mod rustls {
             let protos = vec!["h2".to_string().into(), "http/1.1".to_string().into()];
             config.set_protocols(&protos);
 
+            let ka = self.cfg.0.tcp_keepalive;
             pipeline_factory(
                 Acceptor::new(config)
                     .timeout(self.cfg.0.ssl_handshake_timeout)
                     .map_err(SslError::Ssl)
                     .map_init_err(|_| panic!()),
             )
-            .and_then(|io: TlsStream<TcpStream>| {
+            .and_then(move |io: TlsStream<TcpStream>| {
+                set_tcp_keepalive(io.get_ref().0, ka.as_ref());
                 let proto = io
                     .get_ref()
                     .1
@@ -544,7 +558,10 @@ where
         match proto {
             Protocol::Http2 => HttpServiceHandlerResponse {
                 state: State::H2Handshake(Some((
-                    server::handshake(io),
+                    server::handshake(WireCaptureIo::new(
+                        io,
+                        self.config.wire_capture.clone(),
+                    )),
                     self.config.clone(),
                     on_connect,
                     peer_addr,
@@ -592,10 +609,10 @@ where
     U::Error: fmt::Display,
 {
     H1(#[pin] h1::Dispatcher<T, S, B, X, U>),
-    H2(Dispatcher<T, S, B, X, U>),
+    H2(Dispatcher<WireCaptureIo<T>, S, B, X, U>),
     H2Handshake(
         Option<(
-            Handshake<T, Bytes>,
+            Handshake<WireCaptureIo<T>, Bytes>,
             Rc<DispatcherConfig<S, X, U>>,
             Option<Box<dyn DataFactory>>,
             Option<net::SocketAddr>,
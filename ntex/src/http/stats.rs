@@ -0,0 +1,181 @@
+use std::cell::Cell;
+use std::fmt;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Live counters of http service
+///
+/// Counters are updated by http/1 and http/2 dispatchers. Handle could be
+/// created in advance and passed to `HttpServiceBuilder::stats()`, same
+/// handle could be used for services of all server workers. Otherwise
+/// every service gets its own counters, available via
+/// `HttpService::stats()`. Cloning handle is cheap.
+///
+/// ```rust,no_run
+/// use ntex::http::{HttpService, Response, ServiceStats};
+///
+/// let stats = ServiceStats::new();
+///
+/// let stats2 = stats.clone();
+/// let server = ntex::server::build().bind("http", "127.0.0.1:8080", move || {
+///     HttpService::build()
+///         .stats(stats2.clone())
+///         .finish(|_| futures::future::ok::<_, std::io::Error>(Response::Ok()))
+///         .tcp()
+/// });
+///
+/// println!("open connections: {}", stats.connections());
+/// ```
+#[derive(Clone, Default)]
+pub struct ServiceStats(Arc<Inner>);
+
+#[derive(Default)]
+struct Inner {
+    connections: AtomicUsize,
+    idle: AtomicUsize,
+    requests: AtomicUsize,
+    in_flight: AtomicUsize,
+}
+
+impl ServiceStats {
+    /// Create new counters
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of open connections
+    pub fn connections(&self) -> usize {
+        self.0.connections.load(Ordering::Relaxed)
+    }
+
+    /// Number of open connections without in-flight requests
+    pub fn idle_connections(&self) -> usize {
+        self.0.idle.load(Ordering::Relaxed)
+    }
+
+    /// Total number of received requests
+    pub fn requests(&self) -> usize {
+        self.0.requests.load(Ordering::Relaxed)
+    }
+
+    /// Number of requests that are received but responses are not
+    /// completely sent yet
+    pub fn in_flight(&self) -> usize {
+        self.0.in_flight.load(Ordering::Relaxed)
+    }
+
+    /// Account new connection, connection is counted until returned
+    /// value is dropped
+    pub(super) fn connection(&self) -> ConnectionStats {
+        self.0.connections.fetch_add(1, Ordering::Relaxed);
+        self.0.idle.fetch_add(1, Ordering::Relaxed);
+        ConnectionStats(Rc::new(ConnInner {
+            stats: self.clone(),
+            in_flight: Cell::new(0),
+        }))
+    }
+}
+
+impl fmt::Debug for ServiceStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServiceStats")
+            .field("connections", &self.connections())
+            .field("idle_connections", &self.idle_connections())
+            .field("requests", &self.requests())
+            .field("in_flight", &self.in_flight())
+            .finish()
+    }
+}
+
+/// Connection counters guard
+pub(super) struct ConnectionStats(Rc<ConnInner>);
+
+struct ConnInner {
+    stats: ServiceStats,
+    in_flight: Cell<usize>,
+}
+
+impl ConnectionStats {
+    /// Account new request, request is in-flight until returned
+    /// value is dropped
+    pub(super) fn request(&self) -> RequestStats {
+        let stats = &self.0.stats.0;
+        stats.requests.fetch_add(1, Ordering::Relaxed);
+        stats.in_flight.fetch_add(1, Ordering::Relaxed);
+
+        let in_flight = self.0.in_flight.get();
+        if in_flight == 0 {
+            stats.idle.fetch_sub(1, Ordering::Relaxed);
+        }
+        self.0.in_flight.set(in_flight + 1);
+        RequestStats(self.0.clone())
+    }
+}
+
+impl Drop for ConnInner {
+    fn drop(&mut self) {
+        // all requests are finished, connection is idle
+        let stats = &self.stats.0;
+        stats.idle.fetch_sub(1, Ordering::Relaxed);
+        stats.connections.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Request counters guard
+pub(super) struct RequestStats(Rc<ConnInner>);
+
+impl Drop for RequestStats {
+    fn drop(&mut self) {
+        let stats = &self.0.stats.0;
+        stats.in_flight.fetch_sub(1, Ordering::Relaxed);
+
+        let in_flight = self.0.in_flight.get() - 1;
+        if in_flight == 0 {
+            stats.idle.fetch_add(1, Ordering::Relaxed);
+        }
+        self.0.in_flight.set(in_flight);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats() {
+        let stats = ServiceStats::new();
+        let conn1 = stats.connection();
+        let conn2 = stats.connection();
+        assert_eq!(stats.connections(), 2);
+        assert_eq!(stats.idle_connections(), 2);
+
+        let req1 = conn1.request();
+        let req2 = conn1.request();
+        assert_eq!(stats.requests(), 2);
+        assert_eq!(stats.in_flight(), 2);
+        assert_eq!(stats.idle_connections(), 1);
+
+        drop(req1);
+        assert_eq!(stats.in_flight(), 1);
+        assert_eq!(stats.idle_connections(), 1);
+        drop(req2);
+        assert_eq!(stats.in_flight(), 0);
+        assert_eq!(stats.idle_connections(), 2);
+
+        // connection is counted until all requests are finished
+        let req3 = conn2.request();
+        drop(conn2);
+        assert_eq!(stats.connections(), 2);
+        assert_eq!(stats.idle_connections(), 1);
+        drop(req3);
+        assert_eq!(stats.connections(), 1);
+        assert_eq!(stats.idle_connections(), 1);
+
+        drop(conn1);
+        assert_eq!(stats.connections(), 0);
+        assert_eq!(stats.idle_connections(), 0);
+        assert_eq!(stats.requests(), 3);
+        assert!(format!("{:?}", stats).contains("requests: 3"));
+    }
+}
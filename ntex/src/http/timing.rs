@@ -0,0 +1,78 @@
+use std::time::{Duration, Instant};
+
+use super::header::{HeaderName, HeaderValue};
+
+/// Request timing
+///
+/// Timing is recorded when request head is received if it is enabled with
+/// `HttpServiceBuilder::request_timing()` and gets stored to the request's
+/// extensions. It could be used for custom latency headers or logging.
+///
+/// ```rust
+/// use ntex::http::RequestTiming;
+/// use ntex::web::{self, HttpRequest, HttpResponse};
+///
+/// async fn index(req: HttpRequest) -> HttpResponse {
+///     let elapsed = req
+///         .extensions()
+///         .get::<RequestTiming>()
+///         .map(|t| t.elapsed().as_micros())
+///         .unwrap_or(0);
+///     HttpResponse::Ok()
+///         .header("x-elapsed", elapsed.to_string())
+///         .finish()
+/// }
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct RequestTiming {
+    received: Instant,
+}
+
+impl RequestTiming {
+    pub(super) fn new() -> Self {
+        RequestTiming {
+            received: Instant::now(),
+        }
+    }
+
+    /// Time when request head was received
+    pub fn received(&self) -> Instant {
+        self.received
+    }
+
+    /// Time elapsed since request head was received
+    pub fn elapsed(&self) -> Duration {
+        self.received.elapsed()
+    }
+
+    /// `Server-Timing` header value with total processing time
+    /// in milliseconds, `total;dur=1.250`
+    pub fn server_timing(&self) -> HeaderValue {
+        let dur = self.elapsed().as_micros() as f64 / 1000.0;
+        HeaderValue::from_str(&format!("total;dur={:.3}", dur)).unwrap()
+    }
+}
+
+/// `Server-Timing` header name
+pub(super) fn server_timing_name() -> HeaderName {
+    HeaderName::from_static("server-timing")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_timing() {
+        let timing = RequestTiming::new();
+        assert!(timing.received() <= Instant::now());
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(timing.elapsed() >= Duration::from_millis(5));
+
+        let val = timing.server_timing();
+        let val = val.to_str().unwrap();
+        assert!(val.starts_with("total;dur="));
+        let dur: f64 = val[10..].parse().unwrap();
+        assert!(dur >= 5.0);
+    }
+}
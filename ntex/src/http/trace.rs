@@ -0,0 +1,81 @@
+//! Per-request tracing spans
+use std::future::Future;
+use std::time::Instant;
+
+use tracing::field::Empty;
+use tracing::span::EnteredSpan;
+use tracing::{Instrument, Span};
+
+use crate::http::message::{RequestHead, ResponseHead};
+use crate::http::StatusCode;
+
+/// Request span with request start time
+pub(crate) struct RequestSpan {
+    span: Span,
+    start: Instant,
+}
+
+impl RequestSpan {
+    /// Create span for server request
+    pub(crate) fn server(head: &RequestHead) -> Self {
+        RequestSpan {
+            span: tracing::info_span!(
+                parent: None,
+                "http.request",
+                method = %head.method,
+                path = %head.uri.path(),
+                version = ?head.version,
+                status = Empty,
+                duration_ms = Empty,
+            ),
+            start: Instant::now(),
+        }
+    }
+
+    /// Create span for client request
+    pub(crate) fn client(head: &RequestHead) -> Self {
+        RequestSpan {
+            span: tracing::info_span!(
+                "http.client.request",
+                method = %head.method,
+                uri = %head.uri,
+                version = ?head.version,
+                status = Empty,
+                duration_ms = Empty,
+            ),
+            start: Instant::now(),
+        }
+    }
+
+    /// Enter span, span is exited when returned guard is dropped
+    pub(crate) fn enter(&self) -> EnteredSpan {
+        self.span.clone().entered()
+    }
+
+    /// Tracing span
+    pub(crate) fn span(&self) -> &Span {
+        &self.span
+    }
+
+    /// Record response status and request duration
+    pub(crate) fn record_response(&self, status: Option<StatusCode>) {
+        if let Some(status) = status {
+            self.span.record("status", status.as_u16());
+        }
+        self.span
+            .record("duration_ms", self.start.elapsed().as_millis() as u64);
+    }
+}
+
+/// Run client request future inside of request span
+pub(crate) async fn client_request<F, P, E>(
+    span: RequestSpan,
+    fut: F,
+) -> Result<(ResponseHead, P), E>
+where
+    F: Future<Output = Result<(ResponseHead, P), E>>,
+{
+    let res = fut.instrument(span.span().clone()).await;
+    span.record_response(res.as_ref().ok().map(|(head, _)| head.status));
+    res
+}
@@ -0,0 +1,137 @@
+//! Uri types and path utilities
+use std::path::{Component, Path, PathBuf};
+
+pub use http::uri::*;
+
+pub use crate::router::{percent_decode_path_segment, DecodeError};
+
+/// Remove dot segments from path (RFC 3986 §5.2.4)
+///
+/// Percent-encoded dots are not decoded, path should be normalized
+/// before decoding of path segments.
+pub fn normalize_path(path: &str) -> String {
+    let mut input = path;
+    let mut output = String::with_capacity(path.len());
+
+    while !input.is_empty() {
+        if let Some(rest) = input.strip_prefix("../") {
+            input = rest;
+        } else if let Some(rest) = input.strip_prefix("./") {
+            input = rest;
+        } else if input.starts_with("/./") {
+            input = &input[2..];
+        } else if input == "/." {
+            input = "/";
+        } else if input.starts_with("/../") {
+            input = &input[3..];
+            remove_last_segment(&mut output);
+        } else if input == "/.." {
+            input = "/";
+            remove_last_segment(&mut output);
+        } else if input == "." || input == ".." {
+            input = "";
+        } else {
+            // move first path segment to output
+            let start = if input.starts_with('/') { 1 } else { 0 };
+            let end = input[start..]
+                .find('/')
+                .map(|idx| idx + start)
+                .unwrap_or_else(|| input.len());
+            output.push_str(&input[..end]);
+            input = &input[end..];
+        }
+    }
+    output
+}
+
+fn remove_last_segment(output: &mut String) {
+    if let Some(idx) = output.rfind('/') {
+        output.truncate(idx);
+    } else {
+        output.clear();
+    }
+}
+
+/// Join decoded path to base directory
+///
+/// Path is split by `/`, empty and `.` segments are skipped, `..` segment
+/// removes previous segment. `None` is returned if path escapes base
+/// directory, or if any segment contains NUL byte, backslash or is not
+/// a plain file name (for example windows drive prefix).
+///
+/// Check is lexical, file system and symlinks are not inspected.
+pub fn safe_join<P: AsRef<Path>>(base: P, path: &str) -> Option<PathBuf> {
+    let mut rel = PathBuf::new();
+
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => continue,
+            ".." => {
+                if !rel.pop() {
+                    return None;
+                }
+            }
+            _ => {
+                if segment.contains('\0') || segment.contains('\\') {
+                    return None;
+                }
+                let mut components = Path::new(segment).components();
+                match (components.next(), components.next()) {
+                    (Some(Component::Normal(_)), None) => rel.push(segment),
+                    _ => return None,
+                }
+            }
+        }
+    }
+    Some(base.as_ref().join(rel))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path("/a/b/c/./../../g"), "/a/g");
+        assert_eq!(normalize_path("mid/content=5/../6"), "mid/6");
+        assert_eq!(normalize_path("/a/b/.."), "/a/");
+        assert_eq!(normalize_path("/a/b/."), "/a/b/");
+        assert_eq!(normalize_path("/../../a"), "/a");
+        assert_eq!(normalize_path("../a/./b"), "a/b");
+        assert_eq!(normalize_path("/.."), "/");
+        assert_eq!(normalize_path("."), "");
+        assert_eq!(normalize_path("/a/%2E%2E/b"), "/a/%2E%2E/b");
+        assert_eq!(normalize_path("/a//b/"), "/a//b/");
+        assert_eq!(normalize_path("/a/..b/.c"), "/a/..b/.c");
+    }
+
+    #[test]
+    fn test_safe_join() {
+        let base = Path::new("/srv/www");
+        assert_eq!(
+            safe_join(base, "a/b.txt"),
+            Some(PathBuf::from("/srv/www/a/b.txt"))
+        );
+        assert_eq!(
+            safe_join(base, "/a//./b/../c.txt"),
+            Some(PathBuf::from("/srv/www/a/c.txt"))
+        );
+        assert_eq!(safe_join(base, ""), Some(PathBuf::from("/srv/www")));
+        assert_eq!(safe_join(base, "a/../b"), Some(PathBuf::from("/srv/www/b")));
+        assert_eq!(safe_join(base, ".."), None);
+        assert_eq!(safe_join(base, "a/../../etc/passwd"), None);
+        assert_eq!(safe_join(base, "a\0b"), None);
+        assert_eq!(safe_join(base, "a\\..\\b"), None);
+    }
+
+    #[test]
+    fn test_decode_and_join() {
+        let segment = percent_decode_path_segment("%2E%2E", false).unwrap();
+        assert_eq!(segment, "..");
+        assert_eq!(safe_join("/srv", &segment), None);
+        assert_eq!(
+            percent_decode_path_segment("a%2Fb", false),
+            Err(DecodeError::Slash)
+        );
+    }
+}
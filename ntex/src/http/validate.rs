@@ -0,0 +1,171 @@
+//! Http service configuration validation
+use super::config::Inner;
+use super::error::{ConfigError, ConfigIssue};
+
+const DEFAULT_MAX_HEADER_SIZE: usize = 32_768;
+const DEFAULT_MAX_LINE_SIZE: usize = 8_192;
+const DEFAULT_H2_SEND_BUFFER: usize = 16_384;
+
+/// Check service configuration for inconsistent settings.
+///
+/// Every inconsistent setting gets corrected in place:
+///
+/// * disconnect timeout larger than keep-alive timeout is reduced
+///   to keep-alive timeout
+/// * zero max header size is replaced with default 32Kb
+/// * zero max request line size is replaced with default 8Kb
+/// * zero http/2 send buffer size is replaced with default 16Kb
+/// * zero tcp keep-alive retries is replaced with system default
+pub(super) fn validate(cfg: &mut Inner) -> Result<(), ConfigError> {
+    let mut issues = Vec::new();
+
+    if let Some(ka) = cfg.keep_alive {
+        let ka = ka.as_millis() as u64;
+        if cfg.client_disconnect > ka {
+            issues.push(ConfigIssue::DisconnectExceedsKeepAlive(
+                cfg.client_disconnect,
+                ka / 1000,
+            ));
+            cfg.client_disconnect = ka;
+        }
+    }
+
+    if cfg.max_header_size == 0 {
+        issues.push(ConfigIssue::ZeroMaxHeaderSize);
+        cfg.max_header_size = DEFAULT_MAX_HEADER_SIZE;
+    }
+
+    if cfg.max_line_size == 0 {
+        issues.push(ConfigIssue::ZeroMaxLineSize);
+        cfg.max_line_size = DEFAULT_MAX_LINE_SIZE;
+    }
+
+    if cfg.h2_send_buffer == 0 {
+        issues.push(ConfigIssue::ZeroH2SendBuffer);
+        cfg.h2_send_buffer = DEFAULT_H2_SEND_BUFFER;
+    }
+
+    if let Some(ref mut ka) = cfg.tcp_keepalive {
+        if ka.retries == Some(0) {
+            issues.push(ConfigIssue::ZeroKeepaliveRetries);
+            ka.retries = None;
+        }
+    }
+
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(ConfigError(issues))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::http::config::{KeepAlive, TcpKeepalive};
+
+    fn inner(ka: KeepAlive, disconnect: u64) -> Inner {
+        Inner::new(ka, 3000, disconnect, 5000)
+    }
+
+    #[ntex_rt::test]
+    async fn test_valid() {
+        let mut cfg = inner(KeepAlive::Timeout(5), 3000);
+        assert!(validate(&mut cfg).is_ok());
+        assert_eq!(cfg.client_disconnect, 3000);
+        assert_eq!(cfg.max_header_size, DEFAULT_MAX_HEADER_SIZE);
+
+        // disconnect timeout is not limited without keep-alive timer
+        let mut cfg = inner(KeepAlive::Os, 60_000);
+        assert!(validate(&mut cfg).is_ok());
+        let mut cfg = inner(KeepAlive::Disabled, 60_000);
+        assert!(validate(&mut cfg).is_ok());
+        assert_eq!(cfg.client_disconnect, 60_000);
+    }
+
+    #[ntex_rt::test]
+    async fn test_disconnect_exceeds_keep_alive() {
+        let mut cfg = inner(KeepAlive::Timeout(2), 5000);
+        let err = validate(&mut cfg).err().unwrap();
+        assert_eq!(
+            err.issues(),
+            &[ConfigIssue::DisconnectExceedsKeepAlive(5000, 2)]
+        );
+        assert_eq!(cfg.client_disconnect, 2000);
+
+        let mut cfg = inner(KeepAlive::Timeout(2), 2000);
+        assert!(validate(&mut cfg).is_ok());
+    }
+
+    #[ntex_rt::test]
+    async fn test_zero_max_header_size() {
+        let mut cfg = inner(KeepAlive::Timeout(5), 0);
+        cfg.max_header_size = 0;
+        let err = validate(&mut cfg).err().unwrap();
+        assert_eq!(err.issues(), &[ConfigIssue::ZeroMaxHeaderSize]);
+        assert_eq!(cfg.max_header_size, DEFAULT_MAX_HEADER_SIZE);
+    }
+
+    #[ntex_rt::test]
+    async fn test_zero_max_line_size() {
+        let mut cfg = inner(KeepAlive::Timeout(5), 0);
+        cfg.max_line_size = 0;
+        let err = validate(&mut cfg).err().unwrap();
+        assert_eq!(err.issues(), &[ConfigIssue::ZeroMaxLineSize]);
+        assert_eq!(cfg.max_line_size, DEFAULT_MAX_LINE_SIZE);
+    }
+
+    #[ntex_rt::test]
+    async fn test_zero_h2_send_buffer() {
+        let mut cfg = inner(KeepAlive::Timeout(5), 0);
+        cfg.h2_send_buffer = 0;
+        let err = validate(&mut cfg).err().unwrap();
+        assert_eq!(err.issues(), &[ConfigIssue::ZeroH2SendBuffer]);
+        assert_eq!(cfg.h2_send_buffer, DEFAULT_H2_SEND_BUFFER);
+    }
+
+    #[ntex_rt::test]
+    async fn test_zero_keepalive_retries() {
+        let ka = TcpKeepalive::new().time(Duration::from_secs(30)).retries(0);
+        let mut cfg = inner(KeepAlive::Tcp(ka), 0);
+        let err = validate(&mut cfg).err().unwrap();
+        assert_eq!(err.issues(), &[ConfigIssue::ZeroKeepaliveRetries]);
+        let ka = cfg.tcp_keepalive.unwrap();
+        assert_eq!(ka.retries, None);
+        assert_eq!(ka.time, Some(Duration::from_secs(30)));
+
+        let mut cfg = inner(KeepAlive::Tcp(ka.retries(3)), 0);
+        assert!(validate(&mut cfg).is_ok());
+    }
+
+    #[ntex_rt::test]
+    async fn test_all_issues() {
+        let mut cfg = inner(KeepAlive::Timeout(1), 3000);
+        cfg.max_header_size = 0;
+        let err = validate(&mut cfg).err().unwrap();
+        assert_eq!(
+            err.issues(),
+            &[
+                ConfigIssue::DisconnectExceedsKeepAlive(3000, 1),
+                ConfigIssue::ZeroMaxHeaderSize
+            ]
+        );
+        assert_eq!(
+            format!("{}", err),
+            "Inconsistent http service configuration: \
+             Disconnect timeout 3000ms exceeds keep-alive timeout 1s; \
+             Max header size is 0, every request would be rejected"
+        );
+        assert!(validate(&mut cfg).is_ok());
+    }
+
+    #[ntex_rt::test]
+    async fn test_zero_disables() {
+        // zero keep-alive timeout disables keep-alive
+        let cfg = inner(KeepAlive::Timeout(0), 0);
+        assert!(!cfg.ka_enabled);
+        assert!(cfg.keep_alive.is_none());
+    }
+}
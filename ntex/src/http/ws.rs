@@ -1,10 +1,20 @@
 //! Websockets protocol helpers
+use std::task::{Context, Poll};
+use std::{future::Future, io, marker::PhantomData, rc::Rc};
+
 use derive_more::Display;
+use futures::future::{ok, LocalBoxFuture, Ready};
+use futures::SinkExt;
 
+use crate::codec::{AsyncRead, AsyncWrite, Framed};
+use crate::http::body::BodySize;
 use crate::http::error::ResponseError;
-use crate::http::message::RequestHead;
+use crate::http::h1::Codec;
+use crate::http::message::{ConnectionType, RequestHead};
+use crate::http::request::Request;
 use crate::http::response::{Response, ResponseBuilder};
 use crate::http::{header, Method, StatusCode};
+use crate::{ws, Service, ServiceFactory};
 
 /// Websocket handshake errors
 #[derive(PartialEq, Debug, Display)]
@@ -132,6 +142,147 @@ pub fn handshake_response(req: &RequestHead) -> ResponseBuilder {
         .take()
 }
 
+/// Create websocket upgrade service.
+///
+/// Service could be passed to `HttpServiceBuilder::upgrade()`. It verifies
+/// websocket handshake, sends `101 Switching Protocols` response and calls
+/// `handler` with original request and websocket framed connection. Bytes
+/// that are already read from the connection are preserved.
+///
+/// Non-websocket upgrade requests are rejected with `426 Upgrade Required`
+/// response, invalid handshakes are rejected with `400 Bad Request`.
+///
+/// ```rust,no_run
+/// use futures::{SinkExt, StreamExt};
+/// use ntex::codec::{AsyncRead, AsyncWrite, Framed};
+/// use ntex::http::{ws::upgrade_service, HttpService, Request, Response};
+/// use ntex::ws;
+///
+/// async fn echo<T>(_: Request, mut framed: Framed<T, ws::Codec>) -> std::io::Result<()>
+/// where
+///     T: AsyncRead + AsyncWrite + Unpin,
+/// {
+///     while let Some(Ok(frame)) = framed.next().await {
+///         if let ws::Frame::Text(text) = frame {
+///             if framed.send(ws::Message::Binary(text)).await.is_err() {
+///                 break;
+///             }
+///         }
+///     }
+///     Ok(())
+/// }
+///
+/// let srv = HttpService::build()
+///     .upgrade(upgrade_service(echo))
+///     .h1(|_| futures::future::ok::<_, std::io::Error>(Response::NotFound()))
+///     .tcp();
+/// ```
+pub fn upgrade_service<T, F, R, E>(handler: F) -> UpgradeService<T, F>
+where
+    F: Fn(Request, Framed<T, ws::Codec>) -> R + 'static,
+    R: Future<Output = Result<(), E>> + 'static,
+    E: From<io::Error>,
+{
+    UpgradeService {
+        handler: Rc::new(handler),
+        codec: ws::Codec::new(),
+        _t: PhantomData,
+    }
+}
+
+/// Websocket upgrade service, see [`upgrade_service`](fn.upgrade_service.html)
+pub struct UpgradeService<T, F> {
+    handler: Rc<F>,
+    codec: ws::Codec,
+    _t: PhantomData<T>,
+}
+
+impl<T, F> UpgradeService<T, F> {
+    /// Set websocket codec for upgraded connections.
+    ///
+    /// By default `ws::Codec::new()` is used.
+    pub fn codec(mut self, codec: ws::Codec) -> Self {
+        self.codec = codec;
+        self
+    }
+}
+
+impl<T, F> Clone for UpgradeService<T, F> {
+    fn clone(&self) -> Self {
+        UpgradeService {
+            handler: self.handler.clone(),
+            codec: self.codec,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<T, F, R, E> ServiceFactory for UpgradeService<T, F>
+where
+    T: AsyncRead + AsyncWrite + Unpin + 'static,
+    F: Fn(Request, Framed<T, ws::Codec>) -> R + 'static,
+    R: Future<Output = Result<(), E>> + 'static,
+    E: From<io::Error>,
+{
+    type Config = ();
+    type Request = (Request, Framed<T, Codec>);
+    type Response = ();
+    type Error = E;
+    type Service = UpgradeService<T, F>;
+    type InitError = io::Error;
+    type Future = Ready<Result<Self::Service, Self::InitError>>;
+
+    #[inline]
+    fn new_service(&self, _: ()) -> Self::Future {
+        ok(self.clone())
+    }
+}
+
+impl<T, F, R, E> Service for UpgradeService<T, F>
+where
+    T: AsyncRead + AsyncWrite + Unpin + 'static,
+    F: Fn(Request, Framed<T, ws::Codec>) -> R + 'static,
+    R: Future<Output = Result<(), E>> + 'static,
+    E: From<io::Error>,
+{
+    type Request = (Request, Framed<T, Codec>);
+    type Response = ();
+    type Error = E;
+    type Future = LocalBoxFuture<'static, Result<(), E>>;
+
+    #[inline]
+    fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&self, (req, mut framed): Self::Request) -> Self::Future {
+        let handler = self.handler.clone();
+        let codec = self.codec;
+
+        Box::pin(async move {
+            let res = match handshake(req.head()) {
+                Ok(mut res) => res.finish().drop_body(),
+                Err(e) => {
+                    let mut res = if e == HandshakeError::NoWebsocketUpgrade {
+                        Response::build(StatusCode::UPGRADE_REQUIRED)
+                            .header(header::UPGRADE, "websocket")
+                            .finish()
+                            .drop_body()
+                    } else {
+                        e.error_response().drop_body()
+                    };
+                    res.head_mut().set_connection_type(ConnectionType::Close);
+                    framed.send((res, BodySize::Empty).into()).await?;
+                    return Ok(());
+                }
+            };
+            framed.send((res, BodySize::None).into()).await?;
+
+            (*handler)(req, framed.into_framed(codec)).await
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -6,6 +6,7 @@
 //! * `rustls` - enables ssl support via `rustls` crate
 //! * `compress` - enables compression support in http and web modules
 //! * `cookie` - enables cookie support in http and web modules
+//! * `tower` - enables `compat` module with `tower` services and layers adapters
 
 #![warn(
     rust_2018_idioms,
@@ -30,6 +31,8 @@ extern crate derive_more;
 pub use ntex_rt_macros::{main, test};
 
 pub mod channel;
+#[cfg(feature = "tower")]
+pub mod compat;
 pub mod connect;
 pub mod http;
 pub mod server;
@@ -57,5 +60,47 @@ pub mod rt {
 }
 
 pub mod service {
+    //! Composable services and service factories.
+    //!
+    //! Combinators adapt services and factories to each other:
+    //!
+    //! * `apply_fn` and `apply_fn_factory` wrap service with transform function
+    //! * `map_init_err` maps init error of service factory
+    //! * `map_config` and `unit_config` adapt config of service factory
+    //! * closures that return future of a service are accepted as service
+    //!   factories via `IntoServiceFactory`
+    //!
+    //! Custom layer wired into http service:
+    //!
+    //! ```rust,no_run
+    //! use std::io;
+    //! use ntex::http::{HttpService, Request, Response};
+    //! use ntex::service::{apply_fn_factory, fn_service, map_init_err, Service};
+    //!
+    //! #[ntex::main]
+    //! async fn main() -> io::Result<()> {
+    //!     ntex::server::build()
+    //!         .bind("http", "127.0.0.1:8080", || {
+    //!             // application service factory
+    //!             let app = || async {
+    //!                 Ok::<_, ()>(fn_service(|_: Request| async {
+    //!                     Ok::<_, io::Error>(Response::Ok().finish())
+    //!                 }))
+    //!             };
+    //!
+    //!             // layer that logs request path
+    //!             let app = apply_fn_factory(app, |req: Request, srv| {
+    //!                 println!("request: {}", req.path());
+    //!                 srv.call(req)
+    //!             });
+    //!
+    //!             HttpService::build()
+    //!                 .finish(map_init_err(app, |_| "can not create app"))
+    //!                 .tcp()
+    //!         })?
+    //!         .run()
+    //!         .await
+    //! }
+    //! ```
     pub use ntex_service::*;
 }
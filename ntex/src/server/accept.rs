@@ -1,14 +1,17 @@
-use std::sync::mpsc as sync_mpsc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc as sync_mpsc, Arc};
 use std::time::Duration;
-use std::{io, thread};
+use std::{io, mem, thread};
 
 use log::{error, info};
 use slab::Slab;
+use socket2::Socket;
 
 use crate::rt::time::{delay_until, Instant};
 use crate::rt::System;
 
-use super::socket::{SocketAddr, SocketListener, StdListener};
+use super::filter::{AcceptDecision, AcceptFilter};
+use super::socket::{SocketAddr, SocketListener, StdListener, StdStream};
 use super::worker::{Conn, WorkerClient};
 use super::{Server, Token};
 
@@ -53,6 +56,7 @@ pub(super) struct AcceptLoop {
     tx: sync_mpsc::Sender<Command>,
     rx: Option<sync_mpsc::Receiver<Command>>,
     srv: Option<Server>,
+    rejected: Arc<AtomicUsize>,
 }
 
 impl AcceptLoop {
@@ -69,6 +73,7 @@ impl AcceptLoop {
             notify_reg: Some(notify_reg),
             rx: Some(rx),
             srv: Some(srv),
+            rejected: Arc::new(AtomicUsize::new(0)),
         }
     }
 
@@ -77,6 +82,11 @@ impl AcceptLoop {
         let _ = self.cmd_ready.set_readiness(mio::Ready::readable());
     }
 
+    /// Number of connections rejected by accept filter
+    pub(super) fn rejected(&self) -> usize {
+        self.rejected.load(Ordering::Relaxed)
+    }
+
     pub(super) fn get_notify(&self) -> AcceptNotify {
         AcceptNotify::new(self.notify_ready.clone())
     }
@@ -85,6 +95,9 @@ impl AcceptLoop {
         &mut self,
         socks: Vec<(Token, StdListener)>,
         workers: Vec<WorkerClient>,
+        batch: usize,
+        filter: Option<Arc<dyn AcceptFilter>>,
+        linger: Option<Duration>,
     ) {
         let srv = self.srv.take().expect("Can not re-use AcceptInfo");
 
@@ -95,6 +108,10 @@ impl AcceptLoop {
             socks,
             srv,
             workers,
+            batch,
+            filter,
+            linger,
+            self.rejected.clone(),
         );
     }
 }
@@ -108,6 +125,11 @@ struct Accept {
     timer: (mio::Registration, mio::SetReadiness),
     next: usize,
     backpressure: bool,
+    batch: usize,
+    pending: Vec<usize>,
+    filter: Option<Arc<dyn AcceptFilter>>,
+    linger: Option<Duration>,
+    rejected: Arc<AtomicUsize>,
 }
 
 const DELTA: usize = 100;
@@ -137,6 +159,10 @@ impl Accept {
         socks: Vec<(Token, StdListener)>,
         srv: Server,
         workers: Vec<WorkerClient>,
+        batch: usize,
+        filter: Option<Arc<dyn AcceptFilter>>,
+        linger: Option<Duration>,
+        rejected: Arc<AtomicUsize>,
     ) {
         let sys = System::current();
 
@@ -145,7 +171,9 @@ impl Accept {
             .name("ntex-server accept loop".to_owned())
             .spawn(move || {
                 System::set_current(sys);
-                let mut accept = Accept::new(rx, socks, workers, srv);
+                let mut accept = Accept::new(
+                    rx, socks, workers, srv, batch, filter, linger, rejected,
+                );
 
                 // Start listening for incoming commands
                 if let Err(err) = accept.poll.register(
@@ -176,6 +204,10 @@ impl Accept {
         socks: Vec<(Token, StdListener)>,
         workers: Vec<WorkerClient>,
         srv: Server,
+        batch: usize,
+        filter: Option<Arc<dyn AcceptFilter>>,
+        linger: Option<Duration>,
+        rejected: Arc<AtomicUsize>,
     ) -> Accept {
         // Create a poll instance
         let poll = match mio::Poll::new() {
@@ -227,6 +259,11 @@ impl Accept {
             next: 0,
             timer: (tm, tmr),
             backpressure: false,
+            batch,
+            pending: Vec::new(),
+            filter,
+            linger,
+            rejected,
         }
     }
 
@@ -237,7 +274,13 @@ impl Accept {
         let mut events = mio::Events::with_capacity(128);
 
         loop {
-            if let Err(err) = self.poll.poll(&mut events, None) {
+            // sockets that reached accept batch size are polled without waiting
+            let timeout = if self.pending.is_empty() {
+                None
+            } else {
+                Some(Duration::from_millis(0))
+            };
+            if let Err(err) = self.poll.poll(&mut events, timeout) {
                 panic!("Poll error: {}", err);
             }
 
@@ -260,6 +303,12 @@ impl Accept {
                     }
                 }
             }
+
+            // continue accepting connections on sockets with reached batch size,
+            // edge triggered sockets do not get new events until `WouldBlock`
+            for token in mem::take(&mut self.pending) {
+                self.accept(token);
+            }
         }
     }
 
@@ -290,6 +339,7 @@ impl Accept {
             match self.rx.try_recv() {
                 Ok(cmd) => match cmd {
                     Command::Pause => {
+                        self.pending.clear();
                         for (_, info) in self.sockets.iter_mut() {
                             if let Err(err) = self.poll.deregister(&info.sock) {
                                 error!("Can not deregister server socket {}", err);
@@ -360,6 +410,7 @@ impl Accept {
             }
         } else if on {
             self.backpressure = true;
+            self.pending.clear();
             for (_, info) in self.sockets.iter() {
                 trace!("Enabling backpressure for {}", info.addr);
                 let _ = self.poll.deregister(&info.sock);
@@ -425,7 +476,47 @@ impl Accept {
         }
     }
 
+    /// Apply accept filter, returns `None` if connection is rejected
+    fn filter(&self, msg: Conn) -> Option<Conn> {
+        if let (Some(filter), Some(SocketAddr::Tcp(addr))) = (&self.filter, &msg.peer) {
+            let decision = filter.decide(addr);
+            if decision != AcceptDecision::Accept {
+                trace!("Connection from {} is rejected", addr);
+                self.rejected.fetch_add(1, Ordering::Relaxed);
+
+                if decision == AcceptDecision::RejectWithRst {
+                    if let StdStream::Tcp(io) = msg.io {
+                        // zero linger timeout resets connection on close
+                        let _ =
+                            Socket::from(io).set_linger(Some(Duration::from_secs(0)));
+                    }
+                }
+                return None;
+            }
+        }
+        Some(msg)
+    }
+
+    /// Apply `SO_LINGER` option to accepted tcp connection
+    fn linger(&self, mut msg: Conn) -> Conn {
+        if let Some(dur) = self.linger {
+            msg.io = match msg.io {
+                StdStream::Tcp(io) => {
+                    let sock = Socket::from(io);
+                    if let Err(err) = sock.set_linger(Some(dur)) {
+                        error!("Can not set linger option: {}", err);
+                    }
+                    StdStream::Tcp(sock.into_tcp_stream())
+                }
+                #[allow(unreachable_patterns)]
+                io => io,
+            };
+        }
+        msg
+    }
+
     fn accept(&mut self, token: usize) {
+        let mut accepted = 0;
         loop {
             let msg = if let Some(info) = self.sockets.get_mut(token) {
                 match info.sock.accept() {
@@ -459,7 +550,18 @@ impl Accept {
                 return;
             };
 
-            self.accept_one(msg);
+            if let Some(msg) = self.filter(msg) {
+                let msg = self.linger(msg);
+                self.accept_one(msg);
+            }
+
+            accepted += 1;
+            if accepted == self.batch {
+                if !self.backpressure {
+                    self.pending.push(token);
+                }
+                return;
+            }
         }
     }
 }
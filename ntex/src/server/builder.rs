@@ -1,4 +1,5 @@
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::Duration;
 use std::{io, mem, net};
@@ -8,26 +9,30 @@ use futures::channel::oneshot;
 use futures::future::ready;
 use futures::stream::FuturesUnordered;
 use futures::{ready, Future, FutureExt, Stream, StreamExt};
-use log::{error, info};
+use log::{error, info, warn};
 use socket2::{Domain, SockAddr, Socket, Type};
 
 use crate::rt::net::TcpStream;
 use crate::rt::time::{delay_until, Instant};
-use crate::rt::{spawn, System};
+use crate::rt::{blocking, spawn, System};
 
 use super::accept::{AcceptLoop, AcceptNotify, Command};
 use super::config::{ConfiguredService, ServiceConfig};
+use super::filter::AcceptFilter;
 use super::service::{Factory, InternalServiceFactory, StreamServiceFactory};
 use super::signals::{Signal, Signals};
 use super::socket::StdListener;
 use super::worker::{self, Worker, WorkerAvailability, WorkerClient};
-use super::{Server, ServerCommand, Token};
+use super::{DrainStatus, Server, ServerCommand, Token};
 
 /// Server builder
 pub struct ServerBuilder {
     threads: usize,
     token: Token,
     backlog: i32,
+    accept_batch: usize,
+    accept_filter: Option<Arc<dyn AcceptFilter>>,
+    linger: Option<Duration>,
     workers: Vec<(usize, WorkerClient)>,
     services: Vec<Box<dyn InternalServiceFactory>>,
     sockets: Vec<(Token, String, StdListener)>,
@@ -38,6 +43,7 @@ pub struct ServerBuilder {
     cmd: UnboundedReceiver<ServerCommand>,
     server: Server,
     notify: Vec<oneshot::Sender<()>>,
+    draining: bool,
 }
 
 impl Default for ServerBuilder {
@@ -60,11 +66,15 @@ impl ServerBuilder {
             sockets: Vec::new(),
             accept: AcceptLoop::new(server.clone()),
             backlog: 2048,
+            accept_batch: 0,
+            accept_filter: None,
+            linger: None,
             exit: false,
             shutdown_timeout: Duration::from_secs(30),
             no_signals: false,
             cmd: rx,
             notify: Vec::new(),
+            draining: false,
             server,
         }
     }
@@ -72,9 +82,9 @@ impl ServerBuilder {
     /// Set number of workers to start.
     ///
     /// By default server uses number of available logical cpu as workers
-    /// count.
+    /// count. Zero value is not allowed, one worker is started instead.
     pub fn workers(mut self, num: usize) -> Self {
-        self.threads = num;
+        self.threads = workers_count(num);
         self
     }
 
@@ -87,12 +97,65 @@ impl ServerBuilder {
     ///
     /// Generally set in the 64-2048 range. Default value is 2048.
     ///
+    /// Operating system silently caps the value. On linux the cap is
+    /// `net.core.somaxconn` sysctl (4096 since linux 5.4, 128 before), on
+    /// macos and bsd it is `kern.ipc.somaxconn` (128 by default). Windows
+    /// uses its own limit for `SOMAXCONN`.
+    ///
     /// This method should be called before `bind()` method call.
     pub fn backlog(mut self, num: i32) -> Self {
         self.backlog = num;
         self
     }
 
+    /// Set the maximum number of connections accepted per listener wakeup.
+    ///
+    /// Accept loop accepts connections from a listener until there are no
+    /// more pending connections. Under connection storms one listener could
+    /// starve other listeners and server commands. With batch size set,
+    /// accept loop switches to other work after accepting `num` connections
+    /// and continues with the listener afterwards.
+    ///
+    /// Zero value disables the limit. By default the limit is disabled.
+    pub fn accept_batch(mut self, num: usize) -> Self {
+        self.accept_batch = num;
+        self
+    }
+
+    /// Set accept filter for incoming tcp connections.
+    ///
+    /// Filter is called on accept thread for every accepted connection
+    /// with peer address, before connection gets passed to a worker, so
+    /// rejected connections do not cost tls handshake or any other
+    /// processing. Filter must be fast and must not block, it delays
+    /// accepting connections on all listeners. Unix domain socket
+    /// connections are not filtered.
+    ///
+    /// Number of rejected connections is available via
+    /// `Server::rejected_connections()`. `IpFilter` implements cidr based
+    /// allow/deny filter.
+    pub fn on_accept<F: AcceptFilter>(mut self, filter: F) -> Self {
+        self.accept_filter = Some(Arc::new(filter));
+        self
+    }
+
+    /// Set `SO_LINGER` option of accepted tcp connections.
+    ///
+    /// Zero timeout resets connection on close, `RST` is sent instead of
+    /// regular `FIN` and server socket does not accumulate in `TIME_WAIT`
+    /// state. Any unsent data is discarded on close, including the tail of
+    /// the last response, so zero timeout is suitable only for protocols
+    /// where peer does not depend on graceful close. Non-zero timeout has
+    /// no effect on non-blocking sockets on most platforms.
+    ///
+    /// Option is applied on accept thread, unix domain socket connections
+    /// are not affected. By default `SO_LINGER` is not set and system
+    /// defaults are used.
+    pub fn linger(mut self, dur: Duration) -> Self {
+        self.linger = Some(dur);
+        self
+    }
+
     /// Sets the maximum per-worker number of concurrent connections.
     ///
     /// All socket listeners will stop accepting connections when this limit is
@@ -120,7 +183,8 @@ impl ServerBuilder {
     ///
     /// After receiving a stop signal, workers have this much time to finish
     /// serving requests. Workers still alive after the timeout are force
-    /// dropped.
+    /// dropped. The same timeout bounds waiting for in-flight blocking
+    /// operations (`rt::blocking`) after workers are stopped.
     ///
     /// By default shutdown timeout sets to 30 seconds.
     pub fn shutdown_timeout(mut self, sec: u64) -> Self {
@@ -150,7 +214,7 @@ impl ServerBuilder {
             }
             self.services.push(Box::new(srv));
         }
-        self.threads = cfg.threads;
+        self.threads = workers_count(cfg.threads);
 
         Ok(self)
     }
@@ -284,6 +348,9 @@ impl ServerBuilder {
                     .map(|t| (t.0, t.2))
                     .collect(),
                 workers,
+                self.accept_batch,
+                self.accept_filter.take(),
+                self.linger,
             );
 
             // handle signals
@@ -350,6 +417,40 @@ impl ServerBuilder {
             ServerCommand::Notify(tx) => {
                 self.notify.push(tx);
             }
+            ServerCommand::Drain { grace, completion } => {
+                if !self.draining {
+                    info!("Draining connections, stopping in {:?}", grace);
+                    self.draining = true;
+                    self.workers.iter().for_each(|worker| worker.1.drain());
+                }
+
+                let server = self.server.clone();
+                spawn(async move {
+                    delay_until(Instant::now() + grace).await;
+                    let _ = server.0.unbounded_send(ServerCommand::Stop {
+                        graceful: true,
+                        completion: Some(completion),
+                    });
+                });
+            }
+            ServerCommand::DrainStatus(tx) => {
+                let draining = self.draining;
+                let conns = self
+                    .workers
+                    .iter()
+                    .map(|worker| worker.1.connections())
+                    .collect::<FuturesUnordered<_>>();
+                spawn(conns.collect::<Vec<_>>().map(move |res| {
+                    let connections = res.into_iter().map(|num| num.unwrap_or(0)).sum();
+                    let _ = tx.send(DrainStatus {
+                        draining,
+                        connections,
+                    });
+                }));
+            }
+            ServerCommand::Rejected(tx) => {
+                let _ = tx.send(self.accept.rejected());
+            }
             ServerCommand::Stop {
                 graceful,
                 completion,
@@ -360,14 +461,16 @@ impl ServerBuilder {
                 self.accept.send(Command::Stop);
                 let notify = std::mem::take(&mut self.notify);
 
-                // stop workers
+                // stop workers, then wait for in-flight blocking operations
                 if !self.workers.is_empty() && graceful {
+                    let deadline = std::time::Instant::now() + self.shutdown_timeout;
                     spawn(
                         self.workers
                             .iter()
                             .map(move |worker| worker.1.stop(graceful))
                             .collect::<FuturesUnordered<_>>()
                             .collect::<Vec<_>>()
+                            .then(move |_| blocking::wait(deadline))
                             .then(move |_| {
                                 if let Some(tx) = completion {
                                     let _ = tx.send(());
@@ -435,6 +538,9 @@ impl ServerBuilder {
                     }
 
                     let worker = self.start_worker(new_idx, self.accept.get_notify());
+                    if self.draining {
+                        worker.drain();
+                    }
                     self.workers.push((new_idx, worker.clone()));
                     self.accept.send(Command::Worker(worker));
                 }
@@ -458,6 +564,15 @@ impl Future for ServerBuilder {
     }
 }
 
+fn workers_count(num: usize) -> usize {
+    if num == 0 {
+        warn!("Workers count is 0, starting 1 worker");
+        1
+    } else {
+        num
+    }
+}
+
 pub(super) fn bind_addr<S: net::ToSocketAddrs>(
     addr: S,
     backlog: i32,
@@ -557,4 +672,10 @@ mod tests {
         let addrs: Vec<net::SocketAddr> = Vec::new();
         assert!(bind_addr(&addrs[..], 10).is_err());
     }
+
+    #[test]
+    fn test_workers_count() {
+        assert_eq!(workers_count(0), 1);
+        assert_eq!(workers_count(4), 4);
+    }
 }
@@ -0,0 +1,110 @@
+//! Worker connections draining
+use std::cell::{Cell, RefCell};
+use std::task::Waker;
+
+use slab::Slab;
+
+thread_local! {
+    static DRAIN: DrainState = DrainState {
+        draining: Cell::new(false),
+        tasks: RefCell::new(Slab::new()),
+    };
+}
+
+struct DrainState {
+    draining: Cell<bool>,
+    tasks: RefCell<Slab<Option<Waker>>>,
+}
+
+/// Start draining connections of current worker, wakes up every
+/// registered connection task
+pub(super) fn start() {
+    DRAIN.with(|st| {
+        if !st.draining.replace(true) {
+            let wakers: Vec<_> = st
+                .tasks
+                .borrow_mut()
+                .iter_mut()
+                .filter_map(|(_, waker)| waker.take())
+                .collect();
+            wakers.into_iter().for_each(|waker| waker.wake());
+        }
+    })
+}
+
+/// Check if current worker drains connections
+pub(crate) fn is_draining() -> bool {
+    DRAIN.with(|st| st.draining.get())
+}
+
+/// Connection task registration for worker drain notification
+///
+/// Registration is removed on drop.
+pub(crate) struct DrainWatch(usize);
+
+impl DrainWatch {
+    pub(crate) fn new() -> Self {
+        DrainWatch(DRAIN.with(|st| st.tasks.borrow_mut().insert(None)))
+    }
+
+    /// Check if worker drains connections
+    pub(crate) fn is_draining(&self) -> bool {
+        is_draining()
+    }
+
+    /// Register connection task, it gets woken up when drain starts
+    pub(crate) fn register(&self, waker: &Waker) {
+        DRAIN.with(|st| {
+            if let Some(slot) = st.tasks.borrow_mut().get_mut(self.0) {
+                match slot {
+                    Some(w) if w.will_wake(waker) => (),
+                    _ => *slot = Some(waker.clone()),
+                }
+            }
+        })
+    }
+}
+
+impl Drop for DrainWatch {
+    fn drop(&mut self) {
+        let _ = DRAIN.try_with(|st| st.tasks.borrow_mut().remove(self.0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    use futures::task::{waker, ArcWake};
+
+    use super::*;
+
+    struct Flag(AtomicBool);
+
+    impl ArcWake for Flag {
+        fn wake_by_ref(arc_self: &Arc<Self>) {
+            arc_self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_drain() {
+        // every test runs in its own thread
+        let flag = Arc::new(Flag(AtomicBool::new(false)));
+        let waker = waker(flag.clone());
+
+        let watch = DrainWatch::new();
+        let dropped = DrainWatch::new();
+        watch.register(&waker);
+        dropped.register(&waker);
+        drop(dropped);
+        assert!(!watch.is_draining());
+        assert!(!is_draining());
+
+        start();
+        assert!(watch.is_draining());
+        assert!(flag.0.load(Ordering::SeqCst));
+        assert_eq!(DRAIN.with(|st| st.tasks.borrow().len()), 1);
+    }
+}
@@ -0,0 +1,274 @@
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::{error, fmt, str::FromStr};
+
+/// Decision for newly accepted connection
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AcceptDecision {
+    /// Pass connection to a worker
+    Accept,
+    /// Close connection immediately
+    Reject,
+    /// Close connection immediately and send `RST` to the peer
+    RejectWithRst,
+}
+
+/// Accept loop connection filter
+///
+/// Filter is called on accept thread for every accepted tcp connection,
+/// before connection gets passed to a worker. Filter must not block,
+/// any delay in filter delays all server listeners.
+///
+/// Filter is implemented for `Fn(&SocketAddr) -> AcceptDecision` closures.
+pub trait AcceptFilter: Send + Sync + 'static {
+    /// Decide what to do with connection from `peer`
+    fn decide(&self, peer: &SocketAddr) -> AcceptDecision;
+}
+
+impl<F> AcceptFilter for F
+where
+    F: Fn(&SocketAddr) -> AcceptDecision + Send + Sync + 'static,
+{
+    fn decide(&self, peer: &SocketAddr) -> AcceptDecision {
+        (self)(peer)
+    }
+}
+
+/// Ip network in CIDR notation, i.e. `10.0.0.0/8` or `fe80::/10`
+///
+/// Address without prefix length matches single address.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct IpNet {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+/// Error returned by `IpNet` parser
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct IpNetParseError;
+
+impl fmt::Display for IpNetParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid ip network")
+    }
+}
+
+impl error::Error for IpNetParseError {}
+
+impl IpNet {
+    /// Create ip network from address and prefix length
+    pub fn new(addr: IpAddr, prefix: u8) -> Result<Self, IpNetParseError> {
+        let max = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        if prefix > max {
+            Err(IpNetParseError)
+        } else {
+            Ok(IpNet { addr, prefix })
+        }
+    }
+
+    /// Check if network contains address
+    pub fn contains(&self, addr: &IpAddr) -> bool {
+        match (self.addr, normalize(addr)) {
+            (IpAddr::V4(net), IpAddr::V4(addr)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u32::from(net) & mask == u32::from(addr) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(addr)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u128::from(net) & mask == u128::from(addr) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpNet {
+    type Err = IpNetParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, '/');
+        let addr = parts
+            .next()
+            .and_then(|addr| addr.parse::<IpAddr>().ok())
+            .ok_or(IpNetParseError)?;
+        let prefix = match parts.next() {
+            Some(prefix) => prefix.parse::<u8>().map_err(|_| IpNetParseError)?,
+            None => match addr {
+                IpAddr::V4(_) => 32,
+                IpAddr::V6(_) => 128,
+            },
+        };
+        IpNet::new(addr, prefix)
+    }
+}
+
+/// Ipv4-mapped ipv6 addresses are matched as ipv4 addresses
+fn normalize(addr: &IpAddr) -> IpAddr {
+    if let IpAddr::V6(addr) = addr {
+        if let [0, 0, 0, 0, 0, 0xffff, hi, lo] = addr.segments() {
+            return IpAddr::V4(Ipv4Addr::from((u32::from(hi) << 16) | u32::from(lo)));
+        }
+    }
+    *addr
+}
+
+/// Cidr based allow/deny accept filter
+///
+/// Connections from denied networks are rejected. If allow list is not
+/// empty, connections from networks that are not allowed are rejected as
+/// well. Deny list takes precedence over allow list.
+///
+/// ```rust
+/// use ntex::server::{AcceptDecision, IpFilter};
+///
+/// let filter = IpFilter::new()
+///     .allow("10.0.0.0/8".parse().unwrap())
+///     .deny("10.0.1.0/24".parse().unwrap());
+///
+/// assert_eq!(
+///     filter.check(&"10.0.0.1".parse().unwrap()),
+///     AcceptDecision::Accept
+/// );
+/// assert_eq!(
+///     filter.check(&"10.0.1.1".parse().unwrap()),
+///     AcceptDecision::Reject
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct IpFilter {
+    allow: Vec<IpNet>,
+    deny: Vec<IpNet>,
+    reject: AcceptDecision,
+}
+
+impl Default for IpFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IpFilter {
+    /// Create filter that accepts all connections
+    pub fn new() -> Self {
+        IpFilter {
+            allow: Vec::new(),
+            deny: Vec::new(),
+            reject: AcceptDecision::Reject,
+        }
+    }
+
+    /// Allow connections from network
+    pub fn allow(mut self, net: IpNet) -> Self {
+        self.allow.push(net);
+        self
+    }
+
+    /// Deny connections from network
+    pub fn deny(mut self, net: IpNet) -> Self {
+        self.deny.push(net);
+        self
+    }
+
+    /// Reset rejected connections with `RST` instead of regular close.
+    ///
+    /// By default rejected connections are closed.
+    pub fn reset(mut self, rst: bool) -> Self {
+        self.reject = if rst {
+            AcceptDecision::RejectWithRst
+        } else {
+            AcceptDecision::Reject
+        };
+        self
+    }
+
+    /// Check ip address
+    pub fn check(&self, addr: &IpAddr) -> AcceptDecision {
+        if self.deny.iter().any(|net| net.contains(addr))
+            || (!self.allow.is_empty()
+                && !self.allow.iter().any(|net| net.contains(addr)))
+        {
+            self.reject
+        } else {
+            AcceptDecision::Accept
+        }
+    }
+}
+
+impl AcceptFilter for IpFilter {
+    fn decide(&self, peer: &SocketAddr) -> AcceptDecision {
+        self.check(&peer.ip())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ip_net() {
+        let net: IpNet = "192.168.0.0/16".parse().unwrap();
+        assert!(net.contains(&"192.168.10.1".parse().unwrap()));
+        assert!(!net.contains(&"192.169.0.1".parse().unwrap()));
+        assert!(net.contains(&"::ffff:192.168.0.1".parse().unwrap()));
+        assert!(!net.contains(&"::1".parse().unwrap()));
+
+        let net: IpNet = "127.0.0.1".parse().unwrap();
+        assert!(net.contains(&"127.0.0.1".parse().unwrap()));
+        assert!(!net.contains(&"127.0.0.2".parse().unwrap()));
+
+        let net: IpNet = "0.0.0.0/0".parse().unwrap();
+        assert!(net.contains(&"1.2.3.4".parse().unwrap()));
+
+        let net: IpNet = "fe80::/10".parse().unwrap();
+        assert!(net.contains(&"fe80::1".parse().unwrap()));
+        assert!(!net.contains(&"fec0::1".parse().unwrap()));
+
+        assert!("10.0.0.0/33".parse::<IpNet>().is_err());
+        assert!("::/129".parse::<IpNet>().is_err());
+        assert!("10.0.0/8".parse::<IpNet>().is_err());
+        assert!("10.0.0.0/".parse::<IpNet>().is_err());
+    }
+
+    #[test]
+    fn test_ip_filter() {
+        let filter = IpFilter::new();
+        assert_eq!(
+            filter.check(&"1.2.3.4".parse().unwrap()),
+            AcceptDecision::Accept
+        );
+
+        let filter = IpFilter::new()
+            .deny("10.0.0.0/8".parse().unwrap())
+            .reset(true);
+        assert_eq!(
+            filter.decide(&"10.1.1.1:8080".parse().unwrap()),
+            AcceptDecision::RejectWithRst
+        );
+        assert_eq!(
+            filter.decide(&"11.1.1.1:8080".parse().unwrap()),
+            AcceptDecision::Accept
+        );
+
+        let filter = IpFilter::new()
+            .allow("127.0.0.1".parse().unwrap())
+            .allow("::1".parse().unwrap());
+        assert_eq!(
+            filter.check(&"127.0.0.1".parse().unwrap()),
+            AcceptDecision::Accept
+        );
+        assert_eq!(
+            filter.check(&"::1".parse().unwrap()),
+            AcceptDecision::Accept
+        );
+        assert_eq!(
+            filter.check(&"127.0.0.2".parse().unwrap()),
+            AcceptDecision::Reject
+        );
+    }
+}
@@ -6,6 +6,7 @@ use std::io;
 use std::pin::Pin;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::task::{Context, Poll};
+use std::time::Duration;
 
 use futures::channel::mpsc::UnboundedSender;
 use futures::channel::oneshot;
@@ -16,6 +17,8 @@ use crate::util::counter::Counter;
 mod accept;
 mod builder;
 mod config;
+mod drain;
+mod filter;
 mod service;
 mod signals;
 mod socket;
@@ -31,6 +34,8 @@ pub mod rustls;
 pub(crate) use self::builder::create_tcp_listener;
 pub use self::builder::ServerBuilder;
 pub use self::config::{ServiceConfig, ServiceRuntime};
+pub(crate) use self::drain::DrainWatch;
+pub use self::filter::{AcceptDecision, AcceptFilter, IpFilter, IpNet, IpNetParseError};
 pub use self::service::StreamServiceFactory;
 pub use self::test::{build_test_server, test_server, TestServer};
 
@@ -92,6 +97,34 @@ enum ServerCommand {
     },
     /// Notify of server stop
     Notify(oneshot::Sender<()>),
+    /// Drain connections and stop after grace period
+    Drain {
+        grace: Duration,
+        completion: oneshot::Sender<()>,
+    },
+    /// Report drain status
+    DrainStatus(oneshot::Sender<DrainStatus>),
+    /// Report number of rejected connections
+    Rejected(oneshot::Sender<usize>),
+}
+
+/// Status of server connections draining
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DrainStatus {
+    draining: bool,
+    connections: usize,
+}
+
+impl DrainStatus {
+    /// Check if server drains connections
+    pub fn is_draining(&self) -> bool {
+        self.draining
+    }
+
+    /// Number of alive connections across all workers
+    pub fn connections(&self) -> usize {
+        self.connections
+    }
 }
 
 /// Server controller
@@ -147,6 +180,47 @@ impl Server {
         });
         rx.map(|_| ())
     }
+
+    /// Drain connections and gracefully stop server after grace period.
+    ///
+    /// Workers keep accepting and serving requests during grace period, but
+    /// every connection is asked to close. Http/1 connections send
+    /// subsequent responses with `Connection: close` header and idle
+    /// connections get closed, http/2 connections send `GOAWAY` frame and
+    /// get closed after current streams complete. After grace period server
+    /// stops the same way as `Server::stop(true)`.
+    ///
+    /// Returned future resolves when server is stopped. Drain progress
+    /// could be checked with `Server::drain_status()`.
+    pub fn drain(&self, grace: Duration) -> impl Future<Output = ()> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.0.unbounded_send(ServerCommand::Drain {
+            grace,
+            completion: tx,
+        });
+        rx.map(|_| ())
+    }
+
+    /// Get status of connections draining
+    pub fn drain_status(&self) -> impl Future<Output = DrainStatus> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.0.unbounded_send(ServerCommand::DrainStatus(tx));
+        rx.map(|res| {
+            res.unwrap_or(DrainStatus {
+                draining: false,
+                connections: 0,
+            })
+        })
+    }
+
+    /// Get number of connections rejected by accept filter
+    ///
+    /// See `ServerBuilder::on_accept()`.
+    pub fn rejected_connections(&self) -> impl Future<Output = usize> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.0.unbounded_send(ServerCommand::Rejected(tx));
+        rx.map(|res| res.unwrap_or(0))
+    }
 }
 
 impl Clone for Server {
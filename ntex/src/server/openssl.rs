@@ -39,6 +39,11 @@ impl<T: AsyncRead + AsyncWrite> Acceptor<T> {
 
     /// Set handshake timeout in milliseconds
     ///
+    /// Timeout starts when connection is accepted and covers whole
+    /// handshake, including client certificate verification callbacks.
+    /// Handshake that completes while timer fires is not rejected.
+    /// To disable timeout set value to 0.
+    ///
     /// Default is set to 5 seconds.
     pub fn timeout(mut self, time: u64) -> Self {
         self.timeout = Duration::from_millis(time);
@@ -140,19 +145,87 @@ impl<T: AsyncRead + AsyncWrite + Unpin> Future for AcceptorServiceResponse<T> {
     type Output = Result<SslStream<T>, Box<dyn Error>>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // handshake result takes precedence over expired timer
+        if let Poll::Ready(res) = Pin::new(&mut self.fut).poll(cx) {
+            return Poll::Ready(res);
+        }
+
         if let Some(ref mut delay) = self.delay {
-            match Pin::new(delay).poll(cx) {
-                Poll::Pending => (),
-                Poll::Ready(_) => {
-                    return Poll::Ready(Err(Box::new(io::Error::new(
-                        io::ErrorKind::TimedOut,
-                        "ssl handshake timeout",
-                    ))))
-                }
+            if Pin::new(delay).poll(cx).is_ready() {
+                return Poll::Ready(Err(Box::new(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "ssl handshake timeout",
+                ))));
             }
         }
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use futures::future::poll_fn;
+    use open_ssl::ssl::{
+        SslConnector, SslFiletype, SslMethod, SslVerifyMode, SslVersion,
+    };
+
+    use super::*;
+    use crate::rt::net::{TcpListener, TcpStream};
+    use crate::rt::time::{delay_until, Instant};
+
+    #[ntex_rt::test]
+    async fn test_handshake_completed_on_timeout() {
+        let mut builder = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+        builder
+            .set_private_key_file("./tests/key.pem", SslFiletype::PEM)
+            .unwrap();
+        builder
+            .set_certificate_chain_file("./tests/cert.pem")
+            .unwrap();
+        // with tls 1.3 server reads last handshake message
+        builder
+            .set_min_proto_version(Some(SslVersion::TLS1_3))
+            .unwrap();
+        let srv = Acceptor::new(builder.build())
+            .timeout(200)
+            .new_service(())
+            .await
+            .unwrap();
+
+        let mut lst =
+            TcpListener::bind("127.0.0.1:0".parse::<std::net::SocketAddr>().unwrap())
+                .await
+                .unwrap();
+        let addr = lst.local_addr().unwrap();
+        let done = Rc::new(Cell::new(false));
+        let done2 = done.clone();
+        crate::rt::spawn(async move {
+            let mut builder = SslConnector::builder(SslMethod::tls()).unwrap();
+            builder.set_verify(SslVerifyMode::NONE);
+            let config = builder.build().configure().unwrap();
+            let io = TcpStream::connect(addr).await.unwrap();
+            let _io = tokio_openssl::connect(config, "localhost", io)
+                .await
+                .unwrap();
+            done2.set(true);
+            delay_for(Duration::from_secs(1)).await;
+        });
+
+        let (io, _) = lst.accept().await.unwrap();
+        let start = Instant::now();
+        let mut fut = srv.call(io);
+
+        // drive handshake until peer sends its last message
+        while !done.get() {
+            let res = poll_fn(|cx| Poll::Ready(Pin::new(&mut fut).poll(cx))).await;
+            assert!(res.is_pending());
+            delay_for(Duration::from_millis(5)).await;
+        }
 
-        let io = futures::ready!(Pin::new(&mut self.fut).poll(cx))?;
-        Poll::Ready(Ok(io))
+        // handshake completes during the same poll when timer expires
+        delay_until(start + Duration::from_millis(250)).await;
+        assert!(fut.await.is_ok());
     }
 }
@@ -42,6 +42,11 @@ impl<T: AsyncRead + AsyncWrite> Acceptor<T> {
 
     /// Set handshake timeout in milliseconds
     ///
+    /// Timeout starts when connection is accepted and covers whole
+    /// handshake, including client certificate verification callbacks.
+    /// Handshake that completes while timer fires is not rejected.
+    /// To disable timeout set value to 0.
+    ///
     /// Default is set to 5 seconds.
     pub fn timeout(mut self, time: u64) -> Self {
         self.timeout = Duration::from_millis(time);
@@ -133,22 +138,85 @@ impl<T: AsyncRead + AsyncWrite + Unpin> Future for AcceptorServiceFut<T> {
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let this = self.get_mut();
 
+        // handshake result takes precedence over expired timer
+        if let Poll::Ready(res) = Pin::new(&mut this.fut).poll(cx) {
+            return match res {
+                Ok(io) => Poll::Ready(Ok(io)),
+                Err(e) => Poll::Ready(Err(Box::new(e))),
+            };
+        }
+
         if let Some(ref mut delay) = this.delay {
-            match Pin::new(delay).poll(cx) {
-                Poll::Pending => (),
-                Poll::Ready(_) => {
-                    return Poll::Ready(Err(Box::new(io::Error::new(
-                        io::ErrorKind::TimedOut,
-                        "ssl handshake timeout",
-                    ))))
-                }
+            if Pin::new(delay).poll(cx).is_ready() {
+                return Poll::Ready(Err(Box::new(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "ssl handshake timeout",
+                ))));
             }
         }
+        Poll::Pending
+    }
+}
 
-        let res = futures::ready!(Pin::new(&mut this.fut).poll(cx));
-        match res {
-            Ok(io) => Poll::Ready(Ok(io)),
-            Err(e) => Poll::Ready(Err(Box::new(e))),
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::{fs::File, io::BufReader};
+
+    use futures::future::poll_fn;
+    use open_ssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
+    use rust_tls::internal::pemfile::{certs, pkcs8_private_keys};
+    use rust_tls::{NoClientAuth, ProtocolVersion};
+
+    use super::*;
+    use crate::rt::net::TcpListener;
+    use crate::rt::time::{delay_until, Instant};
+
+    #[ntex_rt::test]
+    async fn test_handshake_completed_on_timeout() {
+        let mut config = ServerConfig::new(NoClientAuth::new());
+        let cert_file = &mut BufReader::new(File::open("tests/cert.pem").unwrap());
+        let key_file = &mut BufReader::new(File::open("tests/key.pem").unwrap());
+        let cert_chain = certs(cert_file).unwrap();
+        let mut keys = pkcs8_private_keys(key_file).unwrap();
+        config.set_single_cert(cert_chain, keys.remove(0)).unwrap();
+        // with tls 1.3 server reads last handshake message
+        config.versions = vec![ProtocolVersion::TLSv1_3];
+        let srv = Acceptor::new(config)
+            .timeout(200)
+            .new_service(())
+            .await
+            .unwrap();
+
+        let mut lst =
+            TcpListener::bind("127.0.0.1:0".parse::<std::net::SocketAddr>().unwrap())
+                .await
+                .unwrap();
+        let addr = lst.local_addr().unwrap();
+        let done = Arc::new(AtomicBool::new(false));
+        let done2 = done.clone();
+        let _ = std::thread::spawn(move || {
+            let mut builder = SslConnector::builder(SslMethod::tls()).unwrap();
+            builder.set_verify(SslVerifyMode::NONE);
+            let io = std::net::TcpStream::connect(addr).unwrap();
+            let _io = builder.build().connect("localhost", io).unwrap();
+            done2.store(true, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_secs(1));
+        });
+
+        let (io, _) = lst.accept().await.unwrap();
+        let start = Instant::now();
+        let mut fut = srv.call(io);
+
+        // drive handshake until peer sends its last message
+        while !done.load(Ordering::SeqCst) {
+            let res = poll_fn(|cx| Poll::Ready(Pin::new(&mut fut).poll(cx))).await;
+            assert!(res.is_pending());
+            delay_for(Duration::from_millis(5)).await;
         }
+
+        // handshake completes during the same poll when timer expires
+        delay_until(start + Duration::from_millis(250)).await;
+        assert!(fut.await.is_ok());
     }
 }
@@ -14,6 +14,7 @@ use crate::rt::{spawn, Arbiter};
 use crate::util::counter::Counter;
 
 use super::accept::AcceptNotify;
+use super::drain;
 use super::service::{BoxedServerService, InternalServiceFactory, ServerMessage};
 use super::socket::{SocketAddr, StdStream};
 use super::Token;
@@ -27,6 +28,16 @@ pub(super) struct StopCommand {
     result: oneshot::Sender<bool>,
 }
 
+/// Worker control message
+pub(super) enum WorkerControl {
+    /// Stop worker
+    Stop(StopCommand),
+    /// Start draining connections
+    Drain,
+    /// Report number of alive connections
+    Connections(oneshot::Sender<usize>),
+}
+
 #[derive(Debug)]
 pub(super) struct Conn {
     pub(super) io: StdStream,
@@ -59,7 +70,7 @@ thread_local! {
 pub(super) struct WorkerClient {
     pub(super) idx: usize,
     tx1: UnboundedSender<WorkerCommand>,
-    tx2: UnboundedSender<StopCommand>,
+    tx2: UnboundedSender<WorkerControl>,
     avail: WorkerAvailability,
 }
 
@@ -67,7 +78,7 @@ impl WorkerClient {
     pub(super) fn new(
         idx: usize,
         tx1: UnboundedSender<WorkerCommand>,
-        tx2: UnboundedSender<StopCommand>,
+        tx2: UnboundedSender<WorkerControl>,
         avail: WorkerAvailability,
     ) -> Self {
         WorkerClient {
@@ -90,7 +101,19 @@ impl WorkerClient {
 
     pub(super) fn stop(&self, graceful: bool) -> oneshot::Receiver<bool> {
         let (result, rx) = oneshot::channel();
-        let _ = self.tx2.unbounded_send(StopCommand { graceful, result });
+        let _ = self
+            .tx2
+            .unbounded_send(WorkerControl::Stop(StopCommand { graceful, result }));
+        rx
+    }
+
+    pub(super) fn drain(&self) {
+        let _ = self.tx2.unbounded_send(WorkerControl::Drain);
+    }
+
+    pub(super) fn connections(&self) -> oneshot::Receiver<usize> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.tx2.unbounded_send(WorkerControl::Connections(tx));
         rx
     }
 }
@@ -127,7 +150,7 @@ impl WorkerAvailability {
 /// processing.
 pub(super) struct Worker {
     rx: UnboundedReceiver<WorkerCommand>,
-    rx2: UnboundedReceiver<StopCommand>,
+    rx2: UnboundedReceiver<WorkerControl>,
     services: Vec<WorkerService>,
     availability: WorkerAvailability,
     conns: Counter,
@@ -191,7 +214,7 @@ impl Worker {
 
     async fn create(
         rx: UnboundedReceiver<WorkerCommand>,
-        rx2: UnboundedReceiver<StopCommand>,
+        rx2: UnboundedReceiver<WorkerControl>,
         factories: Vec<Box<dyn InternalServiceFactory>>,
         availability: WorkerAvailability,
         shutdown_timeout: time::Duration,
@@ -330,10 +353,26 @@ impl Future for Worker {
     type Output = ();
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // worker control messages
+        let mut stop = None;
+        while let Poll::Ready(Some(msg)) = Pin::new(&mut self.rx2).poll_next(cx) {
+            match msg {
+                WorkerControl::Drain => {
+                    info!("Draining worker, {} connections", num_connections());
+                    drain::start();
+                }
+                WorkerControl::Connections(tx) => {
+                    let _ = tx.send(num_connections());
+                }
+                WorkerControl::Stop(cmd) => {
+                    stop = Some(cmd);
+                    break;
+                }
+            }
+        }
+
         // `StopWorker` message handler
-        if let Poll::Ready(Some(StopCommand { graceful, result })) =
-            Pin::new(&mut self.rx2).poll_next(cx)
-        {
+        if let Some(StopCommand { graceful, result }) = stop {
             self.availability.set(false);
             let num = num_connections();
             if num == 0 {
@@ -639,10 +678,10 @@ mod tests {
         let g = MAX_CONNS_COUNTER.with(|conns| conns.get());
 
         let (tx, rx) = oneshot::channel();
-        tx2.send(StopCommand {
+        tx2.send(WorkerControl::Stop(StopCommand {
             graceful: true,
             result: tx,
-        })
+        }))
         .await
         .unwrap();
 
@@ -684,10 +723,10 @@ mod tests {
         assert!(avail.available());
 
         let (tx, rx) = oneshot::channel();
-        tx2.send(StopCommand {
+        tx2.send(WorkerControl::Stop(StopCommand {
             graceful: false,
             result: tx,
-        })
+        }))
         .await
         .unwrap();
 
@@ -1,12 +1,24 @@
-use std::any::{Any, TypeId};
+use std::any::{type_name, Any, TypeId};
 use std::fmt;
 
 use fxhash::FxHashMap;
 
+/// Extensions with more entries are released on reuse
+const MAX_REUSE_CAPACITY: usize = 32;
+
 #[derive(Default)]
 /// A type map of request extensions.
+///
+/// Extensions are the side channel between middlewares and handlers.
+/// Every type could be stored only once, inserting value of the same
+/// type replaces previous value.
 pub struct Extensions {
-    map: FxHashMap<TypeId, Box<dyn Any>>,
+    map: FxHashMap<TypeId, Entry>,
+}
+
+struct Entry {
+    value: Box<dyn Any>,
+    name: &'static str,
 }
 
 impl Extensions {
@@ -21,9 +33,9 @@ impl Extensions {
     /// Insert a type into this `Extensions`.
     ///
     /// If a extension of this type already existed, it will
-    /// be returned.
+    /// be replaced.
     pub fn insert<T: 'static>(&mut self, val: T) {
-        self.map.insert(TypeId::of::<T>(), Box::new(val));
+        self.map.insert(TypeId::of::<T>(), Entry::new(val));
     }
 
     /// Check if container contains entry
@@ -35,14 +47,28 @@ impl Extensions {
     pub fn get<T: 'static>(&self) -> Option<&T> {
         self.map
             .get(&TypeId::of::<T>())
-            .and_then(|boxed| boxed.downcast_ref())
+            .and_then(|entry| entry.value.downcast_ref())
     }
 
     /// Get a mutable reference to a type previously inserted on this `Extensions`.
     pub fn get_mut<T: 'static>(&mut self) -> Option<&mut T> {
         self.map
             .get_mut(&TypeId::of::<T>())
-            .and_then(|boxed| boxed.downcast_mut())
+            .and_then(|entry| entry.value.downcast_mut())
+    }
+
+    /// Get a mutable reference to a type, inserts value returned
+    /// by `f` if `Extensions` does not contain this type.
+    pub fn get_or_insert_with<T: 'static, F>(&mut self, f: F) -> &mut T
+    where
+        F: FnOnce() -> T,
+    {
+        self.map
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Entry::new(f()))
+            .value
+            .downcast_mut()
+            .unwrap()
     }
 
     /// Remove a type from this `Extensions`.
@@ -51,7 +77,26 @@ impl Extensions {
     pub fn remove<T: 'static>(&mut self) -> Option<T> {
         self.map
             .remove(&TypeId::of::<T>())
-            .and_then(|boxed| boxed.downcast().ok().map(|boxed| *boxed))
+            .and_then(|entry| entry.value.downcast().ok().map(|boxed| *boxed))
+    }
+
+    /// Iterate over type names of inserted extensions.
+    ///
+    /// Names are intended for debugging, order is not specified.
+    pub fn type_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.map.values().map(|entry| entry.name)
+    }
+
+    /// Number of inserted extensions
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Check if `Extensions` is empty
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
     }
 
     /// Clear the `Extensions` of all inserted extensions.
@@ -59,11 +104,33 @@ impl Extensions {
     pub fn clear(&mut self) {
         self.map.clear();
     }
+
+    /// Clear extensions of pooled message before it gets reused.
+    ///
+    /// Empty container is not touched, oversized container
+    /// releases its memory.
+    #[inline]
+    pub(crate) fn clear_on_reuse(&mut self) {
+        if self.map.capacity() > MAX_REUSE_CAPACITY {
+            self.map = FxHashMap::default();
+        } else if !self.map.is_empty() {
+            self.map.clear();
+        }
+    }
+}
+
+impl Entry {
+    fn new<T: 'static>(val: T) -> Self {
+        Entry {
+            value: Box::new(val),
+            name: type_name::<T>(),
+        }
+    }
 }
 
 impl fmt::Debug for Extensions {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("Extensions").finish()
+        f.debug_set().entries(self.type_names()).finish()
     }
 }
 
@@ -172,3 +239,45 @@ fn test_extensions() {
     assert_eq!(extensions.get::<bool>(), None);
     assert_eq!(extensions.get(), Some(&MyType(10)));
 }
+
+#[test]
+fn test_get_or_insert_with() {
+    let mut map = Extensions::new();
+
+    *map.get_or_insert_with(|| 1u32) += 1;
+    assert_eq!(map.get::<u32>(), Some(&2));
+
+    // existing value is not replaced
+    *map.get_or_insert_with(|| 10u32) += 1;
+    assert_eq!(map.get::<u32>(), Some(&3));
+    assert_eq!(map.len(), 1);
+}
+
+#[test]
+fn test_type_names() {
+    let mut map = Extensions::new();
+    assert!(map.is_empty());
+    assert_eq!(format!("{:?}", map), "{}");
+
+    map.insert(1u8);
+    map.insert(String::new());
+    let mut names: Vec<_> = map.type_names().collect();
+    names.sort_unstable();
+    assert_eq!(names, vec!["alloc::string::String", "u8"]);
+    assert!(format!("{:?}", map).contains("\"u8\""));
+}
+
+#[test]
+fn test_clear_on_reuse() {
+    let mut map = Extensions::new();
+    map.insert(1u8);
+    map.clear_on_reuse();
+    assert!(map.is_empty());
+
+    // oversized map is released
+    map.map = FxHashMap::with_capacity_and_hasher(64, Default::default());
+    map.insert(1u8);
+    map.clear_on_reuse();
+    assert!(map.is_empty());
+    assert!(map.map.capacity() <= MAX_REUSE_CAPACITY);
+}
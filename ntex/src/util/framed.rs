@@ -1,7 +1,9 @@
 //! Framed transport dispatcher
+use std::cell::Cell;
 use std::fmt;
 use std::future::Future;
 use std::pin::Pin;
+use std::rc::Rc;
 use std::task::{Context, Poll};
 use std::time::Duration;
 
@@ -10,7 +12,7 @@ use log::debug;
 
 use crate::channel::mpsc;
 use crate::codec::{AsyncRead, AsyncWrite, Decoder, Encoder, Framed};
-use crate::rt::time::{delay_for, Delay};
+use crate::rt::time::{delay_for, Delay, Instant};
 use crate::service::{IntoService, Service};
 
 type Request<U> = <U as Decoder>::Item;
@@ -24,6 +26,8 @@ pub enum DispatcherError<E, U: Encoder + Decoder> {
     Encoder(<U as Encoder>::Error),
     /// Decoder parse error
     Decoder(<U as Decoder>::Error),
+    /// Keep-alive timeout expired
+    KeepAlive,
 }
 
 impl<E, U: Encoder + Decoder> From<E> for DispatcherError<E, U> {
@@ -49,6 +53,7 @@ where
             DispatcherError::Decoder(ref e) => {
                 write!(fmt, "DispatcherError::Decoder({:?})", e)
             }
+            DispatcherError::KeepAlive => write!(fmt, "DispatcherError::KeepAlive"),
         }
     }
 }
@@ -64,6 +69,7 @@ where
             DispatcherError::Service(ref e) => write!(fmt, "{}", e),
             DispatcherError::Encoder(ref e) => write!(fmt, "{:?}", e),
             DispatcherError::Decoder(ref e) => write!(fmt, "{:?}", e),
+            DispatcherError::KeepAlive => write!(fmt, "Keep-alive timeout expired"),
         }
     }
 }
@@ -105,6 +111,9 @@ where
                 service: service.into_service(),
                 state: FramedState::Processing,
                 disconnect_timeout: 1000,
+                keepalive_timeout: 0,
+                ka_timer: None,
+                inflight: Rc::new(Cell::new(0)),
             },
         }
     }
@@ -135,6 +144,9 @@ where
                 service: service.into_service(),
                 state: FramedState::Processing,
                 disconnect_timeout: 1000,
+                keepalive_timeout: 0,
+                ka_timer: None,
+                inflight: Rc::new(Cell::new(0)),
             },
         }
     }
@@ -151,6 +163,20 @@ where
         self.inner.disconnect_timeout = val;
         self
     }
+
+    /// Set keep-alive timeout in milliseconds.
+    ///
+    /// If no frames get received from the peer within this time and there
+    /// are no in-flight service calls, the dispatcher flushes the write
+    /// buffer, shutdowns the service and resolves with
+    /// `DispatcherError::KeepAlive` error. Timer starts on first poll of
+    /// the dispatcher and is suspended while the write buffer is not empty.
+    ///
+    /// By default keep-alive timeout is disabled.
+    pub fn keepalive_timeout(mut self, val: u64) -> Self {
+        self.inner.keepalive_timeout = val;
+        self
+    }
 }
 
 impl<S, T, U, In> Future for Dispatcher<S, T, U, In>
@@ -182,6 +208,8 @@ enum FramedState<S: Service, U: Encoder + Decoder> {
 enum PollResult {
     Continue,
     Pending,
+    /// Reading is paused because write buffer is full
+    Paused,
 }
 
 struct InnerDispatcher<S, T, U, Out>
@@ -201,6 +229,9 @@ where
     framed: Framed<T, U>,
     rx: mpsc::Receiver<Result<<U as Encoder>::Item, S::Error>>,
     disconnect_timeout: u64,
+    keepalive_timeout: u64,
+    ka_timer: Option<Delay>,
+    inflight: Rc<Cell<usize>>,
 }
 
 impl<S, T, U, Out> InnerDispatcher<S, T, U, Out>
@@ -216,6 +247,13 @@ where
 {
     fn poll_read(&mut self, cx: &mut Context<'_>) -> PollResult {
         loop {
+            // write backpressure, do not accept new frames until
+            // peer reads pending data
+            if self.framed.is_write_buf_full() {
+                log::trace!("Write buffer is full, pause reading");
+                return PollResult::Paused;
+            }
+
             match self.service.poll_ready(cx) {
                 Poll::Ready(Ok(_)) => {
                     let item = match self.framed.next_item(cx) {
@@ -235,8 +273,19 @@ where
                         }
                     };
 
+                    // reset keep-alive timer
+                    if let Some(ref mut timer) = self.ka_timer {
+                        timer.reset(
+                            Instant::now()
+                                + Duration::from_millis(self.keepalive_timeout),
+                        );
+                    }
+
                     let tx = self.rx.sender();
+                    let inflight = self.inflight.clone();
+                    inflight.set(inflight.get() + 1);
                     crate::rt::spawn(self.service.call(item).map(move |item| {
+                        inflight.set(inflight.get() - 1);
                         let item = match item {
                             Ok(Some(item)) => Ok(item),
                             Err(err) => Err(err),
@@ -320,6 +369,37 @@ where
         PollResult::Pending
     }
 
+    /// check keep-alive timer
+    fn poll_keepalive(&mut self, cx: &mut Context<'_>) -> PollResult {
+        if self.keepalive_timeout == 0 {
+            return PollResult::Pending;
+        }
+
+        // output is still draining, peer is alive
+        if !self.framed.is_write_buf_empty() {
+            self.ka_timer.take();
+            return PollResult::Pending;
+        }
+
+        let keepalive_timeout = self.keepalive_timeout;
+        let timer = self
+            .ka_timer
+            .get_or_insert_with(|| delay_for(Duration::from_millis(keepalive_timeout)));
+        if Pin::new(&mut *timer).poll(cx).is_ready() {
+            if self.inflight.get() == 0 {
+                log::trace!("Keep-alive timeout expired, stop dispatcher");
+                self.ka_timer.take();
+                self.state = FramedState::FlushAndStop(Some(DispatcherError::KeepAlive));
+                return PollResult::Continue;
+            } else {
+                // service is still processing requests
+                timer.reset(Instant::now() + Duration::from_millis(keepalive_timeout));
+                let _ = Pin::new(timer).poll(cx);
+            }
+        }
+        PollResult::Pending
+    }
+
     pub(super) fn poll(
         &mut self,
         cx: &mut Context<'_>,
@@ -331,6 +411,13 @@ where
                     let write = self.poll_write(cx);
                     if read == PollResult::Continue || write == PollResult::Continue {
                         continue;
+                    } else if read == PollResult::Paused
+                        && !self.framed.is_write_buf_full()
+                    {
+                        // write buffer got flushed, resume reading
+                        continue;
+                    } else if self.poll_keepalive(cx) == PollResult::Continue {
+                        continue;
                     } else {
                         return Poll::Pending;
                     }
@@ -371,7 +458,9 @@ where
                 FramedState::Shutdown(ref mut err) => {
                     return if self.service.poll_shutdown(cx, err.is_some()).is_ready() {
                         let result = if let Some(err) = err.take() {
-                            if let DispatcherError::Service(_) = err {
+                            if let DispatcherError::Service(_)
+                            | DispatcherError::KeepAlive = err
+                            {
                                 Err(err)
                             } else {
                                 // no need for io shutdown because io error occured
@@ -410,7 +499,7 @@ where
                         };
                     } else {
                         ready!(Pin::new(delay).poll(cx));
-                        return Poll::Ready(Ok(()));
+                        return Poll::Ready(err.take().unwrap_or(Ok(())));
                     }
                 }
             }
@@ -423,7 +512,7 @@ mod tests {
     use bytes::{Bytes, BytesMut};
     use derive_more::Display;
     use futures::future::ok;
-    use std::io;
+    use std::{cell::RefCell, io};
 
     use super::*;
     use crate::channel::mpsc;
@@ -445,6 +534,9 @@ mod tests {
         let err = T::from(TestError);
         assert!(format!("{:?}", err).contains("DispatcherError::Service"));
         assert_eq!(format!("{}", err), "TestError");
+        let err = T::KeepAlive;
+        assert!(format!("{:?}", err).contains("DispatcherError::KeepAlive"));
+        assert_eq!(format!("{}", err), "Keep-alive timeout expired");
     }
 
     #[ntex_rt::test]
@@ -529,4 +621,152 @@ mod tests {
         client.close().await;
         assert!(client.is_server_dropped());
     }
+
+    #[ntex_rt::test]
+    async fn test_err_mid_stream() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+
+        let framed = Framed::new(server, BytesCodec);
+        let disp = Dispatcher::new(
+            framed,
+            crate::fn_service(|msg: BytesMut| async move {
+                if msg.as_ref() == b"error" {
+                    Err(())
+                } else {
+                    Ok(Some(msg.freeze()))
+                }
+            }),
+        );
+        let result = Rc::new(RefCell::new(None));
+        let result2 = result.clone();
+        crate::rt::spawn(disp.map(move |res| {
+            *result2.borrow_mut() = Some(res.is_err());
+        }));
+
+        client.write("test");
+        let buf = client.read().await.unwrap();
+        assert_eq!(buf, Bytes::from_static(b"test"));
+
+        client.write("error");
+        delay_for(Duration::from_millis(25)).await;
+
+        // write side must be closed
+        assert!(client.is_closed());
+        client.close().await;
+        assert!(client.is_server_dropped());
+        assert_eq!(*result.borrow(), Some(true));
+    }
+
+    #[ntex_rt::test]
+    async fn test_backpressure() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(0);
+
+        // fill write buffer, peer does not read data
+        let mut framed = Framed::new(server, BytesCodec);
+        framed.write_buf().extend(&[b'x'; 9 * 1024][..]);
+
+        let calls = Rc::new(Cell::new(0));
+        let calls2 = calls.clone();
+        let disp = Dispatcher::new(
+            framed,
+            crate::fn_service(move |msg: BytesMut| {
+                calls2.set(calls2.get() + 1);
+                ok::<_, ()>(Some(msg.freeze()))
+            }),
+        );
+        crate::rt::spawn(disp.map(|_| ()));
+
+        client.write("test");
+        delay_for(Duration::from_millis(25)).await;
+        assert_eq!(calls.get(), 0);
+
+        // slow consumer reads data, dispatcher continues processing
+        client.remote_buffer_cap(16 * 1024);
+        delay_for(Duration::from_millis(25)).await;
+        let buf = client.read_any();
+        assert_eq!(buf.len(), 9 * 1024 + 4);
+        assert!(buf.ends_with(b"test"));
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[ntex_rt::test]
+    async fn test_keepalive() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+
+        let framed = Framed::new(server, BytesCodec);
+        let disp = Dispatcher::new(
+            framed,
+            crate::fn_service(|msg: BytesMut| ok::<_, ()>(Some(msg.freeze()))),
+        )
+        .keepalive_timeout(100)
+        .disconnect_timeout(25);
+        let result = Rc::new(RefCell::new(None));
+        let result2 = result.clone();
+        crate::rt::spawn(disp.map(move |res| {
+            *result2.borrow_mut() = Some(res);
+        }));
+
+        delay_for(Duration::from_millis(50)).await;
+        client.write("test");
+        let buf = client.read().await.unwrap();
+        assert_eq!(buf, Bytes::from_static(b"test"));
+
+        // timer is reset by incoming frame
+        delay_for(Duration::from_millis(75)).await;
+        assert!(!client.is_closed());
+
+        delay_for(Duration::from_millis(50)).await;
+        assert!(client.is_closed());
+        delay_for(Duration::from_millis(50)).await;
+        assert!(client.is_server_dropped());
+        assert!(matches!(
+            *result.borrow(),
+            Some(Err(DispatcherError::KeepAlive))
+        ));
+    }
+
+    #[ntex_rt::test]
+    async fn test_keepalive_backpressure() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(0);
+
+        // fill write buffer, peer does not read data
+        let mut framed = Framed::new(server, BytesCodec);
+        framed.write_buf().extend(&[b'x'; 9 * 1024][..]);
+
+        let disp = Dispatcher::new(
+            framed,
+            crate::fn_service(|msg: BytesMut| ok::<_, ()>(Some(msg.freeze()))),
+        )
+        .keepalive_timeout(50)
+        .disconnect_timeout(25);
+
+        // timer starts on first poll
+        delay_for(Duration::from_millis(75)).await;
+        let result = Rc::new(RefCell::new(None));
+        let result2 = result.clone();
+        crate::rt::spawn(disp.map(move |res| {
+            *result2.borrow_mut() = Some(res);
+        }));
+
+        // timer is suspended while output is draining
+        delay_for(Duration::from_millis(100)).await;
+        assert!(!client.is_closed());
+        assert!(result.borrow().is_none());
+
+        client.remote_buffer_cap(16 * 1024);
+        delay_for(Duration::from_millis(25)).await;
+        assert_eq!(client.read_any().len(), 9 * 1024);
+        assert!(!client.is_closed());
+
+        delay_for(Duration::from_millis(100)).await;
+        assert!(client.is_closed());
+        assert!(matches!(
+            *result.borrow(),
+            Some(Err(DispatcherError::KeepAlive))
+        ));
+    }
 }
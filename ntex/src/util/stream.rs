@@ -22,6 +22,8 @@ where
     sink: Option<U>,
     rx: mpsc::Receiver<Result<S::Response, S::Error>>,
     shutdown: Option<bool>,
+    on_error: Option<Box<dyn Fn(&S::Error) -> Option<R>>>,
+    error_item: Option<R>,
 }
 
 impl<R, S, T, U> Dispatcher<R, S, T, U>
@@ -43,8 +45,22 @@ where
             service: service.into_service(),
             rx: mpsc::channel().1,
             shutdown: None,
+            on_error: None,
+            error_item: None,
         }
     }
+
+    /// Set stream error handler.
+    ///
+    /// Item returned by the handler is sent to the sink before
+    /// the sink gets closed.
+    pub fn on_stream_error<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&S::Error) -> Option<R> + 'static,
+    {
+        self.on_error = Some(Box::new(f));
+        self
+    }
 }
 
 impl<R, S, T, U> Future for Dispatcher<R, S, T, U>
@@ -63,7 +79,14 @@ where
 
         if let Some(is_err) = this.shutdown {
             if let Some(mut sink) = this.sink.take() {
+                let item = this.error_item.take();
                 crate::rt::spawn(async move {
+                    if let Some(item) = item {
+                        if let Err(e) = sink.send(Ok(item)).await {
+                            error!("Failed to send message to sink: {:?}", e);
+                            return;
+                        }
+                    }
                     if sink.flush().await.is_ok() {
                         let _ = sink.close().await;
                     }
@@ -132,7 +155,11 @@ where
                         continue;
                     }
                     Poll::Pending => Poll::Pending,
-                    Poll::Ready(Some(Err(_))) => {
+                    Poll::Ready(Some(Err(e))) => {
+                        trace!("Stream is failed: {:?}", e);
+                        if let Some(ref on_error) = this.on_error {
+                            *this.error_item = on_error(&e);
+                        }
                         *this.shutdown = Some(true);
                         return self.poll(cx);
                     }
@@ -203,4 +230,33 @@ mod tests {
 
         assert_eq!(counter.get(), 1);
     }
+
+    #[ntex_rt::test]
+    async fn test_stream_error() {
+        let (tx1, mut rx) = mpsc::channel();
+        let (tx, rx2) = mpsc::channel();
+        let encoder = ws::StreamEncoder::new(tx1);
+        let decoder = ws::StreamDecoder::new(rx2);
+
+        let disp = Dispatcher::new(decoder, encoder, crate::fn_service(|_| ok(None)))
+            .on_stream_error(|e| match e {
+                ws::StreamError::Protocol(err) => {
+                    Some(ws::Message::Close(Some(err.close_code().into())))
+                }
+                _ => None,
+            });
+        crate::rt::spawn(disp.map(|_| ()));
+
+        // unmasked client frame
+        let mut buf = BytesMut::new();
+        let mut codec = ws::Codec::new();
+        codec
+            .encode(ws::Message::Text("test".to_string()), &mut buf)
+            .unwrap();
+        tx.send(Ok::<_, ()>(buf.split().freeze())).unwrap();
+
+        let data = rx.next().await.unwrap().unwrap();
+        assert_eq!(data, b"\x88\x02\x03\xea".as_ref());
+        assert!(rx.next().await.is_none());
+    }
 }
@@ -27,6 +27,18 @@ type HttpNewService<Err: ErrorRenderer> =
 type FnDataFactory =
     Box<dyn Fn() -> LocalBoxFuture<'static, Result<Box<dyn DataFactory>, ()>>>;
 
+/// Type-erased application factory
+///
+/// Created with `App::into_boxed_factory()` method. Boxed factory could be
+/// used anywhere regular application is accepted.
+pub type BoxedAppFactory<Err = DefaultError> = BoxServiceFactory<
+    AppConfig,
+    Request,
+    WebResponse,
+    <Err as ErrorRenderer>::Container,
+    (),
+>;
+
 /// Application builder - structure that follows the builder pattern
 /// for building application instances.
 pub struct App<T, Err: ErrorRenderer = DefaultError> {
@@ -501,6 +513,35 @@ where
     > {
         crate::map_config(self.into_factory(), move |_| cfg.clone())
     }
+
+    /// Finish application configuration and create type-erased
+    /// application factory.
+    ///
+    /// Boxed factory does not expose types of registered services and
+    /// middlewares, so applications could be stored in collections or passed
+    /// between crates. Each request costs one extra allocation.
+    ///
+    /// ```rust
+    /// use ntex::web::{self, App, BoxedAppFactory, HttpResponse};
+    ///
+    /// fn plugin(name: &str) -> BoxedAppFactory {
+    ///     App::new()
+    ///         .wrap(web::middleware::Logger::default())
+    ///         .service(web::resource(name).to(|| async { HttpResponse::Ok() }))
+    ///         .into_boxed_factory()
+    /// }
+    ///
+    /// fn main() {
+    ///     let apps: Vec<BoxedAppFactory> = vec![plugin("/one"), plugin("/two")];
+    /// }
+    /// ```
+    pub fn into_boxed_factory(self) -> BoxedAppFactory<Err>
+    where
+        T: 'static,
+        Err: 'static,
+    {
+        boxed::factory(self.into_factory())
+    }
 }
 
 impl<T, Err> IntoServiceFactory<AppFactory<T, Err>> for App<T, Err>
@@ -648,6 +689,38 @@ mod tests {
         );
     }
 
+    #[ntex_rt::test]
+    async fn test_boxed_factory() {
+        let apps: Vec<BoxedAppFactory> = vec![
+            App::new()
+                .wrap(
+                    DefaultHeaders::new()
+                        .header(header::CONTENT_TYPE, HeaderValue::from_static("0001")),
+                )
+                .route("/test", web::get().to(|| async { HttpResponse::Ok() }))
+                .into_boxed_factory(),
+            App::new()
+                .route("/test", web::get().to(|| async { HttpResponse::Created() }))
+                .into_boxed_factory(),
+        ];
+        let mut apps = apps.into_iter();
+
+        let srv = init_service(apps.next().unwrap()).await;
+        let req = TestRequest::with_uri("/test").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            HeaderValue::from_static("0001")
+        );
+
+        let srv = init_service(apps.next().unwrap()).await;
+        let req = TestRequest::with_uri("/test").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        assert!(resp.headers().get(header::CONTENT_TYPE).is_none());
+    }
+
     #[ntex_rt::test]
     async fn test_router_wrap() {
         let srv = init_service(
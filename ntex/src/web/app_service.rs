@@ -9,7 +9,7 @@ use futures::future::{ok, FutureExt, LocalBoxFuture};
 
 use crate::http::{Extensions, Request, Response};
 use crate::router::{Path, ResourceDef, ResourceInfo, Router};
-use crate::service::boxed::{self, BoxService, BoxServiceFactory};
+use crate::service::boxed::{self, BoxServiceFactory, LocalBoxService};
 use crate::{fn_service, Service, ServiceFactory};
 
 use super::config::AppConfig;
@@ -24,7 +24,7 @@ use super::types::data::DataFactory;
 
 type Guards = Vec<Box<dyn Guard>>;
 type HttpService<Err: ErrorRenderer> =
-    BoxService<WebRequest<Err>, WebResponse, Err::Container>;
+    LocalBoxService<WebRequest<Err>, WebResponse, Err::Container>;
 type HttpNewService<Err: ErrorRenderer> =
     BoxServiceFactory<(), WebRequest<Err>, WebResponse, Err::Container, ()>;
 type BoxResponse<Err: ErrorRenderer> =
@@ -263,6 +263,9 @@ where
         let req = if let Some(mut req) = self.pool.get_request() {
             let inner = Rc::get_mut(&mut req.0).unwrap();
             inner.path.set(head.uri.clone());
+            inner.pattern.clear();
+            inner.match_name = None;
+            inner.match_default = false;
             inner.head = head;
             inner.payload = payload;
             inner.app_data = self.data.clone();
@@ -392,7 +395,13 @@ impl<Err: ErrorRenderer> Future for AppRoutingFactoryResponse<Err> {
                     .fold(Router::build(), |mut router, item| {
                         match item {
                             CreateAppRoutingItem::Service(path, guards, service) => {
-                                router.rdef(path, service).2 = guards;
+                                let pattern = Rc::from(path.pattern());
+                                let name = if path.name().is_empty() {
+                                    None
+                                } else {
+                                    Some(Rc::from(path.name()))
+                                };
+                                router.rdef(path, (service, pattern, name)).2 = guards;
                             }
                             CreateAppRoutingItem::Future(_, _, _) => unreachable!(),
                         }
@@ -415,7 +424,7 @@ impl<Err: ErrorRenderer> Future for AppRoutingFactoryResponse<Err> {
 }
 
 pub struct AppRouting<Err: ErrorRenderer> {
-    router: Router<HttpService<Err>, Guards>,
+    router: Router<(HttpService<Err>, Rc<str>, Option<Rc<str>>), Guards>,
     ready: Option<(WebRequest<Err>, ResourceInfo)>,
     default: Option<HttpService<Err>>,
 }
@@ -447,9 +456,11 @@ impl<Err: ErrorRenderer> Service for AppRouting<Err> {
             true
         });
 
-        if let Some((srv, _info)) = res {
+        if let Some(((srv, pattern, name), _info)) = res {
+            req.push_match(pattern, name);
             srv.call(req)
         } else if let Some(ref default) = self.default {
+            req.set_default_match();
             default.call(req)
         } else {
             let req = req.into_parts().0;
@@ -17,11 +17,22 @@ struct AppConfigInner {
     secure: bool,
     host: String,
     addr: SocketAddr,
+    catch_panic: bool,
 }
 
 impl AppConfig {
     pub(crate) fn new(secure: bool, addr: SocketAddr, host: String) -> Self {
-        AppConfig(Rc::new(AppConfigInner { secure, addr, host }))
+        AppConfig(Rc::new(AppConfigInner {
+            secure,
+            addr,
+            host,
+            catch_panic: true,
+        }))
+    }
+
+    pub(crate) fn set_catch_panic(mut self, val: bool) -> Self {
+        Rc::get_mut(&mut self.0).unwrap().catch_panic = val;
+        self
     }
 
     /// Server host name.
@@ -44,6 +55,11 @@ impl AppConfig {
     pub fn local_addr(&self) -> SocketAddr {
         self.0.addr
     }
+
+    /// Returns true if panics in handlers are converted to error responses
+    pub fn catch_panic(&self) -> bool {
+        self.0.catch_panic
+    }
 }
 
 impl Default for AppConfig {
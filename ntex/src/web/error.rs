@@ -1,5 +1,7 @@
 //! Web error
+use std::any::TypeId;
 use std::cell::RefCell;
+use std::error::Error as StdError;
 use std::fmt;
 use std::io::Write;
 use std::marker::PhantomData;
@@ -7,7 +9,7 @@ use std::marker::PhantomData;
 use bytes::BytesMut;
 use derive_more::{Display, From};
 
-pub use actix_threadpool::BlockingError;
+pub use crate::rt::blocking::BlockingError;
 pub use futures::channel::oneshot::Canceled;
 pub use http::Error as HttpError;
 pub use serde_json::error::Error as JsonError;
@@ -44,19 +46,66 @@ where
 
     /// Generate response for error
     ///
-    /// Internal server error is generated by default.
+    /// Internal server error is generated by default. Error message is
+    /// used as response body, except for internal server errors.
     fn error_response(&self, _: &HttpRequest) -> HttpResponse {
-        let mut resp = HttpResponse::new(self.status_code());
-        let mut buf = BytesMut::new();
-        let _ = write!(Writer(&mut buf), "{}", self);
-        resp.headers_mut().insert(
-            header::CONTENT_TYPE,
-            header::HeaderValue::from_static("text/plain; charset=utf-8"),
-        );
-        resp.set_body(Body::from(buf))
+        text_response(self.status_code(), self)
+    }
+
+    /// Error as `std::error::Error`
+    ///
+    /// It is used as `source()` of error container, so error chain
+    /// is available for logging. Returns `None` by default.
+    fn std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        None
+    }
+
+    #[doc(hidden)]
+    /// Type id of the error, used for downcasting
+    fn __private_get_type_id__(&self, _: private::Token) -> TypeId {
+        TypeId::of::<Self>()
     }
 }
 
+mod private {
+    /// Prevents overriding of `__private_get_type_id__`
+    pub struct Token(pub(super) ());
+}
+
+impl<Err: ErrorRenderer> dyn WebResponseError<Err> {
+    /// Downcast error to a concrete type
+    pub fn downcast_ref<T: WebResponseError<Err>>(&self) -> Option<&T> {
+        if self.__private_get_type_id__(private::Token(())) == TypeId::of::<T>() {
+            // Safety: type id matches, so it is the same type
+            unsafe { Some(&*(self as *const dyn WebResponseError<Err> as *const T)) }
+        } else {
+            None
+        }
+    }
+}
+
+/// Plain text response with error message as body
+///
+/// Message of internal server error is not sent to the client,
+/// it could expose internal details. Canonical reason is used instead.
+pub(super) fn text_response<T>(status: StatusCode, err: &T) -> HttpResponse
+where
+    T: fmt::Display + ?Sized,
+{
+    let mut resp = HttpResponse::new(status);
+    let mut buf = BytesMut::new();
+    if status == StatusCode::INTERNAL_SERVER_ERROR {
+        buf.extend_from_slice(b"Internal Server Error");
+    } else {
+        let _ = write!(Writer(&mut buf), "{}", err);
+    }
+    resp.headers_mut().insert(
+        header::CONTENT_TYPE,
+        header::HeaderValue::from_static("text/plain; charset=utf-8"),
+    );
+    resp.set_body(Body::from(buf))
+}
+
 impl<Err: ErrorRenderer> WebResponseError<Err> for std::convert::Infallible {}
 
 impl<A, B, Err> WebResponseError<Err> for either::Either<A, B>
@@ -78,6 +127,13 @@ where
             either::Either::Right(ref b) => b.error_response(req),
         }
     }
+
+    fn std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            either::Either::Left(ref a) => a.std_error(),
+            either::Either::Right(ref b) => b.std_error(),
+        }
+    }
 }
 
 /// Errors which can occur when attempting to work with `Data` extractor
@@ -87,6 +143,8 @@ pub enum DataExtractorError {
     NotConfigured,
 }
 
+impl StdError for DataExtractorError {}
+
 /// Errors which can occur when attempting to generate resource uri.
 #[derive(Debug, PartialEq, Display, From)]
 pub enum UrlGenerationError {
@@ -101,6 +159,15 @@ pub enum UrlGenerationError {
     ParseError(UrlParseError),
 }
 
+impl StdError for UrlGenerationError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            UrlGenerationError::ParseError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
 /// A set of errors that can occur during parsing urlencoded payloads
 #[derive(Debug, Display, From)]
 pub enum UrlencodedError {
@@ -128,6 +195,15 @@ pub enum UrlencodedError {
     Payload(error::PayloadError),
 }
 
+impl StdError for UrlencodedError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            UrlencodedError::Payload(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
 /// A set of errors that can occur during parsing json payloads
 #[derive(Debug, Display, From)]
 pub enum JsonPayloadError {
@@ -145,6 +221,37 @@ pub enum JsonPayloadError {
     Payload(error::PayloadError),
 }
 
+impl StdError for JsonPayloadError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            JsonPayloadError::Deserialize(e) => Some(e),
+            JsonPayloadError::Payload(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Response serialization error
+#[derive(Debug, Display)]
+#[display(fmt = "Response serialize error: {}", _0)]
+pub struct SerializeError(Box<dyn std::error::Error + Send + Sync>);
+
+impl SerializeError {
+    /// Create serialization error
+    pub fn new<E>(err: E) -> Self
+    where
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        SerializeError(err.into())
+    }
+}
+
+impl StdError for SerializeError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self.0.as_ref())
+    }
+}
+
 /// A set of errors that can occur during parsing request paths
 #[derive(Debug, Display, From)]
 pub enum PathError {
@@ -153,6 +260,14 @@ pub enum PathError {
     Deserialize(serde::de::value::Error),
 }
 
+impl StdError for PathError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            PathError::Deserialize(e) => Some(e),
+        }
+    }
+}
+
 /// A set of errors that can occur during parsing query strings
 #[derive(Debug, Display, From)]
 pub enum QueryPayloadError {
@@ -161,6 +276,14 @@ pub enum QueryPayloadError {
     Deserialize(serde::de::value::Error),
 }
 
+impl StdError for QueryPayloadError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            QueryPayloadError::Deserialize(e) => Some(e),
+        }
+    }
+}
+
 #[derive(Debug, Display, From)]
 pub enum PayloadError {
     /// Http error.
@@ -174,6 +297,16 @@ pub enum PayloadError {
     Decoding,
 }
 
+impl StdError for PayloadError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            PayloadError::Http(e) => Some(e),
+            PayloadError::Payload(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
 /// Helper type that can wrap any error and generate custom response.
 ///
 /// In following example any `io::Error` will be converted into "BAD REQUEST"
@@ -252,6 +385,17 @@ where
     T: fmt::Debug + fmt::Display + 'static,
     E: ErrorRenderer,
 {
+    fn status_code(&self) -> StatusCode {
+        match self.status {
+            InternalErrorType::Status(st) => st,
+            InternalErrorType::Response(ref resp) => resp
+                .borrow()
+                .as_ref()
+                .map(|resp| resp.status())
+                .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+        }
+    }
+
     fn error_response(&self, _: &HttpRequest) -> HttpResponse {
         crate::http::error::ResponseError::error_response(self)
     }
@@ -264,16 +408,7 @@ where
 {
     fn error_response(&self) -> HttpResponse {
         match self.status {
-            InternalErrorType::Status(st) => {
-                let mut res = HttpResponse::new(st);
-                let mut buf = BytesMut::new();
-                let _ = write!(Writer(&mut buf), "{}", self);
-                res.headers_mut().insert(
-                    header::CONTENT_TYPE,
-                    header::HeaderValue::from_static("text/plain; charset=utf-8"),
-                );
-                res.set_body(Body::from(buf))
-            }
+            InternalErrorType::Status(st) => text_response(st, self),
             InternalErrorType::Response(ref resp) => {
                 if let Some(resp) = resp.borrow_mut().take() {
                     resp
@@ -679,7 +814,10 @@ where
 mod tests {
     use std::io;
 
+    use bytes::Bytes;
+
     use super::*;
+    use crate::http::body::ResponseBody;
     use crate::http::client::error::{ConnectError, SendRequestError};
     use crate::web::test::TestRequest;
     use crate::web::DefaultError;
@@ -703,6 +841,62 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_downcast_error() {
+        let e: Error = JsonPayloadError::Overflow.into();
+        assert!(matches!(
+            e.as_error::<JsonPayloadError>(),
+            Some(JsonPayloadError::Overflow)
+        ));
+        assert!(e.as_error::<UrlencodedError>().is_none());
+        assert!(e.source().unwrap().is::<JsonPayloadError>());
+
+        // source chain
+        let err = serde_json::from_str::<u32>("-").unwrap_err();
+        let e: Error = JsonPayloadError::Deserialize(err).into();
+        let source = e.source().unwrap().source().unwrap();
+        assert!(source.is::<serde_json::Error>());
+
+        let e: Error = InternalError::new("err", StatusCode::BAD_REQUEST).into();
+        assert!(e.as_error::<InternalError<&str>>().is_some());
+        assert!(e.source().is_none());
+        assert_eq!(e.as_response_error().status_code(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_internal_error_body() {
+        fn body(resp: &HttpResponse) -> Bytes {
+            if let ResponseBody::Body(Body::Bytes(ref b)) = resp.body() {
+                b.clone()
+            } else {
+                panic!()
+            }
+        }
+
+        // internal details are not sent to the client
+        let e: Error = io::Error::new(io::ErrorKind::Other, "secret").into();
+        let resp = crate::http::ResponseError::error_response(&e);
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body(&resp), Bytes::from_static(b"Internal Server Error"));
+        assert_eq!(e.to_string(), "secret");
+
+        let resp: HttpResponse =
+            ErrorInternalServerError::<_, DefaultError>("secret").into();
+        assert_eq!(body(&resp), Bytes::from_static(b"Internal Server Error"));
+
+        let resp: HttpResponse = ErrorBadRequest::<_, DefaultError>("bad").into();
+        assert_eq!(body(&resp), Bytes::from_static(b"bad"));
+
+        let e: Error = InternalError::from_response(
+            "err",
+            HttpResponse::Conflict().body("conflict"),
+        )
+        .into();
+        assert_eq!(e.as_response_error().status_code(), StatusCode::CONFLICT);
+        let resp = crate::http::ResponseError::error_response(&e);
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+    }
+
     #[test]
     fn test_other_errors() {
         let req = TestRequest::default().to_http_request();
@@ -754,6 +948,18 @@ mod tests {
         let err = PayloadError::Decoding;
         let resp = WebResponseError::<DefaultError>::error_response(&err, &req);
         assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let resp = WebResponseError::<DefaultError>::error_response(
+            &BlockingError::<()>::Overloaded,
+            &req,
+        );
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let resp = WebResponseError::<DefaultError>::error_response(
+            &BlockingError::<()>::Canceled,
+            &req,
+        );
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
     }
 
     #[test]
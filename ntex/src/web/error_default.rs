@@ -1,15 +1,12 @@
 //! Web error
-use std::io::Write;
+use std::error::Error as StdError;
 use std::str::Utf8Error;
 use std::{fmt, io};
 
-use bytes::BytesMut;
 use serde::de::value::Error as DeError;
 use serde_json::error::Error as JsonError;
 use serde_urlencoded::ser::Error as FormError;
 
-use crate::http::body::Body;
-use crate::http::helpers::Writer;
 use crate::http::ws::HandshakeError;
 use crate::http::{self, header, StatusCode};
 use crate::util::timeout::TimeoutError;
@@ -41,6 +38,20 @@ impl Error {
     pub fn as_response_error(&self) -> &dyn WebResponseError<DefaultError> {
         self.cause.as_ref()
     }
+
+    /// Returns the reference to the underlying error of type `T`,
+    /// if error container holds error of that type.
+    ///
+    /// ```rust
+    /// use ntex::web::error::{Error, JsonPayloadError};
+    ///
+    /// let err = Error::from(JsonPayloadError::Overflow);
+    /// assert!(err.as_error::<JsonPayloadError>().is_some());
+    /// assert!(err.as_error::<std::io::Error>().is_none());
+    /// ```
+    pub fn as_error<T: WebResponseError<DefaultError>>(&self) -> Option<&T> {
+        self.cause.downcast_ref()
+    }
 }
 
 /// `Error` for any error which implements `WebResponseError<DefaultError>`
@@ -52,7 +63,13 @@ impl<T: WebResponseError<DefaultError>> From<T> for Error {
     }
 }
 
-impl std::error::Error for Error {}
+/// Source of the error is the underlying error, if it implements
+/// `std::error::Error`
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.cause.std_error()
+    }
+}
 
 impl ErrorContainer for Error {
     fn error_response(&self, req: &HttpRequest) -> HttpResponse {
@@ -62,14 +79,7 @@ impl ErrorContainer for Error {
 
 impl crate::http::error::ResponseError for Error {
     fn error_response(&self) -> HttpResponse {
-        let mut resp = HttpResponse::new(self.cause.status_code());
-        let mut buf = BytesMut::new();
-        let _ = write!(Writer(&mut buf), "{}", self.cause);
-        resp.headers_mut().insert(
-            header::CONTENT_TYPE,
-            header::HeaderValue::from_static("text/plain; charset=utf-8"),
-        );
-        resp.set_body(Body::from(buf))
+        error::text_response(self.cause.status_code(), self.cause.as_ref())
     }
 }
 
@@ -95,16 +105,42 @@ impl<E: WebResponseError<DefaultError>> WebResponseError<DefaultError>
             TimeoutError::Timeout => StatusCode::GATEWAY_TIMEOUT,
         }
     }
+
+    fn std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            TimeoutError::Service(e) => e.std_error(),
+            TimeoutError::Timeout => None,
+        }
+    }
 }
 
 /// `InternalServerError` for `DataExtractorError`
-impl WebResponseError<DefaultError> for error::DataExtractorError {}
+impl WebResponseError<DefaultError> for error::DataExtractorError {
+    fn std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self)
+    }
+}
 
 /// `InternalServerError` for `JsonError`
-impl WebResponseError<DefaultError> for JsonError {}
+impl WebResponseError<DefaultError> for JsonError {
+    fn std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self)
+    }
+}
+
+/// `InternalServerError` for `SerializeError`
+impl WebResponseError<DefaultError> for error::SerializeError {
+    fn std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self)
+    }
+}
 
 /// `InternalServerError` for `FormError`
-impl WebResponseError<DefaultError> for FormError {}
+impl WebResponseError<DefaultError> for FormError {
+    fn std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self)
+    }
+}
 
 #[cfg(feature = "openssl")]
 /// `InternalServerError` for `openssl::ssl::Error`
@@ -122,15 +158,28 @@ impl WebResponseError<DefaultError> for DeError {
     fn status_code(&self) -> StatusCode {
         StatusCode::BAD_REQUEST
     }
+
+    fn std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self)
+    }
 }
 
 /// `InternalServerError` for `Canceled`
 impl WebResponseError<DefaultError> for crate::http::error::Canceled {}
 
-/// `InternalServerError` for `BlockingError`
+/// `ServiceUnavailable` for overloaded thread pool,
+/// `InternalServerError` for other `BlockingError` variants
 impl<E: fmt::Debug + 'static> WebResponseError<DefaultError>
     for crate::http::error::BlockingError<E>
 {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            crate::http::error::BlockingError::Overloaded => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
 }
 
 /// Return `BAD_REQUEST` for `Utf8Error`
@@ -138,11 +187,19 @@ impl WebResponseError<DefaultError> for Utf8Error {
     fn status_code(&self) -> StatusCode {
         StatusCode::BAD_REQUEST
     }
+
+    fn std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self)
+    }
 }
 
 /// Return `InternalServerError` for `HttpError`,
 /// Response generation can return `HttpError`, so it is internal error
-impl WebResponseError<DefaultError> for crate::http::error::HttpError {}
+impl WebResponseError<DefaultError> for crate::http::error::HttpError {
+    fn std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self)
+    }
+}
 
 /// Return `InternalServerError` for `io::Error`
 impl WebResponseError<DefaultError> for io::Error {
@@ -153,10 +210,18 @@ impl WebResponseError<DefaultError> for io::Error {
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
+
+    fn std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self)
+    }
 }
 
 /// `InternalServerError` for `UrlGeneratorError`
-impl WebResponseError<DefaultError> for error::UrlGenerationError {}
+impl WebResponseError<DefaultError> for error::UrlGenerationError {
+    fn std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self)
+    }
+}
 
 /// Response renderer for `UrlencodedError`
 impl WebResponseError<DefaultError> for error::UrlencodedError {
@@ -164,9 +229,16 @@ impl WebResponseError<DefaultError> for error::UrlencodedError {
         match *self {
             error::UrlencodedError::Overflow { .. } => StatusCode::PAYLOAD_TOO_LARGE,
             error::UrlencodedError::UnknownLength => StatusCode::LENGTH_REQUIRED,
+            error::UrlencodedError::Payload(http::error::PayloadError::Timeout) => {
+                StatusCode::REQUEST_TIMEOUT
+            }
             _ => StatusCode::BAD_REQUEST,
         }
     }
+
+    fn std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self)
+    }
 }
 
 /// Return `BadRequest` for `JsonPayloadError`
@@ -174,9 +246,16 @@ impl WebResponseError<DefaultError> for error::JsonPayloadError {
     fn status_code(&self) -> StatusCode {
         match *self {
             error::JsonPayloadError::Overflow => StatusCode::PAYLOAD_TOO_LARGE,
+            error::JsonPayloadError::Payload(http::error::PayloadError::Timeout) => {
+                StatusCode::REQUEST_TIMEOUT
+            }
             _ => StatusCode::BAD_REQUEST,
         }
     }
+
+    fn std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self)
+    }
 }
 
 /// Error renderer for `PathError`
@@ -184,6 +263,10 @@ impl WebResponseError<DefaultError> for error::PathError {
     fn status_code(&self) -> StatusCode {
         StatusCode::NOT_FOUND
     }
+
+    fn std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self)
+    }
 }
 
 /// Error renderer `QueryPayloadError`
@@ -191,25 +274,44 @@ impl WebResponseError<DefaultError> for error::QueryPayloadError {
     fn status_code(&self) -> StatusCode {
         StatusCode::BAD_REQUEST
     }
+
+    fn std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self)
+    }
 }
 
 impl WebResponseError<DefaultError> for error::PayloadError {
     fn status_code(&self) -> StatusCode {
-        StatusCode::BAD_REQUEST
+        match *self {
+            error::PayloadError::Payload(http::error::PayloadError::Timeout) => {
+                StatusCode::REQUEST_TIMEOUT
+            }
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    fn std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self)
     }
 }
 
-/// `PayloadError` returns two possible results:
+/// `PayloadError` returns three possible results:
 ///
 /// - `Overflow` returns `PayloadTooLarge`
+/// - `Timeout` returns `RequestTimeout`
 /// - Other errors returns `BadRequest`
 impl WebResponseError<DefaultError> for http::error::PayloadError {
     fn status_code(&self) -> StatusCode {
         match *self {
             http::error::PayloadError::Overflow => StatusCode::PAYLOAD_TOO_LARGE,
+            http::error::PayloadError::Timeout => StatusCode::REQUEST_TIMEOUT,
             _ => StatusCode::BAD_REQUEST,
         }
     }
+
+    fn std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self)
+    }
 }
 
 #[cfg(feature = "cookie")]
@@ -238,6 +340,10 @@ impl WebResponseError<DefaultError> for http::client::error::SendRequestError {
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
+
+    fn std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self)
+    }
 }
 
 /// Error renderer for ws::HandshakeError
@@ -1,10 +1,14 @@
 use std::future::Future;
 use std::marker::PhantomData;
+use std::panic::{self, AssertUnwindSafe};
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
 use futures::future::{FutureExt, LocalBoxFuture};
 
+use crate::http::error::PanicError;
+use crate::http::Response;
+
 use super::error::ErrorRenderer;
 use super::extract::FromRequest;
 use super::httprequest::HttpRequest;
@@ -143,7 +147,14 @@ where
         if let Some(fut) = this.from_request.as_pin_mut() {
             return match fut.poll(cx) {
                 Poll::Ready(Ok(param)) => {
-                    let fut = this.hnd.call(param);
+                    let (hnd, req) = (&*this.hnd, this.req.as_ref().unwrap());
+                    let fut = match catch_panic(req, || hnd.call(param)) {
+                        Ok(fut) => fut,
+                        Err(err) => {
+                            let req = this.req.take().unwrap();
+                            return Poll::Ready(Ok(panic_response(req, err)));
+                        }
+                    };
                     this = self.as_mut().project();
                     this.from_request.set(None);
                     this.handler.set(Some(fut));
@@ -158,15 +169,20 @@ where
         }
 
         if let Some(fut) = this.handler.as_pin_mut() {
-            return match fut.poll(cx) {
-                Poll::Ready(res) => {
+            let req = this.req.as_ref().unwrap();
+            return match catch_panic(req, || fut.poll(cx)) {
+                Err(err) => {
+                    let req = this.req.take().unwrap();
+                    Poll::Ready(Ok(panic_response(req, err)))
+                }
+                Ok(Poll::Ready(res)) => {
                     let fut = res.respond_to(this.req.as_ref().unwrap());
                     this = self.as_mut().project();
                     this.handler.set(None);
                     this.responder.set(Some(fut));
                     self.poll(cx)
                 }
-                Poll::Pending => Poll::Pending,
+                Ok(Poll::Pending) => Poll::Pending,
             };
         }
 
@@ -183,6 +199,27 @@ where
     }
 }
 
+/// Run handler, panic is captured if enabled by app config
+fn catch_panic<F, R>(req: &HttpRequest, f: F) -> Result<R, PanicError>
+where
+    F: FnOnce() -> R,
+{
+    if req.app_config().catch_panic() {
+        panic::catch_unwind(AssertUnwindSafe(f)).map_err(PanicError::new)
+    } else {
+        Ok(f())
+    }
+}
+
+fn panic_response(req: HttpRequest, err: PanicError) -> WebResponse {
+    error!(
+        "Handler panicked while handling {}: {}",
+        req.path(),
+        err.message()
+    );
+    WebResponse::new(Response::InternalServerError().finish(), req)
+}
+
 /// FromRequest trait impl for tuples
 macro_rules! factory_tuple ({ $(($n:tt, $T:ident)),+} => {
     impl<Func, $($T,)+ Res, Err> Handler<($($T,)+), Err> for Func
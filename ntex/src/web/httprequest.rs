@@ -25,6 +25,9 @@ pub(crate) struct HttpRequestInner {
     pub(crate) path: Path<Uri>,
     pub(crate) payload: Payload,
     pub(crate) app_data: Rc<Extensions>,
+    pub(crate) pattern: String,
+    pub(crate) match_name: Option<Rc<str>>,
+    pub(crate) match_default: bool,
     rmap: Rc<ResourceMap>,
     config: AppConfig,
     pool: &'static HttpRequestPool,
@@ -49,6 +52,9 @@ impl HttpRequest {
             config,
             app_data,
             pool,
+            pattern: String::new(),
+            match_name: None,
+            match_default: false,
         }))
     }
 }
@@ -125,6 +131,42 @@ impl HttpRequest {
         &mut Rc::get_mut(&mut self.0).unwrap().path
     }
 
+    /// Resource pattern that matched the request.
+    ///
+    /// Pattern includes prefixes of all matched scopes, for example
+    /// `/api/users/{id}`. Returns `None` if request is not routed or
+    /// no resource matched the request.
+    #[inline]
+    pub fn match_pattern(&self) -> Option<&str> {
+        if self.0.pattern.is_empty() || self.0.match_default {
+            None
+        } else {
+            Some(&self.0.pattern)
+        }
+    }
+
+    /// Name of the resource that matched the request.
+    ///
+    /// Returns `None` if resource has no name or no resource matched
+    /// the request.
+    #[inline]
+    pub fn match_name(&self) -> Option<&str> {
+        if self.0.match_default {
+            None
+        } else {
+            self.0.match_name.as_deref()
+        }
+    }
+
+    /// Check if request is handled by default service.
+    ///
+    /// Returns `true` if no resource matched the request and it is
+    /// passed to app or scope default service.
+    #[inline]
+    pub fn is_default_match(&self) -> bool {
+        self.0.match_default
+    }
+
     /// Request extensions
     #[inline]
     pub fn extensions(&self) -> Ref<'_, Extensions> {
@@ -250,7 +292,7 @@ impl Drop for HttpRequest {
         if Rc::strong_count(&self.0) == 1 {
             let v = &mut self.0.pool.0.borrow_mut();
             if v.len() < 128 {
-                self.extensions_mut().clear();
+                self.extensions_mut().clear_on_reuse();
                 v.push(self.0.clone());
             }
         }
@@ -376,10 +418,10 @@ mod tests {
         {
             let cookies = req.cookies().unwrap();
             assert_eq!(cookies.len(), 2);
-            assert_eq!(cookies[0].name(), "cookie2");
-            assert_eq!(cookies[0].value(), "value2");
-            assert_eq!(cookies[1].name(), "cookie1");
-            assert_eq!(cookies[1].value(), "value1");
+            assert_eq!(cookies[0].name(), "cookie1");
+            assert_eq!(cookies[0].value(), "value1");
+            assert_eq!(cookies[1].name(), "cookie2");
+            assert_eq!(cookies[1].value(), "value2");
         }
 
         let cookie = req.cookie("cookie1");
@@ -0,0 +1,293 @@
+//! Streaming json responses
+//!
+//! Items of the stream are serialized as they arrive, so memory use does
+//! not depend on the number of items. If the stream or item serialization
+//! fails, response body is aborted: http/1 connection gets closed without
+//! terminating chunk and http/2 stream gets reset, so clients could detect
+//! incomplete response.
+//!
+//! ```rust
+//! use futures::stream;
+//! use ntex::web::{self, App};
+//!
+//! fn main() {
+//!     let app = App::new().service(web::resource("/rows").to(|| async {
+//!         let rows = stream::iter((0..1000).map(Ok::<_, std::io::Error>));
+//!         web::json_stream::array(rows)
+//!     }));
+//! }
+//! ```
+use std::error::Error;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::{BufMut, Bytes, BytesMut};
+use futures::future::{ready, Ready};
+use futures::Stream;
+use serde::Serialize;
+
+use crate::http::body::{Body, BodySize, MessageBody};
+use crate::http::header::CONTENT_TYPE;
+use crate::http::helpers::Writer;
+use crate::http::Response;
+
+use super::error::ErrorRenderer;
+use super::httprequest::HttpRequest;
+use super::responder::Responder;
+
+/// Serialized items are batched to chunks up to this size
+const CHUNK_SIZE: usize = 8192;
+
+/// Create json array response from a stream, `[item,item,...]`
+///
+/// Closing bracket is sent only if stream completes successfully.
+pub fn array<S, T, E>(stream: S) -> JsonStream<S, E>
+where
+    S: Stream<Item = Result<T, E>> + 'static,
+    T: Serialize,
+    E: Into<Box<dyn Error>>,
+{
+    JsonStream::new(stream, Format::Array)
+}
+
+/// Create newline-delimited json response from a stream
+///
+/// Every item is serialized to a single line terminated with `\n`.
+pub fn ndjson<S, T, E>(stream: S) -> JsonStream<S, E>
+where
+    S: Stream<Item = Result<T, E>> + 'static,
+    T: Serialize,
+    E: Into<Box<dyn Error>>,
+{
+    JsonStream::new(stream, Format::NdJson)
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Format {
+    Array,
+    NdJson,
+}
+
+/// Streaming json body
+///
+/// Use [`array`] or [`ndjson`] functions to create json stream.
+/// Json stream could be used as a responder or as a response body.
+pub struct JsonStream<S, E> {
+    stream: Pin<Box<S>>,
+    format: Format,
+    items: usize,
+    done: bool,
+    _t: PhantomData<E>,
+}
+
+impl<S, E> JsonStream<S, E> {
+    fn new(stream: S, format: Format) -> Self {
+        JsonStream {
+            format,
+            stream: Box::pin(stream),
+            items: 0,
+            done: false,
+            _t: PhantomData,
+        }
+    }
+
+    fn content_type(&self) -> &'static str {
+        match self.format {
+            Format::Array => "application/json",
+            Format::NdJson => "application/x-ndjson",
+        }
+    }
+}
+
+impl<S, T, E> MessageBody for JsonStream<S, E>
+where
+    S: Stream<Item = Result<T, E>>,
+    T: Serialize,
+    E: Into<Box<dyn Error>>,
+{
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+
+        let mut buf = BytesMut::new();
+        if self.format == Format::Array && self.items == 0 {
+            buf.put_u8(b'[');
+        }
+
+        loop {
+            match self.stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(item))) => {
+                    if self.format == Format::Array && self.items != 0 {
+                        buf.put_u8(b',');
+                    }
+                    self.items += 1;
+
+                    if let Err(e) = serde_json::to_writer(Writer(&mut buf), &item) {
+                        self.done = true;
+                        return Poll::Ready(Some(Err(e.into())));
+                    }
+                    if self.format == Format::NdJson {
+                        buf.put_u8(b'\n');
+                    }
+                    if buf.len() >= CHUNK_SIZE {
+                        return Poll::Ready(Some(Ok(buf.freeze())));
+                    }
+                }
+                Poll::Ready(Some(Err(e))) => {
+                    self.done = true;
+                    return Poll::Ready(Some(Err(e.into())));
+                }
+                Poll::Ready(None) => {
+                    self.done = true;
+                    if self.format == Format::Array {
+                        buf.put_u8(b']');
+                    }
+                    return if buf.is_empty() {
+                        Poll::Ready(None)
+                    } else {
+                        Poll::Ready(Some(Ok(buf.freeze())))
+                    };
+                }
+                Poll::Pending => {
+                    return if buf.is_empty() || self.items == 0 {
+                        // opening bracket is sent with the first item
+                        Poll::Pending
+                    } else {
+                        Poll::Ready(Some(Ok(buf.freeze())))
+                    };
+                }
+            }
+        }
+    }
+}
+
+impl<S, T, E, Err> Responder<Err> for JsonStream<S, E>
+where
+    S: Stream<Item = Result<T, E>> + 'static,
+    T: Serialize + 'static,
+    E: Into<Box<dyn Error>> + 'static,
+    Err: ErrorRenderer,
+{
+    type Error = Err::Container;
+    type Future = Ready<Response>;
+
+    fn respond_to(self, _: &HttpRequest) -> Self::Future {
+        ready(
+            Response::Ok()
+                .header(CONTENT_TYPE, self.content_type())
+                .body(Body::from_message(self)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use futures::future::poll_fn;
+    use futures::{stream, StreamExt};
+
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::web::test::{init_service, read_body, TestRequest};
+    use crate::web::{self, App};
+    use crate::Service;
+
+    async fn chunks<B: MessageBody>(mut body: B) -> Vec<Result<Bytes, String>> {
+        let mut chunks = Vec::new();
+        while let Some(item) = poll_fn(|cx| body.poll_next_chunk(cx)).await {
+            chunks.push(item.map_err(|e| e.to_string()));
+        }
+        chunks
+    }
+
+    #[ntex_rt::test]
+    async fn test_array() {
+        let body = array(stream::iter(vec![Ok::<_, io::Error>(1), Ok(2), Ok(3)]));
+        assert_eq!(chunks(body).await, vec![Ok(Bytes::from_static(b"[1,2,3]"))]);
+
+        let body = array(stream::empty::<Result<u32, io::Error>>());
+        assert_eq!(chunks(body).await, vec![Ok(Bytes::from_static(b"[]"))]);
+
+        // large streams are split to chunks
+        let body = array(stream::iter((0..10_000).map(Ok::<_, io::Error>)));
+        let chunks = chunks(body).await;
+        assert!(chunks.len() > 1);
+        let data: Vec<u8> = chunks
+            .into_iter()
+            .flat_map(|chunk| chunk.unwrap().to_vec())
+            .collect();
+        let data: Vec<u32> = serde_json::from_slice(&data).unwrap();
+        assert_eq!(data.len(), 10_000);
+        assert_eq!(data[9_999], 9_999);
+    }
+
+    #[ntex_rt::test]
+    async fn test_ndjson() {
+        let body = ndjson(stream::iter(vec![Ok::<_, io::Error>("a"), Ok("b")]));
+        assert_eq!(
+            chunks(body).await,
+            vec![Ok(Bytes::from_static(b"\"a\"\n\"b\"\n"))]
+        );
+
+        let body = ndjson(stream::empty::<Result<u32, io::Error>>());
+        assert!(chunks(body).await.is_empty());
+    }
+
+    #[ntex_rt::test]
+    async fn test_errors() {
+        // stream error, closing bracket is not sent
+        let items = vec![Ok(1), Err(io::Error::new(io::ErrorKind::Other, "err"))];
+        let body = array(stream::iter(items).chain(stream::iter(vec![Ok(2)])));
+        let items = chunks(body).await;
+        assert_eq!(items, vec![Err("err".to_string())]);
+
+        // serialization error
+        let mut map = std::collections::BTreeMap::new();
+        map.insert((1, 2), 3);
+        let body = ndjson(stream::iter(vec![Ok::<_, io::Error>(map)]));
+        let items = chunks(body).await;
+        assert_eq!(items.len(), 1);
+        assert!(items[0].is_err());
+    }
+
+    #[ntex_rt::test]
+    async fn test_responder() {
+        let srv = init_service(
+            App::new()
+                .service(web::resource("/array").to(|| async {
+                    array(stream::iter(vec![Ok::<_, io::Error>(1), Ok(2)]))
+                }))
+                .service(web::resource("/ndjson").to(|| async {
+                    ndjson(stream::iter(vec![Ok::<_, io::Error>(1), Ok(2)]))
+                })),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/array").to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"[1,2]"));
+
+        let req = TestRequest::with_uri("/ndjson").to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(
+            resp.headers().get(CONTENT_TYPE).unwrap(),
+            "application/x-ndjson"
+        );
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"1\n2\n"));
+    }
+}
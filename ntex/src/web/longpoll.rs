@@ -0,0 +1,214 @@
+use std::error::Error;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures::future::{ready, Ready};
+
+use crate::http::body::{Body, BodySize, MessageBody};
+use crate::http::Response;
+use crate::rt::time::{delay_for, Delay, Instant};
+
+use super::error::ErrorRenderer;
+use super::httprequest::HttpRequest;
+use super::responder::Responder;
+
+/// Create long-polling responder.
+///
+/// Response headers are sent immediately, response body is sent with
+/// streaming (chunked) encoding. While `fut` is pending, padding bytes
+/// are sent every `interval`, so intermediaries do not close idle
+/// connection. Once `fut` resolves, its output is sent as the last
+/// chunk of the body. If `fut` does not complete within `timeout`, it
+/// gets dropped and the body is finished with timeout payload, which is
+/// empty by default.
+///
+/// If client disconnects, the response body gets dropped together
+/// with `fut`.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use ntex::web::{self, App};
+///
+/// fn main() {
+///     let app = App::new().service(web::resource("/poll").to(|| async {
+///         web::long_poll(Duration::from_secs(60), Duration::from_secs(15), async {
+///             // wait for the event
+///             "event"
+///         })
+///         .padding("\n")
+///         .timeout_payload("timeout")
+///     }));
+/// }
+/// ```
+pub fn long_poll<F>(timeout: Duration, interval: Duration, fut: F) -> LongPoll<F>
+where
+    F: Future + 'static,
+    F::Output: Into<Bytes>,
+{
+    LongPoll {
+        fut,
+        timeout,
+        interval,
+        padding: Bytes::from_static(b" "),
+        timeout_payload: Bytes::new(),
+    }
+}
+
+/// Long-polling responder
+///
+/// Use [`long_poll`] function to create responder.
+pub struct LongPoll<F> {
+    fut: F,
+    timeout: Duration,
+    interval: Duration,
+    padding: Bytes,
+    timeout_payload: Bytes,
+}
+
+impl<F> LongPoll<F> {
+    /// Set bytes sent to the client while future is pending.
+    ///
+    /// By default single space is sent. Empty padding disables keep-alive
+    /// chunks.
+    pub fn padding<B: Into<Bytes>>(mut self, padding: B) -> Self {
+        self.padding = padding.into();
+        self
+    }
+
+    /// Set bytes sent to the client if future does not complete in time.
+    pub fn timeout_payload<B: Into<Bytes>>(mut self, payload: B) -> Self {
+        self.timeout_payload = payload.into();
+        self
+    }
+}
+
+impl<F, Err> Responder<Err> for LongPoll<F>
+where
+    F: Future + 'static,
+    F::Output: Into<Bytes>,
+    Err: ErrorRenderer,
+{
+    type Error = Err::Container;
+    type Future = Ready<Response>;
+
+    fn respond_to(self, _: &HttpRequest) -> Self::Future {
+        ready(Response::Ok().body(Body::from_message(LongPollBody {
+            fut: Some(Box::pin(self.fut)),
+            timeout: delay_for(self.timeout),
+            keepalive: delay_for(self.interval),
+            interval: self.interval,
+            padding: self.padding,
+            timeout_payload: self.timeout_payload,
+        })))
+    }
+}
+
+struct LongPollBody<F> {
+    fut: Option<Pin<Box<F>>>,
+    timeout: Delay,
+    keepalive: Delay,
+    interval: Duration,
+    padding: Bytes,
+    timeout_payload: Bytes,
+}
+
+impl<F> LongPollBody<F> {
+    fn last_chunk(chunk: Bytes) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        // empty chunk terminates chunked body
+        if chunk.is_empty() {
+            Poll::Ready(None)
+        } else {
+            Poll::Ready(Some(Ok(chunk)))
+        }
+    }
+}
+
+impl<F> MessageBody for LongPollBody<F>
+where
+    F: Future,
+    F::Output: Into<Bytes>,
+{
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        let fut = if let Some(ref mut fut) = self.fut {
+            fut
+        } else {
+            return Poll::Ready(None);
+        };
+
+        if let Poll::Ready(item) = fut.as_mut().poll(cx) {
+            self.fut = None;
+            return Self::last_chunk(item.into());
+        }
+
+        if Pin::new(&mut self.timeout).poll(cx).is_ready() {
+            self.fut = None;
+            return Self::last_chunk(std::mem::take(&mut self.timeout_payload));
+        }
+
+        if !self.padding.is_empty() && Pin::new(&mut self.keepalive).poll(cx).is_ready()
+        {
+            self.keepalive.reset(Instant::now() + self.interval);
+            return Poll::Ready(Some(Ok(self.padding.clone())));
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::pending;
+
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::rt::time::delay_for;
+    use crate::web::test::{init_service, read_body, TestRequest};
+    use crate::web::{self, App};
+    use crate::Service;
+
+    #[ntex_rt::test]
+    async fn test_long_poll() {
+        let srv = init_service(
+            App::new()
+                .service(web::resource("/ready").to(|| async {
+                    web::long_poll(
+                        Duration::from_secs(10),
+                        Duration::from_millis(100),
+                        async {
+                            delay_for(Duration::from_millis(250)).await;
+                            "data"
+                        },
+                    )
+                    .padding("-")
+                }))
+                .service(web::resource("/timeout").to(|| async {
+                    web::long_poll(
+                        Duration::from_millis(250),
+                        Duration::from_millis(100),
+                        pending::<Bytes>(),
+                    )
+                    .padding("-")
+                    .timeout_payload("timeout")
+                })),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/ready").to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"--data"));
+
+        let req = TestRequest::with_uri("/timeout").to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"--timeout"));
+    }
+}
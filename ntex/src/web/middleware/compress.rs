@@ -8,8 +8,9 @@ use std::task::{Context, Poll};
 
 use futures::future::{ok, Ready};
 
-use crate::http::encoding::Encoder;
-use crate::http::header::{ContentEncoding, ACCEPT_ENCODING};
+use crate::http::encoding::{Encoder, Level};
+use crate::http::header::{ContentEncoding, HeaderValue, ACCEPT_ENCODING, VARY};
+use crate::http::{Method, ResponseHead};
 use crate::service::{Service, Transform};
 
 use crate::web::dev::{WebRequest, WebResponse};
@@ -21,6 +22,10 @@ use crate::web::{BodyEncoding, ErrorRenderer};
 /// Use `BodyEncoding` trait for overriding response compression.
 /// To disable compression set encoding to `ContentEncoding::Identity` value.
 ///
+/// Responses with negotiated encoding get `Vary: Accept-Encoding` header.
+/// Responses to `HEAD` requests get same headers as responses to `GET`
+/// requests, but body is not encoded and content length is omitted.
+///
 /// ```rust
 /// use ntex::web::{self, middleware, App, HttpResponse};
 ///
@@ -36,12 +41,24 @@ use crate::web::{BodyEncoding, ErrorRenderer};
 /// ```
 pub struct Compress {
     enc: ContentEncoding,
+    level: Level,
 }
 
 impl Compress {
     /// Create new `Compress` middleware with default encoding.
     pub fn new(encoding: ContentEncoding) -> Self {
-        Compress { enc: encoding }
+        Compress {
+            enc: encoding,
+            level: Level::default(),
+        }
+    }
+
+    /// Set compression level.
+    ///
+    /// By default `Level::Fast` is used.
+    pub fn compression_level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
     }
 }
 
@@ -67,6 +84,7 @@ where
         ok(CompressMiddleware {
             service,
             encoding: self.enc,
+            level: self.level,
             _t: PhantomData,
         })
     }
@@ -75,6 +93,7 @@ where
 pub struct CompressMiddleware<S, E> {
     service: S,
     encoding: ContentEncoding,
+    level: Level,
     _t: PhantomData<E>,
 }
 
@@ -99,6 +118,8 @@ where
     }
 
     fn call(&self, req: WebRequest<E>) -> Self::Future {
+        let head_req = req.head().method == Method::HEAD;
+
         // negotiate content-encoding
         let encoding = if let Some(val) = req.headers().get(&ACCEPT_ENCODING) {
             if let Ok(enc) = val.to_str() {
@@ -112,6 +133,9 @@ where
 
         CompressResponse {
             encoding,
+            head_req,
+            negotiated: self.encoding != ContentEncoding::Identity,
+            level: self.level,
             fut: self.service.call(req),
             _t: PhantomData,
         }
@@ -125,6 +149,10 @@ pin_project_lite::pin_project! {
         #[pin]
         fut: S::Future,
         encoding: ContentEncoding,
+        // encoding is negotiated with accept-encoding header
+        negotiated: bool,
+        head_req: bool,
+        level: Level,
         _t: PhantomData<E>,
     }
 }
@@ -141,21 +169,50 @@ where
 
         match futures::ready!(this.fut.poll(cx)) {
             Ok(resp) => {
-                let enc = if let Some(enc) = resp.response().get_encoding() {
-                    enc
+                let (enc, negotiated) = if let Some(enc) = resp.response().get_encoding()
+                {
+                    (enc, false)
                 } else {
-                    *this.encoding
+                    (*this.encoding, *this.negotiated)
                 };
 
-                Poll::Ready(Ok(
-                    resp.map_body(move |head, body| Encoder::response(enc, head, body))
-                ))
+                let level = *this.level;
+                let head_req = *this.head_req;
+                Poll::Ready(Ok(resp.map_body(move |head, body| {
+                    if negotiated {
+                        vary(head);
+                    }
+                    if head_req {
+                        Encoder::head_response(enc, head, body)
+                    } else {
+                        Encoder::response_with_level(enc, level, head, body)
+                    }
+                })))
             }
             Err(e) => Poll::Ready(Err(e)),
         }
     }
 }
 
+/// Add `Vary: Accept-Encoding` header, response content depends
+/// on request's accept-encoding header
+fn vary(head: &mut ResponseHead) {
+    let exists = head.headers().get_all(VARY).any(|val| {
+        val.to_str()
+            .map(|val| {
+                val.split(',').any(|name| {
+                    let name = name.trim();
+                    name == "*" || name.eq_ignore_ascii_case("accept-encoding")
+                })
+            })
+            .unwrap_or(false)
+    });
+    if !exists {
+        head.headers_mut()
+            .append(VARY, HeaderValue::from_static("accept-encoding"));
+    }
+}
+
 struct AcceptEncoding {
     encoding: ContentEncoding,
     quality: f64,
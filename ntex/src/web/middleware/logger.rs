@@ -15,7 +15,7 @@ use regex::Regex;
 use time::OffsetDateTime;
 
 use crate::http::body::{Body, BodySize, MessageBody, ResponseBody};
-use crate::http::header::HeaderName;
+use crate::http::header::{HeaderMap, HeaderName};
 use crate::service::{Service, Transform};
 use crate::web::dev::{WebRequest, WebResponse};
 use crate::web::HttpResponse;
@@ -269,6 +269,10 @@ impl MessageBody for StreamLog {
             val => val,
         }
     }
+
+    fn trailers(&mut self) -> Option<HeaderMap> {
+        self.body.trailers()
+    }
 }
 
 /// A formatting style for the `Logger`, consisting of multiple
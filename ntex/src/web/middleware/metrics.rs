@@ -0,0 +1,416 @@
+//! Request metrics middleware
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures::future::{ok, ready, Ready};
+
+use crate::http::header::{HeaderValue, CONTENT_TYPE};
+use crate::http::{Method, StatusCode};
+use crate::service::{Service, Transform};
+use crate::web::dev::{WebRequest, WebResponse};
+use crate::web::HttpResponse;
+
+/// Default histogram buckets, in seconds
+const BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Route label for requests that did not match any resource
+const UNMATCHED: &str = "unmatched";
+
+/// Metrics sink
+///
+/// Sink receives request events from `Metrics` middleware.
+pub trait MetricsSink: 'static {
+    /// Request processing is started
+    fn request_started(&self) {}
+
+    /// Request processing is finished
+    ///
+    /// `route` is the matched resource pattern.
+    fn request_finished(
+        &self,
+        method: &Method,
+        route: &str,
+        status: StatusCode,
+        elapsed: Duration,
+    );
+}
+
+/// `Middleware` for collecting request metrics.
+///
+/// Middleware records request counters and latency histograms keyed by
+/// method, matched resource pattern and status class, and number of
+/// in-flight requests. Resource pattern is used instead of request path
+/// to bound number of time series, requests that did not match any
+/// resource are recorded with `unmatched` route.
+///
+/// `MetricsRegistry` stores metrics in memory and could be rendered in
+/// prometheus text format with `metrics_handler()`. Registry is thread
+/// safe and could be shared between server workers.
+///
+/// ```rust
+/// use ntex::web::{self, App};
+/// use ntex::web::middleware::{metrics_handler, Metrics, MetricsRegistry};
+///
+/// fn main() {
+///     let registry = MetricsRegistry::new();
+///
+///     let app = App::new()
+///         .wrap(Metrics::new(registry.clone()))
+///         .service(web::resource("/metrics").to(metrics_handler(registry)));
+/// }
+/// ```
+pub struct Metrics {
+    sink: Rc<dyn MetricsSink>,
+}
+
+impl Metrics {
+    /// Create `Metrics` middleware with specified sink.
+    pub fn new<T: MetricsSink>(sink: T) -> Metrics {
+        Metrics {
+            sink: Rc::new(sink),
+        }
+    }
+}
+
+impl<S, Err> Transform<S> for Metrics
+where
+    S: Service<Request = WebRequest<Err>, Response = WebResponse>,
+{
+    type Request = WebRequest<Err>;
+    type Response = WebResponse;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = MetricsMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(MetricsMiddleware {
+            service,
+            sink: self.sink.clone(),
+        })
+    }
+}
+
+/// Metrics middleware
+pub struct MetricsMiddleware<S> {
+    sink: Rc<dyn MetricsSink>,
+    service: S,
+}
+
+impl<S, E> Service for MetricsMiddleware<S>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Future = MetricsResponse<S>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    #[inline]
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        self.sink.request_started();
+
+        MetricsResponse {
+            method: req.method().clone(),
+            fut: self.service.call(req),
+            start: Instant::now(),
+            sink: self.sink.clone(),
+        }
+    }
+}
+
+pin_project_lite::pin_project! {
+    #[doc(hidden)]
+    pub struct MetricsResponse<S: Service>
+    {
+        #[pin]
+        fut: S::Future,
+        method: Method,
+        start: Instant,
+        sink: Rc<dyn MetricsSink>,
+    }
+}
+
+impl<S, E> Future for MetricsResponse<S>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse>,
+{
+    type Output = Result<WebResponse, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        let res = futures::ready!(this.fut.poll(cx));
+        let (route, status) = match res {
+            Ok(ref res) => (
+                res.request().match_pattern().unwrap_or(UNMATCHED),
+                res.status(),
+            ),
+            Err(_) => (UNMATCHED, StatusCode::INTERNAL_SERVER_ERROR),
+        };
+        this.sink
+            .request_finished(this.method, route, status, this.start.elapsed());
+        Poll::Ready(res)
+    }
+}
+
+/// In-memory metrics registry
+#[derive(Clone)]
+pub struct MetricsRegistry(Arc<RegistryInner>);
+
+struct RegistryInner {
+    buckets: Vec<f64>,
+    in_flight: AtomicI64,
+    series: Mutex<BTreeMap<(String, String, &'static str), Series>>,
+}
+
+struct Series {
+    count: u64,
+    sum: f64,
+    buckets: Vec<u64>,
+}
+
+impl MetricsRegistry {
+    /// Create registry with default histogram buckets.
+    pub fn new() -> Self {
+        MetricsRegistry::with_buckets(BUCKETS.to_vec())
+    }
+
+    /// Create registry with specified histogram buckets, in seconds.
+    pub fn with_buckets(mut buckets: Vec<f64>) -> Self {
+        buckets.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        MetricsRegistry(Arc::new(RegistryInner {
+            buckets,
+            in_flight: AtomicI64::new(0),
+            series: Mutex::new(BTreeMap::new()),
+        }))
+    }
+
+    /// Number of in-flight requests
+    pub fn in_flight(&self) -> i64 {
+        self.0.in_flight.load(Ordering::Relaxed)
+    }
+
+    /// Render metrics in prometheus text exposition format
+    pub fn render(&self) -> String {
+        let series = self.0.series.lock().unwrap();
+        let mut buf = String::new();
+
+        let _ = writeln!(
+            buf,
+            "# HELP http_requests_total Total number of http requests\n\
+             # TYPE http_requests_total counter"
+        );
+        for ((method, route, status), s) in series.iter() {
+            let _ = writeln!(
+                buf,
+                "http_requests_total{{{}}} {}",
+                Labels(method, route, status),
+                s.count
+            );
+        }
+
+        let _ = writeln!(
+            buf,
+            "# HELP http_request_duration_seconds Http request duration in seconds\n\
+             # TYPE http_request_duration_seconds histogram"
+        );
+        for ((method, route, status), s) in series.iter() {
+            let labels = Labels(method, route, status);
+            for (le, count) in self.0.buckets.iter().zip(s.buckets.iter()) {
+                let _ = writeln!(
+                    buf,
+                    "http_request_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                    labels, le, count
+                );
+            }
+            let _ = writeln!(
+                buf,
+                "http_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}\n\
+                 http_request_duration_seconds_sum{{{}}} {}\n\
+                 http_request_duration_seconds_count{{{}}} {}",
+                labels, s.count, labels, s.sum, labels, s.count
+            );
+        }
+
+        let _ = writeln!(
+            buf,
+            "# HELP http_requests_in_flight Number of in-flight http requests\n\
+             # TYPE http_requests_in_flight gauge\n\
+             http_requests_in_flight {}",
+            self.in_flight()
+        );
+        buf
+    }
+}
+
+impl Default for MetricsRegistry {
+    fn default() -> Self {
+        MetricsRegistry::new()
+    }
+}
+
+impl MetricsSink for MetricsRegistry {
+    fn request_started(&self) {
+        self.0.in_flight.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn request_finished(
+        &self,
+        method: &Method,
+        route: &str,
+        status: StatusCode,
+        elapsed: Duration,
+    ) {
+        self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+
+        let elapsed = elapsed.as_secs_f64();
+        let mut series = self.0.series.lock().unwrap();
+        let s = series
+            .entry((method.to_string(), route.to_string(), status_class(status)))
+            .or_insert_with(|| Series {
+                count: 0,
+                sum: 0.0,
+                buckets: vec![0; self.0.buckets.len()],
+            });
+        s.count += 1;
+        s.sum += elapsed;
+        for (le, count) in self.0.buckets.iter().zip(s.buckets.iter_mut()) {
+            if elapsed <= *le {
+                *count += 1;
+            }
+        }
+    }
+}
+
+/// Create handler that renders registry metrics in prometheus text format
+pub fn metrics_handler(
+    registry: MetricsRegistry,
+) -> impl Fn() -> Ready<HttpResponse> + Clone + 'static {
+    move || {
+        ready(
+            HttpResponse::Ok()
+                .header(
+                    CONTENT_TYPE,
+                    HeaderValue::from_static("text/plain; version=0.0.4"),
+                )
+                .body(registry.render()),
+        )
+    }
+}
+
+fn status_class(status: StatusCode) -> &'static str {
+    match status.as_u16() / 100 {
+        1 => "1xx",
+        2 => "2xx",
+        3 => "3xx",
+        4 => "4xx",
+        _ => "5xx",
+    }
+}
+
+/// Label set formatter
+struct Labels<'a>(&'a str, &'a str, &'a str);
+
+impl<'a> std::fmt::Display for Labels<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "method=\"")?;
+        escape(f, self.0)?;
+        write!(f, "\",route=\"")?;
+        escape(f, self.1)?;
+        write!(f, "\",status=\"{}\"", self.2)
+    }
+}
+
+fn escape(f: &mut std::fmt::Formatter<'_>, val: &str) -> std::fmt::Result {
+    for ch in val.chars() {
+        match ch {
+            '\\' => f.write_str("\\\\")?,
+            '"' => f.write_str("\\\"")?,
+            '\n' => f.write_str("\\n")?,
+            ch => f.write_char(ch)?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::test::{init_service, read_body, TestRequest};
+    use crate::web::{self, App};
+
+    #[ntex_rt::test]
+    async fn test_metrics() {
+        let registry = MetricsRegistry::with_buckets(vec![10.0, 0.5]);
+        let srv =
+            init_service(
+                App::new()
+                    .wrap(Metrics::new(registry.clone()))
+                    .service(web::scope("/users").service(
+                        web::resource("/{id}").to(|| async { HttpResponse::Ok() }),
+                    ))
+                    .service(
+                        web::resource("/metrics").to(metrics_handler(registry.clone())),
+                    ),
+            )
+            .await;
+
+        for uri in &["/users/1", "/users/2", "/unknown"] {
+            let req = TestRequest::with_uri(uri).to_request();
+            let _ = srv.call(req).await.unwrap();
+        }
+        assert_eq!(registry.in_flight(), 0);
+
+        let req = TestRequest::with_uri("/metrics").to_request();
+        let res = srv.call(req).await.unwrap();
+        assert_eq!(
+            res.headers().get(CONTENT_TYPE).unwrap(),
+            "text/plain; version=0.0.4"
+        );
+        let body = read_body(res).await;
+        let body = std::str::from_utf8(&body).unwrap();
+        assert!(body.contains(
+            "http_requests_total{method=\"GET\",route=\"/users/{id}\",status=\"2xx\"} 2\n"
+        ));
+        assert!(body.contains(
+            "http_requests_total{method=\"GET\",route=\"unmatched\",status=\"4xx\"} 1\n"
+        ));
+        assert!(body.contains(
+            "http_request_duration_seconds_bucket{method=\"GET\",route=\"/users/{id}\",status=\"2xx\",le=\"0.5\"} 2\n"
+        ));
+        assert!(body.contains(
+            "http_request_duration_seconds_count{method=\"GET\",route=\"/users/{id}\",status=\"2xx\"} 2\n"
+        ));
+        // metrics request itself is in flight
+        assert!(body.contains("http_requests_in_flight 1\n"));
+        assert!(body.find("le=\"0.5\"").unwrap() < body.find("le=\"10\"").unwrap());
+    }
+
+    #[test]
+    fn test_labels_escape() {
+        assert_eq!(
+            Labels("GET", "/a\"b\\c\n", "2xx").to_string(),
+            "method=\"GET\",route=\"/a\\\"b\\\\c\\n\",status=\"2xx\""
+        );
+    }
+}
@@ -10,3 +10,11 @@ pub use self::logger::Logger;
 
 mod defaultheaders;
 pub use self::defaultheaders::DefaultHeaders;
+
+mod metrics;
+pub use self::metrics::{metrics_handler, Metrics, MetricsRegistry, MetricsSink};
+
+#[cfg(feature = "tracing")]
+mod tracinglogger;
+#[cfg(feature = "tracing")]
+pub use self::tracinglogger::TracingLogger;
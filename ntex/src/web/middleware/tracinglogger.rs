@@ -0,0 +1,319 @@
+//! Request tracing middleware
+use std::collections::HashSet;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use futures::future::{ok, Either, Ready};
+use tracing::field::Empty;
+use tracing::Span;
+
+use crate::http::header::HeaderName;
+use crate::http::RequestHead;
+use crate::service::{Service, Transform};
+use crate::web::dev::{WebRequest, WebResponse};
+
+/// `Middleware` for creating tracing span for each request.
+///
+/// Service call and response future are executed inside of request span.
+/// `status` and `duration_ms` fields are recorded when response is ready,
+/// `request_id` field is recorded from configured request header.
+///
+/// Default span builder creates `web.request` span with `method`, `path`,
+/// `version`, `request_id`, `status` and `duration_ms` fields.
+///
+/// ```rust
+/// use ntex::http::RequestHead;
+/// use ntex::web::App;
+/// use ntex::web::middleware::TracingLogger;
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(TracingLogger::default().request_id("x-request-id"))
+///         .wrap(TracingLogger::new(|head: &RequestHead| {
+///             tracing::info_span!(
+///                 "request",
+///                 uri = %head.uri,
+///                 status = tracing::field::Empty,
+///             )
+///         }));
+/// }
+/// ```
+pub struct TracingLogger {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    builder: Box<dyn Fn(&RequestHead) -> Span>,
+    request_id: Option<HeaderName>,
+    exclude: HashSet<String>,
+}
+
+impl TracingLogger {
+    /// Create `TracingLogger` middleware with custom span builder.
+    pub fn new<F>(builder: F) -> TracingLogger
+    where
+        F: Fn(&RequestHead) -> Span + 'static,
+    {
+        TracingLogger {
+            inner: Rc::new(Inner {
+                builder: Box::new(builder),
+                request_id: None,
+                exclude: HashSet::new(),
+            }),
+        }
+    }
+
+    /// Record value of specified request header as `request_id` span field.
+    ///
+    /// Custom span must declare `request_id` field, otherwise value
+    /// is not recorded.
+    pub fn request_id(mut self, header: &'static str) -> Self {
+        Rc::get_mut(&mut self.inner).unwrap().request_id =
+            Some(HeaderName::from_static(header));
+        self
+    }
+
+    /// Do not create span for specified path.
+    pub fn exclude<T: Into<String>>(mut self, path: T) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .unwrap()
+            .exclude
+            .insert(path.into());
+        self
+    }
+}
+
+impl Default for TracingLogger {
+    /// Create `TracingLogger` middleware with default span builder.
+    fn default() -> Self {
+        TracingLogger::new(|head| {
+            tracing::info_span!(
+                "web.request",
+                method = %head.method,
+                path = %head.uri.path(),
+                version = ?head.version,
+                request_id = Empty,
+                status = Empty,
+                duration_ms = Empty,
+            )
+        })
+    }
+}
+
+impl<S, Err> Transform<S> for TracingLogger
+where
+    S: Service<Request = WebRequest<Err>, Response = WebResponse>,
+{
+    type Request = WebRequest<Err>;
+    type Response = WebResponse;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = TracingLoggerMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(TracingLoggerMiddleware {
+            service,
+            inner: self.inner.clone(),
+        })
+    }
+}
+
+/// Tracing logger middleware
+pub struct TracingLoggerMiddleware<S> {
+    inner: Rc<Inner>,
+    service: S,
+}
+
+impl<S, E> Service for TracingLoggerMiddleware<S>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Future = Either<TracingLoggerResponse<S>, S::Future>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    #[inline]
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        if self.inner.exclude.contains(req.path()) {
+            Either::Right(self.service.call(req))
+        } else {
+            let span = (*self.inner.builder)(req.head());
+            if let Some(ref name) = self.inner.request_id {
+                if let Some(val) = req.headers().get(name).and_then(|v| v.to_str().ok())
+                {
+                    span.record("request_id", val);
+                }
+            }
+
+            let fut = {
+                let _entered = span.enter();
+                self.service.call(req)
+            };
+            Either::Left(TracingLoggerResponse {
+                fut,
+                span,
+                start: Instant::now(),
+            })
+        }
+    }
+}
+
+pin_project_lite::pin_project! {
+    #[doc(hidden)]
+    pub struct TracingLoggerResponse<S: Service>
+    {
+        #[pin]
+        fut: S::Future,
+        span: Span,
+        start: Instant,
+    }
+}
+
+impl<S, E> Future for TracingLoggerResponse<S>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse>,
+{
+    type Output = Result<WebResponse, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let _entered = this.span.enter();
+
+        let res = futures::ready!(this.fut.poll(cx));
+        if let Ok(ref res) = res {
+            this.span.record("status", res.status().as_u16());
+        }
+        this.span
+            .record("duration_ms", this.start.elapsed().as_millis() as u64);
+        Poll::Ready(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use futures::future::{lazy, ok};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    use super::*;
+    use crate::http::{header, StatusCode};
+    use crate::service::{IntoService, Service, Transform};
+    use crate::web::test::TestRequest;
+    use crate::web::{DefaultError, Error, HttpResponse};
+
+    #[derive(Default)]
+    struct TestSubscriber {
+        fields: Arc<Mutex<Vec<String>>>,
+        next: AtomicU64,
+    }
+
+    struct Visitor<'a>(&'a Mutex<Vec<String>>);
+
+    impl<'a> Visit for Visitor<'a> {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("{}={:?}", field.name(), value));
+        }
+    }
+
+    impl Subscriber for TestSubscriber {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            self.fields
+                .lock()
+                .unwrap()
+                .push(span.metadata().name().to_string());
+            span.record(&mut Visitor(&self.fields));
+            Id::from_u64(self.next.fetch_add(1, Ordering::Relaxed) + 1)
+        }
+
+        fn record(&self, _: &Id, values: &Record<'_>) {
+            values.record(&mut Visitor(&self.fields));
+        }
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, _: &Event<'_>) {}
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[ntex_rt::test]
+    async fn test_tracing_logger() {
+        let subscriber = TestSubscriber::default();
+        let fields = subscriber.fields.clone();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let srv = |req: WebRequest<DefaultError>| {
+            ok::<_, Error>(
+                req.into_response(HttpResponse::build(StatusCode::OK).finish()),
+            )
+        };
+        let logger = TracingLogger::default()
+            .request_id("x-request-id")
+            .exclude("/excluded");
+        let srv = Transform::new_transform(&logger, srv.into_service())
+            .await
+            .unwrap();
+
+        assert!(lazy(|cx| srv.poll_ready(cx).is_ready()).await);
+        assert!(lazy(|cx| srv.poll_shutdown(cx, true).is_ready()).await);
+
+        let req = TestRequest::with_header(
+            header::HeaderName::from_static("x-request-id"),
+            header::HeaderValue::from_static("req-1"),
+        )
+        .uri("/test")
+        .to_srv_request();
+        let res = srv.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        {
+            let fields = fields.lock().unwrap();
+            assert_eq!(
+                &fields[..5],
+                &[
+                    "web.request",
+                    "method=GET",
+                    "path=/test",
+                    "version=HTTP/1.1",
+                    "request_id=\"req-1\""
+                ]
+            );
+            assert_eq!(fields[5], "status=200");
+            assert!(fields[6].starts_with("duration_ms="));
+        }
+
+        // excluded path
+        fields.lock().unwrap().clear();
+        let req = TestRequest::with_uri("/excluded").to_srv_request();
+        let _ = srv.call(req).await.unwrap();
+        assert!(fields.lock().unwrap().is_empty());
+    }
+}
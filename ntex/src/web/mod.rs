@@ -72,6 +72,8 @@ pub mod guard;
 mod handler;
 mod httprequest;
 mod info;
+pub mod json_stream;
+mod longpoll;
 pub mod middleware;
 mod request;
 mod resource;
@@ -102,7 +104,7 @@ pub use crate::http::Response as HttpResponse;
 pub use crate::http::ResponseBuilder as HttpResponseBuilder;
 pub use either::Either;
 
-pub use self::app::App;
+pub use self::app::{App, BoxedAppFactory};
 pub use self::config::ServiceConfig;
 pub use self::error::{
     DefaultError, Error, ErrorContainer, ErrorRenderer, WebResponseError,
@@ -110,6 +112,7 @@ pub use self::error::{
 pub use self::extract::FromRequest;
 pub use self::handler::Handler;
 pub use self::httprequest::HttpRequest;
+pub use self::longpoll::{long_poll, LongPoll};
 pub use self::resource::Resource;
 pub use self::responder::Responder;
 pub use self::route::Route;
@@ -4,8 +4,8 @@ use std::rc::Rc;
 use std::{fmt, net};
 
 use crate::http::{
-    header, Extensions, HeaderMap, HttpMessage, Method, Payload, PayloadStream,
-    RequestHead, Response, Uri, Version,
+    header, Extensions, HeaderMap, HttpMessage, Method, Payload, PayloadCopy,
+    PayloadStream, RequestHead, Response, Uri, Version,
 };
 use crate::router::{Path, Resource};
 
@@ -181,6 +181,35 @@ impl<Err> WebRequest<Err> {
         self.req.match_info_mut()
     }
 
+    #[inline]
+    /// Resource pattern that matched the request.
+    pub fn match_pattern(&self) -> Option<&str> {
+        self.req.match_pattern()
+    }
+
+    #[inline]
+    /// Name of the resource that matched the request.
+    pub fn match_name(&self) -> Option<&str> {
+        self.req.match_name()
+    }
+
+    #[inline]
+    /// Check if request is handled by default service.
+    pub fn is_default_match(&self) -> bool {
+        self.req.is_default_match()
+    }
+
+    #[inline]
+    pub(crate) fn push_match(&mut self, pattern: &str, name: &Option<Rc<str>>) {
+        let inner = Rc::get_mut(&mut (self.req).0).unwrap();
+        inner.pattern.push_str(pattern);
+        inner.match_name = name.clone();
+    }
+
+    #[inline]
+    pub(crate) fn set_default_match(&mut self) {
+        Rc::get_mut(&mut (self.req).0).unwrap().match_default = true;
+    }
     #[inline]
     /// Get a reference to a `ResourceMap` of current application.
     pub fn resource_map(&self) -> &ResourceMap {
@@ -214,6 +243,17 @@ impl<Err> WebRequest<Err> {
         Rc::get_mut(&mut (self.req).0).unwrap().payload = payload;
     }
 
+    /// Copy request payload while it is consumed by the handler
+    ///
+    /// Up to `limit` bytes of payload get copied, copy is also stored
+    /// in request extensions and is available after handler completes.
+    pub fn tee_payload(&mut self, limit: usize) -> PayloadCopy {
+        let (payload, copy) = self.take_payload().tee(limit);
+        self.set_payload(payload);
+        self.extensions_mut().insert(copy.clone());
+        copy
+    }
+
     #[doc(hidden)]
     /// Set new app data container
     pub fn set_data_container(&mut self, extensions: Rc<Extensions>) {
@@ -326,4 +366,45 @@ mod tests {
         req.message_extensions_mut().remove::<String>();
         assert!(!req.extensions().contains::<String>());
     }
+
+    #[ntex_rt::test]
+    async fn test_tee_payload() {
+        use crate::web::test::{call_service, init_service, read_body};
+        use crate::web::{self, App};
+        use crate::Service;
+
+        let srv = init_service(
+            App::new()
+                .wrap_fn(|mut req, srv| {
+                    req.tee_payload(5);
+                    let fut = srv.call(req);
+                    async move {
+                        let mut res = fut.await?;
+                        let copy = res
+                            .request()
+                            .extensions()
+                            .get::<http::PayloadCopy>()
+                            .unwrap()
+                            .clone();
+                        assert!(copy.is_eof());
+                        assert!(copy.is_truncated());
+                        res.headers_mut().insert(
+                            header::HeaderName::from_static("x-body"),
+                            header::HeaderValue::from_maybe_shared(copy.bytes())
+                                .unwrap(),
+                        );
+                        Ok(res)
+                    }
+                })
+                .service(web::resource("/test").to(|body: String| async move { body })),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/test")
+            .set_payload("hello world")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.headers().get("x-body").unwrap(), "hello");
+        assert_eq!(read_body(resp).await, "hello world");
+    }
 }
@@ -9,7 +9,7 @@ use futures::future::{ok, Either, LocalBoxFuture, Ready};
 
 use crate::http::{Extensions, Response};
 use crate::router::{IntoPattern, ResourceDef};
-use crate::service::boxed::{self, BoxService, BoxServiceFactory};
+use crate::service::boxed::{self, BoxServiceFactory, LocalBoxService};
 use crate::service::{
     apply, apply_fn_factory, IntoServiceFactory, Service, ServiceFactory, Transform,
 };
@@ -26,7 +26,7 @@ use super::route::{IntoRoutes, Route, RouteService};
 use super::types::Data;
 
 type HttpService<Err: ErrorRenderer> =
-    BoxService<WebRequest<Err>, WebResponse, Err::Container>;
+    LocalBoxService<WebRequest<Err>, WebResponse, Err::Container>;
 type HttpNewService<Err: ErrorRenderer> =
     BoxServiceFactory<(), WebRequest<Err>, WebResponse, Err::Container, ()>;
 
@@ -287,7 +287,7 @@ mod tests {
     use crate::http::{Method, StatusCode};
     use crate::rt::time::delay_for;
     use crate::web::test::{call_service, init_service, read_body, TestRequest};
-    use crate::web::{self, error, App, DefaultError, HttpResponse};
+    use crate::web::{self, error, App, DefaultError, HttpRequest, HttpResponse};
 
     #[derive(Serialize, PartialEq, Debug)]
     struct MyObject {
@@ -360,4 +360,32 @@ mod tests {
         let body = read_body(resp).await;
         assert_eq!(body, Bytes::from_static(b"{\"name\":\"test\"}"));
     }
+
+    #[ntex_rt::test]
+    async fn test_route_panic() {
+        let srv = init_service(App::new().service(web::resource("/{name}").route(
+            web::get().to(|req: HttpRequest| {
+                if req.path() == "/call" {
+                    panic!("call");
+                }
+                async move {
+                    if req.path() == "/poll" {
+                        panic!("poll");
+                    }
+                    HttpResponse::Ok()
+                }
+            }),
+        )))
+        .await;
+
+        for path in &["/call", "/poll"] {
+            let req = TestRequest::with_uri(path).to_request();
+            let resp = call_service(&srv, req).await;
+            assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        }
+
+        let req = TestRequest::with_uri("/ok").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
 }
@@ -8,7 +8,7 @@ use futures::future::{ok, Either, Future, LocalBoxFuture, Ready};
 
 use crate::http::{Extensions, Response};
 use crate::router::{ResourceDef, ResourceInfo, Router};
-use crate::service::boxed::{self, BoxService, BoxServiceFactory};
+use crate::service::boxed::{self, BoxServiceFactory, LocalBoxService};
 use crate::service::{
     apply, apply_fn_factory, IntoServiceFactory, Service, ServiceFactory, Transform,
 };
@@ -27,7 +27,7 @@ use super::types::Data;
 
 type Guards = Vec<Box<dyn Guard>>;
 type HttpService<Err: ErrorRenderer> =
-    BoxService<WebRequest<Err>, WebResponse, Err::Container>;
+    LocalBoxService<WebRequest<Err>, WebResponse, Err::Container>;
 type HttpNewService<Err: ErrorRenderer> =
     BoxServiceFactory<(), WebRequest<Err>, WebResponse, Err::Container, ()>;
 type BoxedResponse<Err: ErrorRenderer> =
@@ -589,7 +589,13 @@ impl<Err: ErrorRenderer> Future for ScopeFactoryResponse<Err> {
                 .fold(Router::build(), |mut router, item| {
                     match item {
                         CreateScopeServiceItem::Service(path, guards, service) => {
-                            router.rdef(path, service).2 = guards;
+                            let pattern = Rc::from(path.pattern());
+                            let name = if path.name().is_empty() {
+                                None
+                            } else {
+                                Some(Rc::from(path.name()))
+                            };
+                            router.rdef(path, (service, pattern, name)).2 = guards;
                         }
                         CreateScopeServiceItem::Future(_, _, _) => unreachable!(),
                     }
@@ -609,7 +615,7 @@ impl<Err: ErrorRenderer> Future for ScopeFactoryResponse<Err> {
 
 pub struct ScopeService<Err: ErrorRenderer> {
     data: Option<Rc<Extensions>>,
-    router: Router<HttpService<Err>, Vec<Box<dyn Guard>>>,
+    router: Router<(HttpService<Err>, Rc<str>, Option<Rc<str>>), Vec<Box<dyn Guard>>>,
     default: Option<HttpService<Err>>,
     _ready: Option<(WebRequest<Err>, ResourceInfo)>,
 }
@@ -637,12 +643,14 @@ impl<Err: ErrorRenderer> Service for ScopeService<Err> {
             true
         });
 
-        if let Some((srv, _info)) = res {
+        if let Some(((srv, pattern, name), _info)) = res {
+            req.push_match(pattern, name);
             if let Some(ref data) = self.data {
                 req.set_data_container(data.clone());
             }
             Either::Left(srv.call(req))
         } else if let Some(ref default) = self.default {
+            req.set_default_match();
             Either::Left(default.call(req))
         } else {
             let req = req.into_parts().0;
@@ -710,6 +718,75 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::NOT_FOUND);
     }
 
+    #[ntex_rt::test]
+    async fn test_scope_match_pattern() {
+        let srv = init_service(
+            App::new()
+                .service(web::scope("/app").service(web::resource("/{id}").to(
+                    |req: HttpRequest| async move {
+                        HttpResponse::Ok().body(req.match_pattern().unwrap().to_string())
+                    },
+                )))
+                .default_service(|req: WebRequest<DefaultError>| {
+                    assert!(req.match_pattern().is_none());
+                    ok(req.into_response(HttpResponse::NotFound()))
+                }),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/app/10").to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"/app/{id}"));
+
+        let req = TestRequest::with_uri("/unknown").to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[ntex_rt::test]
+    async fn test_scope_match_name() {
+        let srv = init_service(
+            App::new().service(
+                web::scope("/app")
+                    .service(
+                        web::resource("/{id}")
+                            .name("item")
+                            .wrap_fn(|req, srv| {
+                                assert_eq!(req.match_pattern(), Some("/app/{id}"));
+                                assert_eq!(req.match_name(), Some("item"));
+                                assert_eq!(req.match_info().get("id"), Some("10"));
+                                assert!(!req.is_default_match());
+                                srv.call(req)
+                            })
+                            .to(|| async { HttpResponse::Ok() }),
+                    )
+                    .service(web::resource("/a/b").to(|req: HttpRequest| async move {
+                        assert!(req.match_name().is_none());
+                        HttpResponse::Ok()
+                    }))
+                    .default_service(|req: WebRequest<DefaultError>| {
+                        assert!(req.is_default_match());
+                        assert!(req.match_pattern().is_none());
+                        assert!(req.match_name().is_none());
+                        ok(req.into_response(HttpResponse::NotFound()))
+                    }),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/app/10").to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/app/a/b").to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/app/a/b/c").to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
     #[ntex_rt::test]
     async fn test_scope_root() {
         let srv = init_service(
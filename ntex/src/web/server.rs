@@ -27,6 +27,7 @@ struct Config {
     client_timeout: u64,
     client_disconnect: u64,
     handshake_timeout: u64,
+    catch_panic: bool,
 }
 
 /// An HTTP Server.
@@ -85,6 +86,7 @@ where
                 client_timeout: 5000,
                 client_disconnect: 5000,
                 handshake_timeout: 5000,
+                catch_panic: true,
             })),
             backlog: 1024,
             builder: ServerBuilder::default(),
@@ -95,7 +97,7 @@ where
     /// Set number of workers to start.
     ///
     /// By default http server uses number of available logical cpu as threads
-    /// count.
+    /// count. Zero value is not allowed, one worker is started instead.
     pub fn workers(mut self, num: usize) -> Self {
         self.builder = self.builder.workers(num);
         self
@@ -109,6 +111,7 @@ where
     /// load.
     ///
     /// Generally set in the 64-2048 range. Default value is 2048.
+    /// Operating system caps the value, see `ServerBuilder::backlog()`.
     ///
     /// This method should be called before `bind()` method call.
     pub fn backlog(mut self, backlog: i32) -> Self {
@@ -117,6 +120,14 @@ where
         self
     }
 
+    /// Set the maximum number of connections accepted per listener wakeup.
+    ///
+    /// Zero value disables the limit. By default the limit is disabled.
+    pub fn accept_batch(mut self, num: usize) -> Self {
+        self.builder = self.builder.accept_batch(num);
+        self
+    }
+
     /// Sets the maximum per-worker number of concurrent connections.
     ///
     /// All socket listeners will stop accepting connections when this limit is reached
@@ -141,6 +152,8 @@ where
 
     /// Set server keep-alive setting.
     ///
+    /// To disable keep-alive set value to 0.
+    ///
     /// By default keep alive is set to a 5 seconds.
     pub fn keep_alive<T: Into<KeepAlive>>(self, val: T) -> Self {
         self.config.lock().unwrap().keep_alive = val.into();
@@ -177,7 +190,8 @@ where
     /// Set server ssl handshake timeout in milliseconds.
     ///
     /// Defines a timeout for connection ssl handshake negotiation.
-    /// To disable timeout set value to 0.
+    /// Timeout covers whole handshake, including client certificate
+    /// verification callbacks. To disable timeout set value to 0.
     ///
     /// By default handshake timeout is set to 5 seconds.
     pub fn ssl_handshake_timeout(self, val: u64) -> Self {
@@ -185,6 +199,18 @@ where
         self
     }
 
+    /// Capture panics in handlers and services.
+    ///
+    /// Panic is logged with request path and converted to
+    /// `500 Internal Server Error` response, connection is kept alive.
+    /// Set to `false` to propagate panics and drop connection.
+    ///
+    /// By default panics are captured.
+    pub fn catch_panic(self, val: bool) -> Self {
+        self.config.lock().unwrap().catch_panic = val;
+        self
+    }
+
     /// Set server host name.
     ///
     /// Host name is used by application router as a hostname for url generation.
@@ -239,10 +265,12 @@ where
                     false,
                     addr,
                     c.host.clone().unwrap_or_else(|| format!("{}", addr)),
-                );
+                )
+                .set_catch_panic(c.catch_panic);
 
                 HttpService::build()
                     .keep_alive(c.keep_alive)
+                    .catch_panic(c.catch_panic)
                     .client_timeout(c.client_timeout)
                     .disconnect_timeout(c.client_disconnect)
                     .finish(map_config(factory(), move |_| cfg.clone()))
@@ -283,9 +311,11 @@ where
                     true,
                     addr,
                     c.host.clone().unwrap_or_else(|| format!("{}", addr)),
-                );
+                )
+                .set_catch_panic(c.catch_panic);
                 HttpService::build()
                     .keep_alive(c.keep_alive)
+                    .catch_panic(c.catch_panic)
                     .client_timeout(c.client_timeout)
                     .disconnect_timeout(c.client_disconnect)
                     .ssl_handshake_timeout(c.handshake_timeout)
@@ -327,9 +357,11 @@ where
                     true,
                     addr,
                     c.host.clone().unwrap_or_else(|| format!("{}", addr)),
-                );
+                )
+                .set_catch_panic(c.catch_panic);
                 HttpService::build()
                     .keep_alive(c.keep_alive)
+                    .catch_panic(c.catch_panic)
                     .client_timeout(c.client_timeout)
                     .disconnect_timeout(c.client_disconnect)
                     .ssl_handshake_timeout(c.handshake_timeout)
@@ -447,10 +479,12 @@ where
                 false,
                 socket_addr,
                 c.host.clone().unwrap_or_else(|| format!("{}", socket_addr)),
-            );
+            )
+            .set_catch_panic(c.catch_panic);
             pipeline_factory(|io: UnixStream| ok((io, Protocol::Http1, None))).and_then(
                 HttpService::build()
                     .keep_alive(c.keep_alive)
+                    .catch_panic(c.catch_panic)
                     .client_timeout(c.client_timeout)
                     .finish(map_config(factory(), move |_| config.clone())),
             )
@@ -484,11 +518,13 @@ where
                     false,
                     socket_addr,
                     c.host.clone().unwrap_or_else(|| format!("{}", socket_addr)),
-                );
+                )
+                .set_catch_panic(c.catch_panic);
                 pipeline_factory(|io: UnixStream| ok((io, Protocol::Http1, None)))
                     .and_then(
                         HttpService::build()
                             .keep_alive(c.keep_alive)
+                            .catch_panic(c.catch_panic)
                             .client_timeout(c.client_timeout)
                             .finish(map_config(factory(), move |_| config.clone())),
                     )
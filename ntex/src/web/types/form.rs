@@ -285,6 +285,10 @@ impl<U> UrlEncoded<U> {
     /// Change max size of payload. By default max size is 256Kb
     fn limit(mut self, limit: usize) -> Self {
         self.limit = limit;
+        #[cfg(feature = "compress")]
+        {
+            self.stream = self.stream.take().map(|s| s.limit(limit));
+        }
         self
     }
 }
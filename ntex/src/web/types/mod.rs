@@ -3,6 +3,7 @@
 pub(in crate::web) mod data;
 pub(in crate::web) mod form;
 pub(in crate::web) mod json;
+mod negotiate;
 mod path;
 pub(in crate::web) mod payload;
 mod query;
@@ -10,6 +11,7 @@ mod query;
 pub use self::data::Data;
 pub use self::form::{Form, FormConfig};
 pub use self::json::{Json, JsonConfig};
+pub use self::negotiate::{Encoder, NegotiateConfig, Negotiated};
 pub use self::path::Path;
-pub use self::payload::{Payload, PayloadConfig};
+pub use self::payload::{Lines, Payload, PayloadConfig};
 pub use self::query::Query;
@@ -0,0 +1,456 @@
+//! Content negotiation responder
+use std::sync::Arc;
+use std::{fmt, io, ops};
+
+use bytes::BytesMut;
+use futures::future::{ready, Ready};
+use serde::Serialize;
+
+use crate::http::header::{HeaderValue, ACCEPT, CONTENT_TYPE};
+use crate::http::helpers::Writer;
+use crate::http::{Response, StatusCode};
+use crate::web::error::{ErrorContainer, ErrorRenderer, SerializeError};
+use crate::web::{HttpRequest, Responder};
+
+/// Response serializer, writes serialized value to the writer
+pub type Encoder = Arc<
+    dyn Fn(
+            &dyn erased_serde::Serialize,
+            &mut dyn io::Write,
+        ) -> Result<(), SerializeError>
+        + Send
+        + Sync,
+>;
+
+/// Negotiated responder
+///
+/// Response representation is selected according to request's `Accept`
+/// header. Json is supported by default, MessagePack is supported
+/// with `msgpack` feature and CBOR with `cbor` feature.
+///
+/// [**NegotiateConfig**](struct.NegotiateConfig.html) allows to register
+/// custom representations.
+///
+/// If client does not accept any of supported representations,
+/// value is serialized with first registered representation (json by default)
+/// and `406 Not Acceptable` status is used.
+///
+/// ```rust
+/// use ntex::web;
+/// use serde_derive::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct MyObj {
+///     name: String,
+/// }
+///
+/// async fn index() -> web::types::Negotiated<MyObj> {
+///     web::types::Negotiated(MyObj {
+///         name: "name".to_string(),
+///     })
+/// }
+/// # fn main() {}
+/// ```
+pub struct Negotiated<T>(pub T);
+
+impl<T> Negotiated<T> {
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> ops::Deref for Negotiated<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> ops::DerefMut for Negotiated<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Negotiated<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Negotiated: {:?}", self.0)
+    }
+}
+
+impl<T: Serialize, Err: ErrorRenderer> Responder<Err> for Negotiated<T>
+where
+    Err::Container: From<SerializeError>,
+{
+    type Error = SerializeError;
+    type Future = Ready<Response>;
+
+    fn respond_to(self, req: &HttpRequest) -> Self::Future {
+        let res = if let Some(cfg) = req.app_data::<NegotiateConfig>() {
+            cfg.respond(req, &self.0)
+        } else {
+            DEFAULT_CONFIG.with(|cfg| cfg.respond(req, &self.0))
+        };
+
+        ready(match res {
+            Ok(res) => res,
+            Err(e) => Err::Container::from(e).error_response(req),
+        })
+    }
+}
+
+thread_local! {
+    static DEFAULT_CONFIG: NegotiateConfig = NegotiateConfig::default();
+}
+
+/// Negotiated responder configuration
+///
+/// Representations are selected in order of registration if client
+/// accepts multiple representations with the same quality.
+///
+/// ```rust
+/// use ntex::web::{error::SerializeError, types::NegotiateConfig, App};
+///
+/// fn main() {
+///     let app = App::new().app_data(
+///         NegotiateConfig::default().encoder("text/plain", |value, writer| {
+///             serde_json::to_writer_pretty(writer, &value)
+///                 .map_err(SerializeError::new)
+///         }),
+///     );
+/// }
+/// ```
+#[derive(Clone)]
+pub struct NegotiateConfig {
+    encoders: Vec<(mime::Mime, HeaderValue, Encoder)>,
+}
+
+impl NegotiateConfig {
+    /// Create configuration without registered representations
+    pub fn empty() -> Self {
+        NegotiateConfig {
+            encoders: Vec::new(),
+        }
+    }
+
+    /// Register representation encoder
+    ///
+    /// Encoder replaces previously registered encoder for the same mime type.
+    ///
+    /// Panics if `mime` is not a valid mime type.
+    pub fn encoder<F>(mut self, mime: &str, f: F) -> Self
+    where
+        F: Fn(
+                &dyn erased_serde::Serialize,
+                &mut dyn io::Write,
+            ) -> Result<(), SerializeError>
+            + Send
+            + Sync
+            + 'static,
+    {
+        let mime: mime::Mime = mime.parse().expect("Invalid mime type");
+        let value = HeaderValue::from_str(mime.as_ref()).unwrap();
+        let encoder: Encoder = Arc::new(f);
+
+        if let Some(item) = self.encoders.iter_mut().find(|item| item.0 == mime) {
+            item.1 = value;
+            item.2 = encoder;
+        } else {
+            self.encoders.push((mime, value, encoder));
+        }
+        self
+    }
+
+    /// Select encoder for request, returns encoder index and
+    /// acceptable flag
+    fn select(&self, req: &HttpRequest) -> (usize, bool) {
+        let accept = req
+            .headers()
+            .get_all(ACCEPT)
+            .filter_map(|val| val.to_str().ok())
+            .flat_map(|val| val.split(','))
+            .filter_map(MediaRange::parse)
+            .collect::<Vec<_>>();
+
+        if accept.is_empty() {
+            return (0, true);
+        }
+
+        let mut selected = None;
+        let mut quality = 0.0;
+        for (idx, (mime, _, _)) in self.encoders.iter().enumerate() {
+            let q = accept
+                .iter()
+                .filter_map(|range| range.matches(mime).map(|s| (s, range.quality)))
+                .max_by_key(|(specificity, _)| *specificity)
+                .map(|(_, q)| q)
+                .unwrap_or(0.0);
+            if q > quality {
+                selected = Some(idx);
+                quality = q;
+            }
+        }
+        match selected {
+            Some(idx) => (idx, true),
+            None => (0, false),
+        }
+    }
+
+    fn respond<T: Serialize>(
+        &self,
+        req: &HttpRequest,
+        value: &T,
+    ) -> Result<Response, SerializeError> {
+        if self.encoders.is_empty() {
+            return Err(SerializeError::new(io::Error::new(
+                io::ErrorKind::Other,
+                "No response encoders are registered",
+            )));
+        }
+        let (idx, acceptable) = self.select(req);
+        let (_, ctype, encoder) = &self.encoders[idx];
+
+        let mut buf = BytesMut::new();
+        (encoder)(value, &mut Writer(&mut buf))?;
+
+        let status = if acceptable {
+            StatusCode::OK
+        } else {
+            StatusCode::NOT_ACCEPTABLE
+        };
+        Ok(Response::build(status)
+            .header(CONTENT_TYPE, ctype.clone())
+            .body(buf))
+    }
+}
+
+impl Default for NegotiateConfig {
+    fn default() -> Self {
+        let cfg =
+            NegotiateConfig::empty().encoder("application/json", |value, writer| {
+                serde_json::to_writer(writer, &value).map_err(SerializeError::new)
+            });
+
+        #[cfg(feature = "msgpack")]
+        let cfg = cfg.encoder("application/msgpack", |value, writer| {
+            let mut writer = writer;
+            rmp_serde::encode::write_named(&mut writer, &value)
+                .map_err(SerializeError::new)
+        });
+
+        #[cfg(feature = "cbor")]
+        let cfg = cfg.encoder("application/cbor", |value, writer| {
+            serde_cbor::to_writer(writer, &value).map_err(SerializeError::new)
+        });
+
+        cfg
+    }
+}
+
+/// Accept header media range
+struct MediaRange<'a> {
+    type_: &'a str,
+    subtype: &'a str,
+    quality: f32,
+}
+
+impl<'a> MediaRange<'a> {
+    fn parse(s: &'a str) -> Option<Self> {
+        let mut parts = s.split(';');
+        let mut range = parts.next()?.trim().splitn(2, '/');
+        let type_ = range.next()?.trim();
+        let subtype = range.next()?.trim();
+        if type_.is_empty() || subtype.is_empty() || (type_ == "*" && subtype != "*") {
+            return None;
+        }
+
+        let mut quality = 1.0;
+        for param in parts {
+            let mut param = param.splitn(2, '=');
+            if param.next()?.trim().eq_ignore_ascii_case("q") {
+                quality = param.next()?.trim().parse().ok()?;
+                if !(0.0..=1.0).contains(&quality) {
+                    return None;
+                }
+            }
+        }
+        Some(MediaRange {
+            type_,
+            subtype,
+            quality,
+        })
+    }
+
+    /// Check if range matches mime type, returns specificity of the range
+    fn matches(&self, mime: &mime::Mime) -> Option<u8> {
+        if self.type_ == "*" {
+            Some(0)
+        } else if !self.type_.eq_ignore_ascii_case(mime.type_().as_str()) {
+            None
+        } else if self.subtype == "*" {
+            Some(1)
+        } else if self.subtype.eq_ignore_ascii_case(mime.subtype().as_str()) {
+            Some(2)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::header;
+    use crate::web::test::{respond_to, TestRequest};
+
+    #[derive(Serialize)]
+    struct MyObject {
+        name: String,
+    }
+
+    fn object() -> Negotiated<MyObject> {
+        Negotiated(MyObject {
+            name: "test".to_string(),
+        })
+    }
+
+    fn ctype(res: &Response) -> &str {
+        res.headers().get(CONTENT_TYPE).unwrap().to_str().unwrap()
+    }
+
+    // json only, independent of enabled features
+    fn json_config() -> NegotiateConfig {
+        NegotiateConfig::empty().encoder("application/json", |value, writer| {
+            serde_json::to_writer(writer, &value).map_err(SerializeError::new)
+        })
+    }
+
+    #[ntex_rt::test]
+    async fn test_default() {
+        let req = TestRequest::default().to_http_request();
+        let res = respond_to(object(), &req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(ctype(&res), "application/json");
+        assert_eq!(res.body().get_ref(), b"{\"name\":\"test\"}");
+
+        let req = TestRequest::default()
+            .header(header::ACCEPT, "text/html, */*;q=0.1")
+            .to_http_request();
+        let res = respond_to(object(), &req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(ctype(&res), "application/json");
+    }
+
+    #[ntex_rt::test]
+    async fn test_not_acceptable() {
+        let req = TestRequest::default()
+            .header(header::ACCEPT, "text/html")
+            .to_http_request();
+        let res = respond_to(object(), &req).await;
+        assert_eq!(res.status(), StatusCode::NOT_ACCEPTABLE);
+        assert_eq!(ctype(&res), "application/json");
+
+        let req = TestRequest::default()
+            .header(header::ACCEPT, "*/*;q=0.5, application/json;q=0")
+            .data(json_config())
+            .to_http_request();
+        let res = respond_to(object(), &req).await;
+        assert_eq!(res.status(), StatusCode::NOT_ACCEPTABLE);
+    }
+
+    #[ntex_rt::test]
+    async fn test_custom_encoder() {
+        let cfg = json_config().encoder("text/plain", |_, writer| {
+            writer.write_all(b"custom").map_err(SerializeError::new)
+        });
+
+        let req = TestRequest::default()
+            .header(header::ACCEPT, "application/json;q=0.5, text/*")
+            .data(cfg.clone())
+            .to_http_request();
+        let res = respond_to(object(), &req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(ctype(&res), "text/plain");
+        assert_eq!(res.body().get_ref(), b"custom");
+
+        // equal quality, registration order
+        let req = TestRequest::default()
+            .header(header::ACCEPT, "text/plain, application/json")
+            .data(cfg.clone())
+            .to_http_request();
+        let res = respond_to(object(), &req).await;
+        assert_eq!(ctype(&res), "application/json");
+
+        // more specific range wins
+        let req = TestRequest::default()
+            .header(header::ACCEPT, "*/*, application/json;q=0.1")
+            .data(cfg)
+            .to_http_request();
+        let res = respond_to(object(), &req).await;
+        assert_eq!(ctype(&res), "text/plain");
+
+        // encoder error
+        let cfg = NegotiateConfig::empty().encoder("text/plain", |_, _| {
+            Err(SerializeError::new(io::Error::new(
+                io::ErrorKind::Other,
+                "err",
+            )))
+        });
+        let req = TestRequest::default().data(cfg).to_http_request();
+        let res = respond_to(object(), &req).await;
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let req = TestRequest::default()
+            .data(NegotiateConfig::empty())
+            .to_http_request();
+        let res = respond_to(object(), &req).await;
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[ntex_rt::test]
+    async fn test_msgpack() {
+        let req = TestRequest::default()
+            .header(header::ACCEPT, "application/msgpack")
+            .to_http_request();
+        let res = respond_to(object(), &req).await;
+        assert_eq!(ctype(&res), "application/msgpack");
+        let body = res.body().get_ref();
+        let val: std::collections::HashMap<String, String> =
+            rmp_serde::from_read_ref(body).unwrap();
+        assert_eq!(val["name"], "test");
+    }
+
+    #[cfg(feature = "cbor")]
+    #[ntex_rt::test]
+    async fn test_cbor() {
+        let req = TestRequest::default()
+            .header(header::ACCEPT, "application/cbor")
+            .to_http_request();
+        let res = respond_to(object(), &req).await;
+        assert_eq!(ctype(&res), "application/cbor");
+        let body = res.body().get_ref();
+        let val: std::collections::HashMap<String, String> =
+            serde_cbor::from_slice(body).unwrap();
+        assert_eq!(val["name"], "test");
+    }
+
+    #[test]
+    fn test_media_range() {
+        assert!(MediaRange::parse("text").is_none());
+        assert!(MediaRange::parse("*/json").is_none());
+        assert!(MediaRange::parse("text/html;q=2").is_none());
+        assert!(MediaRange::parse("text/html;q=a").is_none());
+        let range =
+            MediaRange::parse(" Application/JSON ; charset=utf-8; Q=0.5").unwrap();
+        assert_eq!(range.quality, 0.5);
+        assert_eq!(range.matches(&mime::APPLICATION_JSON), Some(2));
+        assert_eq!(range.matches(&mime::TEXT_PLAIN), None);
+        let range = MediaRange::parse("application/*").unwrap();
+        assert_eq!(range.matches(&mime::APPLICATION_JSON), Some(1));
+        let range = MediaRange::parse("*/*").unwrap();
+        assert_eq!(range.matches(&mime::TEXT_PLAIN), Some(0));
+    }
+}
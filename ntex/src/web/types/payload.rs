@@ -50,6 +50,30 @@ impl Payload {
     pub fn into_inner(self) -> crate::http::Payload {
         self.0
     }
+
+    /// Convert payload to a stream of lines
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use futures::StreamExt;
+    /// use ntex::web::{self, error, HttpResponse};
+    ///
+    /// /// count ndjson records
+    /// async fn index(body: web::types::Payload) -> Result<HttpResponse, error::PayloadError>
+    /// {
+    ///     let mut lines = body.lines().max_line_length(1024);
+    ///     let mut count = 0;
+    ///     while let Some(line) = lines.next().await {
+    ///         let _ = line?;
+    ///         count += 1;
+    ///     }
+    ///     Ok(HttpResponse::Ok().body(format!("{}", count)))
+    /// }
+    /// ```
+    pub fn lines(self) -> Lines<Self> {
+        Lines::new(self)
+    }
 }
 
 impl Stream for Payload {
@@ -64,6 +88,105 @@ impl Stream for Payload {
     }
 }
 
+/// Stream of payload lines
+///
+/// Lines are split on `\n`, line terminator and trailing `\r` are
+/// not included. Last line could be unterminated. Line that is longer
+/// than max line length terminates stream with `PayloadError::Overflow`
+/// error. Default max line length is 64Kb.
+#[derive(Debug)]
+pub struct Lines<S> {
+    stream: S,
+    buf: BytesMut,
+    // length of the buffer prefix that does not contain `\n`
+    scanned: usize,
+    max_len: usize,
+    eof: bool,
+}
+
+impl<S> Lines<S> {
+    /// Create lines stream
+    pub fn new(stream: S) -> Self {
+        Lines {
+            stream,
+            buf: BytesMut::new(),
+            scanned: 0,
+            max_len: 65_536,
+            eof: false,
+        }
+    }
+
+    /// Set max line length, by default max length is 64Kb
+    pub fn max_line_length(mut self, len: usize) -> Self {
+        self.max_len = len;
+        self
+    }
+
+    fn overflow(&mut self) -> Poll<Option<Result<Bytes, error::PayloadError>>> {
+        self.eof = true;
+        self.buf.clear();
+        Poll::Ready(Some(Err(error::PayloadError::Overflow)))
+    }
+}
+
+impl<S> Stream for Lines<S>
+where
+    S: Stream<Item = Result<Bytes, error::PayloadError>> + Unpin,
+{
+    type Item = Result<Bytes, error::PayloadError>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.as_mut().get_mut();
+
+        loop {
+            if let Some(pos) = this.buf[this.scanned..].iter().position(|b| *b == b'\n')
+            {
+                let mut line = this.buf.split_to(this.scanned + pos + 1);
+                this.scanned = 0;
+                line.truncate(line.len() - 1);
+                if line.last() == Some(&b'\r') {
+                    line.truncate(line.len() - 1);
+                }
+                return if line.len() > this.max_len {
+                    this.overflow()
+                } else {
+                    Poll::Ready(Some(Ok(line.freeze())))
+                };
+            }
+            this.scanned = this.buf.len();
+
+            // allow room for line terminator
+            if this.buf.len() > this.max_len + 2 {
+                return this.overflow();
+            }
+
+            if this.eof {
+                return if this.buf.is_empty() {
+                    Poll::Ready(None)
+                } else if this.buf.len() > this.max_len {
+                    this.overflow()
+                } else {
+                    this.scanned = 0;
+                    Poll::Ready(Some(Ok(this.buf.split().freeze())))
+                };
+            }
+
+            match futures::ready!(Pin::new(&mut this.stream).poll_next(cx)) {
+                Some(Ok(chunk)) => this.buf.extend_from_slice(&chunk),
+                Some(Err(e)) => {
+                    this.eof = true;
+                    this.buf.clear();
+                    return Poll::Ready(Some(Err(e)));
+                }
+                None => this.eof = true,
+            }
+        }
+    }
+}
+
 /// Get request's payload stream
 ///
 /// ## Example
@@ -358,6 +481,10 @@ impl HttpMessageBody {
     /// Change max size of payload. By default max size is 256Kb
     fn limit(mut self, limit: usize) -> Self {
         self.limit = limit;
+        #[cfg(feature = "compress")]
+        {
+            self.stream = self.stream.take().map(|s| s.limit(limit));
+        }
         self
     }
 
@@ -530,4 +657,42 @@ mod tests {
             _ => unreachable!("error"),
         }
     }
+
+    #[ntex_rt::test]
+    async fn test_lines() {
+        let (tx, rx) = crate::channel::mpsc::channel();
+        for chunk in &["one\r\ntw", "o\n\nthr", "ee"] {
+            tx.send(Ok::<_, error::PayloadError>(Bytes::from_static(
+                chunk.as_bytes(),
+            )))
+            .unwrap();
+        }
+        drop(tx);
+
+        let lines: Vec<_> = Lines::new(rx).map(|line| line.unwrap()).collect().await;
+        assert_eq!(
+            lines,
+            vec![
+                Bytes::from_static(b"one"),
+                Bytes::from_static(b"two"),
+                Bytes::from_static(b""),
+                Bytes::from_static(b"three")
+            ]
+        );
+
+        let (req, mut pl) = TestRequest::default()
+            .set_payload(Bytes::from_static(b"short\nvery long line\n"))
+            .to_http_parts();
+        let pl = from_request::<Payload>(&req, &mut pl).await.unwrap();
+        let mut lines = pl.lines().max_line_length(5);
+        assert_eq!(
+            lines.next().await.unwrap().unwrap(),
+            Bytes::from_static(b"short")
+        );
+        assert!(matches!(
+            lines.next().await.unwrap(),
+            Err(error::PayloadError::Overflow)
+        ));
+        assert!(lines.next().await.is_none());
+    }
 }
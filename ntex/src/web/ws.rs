@@ -48,6 +48,38 @@ pub async fn start_with<T, F, S, Err, Tx, Rx>(
     rx: Rx,
     factory: F,
 ) -> Result<HttpResponse, Err>
+where
+    T: ServiceFactory<
+        Config = ws::StreamEncoder<Tx>,
+        Request = Frame,
+        Response = Option<Message>,
+    >,
+    T::Error: StdError + 'static,
+    T::InitError: 'static,
+    T::Service: 'static,
+    F: IntoServiceFactory<T>,
+    S: Stream<Item = Result<Bytes, PayloadError>> + Unpin + 'static,
+    Err: From<T::InitError>,
+    Err: From<HandshakeError>,
+    Tx: Sink<Result<Bytes, Box<dyn StdError>>> + Clone + Unpin + 'static,
+    Tx::Error: StdError,
+    Rx: Stream<Item = Result<Bytes, Box<dyn StdError>>> + Unpin + 'static,
+{
+    start_with_codec(req, payload, ws::Codec::new(), tx, rx, factory).await
+}
+
+/// Do websocket handshake and start websockets service with custom codec.
+///
+/// Protocol errors are reported to the peer with close frame
+/// with corresponding close code.
+pub async fn start_with_codec<T, F, S, Err, Tx, Rx>(
+    req: HttpRequest,
+    payload: S,
+    codec: ws::Codec,
+    tx: Tx,
+    rx: Rx,
+    factory: F,
+) -> Result<HttpResponse, Err>
 where
     T: ServiceFactory<
         Config = ws::StreamEncoder<Tx>,
@@ -69,7 +101,7 @@ where
     let mut res = handshake(req.head())?;
 
     // converter wraper from ws::Message to Bytes
-    let sink = ws::StreamEncoder::new(tx);
+    let sink = ws::StreamEncoder::with(tx, codec);
 
     // create ws service
     let srv = factory
@@ -82,17 +114,28 @@ where
         });
 
     // start websockets service dispatcher
-    rt::spawn(crate::util::stream::Dispatcher::new(
-        // wrap bytes stream to ws::Frame's stream
-        ws::StreamDecoder::new(payload).map_err(|e| {
-            let e: Box<dyn StdError> = Box::new(e);
-            e
+    rt::spawn(
+        crate::util::stream::Dispatcher::new(
+            // wrap bytes stream to ws::Frame's stream
+            ws::StreamDecoder::with(payload, codec).map_err(|e| {
+                let e: Box<dyn StdError> = Box::new(e);
+                e
+            }),
+            // converter wraper from ws::Message to Bytes
+            sink,
+            // websockets handler service
+            srv,
+        )
+        // report protocol errors to the peer
+        .on_stream_error(|e| {
+            match e.downcast_ref::<ws::StreamError<PayloadError>>() {
+                Some(ws::StreamError::Protocol(err)) => {
+                    Some(Message::Close(Some(err.close_code().into())))
+                }
+                _ => None,
+            }
         }),
-        // converter wraper from ws::Message to Bytes
-        sink,
-        // websockets handler service
-        srv,
-    ));
+    );
 
     Ok(res.body(Body::from_message(BoxedBodyStream::new(rx))))
 }
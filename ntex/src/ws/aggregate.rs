@@ -0,0 +1,323 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::{Bytes, BytesMut};
+use futures::{ready, Stream};
+
+use super::{Frame, Item, Message, ProtocolError};
+
+pin_project_lite::pin_project! {
+/// Stream of whole websocket messages.
+///
+/// Wraps stream of websocket frames, for example `Framed` object with
+/// server or client `Codec` or `StreamDecoder`, and reassembles fragmented
+/// text and binary messages. Text messages get validated for utf-8.
+/// Control frames (ping, pong, close) are passed through immediately,
+/// even in the middle of fragmented message.
+///
+/// `ProtocolError::close_code()` could be used for closing connection
+/// in case of error.
+pub struct MessageStream<S> {
+    #[pin]
+    stream: S,
+    aggregator: Aggregator,
+}
+}
+
+impl<S> MessageStream<S> {
+    /// Create new message stream
+    pub fn new(stream: S) -> Self {
+        MessageStream {
+            stream,
+            aggregator: Aggregator {
+                max_size: 65_536,
+                cont: None,
+            },
+        }
+    }
+
+    /// Set max message size
+    ///
+    /// By default max size is set to 64kb
+    pub fn max_size(mut self, size: usize) -> Self {
+        self.aggregator.max_size = size;
+        self
+    }
+}
+
+impl<S, E> Stream for MessageStream<S>
+where
+    S: Stream<Item = Result<Frame, E>>,
+    E: From<ProtocolError>,
+{
+    type Item = Result<Message, E>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+
+        loop {
+            match ready!(this.stream.as_mut().poll_next(cx)) {
+                Some(Ok(frame)) => match this.aggregator.feed(frame) {
+                    Ok(Some(msg)) => return Poll::Ready(Some(Ok(msg))),
+                    Ok(None) => continue,
+                    Err(err) => return Poll::Ready(Some(Err(err.into()))),
+                },
+                Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+struct Aggregator {
+    max_size: usize,
+    cont: Option<Continuation>,
+}
+
+struct Continuation {
+    text: bool,
+    buf: BytesMut,
+    // length of already validated utf-8 data
+    valid: usize,
+}
+
+impl Aggregator {
+    fn feed(&mut self, frame: Frame) -> Result<Option<Message>, ProtocolError> {
+        match frame {
+            Frame::Ping(data) => Ok(Some(Message::Ping(data))),
+            Frame::Pong(data) => Ok(Some(Message::Pong(data))),
+            Frame::Close(reason) => Ok(Some(Message::Close(reason))),
+            Frame::Text(data) => {
+                self.check_not_started(&data)?;
+                Ok(Some(Message::Text(into_string(&data)?)))
+            }
+            Frame::Binary(data) => {
+                self.check_not_started(&data)?;
+                Ok(Some(Message::Binary(data)))
+            }
+            Frame::Continuation(Item::FirstText(data)) => {
+                self.check_not_started(&data)?;
+                self.cont = Some(Continuation {
+                    text: true,
+                    buf: BytesMut::from(&data[..]),
+                    valid: 0,
+                });
+                self.validate(false)?;
+                Ok(None)
+            }
+            Frame::Continuation(Item::FirstBinary(data)) => {
+                self.check_not_started(&data)?;
+                self.cont = Some(Continuation {
+                    text: false,
+                    buf: BytesMut::from(&data[..]),
+                    valid: 0,
+                });
+                Ok(None)
+            }
+            Frame::Continuation(Item::Continue(data)) => {
+                self.extend(&data)?;
+                self.validate(false)?;
+                Ok(None)
+            }
+            Frame::Continuation(Item::Last(data)) => {
+                self.extend(&data)?;
+                self.validate(true)?;
+                let cont = self.cont.take().unwrap();
+                if cont.text {
+                    Ok(Some(Message::Text(into_string(&cont.buf)?)))
+                } else {
+                    Ok(Some(Message::Binary(cont.buf.freeze())))
+                }
+            }
+        }
+    }
+
+    fn check_not_started(&self, data: &Bytes) -> Result<(), ProtocolError> {
+        if self.cont.is_some() {
+            Err(ProtocolError::ContinuationStarted)
+        } else if data.len() > self.max_size {
+            Err(ProtocolError::Overflow)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn extend(&mut self, data: &Bytes) -> Result<(), ProtocolError> {
+        if let Some(ref mut cont) = self.cont {
+            if cont.buf.len() + data.len() > self.max_size {
+                Err(ProtocolError::Overflow)
+            } else {
+                cont.buf.extend_from_slice(data);
+                Ok(())
+            }
+        } else {
+            Err(ProtocolError::ContinuationNotStarted)
+        }
+    }
+
+    /// Validate utf-8 data of fragmented text message, trailing
+    /// incomplete sequence is allowed for not last fragments.
+    fn validate(&mut self, last: bool) -> Result<(), ProtocolError> {
+        if let Some(ref mut cont) = self.cont {
+            if cont.text {
+                match std::str::from_utf8(&cont.buf[cont.valid..]) {
+                    Ok(_) => cont.valid = cont.buf.len(),
+                    Err(e) => {
+                        if last || e.error_len().is_some() {
+                            return Err(ProtocolError::InvalidUtf8);
+                        }
+                        cont.valid += e.valid_up_to();
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn into_string(data: &[u8]) -> Result<String, ProtocolError> {
+    std::str::from_utf8(data)
+        .map(|s| s.to_string())
+        .map_err(|_| ProtocolError::InvalidUtf8)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::channel::mpsc;
+    use crate::codec::{Decoder, Encoder};
+    use crate::ws::Codec;
+    use crate::ws::{CloseCode, CloseReason};
+
+    fn frames(
+        frames: Vec<Frame>,
+    ) -> MessageStream<mpsc::Receiver<Result<Frame, ProtocolError>>> {
+        let (tx, rx) = mpsc::channel();
+        for frame in frames {
+            tx.send(Ok(frame)).unwrap();
+        }
+        MessageStream::new(rx)
+    }
+
+    #[ntex_rt::test]
+    async fn test_aggregate() {
+        let mut stream = frames(vec![
+            Frame::Binary(Bytes::from_static(b"bin")),
+            Frame::Continuation(Item::FirstText(Bytes::from_static(b"te"))),
+            Frame::Ping(Bytes::from_static(b"ping")),
+            Frame::Continuation(Item::Continue(Bytes::from_static(b"x"))),
+            Frame::Continuation(Item::Last(Bytes::from_static(b"t"))),
+            Frame::Continuation(Item::FirstBinary(Bytes::from_static(b"b"))),
+            Frame::Continuation(Item::Last(Bytes::from_static(b"in"))),
+            Frame::Close(Some(CloseCode::Normal.into())),
+        ]);
+
+        let msg = stream.next().await.unwrap().unwrap();
+        assert_eq!(msg, Message::Binary(Bytes::from_static(b"bin")));
+        // control frame is passed through immediately
+        let msg = stream.next().await.unwrap().unwrap();
+        assert_eq!(msg, Message::Ping(Bytes::from_static(b"ping")));
+        let msg = stream.next().await.unwrap().unwrap();
+        assert_eq!(msg, Message::Text("text".to_string()));
+        let msg = stream.next().await.unwrap().unwrap();
+        assert_eq!(msg, Message::Binary(Bytes::from_static(b"bin")));
+        let msg = stream.next().await.unwrap().unwrap();
+        assert_eq!(
+            msg,
+            Message::Close(Some(CloseReason::from(CloseCode::Normal)))
+        );
+    }
+
+    #[ntex_rt::test]
+    async fn test_utf8() {
+        // "ü" is split between fragments
+        let mut stream = frames(vec![
+            Frame::Continuation(Item::FirstText(Bytes::from_static(b"\xc3"))),
+            Frame::Continuation(Item::Last(Bytes::from_static(b"\xbc"))),
+            Frame::Text(Bytes::from_static(b"\xc3")),
+        ]);
+        let msg = stream.next().await.unwrap().unwrap();
+        assert_eq!(msg, Message::Text("ü".to_string()));
+        let err = stream.next().await.unwrap().err().unwrap();
+        assert_eq!(err.close_code(), CloseCode::Invalid);
+
+        // invalid sequence is detected before last fragment
+        let mut stream = frames(vec![
+            Frame::Continuation(Item::FirstText(Bytes::from_static(b"\xff"))),
+            Frame::Continuation(Item::Last(Bytes::from_static(b"t"))),
+        ]);
+        let err = stream.next().await.unwrap().err().unwrap();
+        assert!(matches!(err, ProtocolError::InvalidUtf8));
+
+        // incomplete sequence in last fragment
+        let mut stream = frames(vec![
+            Frame::Continuation(Item::FirstText(Bytes::from_static(b"t"))),
+            Frame::Continuation(Item::Last(Bytes::from_static(b"\xc3"))),
+        ]);
+        let err = stream.next().await.unwrap().err().unwrap();
+        assert!(matches!(err, ProtocolError::InvalidUtf8));
+    }
+
+    #[ntex_rt::test]
+    async fn test_errors() {
+        let mut stream = frames(vec![
+            Frame::Continuation(Item::FirstText(Bytes::from_static(b"1234"))),
+            Frame::Continuation(Item::Last(Bytes::from_static(b"5"))),
+        ])
+        .max_size(4);
+        let err = stream.next().await.unwrap().err().unwrap();
+        assert!(matches!(err, ProtocolError::Overflow));
+        assert_eq!(err.close_code(), CloseCode::Size);
+
+        let mut stream = frames(vec![Frame::Continuation(Item::Last(
+            Bytes::from_static(b"5"),
+        ))]);
+        let err = stream.next().await.unwrap().err().unwrap();
+        assert!(matches!(err, ProtocolError::ContinuationNotStarted));
+        assert_eq!(err.close_code(), CloseCode::Protocol);
+
+        let mut stream = frames(vec![
+            Frame::Continuation(Item::FirstBinary(Bytes::from_static(b"1"))),
+            Frame::Binary(Bytes::from_static(b"2")),
+        ]);
+        let err = stream.next().await.unwrap().err().unwrap();
+        assert!(matches!(err, ProtocolError::ContinuationStarted));
+    }
+
+    #[ntex_rt::test]
+    async fn test_client_codec() {
+        let mut buf = BytesMut::new();
+        let mut server = Codec::new();
+        for msg in [
+            Message::Continuation(Item::FirstText(Bytes::from_static(b"\xc3"))),
+            Message::Pong(Bytes::from_static(b"pong")),
+            Message::Continuation(Item::Last(Bytes::from_static(b"\xbc"))),
+        ] {
+            server.encode(msg, &mut buf).unwrap();
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let mut client = Codec::new().client_mode();
+        while let Some(frame) = client.decode(&mut buf).unwrap() {
+            tx.send(Ok::<_, ProtocolError>(frame)).unwrap();
+        }
+        drop(tx);
+
+        let msgs: Vec<_> = MessageStream::new(rx)
+            .map(|msg| msg.unwrap())
+            .collect()
+            .await;
+        assert_eq!(
+            msgs,
+            vec![
+                Message::Pong(Bytes::from_static(b"pong")),
+                Message::Text("ü".to_string())
+            ]
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use std::time::Duration;
+
+use futures::future::poll_fn;
+use futures::StreamExt;
+
+use crate::codec::{AsyncRead, AsyncWrite, Framed};
+use crate::rt::time::timeout;
+
+use super::{CloseReason, Codec, Frame, Message, ProtocolError};
+
+/// Perform websocket closing handshake.
+///
+/// Sends close frame, then waits for close frame from the peer and
+/// shutdowns io object. Incoming data frames are discarded, waiting
+/// for the peer is bounded with `wait` timeout. If close frame has
+/// already been received, it gets echoed and io is shut down immediately.
+///
+/// Codec refuses to encode data frames after close frame is sent.
+pub async fn close<Io>(
+    framed: &mut Framed<Io, Codec>,
+    reason: Option<CloseReason>,
+    wait: Duration,
+) -> Result<(), ProtocolError>
+where
+    Io: AsyncRead + AsyncWrite + Unpin,
+{
+    if !framed.get_codec().is_close_sent() {
+        framed.write(Message::Close(reason))?;
+    }
+    poll_fn(|cx| framed.flush(cx)).await?;
+
+    if !framed.get_codec().is_closed() {
+        let echo = timeout(wait, async {
+            while let Some(item) = framed.next().await {
+                match item {
+                    Ok(Frame::Close(_)) => return Ok(()),
+                    Ok(_) => continue,
+                    Err(err) => return Err(err),
+                }
+            }
+            Ok(())
+        })
+        .await;
+
+        match echo {
+            Ok(Ok(_)) => (),
+            Ok(Err(err)) => {
+                let _ = timeout(wait, poll_fn(|cx| framed.close(cx))).await;
+                return Err(err);
+            }
+            Err(_) => log::trace!("Timeout while waiting for close frame"),
+        }
+    }
+
+    let _ = timeout(wait, poll_fn(|cx| framed.close(cx))).await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{Bytes, BytesMut};
+
+    use super::*;
+    use crate::codec::{Decoder, Encoder};
+    use crate::testing::Io;
+    use crate::ws::CloseCode;
+
+    #[ntex_rt::test]
+    async fn test_close() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+        let mut framed = Framed::new(server, Codec::new());
+
+        let mut peer = Codec::new().client_mode();
+        let mut buf = BytesMut::new();
+        peer.encode(Message::Text("text".to_string()), &mut buf)
+            .unwrap();
+        peer.encode(Message::Close(None), &mut buf).unwrap();
+        client.write(buf);
+
+        close(
+            &mut framed,
+            Some(CloseCode::Normal.into()),
+            Duration::from_millis(100),
+        )
+        .await
+        .unwrap();
+
+        let mut buf = client.read_any();
+        assert_eq!(
+            peer.decode(&mut buf).unwrap().unwrap(),
+            Frame::Close(Some(CloseCode::Normal.into()))
+        );
+
+        // data frames are not allowed after close
+        assert!(matches!(
+            framed.write(Message::Binary(Bytes::from_static(b"data"))),
+            Err(ProtocolError::Closed)
+        ));
+    }
+
+    #[ntex_rt::test]
+    async fn test_close_timeout() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+        let mut framed = Framed::new(server, Codec::new());
+
+        close(&mut framed, None, Duration::from_millis(50))
+            .await
+            .unwrap();
+        let mut buf = client.read_any();
+        assert_eq!(
+            Codec::new()
+                .client_mode()
+                .decode(&mut buf)
+                .unwrap()
+                .unwrap(),
+            Frame::Close(None)
+        );
+    }
+
+    #[ntex_rt::test]
+    async fn test_close_invalid_code() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+        let mut framed = Framed::new(server, Codec::new());
+
+        // close frame with reserved code 1006
+        let mut buf = BytesMut::new();
+        crate::ws::Parser::write_close(&mut buf, Some(CloseCode::Abnormal.into()), true);
+        client.write(buf);
+
+        let err = close(&mut framed, None, Duration::from_millis(50))
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err, ProtocolError::InvalidCloseCode(1006)));
+        assert_eq!(err.close_code(), CloseCode::Protocol);
+    }
+}
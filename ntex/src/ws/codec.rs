@@ -61,6 +61,9 @@ bitflags::bitflags! {
         const SERVER         = 0b0000_0001;
         const CONTINUATION   = 0b0000_0010;
         const W_CONTINUATION = 0b0000_0100;
+        const CLOSED         = 0b0000_1000;
+        const W_CLOSED       = 0b0001_0000;
+        const NO_MASKING     = 0b0010_0000;
     }
 }
 
@@ -76,7 +79,15 @@ impl Codec {
     /// Set max frame size
     ///
     /// By default max size is set to 64kb
-    pub fn max_size(mut self, size: usize) -> Self {
+    pub fn max_size(self, size: usize) -> Self {
+        self.max_frame_size(size)
+    }
+
+    /// Set max frame size
+    ///
+    /// Declared payload length is checked before payload get buffered.
+    /// By default max size is set to 64kb
+    pub fn max_frame_size(mut self, size: usize) -> Self {
         self.max_size = size;
         self
     }
@@ -88,6 +99,32 @@ impl Codec {
         self.flags.remove(Flags::SERVER);
         self
     }
+
+    /// Set decoder to server mode.
+    pub fn server_mode(mut self) -> Self {
+        self.flags.insert(Flags::SERVER);
+        self
+    }
+
+    /// Require masking of incoming frames.
+    ///
+    /// Server rejects unmasked frames and client rejects masked frames,
+    /// as required by RFC 6455. If set to false, incoming frames get unmasked
+    /// if mask is present. By default masking is required.
+    pub fn require_masking(mut self, val: bool) -> Self {
+        self.flags.set(Flags::NO_MASKING, !val);
+        self
+    }
+
+    /// Check if close frame has been received from the peer
+    pub fn is_closed(&self) -> bool {
+        self.flags.contains(Flags::CLOSED)
+    }
+
+    /// Check if close frame has been sent to the peer
+    pub fn is_close_sent(&self) -> bool {
+        self.flags.contains(Flags::W_CLOSED)
+    }
 }
 
 impl Default for Codec {
@@ -101,6 +138,14 @@ impl Encoder for Codec {
     type Error = ProtocolError;
 
     fn encode(&mut self, item: Message, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        // data frames are not allowed after close frame
+        if self.flags.contains(Flags::W_CLOSED) {
+            match item {
+                Message::Ping(_) | Message::Pong(_) => (),
+                _ => return Err(ProtocolError::Closed),
+            }
+        }
+
         match item {
             Message::Text(txt) => Parser::write_message(
                 dst,
@@ -131,6 +176,7 @@ impl Encoder for Codec {
                 !self.flags.contains(Flags::SERVER),
             ),
             Message::Close(reason) => {
+                self.flags.insert(Flags::W_CLOSED);
                 Parser::write_close(dst, reason, !self.flags.contains(Flags::SERVER))
             }
             Message::Continuation(cont) => match cont {
@@ -200,7 +246,12 @@ impl Decoder for Codec {
     type Error = ProtocolError;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        match Parser::parse(src, self.flags.contains(Flags::SERVER), self.max_size) {
+        match Parser::parse_with_masking(
+            src,
+            self.flags.contains(Flags::SERVER),
+            !self.flags.contains(Flags::NO_MASKING),
+            self.max_size,
+        ) {
             Ok(Some((finished, opcode, payload))) => {
                 // handle continuation
                 if !finished {
@@ -260,7 +311,9 @@ impl Decoder for Codec {
                     }
                     OpCode::Bad => Err(ProtocolError::BadOpCode),
                     OpCode::Close => {
+                        self.flags.insert(Flags::CLOSED);
                         if let Some(ref pl) = payload {
+                            Parser::validate_close_payload(pl)?;
                             let close_reason = Parser::parse_close_payload(pl);
                             Ok(Some(Frame::Close(close_reason)))
                         } else {
@@ -15,6 +15,7 @@ impl Parser {
     fn parse_metadata(
         src: &[u8],
         server: bool,
+        require_masking: bool,
         max_size: usize,
     ) -> Result<Option<(usize, bool, OpCode, usize, Option<u32>)>, ProtocolError> {
         let chunk_len = src.len();
@@ -30,10 +31,12 @@ impl Parser {
 
         // check masking
         let masked = second & 0x80 != 0;
-        if !masked && server {
-            return Err(ProtocolError::UnmaskedFrame);
-        } else if masked && !server {
-            return Err(ProtocolError::MaskedFrame);
+        if require_masking {
+            if !masked && server {
+                return Err(ProtocolError::UnmaskedFrame);
+            } else if masked && !server {
+                return Err(ProtocolError::MaskedFrame);
+            }
         }
 
         // Op code
@@ -72,7 +75,7 @@ impl Parser {
             return Err(ProtocolError::Overflow);
         }
 
-        let mask = if server {
+        let mask = if masked {
             if chunk_len < idx + 4 {
                 return Ok(None);
             }
@@ -93,10 +96,23 @@ impl Parser {
         src: &mut BytesMut,
         server: bool,
         max_size: usize,
+    ) -> Result<Option<(bool, OpCode, Option<BytesMut>)>, ProtocolError> {
+        Parser::parse_with_masking(src, server, true, max_size)
+    }
+
+    /// Parse the input stream into a frame.
+    ///
+    /// If `require_masking` is false, masking direction is not checked,
+    /// server accepts unmasked frames and client accepts masked frames.
+    pub fn parse_with_masking(
+        src: &mut BytesMut,
+        server: bool,
+        require_masking: bool,
+        max_size: usize,
     ) -> Result<Option<(bool, OpCode, Option<BytesMut>)>, ProtocolError> {
         // try to parse ws frame metadata
         let (idx, finished, opcode, length, mask) =
-            match Parser::parse_metadata(src, server, max_size)? {
+            match Parser::parse_metadata(src, server, require_masking, max_size)? {
                 None => return Ok(None),
                 Some(res) => res,
             };
@@ -152,6 +168,27 @@ impl Parser {
         }
     }
 
+    /// Validate the payload of a close frame.
+    ///
+    /// Payload must be empty or contain allowed close code
+    /// followed by utf-8 encoded reason.
+    pub fn validate_close_payload(payload: &[u8]) -> Result<(), ProtocolError> {
+        match payload.len() {
+            0 => Ok(()),
+            1 => Err(ProtocolError::InvalidLength(1)),
+            _ => {
+                let code = u16::from_be_bytes([payload[0], payload[1]]);
+                if !CloseCode::is_allowed(code) {
+                    Err(ProtocolError::InvalidCloseCode(code))
+                } else if std::str::from_utf8(&payload[2..]).is_err() {
+                    Err(ProtocolError::InvalidUtf8)
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+
     /// Generate binary representation
     pub fn write_message<B: AsRef<[u8]>>(
         dst: &mut BytesMut,
@@ -380,4 +417,53 @@ mod tests {
         Parser::write_close(&mut buf, None, false);
         assert_eq!(&buf[..], &vec![0x88, 0x00][..]);
     }
+
+    #[test]
+    fn test_validate_close_payload() {
+        assert!(Parser::validate_close_payload(b"").is_ok());
+        assert!(Parser::validate_close_payload(b"\x03\xe8data").is_ok());
+        assert!(Parser::validate_close_payload(b"\x0f\xa0").is_ok());
+        assert!(matches!(
+            Parser::validate_close_payload(b"\x03"),
+            Err(ProtocolError::InvalidLength(1))
+        ));
+        // 1005 is reserved
+        assert!(matches!(
+            Parser::validate_close_payload(b"\x03\xed"),
+            Err(ProtocolError::InvalidCloseCode(1005))
+        ));
+        assert!(matches!(
+            Parser::validate_close_payload(b"\x13\x88"),
+            Err(ProtocolError::InvalidCloseCode(5000))
+        ));
+        assert!(matches!(
+            Parser::validate_close_payload(b"\x03\xe8\xff"),
+            Err(ProtocolError::InvalidUtf8)
+        ));
+    }
+
+    #[test]
+    fn test_parse_frame_masking_not_required() {
+        let mut buf = BytesMut::from(&[0b0000_0001u8, 0b0000_0001u8][..]);
+        buf.extend(&[1u8]);
+        let frame = extract(Parser::parse_with_masking(&mut buf, true, false, 1024));
+        assert_eq!(frame.payload, Bytes::from(vec![1u8]));
+
+        let mut buf = BytesMut::from(&[0b0000_0001u8, 0b1000_0001u8][..]);
+        buf.extend(b"0001");
+        buf.extend(b"1");
+        let frame = extract(Parser::parse_with_masking(&mut buf, false, false, 1024));
+        assert_eq!(frame.payload, Bytes::from(vec![1u8]));
+    }
+
+    #[test]
+    fn test_parse_frame_declared_size() {
+        // only header of 2gb frame is available
+        let mut buf = BytesMut::from(&[0b0000_0010u8, 127u8][..]);
+        buf.extend(&[0u8, 0u8, 0u8, 0u8, 0x80, 0u8, 0u8, 0u8][..]);
+        assert!(matches!(
+            Parser::parse(&mut buf, false, 1024),
+            Err(ProtocolError::Overflow)
+        ));
+    }
 }
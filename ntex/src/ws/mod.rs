@@ -7,16 +7,20 @@ use std::io;
 
 use derive_more::{Display, From};
 
+mod aggregate;
+mod close;
 mod codec;
 mod frame;
 mod mask;
 mod proto;
 mod stream;
 
+pub use self::aggregate::MessageStream;
+pub use self::close::close;
 pub use self::codec::{Codec, Frame, Item, Message};
 pub use self::frame::Parser;
 pub use self::proto::{hash_key, CloseCode, CloseReason, OpCode};
-pub use self::stream::{StreamDecoder, StreamEncoder};
+pub use self::stream::{StreamDecoder, StreamEncoder, StreamError};
 
 /// Websocket protocol errors
 #[derive(Debug, Display, From)]
@@ -48,7 +52,28 @@ pub enum ProtocolError {
     /// Unknown continuation fragment
     #[display(fmt = "Unknown continuation fragment.")]
     ContinuationFragment(OpCode),
+    /// Received close frame with invalid close code
+    #[display(fmt = "Invalid close code: {}", _0)]
+    InvalidCloseCode(u16),
+    /// Close frame has been sent, data frames are not allowed
+    #[display(fmt = "Close frame has been sent")]
+    Closed,
+    /// Text message contains invalid utf-8
+    #[display(fmt = "Invalid utf-8 in text message")]
+    InvalidUtf8,
     /// Io error
     #[display(fmt = "io error: {}", _0)]
     Io(io::Error),
 }
+
+impl ProtocolError {
+    /// Close code that should be sent to the peer for this error
+    pub fn close_code(&self) -> CloseCode {
+        match self {
+            ProtocolError::Overflow => CloseCode::Size,
+            ProtocolError::InvalidUtf8 => CloseCode::Invalid,
+            ProtocolError::Io(_) => CloseCode::Error,
+            _ => CloseCode::Protocol,
+        }
+    }
+}
@@ -174,6 +174,16 @@ impl From<u16> for CloseCode {
     }
 }
 
+impl CloseCode {
+    /// Check if close code is allowed to be sent in close frame.
+    ///
+    /// Codes 1005, 1006 and 1015 are reserved for local use, codes
+    /// in range 3000-4999 are available for libraries and applications.
+    pub fn is_allowed(code: u16) -> bool {
+        matches!(code, 1000..=1003 | 1007..=1013 | 3000..=4999)
+    }
+}
+
 #[derive(Debug, Eq, PartialEq, Clone)]
 /// Reason for closing the connection
 pub struct CloseReason {
@@ -1,5 +1,7 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
-use std::io::{Read, Write};
+use std::io::{self, Read, Write};
+use std::rc::Rc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
@@ -9,13 +11,16 @@ use bytes::Bytes;
 use coo_kie::Cookie;
 use flate2::{read::GzDecoder, write::GzEncoder, write::ZlibEncoder, Compression};
 use futures::future::ok;
-use futures::stream::once;
+use futures::stream::{once, StreamExt};
 use rand::Rng;
 
-use ntex::http::client::error::{JsonPayloadError, SendRequestError};
-use ntex::http::client::{Client, Connector};
+use ntex::http::client::error::{JsonPayloadError, SaveToError, SendRequestError};
+use ntex::http::client::{Client, Connector, Multipart};
 use ntex::http::test::server as test_server;
-use ntex::http::{header, HttpMessage, HttpService};
+use ntex::http::{
+    header, HttpMessage, HttpService, Protocol, Request, Response, Version,
+    WireDirection,
+};
 use ntex::service::{map_config, pipeline_factory, Service};
 use ntex::web::dev::AppConfig;
 use ntex::web::middleware::Compress;
@@ -108,6 +113,117 @@ async fn test_freeze() {
     assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
 }
 
+#[ntex::test]
+async fn test_send_payload() {
+    let upstream = test::server(|| {
+        App::new().service(web::resource("/").route(web::to(|body: Bytes| async move {
+            HttpResponse::Ok().body(body)
+        })))
+    });
+    let url = upstream.url("/");
+
+    let srv = test::server(move || {
+        let url = url.clone();
+        App::new().service(web::resource("/").route(web::to(
+            move |req: HttpRequest, payload: web::types::Payload| {
+                let url = url.clone();
+                async move {
+                    let mut res = Client::new()
+                        .request_from(url.as_str(), req.head())
+                        .send_payload(payload.into_inner())
+                        .await
+                        .unwrap();
+                    let body = res.body().await.unwrap();
+                    HttpResponse::build(res.status()).body(body)
+                }
+            },
+        )))
+    });
+
+    // sized body
+    let mut response = srv.post("/").send_body(STR).await.unwrap();
+    assert!(response.status().is_success());
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
+
+    // streaming body
+    let mut response = srv
+        .post("/")
+        .send_stream(once(ok::<_, std::io::Error>(Bytes::from_static(
+            STR.as_ref(),
+        ))))
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
+}
+
+#[ntex::test]
+async fn test_send_chunks() {
+    let srv = test::server(|| {
+        App::new().service(web::resource("/").route(web::to(
+            |req: HttpRequest, body: Bytes| async move {
+                assert_eq!(
+                    req.headers().get(header::CONTENT_LENGTH).unwrap(),
+                    &STR.len().to_string()
+                );
+                HttpResponse::Ok().body(body)
+            },
+        )))
+    });
+
+    let (first, second) = STR.split_at(100);
+    let mut response = srv
+        .post("/")
+        .send_body(vec![Bytes::from(first), Bytes::from(second)])
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
+}
+
+#[ntex::test]
+async fn test_send_multipart() {
+    let srv = test::server(|| {
+        App::new().service(web::resource("/").route(web::to(
+            |req: HttpRequest, body: Bytes| async move {
+                let content_type = req.headers().get(header::CONTENT_TYPE).unwrap();
+                assert!(content_type
+                    .to_str()
+                    .unwrap()
+                    .starts_with("multipart/form-data; boundary="));
+                assert_eq!(
+                    req.headers().get(header::CONTENT_LENGTH).unwrap(),
+                    &body.len().to_string()
+                );
+                HttpResponse::Ok().body(body)
+            },
+        )))
+    });
+
+    let form = Multipart::new().text("name", "value").bytes(
+        "file",
+        "file.txt",
+        mime::TEXT_PLAIN,
+        STR,
+    );
+    let boundary = form.boundary().to_string();
+    let mut response = srv.post("/").send_multipart(form).await.unwrap();
+    assert!(response.status().is_success());
+
+    let bytes = response.body().await.unwrap();
+    let expected = format!(
+        "--{b}\r\nContent-Disposition: form-data; name=\"name\"\r\n\r\nvalue\r\n\
+         --{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"file.txt\"\r\n\
+         Content-Type: text/plain\r\n\r\n{s}\r\n--{b}--\r\n",
+        b = boundary,
+        s = STR
+    );
+    assert_eq!(bytes, Bytes::from(expected));
+}
+
 #[ntex::test]
 async fn test_json() {
     let srv = test::server(|| {
@@ -194,6 +310,34 @@ async fn test_timeout() {
     }
 }
 
+#[ntex::test]
+async fn test_wire_capture() {
+    let srv = test::server(|| {
+        App::new()
+            .service(web::resource("/").route(web::to(|| async { HttpResponse::Ok() })))
+    });
+
+    let captured = Rc::new(RefCell::new((Vec::new(), Vec::new())));
+    let captured2 = captured.clone();
+    let connector = Connector::default()
+        .wire_capture(move |dir, buf| {
+            let mut captured = captured2.borrow_mut();
+            match dir {
+                WireDirection::Read => captured.0.extend_from_slice(buf),
+                WireDirection::Write => captured.1.extend_from_slice(buf),
+            }
+        })
+        .finish();
+    let client = Client::build().connector(connector).finish();
+
+    let response = client.get(srv.url("/")).send().await.unwrap();
+    assert!(response.status().is_success());
+
+    let captured = captured.borrow();
+    assert!(captured.0.starts_with(b"HTTP/1.1 200 OK\r\n"));
+    assert!(captured.1.starts_with(b"GET / HTTP/1.1\r\n"));
+}
+
 #[ntex::test]
 async fn test_timeout_override() {
     let srv = test::server(|| {
@@ -254,6 +398,264 @@ async fn test_connection_reuse() {
     assert_eq!(num.load(Ordering::Relaxed), 1);
 }
 
+#[ntex::test]
+async fn test_connection_reuse_policy() {
+    let num = Arc::new(AtomicUsize::new(0));
+    let num2 = num.clone();
+
+    let srv = test_server(move || {
+        let num2 = num2.clone();
+        pipeline_factory(move |io| {
+            num2.fetch_add(1, Ordering::Relaxed);
+            ok(io)
+        })
+        .and_then(
+            HttpService::new(map_config(
+                App::new()
+                    .service(
+                        web::resource("/")
+                            .route(web::to(|| async { HttpResponse::Ok() })),
+                    )
+                    .service(web::resource("/err").route(web::to(|| async {
+                        HttpResponse::InternalServerError().body("error")
+                    }))),
+                |_| AppConfig::default(),
+            ))
+            .tcp(),
+        )
+    });
+
+    let connector = Connector::default()
+        .reuse_policy(|head| !head.status.is_server_error())
+        .finish();
+    let client = Client::build()
+        .connector(connector)
+        .timeout(Duration::from_secs(10))
+        .finish();
+
+    let response = client.get(srv.url("/")).send().await.unwrap();
+    assert!(response.status().is_success());
+    let response = client.get(srv.url("/")).send().await.unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(num.load(Ordering::Relaxed), 1);
+
+    // connection is closed after server error
+    let mut response = client.get(srv.url("/err")).send().await.unwrap();
+    assert!(response.status().is_server_error());
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"error"));
+
+    let response = client.get(srv.url("/")).send().await.unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(num.load(Ordering::Relaxed), 2);
+}
+
+#[ntex::test]
+async fn test_h2_prior_knowledge() {
+    let num = Arc::new(AtomicUsize::new(0));
+    let num2 = num.clone();
+
+    let srv = test_server(move || {
+        let num2 = num2.clone();
+        pipeline_factory(move |io| {
+            num2.fetch_add(1, Ordering::Relaxed);
+            ok(io)
+        })
+        .and_then(
+            HttpService::build()
+                .h2(|req: Request| {
+                    ok::<_, io::Error>(
+                        Response::Ok().body(format!("{:?}", req.version())),
+                    )
+                })
+                .tcp(),
+        )
+    });
+
+    let client = Client::build()
+        .connector(Connector::default().h2_prior_knowledge(true).finish())
+        .timeout(Duration::from_secs(10))
+        .finish();
+
+    // req 1
+    let mut response = client.get(srv.url("/")).send().await.unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.version(), Version::HTTP_2);
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"HTTP/2.0"));
+
+    // req 2
+    let response = client.post(srv.url("/")).send().await.unwrap();
+    assert!(response.status().is_success());
+
+    // one multiplexed connection
+    assert_eq!(num.load(Ordering::Relaxed), 1);
+}
+
+#[ntex::test]
+async fn test_h2_server_wire_capture() {
+    let read = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let read2 = read.clone();
+
+    let srv = test_server(move || {
+        let read2 = read2.clone();
+        HttpService::build()
+            .wire_capture(move |dir, buf| {
+                if dir == WireDirection::Read {
+                    read2.lock().unwrap().extend_from_slice(buf);
+                }
+            })
+            .h2(|_| ok::<_, io::Error>(Response::Ok().finish()))
+            .tcp()
+    });
+
+    let client = Client::build()
+        .connector(Connector::default().h2_prior_knowledge(true).finish())
+        .finish();
+    let response = client.get(srv.url("/")).send().await.unwrap();
+    assert!(response.status().is_success());
+    assert!(read.lock().unwrap().starts_with(b"PRI * HTTP/2.0\r\n"));
+
+    // http/2 connection of http service
+    let read = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let read2 = read.clone();
+
+    let srv = test_server(move || {
+        let read2 = read2.clone();
+        pipeline_factory(|io: ntex::rt::net::TcpStream| ok((io, Protocol::Http2, None)))
+            .and_then(
+                HttpService::build()
+                    .wire_capture(move |dir, buf| {
+                        if dir == WireDirection::Read {
+                            read2.lock().unwrap().extend_from_slice(buf);
+                        }
+                    })
+                    .finish(|_| ok::<_, io::Error>(Response::Ok().finish())),
+            )
+    });
+
+    let response = client.get(srv.url("/")).send().await.unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.version(), Version::HTTP_2);
+    assert!(read.lock().unwrap().starts_with(b"PRI * HTTP/2.0\r\n"));
+}
+
+#[ntex::test]
+async fn test_save_to() {
+    let num = Arc::new(AtomicUsize::new(0));
+    let num2 = num.clone();
+    let data = STR.repeat(100);
+    let srv_data = data.clone();
+
+    let srv = test_server(move || {
+        let num2 = num2.clone();
+        let data = srv_data.clone();
+        pipeline_factory(move |io| {
+            num2.fetch_add(1, Ordering::Relaxed);
+            ok(io)
+        })
+        .and_then(
+            HttpService::new(map_config(
+                App::new().service(web::resource("/").route(web::to(move || {
+                    let data = data.clone();
+                    async move { HttpResponse::Ok().body(data) }
+                }))),
+                |_| AppConfig::default(),
+            ))
+            .tcp(),
+        )
+    });
+
+    let dir = std::env::temp_dir()
+        .join(format!("ntex-save-to-{}", rand::thread_rng().gen::<u32>()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("body.txt");
+    std::fs::write(&path, "old content").unwrap();
+
+    let client = Client::build().timeout(Duration::from_secs(10)).finish();
+    let mut response = client.get(srv.url("/")).send().await.unwrap();
+    assert!(response.status().is_success());
+
+    let progress = Rc::new(RefCell::new(Vec::new()));
+    let progress2 = progress.clone();
+    let size = response
+        .save_to(&path)
+        .progress(move |received, total| progress2.borrow_mut().push((received, total)))
+        .await
+        .unwrap();
+    assert_eq!(size, data.len() as u64);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), data);
+    assert_eq!(
+        progress.borrow().last(),
+        Some(&(data.len() as u64, Some(data.len() as u64)))
+    );
+    // no temporary files left
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+    // file operation error
+    let mut response = client.get(srv.url("/")).send().await.unwrap();
+    let res = response.save_to(dir.join("missing").join("body.txt")).await;
+    assert!(matches!(res, Err(SaveToError::Io(_))));
+
+    // first connection is reused after body is saved, second one is
+    // closed because body of failed request is not read
+    let mut response = client.get(srv.url("/")).send().await.unwrap();
+    let _ = response.body().limit(1_000_000).await.unwrap();
+    assert_eq!(num.load(Ordering::Relaxed), 2);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[ntex::test]
+async fn test_save_to_decompressed() {
+    let data = STR.repeat(100);
+    let srv_data = data.clone();
+    let srv = test::server(move || {
+        let data = srv_data.clone();
+        App::new().service(web::resource("/").route(web::to(move || {
+            let mut e = GzEncoder::new(Vec::new(), Compression::default());
+            e.write_all(data.as_ref()).unwrap();
+            let body = e.finish().unwrap();
+            async move {
+                HttpResponse::Ok()
+                    .header("content-encoding", "gzip")
+                    .body(body)
+            }
+        })))
+    });
+
+    let dir = std::env::temp_dir()
+        .join(format!("ntex-save-to-{}", rand::thread_rng().gen::<u32>()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("body.txt");
+
+    // content-length is size of compressed body
+    let mut response = srv.get("/").send().await.unwrap();
+    assert!(response.status().is_success());
+    let length: usize = response
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(length < data.len());
+
+    let progress = Rc::new(RefCell::new(Vec::new()));
+    let progress2 = progress.clone();
+    let size = response
+        .save_to(&path)
+        .progress(move |received, total| progress2.borrow_mut().push((received, total)))
+        .await
+        .unwrap();
+    assert_eq!(size, data.len() as u64);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), data);
+    assert_eq!(progress.borrow().last(), Some(&(data.len() as u64, None)));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
 #[ntex::test]
 async fn test_connection_force_close() {
     let num = Arc::new(AtomicUsize::new(0));
@@ -856,6 +1258,183 @@ async fn client_read_until_eof() {
     assert_eq!(bytes, Bytes::from_static(b"welcome!"));
 }
 
+#[ntex::test]
+async fn client_read_until_eof_http11() {
+    let addr = ntex::server::TestServer::unused_addr();
+
+    std::thread::spawn(move || {
+        let lst = std::net::TcpListener::bind(addr).unwrap();
+
+        for stream in lst.incoming() {
+            let mut stream = stream.unwrap();
+            let mut b = [0; 1000];
+            let _ = stream.read(&mut b).unwrap();
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\n\r\nwelcome!");
+        }
+    });
+    ntex::rt::time::delay_for(Duration::from_millis(300)).await;
+
+    let client = Client::build().timeout(Duration::from_secs(30)).finish();
+
+    // connection is not reused, every request uses new connection
+    for _ in 0..2 {
+        let mut response = client
+            .get(format!("http://{}/", addr).as_str())
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+
+        let bytes = response.body().await.unwrap();
+        assert_eq!(bytes, Bytes::from_static(b"welcome!"));
+    }
+}
+
+#[ntex::test]
+async fn client_incomplete_payload() {
+    let addr = ntex::server::TestServer::unused_addr();
+
+    std::thread::spawn(move || {
+        let lst = std::net::TcpListener::bind(addr).unwrap();
+
+        for stream in lst.incoming() {
+            let mut stream = stream.unwrap();
+            let mut b = [0; 1000];
+            let _ = stream.read(&mut b).unwrap();
+            let _ = stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 100\r\n\r\nwelcome!");
+        }
+    });
+    ntex::rt::time::delay_for(Duration::from_millis(300)).await;
+
+    let mut response = Client::build()
+        .timeout(Duration::from_secs(30))
+        .finish()
+        .get(format!("http://{}/", addr).as_str())
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    assert!(response.body().await.is_err());
+}
+
+#[ntex::test]
+async fn client_read_timeout() {
+    let addr = ntex::server::TestServer::unused_addr();
+
+    std::thread::spawn(move || {
+        let lst = std::net::TcpListener::bind(addr).unwrap();
+
+        for stream in lst.incoming() {
+            let mut stream = stream.unwrap();
+            let mut b = [0; 1000];
+            let _ = stream.read(&mut b).unwrap();
+            // response head stalls
+            std::thread::sleep(Duration::from_millis(500));
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n");
+        }
+    });
+    ntex::rt::time::delay_for(Duration::from_millis(300)).await;
+
+    let client = Client::build()
+        .connector(
+            Connector::default()
+                .read_timeout(Duration::from_millis(100))
+                .finish(),
+        )
+        .timeout(Duration::from_secs(30))
+        .finish();
+
+    let res = client
+        .get(format!("http://{}/", addr).as_str())
+        .send()
+        .await;
+    assert!(matches!(res, Err(SendRequestError::ReadTimeout)));
+}
+
+#[ntex::test]
+async fn client_expect_continue() {
+    let addr = ntex::server::TestServer::unused_addr();
+    let (tx, mut rx) = futures::channel::mpsc::unbounded();
+
+    std::thread::spawn(move || {
+        let lst = std::net::TcpListener::bind(addr).unwrap();
+
+        for stream in lst.incoming() {
+            let mut stream = stream.unwrap();
+            let port = stream.peer_addr().unwrap().port();
+            let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+            let mut b = [0; 1000];
+            let n = stream.read(&mut b).unwrap();
+            let head = String::from_utf8_lossy(&b[..n]).to_string();
+
+            if head.starts_with("POST /auth") {
+                // final response before body, client must not send body
+                let _ = stream.write_all(
+                    b"HTTP/1.1 401 Unauthorized\r\ncontent-length: 0\r\n\r\n",
+                );
+                let n = stream.read(&mut b).ok();
+                let _ = tx.unbounded_send((port, head, n.map(|n| b[..n].to_vec())));
+            } else {
+                let _ = stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n");
+                let mut body = Vec::new();
+                while !body.ends_with(b"0\r\n\r\n") {
+                    match stream.read(&mut b) {
+                        Ok(n) if n > 0 => body.extend_from_slice(&b[..n]),
+                        _ => break,
+                    }
+                }
+                let _ =
+                    stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n");
+                let _ = tx.unbounded_send((port, head, Some(body)));
+            }
+        }
+    });
+    ntex::rt::time::delay_for(Duration::from_millis(300)).await;
+
+    let client = Client::build().timeout(Duration::from_secs(30)).finish();
+
+    // chunked body is sent after `100 Continue`
+    let response = client
+        .post(format!("http://{}/upload", addr).as_str())
+        .header(header::EXPECT, "100-continue")
+        .send_stream(once(ok::<_, io::Error>(Bytes::from_static(b"data"))))
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    drop(response);
+    let (_, head, body) = rx.next().await.unwrap();
+    assert!(head.to_lowercase().contains("transfer-encoding: chunked"));
+    assert!(head.ends_with("\r\n\r\n"));
+    assert_eq!(body.unwrap(), b"4\r\ndata\r\n0\r\n\r\n".to_vec());
+
+    // body is not sent on final response, connection is closed
+    let response = client
+        .post(format!("http://{}/auth", addr).as_str())
+        .header(header::EXPECT, "100-continue")
+        .send_stream(once(ok::<_, io::Error>(Bytes::from_static(b"data"))))
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+    drop(response);
+    let (port, head, rest) = rx.next().await.unwrap();
+    assert!(head.ends_with("\r\n\r\n"));
+    // peer closed connection without sending body
+    assert_eq!(rest, Some(Vec::new()));
+
+    // closed connection is not reused
+    let response = client
+        .post(format!("http://{}/upload", addr).as_str())
+        .header(header::EXPECT, "100-continue")
+        .send_stream(once(ok::<_, io::Error>(Bytes::from_static(b"data"))))
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    drop(response);
+    let (port2, _, _) = rx.next().await.unwrap();
+    assert_ne!(port, port2);
+}
+
 #[ntex::test]
 async fn client_basic_auth() {
     let srv = test::server(|| {
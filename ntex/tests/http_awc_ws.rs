@@ -5,9 +5,12 @@ use futures::future::ok;
 use futures::{SinkExt, StreamExt};
 
 use ntex::codec::Framed;
+use ntex::http::client::{error::WsClientError, Client};
 use ntex::http::test::server as test_server;
 use ntex::http::ws::handshake_response;
-use ntex::http::{body::BodySize, h1, HttpService, Request, Response};
+use ntex::http::{
+    body::BodySize, h1, header, HttpService, Request, Response, StatusCode,
+};
 use ntex::util::framed::Dispatcher;
 use ntex::ws;
 
@@ -73,3 +76,48 @@ async fn test_simple() {
     let item = framed.next().await.unwrap().unwrap();
     assert_eq!(item, ws::Frame::Close(Some(ws::CloseCode::Normal.into())));
 }
+
+#[ntex::test]
+async fn test_protocols() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .upgrade(|(req, mut framed): (Request, Framed<_, _>)| async move {
+                let proto = if req.path() == "/unknown" { "v3" } else { "v2" };
+                let res = handshake_response(req.head())
+                    .header(header::SEC_WEBSOCKET_PROTOCOL, proto)
+                    .header("x-token", "secret")
+                    .finish();
+                framed
+                    .send(h1::Message::Item((res.drop_body(), BodySize::None)))
+                    .await?;
+
+                let framed = framed.into_framed(ws::Codec::default());
+                Dispatcher::new(framed, ws_service).await
+            })
+            .finish(|_| ok::<_, io::Error>(Response::NotFound()))
+            .tcp()
+    });
+
+    let (res, _) = Client::new()
+        .ws(srv.url("/"))
+        .protocols(&["v1", "v2"])
+        .connect()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::SWITCHING_PROTOCOLS);
+    assert_eq!(res.selected_protocol(), Some("v2"));
+    assert_eq!(res.head().headers.get("x-token").unwrap(), "secret");
+
+    let res = Client::new()
+        .ws(srv.url("/unknown"))
+        .protocols(&["v1", "v2"])
+        .connect()
+        .await;
+    match res {
+        Err(WsClientError::UnexpectedProtocol(proto)) => assert_eq!(proto, "v3"),
+        _ => panic!("protocol v3 is not requested"),
+    }
+
+    let res = Client::new().ws(srv.url("/")).connect().await;
+    assert!(matches!(res, Err(WsClientError::UnexpectedProtocol(_))));
+}
@@ -1,8 +1,12 @@
-use std::io;
+use std::task::{Context, Poll};
+use std::{error::Error, io};
 
 use bytes::Bytes;
 use futures::future::{self, ok};
 
+use ntex::http::body::{BodySize, MessageBody};
+use ntex::http::client::Client;
+use ntex::http::header::{self, HeaderMap, HeaderName, HeaderValue};
 use ntex::http::test::server as test_server;
 use ntex::http::{HttpService, Method, Request, Response};
 use ntex::service::ServiceFactory;
@@ -93,3 +97,98 @@ async fn test_with_query_parameter() {
     let response = request.send().await.unwrap();
     assert!(response.status().is_success());
 }
+
+struct TrailersBody(Vec<Bytes>);
+
+impl MessageBody for TrailersBody {
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        _: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        if self.0.is_empty() {
+            Poll::Ready(None)
+        } else {
+            Poll::Ready(Some(Ok(self.0.remove(0))))
+        }
+    }
+
+    fn trailers(&mut self) -> Option<HeaderMap> {
+        let mut trailers = HeaderMap::new();
+        trailers.insert(
+            HeaderName::from_static("grpc-status"),
+            HeaderValue::from_static("0"),
+        );
+        Some(trailers)
+    }
+}
+
+#[ntex::test]
+async fn test_proxy_trailers() {
+    let upstream = test_server(move || {
+        HttpService::build()
+            .finish(|_| {
+                ok::<_, io::Error>(
+                    Response::Ok()
+                        .header(header::TRAILER, "grpc-status")
+                        .message_body(TrailersBody(vec![
+                            Bytes::from_static(b"Hello "),
+                            Bytes::from_static(b"World"),
+                        ])),
+                )
+            })
+            .tcp()
+    });
+    let url = upstream.url("/");
+
+    let proxy = test_server(move || {
+        let url = url.clone();
+        HttpService::build()
+            .finish(move |req: Request| {
+                let client = Client::new();
+                let mut upstream_req = client.get(&url);
+                if let Some(te) = req.headers().get(header::TE) {
+                    upstream_req = upstream_req.header(header::TE, te.clone());
+                }
+                async move {
+                    let res = upstream_req.send().await.map_err(|e| {
+                        io::Error::new(io::ErrorKind::NotConnected, e.to_string())
+                    })?;
+                    let mut builder = Response::build(res.status());
+                    if let Some(trailer) = res.headers().get(header::TRAILER) {
+                        builder.header(header::TRAILER, trailer.clone());
+                    }
+                    Ok::<_, io::Error>(builder.message_body(res))
+                }
+            })
+            .tcp()
+    });
+
+    // trailers are forwarded to peer that accepts them
+    let mut response = proxy
+        .request(Method::GET, "/")
+        .header(header::TE, "trailers")
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(
+        response.headers().get(header::TRAILER).unwrap(),
+        "grpc-status"
+    );
+    assert!(response.trailers().is_none());
+
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"Hello World"));
+    let trailers = response.trailers().unwrap();
+    assert_eq!(trailers.get("grpc-status").unwrap(), "0");
+
+    // trailers are dropped if peer does not accept them
+    let mut response = proxy.request(Method::GET, "/").send().await.unwrap();
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"Hello World"));
+    assert!(response.trailers().is_none());
+}
@@ -1,15 +1,22 @@
 #![cfg(feature = "openssl")]
 use std::io;
+use std::time::Duration;
 
 use bytes::{Bytes, BytesMut};
 use futures::future::{err, ok, ready};
 use futures::stream::{once, Stream, StreamExt};
-use open_ssl::ssl::{AlpnError, SslAcceptor, SslFiletype, SslMethod};
+use open_ssl::ssl::{
+    AlpnError, SslAcceptor, SslConnector, SslFiletype, SslMethod, SslVerifyMode,
+};
 
+use ntex::http::client::{Client, Connector};
 use ntex::http::error::PayloadError;
 use ntex::http::header::{self, HeaderName, HeaderValue};
 use ntex::http::test::server as test_server;
-use ntex::http::{body, HttpService, Method, Request, Response, StatusCode, Version};
+use ntex::http::{
+    body, HttpService, KeepAlive, Method, Request, Response, StatusCode, Version,
+};
+use ntex::rt::time::delay_for;
 use ntex::service::{fn_service, ServiceFactory};
 use ntex::web::error::InternalError;
 
@@ -69,6 +76,118 @@ async fn test_h2() -> io::Result<()> {
     Ok(())
 }
 
+#[ntex::test]
+async fn test_h2_alt_svc() -> io::Result<()> {
+    let srv = test_server(move || {
+        HttpService::build()
+            .alt_svc("h3=\":443\"")
+            .h2(|req: Request| {
+                let res = if req.path() == "/custom" {
+                    Response::Ok().header("alt-svc", "clear").finish()
+                } else {
+                    Response::Ok().finish()
+                };
+                ok::<_, io::Error>(res)
+            })
+            .openssl(ssl_acceptor())
+            .map_err(|_| ())
+    });
+
+    let response = srv.srequest(Method::GET, "/").send().await.unwrap();
+    assert_eq!(response.headers().get("alt-svc").unwrap(), "h3=\":443\"");
+
+    let response = srv.srequest(Method::GET, "/custom").send().await.unwrap();
+    let values: Vec<_> = response.headers().get_all("alt-svc").collect();
+    assert_eq!(values, vec!["clear"]);
+    Ok(())
+}
+
+#[ntex::test]
+async fn test_h2_catch_panic() -> io::Result<()> {
+    let srv = test_server(move || {
+        HttpService::build()
+            .h2(|req: Request| {
+                if req.path() == "/call" {
+                    panic!("call");
+                }
+                async move {
+                    if req.path() == "/poll" {
+                        panic!("poll");
+                    }
+                    Ok::<_, io::Error>(Response::Ok().finish())
+                }
+            })
+            .openssl(ssl_acceptor())
+            .map_err(|_| ())
+    });
+
+    let response = srv.srequest(Method::GET, "/call").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    let response = srv.srequest(Method::GET, "/poll").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    let response = srv.srequest(Method::GET, "/").send().await.unwrap();
+    assert!(response.status().is_success());
+    Ok(())
+}
+
+#[ntex::test]
+async fn test_h2_first_byte_timeout() -> io::Result<()> {
+    let srv = test_server(move || {
+        HttpService::build()
+            .first_byte_timeout(100)
+            .h2(|req: Request| async move {
+                if req.path() == "/slow" {
+                    delay_for(Duration::from_millis(500)).await;
+                }
+                Ok::<_, io::Error>(Response::Ok().finish())
+            })
+            .openssl(ssl_acceptor())
+            .map_err(|_| ())
+    });
+
+    let response = srv.srequest(Method::GET, "/slow").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let response = srv.srequest(Method::GET, "/").send().await.unwrap();
+    assert!(response.status().is_success());
+    Ok(())
+}
+
+struct Upper;
+
+impl body::BodyTransform for Upper {
+    fn start(&mut self, _: &ntex::http::ResponseHead) {}
+
+    fn chunk(&mut self, chunk: Bytes) -> Bytes {
+        Bytes::from(chunk.to_ascii_uppercase())
+    }
+
+    fn finish(&mut self) -> Option<Bytes> {
+        Some(Bytes::from_static(b"!"))
+    }
+}
+
+#[ntex::test]
+async fn test_h2_body_transform() -> io::Result<()> {
+    let srv = test_server(move || {
+        HttpService::build()
+            .h2(|_| {
+                let mut res = Response::Ok().body(STR);
+                res.set_body_transform(Upper);
+                ok::<_, io::Error>(res)
+            })
+            .openssl(ssl_acceptor())
+            .map_err(|_| ())
+    });
+
+    let mut response = srv.srequest(Method::GET, "/").send().await.unwrap();
+    assert!(response.status().is_success());
+    assert!(response.headers().get(header::CONTENT_LENGTH).is_none());
+
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from(format!("{}!", STR.to_ascii_uppercase())));
+    Ok(())
+}
+
 #[ntex::test]
 async fn test_h1() -> io::Result<()> {
     let srv = test_server(move || {
@@ -91,6 +210,51 @@ async fn test_h1() -> io::Result<()> {
     Ok(())
 }
 
+#[ntex::test]
+async fn test_tls_session_resumption() -> io::Result<()> {
+    let acceptor = ssl_acceptor();
+    let srv = test_server(move || {
+        HttpService::build()
+            .keep_alive(KeepAlive::Disabled)
+            .h1(|_| ok::<_, io::Error>(Response::Ok().finish()))
+            .openssl(acceptor.clone())
+            .map_err(|_| ())
+    });
+
+    let mut builder = SslConnector::builder(SslMethod::tls()).unwrap();
+    builder.set_verify(SslVerifyMode::NONE);
+    let connector = Connector::default().openssl(builder.build());
+    let sessions = connector.tls_sessions();
+    let client = Client::build().connector(connector.finish()).finish();
+
+    // session of closed connection is resumed
+    for _ in 0..2 {
+        let response = client.get(srv.surl("/")).send().await.unwrap();
+        assert!(response.status().is_success());
+        delay_for(Duration::from_millis(100)).await;
+    }
+    assert_eq!(sessions.full_handshakes(), 1);
+    assert_eq!(sessions.resumed(), 1);
+
+    // resumption is disabled
+    let mut builder = SslConnector::builder(SslMethod::tls()).unwrap();
+    builder.set_verify(SslVerifyMode::NONE);
+    let connector = Connector::default()
+        .openssl(builder.build())
+        .tls_session_cache(0);
+    let sessions = connector.tls_sessions();
+    let client = Client::build().connector(connector.finish()).finish();
+
+    for _ in 0..2 {
+        let response = client.get(srv.surl("/")).send().await.unwrap();
+        assert!(response.status().is_success());
+        delay_for(Duration::from_millis(100)).await;
+    }
+    assert_eq!(sessions.full_handshakes(), 2);
+    assert_eq!(sessions.resumed(), 0);
+    Ok(())
+}
+
 #[ntex::test]
 async fn test_h2_1() -> io::Result<()> {
     let srv = test_server(move || {
@@ -136,6 +300,27 @@ async fn test_h2_body() -> io::Result<()> {
     Ok(())
 }
 
+#[ntex::test]
+async fn test_h2_send_buffer_size() -> io::Result<()> {
+    let data = "HELLOWORLD".to_owned().repeat(64 * 1024);
+    let mut srv = test_server(move || {
+        let data = data.clone();
+        HttpService::build()
+            .h2_send_buffer_size(1024)
+            .h2(move |_| ok::<_, io::Error>(Response::Ok().body(data.clone())))
+            .openssl(ssl_acceptor())
+            .map_err(|_| ())
+    });
+
+    let response = srv.srequest(Method::GET, "/").send().await.unwrap();
+    assert!(response.status().is_success());
+
+    let body = srv.load_body(response).await.unwrap();
+    assert_eq!(body.len(), 640 * 1024);
+    assert_eq!(&body[..10], b"HELLOWORLD");
+    Ok(())
+}
+
 #[ntex::test]
 async fn test_h2_content_length() {
     let srv = test_server(move || {
@@ -258,6 +443,75 @@ async fn test_h2_body2() {
     assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
 }
 
+#[ntex::test]
+async fn test_h2_static_body() {
+    let mut srv = test_server(move || {
+        HttpService::build()
+            .h2(|_| {
+                ok::<_, io::Error>(
+                    Response::Ok().body(body::Body::from_static(STR.as_bytes())),
+                )
+            })
+            .openssl(ssl_acceptor())
+            .map_err(|_| ())
+    });
+
+    let response = srv.srequest(Method::GET, "/").send().await.unwrap();
+    assert!(response.status().is_success());
+    let len = response.headers().get(header::CONTENT_LENGTH).unwrap();
+    assert_eq!(format!("{}", STR.len()), len.to_str().unwrap());
+
+    let bytes = srv.load_body(response).await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
+}
+
+#[ntex::test]
+async fn test_h2_set_cookie() {
+    let srv = test_server(move || {
+        HttpService::build()
+            .h2(|_| {
+                ok::<_, io::Error>(
+                    Response::Ok()
+                        .header(
+                            header::SET_COOKIE,
+                            "id=a=b; Expires=Wed, 21 Oct 2015 07:28:00 GMT",
+                        )
+                        .header(header::SET_COOKIE, "lang=en; Path=/")
+                        .header(
+                            header::SET_COOKIE,
+                            "theme=dark; Expires=Thu, 22 Oct 2015 07:28:00 GMT",
+                        )
+                        .finish(),
+                )
+            })
+            .openssl(ssl_acceptor())
+            .map_err(|_| ())
+    });
+
+    let response = srv.srequest(Method::GET, "/").send().await.unwrap();
+    assert!(response.status().is_success());
+
+    // every cookie is sent in separate header, in insertion order
+    let cookies: Vec<_> = response
+        .headers()
+        .get_all(header::SET_COOKIE)
+        .map(|v| v.to_str().unwrap())
+        .collect();
+    assert_eq!(
+        cookies,
+        vec![
+            "id=a=b; Expires=Wed, 21 Oct 2015 07:28:00 GMT",
+            "lang=en; Path=/",
+            "theme=dark; Expires=Thu, 22 Oct 2015 07:28:00 GMT",
+        ]
+    );
+    let names: Vec<_> = response
+        .cookies_iter()
+        .map(|c| c.name().to_string())
+        .collect();
+    assert_eq!(names, vec!["id", "lang", "theme"]);
+}
+
 #[ntex::test]
 async fn test_h2_head_empty() {
     let mut srv = test_server(move || {
@@ -437,6 +691,35 @@ async fn test_h2_on_connect() {
     assert!(response.status().is_success());
 }
 
+#[ntex::test]
+async fn test_h2_connection_drain() {
+    use ntex::http::ConnectionHandle;
+
+    let srv = test_server(move || {
+        HttpService::build()
+            .connection_handle(true)
+            .h2(|req: Request| {
+                let handle = req.extensions().get::<ConnectionHandle>().unwrap().clone();
+                if req.path() == "/drain" {
+                    handle.drain();
+                }
+                ok::<_, io::Error>(
+                    Response::Ok()
+                        .header("x-started", handle.requests_started().to_string())
+                        .finish(),
+                )
+            })
+            .openssl(ssl_acceptor())
+            .map_err(|_| ())
+    });
+
+    let response = srv.srequest(Method::GET, "/").send().await.unwrap();
+    assert_eq!(response.headers().get("x-started").unwrap(), "1");
+    let response = srv.srequest(Method::GET, "/drain").send().await.unwrap();
+    assert_eq!(response.headers().get("x-started").unwrap(), "2");
+    assert!(response.status().is_success());
+}
+
 #[ntex::test]
 async fn test_ssl_handshake_timeout() {
     use std::io::Read;
@@ -1,17 +1,20 @@
 use std::io::{Read, Write};
 use std::time::Duration;
-use std::{io, net, thread};
+use std::{io, net, sync::mpsc, thread};
 
 use bytes::Bytes;
 use futures::future::{self, err, ok, ready, FutureExt};
 use futures::stream::{once, StreamExt};
 use regex::Regex;
 
+use ntex::http::error::PayloadError;
 use ntex::http::test::server as test_server;
 use ntex::http::{
-    body, header, HttpService, KeepAlive, Method, Request, Response, StatusCode,
+    body, header, ExpectContinue, HeaderValidation, HttpService, KeepAlive, Method,
+    PipelineOverflow, Request, Response, ServiceStats, StatusCode, UnknownExpectation,
 };
 use ntex::rt::time::delay_for;
+use ntex::server::{Server, TestServer};
 use ntex::service::fn_service;
 use ntex::web::error;
 
@@ -119,6 +122,715 @@ async fn test_expect_continue_h1() {
     assert!(data.starts_with("HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\n"));
 }
 
+#[ntex::test]
+async fn test_expect_continue_limit() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .expect_continue(ExpectContinue::Continue(10))
+            .keep_alive(KeepAlive::Disabled)
+            .h1(fn_service(|_| {
+                future::ok::<_, io::Error>(Response::Ok().finish())
+            }))
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(
+        b"POST /test HTTP/1.1\r\nexpect: 100-continue\r\ncontent-length: 20\r\n\r\n",
+    );
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 417 Expectation Failed\r\n"));
+    assert!(!data.contains("100 Continue"));
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(
+        b"POST /test HTTP/1.1\r\nexpect: 100-continue\r\ncontent-length: 4\r\n\r\ndata",
+    );
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\n"));
+}
+
+#[ntex::test]
+async fn test_expect_continue_ignore() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .expect_continue(ExpectContinue::Ignore)
+            .keep_alive(KeepAlive::Disabled)
+            .h1(fn_service(|_| {
+                future::ok::<_, io::Error>(Response::Ok().finish())
+            }))
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(
+        b"POST /test HTTP/1.1\r\nexpect: 100-continue\r\ncontent-length: 4\r\n\r\ndata",
+    );
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+}
+
+#[ntex::test]
+async fn test_unknown_expectation() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .keep_alive(KeepAlive::Disabled)
+            .h1(fn_service(|_| {
+                future::ok::<_, io::Error>(Response::Ok().finish())
+            }))
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(
+        b"POST /test HTTP/1.1\r\nexpect: foo\r\ncontent-length: 4\r\n\r\ndata",
+    );
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 417 Expectation Failed\r\n"));
+
+    // lenient mode
+    let srv = test_server(|| {
+        HttpService::build()
+            .unknown_expectation(UnknownExpectation::Ignore)
+            .keep_alive(KeepAlive::Disabled)
+            .h1(fn_service(|_| {
+                future::ok::<_, io::Error>(Response::Ok().finish())
+            }))
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(
+        b"POST /test HTTP/1.1\r\nexpect: foo\r\ncontent-length: 4\r\n\r\ndata",
+    );
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+}
+
+#[ntex::test]
+async fn test_error_handler() {
+    let mut srv = test_server(|| {
+        HttpService::build()
+            .error_handler(|err| {
+                if err.to_string() == "custom" {
+                    Some(
+                        Response::build(StatusCode::IM_A_TEAPOT)
+                            .content_type("application/json")
+                            .body(format!("{{\"message\":\"{}\"}}", err)),
+                    )
+                } else {
+                    None
+                }
+            })
+            .h1(fn_service(|req: Request| {
+                let msg = req.path().trim_start_matches('/').to_string();
+                err::<Response, _>(error::InternalError::default(
+                    msg,
+                    StatusCode::BAD_REQUEST,
+                ))
+            }))
+            .tcp()
+    });
+
+    let response = srv.request(Method::GET, "/custom").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::IM_A_TEAPOT);
+    assert_eq!(
+        response.headers().get(header::CONTENT_TYPE).unwrap(),
+        "application/json"
+    );
+    let bytes = srv.load_body(response).await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"{\"message\":\"custom\"}"));
+
+    // default mapping
+    let response = srv.request(Method::GET, "/other").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let bytes = srv.load_body(response).await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"other"));
+}
+
+#[ntex::test]
+async fn test_request_timing() {
+    use ntex::http::RequestTiming;
+
+    let srv = test_server(|| {
+        HttpService::build()
+            .request_timing(true)
+            .h1(|req: Request| async move {
+                let timing = *req.extensions().get::<RequestTiming>().unwrap();
+                delay_for(Duration::from_millis(10)).await;
+                assert!(timing.elapsed() >= Duration::from_millis(10));
+                Ok::<_, io::Error>(Response::Ok().finish())
+            })
+            .tcp()
+    });
+
+    let response = srv.request(Method::GET, "/").send().await.unwrap();
+    assert!(response.status().is_success());
+    assert!(response.headers().get("server-timing").is_none());
+}
+
+#[ntex::test]
+async fn test_server_timing() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .server_timing(true)
+            .h1(|_| async {
+                delay_for(Duration::from_millis(10)).await;
+                Ok::<_, io::Error>(Response::Ok().finish())
+            })
+            .tcp()
+    });
+
+    let response = srv.request(Method::GET, "/").send().await.unwrap();
+    assert!(response.status().is_success());
+    let val = response.headers().get("server-timing").unwrap();
+    let val = val.to_str().unwrap();
+    assert!(val.starts_with("total;dur="));
+    let dur: f64 = val[10..].parse().unwrap();
+    assert!(dur >= 10.0);
+}
+
+#[ntex::test]
+async fn test_alt_svc() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .alt_svc("h3=\":443\"; ma=86400")
+            .h1(|req: Request| {
+                let res = if req.path() == "/custom" {
+                    Response::Ok().header("alt-svc", "clear").finish()
+                } else {
+                    Response::Ok().finish()
+                };
+                future::ok::<_, io::Error>(res)
+            })
+            .tcp()
+    });
+
+    let response = srv.request(Method::GET, "/").send().await.unwrap();
+    assert_eq!(
+        response.headers().get("alt-svc").unwrap(),
+        "h3=\":443\"; ma=86400"
+    );
+
+    let response = srv.request(Method::GET, "/custom").send().await.unwrap();
+    let values: Vec<_> = response.headers().get_all("alt-svc").collect();
+    assert_eq!(values, vec!["clear"]);
+}
+
+#[ntex::test]
+async fn test_rewrite_uri() {
+    use ntex::http::Uri;
+    use std::convert::TryFrom;
+
+    let srv = test_server(|| {
+        HttpService::build()
+            .rewrite_uri(|uri: &mut Uri| {
+                if uri.path() == "/invalid" {
+                    *uri = Uri::from_static("localhost");
+                } else if let Some(path) =
+                    uri.path_and_query().unwrap().as_str().strip_prefix("/api")
+                {
+                    if let Ok(new_uri) = Uri::try_from(path) {
+                        *uri = new_uri;
+                    }
+                }
+            })
+            .h1(|req: Request| {
+                future::ok::<_, io::Error>(Response::Ok().body(req.uri().to_string()))
+            })
+            .tcp()
+    });
+
+    let mut response = srv
+        .request(Method::GET, "/api/test?q=1")
+        .send()
+        .await
+        .unwrap();
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"/test?q=1"));
+
+    let mut response = srv.request(Method::GET, "/test").send().await.unwrap();
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"/test"));
+
+    // rewrite without path is ignored
+    let mut response = srv.request(Method::GET, "/invalid").send().await.unwrap();
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"/invalid"));
+}
+
+#[ntex::test]
+async fn test_upgrade_into_io() {
+    use ntex::codec::Framed;
+    use ntex::http::h1;
+    use ntex::rt::net::TcpStream;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let srv = test_server(|| {
+        HttpService::build()
+            .upgrade(|(_, framed): (Request, Framed<TcpStream, _>)| async move {
+                let (mut io, buf) = h1::into_io(framed).await?;
+                io.write_all(b"HTTP/1.1 101 Switching Protocols\r\n\r\n")
+                    .await?;
+                io.write_all(&buf).await?;
+
+                let mut data = [0u8; 5];
+                io.read_exact(&mut data).await?;
+                io.write_all(&data).await?;
+                io.shutdown(net::Shutdown::Write)
+            })
+            .h1(|_| future::ok::<_, io::Error>(Response::NotFound()))
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(
+        b"GET /test HTTP/1.1\r\nconnection: upgrade\r\nupgrade: raw\r\n\r\nfirst",
+    );
+    thread::sleep(Duration::from_millis(100));
+    let _ = stream.write_all(b"-next");
+
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert_eq!(data, "HTTP/1.1 101 Switching Protocols\r\n\r\nfirst-next");
+}
+
+struct Upper;
+
+impl body::BodyTransform for Upper {
+    fn start(&mut self, _: &ntex::http::ResponseHead) {}
+
+    fn chunk(&mut self, chunk: Bytes) -> Bytes {
+        Bytes::from(chunk.to_ascii_uppercase())
+    }
+
+    fn finish(&mut self) -> Option<Bytes> {
+        Some(Bytes::from_static(b"!"))
+    }
+}
+
+#[ntex::test]
+async fn test_body_transform() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .h1(|_| {
+                let mut res = Response::Ok()
+                    .header(header::CONTENT_LENGTH, "4")
+                    .body("test");
+                res.set_body_transform(Upper);
+                future::ok::<_, io::Error>(res)
+            })
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET /test HTTP/1.1\r\nconnection: close\r\n\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(data.contains("transfer-encoding: chunked\r\n"));
+    assert!(!data.contains("content-length"));
+    assert!(data.ends_with("\r\n\r\n4\r\nTEST\r\n1\r\n!\r\n0\r\n\r\n"));
+}
+
+#[ntex::test]
+async fn test_max_header_size() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .max_header_size(65_536)
+            .h1(|_| future::ok::<_, io::Error>(Response::Ok().finish()))
+            .tcp()
+    });
+
+    // head is larger than read buffer
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let req = format!(
+        "GET /test HTTP/1.1\r\nx-header: {}\r\nconnection: close\r\n\r\n",
+        "a".repeat(40_000)
+    );
+    let _ = stream.write_all(req.as_bytes());
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+
+    // head is larger than max header size
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let req = format!(
+        "GET /test HTTP/1.1\r\nx-header: {}\r\n\r\n",
+        "a".repeat(70_000)
+    );
+    let _ = stream.write_all(req.as_bytes());
+    let mut data = vec![0; 1024];
+    let n = stream.read(&mut data).unwrap();
+    assert!(String::from_utf8_lossy(&data[..n])
+        .starts_with("HTTP/1.1 431 Request Header Fields Too Large\r\n"));
+
+    let srv = test_server(|| {
+        HttpService::build()
+            .max_header_size(1024)
+            .h1(|_| future::ok::<_, io::Error>(Response::Ok().finish()))
+            .tcp()
+    });
+
+    // complete head in single read
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let req = format!(
+        "GET /test HTTP/1.1\r\nx-header: {}\r\n\r\n",
+        "a".repeat(2048)
+    );
+    let _ = stream.write_all(req.as_bytes());
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 431 Request Header Fields Too Large\r\n"));
+}
+
+#[ntex::test]
+async fn test_max_request_line_size() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .max_request_line_size(128)
+            .h1(|_| future::ok::<_, io::Error>(Response::Ok().finish()))
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let req = format!(
+        "GET /{} HTTP/1.1\r\nconnection: close\r\n\r\n",
+        "a".repeat(100)
+    );
+    let _ = stream.write_all(req.as_bytes());
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+
+    // line is rejected before head is complete
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let req = format!("GET /{}", "a".repeat(200));
+    let _ = stream.write_all(req.as_bytes());
+    let mut data = vec![0; 1024];
+    let n = stream.read(&mut data).unwrap();
+    assert!(
+        String::from_utf8_lossy(&data[..n]).starts_with("HTTP/1.1 414 URI Too Long\r\n")
+    );
+}
+
+#[ntex::test]
+async fn test_catch_panic() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .error_handler(|err| {
+                if err.to_string() == "Service panicked: custom" {
+                    Some(Response::build(StatusCode::IM_A_TEAPOT).finish())
+                } else {
+                    None
+                }
+            })
+            .h1(fn_service(|req: Request| {
+                if req.path() == "/call" {
+                    panic!("call");
+                }
+                async move {
+                    match req.path() {
+                        "/poll" => panic!("poll"),
+                        "/custom" => panic!("custom"),
+                        _ => Ok::<_, io::Error>(Response::Ok().finish()),
+                    }
+                }
+            }))
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(
+        b"GET /call HTTP/1.1\r\n\r\nGET /poll HTTP/1.1\r\n\r\n\
+          GET /custom HTTP/1.1\r\n\r\nGET /ok HTTP/1.1\r\nconnection: close\r\n\r\n",
+    );
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    let status: Vec<_> = data
+        .lines()
+        .filter(|line| line.starts_with("HTTP/1.1"))
+        .collect();
+    assert_eq!(
+        status,
+        vec![
+            "HTTP/1.1 500 Internal Server Error",
+            "HTTP/1.1 500 Internal Server Error",
+            "HTTP/1.1 418 I'm a teapot",
+            "HTTP/1.1 200 OK",
+        ]
+    );
+}
+
+#[ntex::test]
+async fn test_first_byte_timeout() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .first_byte_timeout(100)
+            .h1(fn_service(|req: Request| async move {
+                if req.path() == "/slow" {
+                    delay_for(Duration::from_millis(500)).await;
+                }
+                Ok::<_, io::Error>(Response::Ok().finish())
+            }))
+            .tcp()
+    });
+
+    let response = srv.request(Method::GET, "/slow").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    let response = srv.request(Method::GET, "/").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[ntex::test]
+async fn test_payload_timeout() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .payload_timeout(200)
+            .h1(|mut req: Request| async move {
+                let mut pl = req.take_payload();
+                while let Some(item) = pl.next().await {
+                    if let Err(PayloadError::Timeout) = item {
+                        return Ok::<_, io::Error>(Response::RequestTimeout().finish());
+                    }
+                }
+                Ok(Response::Ok().finish())
+            })
+            .tcp()
+    });
+
+    // payload is received in time
+    let response = srv
+        .request(Method::POST, "/")
+        .send_body("data")
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // connection is closed after timeout
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(2)))
+        .unwrap();
+    let _ = stream.write_all(b"POST /test HTTP/1.1\r\ncontent-length: 10\r\n\r\ndata");
+    let mut data = String::new();
+    assert!(stream.read_to_string(&mut data).is_ok());
+    assert!(data.starts_with("HTTP/1.1 408 Request Timeout\r\n"));
+}
+
+#[ntex::test]
+async fn test_h1_allowed_hosts() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .allowed_hosts(vec!["localhost".to_string(), "*.example.com".to_string()])
+            .h1(fn_service(|_| ok::<_, io::Error>(Response::Ok().finish())))
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET /test HTTP/1.1\r\nHost: localhost:8080\r\n\r\n");
+    let mut data = vec![0; 1024];
+    let _ = stream.read(&mut data);
+    assert!(data.starts_with(b"HTTP/1.1 200 OK\r\n"));
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET /test HTTP/1.1\r\nHost: www.example.com\r\n\r\n");
+    let mut data = vec![0; 1024];
+    let _ = stream.read(&mut data);
+    assert!(data.starts_with(b"HTTP/1.1 200 OK\r\n"));
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET /test HTTP/1.1\r\nHost: evil.com\r\n\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 421 Misdirected Request\r\n"));
+
+    // host header does not match absolute-form target
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream
+        .write_all(b"GET http://evil.com/test HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET /test HTTP/1.1\r\n\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+
+    // absolute-form target is checked instead of host header
+    let srv = test_server(|| {
+        HttpService::build()
+            .allowed_hosts(vec!["localhost".to_string()])
+            .header_validation(HeaderValidation::Lenient)
+            .h1(fn_service(|_| ok::<_, io::Error>(Response::Ok().finish())))
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream
+        .write_all(b"GET http://evil.com/test HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 421 Misdirected Request\r\n"));
+}
+
+#[ntex::test]
+async fn test_h1_stats() {
+    let stats = ServiceStats::new();
+    let stats2 = stats.clone();
+    let srv = test_server(move || {
+        HttpService::build()
+            .stats(stats2.clone())
+            .h1(|req: Request| async move {
+                if req.path() == "/slow" {
+                    delay_for(Duration::from_millis(300)).await;
+                }
+                Ok::<_, io::Error>(Response::Ok().finish())
+            })
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET /test HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    let mut data = vec![0; 1024];
+    let _ = stream.read(&mut data);
+    assert!(data.starts_with(b"HTTP/1.1 200 OK\r\n"));
+    delay_for(Duration::from_millis(100)).await;
+    assert_eq!(stats.connections(), 1);
+    assert_eq!(stats.idle_connections(), 1);
+    assert_eq!(stats.requests(), 1);
+    assert_eq!(stats.in_flight(), 0);
+
+    let _ = stream.write_all(b"GET /slow HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    delay_for(Duration::from_millis(100)).await;
+    assert_eq!(stats.idle_connections(), 0);
+    assert_eq!(stats.requests(), 2);
+    assert_eq!(stats.in_flight(), 1);
+
+    let mut data = vec![0; 1024];
+    let _ = stream.read(&mut data);
+    assert!(data.starts_with(b"HTTP/1.1 200 OK\r\n"));
+    drop(stream);
+    delay_for(Duration::from_millis(100)).await;
+    assert_eq!(stats.connections(), 0);
+    assert_eq!(stats.idle_connections(), 0);
+    assert_eq!(stats.in_flight(), 0);
+}
+
+#[cfg(feature = "compress")]
+#[ntex::test]
+async fn test_h1_transfer_codings() {
+    use flate2::{write::GzEncoder, Compression};
+    use ntex::http::TransferCodings;
+
+    let srv = test_server(|| {
+        HttpService::build()
+            .transfer_codings(TransferCodings::Decompress)
+            .keep_alive(KeepAlive::Disabled)
+            .h1(|mut req: Request| async move {
+                let mut pl = req.take_payload();
+                let mut body = Vec::new();
+                while let Some(item) = pl.next().await {
+                    body.extend_from_slice(&item.unwrap());
+                }
+                Ok::<_, io::Error>(Response::Ok().body(body))
+            })
+            .tcp()
+    });
+
+    let mut enc = GzEncoder::new(Vec::new(), Compression::default());
+    enc.write_all(b"hello world").unwrap();
+    let data = enc.finish().unwrap();
+    let mut req = format!(
+        "POST /test HTTP/1.1\r\ntransfer-encoding: gzip, chunked\r\n\r\n{:x}\r\n",
+        data.len()
+    )
+    .into_bytes();
+    req.extend_from_slice(&data);
+    req.extend_from_slice(b"\r\n0\r\n\r\n");
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(&req);
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(data.ends_with("hello world"));
+
+    // chunked must be the final coding
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream
+        .write_all(b"POST /test HTTP/1.1\r\ntransfer-encoding: chunked, gzip\r\n\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+
+    // codings are rejected by default
+    let srv = test_server(|| {
+        HttpService::build()
+            .h1(fn_service(|_| {
+                future::ok::<_, io::Error>(Response::Ok().finish())
+            }))
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(&req);
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 501 Not Implemented\r\n"));
+}
+
+#[ntex::test]
+async fn test_h1_verify_body_digest() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .verify_body_digest(true)
+            .h1(|mut req: Request| async move {
+                let mut pl = req.take_payload();
+                while let Some(item) = pl.next().await {
+                    if item.is_err() {
+                        return Ok::<_, io::Error>(Response::BadRequest().finish());
+                    }
+                }
+                Ok(Response::Ok().finish())
+            })
+            .tcp()
+    });
+
+    // md5 of "hello"
+    let response = srv
+        .request(Method::POST, "/")
+        .header("content-md5", "XUFAKrxLKna5cZ2REBfFkg==")
+        .send_body("hello")
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = srv
+        .request(Method::POST, "/")
+        .header("content-md5", "XUFAKrxLKna5cZ2REBfFkg==")
+        .send_body("hello!")
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = srv
+        .request(Method::POST, "/")
+        .header("digest", "sha-512=AAAA")
+        .send_body("hello")
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
 #[ntex::test]
 async fn test_chunked_payload() {
     let chunk_sizes = vec![32768, 32, 32768];
@@ -126,110 +838,383 @@ async fn test_chunked_payload() {
 
     let srv = test_server(|| {
         HttpService::build()
-            .h1(fn_service(|mut request: Request| {
-                request
-                    .take_payload()
-                    .map(|res| match res {
-                        Ok(pl) => pl,
-                        Err(e) => panic!(format!("Error reading payload: {}", e)),
-                    })
-                    .fold(0usize, |acc, chunk| ready(acc + chunk.len()))
-                    .map(|req_size| {
-                        Ok::<_, io::Error>(
-                            Response::Ok().body(format!("size={}", req_size)),
-                        )
-                    })
-            }))
+            .h1(fn_service(|mut request: Request| {
+                request
+                    .take_payload()
+                    .map(|res| match res {
+                        Ok(pl) => pl,
+                        Err(e) => panic!(format!("Error reading payload: {}", e)),
+                    })
+                    .fold(0usize, |acc, chunk| ready(acc + chunk.len()))
+                    .map(|req_size| {
+                        Ok::<_, io::Error>(
+                            Response::Ok().body(format!("size={}", req_size)),
+                        )
+                    })
+            }))
+            .tcp()
+    });
+
+    let returned_size = {
+        let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+        let _ = stream
+            .write_all(b"POST /test HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n");
+
+        for chunk_size in chunk_sizes.iter() {
+            let mut bytes = Vec::new();
+            let random_bytes: Vec<u8> =
+                (0..*chunk_size).map(|_| rand::random::<u8>()).collect();
+
+            bytes.extend(format!("{:X}\r\n", chunk_size).as_bytes());
+            bytes.extend(&random_bytes[..]);
+            bytes.extend(b"\r\n");
+            let _ = stream.write_all(&bytes);
+        }
+        let _ = stream.write_all(b"0\r\n\r\n");
+
+        let mut data = String::new();
+        let _ = stream.read_to_string(&mut data);
+
+        let re = Regex::new(r"size=(\d+)").unwrap();
+        let size: usize = match re.captures(&data) {
+            Some(caps) => caps.get(1).unwrap().as_str().parse().unwrap(),
+            None => panic!(format!("Failed to find size in HTTP Response: {}", data)),
+        };
+        size
+    };
+
+    assert_eq!(returned_size, total_size);
+}
+
+#[ntex::test]
+async fn test_slow_request() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .client_timeout(100)
+            .finish(|_| future::ok::<_, io::Error>(Response::Ok().finish()))
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET /test/tests/test HTTP/1.1\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 408 Request Timeout"));
+}
+
+#[ntex::test]
+async fn test_error_format() {
+    use ntex::http::ErrorFormat;
+
+    let srv = test_server(|| {
+        HttpService::build()
+            .client_timeout(100)
+            .error_format(ErrorFormat::Json)
+            .h1(|_| future::ok::<_, io::Error>(Response::Ok().finish()))
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET /test/tests/test HTTP1.1\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 400 Bad Request"));
+    assert!(data.contains("content-type: application/json\r\n"));
+    assert!(data.ends_with("\r\n\r\n{\"error\":\"Malformed request\",\"status\":400}"));
+
+    let srv = test_server(|| {
+        HttpService::build()
+            .client_timeout(100)
+            .error_format(ErrorFormat::Text)
+            .h1(|_| future::ok::<_, io::Error>(Response::Ok().finish()))
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET /test/tests/test HTTP/1.1\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 408 Request Timeout"));
+    assert!(data.contains("content-type: text/plain; charset=utf-8\r\n"));
+    assert!(data.ends_with("\r\n\r\nRequest timeout"));
+
+    let srv = test_server(|| {
+        HttpService::build()
+            .error_formatter(|status, msg| {
+                Response::build(status)
+                    .header("x-error", msg)
+                    .body(format!("custom {}", status.as_u16()))
+            })
+            .h1(|_| future::ok::<_, io::Error>(Response::Ok().finish()))
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET /test/tests/test HTTP1.1\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 400 Bad Request"));
+    assert!(data.contains("x-error: Malformed request\r\n"));
+    assert!(data.ends_with("\r\n\r\ncustom 400"));
+}
+
+#[ntex::test]
+async fn test_http1_malformed_request() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .h1(|_| future::ok::<_, io::Error>(Response::Ok().finish()))
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET /test/tests/test HTTP1.1\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 400 Bad Request"));
+}
+
+#[ntex::test]
+async fn test_http1_keepalive() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .h1(|_| future::ok::<_, io::Error>(Response::Ok().finish()))
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET /test/tests/test HTTP/1.1\r\n\r\n");
+    let mut data = vec![0; 1024];
+    let _ = stream.read(&mut data);
+    assert_eq!(&data[..17], b"HTTP/1.1 200 OK\r\n");
+
+    let _ = stream.write_all(b"GET /test/tests/test HTTP/1.1\r\n\r\n");
+    let mut data = vec![0; 1024];
+    let _ = stream.read(&mut data);
+    assert_eq!(&data[..17], b"HTTP/1.1 200 OK\r\n");
+}
+
+#[ntex::test]
+async fn test_http1_keepalive_timeout() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .keep_alive(1)
+            .h1(|_| future::ok::<_, io::Error>(Response::Ok().finish()))
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET /test/tests/test HTTP/1.1\r\n\r\n");
+    let mut data = vec![0; 1024];
+    let _ = stream.read(&mut data);
+    assert_eq!(&data[..17], b"HTTP/1.1 200 OK\r\n");
+    thread::sleep(Duration::from_millis(1100));
+
+    let mut data = vec![0; 1024];
+    let res = stream.read(&mut data).unwrap();
+    assert_eq!(res, 0);
+}
+
+#[ntex::test]
+async fn test_http1_keepalive_close() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .h1(|_| future::ok::<_, io::Error>(Response::Ok().finish()))
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ =
+        stream.write_all(b"GET /test/tests/test HTTP/1.1\r\nconnection: close\r\n\r\n");
+    let mut data = vec![0; 1024];
+    let _ = stream.read(&mut data);
+    assert_eq!(&data[..17], b"HTTP/1.1 200 OK\r\n");
+
+    let mut data = vec![0; 1024];
+    let res = stream.read(&mut data).unwrap();
+    assert_eq!(res, 0);
+}
+
+#[ntex::test]
+async fn test_http10_keepalive_default_close() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .h1(|_| future::ok::<_, io::Error>(Response::Ok().finish()))
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET /test/tests/test HTTP/1.0\r\n\r\n");
+    let mut data = vec![0; 1024];
+    let _ = stream.read(&mut data);
+    assert_eq!(&data[..17], b"HTTP/1.0 200 OK\r\n");
+
+    let mut data = vec![0; 1024];
+    let res = stream.read(&mut data).unwrap();
+    assert_eq!(res, 0);
+}
+
+#[ntex::test]
+async fn test_http10_keepalive() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .h1(|_| future::ok::<_, io::Error>(Response::Ok().finish()))
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream
+        .write_all(b"GET /test/tests/test HTTP/1.0\r\nconnection: keep-alive\r\n\r\n");
+    let mut data = vec![0; 1024];
+    let _ = stream.read(&mut data);
+    assert_eq!(&data[..17], b"HTTP/1.0 200 OK\r\n");
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET /test/tests/test HTTP/1.0\r\n\r\n");
+    let mut data = vec![0; 1024];
+    let _ = stream.read(&mut data);
+    assert_eq!(&data[..17], b"HTTP/1.0 200 OK\r\n");
+
+    let mut data = vec![0; 1024];
+    let res = stream.read(&mut data).unwrap();
+    assert_eq!(res, 0);
+}
+
+#[ntex::test]
+async fn test_http10_keepalive_pipelined() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .h1(|req: Request| {
+                future::ok::<_, io::Error>(Response::Ok().body(req.path().to_string()))
+            })
             .tcp()
     });
 
-    let returned_size = {
-        let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
-        let _ = stream
-            .write_all(b"POST /test HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n");
+    // requests without host header, last request closes connection
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(
+        b"GET /1 HTTP/1.0\r\nconnection: keep-alive\r\n\r\n\
+          GET /2 HTTP/1.0\r\nconnection: Keep-Alive\r\n\r\n\
+          GET /3 HTTP/1.0\r\n\r\n",
+    );
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
 
-        for chunk_size in chunk_sizes.iter() {
-            let mut bytes = Vec::new();
-            let random_bytes: Vec<u8> =
-                (0..*chunk_size).map(|_| rand::random::<u8>()).collect();
+    let responses: Vec<_> = data.split("HTTP/1.0 200 OK\r\n").skip(1).collect();
+    assert_eq!(responses.len(), 3);
+    assert!(responses[0].contains("connection: keep-alive\r\n"));
+    assert!(responses[0].ends_with("\r\n\r\n/1"));
+    assert!(responses[1].contains("connection: keep-alive\r\n"));
+    assert!(responses[1].ends_with("\r\n\r\n/2"));
+    assert!(!responses[2].contains("connection:"));
+    assert!(responses[2].ends_with("\r\n\r\n/3"));
+}
 
-            bytes.extend(format!("{:X}\r\n", chunk_size).as_bytes());
-            bytes.extend(&random_bytes[..]);
-            bytes.extend(b"\r\n");
-            let _ = stream.write_all(&bytes);
-        }
-        let _ = stream.write_all(b"0\r\n\r\n");
+#[ntex::test]
+async fn test_h1_keepalive_extensions() {
+    struct Marker(String);
 
-        let mut data = String::new();
-        let _ = stream.read_to_string(&mut data);
+    let srv = test_server(|| {
+        HttpService::build()
+            .h1(|req: Request| {
+                // extensions of previous request must not be visible
+                let prev = req.extensions().get::<Marker>().map(|m| m.0.clone());
+                req.extensions_mut().insert(Marker(req.path().to_string()));
+                future::ok::<_, io::Error>(
+                    Response::Ok().body(prev.unwrap_or_else(|| "none".to_string())),
+                )
+            })
+            .tcp()
+    });
 
-        let re = Regex::new(r"size=(\d+)").unwrap();
-        let size: usize = match re.captures(&data) {
-            Some(caps) => caps.get(1).unwrap().as_str().parse().unwrap(),
-            None => panic!(format!("Failed to find size in HTTP Response: {}", data)),
-        };
-        size
-    };
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(
+        b"GET /1 HTTP/1.1\r\nhost: localhost\r\n\r\n\
+          GET /2 HTTP/1.1\r\nhost: localhost\r\n\r\n\
+          GET /3 HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n",
+    );
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
 
-    assert_eq!(returned_size, total_size);
+    let responses: Vec<_> = data.split("HTTP/1.1 200 OK\r\n").skip(1).collect();
+    assert_eq!(responses.len(), 3);
+    for resp in responses {
+        assert!(resp.ends_with("\r\n\r\nnone"));
+    }
 }
 
 #[ntex::test]
-async fn test_slow_request() {
+async fn test_http10_streaming_body() {
     let srv = test_server(|| {
         HttpService::build()
-            .client_timeout(100)
-            .finish(|_| future::ok::<_, io::Error>(Response::Ok().finish()))
+            .h1(|_| {
+                let body = once(ok::<_, io::Error>(Bytes::from_static(b"welcome!")));
+                ok::<_, io::Error>(Response::Ok().streaming(body))
+            })
             .tcp()
     });
 
+    // body is delimited by connection close, keep-alive is ignored
     let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
-    let _ = stream.write_all(b"GET /test/tests/test HTTP/1.1\r\n");
+    let _ = stream.write_all(b"GET / HTTP/1.0\r\nconnection: keep-alive\r\n\r\n");
     let mut data = String::new();
     let _ = stream.read_to_string(&mut data);
-    assert!(data.starts_with("HTTP/1.1 408 Request Timeout"));
+    assert!(data.starts_with("HTTP/1.0 200 OK\r\n"));
+    assert!(!data.contains("transfer-encoding"));
+    assert!(!data.contains("content-length"));
+    assert!(!data.contains("connection: keep-alive"));
+    assert!(data.ends_with("\r\n\r\nwelcome!"));
 }
 
 #[ntex::test]
-async fn test_http1_malformed_request() {
+async fn test_http10_transfer_encoding() {
     let srv = test_server(|| {
         HttpService::build()
             .h1(|_| future::ok::<_, io::Error>(Response::Ok().finish()))
             .tcp()
     });
 
+    // transfer codings are not supported by http/1.0
     let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
-    let _ = stream.write_all(b"GET /test/tests/test HTTP1.1\r\n");
+    let _ = stream.write_all(
+        b"POST / HTTP/1.0\r\ntransfer-encoding: chunked\r\n\r\n4\r\ndata\r\n0\r\n\r\n",
+    );
     let mut data = String::new();
     let _ = stream.read_to_string(&mut data);
-    assert!(data.starts_with("HTTP/1.1 400 Bad Request"));
+    assert!(data.contains(" 400 Bad Request\r\n"));
 }
 
 #[ntex::test]
-async fn test_http1_keepalive() {
+async fn test_http10_expect_ignored() {
     let srv = test_server(|| {
         HttpService::build()
-            .h1(|_| future::ok::<_, io::Error>(Response::Ok().finish()))
+            .h1(|mut req: Request| async move {
+                let mut pl = req.take_payload();
+                let mut size = 0;
+                while let Some(Ok(chunk)) = pl.next().await {
+                    size += chunk.len();
+                }
+                Ok::<_, io::Error>(Response::Ok().body(format!("size={}", size)))
+            })
             .tcp()
     });
 
+    // `100 Continue` is not sent to http/1.0 client
     let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
-    let _ = stream.write_all(b"GET /test/tests/test HTTP/1.1\r\n\r\n");
-    let mut data = vec![0; 1024];
-    let _ = stream.read(&mut data);
-    assert_eq!(&data[..17], b"HTTP/1.1 200 OK\r\n");
-
-    let _ = stream.write_all(b"GET /test/tests/test HTTP/1.1\r\n\r\n");
-    let mut data = vec![0; 1024];
-    let _ = stream.read(&mut data);
-    assert_eq!(&data[..17], b"HTTP/1.1 200 OK\r\n");
+    let _ = stream.write_all(
+        b"POST / HTTP/1.0\r\nexpect: 100-continue\r\ncontent-length: 4\r\n\r\ndata",
+    );
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.0 200 OK\r\n"));
+    assert!(!data.contains("100 Continue"));
+    assert!(data.ends_with("size=4"));
 }
 
 #[ntex::test]
-async fn test_http1_keepalive_timeout() {
+async fn test_http1_keepalive_disabled() {
     let srv = test_server(|| {
         HttpService::build()
-            .keep_alive(1)
+            .keep_alive(KeepAlive::Disabled)
             .h1(|_| future::ok::<_, io::Error>(Response::Ok().finish()))
             .tcp()
     });
@@ -239,27 +1224,198 @@ async fn test_http1_keepalive_timeout() {
     let mut data = vec![0; 1024];
     let _ = stream.read(&mut data);
     assert_eq!(&data[..17], b"HTTP/1.1 200 OK\r\n");
-    thread::sleep(Duration::from_millis(1100));
 
     let mut data = vec![0; 1024];
     let res = stream.read(&mut data).unwrap();
     assert_eq!(res, 0);
 }
 
+#[test]
+fn test_server_drain() {
+    let addr = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let mut sys = ntex::rt::System::new("test");
+        let srv = sys.exec(|| {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .bind("test", addr, || {
+                    HttpService::build()
+                        .h1(|_| future::ok::<_, io::Error>(Response::Ok().finish()))
+                        .tcp()
+                })
+                .unwrap()
+                .start()
+        });
+        let _ = tx.send((srv, ntex::rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    let mut idle = net::TcpStream::connect(addr).unwrap();
+    let _ = idle.write_all(b"GET /test HTTP/1.1\r\n\r\n");
+    let mut data = vec![0; 1024];
+    let _ = idle.read(&mut data);
+    assert!(data.starts_with(b"HTTP/1.1 200 OK\r\n"));
+
+    let status = futures::executor::block_on(srv.drain_status());
+    assert!(!status.is_draining());
+    assert_eq!(status.connections(), 1);
+
+    let _ = srv.drain(Duration::from_millis(500));
+    thread::sleep(Duration::from_millis(100));
+
+    // idle keep-alive connection is closed
+    let res = idle.read(&mut data).unwrap();
+    assert_eq!(res, 0);
+
+    // new connections are served and closed after response
+    let mut stream = net::TcpStream::connect(addr).unwrap();
+    let _ = stream.write_all(b"GET /test HTTP/1.1\r\n\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(data.contains("connection: close\r\n"));
+
+    // server waits for peer to close its side of connection
+    drop(idle);
+    drop(stream);
+    thread::sleep(Duration::from_millis(100));
+
+    let status = futures::executor::block_on(srv.drain_status());
+    assert!(status.is_draining());
+    assert_eq!(status.connections(), 0);
+
+    // server stops after grace period
+    thread::sleep(Duration::from_millis(700));
+    assert!(net::TcpStream::connect(addr).is_err());
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[cfg(target_os = "linux")]
+fn getsockopt(io: &ntex::rt::net::TcpStream, level: i32, name: i32) -> i32 {
+    use std::os::unix::io::AsRawFd;
+
+    let mut val: libc::c_int = 0;
+    let mut len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+    let res = unsafe {
+        libc::getsockopt(
+            io.as_raw_fd(),
+            level,
+            name,
+            &mut val as *mut libc::c_int as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    assert_eq!(res, 0);
+    val
+}
+
+#[cfg(target_os = "linux")]
 #[ntex::test]
-async fn test_http1_keepalive_close() {
+async fn test_http1_keepalive_tcp() {
+    use ntex::http::TcpKeepalive;
+
+    let mut srv = test_server(|| {
+        HttpService::build()
+            .keep_alive(
+                TcpKeepalive::new()
+                    .time(Duration::from_secs(30))
+                    .interval(Duration::from_secs(5))
+                    .retries(3),
+            )
+            .on_connect(|io: &ntex::rt::net::TcpStream| {
+                (
+                    getsockopt(io, libc::SOL_SOCKET, libc::SO_KEEPALIVE),
+                    getsockopt(io, libc::IPPROTO_TCP, libc::TCP_KEEPIDLE),
+                    getsockopt(io, libc::IPPROTO_TCP, libc::TCP_KEEPINTVL),
+                    getsockopt(io, libc::IPPROTO_TCP, libc::TCP_KEEPCNT),
+                )
+            })
+            .h1(|req: Request| {
+                let opts = *req.extensions().get::<(i32, i32, i32, i32)>().unwrap();
+                future::ok::<_, io::Error>(Response::Ok().body(format!("{:?}", opts)))
+            })
+            .tcp()
+    });
+
+    let response = srv.request(Method::GET, "/").send().await.unwrap();
+    assert!(response.status().is_success());
+    let bytes = srv.load_body(response).await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"(1, 30, 5, 3)"));
+}
+
+#[cfg(target_os = "linux")]
+#[ntex::test]
+async fn test_http1_keepalive_os() {
+    let mut srv = test_server(|| {
+        HttpService::build()
+            .keep_alive(KeepAlive::Os)
+            .on_connect(|io: &ntex::rt::net::TcpStream| {
+                getsockopt(io, libc::SOL_SOCKET, libc::SO_KEEPALIVE)
+            })
+            .h1(|req: Request| {
+                let enabled = *req.extensions().get::<i32>().unwrap();
+                future::ok::<_, io::Error>(Response::Ok().body(enabled.to_string()))
+            })
+            .tcp()
+    });
+
+    let response = srv.request(Method::GET, "/").send().await.unwrap();
+    let bytes = srv.load_body(response).await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"1"));
+}
+
+#[cfg(target_os = "linux")]
+#[ntex::test]
+async fn test_http1_tcp_keepalive() {
+    let mut srv = test_server(|| {
+        HttpService::build()
+            .keep_alive(KeepAlive::Timeout(5))
+            .tcp_keepalive(Duration::from_secs(45))
+            .on_connect(|io: &ntex::rt::net::TcpStream| {
+                (
+                    getsockopt(io, libc::SOL_SOCKET, libc::SO_KEEPALIVE),
+                    getsockopt(io, libc::IPPROTO_TCP, libc::TCP_KEEPIDLE),
+                )
+            })
+            .h1(|req: Request| {
+                let opts = *req.extensions().get::<(i32, i32)>().unwrap();
+                future::ok::<_, io::Error>(Response::Ok().body(format!("{:?}", opts)))
+            })
+            .tcp()
+    });
+
+    let response = srv.request(Method::GET, "/").send().await.unwrap();
+    assert!(response.status().is_success());
+    let bytes = srv.load_body(response).await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"(1, 45)"));
+}
+
+#[ntex::test]
+async fn test_http1_max_requests_per_connection() {
     let srv = test_server(|| {
         HttpService::build()
+            .max_requests_per_connection(2)
             .h1(|_| future::ok::<_, io::Error>(Response::Ok().finish()))
             .tcp()
     });
 
     let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
-    let _ =
-        stream.write_all(b"GET /test/tests/test HTTP/1.1\r\nconnection: close\r\n\r\n");
+    let _ = stream.write_all(b"GET /test/tests/test HTTP/1.1\r\n\r\n");
+    let mut data = vec![0; 1024];
+    let _ = stream.read(&mut data);
+    assert_eq!(&data[..17], b"HTTP/1.1 200 OK\r\n");
+
+    let _ = stream.write_all(b"GET /test/tests/test HTTP/1.1\r\n\r\n");
     let mut data = vec![0; 1024];
     let _ = stream.read(&mut data);
     assert_eq!(&data[..17], b"HTTP/1.1 200 OK\r\n");
+    assert!(String::from_utf8_lossy(&data).contains("connection: close\r\n"));
 
     let mut data = vec![0; 1024];
     let res = stream.read(&mut data).unwrap();
@@ -267,44 +1423,92 @@ async fn test_http1_keepalive_close() {
 }
 
 #[ntex::test]
-async fn test_http10_keepalive_default_close() {
+async fn test_http1_max_pipelined_requests() {
     let srv = test_server(|| {
         HttpService::build()
+            .max_pipelined_requests(1)
+            .pipeline_overflow(PipelineOverflow::Close)
             .h1(|_| future::ok::<_, io::Error>(Response::Ok().finish()))
             .tcp()
     });
 
     let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
-    let _ = stream.write_all(b"GET /test/tests/test HTTP/1.0\r\n\r\n");
-    let mut data = vec![0; 1024];
-    let _ = stream.read(&mut data);
-    assert_eq!(&data[..17], b"HTTP/1.0 200 OK\r\n");
+    let _ = stream.write_all(
+        b"GET /test HTTP/1.1\r\n\r\nGET /test HTTP/1.1\r\n\r\nGET /test HTTP/1.1\r\n\r\n",
+    );
 
-    let mut data = vec![0; 1024];
-    let res = stream.read(&mut data).unwrap();
-    assert_eq!(res, 0);
+    // requests above the limit are not processed, connection is closed
+    let mut data = Vec::new();
+    let _ = stream.read_to_end(&mut data);
+    let data = String::from_utf8_lossy(&data);
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert_eq!(data.matches("HTTP/1.1").count(), 1);
 }
 
 #[ntex::test]
-async fn test_http10_keepalive() {
+async fn test_http1_connection_drain() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use ntex::http::ConnectionHandle;
+
     let srv = test_server(|| {
+        let stored: Rc<RefCell<Option<ConnectionHandle>>> = Rc::new(RefCell::new(None));
         HttpService::build()
-            .h1(|_| future::ok::<_, io::Error>(Response::Ok().finish()))
+            .connection_handle(true)
+            .h1(move |req: Request| {
+                let handle = req.extensions().get::<ConnectionHandle>().unwrap().clone();
+                match req.path() {
+                    "/drain" => handle.drain(),
+                    "/store" => *stored.borrow_mut() = Some(handle.clone()),
+                    "/drain-stored" => stored.borrow().as_ref().unwrap().drain(),
+                    _ => (),
+                }
+                future::ok::<_, io::Error>(
+                    Response::Ok()
+                        .header("x-started", handle.requests_started().to_string())
+                        .header("x-finished", handle.requests_finished().to_string())
+                        .finish(),
+                )
+            })
             .tcp()
     });
 
+    // drain connection from its own request
     let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
-    let _ = stream
-        .write_all(b"GET /test/tests/test HTTP/1.0\r\nconnection: keep-alive\r\n\r\n");
+    let _ = stream.write_all(b"GET / HTTP/1.1\r\n\r\n");
     let mut data = vec![0; 1024];
     let _ = stream.read(&mut data);
-    assert_eq!(&data[..17], b"HTTP/1.0 200 OK\r\n");
+    let data = String::from_utf8_lossy(&data);
+    assert!(data.contains("x-started: 1\r\n"));
+    assert!(data.contains("x-finished: 0\r\n"));
+    assert!(!data.contains("connection: close\r\n"));
+
+    let _ = stream.write_all(b"GET /drain HTTP/1.1\r\n\r\n");
+    let mut data = vec![0; 1024];
+    let _ = stream.read(&mut data);
+    let data = String::from_utf8_lossy(&data);
+    assert!(data.contains("x-started: 2\r\n"));
+    assert!(data.contains("x-finished: 1\r\n"));
+    assert!(data.contains("connection: close\r\n"));
+
+    let mut data = vec![0; 1024];
+    let res = stream.read(&mut data).unwrap();
+    assert_eq!(res, 0);
 
+    // drain idle connection from other connection
     let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
-    let _ = stream.write_all(b"GET /test/tests/test HTTP/1.0\r\n\r\n");
+    let _ = stream.write_all(b"GET /store HTTP/1.1\r\n\r\n");
     let mut data = vec![0; 1024];
     let _ = stream.read(&mut data);
-    assert_eq!(&data[..17], b"HTTP/1.0 200 OK\r\n");
+    assert_eq!(&data[..17], b"HTTP/1.1 200 OK\r\n");
+
+    let mut stream2 = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream2.write_all(b"GET /drain-stored HTTP/1.1\r\n\r\n");
+    let mut data = vec![0; 1024];
+    let _ = stream2.read(&mut data);
+    assert_eq!(&data[..17], b"HTTP/1.1 200 OK\r\n");
+    assert!(!String::from_utf8_lossy(&data).contains("connection: close\r\n"));
 
     let mut data = vec![0; 1024];
     let res = stream.read(&mut data).unwrap();
@@ -312,23 +1516,38 @@ async fn test_http10_keepalive() {
 }
 
 #[ntex::test]
-async fn test_http1_keepalive_disabled() {
+async fn test_http1_request_rate() {
+    use ntex::http::ConnectionHandle;
+
     let srv = test_server(|| {
         HttpService::build()
-            .keep_alive(KeepAlive::Disabled)
-            .h1(|_| future::ok::<_, io::Error>(Response::Ok().finish()))
+            .request_rate_window(Duration::from_secs(60))
+            .h1(|req: Request| {
+                let handle = req.extensions().get::<ConnectionHandle>().unwrap().clone();
+                let rate = handle.request_rate().unwrap();
+                future::ok::<_, io::Error>(
+                    Response::Ok().header("x-rate", rate.to_string()).finish(),
+                )
+            })
             .tcp()
     });
 
     let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
-    let _ = stream.write_all(b"GET /test/tests/test HTTP/1.1\r\n\r\n");
-    let mut data = vec![0; 1024];
-    let _ = stream.read(&mut data);
-    assert_eq!(&data[..17], b"HTTP/1.1 200 OK\r\n");
+    for idx in 1..4 {
+        let _ = stream.write_all(b"GET / HTTP/1.1\r\n\r\n");
+        let mut data = vec![0; 1024];
+        let _ = stream.read(&mut data);
+        let data = String::from_utf8_lossy(&data);
+        assert!(data.contains(&format!("x-rate: {}\r\n", idx)));
+    }
 
+    // rate is tracked per connection
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET / HTTP/1.1\r\n\r\n");
     let mut data = vec![0; 1024];
-    let res = stream.read(&mut data).unwrap();
-    assert_eq!(res, 0);
+    let _ = stream.read(&mut data);
+    let data = String::from_utf8_lossy(&data);
+    assert!(data.contains("x-rate: 1\r\n"));
 }
 
 #[ntex::test]
@@ -454,6 +1673,70 @@ async fn test_h1_body() {
     assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
 }
 
+#[ntex::test]
+async fn test_h1_static_body() {
+    let mut srv = test_server(|| {
+        HttpService::build()
+            .h1(|_| {
+                ok::<_, io::Error>(
+                    Response::Ok().body(body::Body::from_static(STR.as_bytes())),
+                )
+            })
+            .tcp()
+    });
+
+    let response = srv.request(Method::GET, "/").send().await.unwrap();
+    assert!(response.status().is_success());
+    let len = response.headers().get(header::CONTENT_LENGTH).unwrap();
+    assert_eq!(format!("{}", STR.len()), len.to_str().unwrap());
+
+    let bytes = srv.load_body(response).await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
+}
+
+#[ntex::test]
+async fn test_h1_set_cookie() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .h1(|_| {
+                ok::<_, io::Error>(
+                    Response::Ok()
+                        .header(
+                            header::SET_COOKIE,
+                            "id=a=b; Expires=Wed, 21 Oct 2015 07:28:00 GMT",
+                        )
+                        .header(header::SET_COOKIE, "lang=en; Path=/")
+                        .header(
+                            header::SET_COOKIE,
+                            "theme=dark; Expires=Thu, 22 Oct 2015 07:28:00 GMT",
+                        )
+                        .finish(),
+                )
+            })
+            .tcp()
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream
+        .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+
+    // every cookie is sent in separate header, in insertion order
+    let cookies: Vec<_> = data
+        .split("\r\n")
+        .filter(|line| line.starts_with("set-cookie: "))
+        .collect();
+    assert_eq!(
+        cookies,
+        vec![
+            "set-cookie: id=a=b; Expires=Wed, 21 Oct 2015 07:28:00 GMT",
+            "set-cookie: lang=en; Path=/",
+            "set-cookie: theme=dark; Expires=Thu, 22 Oct 2015 07:28:00 GMT",
+        ]
+    );
+}
+
 #[ntex::test]
 async fn test_h1_head_empty() {
     let mut srv = test_server(|| {
@@ -9,7 +9,7 @@ use bytes::Bytes;
 use futures::{future, Future, SinkExt, StreamExt};
 
 use ntex::codec::{AsyncRead, AsyncWrite, Framed};
-use ntex::http::ws::handshake;
+use ntex::http::ws::{handshake, upgrade_service};
 use ntex::http::{body, h1, test, HttpService, Request, Response};
 use ntex::service::{fn_factory, Service};
 use ntex::util::framed::Dispatcher;
@@ -228,3 +228,63 @@ async fn test_simple() {
 
     assert!(ws_service.was_polled());
 }
+
+async fn echo<T>(_: Request, framed: Framed<T, ws::Codec>) -> Result<(), io::Error>
+where
+    T: AsyncRead + AsyncWrite + Unpin + 'static,
+{
+    Dispatcher::new(framed, service)
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::Other, "dispatcher error"))
+}
+
+#[ntex::test]
+async fn test_upgrade_service() {
+    use std::io::{Read, Write};
+
+    let mut srv = test::server(|| {
+        HttpService::build()
+            .upgrade(upgrade_service(echo))
+            .h1(|_| future::ok::<_, io::Error>(Response::NotFound()))
+            .tcp()
+    });
+
+    let mut framed = srv.ws().await.unwrap();
+    framed
+        .send(ws::Message::Text("text".to_string()))
+        .await
+        .unwrap();
+    let (item, mut framed) = framed.into_future().await;
+    assert_eq!(
+        item.unwrap().unwrap(),
+        ws::Frame::Text(Bytes::from_static(b"text"))
+    );
+
+    framed
+        .send(ws::Message::Close(Some(ws::CloseCode::Normal.into())))
+        .await
+        .unwrap();
+    let (item, _framed) = framed.into_future().await;
+    assert_eq!(
+        item.unwrap().unwrap(),
+        ws::Frame::Close(Some(ws::CloseCode::Normal.into()))
+    );
+
+    // non-websocket upgrade
+    let mut stream = std::net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream
+        .write_all(b"GET / HTTP/1.1\r\nconnection: upgrade\r\nupgrade: h2c\r\n\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 426 Upgrade Required\r\n"));
+    assert!(data.contains("upgrade: websocket\r\n"));
+
+    // invalid handshake
+    let mut stream = std::net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(
+        b"GET / HTTP/1.1\r\nconnection: upgrade\r\nupgrade: websocket\r\n\r\n",
+    );
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 400 "));
+}
@@ -9,7 +9,7 @@ use futures::SinkExt;
 
 use ntex::codec::{BytesCodec, Framed};
 use ntex::rt::net::TcpStream;
-use ntex::server::{Server, TestServer};
+use ntex::server::{AcceptDecision, IpFilter, Server, TestServer};
 use ntex::service::fn_service;
 
 #[test]
@@ -129,6 +129,191 @@ fn test_start() {
     let _ = h.join();
 }
 
+#[test]
+fn test_accept_batch() {
+    let addr = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let mut sys = ntex::rt::System::new("test");
+        sys.exec(|| {
+            Server::build()
+                .workers(1)
+                .accept_batch(2)
+                .disable_signals()
+                .bind("test", addr, move || {
+                    fn_service(|io: TcpStream| async move {
+                        let mut f = Framed::new(io, BytesCodec);
+                        f.send(Bytes::from_static(b"test")).await.unwrap();
+                        Ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .start()
+        });
+        let _ = tx.send(ntex::rt::System::current());
+        let _ = sys.run();
+    });
+    let sys = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    // connections pending in backlog are accepted in several batches
+    let mut conns: Vec<_> = (0..10)
+        .map(|_| net::TcpStream::connect(addr).unwrap())
+        .collect();
+    for conn in &mut conns {
+        let mut buf = [0u8; 4];
+        conn.set_read_timeout(Some(time::Duration::from_secs(1)))
+            .unwrap();
+        conn.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, b"test"[..]);
+    }
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_on_accept() {
+    let addr = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let mut sys = ntex::rt::System::new("test");
+        let srv = sys.exec(|| {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .on_accept(|peer: &net::SocketAddr| {
+                    if peer.port() % 2 == 0 {
+                        AcceptDecision::Accept
+                    } else {
+                        AcceptDecision::RejectWithRst
+                    }
+                })
+                .bind("test", addr, move || {
+                    fn_service(|io: TcpStream| async move {
+                        let mut f = Framed::new(io, BytesCodec);
+                        f.send(Bytes::from_static(b"test")).await.unwrap();
+                        Ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .start()
+        });
+        let _ = tx.send((srv, ntex::rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let mut rejected = 0;
+    for _ in 0..10 {
+        let mut conn = net::TcpStream::connect(addr).unwrap();
+        conn.set_read_timeout(Some(time::Duration::from_secs(1)))
+            .unwrap();
+        let mut buf = [0u8; 4];
+        if conn.local_addr().unwrap().port() % 2 == 0 {
+            conn.read_exact(&mut buf).unwrap();
+            assert_eq!(buf, b"test"[..]);
+        } else {
+            rejected += 1;
+            assert!(conn.read_exact(&mut buf).is_err());
+        }
+    }
+    assert_eq!(
+        futures::executor::block_on(srv.rejected_connections()),
+        rejected
+    );
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_linger() {
+    let addr = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let mut sys = ntex::rt::System::new("test");
+        let srv = sys.exec(|| {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .linger(time::Duration::from_secs(0))
+                .bind("test", addr, move || {
+                    fn_service(|io: TcpStream| async move {
+                        let linger = io.linger().unwrap();
+                        let mut f = Framed::new(io, BytesCodec);
+                        if linger == Some(time::Duration::from_secs(0)) {
+                            f.send(Bytes::from_static(b"test")).await.unwrap();
+                        }
+                        // unsent data is discarded on reset
+                        ntex::rt::time::delay_for(time::Duration::from_millis(500))
+                            .await;
+                        Ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .start()
+        });
+        let _ = tx.send((srv, ntex::rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.set_read_timeout(Some(time::Duration::from_secs(1)))
+        .unwrap();
+    let mut buf = [0u8; 4];
+    conn.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, b"test"[..]);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_ip_filter() {
+    let addr = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let mut sys = ntex::rt::System::new("test");
+        sys.exec(|| {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .on_accept(IpFilter::new().deny("127.0.0.0/8".parse().unwrap()))
+                .bind("test", addr, move || {
+                    fn_service(|io: TcpStream| async move {
+                        let mut f = Framed::new(io, BytesCodec);
+                        f.send(Bytes::from_static(b"test")).await.unwrap();
+                        Ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .start()
+        });
+        let _ = tx.send(ntex::rt::System::current());
+        let _ = sys.run();
+    });
+    let sys = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.set_read_timeout(Some(time::Duration::from_secs(1)))
+        .unwrap();
+    let mut buf = Vec::new();
+    let _ = conn.read_to_end(&mut buf);
+    assert!(buf.is_empty());
+
+    sys.stop();
+    let _ = h.join();
+}
+
 #[test]
 fn test_configure() {
     let addr1 = TestServer::unused_addr();
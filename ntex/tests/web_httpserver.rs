@@ -25,6 +25,7 @@ async fn test_run() {
             })
             .workers(1)
             .backlog(1)
+            .accept_batch(4)
             .maxconn(10)
             .maxconnrate(10)
             .keep_alive(10)
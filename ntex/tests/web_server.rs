@@ -13,9 +13,10 @@ use futures::{future::ready, ready, Future};
 use rand::{distributions::Alphanumeric, Rng};
 
 use ntex::http::body::Body;
+use ntex::http::encoding::Level;
 use ntex::http::header::{
     ContentEncoding, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE,
-    TRANSFER_ENCODING,
+    TRANSFER_ENCODING, VARY,
 };
 use ntex::http::{Method, StatusCode};
 
@@ -128,6 +129,45 @@ async fn test_body_gzip() {
     assert_eq!(Bytes::from(dec), Bytes::from_static(STR.as_ref()));
 }
 
+#[ntex::test]
+async fn test_body_compression_level() {
+    let srv = test::server_with(test::config().h1(), || {
+        App::new()
+            .wrap(Compress::new(ContentEncoding::Auto).compression_level(Level::Best))
+            .service(
+                web::resource("/")
+                    .route(web::to(|| async { HttpResponse::Ok().body(STR) })),
+            )
+    });
+
+    let mut response = srv
+        .get("/")
+        .no_decompress()
+        .header(ACCEPT_ENCODING, "gzip")
+        .send()
+        .await
+        .unwrap();
+    let bytes = response.body().await.unwrap();
+
+    let mut enc = GzEncoder::new(Vec::new(), Compression::best());
+    enc.write_all(STR.as_ref()).unwrap();
+    assert_eq!(bytes, Bytes::from(enc.finish().unwrap()));
+
+    let mut response = srv
+        .get("/")
+        .no_decompress()
+        .header(ACCEPT_ENCODING, "br")
+        .send()
+        .await
+        .unwrap();
+    let bytes = response.body().await.unwrap();
+
+    let mut dec = BrotliDecoder::new(Vec::with_capacity(2048));
+    dec.write_all(bytes.as_ref()).unwrap();
+    let dec = dec.finish().unwrap();
+    assert_eq!(Bytes::from(dec), Bytes::from_static(STR.as_ref()));
+}
+
 #[ntex::test]
 async fn test_body_gzip2() {
     let srv = test::server_with(test::config().h1(), || {
@@ -375,6 +415,89 @@ async fn test_head_binary() {
     assert!(bytes.is_empty());
 }
 
+#[ntex::test]
+async fn test_head_compress() {
+    let mut e = GzEncoder::new(Vec::new(), Compression::default());
+    e.write_all(STR.as_ref()).unwrap();
+    let precompressed = Bytes::from(e.finish().unwrap());
+    let len = precompressed.len();
+
+    let srv = test::server_with(test::config().h1(), move || {
+        let precompressed = precompressed.clone();
+        App::new()
+            .wrap(Compress::new(ContentEncoding::Gzip))
+            .service(
+                web::resource("/")
+                    .route(web::to(|| async { HttpResponse::Ok().body(STR) })),
+            )
+            .service(web::resource("/pre").route(web::to(move || {
+                let body = precompressed.clone();
+                async move {
+                    HttpResponse::Ok()
+                        .header(CONTENT_ENCODING, "gzip")
+                        .body(body)
+                }
+            })))
+    });
+
+    // GET and HEAD responses have same headers
+    for method in &[Method::GET, Method::HEAD] {
+        let mut response = srv
+            .request(method.clone(), srv.url("/"))
+            .no_decompress()
+            .header(ACCEPT_ENCODING, "gzip")
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+        assert_eq!(response.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+        assert_eq!(response.headers().get(VARY).unwrap(), "accept-encoding");
+        assert_eq!(
+            response.headers().get(TRANSFER_ENCODING).unwrap(),
+            "chunked"
+        );
+        assert!(!response.headers().contains_key(CONTENT_LENGTH));
+
+        let bytes = response.body().await.unwrap();
+        assert_eq!(bytes.is_empty(), method == Method::HEAD);
+    }
+
+    // encoding is not negotiated
+    for method in &[Method::GET, Method::HEAD] {
+        let response = srv
+            .request(method.clone(), srv.url("/"))
+            .no_decompress()
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+        assert!(!response.headers().contains_key(CONTENT_ENCODING));
+        assert_eq!(response.headers().get(VARY).unwrap(), "accept-encoding");
+        assert_eq!(
+            response.headers().get(CONTENT_LENGTH).unwrap(),
+            &format!("{}", STR.len())
+        );
+    }
+
+    // precompressed response keeps content length
+    for method in &[Method::GET, Method::HEAD] {
+        let response = srv
+            .request(method.clone(), srv.url("/pre"))
+            .no_decompress()
+            .header(ACCEPT_ENCODING, "gzip")
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+        assert_eq!(response.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+        assert_eq!(response.headers().get(VARY).unwrap(), "accept-encoding");
+        assert_eq!(
+            response.headers().get(CONTENT_LENGTH).unwrap(),
+            &format!("{}", len)
+        );
+    }
+}
+
 #[ntex::test]
 async fn test_no_chunking() {
     let srv = test::server_with(test::config().h1(), || {
@@ -1138,3 +1261,51 @@ async fn test_custom_error() {
     let tp = response.headers().get(CONTENT_TYPE).unwrap();
     assert_eq!("application/json", tp.to_str().unwrap());
 }
+
+#[ntex::test]
+async fn test_long_poll_disconnect() {
+    use std::net;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    struct Guard(Arc<AtomicBool>);
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::Relaxed);
+        }
+    }
+
+    let dropped = Arc::new(AtomicBool::new(false));
+    let dropped2 = dropped.clone();
+    let srv = test::server(move || {
+        let dropped = dropped2.clone();
+        App::new().service(web::resource("/").route(web::to(move || {
+            let guard = Guard(dropped.clone());
+            async move {
+                web::long_poll(
+                    Duration::from_secs(10),
+                    Duration::from_millis(50),
+                    async move {
+                        let _guard = guard;
+                        futures::future::pending::<Bytes>().await
+                    },
+                )
+            }
+        })))
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET / HTTP/1.1\r\n\r\n");
+    let mut data = vec![0; 1024];
+    let size = stream.read(&mut data).unwrap();
+    let data = String::from_utf8_lossy(&data[..size]);
+    assert!(data.starts_with("HTTP/1.1 200 OK"));
+    assert!(data.contains("transfer-encoding: chunked"));
+    assert!(!dropped.load(Ordering::Relaxed));
+
+    // client disconnect cancels pending future
+    drop(stream);
+    ntex::rt::time::delay_for(Duration::from_millis(500)).await;
+    assert!(dropped.load(Ordering::Relaxed));
+}
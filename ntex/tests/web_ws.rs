@@ -3,6 +3,7 @@ use std::io;
 use bytes::Bytes;
 use futures::{SinkExt, StreamExt};
 
+use ntex::channel::mpsc;
 use ntex::service::{fn_factory_with_config, fn_service};
 use ntex::web::{self, test, ws, App, HttpRequest};
 
@@ -64,3 +65,41 @@ async fn web_ws() {
     let item = framed.next().await.unwrap().unwrap();
     assert_eq!(item, ws::Frame::Close(Some(ws::CloseCode::Normal.into())));
 }
+
+#[ntex::test]
+async fn web_ws_protocol_error() {
+    let srv = test::server(|| {
+        App::new().service(web::resource("/").route(web::to(
+            |req: HttpRequest, pl: web::types::Payload| async move {
+                let (tx, rx) = mpsc::channel();
+                ws::start_with_codec::<_, _, _, web::Error, _, _>(
+                    req,
+                    pl,
+                    ntex::ws::Codec::new().max_frame_size(4),
+                    tx,
+                    rx,
+                    fn_factory_with_config(|_| async {
+                        Ok::<_, web::Error>(fn_service(service))
+                    }),
+                )
+                .await
+            },
+        )))
+    });
+
+    let mut framed = srv.ws().await.unwrap();
+    framed
+        .send(ws::Message::Text("text".to_string()))
+        .await
+        .unwrap();
+    let item = framed.next().await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Text(Bytes::from_static(b"text")));
+
+    // frame is larger than max frame size
+    framed
+        .send(ws::Message::Text("text text".to_string()))
+        .await
+        .unwrap();
+    let item = framed.next().await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Close(Some(ws::CloseCode::Size.into())));
+}